@@ -0,0 +1,76 @@
+//! SSE observation overhead for large streamed completions.
+//!
+//! Run with `cargo bench --bench sse_stream`. Compare against a saved
+//! baseline (`-- --save-baseline before`, then `-- --baseline before`) to
+//! measure a change. Heap allocations per stream are printed once per case.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::StreamExt;
+
+use arbstr::bench::{allocations, CountingAllocator};
+use arbstr::proxy::wrap_sse_stream;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A streamed completion of `tokens` content chunks plus usage and `[DONE]`.
+fn completion(tokens: usize) -> Vec<u8> {
+    let mut body = String::new();
+    for i in 0..tokens {
+        body.push_str(&format!(
+            "data: {{\"id\":\"chatcmpl-bench\",\"object\":\"chat.completion.chunk\",\"model\":\"gpt-4o\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\" token{}\"}},\"finish_reason\":null}}]}}\n\n",
+            i
+        ));
+    }
+    body.push_str("data: {\"id\":\"chatcmpl-bench\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n");
+    body.push_str("data: {\"id\":\"chatcmpl-bench\",\"choices\":[],\"usage\":{\"prompt_tokens\":100,\"completion_tokens\":4000,\"total_tokens\":4100}}\n\n");
+    body.push_str("data: [DONE]\n\n");
+    body.into_bytes()
+}
+
+/// Split `body` into chunks of `size` bytes, as they might arrive off the wire.
+fn chunked(body: &[u8], size: usize) -> Vec<Bytes> {
+    body.chunks(size).map(Bytes::copy_from_slice).collect()
+}
+
+/// Pass every chunk through the observer and read the result.
+fn observe(chunks: &[Bytes]) {
+    let stream = futures::stream::iter(chunks.iter().cloned().map(Ok::<_, reqwest::Error>));
+    let (wrapped, handle) = wrap_sse_stream(stream);
+    futures::executor::block_on(wrapped.for_each(|chunk| {
+        std::hint::black_box(chunk.ok());
+        async {}
+    }));
+    let result = handle.lock().unwrap().take().unwrap();
+    assert!(result.done_received && result.usage.is_some());
+}
+
+fn bench_large_stream(c: &mut Criterion) {
+    let body = completion(4000);
+    let mut group = c.benchmark_group("sse_observer");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+
+    for size in [64, 1460, 16 * 1024] {
+        let chunks = chunked(&body, size);
+
+        let before = allocations();
+        observe(&chunks);
+        let alloc = allocations().since(before);
+        eprintln!(
+            "sse_observer/{}: {} allocations, {} bytes per {}-byte stream",
+            size,
+            alloc.allocations,
+            alloc.bytes,
+            body.len()
+        );
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &chunks, |b, chunks| {
+            b.iter(|| observe(chunks))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_large_stream);
+criterion_main!(benches);
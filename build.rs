@@ -1,3 +1,33 @@
+use std::path::Path;
+use std::process::Command;
+
 fn main() {
     println!("cargo:rerun-if-changed=migrations");
+
+    // Commit reported by /v1/about; "unknown" outside a git checkout
+    // (e.g. Docker builds, which copy only the sources)
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .filter(|commit| !commit.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ARBSTR_BUILD_COMMIT={}", commit);
+
+    // Rebuild when HEAD moves: HEAD itself changes on checkout, the branch
+    // ref on commit
+    if Path::new(".git/HEAD").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+            if let Some(reference) = head.strip_prefix("ref: ") {
+                let path = format!(".git/{}", reference.trim());
+                if Path::new(&path).exists() {
+                    println!("cargo:rerun-if-changed={}", path);
+                }
+            }
+        }
+    }
 }
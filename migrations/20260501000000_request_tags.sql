@@ -0,0 +1,13 @@
+-- Cost allocation tags attached to requests via the X-Arbstr-Tags header.
+--
+-- Normalized one-row-per-tag so stats and log queries can filter or
+-- group by any key without parsing a packed column.
+CREATE TABLE IF NOT EXISTS request_tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    correlation_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_request_tags_correlation_id ON request_tags(correlation_id);
+CREATE INDEX IF NOT EXISTS idx_request_tags_key_value ON request_tags(key, value);
@@ -0,0 +1,3 @@
+-- Record the upstream finish_reason for streaming requests.
+-- Nullable: non-streaming rows and rows predating this column remain NULL.
+ALTER TABLE requests ADD COLUMN finish_reason TEXT;
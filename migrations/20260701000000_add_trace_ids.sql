@@ -0,0 +1,7 @@
+-- Correlate requests with distributed traces.
+-- trace_id: W3C trace ID from the client's traceparent (or generated).
+-- client_request_id: the client's x-request-id (defaults to correlation_id).
+ALTER TABLE requests ADD COLUMN trace_id TEXT;
+ALTER TABLE requests ADD COLUMN client_request_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_requests_trace_id ON requests(trace_id);
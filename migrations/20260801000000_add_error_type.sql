@@ -0,0 +1,4 @@
+-- Classified failure mode for failed requests (timeout, connect, tls,
+-- auth, rate_limited, 5xx, 4xx, malformed). NULL for successes and for
+-- failures that never reached a provider.
+ALTER TABLE requests ADD COLUMN error_type TEXT;
@@ -0,0 +1,36 @@
+-- Hourly and daily request aggregates for long-range stats queries.
+--
+-- Rows are rebuilt by the rollup job ([stats] rollups = true); raw
+-- requests remain the source of truth. provider is '' for requests that
+-- never reached one and tier is 'unknown' when not recorded, so both can
+-- be part of the primary key.
+CREATE TABLE IF NOT EXISTS request_rollups (
+    granularity TEXT NOT NULL,          -- 'hour' or 'day'
+    bucket_start TEXT NOT NULL,         -- RFC 3339 UTC start of the bucket
+    model TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    tier TEXT NOT NULL,
+    total_requests INTEGER NOT NULL,
+    success_count INTEGER NOT NULL,
+    error_count INTEGER NOT NULL,
+    streaming_count INTEGER NOT NULL,
+    total_cost_sats REAL NOT NULL,
+    total_input_tokens REAL NOT NULL,
+    total_output_tokens REAL NOT NULL,
+    total_latency_ms REAL NOT NULL,
+    timeout_errors INTEGER NOT NULL,
+    connect_errors INTEGER NOT NULL,
+    tls_errors INTEGER NOT NULL,
+    auth_errors INTEGER NOT NULL,
+    rate_limited_errors INTEGER NOT NULL,
+    server_errors INTEGER NOT NULL,
+    client_errors INTEGER NOT NULL,
+    malformed_errors INTEGER NOT NULL,
+    PRIMARY KEY (granularity, bucket_start, model, provider, tier)
+);
+
+-- End of the last fully aggregated hour.
+CREATE TABLE IF NOT EXISTS rollup_state (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    rolled_until TEXT NOT NULL
+);
@@ -0,0 +1,17 @@
+-- Spend ledger for prepaid providers (balance_sats in provider config).
+--
+-- Only credits are recorded here: an 'open' row holds the configured
+-- starting balance and 'topup' rows hold manual top-ups. Debits are the
+-- cost_sats of requests routed to the provider since its latest 'open'
+-- row, so they are never stored twice.
+CREATE TABLE IF NOT EXISTS provider_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    provider TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK(kind IN ('open', 'topup')),
+    amount_sats REAL NOT NULL,
+    note TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_provider_ledger_provider
+    ON provider_ledger(provider, id);
@@ -0,0 +1,5 @@
+-- Exchange rate snapshot per request ([currency]): the price of one
+-- bitcoin in fiat_currency when the request was logged, so fiat totals
+-- use the rate of the day rather than today's.
+ALTER TABLE requests ADD COLUMN fiat_currency TEXT;
+ALTER TABLE requests ADD COLUMN fiat_rate REAL;
@@ -0,0 +1,4 @@
+-- Streaming throughput: output tokens per second between the first and
+-- last content chunk. NULL for non-streaming requests and for streams
+-- without usage or delivered in a single chunk.
+ALTER TABLE requests ADD COLUMN tokens_per_second REAL;
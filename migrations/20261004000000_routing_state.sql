@@ -0,0 +1,11 @@
+-- In-memory routing state saved on shutdown and reloaded on startup, so
+-- reputation windows, demotions, and canary progress survive a deploy.
+-- One row per (kind, provider); state is a JSON document owned by the
+-- tracker named by kind.
+CREATE TABLE IF NOT EXISTS routing_state (
+    kind TEXT NOT NULL,            -- 'reputation' or 'canary'
+    provider TEXT NOT NULL,
+    state TEXT NOT NULL,
+    saved_at TEXT NOT NULL,        -- RFC 3339 UTC
+    PRIMARY KEY (kind, provider)
+);
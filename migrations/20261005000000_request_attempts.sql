@@ -0,0 +1,15 @@
+-- Failed upstream attempts behind a request (retries and fallbacks), the
+-- detailed form of the x-arbstr-retries header. One row per attempt, in
+-- the order they were made, so failed-request forensics need no log digging.
+CREATE TABLE IF NOT EXISTS request_attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    correlation_id TEXT NOT NULL,
+    attempt INTEGER NOT NULL,      -- 1-based order within the request
+    provider TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    error_type TEXT NOT NULL,      -- ProviderErrorKind, e.g. '5xx', 'timeout'
+    backoff_ms INTEGER NOT NULL,   -- wait before this attempt
+    timestamp TEXT NOT NULL        -- RFC 3339 UTC, when the attempt was sent
+);
+
+CREATE INDEX IF NOT EXISTS idx_request_attempts_correlation_id ON request_attempts(correlation_id);
@@ -0,0 +1,4 @@
+-- Circuit breaker state of every provider when the request was routed, as a
+-- JSON object: {"<provider>": {"state": "closed", "failure_count": 0}, ...}.
+-- NULL for rows logged before routing (or before this migration).
+ALTER TABLE requests ADD COLUMN circuit_snapshot TEXT;
@@ -0,0 +1,28 @@
+-- Fan-out comparisons from POST /v1/compare: one prompt sent to several
+-- models/providers at once. Each response is also logged in requests under
+-- its own correlation ID; these tables keep the set together with the
+-- response text for later evaluation.
+CREATE TABLE IF NOT EXISTS comparisons (
+    id TEXT PRIMARY KEY,           -- the compare call's correlation ID
+    timestamp TEXT NOT NULL,       -- RFC 3339 UTC
+    request TEXT                   -- request JSON without targets; NULL with privacy.strip_prompts
+);
+
+CREATE TABLE IF NOT EXISTS comparison_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    comparison_id TEXT NOT NULL REFERENCES comparisons(id),
+    position INTEGER NOT NULL,     -- 1-based, in target order
+    model TEXT NOT NULL,
+    provider TEXT,
+    correlation_id TEXT NOT NULL,  -- requests.correlation_id of this response
+    success INTEGER NOT NULL,
+    latency_ms INTEGER NOT NULL,
+    cost_sats REAL,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    content TEXT,                  -- assistant reply; NULL with privacy.strip_prompts
+    error_status INTEGER,
+    error_message TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_comparison_results_comparison_id ON comparison_results(comparison_id);
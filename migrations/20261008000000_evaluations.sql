@@ -0,0 +1,20 @@
+-- Scheduled evaluation runs ([evaluation]): one row per prompt sent to a
+-- provider/model pair. A pair's quality score is the mean score of its
+-- rows in the latest run.
+CREATE TABLE IF NOT EXISTS evaluations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,          -- RFC 3339 UTC start of the run
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt TEXT NOT NULL,          -- evaluation prompt name
+    success INTEGER NOT NULL,      -- provider answered with a 2xx
+    status INTEGER,                -- HTTP status, NULL when no response
+    latency_ms INTEGER NOT NULL,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    cost_sats REAL,
+    passed INTEGER,                -- expect/pattern checks; NULL when the prompt has none
+    score REAL NOT NULL            -- 0.0-1.0
+);
+
+CREATE INDEX IF NOT EXISTS idx_evaluations_run_id ON evaluations(run_id);
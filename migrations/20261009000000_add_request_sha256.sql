@@ -0,0 +1,4 @@
+-- SHA-256 (hex) of the request body as received, signed into the request's
+-- receipt. NULL for bodies streamed to the provider without buffering and
+-- for rows logged before this migration.
+ALTER TABLE requests ADD COLUMN request_sha256 TEXT;
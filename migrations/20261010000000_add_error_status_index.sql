@@ -0,0 +1,2 @@
+-- Keeps GET /v1/requests?status=5xx fast on large logs.
+CREATE INDEX IF NOT EXISTS idx_requests_error_status ON requests(error_status);
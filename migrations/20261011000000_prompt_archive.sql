@@ -0,0 +1,12 @@
+-- Prompt archive ([archive] enabled = true): each request's messages as
+-- Brotli-compressed JSON, read by `arbstr analyze duplicates` to estimate
+-- what a response cache would save. Costs come from the requests rows with
+-- the same correlation ID.
+CREATE TABLE IF NOT EXISTS prompt_archive (
+    correlation_id TEXT PRIMARY KEY,
+    timestamp TEXT NOT NULL,       -- RFC 3339 UTC, as in requests
+    model TEXT NOT NULL,
+    prompt_br BLOB NOT NULL        -- Brotli-compressed messages JSON
+);
+
+CREATE INDEX IF NOT EXISTS idx_prompt_archive_timestamp ON prompt_archive(timestamp);
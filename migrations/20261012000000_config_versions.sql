@@ -0,0 +1,15 @@
+-- Config versions: one row per configuration that served traffic, bumped
+-- at startup when the config changed and on each admin API change.
+-- AUTOINCREMENT keeps versions increasing even if old rows are deleted.
+CREATE TABLE config_versions (
+    version INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT NOT NULL,
+    config_hash TEXT NOT NULL,
+    source TEXT NOT NULL,
+    actor TEXT,
+    change TEXT
+);
+
+-- Config version active when each request was dispatched
+ALTER TABLE requests ADD COLUMN config_version INTEGER;
+CREATE INDEX idx_requests_config_version ON requests(config_version);
@@ -0,0 +1,5 @@
+-- Conversation a request belongs to (`X-Arbstr-Conversation-Id` header or
+-- `metadata.conversation_id` in the body), for per-conversation costs.
+ALTER TABLE requests ADD COLUMN conversation_id TEXT;
+CREATE INDEX idx_requests_conversation_id ON requests(conversation_id)
+    WHERE conversation_id IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Prepaid request vouchers (POST /admin/vouchers).
+--
+-- Only the voucher itself is stored: spend and use are counted from the
+-- requests tagged voucher=<id>, as with provider_ledger debits, so they are
+-- never stored twice.
+CREATE TABLE IF NOT EXISTS vouchers (
+    id TEXT PRIMARY KEY,            -- public ID, used in the request tag
+    code TEXT NOT NULL UNIQUE,      -- secret presented in x-arbstr-voucher
+    created_at TEXT NOT NULL,       -- RFC 3339 UTC
+    limit_sats REAL,                -- NULL: no spend limit
+    limit_requests INTEGER,         -- NULL: no request limit
+    models TEXT,                    -- JSON array of allowed models; NULL: any
+    note TEXT
+);
@@ -0,0 +1,9 @@
+-- Costs are computed to the millisat (rates may now be decimal, e.g.
+-- input_rate = 0.15). Round costs logged before this change the same
+-- way, dropping float noise such as 0.30000000000000004, so totals over
+-- old and new rows agree. Provider-reported provider_cost_sats is left
+-- as reported.
+UPDATE requests SET cost_sats = ROUND(cost_sats, 3) WHERE cost_sats IS NOT NULL;
+UPDATE request_rollups SET total_cost_sats = ROUND(total_cost_sats, 3);
+UPDATE comparison_results SET cost_sats = ROUND(cost_sats, 3) WHERE cost_sats IS NOT NULL;
+UPDATE evaluations SET cost_sats = ROUND(cost_sats, 3) WHERE cost_sats IS NOT NULL;
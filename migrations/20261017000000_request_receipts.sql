@@ -0,0 +1,8 @@
+-- Signed receipts, stored when a request completes so a receipt vouches
+-- for the request as it was served rather than for whatever its requests
+-- row says later. receipt is the signed receipt as returned by
+-- GET /v1/requests/{id}/receipt.
+CREATE TABLE IF NOT EXISTS request_receipts (
+    correlation_id TEXT PRIMARY KEY,
+    receipt TEXT NOT NULL          -- signed receipt JSON
+);
@@ -0,0 +1,36 @@
+-- Hourly and daily aggregates per request tag, so tag-filtered and
+-- tag-grouped stats (e.g. per tenant) can read rollups too.
+--
+-- Same columns as request_rollups, keyed additionally by tag. A request
+-- with several tags is counted once under each of them; requests without
+-- tags only appear in request_rollups.
+CREATE TABLE IF NOT EXISTS request_tag_rollups (
+    granularity TEXT NOT NULL,          -- 'hour' or 'day'
+    bucket_start TEXT NOT NULL,         -- RFC 3339 UTC start of the bucket
+    tag_key TEXT NOT NULL,
+    tag_value TEXT NOT NULL,
+    model TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    tier TEXT NOT NULL,
+    total_requests INTEGER NOT NULL,
+    success_count INTEGER NOT NULL,
+    error_count INTEGER NOT NULL,
+    streaming_count INTEGER NOT NULL,
+    total_cost_sats REAL NOT NULL,
+    total_input_tokens REAL NOT NULL,
+    total_output_tokens REAL NOT NULL,
+    total_latency_ms REAL NOT NULL,
+    timeout_errors INTEGER NOT NULL,
+    connect_errors INTEGER NOT NULL,
+    tls_errors INTEGER NOT NULL,
+    auth_errors INTEGER NOT NULL,
+    rate_limited_errors INTEGER NOT NULL,
+    server_errors INTEGER NOT NULL,
+    client_errors INTEGER NOT NULL,
+    malformed_errors INTEGER NOT NULL,
+    PRIMARY KEY (granularity, bucket_start, tag_key, tag_value, model, provider, tier)
+);
+
+-- Forget the watermark so the next rollup run backfills tag rollups for
+-- every hour already rolled up.
+DELETE FROM rollup_state;
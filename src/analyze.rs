@@ -0,0 +1,284 @@
+//! Offline analyses behind `arbstr analyze`.
+//!
+//! `duplicates` clusters near-duplicate prompts from the prompt archive
+//! (`[archive]`) and estimates how much spend a response cache would have
+//! saved: within a cluster, every request after the first could have been
+//! answered from the cache. Prompts are compared per model, after
+//! lowercasing and collapsing whitespace, by the Jaccard similarity of
+//! their word trigrams.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::storage::archive::ArchivedPrompt;
+
+/// Characters of a cluster's prompt shown in the report.
+const SAMPLE_CHARS: usize = 60;
+
+/// Near-duplicate prompts for one model.
+#[derive(Debug, Clone)]
+pub struct DuplicateCluster {
+    pub model: String,
+    /// Start of the first prompt, normalized.
+    pub sample: String,
+    pub requests: usize,
+    pub spend_sats: f64,
+    /// Spend on every request after the first.
+    pub cacheable_sats: f64,
+}
+
+/// Result of `arbstr analyze duplicates`.
+#[derive(Debug, Clone)]
+pub struct DuplicateReport {
+    /// Human-readable range, e.g. `last_30d`.
+    pub range: String,
+    pub threshold: f64,
+    pub requests: usize,
+    pub spend_sats: f64,
+    /// Clusters with more than one request, most cacheable spend first.
+    pub clusters: Vec<DuplicateCluster>,
+    /// Clusters listed by `Display`.
+    pub top: usize,
+}
+
+impl DuplicateReport {
+    /// Requests that repeat an earlier prompt in their cluster.
+    pub fn duplicate_requests(&self) -> usize {
+        self.clusters.iter().map(|c| c.requests - 1).sum()
+    }
+
+    /// Spend a response cache would have saved.
+    pub fn cacheable_sats(&self) -> f64 {
+        self.clusters.iter().map(|c| c.cacheable_sats).sum()
+    }
+}
+
+/// Cluster `prompts` (oldest first) whose similarity is at least `threshold`.
+pub fn find_duplicates(
+    prompts: &[ArchivedPrompt],
+    threshold: f64,
+    range: &str,
+    top: usize,
+) -> DuplicateReport {
+    struct Building {
+        cluster: DuplicateCluster,
+        shingles: HashSet<u64>,
+    }
+
+    let mut clusters: Vec<Building> = Vec::new();
+    let mut exact: HashMap<(String, String), usize> = HashMap::new();
+    let mut by_model: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for prompt in prompts {
+        let text = normalize(&prompt.prompt);
+        let key = (prompt.model.clone(), text.clone());
+        let index = match exact.get(&key) {
+            Some(&index) => Some(index),
+            None => {
+                let shingles = shingles(&text);
+                let similar = by_model.get(&prompt.model).and_then(|indexes| {
+                    indexes
+                        .iter()
+                        .copied()
+                        .find(|&i| jaccard(&clusters[i].shingles, &shingles) >= threshold)
+                });
+                match similar {
+                    Some(index) => {
+                        exact.insert(key, index);
+                        Some(index)
+                    }
+                    None => {
+                        clusters.push(Building {
+                            cluster: DuplicateCluster {
+                                model: prompt.model.clone(),
+                                sample: text.chars().take(SAMPLE_CHARS).collect(),
+                                requests: 1,
+                                spend_sats: prompt.cost_sats,
+                                cacheable_sats: 0.0,
+                            },
+                            shingles,
+                        });
+                        let index = clusters.len() - 1;
+                        exact.insert(key, index);
+                        by_model
+                            .entry(prompt.model.clone())
+                            .or_default()
+                            .push(index);
+                        None
+                    }
+                }
+            }
+        };
+        if let Some(index) = index {
+            let cluster = &mut clusters[index].cluster;
+            cluster.requests += 1;
+            cluster.spend_sats += prompt.cost_sats;
+            cluster.cacheable_sats += prompt.cost_sats;
+        }
+    }
+
+    let mut duplicates: Vec<DuplicateCluster> = clusters
+        .into_iter()
+        .map(|b| b.cluster)
+        .filter(|c| c.requests > 1)
+        .collect();
+    duplicates.sort_by(|a, b| b.cacheable_sats.total_cmp(&a.cacheable_sats));
+
+    DuplicateReport {
+        range: range.to_string(),
+        threshold,
+        requests: prompts.len(),
+        spend_sats: prompts.iter().map(|p| p.cost_sats).sum(),
+        clusters: duplicates,
+        top,
+    }
+}
+
+/// The text of an archived messages array, lowercased with whitespace
+/// collapsed: one `role: content` line per message. Falls back to the raw
+/// text when it is not a messages array.
+fn normalize(prompt: &str) -> String {
+    let text = match serde_json::from_str::<Vec<serde_json::Value>>(prompt) {
+        Ok(messages) => messages
+            .iter()
+            .map(|m| {
+                let role = m["role"].as_str().unwrap_or("");
+                let content = match &m["content"] {
+                    serde_json::Value::String(s) => s.clone(),
+                    // Content parts: keep the text ones
+                    serde_json::Value::Array(parts) => parts
+                        .iter()
+                        .filter_map(|p| p["text"].as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    _ => String::new(),
+                };
+                format!("{}: {}", role, content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(_) => prompt.to_string(),
+    };
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Hashed word trigrams of `text` (the whole text for fewer than three words).
+fn shingles(text: &str) -> HashSet<u64> {
+    let words: Vec<&str> = text.split(' ').collect();
+    let hash = |parts: &[&str]| {
+        let mut hasher = DefaultHasher::new();
+        parts.hash(&mut hasher);
+        hasher.finish()
+    };
+    if words.len() < 3 {
+        return HashSet::from([hash(&words)]);
+    }
+    words.windows(3).map(hash).collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+impl fmt::Display for DuplicateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.requests == 0 {
+            return writeln!(
+                f,
+                "No archived prompts in {} (is [archive] enabled?)",
+                self.range
+            );
+        }
+        writeln!(
+            f,
+            "Archived requests: {} in {} ({:.2} sats)",
+            self.requests, self.range, self.spend_sats
+        )?;
+        writeln!(
+            f,
+            "Duplicates:        {} clusters, {} repeated requests (similarity >= {:.2})",
+            self.clusters.len(),
+            self.duplicate_requests(),
+            self.threshold
+        )?;
+        let share = if self.spend_sats > 0.0 {
+            self.cacheable_sats() / self.spend_sats * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            f,
+            "Cacheable spend:   {:.2} sats ({:.1}%)",
+            self.cacheable_sats(),
+            share
+        )?;
+        if self.clusters.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>8}  {:>12}  {:<20}  prompt",
+            "requests", "cacheable", "model"
+        )?;
+        for cluster in self.clusters.iter().take(self.top) {
+            writeln!(
+                f,
+                "{:>8}  {:>12.2}  {:<20}  {}",
+                cluster.requests, cluster.cacheable_sats, cluster.model, cluster.sample
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(model: &str, content: &str, cost_sats: f64) -> ArchivedPrompt {
+        ArchivedPrompt {
+            correlation_id: String::new(),
+            model: model.to_string(),
+            prompt: serde_json::json!([{"role": "user", "content": content}]).to_string(),
+            cost_sats,
+        }
+    }
+
+    #[test]
+    fn clusters_near_duplicates_per_model() {
+        let base =
+            "Summarize the quarterly revenue report for the board and list the three largest risks";
+        let prompts = vec![
+            prompt("gpt-4o", base, 10.0),
+            prompt("gpt-4o", &format!("  {}  ", base.to_uppercase()), 10.0),
+            // One word changed at the end
+            prompt("gpt-4o", &base.replace("risks", "threats"), 10.0),
+            // Same prompt, other model: its own cluster
+            prompt("gpt-4o-mini", base, 1.0),
+            prompt("gpt-4o", "Write a haiku about routers", 2.0),
+        ];
+
+        let report = find_duplicates(&prompts, 0.8, "last_30d", 10);
+        assert_eq!(report.requests, 5);
+        assert!((report.spend_sats - 33.0).abs() < 1e-9);
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].requests, 3);
+        assert!((report.cacheable_sats() - 20.0).abs() < 1e-9);
+        assert_eq!(report.duplicate_requests(), 2);
+
+        // A stricter threshold only keeps the exact (normalized) repeat
+        let report = find_duplicates(&prompts, 0.99, "last_30d", 10);
+        assert_eq!(report.clusters[0].requests, 2);
+        assert!(report.to_string().contains("Cacheable spend:   10.00 sats"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Load generator behind `arbstr bench`.
+//!
+//! Sends chat completions to a running proxy with fixed concurrency and
+//! reports throughput, latency percentiles, how requests were spread across
+//! providers, and heap allocations made in this process during the run
+//! (which covers the proxy itself under `bench --mock`).
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+
+/// Global allocator that counts allocations. Installed by the `arbstr`
+/// binary built with `--features alloc-stats`, and by the benches; the
+/// counters stay at zero when it is not the global allocator.
+pub struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Allocations made so far by this process. Take the difference of two
+/// readings to measure a section of code.
+pub fn allocations() -> AllocStats {
+    AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// What to send.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Proxy base URL, e.g. `http://127.0.0.1:8080`.
+    pub target: String,
+    pub requests: usize,
+    pub concurrency: usize,
+    pub model: String,
+    pub stream: bool,
+}
+
+/// Heap allocations made during the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocStats {
+    pub allocations: u64,
+    pub bytes: u64,
+}
+
+impl AllocStats {
+    /// Allocations between an earlier reading and this one.
+    pub fn since(self, earlier: AllocStats) -> AllocStats {
+        AllocStats {
+            allocations: self.allocations - earlier.allocations,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+/// Result of one bench run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub requests: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+    /// Full response latencies of successful requests, ascending.
+    pub latencies: Vec<Duration>,
+    /// Time to first body byte of successful requests, ascending.
+    pub first_byte: Vec<Duration>,
+    /// Successful requests per `x-arbstr-provider`.
+    pub providers: BTreeMap<String, usize>,
+    /// Failed requests per HTTP status (0 when the request never completed).
+    pub errors: BTreeMap<u16, usize>,
+    /// `None` when the counting allocator isn't installed.
+    pub alloc: Option<AllocStats>,
+}
+
+struct Sample {
+    status: u16,
+    provider: Option<String>,
+    first_byte: Duration,
+    latency: Duration,
+}
+
+/// Send `options.requests` requests, at most `options.concurrency` at a time.
+pub async fn run(client: &reqwest::Client, options: &BenchOptions) -> BenchReport {
+    let url = format!(
+        "{}/v1/chat/completions",
+        options.target.trim_end_matches('/')
+    );
+    let body = Arc::new(serde_json::json!({
+        "model": options.model,
+        "messages": [{"role": "user", "content": "Benchmark request: reply with a short sentence."}],
+        "stream": options.stream
+    }));
+
+    let before = allocations();
+    let start = Instant::now();
+    let samples: Vec<Sample> = futures::stream::iter(0..options.requests)
+        .map(|_| send_one(client, &url, body.clone()))
+        .buffer_unordered(options.concurrency.max(1))
+        .collect()
+        .await;
+    let elapsed = start.elapsed();
+    let after = allocations();
+
+    let mut report = BenchReport {
+        requests: options.requests,
+        succeeded: 0,
+        failed: 0,
+        elapsed,
+        latencies: Vec::new(),
+        first_byte: Vec::new(),
+        providers: BTreeMap::new(),
+        errors: BTreeMap::new(),
+        // Nothing counted means the counting allocator isn't installed
+        alloc: (after.allocations > 0).then(|| after.since(before)),
+    };
+    for sample in samples {
+        if (200..300).contains(&sample.status) {
+            report.succeeded += 1;
+            report.latencies.push(sample.latency);
+            report.first_byte.push(sample.first_byte);
+            let provider = sample.provider.unwrap_or_else(|| "unknown".to_string());
+            *report.providers.entry(provider).or_default() += 1;
+        } else {
+            report.failed += 1;
+            *report.errors.entry(sample.status).or_default() += 1;
+        }
+    }
+    report.latencies.sort();
+    report.first_byte.sort();
+    report
+}
+
+async fn send_one(client: &reqwest::Client, url: &str, body: Arc<serde_json::Value>) -> Sample {
+    let start = Instant::now();
+    let response = match client.post(url).json(&*body).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::debug!(error = %e, "Bench request failed");
+            return Sample {
+                status: 0,
+                provider: None,
+                first_byte: start.elapsed(),
+                latency: start.elapsed(),
+            };
+        }
+    };
+    let status = response.status().as_u16();
+    let provider = response
+        .headers()
+        .get("x-arbstr-provider")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    // Drain the body so streamed responses are timed to completion
+    let mut body = response.bytes_stream();
+    let mut first_byte = None;
+    let mut status = status;
+    while let Some(chunk) = body.next().await {
+        if chunk.is_err() {
+            status = 0;
+            break;
+        }
+        first_byte.get_or_insert_with(|| start.elapsed());
+    }
+    let latency = start.elapsed();
+    Sample {
+        status,
+        provider,
+        first_byte: first_byte.unwrap_or(latency),
+        latency,
+    }
+}
+
+/// Nearest-rank percentile of ascending `sorted`.
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl BenchReport {
+    /// Completed requests per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.requests as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Requests:    {} ({} ok, {} failed) in {:.2}s",
+            self.requests,
+            self.succeeded,
+            self.failed,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(f, "Throughput:  {:.1} req/s", self.throughput())?;
+        for (label, samples) in [
+            ("Latency:", &self.latencies),
+            ("First byte:", &self.first_byte),
+        ] {
+            writeln!(
+                f,
+                "{:<12} p50 {}  p90 {}  p99 {}  max {}",
+                label,
+                ms(percentile(samples, 50.0)),
+                ms(percentile(samples, 90.0)),
+                ms(percentile(samples, 99.0)),
+                ms(samples.last().copied().unwrap_or_default()),
+            )?;
+        }
+        let per_request = self.requests.max(1) as u64;
+        match self.alloc {
+            Some(alloc) => writeln!(
+                f,
+                "Allocations: {} ({} per request), {} bytes ({} per request)",
+                alloc.allocations,
+                alloc.allocations / per_request,
+                alloc.bytes,
+                alloc.bytes / per_request
+            )?,
+            None => writeln!(
+                f,
+                "Allocations: not counted (build with --features alloc-stats)"
+            )?,
+        }
+        if !self.providers.is_empty() {
+            writeln!(f, "Providers:")?;
+            for (name, count) in &self.providers {
+                writeln!(f, "  {:<20} {}", name, count)?;
+            }
+        }
+        if !self.errors.is_empty() {
+            writeln!(f, "Errors:")?;
+            for (status, count) in &self.errors {
+                let label = if *status == 0 {
+                    "transport".to_string()
+                } else {
+                    format!("HTTP {}", status)
+                };
+                writeln!(f, "  {:<20} {}", label, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&samples[..1], 90.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn report_renders_summary() {
+        let report = BenchReport {
+            requests: 4,
+            succeeded: 3,
+            failed: 1,
+            elapsed: Duration::from_secs(2),
+            latencies: vec![Duration::from_millis(10); 3],
+            first_byte: vec![Duration::from_millis(5); 3],
+            providers: BTreeMap::from([("alpha".to_string(), 3)]),
+            errors: BTreeMap::from([(502, 1)]),
+            alloc: Some(AllocStats {
+                allocations: 400,
+                bytes: 8000,
+            }),
+        };
+        assert_eq!(report.throughput(), 2.0);
+        let text = report.to_string();
+        assert!(text.contains("3 ok, 1 failed"));
+        assert!(text.contains("p50 10.0ms"));
+        assert!(text.contains("100 per request"));
+        assert!(text.contains("alpha"));
+        assert!(text.contains("HTTP 502"));
+
+        let uncounted = BenchReport {
+            alloc: None,
+            ..report
+        };
+        assert!(uncounted.to_string().contains("not counted"));
+    }
+}
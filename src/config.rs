@@ -2,6 +2,8 @@
 
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
 use std::path::Path;
 
 /// Root configuration structure.
@@ -18,45 +20,730 @@ pub struct Config {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub routing: RoutingConfig,
+    pub reports: Option<ReportsConfig>,
+    #[serde(default)]
+    pub headers: HeadersConfig,
+    #[serde(default)]
+    pub dns: DnsConfig,
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    pub currency: Option<CurrencyConfig>,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    /// Record provider interactions to disk, or replay them offline.
+    pub recording: Option<RecordingConfig>,
+    /// Nightly prompt-suite evaluation of every provider/model pair.
+    pub evaluation: Option<EvaluationConfig>,
+    #[serde(default)]
+    pub arbitrage: ArbitrageConfig,
+    /// Announce the model catalogue on Nostr relays.
+    pub nostr: Option<NostrConfig>,
+    #[serde(default)]
+    pub receipts: ReceiptsConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    /// Stop streams whose reply text matches a blocklist entry or pattern.
+    pub content_filter: Option<ContentFilterConfig>,
+    /// Alert on cost spikes against a rolling baseline.
+    pub anomaly: Option<AnomalyConfig>,
 }
 
 /// HTTP server configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ServerConfig {
-    /// Address to listen on (e.g., "127.0.0.1:8080")
-    #[serde(default = "default_listen")]
-    pub listen: String,
+    /// Addresses to listen on, all serving the same proxy: `host:port`
+    /// (IPv6 in brackets, e.g. "[::1]:8080") or `unix:/path/to.sock`.
+    /// A single string or an array.
+    #[serde(default = "default_listen", deserialize_with = "one_or_many")]
+    pub listen: Vec<String>,
+    /// Addresses serving only the read-only analytics endpoints (stats,
+    /// logs, routing introspection), in the same forms as `listen`. When
+    /// set, those endpoints are no longer served on `listen`. Default: none.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub analytics_listen: Vec<String>,
     /// Global rate limit in requests per second (0 or absent = unlimited)
     #[serde(default)]
     pub rate_limit_rps: Option<u64>,
     /// Optional bearer token for proxy endpoint authentication
     pub auth_token: Option<String>,
+    /// Optional bearer token for stats, logs, and `/admin` endpoints,
+    /// distinct from the client token. Without it stats and logs are open
+    /// and `/admin` falls back to `auth_token`.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Requests with a larger `Content-Length` are streamed to the provider
+    /// after reading just enough to find the model, instead of being buffered
+    /// and parsed. Requests whose policy rewrites the body (system prompt,
+    /// trimming, compression, token limits), requests without a policy
+    /// header when policies are picked by prompt length, and requests with
+    /// a `response_format` in the part read to find the model, are still
+    /// buffered, as are requests paid with a voucher.
+    /// Ignored when vault billing is configured. Absent = never.
+    #[serde(default)]
+    pub stream_body_threshold_bytes: Option<u64>,
+    /// CORS for browser clients. Absent = no CORS headers, so browsers
+    /// block cross-origin calls.
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+    /// Edge limits on request size, time, and concurrency.
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Where chat completion responses carry arbstr's routing metadata.
+    /// Default: "body".
+    #[serde(default)]
+    pub metadata_mode: MetadataMode,
+    /// Send an SSE `: keep-alive` comment to streaming clients after this
+    /// many seconds without provider output, so idle connections are not
+    /// dropped by clients or proxies in between. Absent = never.
+    #[serde(default)]
+    pub sse_keepalive_secs: Option<u64>,
+}
+
+/// Where arbstr reports routing metadata (provider, cost, latency, retries)
+/// on chat completion responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataMode {
+    /// `x-arbstr-*` headers, plus an `arbstr` object in JSON bodies and a
+    /// trailing `arbstr` event on streams.
+    #[default]
+    Body,
+    /// `x-arbstr-*` headers only; bodies are the provider's, unchanged.
+    Headers,
+    /// Only `x-arbstr-request-id`; no other metadata headers or body fields.
+    None,
+}
+
+fn default_listen() -> Vec<String> {
+    vec!["127.0.0.1:8080".to_string()]
+}
+
+/// Accept either a single string or an array of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+/// A parsed `server.listen` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    /// A TCP `host:port`.
+    Tcp(String),
+    /// A Unix domain socket path.
+    Unix(std::path::PathBuf),
+}
+
+impl ListenAddr {
+    /// Parse a `server.listen` entry.
+    pub fn parse(addr: &str) -> Result<Self, String> {
+        if let Some(path) = addr.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(format!("'{}' has no socket path", addr));
+            }
+            if cfg!(not(unix)) {
+                return Err(format!(
+                    "'{}': Unix sockets are not supported on this platform",
+                    addr
+                ));
+            }
+            return Ok(Self::Unix(path.into()));
+        }
+        match addr.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(Self::Tcp(addr.to_string()))
+            }
+            _ => Err(format!("'{}' is not host:port or unix:/path/to.sock", addr)),
+        }
+    }
+}
+
+impl std::fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => f.write_str(addr),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// The parsed `listen` addresses.
+    pub fn listen_addrs(&self) -> Result<Vec<ListenAddr>, String> {
+        self.listen.iter().map(|a| ListenAddr::parse(a)).collect()
+    }
+
+    /// The parsed `analytics_listen` addresses.
+    pub fn analytics_listen_addrs(&self) -> Result<Vec<ListenAddr>, String> {
+        self.analytics_listen
+            .iter()
+            .map(|a| ListenAddr::parse(a))
+            .collect()
+    }
+}
+
+/// `[server.limits]`: limits enforced at the server edge, before routing.
+/// Every limit is off unless set.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LimitsConfig {
+    /// Largest request body accepted, in bytes; larger ones get 413.
+    /// Absent = axum's 2 MiB limit on parsed requests only.
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+    /// Requests without response headers after this long get 504. Streams
+    /// that have started are not cut off.
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// Requests handled at once, counting open streams until they finish;
+    /// requests beyond it get 503 immediately.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+}
+
+impl LimitsConfig {
+    /// Whether any limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_body_bytes.is_some()
+            || self.request_timeout_secs.is_some()
+            || self.max_concurrent_requests.is_some()
+    }
+}
+
+/// `[server.cors]`: lets browser playgrounds and dashboards on other
+/// origins call arbstr directly.
+///
+/// `"*"` in `allowed_origins`, `allowed_headers`, `allowed_methods`, or
+/// `expose_headers` allows any value, but browsers refuse wildcards on credentialed
+/// requests, so none may be combined with `allow_credentials`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to call arbstr, e.g. "http://localhost:3000".
+    pub allowed_origins: Vec<String>,
+    /// Request headers browsers may send. Default: authorization, content-type.
+    #[serde(default = "default_cors_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    /// Default: GET, POST.
+    #[serde(default = "default_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Response headers scripts may read. Default: the arbstr request ID,
+    /// provider, cost, and latency headers.
+    #[serde(default = "default_cors_expose_headers")]
+    pub expose_headers: Vec<String>,
+    /// Allow cookies and `Authorization` on cross-origin requests. Default: false.
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response. Absent = browser default.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+fn default_cors_allowed_headers() -> Vec<String> {
+    ["authorization", "content-type"].map(String::from).to_vec()
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    ["GET", "POST"].map(String::from).to_vec()
+}
+
+fn default_cors_expose_headers() -> Vec<String> {
+    [
+        "x-arbstr-request-id",
+        "x-arbstr-provider",
+        "x-arbstr-cost-sats",
+        "x-arbstr-latency-ms",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl CorsConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.allowed_origins.is_empty() {
+            return Err("allowed_origins needs at least one origin".to_string());
+        }
+        let lists = [
+            ("allowed_origins", &self.allowed_origins),
+            ("allowed_headers", &self.allowed_headers),
+            ("allowed_methods", &self.allowed_methods),
+            ("expose_headers", &self.expose_headers),
+        ];
+        for (field, values) in lists {
+            let wildcard = values.iter().any(|v| v == "*");
+            if wildcard && self.allow_credentials {
+                return Err(format!(
+                    "{} cannot contain \"*\" with allow_credentials",
+                    field
+                ));
+            }
+            if wildcard && values.len() > 1 {
+                return Err(format!("{} mixes \"*\" with other values", field));
+            }
+        }
+        for origin in self.allowed_origins.iter().filter(|o| *o != "*") {
+            if reqwest::header::HeaderValue::from_str(origin).is_err()
+                || !(origin.starts_with("http://") || origin.starts_with("https://"))
+                || origin.ends_with('/')
+            {
+                return Err(format!(
+                    "invalid origin '{}' (expected scheme://host[:port])",
+                    origin
+                ));
+            }
+        }
+        let header_lists = [&self.allowed_headers, &self.expose_headers];
+        for name in header_lists.into_iter().flatten().filter(|h| *h != "*") {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("invalid header name '{}'", name));
+            }
+        }
+        for method in self.allowed_methods.iter().filter(|m| *m != "*") {
+            if reqwest::Method::from_bytes(method.as_bytes()).is_err() {
+                return Err(format!("invalid method '{}'", method));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How upstream hostnames are resolved.
+///
+/// Per-provider `resolve` overrides take precedence over either resolver.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DnsConfig {
+    /// `system` (default) uses the OS resolver; `doh` queries `doh_url`.
+    #[serde(default)]
+    pub resolver: DnsResolverKind,
+    /// DNS-over-HTTPS endpoint speaking the JSON API (`application/dns-json`).
+    /// Its own hostname is resolved by the system resolver, so use an IP
+    /// address URL where system DNS is unavailable.
+    #[serde(default = "default_doh_url")]
+    pub doh_url: String,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            resolver: DnsResolverKind::default(),
+            doh_url: default_doh_url(),
+        }
+    }
+}
+
+fn default_doh_url() -> String {
+    "https://cloudflare-dns.com/dns-query".to_string()
+}
+
+/// Fault injection for rehearsing failures. Disabled by default.
+///
+/// Applies to requests sent to `providers` (every provider when empty).
+/// Injected errors fail before the provider is contacted and are handled
+/// exactly like real ones, so retries, fallback, circuit breakers, and
+/// alerting can be exercised on a staging deployment.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosConfig {
+    /// Default: false.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Providers to disrupt. Default: all.
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Delay added before each request is sent. Default: 0.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Extra random delay of up to this many milliseconds. Default: 0.
+    #[serde(default)]
+    pub latency_jitter_ms: u64,
+    /// Fraction of requests (0.0-1.0) failed with `error_status`. Default: 0.0.
+    #[serde(default)]
+    pub error_rate: f64,
+    /// 5xx status returned for injected errors. Default: 503.
+    #[serde(default = "default_chaos_error_status")]
+    pub error_status: u16,
+    /// Fraction of streams (0.0-1.0) cut off mid-response. Default: 0.0.
+    #[serde(default)]
+    pub drop_stream_rate: f64,
+    /// Fraction of streams (0.0-1.0) given an unparseable SSE event. Default: 0.0.
+    #[serde(default)]
+    pub malformed_sse_rate: f64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            providers: Vec::new(),
+            latency_ms: 0,
+            latency_jitter_ms: 0,
+            error_rate: 0.0,
+            error_status: default_chaos_error_status(),
+            drop_stream_rate: 0.0,
+            malformed_sse_rate: 0.0,
+        }
+    }
+}
+
+fn default_chaos_error_status() -> u16 {
+    503
+}
+
+/// Connection warmup at startup (`[warmup]`).
+///
+/// Each provider is sent a `GET /models` through the client that will carry
+/// its traffic, so DNS, TCP, and TLS setup happen before the first user
+/// request. `/ready` answers 503 until warmup has finished.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WarmupConfig {
+    /// Default: false.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also send a 1-token completion for the provider's first model, to warm
+    /// the provider's own serving path. Costs one tiny request per provider.
+    /// Default: false.
+    #[serde(default)]
+    pub completion: bool,
+    /// Per-request timeout. Default: 5000.
+    #[serde(default = "default_warmup_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            completion: false,
+            timeout_ms: default_warmup_timeout_ms(),
+        }
+    }
+}
+
+fn default_warmup_timeout_ms() -> u64 {
+    5000
 }
 
-fn default_listen() -> String {
-    "127.0.0.1:8080".to_string()
+/// Analytics endpoint settings (`[stats]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsConfig {
+    /// Seconds a `/v1/stats` response is served from cache for the same
+    /// query, also sent as `Cache-Control: max-age`. Keeps dashboards that
+    /// poll every second off the database. Default: 0 (no caching).
+    #[serde(default)]
+    pub cache_ttl_secs: u64,
+    /// Maintain hourly/daily rollup tables and answer long-range `/v1/stats`
+    /// queries from them instead of scanning raw rows. Default: false.
+    #[serde(default)]
+    pub rollups: bool,
+    /// Seconds between rollup refreshes. Default: 300.
+    #[serde(default = "default_rollup_interval_secs")]
+    pub rollup_interval_secs: u64,
+    /// Shortest query range, in hours, answered from rollups. Default: 24.
+    #[serde(default = "default_rollup_min_range_hours")]
+    pub rollup_min_range_hours: u64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: 0,
+            rollups: false,
+            rollup_interval_secs: default_rollup_interval_secs(),
+            rollup_min_range_hours: default_rollup_min_range_hours(),
+        }
+    }
+}
+
+fn default_rollup_interval_secs() -> u64 {
+    300
+}
+
+fn default_rollup_min_range_hours() -> u64 {
+    24
+}
+
+/// Fiat conversion of sats costs for reporting (`[currency]`).
+///
+/// The rate in effect is stored with each request, so historical totals use
+/// the price at the time of the request rather than today's.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurrencyConfig {
+    /// ISO 4217 code costs are converted to, e.g. "USD" or "EUR".
+    pub code: String,
+    /// Static price of one bitcoin in `code`. With `price_url` set, used
+    /// only until the first successful fetch.
+    #[serde(default)]
+    pub btc_price: Option<f64>,
+    /// JSON endpoint polled for the bitcoin price.
+    #[serde(default)]
+    pub price_url: Option<String>,
+    /// JSON pointer to the price in the `price_url` response; the value may
+    /// be a number or a numeric string. Default: "/data/amount" (Coinbase
+    /// spot price format).
+    #[serde(default = "default_price_pointer")]
+    pub price_pointer: String,
+    /// Seconds between `price_url` fetches. Default: 300.
+    #[serde(default = "default_price_refresh_secs")]
+    pub refresh_secs: u64,
+}
+
+fn default_price_pointer() -> String {
+    "/data/amount".to_string()
+}
+
+fn default_price_refresh_secs() -> u64 {
+    300
+}
+
+/// Whether provider interactions are recorded or replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingMode {
+    /// Send to providers and append each interaction to `dir`.
+    Record,
+    /// Answer from `dir` without contacting providers.
+    Replay,
+}
+
+/// VCR-style provider recording (`[recording]`).
+///
+/// Each provider's interactions are kept in `<dir>/<provider>.jsonl`.
+/// Credentials never reach the files: request headers are not recorded and
+/// cookie headers are dropped from responses. Prompts and replies do, so
+/// `mode = "record"` is rejected with `[privacy] strip_prompts`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingConfig {
+    pub mode: RecordingMode,
+    /// Directory holding the recordings. Default: "./recordings".
+    #[serde(default = "default_recording_dir")]
+    pub dir: String,
+    /// Replay with the recorded time to first byte and gaps between
+    /// stream chunks, instead of as fast as possible. Default: false.
+    #[serde(default)]
+    pub realtime: bool,
+}
+
+fn default_recording_dir() -> String {
+    "./recordings".to_string()
+}
+
+/// How client identifiers (`x-request-id`, trace IDs) are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientIdMode {
+    /// Stored as received.
+    #[default]
+    Off,
+    /// Replaced by a keyed hash, so requests can still be correlated with
+    /// each other but not with the client's own records.
+    Hash,
+    /// Not stored.
+    Omit,
+}
+
+impl std::fmt::Display for ClientIdMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientIdMode::Off => write!(f, "off"),
+            ClientIdMode::Hash => write!(f, "hash"),
+            ClientIdMode::Omit => write!(f, "omit"),
+        }
+    }
+}
+
+/// Request log anonymization (`[privacy]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrivacyConfig {
+    /// Treatment of client identifiers in the request log. Default: off.
+    #[serde(default)]
+    pub mode: ClientIdMode,
+    /// HMAC key for `mode = "hash"`. When absent a random key is generated
+    /// at startup, so hashes only correlate within one process lifetime.
+    #[serde(default)]
+    pub salt: Option<ApiKey>,
+    /// Never store upstream error bodies, which may echo prompt content.
+    /// The prompt archive (`[archive]`), policy dataset capture, and
+    /// `[recording] mode = "record"` are rejected alongside it, and arbstr
+    /// stores no prompts otherwise, so
+    /// logs, backups, and reports are then prompt-free. Default: false.
+    #[serde(default)]
+    pub strip_prompts: bool,
+    /// Clear trace, client request, and conversation IDs from request rows
+    /// older than this many days. Absent = kept forever.
+    #[serde(default)]
+    pub correlation_retention_days: Option<u32>,
+}
+
+/// DNS resolver selection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsResolverKind {
+    #[default]
+    System,
+    Doh,
+}
+
+/// Header passthrough between clients and providers.
+///
+/// Only headers named here are forwarded; everything else is dropped.
+/// Names are case-insensitive. Headers in [`NEVER_FORWARDED_HEADERS`] are
+/// rejected at config load since forwarding them would leak credentials or
+/// corrupt framing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeadersConfig {
+    /// Client request headers forwarded to the provider.
+    /// Default: OpenAI organization/project/beta and W3C trace context.
+    #[serde(default = "default_forward_request_headers")]
+    pub forward_request: Vec<String>,
+    /// Provider response headers forwarded to the client.
+    /// Default: OpenAI-style `x-ratelimit-*` headers.
+    #[serde(default = "default_forward_response_headers")]
+    pub forward_response: Vec<String>,
+}
+
+impl Default for HeadersConfig {
+    fn default() -> Self {
+        Self {
+            forward_request: default_forward_request_headers(),
+            forward_response: default_forward_response_headers(),
+        }
+    }
+}
+
+fn default_forward_request_headers() -> Vec<String> {
+    [
+        "openai-organization",
+        "openai-project",
+        "openai-beta",
+        "traceparent",
+        "tracestate",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_forward_response_headers() -> Vec<String> {
+    [
+        "x-ratelimit-limit-requests",
+        "x-ratelimit-limit-tokens",
+        "x-ratelimit-remaining-requests",
+        "x-ratelimit-remaining-tokens",
+        "x-ratelimit-reset-requests",
+        "x-ratelimit-reset-tokens",
+    ]
+    .map(String::from)
+    .to_vec()
 }
 
+/// Headers that are never forwarded in either direction: credentials,
+/// hop-by-hop and framing headers, and headers arbstr sets itself.
+pub const NEVER_FORWARDED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "cookie",
+    "set-cookie",
+    "host",
+    "connection",
+    "keep-alive",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+    "content-type",
+    "content-encoding",
+    "idempotency-key",
+];
+
 /// Database configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct DatabaseConfig {
     /// Path to SQLite database file
     #[serde(default = "default_db_path")]
     pub path: String,
+    /// Where request history is kept. Default: sqlite (the file at `path`).
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Most request rows the memory backend keeps; the oldest are pruned
+    /// beyond this, with archived prompts, receipts, comparisons, and
+    /// evaluations from the same period. Ignored by the sqlite backend.
+    /// Default: 100000.
+    #[serde(default = "default_memory_max_rows")]
+    pub max_rows: u64,
+    /// Periodic online backups (optional).
+    pub backup: Option<BackupConfig>,
 }
 
 fn default_db_path() -> String {
     "./arbstr.db".to_string()
 }
 
+fn default_memory_max_rows() -> u64 {
+    100_000
+}
+
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             path: default_db_path(),
+            backend: StorageBackend::default(),
+            max_rows: default_memory_max_rows(),
+            backup: None,
         }
     }
 }
 
+/// Request history storage backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// SQLite database file at `database.path`.
+    #[default]
+    Sqlite,
+    /// Bounded in-memory SQLite database, for ephemeral deployments.
+    /// Stats and logs work as usual but are lost on restart.
+    Memory,
+}
+
+/// Online database backup configuration.
+///
+/// Backups are written with `VACUUM INTO` while the proxy keeps serving,
+/// named `arbstr-<UTC timestamp>.db`, and rotated so only the newest
+/// `keep` files remain in `dir`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackupConfig {
+    /// Directory that receives backup files (created if missing).
+    pub dir: String,
+    /// Hours between automatic backups. 0 disables the periodic task
+    /// (on-demand backups via the admin API still rotate). Default: 24.
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u64,
+    /// Number of backup files to retain. Default: 7.
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+}
+
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
 /// API key wrapper that redacts in Debug/Display/Serialize and zeroizes on drop.
 ///
 /// The inner `SecretString` ensures the key value is:
@@ -200,6 +887,60 @@ pub struct RoutingConfig {
     /// Signal weights for the complexity scorer.
     #[serde(default)]
     pub complexity_weights: ComplexityWeightsConfig,
+    /// Reliability-based demotion of providers. Disabled when absent.
+    #[serde(default)]
+    pub reputation: Option<ReputationConfig>,
+    /// Promotion thresholds for providers marked `canary = true`.
+    #[serde(default)]
+    pub canary: CanaryConfig,
+    /// Global cap on retries relative to request volume. Disabled when absent.
+    #[serde(default)]
+    pub retry_budget: Option<RetryBudgetConfig>,
+    /// Default backoff between retries. Providers and policies may override it.
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+    /// How many further candidates are tried, once each and in routing
+    /// order, after the primary exhausts its retries. Default: 1
+    #[serde(default = "default_max_fallback_providers")]
+    pub max_fallback_providers: usize,
+    /// Suspension of providers that reject their API key.
+    #[serde(default)]
+    pub auth_quarantine: AuthQuarantineConfig,
+    /// Check non-streaming `json_object` / `json_schema` responses at the
+    /// proxy and re-ask the provider once when they don't conform.
+    /// Default: false.
+    #[serde(default)]
+    pub validate_structured_output: bool,
+    /// Save reputation, canary and circuit breaker state to the database
+    /// and reload it on startup, so routing doesn't start from scratch
+    /// after every deploy and an open circuit stays open across a crash
+    /// loop. Default: true.
+    #[serde(default = "default_true")]
+    pub persist_state: bool,
+    /// How often routing state is saved while running, in seconds, on top
+    /// of the save at shutdown. 0 saves only at shutdown. Default: 30.
+    #[serde(default = "default_persist_interval_secs")]
+    pub persist_interval_secs: u64,
+    /// Circuit breaker tuning.
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Windows and alerting for provider SLOs (`[providers.slo]`).
+    #[serde(default)]
+    pub slo: SloConfig,
+    /// How candidates with the same routing cost are ordered, tried in
+    /// turn until one tells them apart. Empty (the default) keeps them in
+    /// the order the providers are declared.
+    #[serde(default)]
+    pub tie_breakers: Vec<TieBreaker>,
+    /// How long a candidate ordering is reused for requests with the same
+    /// model, policy and tier, in milliseconds. 0 (the default) computes
+    /// it for every request.
+    #[serde(default)]
+    pub route_cache_ttl_ms: u64,
+}
+
+fn default_max_fallback_providers() -> usize {
+    1
 }
 
 fn default_threshold_low() -> f64 {
@@ -215,37 +956,431 @@ impl Default for RoutingConfig {
             complexity_threshold_low: default_threshold_low(),
             complexity_threshold_high: default_threshold_high(),
             complexity_weights: ComplexityWeightsConfig::default(),
+            reputation: None,
+            canary: CanaryConfig::default(),
+            retry_budget: None,
+            backoff: BackoffConfig::default(),
+            max_fallback_providers: default_max_fallback_providers(),
+            auth_quarantine: AuthQuarantineConfig::default(),
+            validate_structured_output: false,
+            persist_state: true,
+            persist_interval_secs: default_persist_interval_secs(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            slo: SloConfig::default(),
+            tie_breakers: Vec::new(),
+            route_cache_ttl_ms: 0,
         }
     }
 }
 
-/// Signal weights for the heuristic complexity scorer.
+/// A rule for ordering candidates whose routing cost is equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreaker {
+    /// Lower average latency of recent successful requests first.
+    Latency,
+    /// Higher share of recent requests that succeeded first.
+    SuccessRate,
+    /// The provider declared first in the config first.
+    Preference,
+    /// A fresh random order for every request.
+    Random,
+}
+
+impl TieBreaker {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TieBreaker::Latency => "latency",
+            TieBreaker::SuccessRate => "success_rate",
+            TieBreaker::Preference => "preference",
+            TieBreaker::Random => "random",
+        }
+    }
+}
+
+/// What happens to a provider whose reputation falls below thresholds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReputationAction {
+    /// Multiply the provider's routing cost so cheaper healthy providers win.
+    #[default]
+    Penalize,
+    /// Drop the provider from candidates unless no other provider remains.
+    Exclude,
+}
+
+/// Rolling reliability thresholds for provider demotion.
 ///
-/// All weights default to 1.0 (equal weighting). Parsed in Phase 16 but
-/// consumed by the scorer in Phase 17.
+/// Each provider's last `window` outcomes are tracked in memory. Once at
+/// least `min_requests` are recorded and either the error rate or the
+/// average success latency exceeds its threshold, the provider is demoted
+/// for `penalty_secs`. A cost-multiplier penalty decays linearly back to
+/// 1.0 over that period.
 #[derive(Debug, Clone, Deserialize)]
-pub struct ComplexityWeightsConfig {
-    #[serde(default = "default_weight")]
-    pub context_length: f64,
-    #[serde(default = "default_weight")]
-    pub code_blocks: f64,
-    #[serde(default = "default_weight")]
-    pub multi_file: f64,
-    #[serde(default = "default_weight")]
-    pub reasoning_keywords: f64,
-    #[serde(default = "default_weight")]
-    pub conversation_depth: f64,
-    /// Additional keywords that boost the reasoning signal (user-extensible).
+pub struct ReputationConfig {
+    /// Number of recent outcomes kept per provider. Default: 50.
+    #[serde(default = "default_reputation_window")]
+    pub window: usize,
+    /// Outcomes required before a provider can be demoted. Default: 10.
+    #[serde(default = "default_reputation_min_requests")]
+    pub min_requests: usize,
+    /// Error rate (0.0-1.0) above which a provider is demoted. Default: 0.25.
+    #[serde(default = "default_reputation_max_error_rate")]
+    pub max_error_rate: f64,
+    /// Average success latency (ms) above which a provider is demoted.
+    pub max_avg_latency_ms: Option<u64>,
+    /// "penalize" (cost multiplier) or "exclude". Default: penalize.
     #[serde(default)]
-    pub extra_keywords: Vec<String>,
+    pub action: ReputationAction,
+    /// Routing cost multiplier applied at the start of a penalty. Default: 2.0.
+    #[serde(default = "default_reputation_cost_multiplier")]
+    pub cost_multiplier: f64,
+    /// How long a demotion lasts, in seconds. Default: 300.
+    #[serde(default = "default_reputation_penalty_secs")]
+    pub penalty_secs: u64,
 }
 
-fn default_weight() -> f64 {
-    1.0
+fn default_reputation_window() -> usize {
+    50
 }
-
-impl Default for ComplexityWeightsConfig {
-    fn default() -> Self {
+fn default_reputation_min_requests() -> usize {
+    10
+}
+fn default_reputation_max_error_rate() -> f64 {
+    0.25
+}
+fn default_reputation_cost_multiplier() -> f64 {
+    2.0
+}
+fn default_reputation_penalty_secs() -> u64 {
+    300
+}
+
+/// Promotion thresholds for canary providers.
+///
+/// A canary provider only receives `canary_percent` of its eligible traffic
+/// until its success rate over the last `min_requests` requests reaches
+/// `min_success_rate`. Promotion is held in memory and resets on restart.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CanaryConfig {
+    /// Requests a canary must serve before it can be promoted. Default: 50.
+    #[serde(default = "default_canary_min_requests")]
+    pub min_requests: usize,
+    /// Success rate (0.0-1.0) required for promotion. Default: 0.95.
+    #[serde(default = "default_canary_min_success_rate")]
+    pub min_success_rate: f64,
+    /// Promote automatically once healthy. When false, a warning is logged
+    /// and the provider stays a canary until the config changes. Default: true.
+    #[serde(default = "default_true")]
+    pub auto_promote: bool,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            min_requests: default_canary_min_requests(),
+            min_success_rate: default_canary_min_success_rate(),
+            auto_promote: true,
+        }
+    }
+}
+
+/// Exponential backoff between retries.
+///
+/// Retry `n` (1-based) waits up to `min(max_ms, base_ms * multiplier^(n-1))`.
+/// With `jitter` the wait is drawn uniformly from zero to that ceiling ("full
+/// jitter"), so clients retrying a recovering provider spread out instead of
+/// arriving together.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BackoffConfig {
+    /// Ceiling for the first retry, in milliseconds. Default: 1000.
+    #[serde(default = "default_backoff_base_ms")]
+    pub base_ms: u64,
+    /// Growth factor per retry. Default: 2.0.
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+    /// Upper bound on any single wait, in milliseconds. Default: 4000.
+    #[serde(default = "default_backoff_max_ms")]
+    pub max_ms: u64,
+    /// Full jitter: wait a random duration up to the ceiling. Default: true.
+    #[serde(default = "default_true")]
+    pub jitter: bool,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            base_ms: default_backoff_base_ms(),
+            multiplier: default_backoff_multiplier(),
+            max_ms: default_backoff_max_ms(),
+            jitter: true,
+        }
+    }
+}
+
+fn default_backoff_base_ms() -> u64 {
+    1000
+}
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+fn default_backoff_max_ms() -> u64 {
+    4000
+}
+
+/// Global retry budget.
+///
+/// Over the last `window_secs`, retries and fallback attempts may total at
+/// most `min_retries` plus `ratio` times the number of requests. Once spent,
+/// a failed request is returned without retrying so a flapping provider
+/// cannot multiply upstream cost.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryBudgetConfig {
+    /// Retries allowed per request in the window (0.0-1.0). Default: 0.2.
+    #[serde(default = "default_retry_budget_ratio")]
+    pub ratio: f64,
+    /// Rolling window length in seconds. Default: 60.
+    #[serde(default = "default_retry_budget_window_secs")]
+    pub window_secs: u64,
+    /// Retries always allowed per window, so low traffic can still retry. Default: 10.
+    #[serde(default = "default_retry_budget_min_retries")]
+    pub min_retries: usize,
+}
+
+impl Default for RetryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            ratio: default_retry_budget_ratio(),
+            window_secs: default_retry_budget_window_secs(),
+            min_retries: default_retry_budget_min_retries(),
+        }
+    }
+}
+
+fn default_persist_interval_secs() -> u64 {
+    30
+}
+
+/// Circuit breaker tuning (`[routing.circuit_breaker]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that trip a circuit, for error types without
+    /// an entry in `thresholds`. Default: 3.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Per-error-type thresholds, keyed by `timeout`, `connect`, `tls`,
+    /// `5xx` or `malformed`. Each failure counts `1 / threshold` of its
+    /// type toward tripping, so mixed failures add up.
+    #[serde(default)]
+    pub thresholds: BTreeMap<String, u32>,
+    /// Extended cool-down for providers whose circuit keeps re-opening.
+    /// Disabled when absent.
+    #[serde(default)]
+    pub trip_storm: Option<TripStormConfig>,
+    /// Probe a circuit whose open timeout has expired with a 1-token
+    /// completion sent by arbstr itself, rather than letting the next user
+    /// request through as the probe. User requests keep failing over until
+    /// the probe succeeds. Default: false.
+    #[serde(default)]
+    pub synthetic_probe: bool,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            thresholds: BTreeMap::new(),
+            trip_storm: None,
+            synthetic_probe: false,
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+/// Error types that count toward tripping a circuit, as keys of
+/// `[routing.circuit_breaker.thresholds]`.
+pub const CIRCUIT_ERROR_TYPES: [&str; 5] = ["timeout", "connect", "tls", "5xx", "malformed"];
+
+/// A provider whose circuit opens more than `max_trips` times within
+/// `window_secs` (trips and failed half-open probes both count) is kept
+/// open for `cooldown_secs` instead of being probed every 30 seconds, and
+/// an alert is raised.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TripStormConfig {
+    /// Openings allowed in the window before the cool-down. Default: 5.
+    #[serde(default = "default_trip_storm_max_trips")]
+    pub max_trips: u32,
+    /// Rolling window length in seconds. Default: 3600.
+    #[serde(default = "default_trip_storm_window_secs")]
+    pub window_secs: u64,
+    /// How long the circuit stays open once a storm is detected. Default: 7200.
+    #[serde(default = "default_trip_storm_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// URL that receives a JSON POST when a cool-down starts.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for TripStormConfig {
+    fn default() -> Self {
+        Self {
+            max_trips: default_trip_storm_max_trips(),
+            window_secs: default_trip_storm_window_secs(),
+            cooldown_secs: default_trip_storm_cooldown_secs(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_trip_storm_max_trips() -> u32 {
+    5
+}
+fn default_trip_storm_window_secs() -> u64 {
+    3600
+}
+fn default_trip_storm_cooldown_secs() -> u64 {
+    7200
+}
+
+/// Quarantine for providers that answer 401/403.
+///
+/// A rejected API key will not fix itself, so the provider is removed from
+/// routing immediately rather than retried, and an alert is raised. After
+/// `retry_after_secs` a single request is let through to see whether the
+/// key has been replaced.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthQuarantineConfig {
+    /// Default: true.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Seconds before a quarantined provider is tried again. Default: 600.
+    #[serde(default = "default_auth_quarantine_retry_after_secs")]
+    pub retry_after_secs: u64,
+    /// URL that receives a JSON POST when a provider is quarantined.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for AuthQuarantineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retry_after_secs: default_auth_quarantine_retry_after_secs(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_auth_quarantine_retry_after_secs() -> u64 {
+    600
+}
+
+/// How provider SLOs (`[providers.slo]`) are measured and alerted on.
+///
+/// Compliance is reported over the last `window_secs`. The burn rate is
+/// how fast a provider is spending its error budget over the last
+/// `alert_window_secs` (1.0 = exactly on budget); at or above
+/// `burn_rate_threshold` an alert is raised, and a recovery once it falls
+/// back below.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SloConfig {
+    /// Compliance window in seconds. Default: 3600.
+    #[serde(default = "default_slo_window_secs")]
+    pub window_secs: u64,
+    /// Window the alerting burn rate is measured over. Default: 300.
+    #[serde(default = "default_slo_alert_window_secs")]
+    pub alert_window_secs: u64,
+    /// Burn rate that raises an alert. Default: 2.0.
+    #[serde(default = "default_slo_burn_rate_threshold")]
+    pub burn_rate_threshold: f64,
+    /// Requests needed in the alert window before alerting. Default: 20.
+    #[serde(default = "default_slo_min_requests")]
+    pub min_requests: usize,
+    /// URL that receives a JSON POST when an alert is raised or recovers.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: default_slo_window_secs(),
+            alert_window_secs: default_slo_alert_window_secs(),
+            burn_rate_threshold: default_slo_burn_rate_threshold(),
+            min_requests: default_slo_min_requests(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_slo_window_secs() -> u64 {
+    3600
+}
+fn default_slo_alert_window_secs() -> u64 {
+    300
+}
+fn default_slo_burn_rate_threshold() -> f64 {
+    2.0
+}
+fn default_slo_min_requests() -> usize {
+    20
+}
+
+/// Service level objectives for one provider. Each request the provider
+/// answers counts once; requests it fails with a 5xx or a timeout count
+/// against `success_rate`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProviderSloConfig {
+    /// 95% of successful requests should see headers within this many ms.
+    pub p95_latency_ms: Option<u64>,
+    /// Share of requests that should succeed, e.g. 0.99.
+    pub success_rate: Option<f64>,
+}
+
+fn default_retry_budget_ratio() -> f64 {
+    0.2
+}
+fn default_retry_budget_window_secs() -> u64 {
+    60
+}
+fn default_retry_budget_min_retries() -> usize {
+    10
+}
+
+fn default_canary_min_requests() -> usize {
+    50
+}
+fn default_canary_min_success_rate() -> f64 {
+    0.95
+}
+
+/// Signal weights for the heuristic complexity scorer.
+///
+/// All weights default to 1.0 (equal weighting). Parsed in Phase 16 but
+/// consumed by the scorer in Phase 17.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComplexityWeightsConfig {
+    #[serde(default = "default_weight")]
+    pub context_length: f64,
+    #[serde(default = "default_weight")]
+    pub code_blocks: f64,
+    #[serde(default = "default_weight")]
+    pub multi_file: f64,
+    #[serde(default = "default_weight")]
+    pub reasoning_keywords: f64,
+    #[serde(default = "default_weight")]
+    pub conversation_depth: f64,
+    /// Additional keywords that boost the reasoning signal (user-extensible).
+    #[serde(default)]
+    pub extra_keywords: Vec<String>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Default for ComplexityWeightsConfig {
+    fn default() -> Self {
         Self {
             context_length: default_weight(),
             code_blocks: default_weight(),
@@ -257,6 +1392,13 @@ impl Default for ComplexityWeightsConfig {
     }
 }
 
+/// Whether `sats` is a whole number of millisats, allowing for float noise
+/// in decimal literals (0.1 + 0.2 and the like).
+fn is_whole_msats(sats: f64) -> bool {
+    let msats = sats * 1000.0;
+    (msats - msats.round()).abs() < 1e-6
+}
+
 /// Provider configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ProviderConfig {
@@ -269,15 +1411,21 @@ pub struct ProviderConfig {
     /// Models supported by this provider
     #[serde(default)]
     pub models: Vec<String>,
-    /// Input token rate in sats per 1000 tokens
+    /// Input token rate in sats per 1000 tokens, to millisat precision
+    /// (e.g. 0.15)
     #[serde(default)]
-    pub input_rate: u64,
-    /// Output token rate in sats per 1000 tokens
+    pub input_rate: f64,
+    /// Output token rate in sats per 1000 tokens, to millisat precision
     #[serde(default)]
-    pub output_rate: u64,
-    /// Base fee per request in sats
+    pub output_rate: f64,
+    /// Base fee per request in sats, to millisat precision
     #[serde(default)]
-    pub base_fee: u64,
+    pub base_fee: f64,
+    /// Least the provider bills for a request, in sats: a request whose
+    /// token cost and base fee come to less is charged this instead.
+    /// 0 (the default) for no minimum.
+    #[serde(default)]
+    pub min_charge_sats: f64,
     /// Provider tier for complexity-based routing (local/standard/frontier).
     /// Default: standard (backward compatible).
     #[serde(default)]
@@ -287,6 +1435,203 @@ pub struct ProviderConfig {
     /// If discovery fails, falls back to the static list (or empty).
     #[serde(default)]
     pub auto_discover: bool,
+    /// When true, the provider is new and only receives `canary_percent` of
+    /// eligible traffic until promoted (see `[routing.canary]`).
+    #[serde(default)]
+    pub canary: bool,
+    /// Share of eligible requests (1-100) sent to a canary provider. Default: 5.
+    #[serde(default = "default_canary_percent")]
+    pub canary_percent: u8,
+    /// How `api_key` is sent upstream. Default: bearer.
+    #[serde(default)]
+    pub auth_scheme: AuthScheme,
+    /// Additional headers sent with every upstream request (organization
+    /// IDs, routing hints). Values support `${VAR}` expansion.
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    /// Dedicated connection pool settings. When absent the provider shares
+    /// the default HTTP client.
+    #[serde(default)]
+    pub pool: Option<PoolConfig>,
+    /// Static hostname -> IP overrides for this provider's connections,
+    /// bypassing DNS (e.g. pinning a Routstr node). The URL's port is kept.
+    #[serde(default)]
+    pub resolve: BTreeMap<String, IpAddr>,
+    /// Retry backoff for this provider, overriding policy and global settings.
+    #[serde(default)]
+    pub backoff: Option<BackoffConfig>,
+    /// Starting balance in sats for a prepaid provider (Cashu or credits).
+    /// When set, spend is tracked in a ledger and the provider is skipped
+    /// once its remaining balance can't cover a request.
+    #[serde(default)]
+    pub balance_sats: Option<f64>,
+    /// Renew `balance_sats` on a schedule instead of spending it down once.
+    #[serde(default)]
+    pub balance_reset: Option<BalanceResetConfig>,
+    /// Jurisdiction the provider operates in (e.g. "eu"), matched against
+    /// a policy's `allowed_regions`.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Requests per minute the provider allows. Routing prefers other
+    /// providers once the last minute's requests reach this quota.
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Tokens (input + output) per minute the provider allows. Routing
+    /// prefers other providers once a request's estimated tokens would
+    /// exceed what is left of this quota.
+    #[serde(default)]
+    pub tokens_per_minute: Option<u64>,
+    /// Whether the provider honours `response_format` JSON modes. Requests
+    /// asking for `json_object` or `json_schema` only route to providers
+    /// with this set.
+    #[serde(default)]
+    pub structured_output: bool,
+    /// When false the provider is kept in config but never routed to.
+    /// Can be changed at runtime via `PUT /admin/providers/{name}/maintenance`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// The provider is left out of routing until this time (RFC 3339),
+    /// e.g. for an announced upstream maintenance window.
+    #[serde(default)]
+    pub maintenance_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Latency and success objectives, tracked per `[routing.slo]`.
+    #[serde(default)]
+    pub slo: Option<ProviderSloConfig>,
+    /// Rewrite this provider's streamed chunks into OpenAI's shape: role on
+    /// the first delta, standard finish reasons, no vendor fields or events.
+    #[serde(default)]
+    pub normalize_stream: bool,
+}
+
+#[cfg(test)]
+impl ProviderConfig {
+    /// A provider at `http://localhost` with no models, zero rates, and
+    /// every other setting at its default, for tests to override with
+    /// struct update syntax.
+    pub(crate) fn for_test(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: "http://localhost".to_string(),
+            api_key: None,
+            models: vec![],
+            input_rate: 0.0,
+            output_rate: 0.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
+            tier: Tier::default(),
+            auto_discover: false,
+            canary: false,
+            canary_percent: default_canary_percent(),
+            auth_scheme: AuthScheme::default(),
+            extra_headers: BTreeMap::new(),
+            pool: None,
+            resolve: BTreeMap::new(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
+        }
+    }
+}
+
+/// When a provider's `balance_sats` budget renews. Periods are in UTC, so
+/// daylight saving changes never move a reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceSchedule {
+    /// Every day at 00:00 UTC.
+    Daily,
+    /// On the 1st of every month at 00:00 UTC.
+    Monthly,
+    /// Continuously: spend over the last `window_hours` counts.
+    Rolling,
+}
+
+/// Recurring budget for a `balance_sats` provider
+/// (`[providers.balance_reset]`).
+///
+/// Each period starts over at `balance_sats`; top-ups count toward the
+/// period (or window) they are made in.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BalanceResetConfig {
+    pub schedule: BalanceSchedule,
+    /// Rolling window length in hours (`rolling` only, default 24).
+    #[serde(default)]
+    pub window_hours: Option<u64>,
+    /// Carry the previous period's unused balance into the next one
+    /// (`daily`/`monthly` only). Carried sats don't carry again.
+    #[serde(default)]
+    pub rollover: bool,
+    /// Most sats carried over into a period.
+    #[serde(default)]
+    pub max_rollover_sats: Option<f64>,
+}
+
+impl BalanceResetConfig {
+    /// Rolling window length.
+    pub fn window(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.window_hours.unwrap_or(24) as i64)
+    }
+}
+
+/// Per-provider HTTP connection pool tuning.
+///
+/// Unset fields keep reqwest's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Maximum idle connections kept open to the provider.
+    pub max_idle_per_host: Option<usize>,
+    /// Seconds an idle connection is kept before closing.
+    pub idle_timeout_secs: Option<u64>,
+    /// Speak HTTP/2 without ALPN negotiation (provider must support h2c or h2).
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+    /// Interval between HTTP/2 keep-alive pings, in seconds.
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    /// Seconds to wait for a keep-alive ping acknowledgement before closing.
+    pub http2_keep_alive_timeout_secs: Option<u64>,
+    /// TCP keep-alive interval in seconds.
+    pub tcp_keepalive_secs: Option<u64>,
+}
+
+fn default_canary_percent() -> u8 {
+    5
+}
+
+/// How a provider's API key is attached to upstream requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>` (OpenAI-compatible).
+    #[default]
+    Bearer,
+    /// `x-api-key: <key>` (Anthropic-style).
+    XApiKey,
+    /// `api-key: <key>` (Azure OpenAI-style).
+    ApiKey,
+    /// Never send the key, even if one is configured or discovered.
+    None,
+}
+
+impl AuthScheme {
+    /// Header name and value carrying `key`, or None for [`AuthScheme::None`].
+    pub fn header(&self, key: &ApiKey) -> Option<(&'static str, String)> {
+        match self {
+            AuthScheme::Bearer => {
+                Some(("authorization", format!("Bearer {}", key.expose_secret())))
+            }
+            AuthScheme::XApiKey => Some(("x-api-key", key.expose_secret().to_string())),
+            AuthScheme::ApiKey => Some(("api-key", key.expose_secret().to_string())),
+            AuthScheme::None => None,
+        }
+    }
 }
 
 /// Policies configuration.
@@ -312,14 +1657,207 @@ pub struct PolicyRule {
     /// Allowed models for this policy
     #[serde(default)]
     pub allowed_models: Vec<String>,
-    /// Routing strategy: "lowest_cost", "lowest_latency", "round_robin"
+    /// Routing strategy: "lowest_cost", "lowest_latency", "round_robin",
+    /// or "race_first_token" to stream from the top two candidates at once
     #[serde(default = "default_strategy")]
     pub strategy: String,
     /// Maximum cost in sats per 1000 output tokens
-    pub max_sats_per_1k_output: Option<u64>,
+    pub max_sats_per_1k_output: Option<f64>,
     /// Keywords for heuristic matching
     #[serde(default)]
     pub keywords: Vec<String>,
+    /// Retry backoff for requests under this policy, overriding the global setting.
+    #[serde(default)]
+    pub backoff: Option<BackoffConfig>,
+    /// Window in which the policy routes normally, as "HH:MM-HH:MM"
+    /// (wraps past midnight when start > end). Absent = all day.
+    #[serde(default)]
+    pub active_hours: Option<String>,
+    /// Days the policy routes normally ("mon".."sun"). Empty = every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Offset `active_hours` and `days` are evaluated in, e.g. "+02:00".
+    /// Default: UTC.
+    #[serde(default)]
+    pub utc_offset: Option<String>,
+    /// Highest tier reachable under this policy outside its schedule.
+    /// Default: local.
+    #[serde(default = "default_off_hours_tier")]
+    pub off_hours_tier: Tier,
+    /// Regions providers must be tagged with to serve this policy
+    /// (case-insensitive). Empty = any provider, tagged or not.
+    #[serde(default)]
+    pub allowed_regions: Vec<String>,
+    /// Checks applied to non-streaming responses under this policy. A
+    /// response that fails them is re-sent once to a higher-tier provider.
+    #[serde(default)]
+    pub validate: Option<ResponseValidationConfig>,
+    /// Upper bound on `max_tokens` for requests under this policy. Larger
+    /// client values are clamped; omitted ones are set to it unless
+    /// `default_max_tokens` is given.
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    /// `max_tokens` injected when the client omits it.
+    #[serde(default)]
+    pub default_max_tokens: Option<u32>,
+    /// Text put ahead of the conversation as system prompt, e.g. a
+    /// compliance preamble. May use `{policy}`, `{model}`, `{date}` and
+    /// `{request_id}`; `{{` and `}}` are literal braces.
+    #[serde(default)]
+    pub system_prompt_prepend: Option<String>,
+    /// Token that lets a trusted client skip `system_prompt_prepend` by
+    /// sending it in `X-Arbstr-System-Prompt-Bypass`. Absent = no bypass.
+    #[serde(default)]
+    pub system_prompt_bypass_token: Option<String>,
+    /// Trim long conversations to fit the models' context window.
+    #[serde(default)]
+    pub trim: Option<TrimConfig>,
+    /// Smallest estimated prompt (all messages, in tokens) this policy is
+    /// picked for automatically. With either prompt bound, the policy
+    /// matches requests without an `X-Arbstr-Policy` header whose prompt
+    /// falls within the bounds (and that match `keywords`, if given); the
+    /// first matching rule wins.
+    #[serde(default)]
+    pub min_prompt_tokens: Option<u32>,
+    /// Largest estimated prompt this policy is picked for automatically.
+    #[serde(default)]
+    pub max_prompt_tokens: Option<u32>,
+    /// Provider tier this policy routes to, whatever the complexity score:
+    /// only providers at this tier serve it.
+    #[serde(default)]
+    pub tier: Option<Tier>,
+    /// Stream from the provider but answer non-streaming requests with one
+    /// JSON response, as with `X-Arbstr-Accumulate: true`.
+    #[serde(default)]
+    pub accumulate: bool,
+    /// Compress long prompts before forwarding them.
+    #[serde(default)]
+    pub compress: Option<PromptCompressionConfig>,
+    /// Static headers added to chat completion responses under this policy,
+    /// for gateways and CDNs to branch on (e.g. `x-team = "search"`).
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    /// `x-arbstr-*` response headers left off responses under this policy.
+    /// `x-arbstr-request-id` is always sent.
+    #[serde(default)]
+    pub omit_headers: Vec<String>,
+    /// Append sampled prompt/response pairs to a JSONL dataset file.
+    #[serde(default)]
+    pub dataset_capture: Option<DatasetCaptureConfig>,
+}
+
+/// How a conversation that outgrows the context window is shortened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimStrategy {
+    /// Drop the oldest non-system messages.
+    #[default]
+    DropOldest,
+    /// Replace the oldest non-system messages with a summary written by
+    /// `summary_model`, dropping them if that fails.
+    Summarize,
+}
+
+/// Conversation trimming for a policy (`[policies.rules.trim]`).
+///
+/// When a request's estimated prompt plus its `max_tokens` (or a 256 token
+/// allowance) exceeds `context_tokens`, the oldest non-system messages are
+/// removed until it fits. System messages and the latest message are kept.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrimConfig {
+    /// Context window of the models under this policy, in tokens.
+    pub context_tokens: u32,
+    /// Default: drop_oldest.
+    #[serde(default)]
+    pub strategy: TrimStrategy,
+    /// Model asked to summarize trimmed messages (`strategy = "summarize"`),
+    /// typically a cheap one.
+    #[serde(default)]
+    pub summary_model: Option<String>,
+}
+
+/// Prompt compression for a policy (`[policies.rules.compress]`).
+///
+/// Prompts estimated at `min_prompt_tokens` or more are compressed before
+/// forwarding: runs of whitespace are collapsed, paragraphs repeated from
+/// earlier in the conversation are replaced with a marker, and with
+/// `model` set the longest earlier message is rewritten by that model.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PromptCompressionConfig {
+    /// Default: 1024.
+    #[serde(default = "default_compress_min_prompt_tokens")]
+    pub min_prompt_tokens: u32,
+    /// Default: true.
+    #[serde(default = "default_true")]
+    pub whitespace: bool,
+    /// Default: true.
+    #[serde(default = "default_true")]
+    pub dedupe: bool,
+    /// Model asked to compress the longest earlier message, typically a
+    /// cheap one.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_compress_min_prompt_tokens() -> u32 {
+    1024
+}
+
+/// Dataset capture for a policy (`[policies.rules.dataset_capture]`).
+///
+/// A sample of successful non-streaming requests is appended to `path` as
+/// JSONL, one prompt/response pair per line with provider, cost, and
+/// latency, for building evaluation and fine-tuning datasets. The file is
+/// rotated to `<path>.1`, `<path>.2`, ... once it reaches `max_file_bytes`.
+/// Not allowed with `[privacy] strip_prompts`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DatasetCaptureConfig {
+    /// JSONL file pairs are appended to.
+    pub path: String,
+    /// Fraction of successful requests captured, 0.0-1.0. Default: 1.0.
+    #[serde(default = "default_dataset_sample_rate")]
+    pub sample_rate: f64,
+    /// Size at which the file is rotated. Default: 100 MiB.
+    #[serde(default = "default_dataset_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Rotated files kept; older ones are deleted. Default: 5.
+    #[serde(default = "default_dataset_keep")]
+    pub keep: usize,
+    /// Redact email addresses and API keys in message and reply text, and
+    /// drop message `name`s. Default: true.
+    #[serde(default = "default_true")]
+    pub sanitize: bool,
+}
+
+fn default_dataset_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_dataset_max_file_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_dataset_keep() -> usize {
+    5
+}
+
+/// Response checks for a policy (`[policies.rules.validate]`), applied to
+/// the first choice's message content.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResponseValidationConfig {
+    /// Content must parse as JSON.
+    #[serde(default)]
+    pub json: bool,
+    /// Minimum content length in characters.
+    #[serde(default)]
+    pub min_length: Option<usize>,
+    /// Regex the content must match somewhere.
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+fn default_off_hours_tier() -> Tier {
+    Tier::Local
 }
 
 /// Vault treasury service configuration.
@@ -351,15 +1889,367 @@ fn default_pending_threshold() -> u32 {
     100
 }
 
-/// Logging configuration.
-#[derive(Debug, Clone, Deserialize)]
-pub struct LoggingConfig {
-    /// Log level
-    #[serde(default = "default_log_level")]
-    pub level: String,
-    /// Whether to log requests to database
-    #[serde(default = "default_true")]
-    pub log_requests: bool,
+/// How often scheduled reports are sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSchedule {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+impl std::fmt::Display for ReportSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportSchedule::Daily => write!(f, "daily"),
+            ReportSchedule::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+/// Scheduled cost and reliability report configuration.
+///
+/// When present, a background task renders a summary of the previous day
+/// or week and delivers it to a webhook and/or via SMTP.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportsConfig {
+    /// Report cadence: "daily" or "weekly". Default: daily.
+    #[serde(default)]
+    pub schedule: ReportSchedule,
+    /// Hour of day (UTC, 0-23) at which the report is sent. Default: 8.
+    #[serde(default = "default_report_hour")]
+    pub hour_utc: u32,
+    /// Day of week for weekly reports (e.g. "mon"). Default: Monday.
+    #[serde(default = "default_report_weekday")]
+    pub weekday: chrono::Weekday,
+    /// URL that receives the report as a JSON POST.
+    pub webhook_url: Option<String>,
+    /// SMTP delivery settings.
+    pub smtp: Option<SmtpConfig>,
+}
+
+fn default_report_hour() -> u32 {
+    8
+}
+
+fn default_report_weekday() -> chrono::Weekday {
+    chrono::Weekday::Mon
+}
+
+/// SMTP delivery settings for scheduled reports.
+///
+/// Speaks plain SMTP without TLS or authentication, so it is intended for
+/// a local relay (e.g. postfix on localhost or a sidecar container).
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    /// SMTP relay host.
+    pub host: String,
+    /// SMTP relay port. Default: 25.
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// Envelope and header sender address.
+    pub from: String,
+    /// Recipient addresses.
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+/// Scheduled model evaluation (`[evaluation]`).
+///
+/// Once a day a background task sends every prompt in the suite to each
+/// provider/model pair, scores the responses, stores them in the
+/// `evaluations` table, and updates each pair's quality score.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvaluationConfig {
+    /// Hour of day (UTC, 0-23) at which the suite runs. Default: 3.
+    #[serde(default = "default_evaluation_hour")]
+    pub hour_utc: u32,
+    /// `max_tokens` sent with every prompt. Default: 64.
+    #[serde(default = "default_evaluation_max_tokens")]
+    pub max_tokens: u32,
+    /// Per-request timeout. Default: 30.
+    #[serde(default = "default_evaluation_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Providers whose latest score for the requested model is below this
+    /// (0.0-1.0) are tried after the others. Unevaluated pairs are not
+    /// affected. Default: none.
+    pub min_quality_score: Option<f64>,
+    /// The prompt suite.
+    pub prompts: Vec<EvaluationPrompt>,
+}
+
+fn default_evaluation_hour() -> u32 {
+    3
+}
+
+fn default_evaluation_max_tokens() -> u32 {
+    64
+}
+
+fn default_evaluation_timeout_secs() -> u64 {
+    30
+}
+
+/// One prompt in the evaluation suite, with optional checks on the reply.
+///
+/// A prompt without checks passes whenever the provider answers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvaluationPrompt {
+    pub name: String,
+    /// Sent as a single user message.
+    pub prompt: String,
+    /// The reply must equal this, ignoring surrounding whitespace.
+    pub expect: Option<String>,
+    /// The reply must match this regex.
+    pub pattern: Option<String>,
+}
+
+/// Arbitrage opportunity detection (`[arbitrage]`).
+///
+/// `GET /v1/arbitrage` always uses these thresholds; `enabled` also runs
+/// the analysis in the background and alerts on new opportunities.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArbitrageConfig {
+    /// Run the analysis periodically and alert. Default: false.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between background analyses. Default: 3600.
+    #[serde(default = "default_arbitrage_interval_secs")]
+    pub interval_secs: u64,
+    /// Hours of recent traffic to analyse. Default: 24.
+    #[serde(default = "default_arbitrage_window_hours")]
+    pub window_hours: u64,
+    /// Projected savings per day (sats) at which a model is reported as an
+    /// opportunity. Default: 100.
+    #[serde(default = "default_arbitrage_min_savings")]
+    pub min_savings_sats_per_day: f64,
+    /// URL that receives a JSON POST for each new opportunity.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for ArbitrageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_arbitrage_interval_secs(),
+            window_hours: default_arbitrage_window_hours(),
+            min_savings_sats_per_day: default_arbitrage_min_savings(),
+            webhook_url: None,
+        }
+    }
+}
+
+/// Longest `[arbitrage] window_hours` (30 days).
+pub const MAX_ARBITRAGE_WINDOW_HOURS: u64 = 720;
+
+fn default_arbitrage_interval_secs() -> u64 {
+    3600
+}
+
+fn default_arbitrage_window_hours() -> u64 {
+    24
+}
+
+fn default_arbitrage_min_savings() -> f64 {
+    100.0
+}
+
+/// Nostr announcements (`[nostr]`).
+///
+/// arbstr publishes its model catalogue (cheapest rates per model) as a
+/// NIP-89 handler event signed with `secret_key`, so Routstr clients can
+/// find it on the relays.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NostrConfig {
+    /// Secret key as 64 hex characters. `${VAR}` references are expanded.
+    pub secret_key: ApiKey,
+    /// Relay websocket URLs (`wss://...`).
+    pub relays: Vec<String>,
+    /// Base URL clients use to reach this proxy (e.g. `https://arbstr.example.com/v1`).
+    pub public_url: String,
+    /// Display name in the announcement. Default: "arbstr".
+    #[serde(default = "default_nostr_name")]
+    pub name: String,
+    /// Free-text description in the announcement.
+    pub about: Option<String>,
+    /// Publish announcements. Set false to only use the key for relay
+    /// discovery. Default: true.
+    #[serde(default = "default_true")]
+    pub announce: bool,
+    /// Seconds between announcements. Default: 3600.
+    #[serde(default = "default_nostr_interval_secs")]
+    pub interval_secs: u64,
+}
+
+/// Signed request receipts (`[receipts]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReceiptsConfig {
+    /// Instance key signing receipts, as 64 hex characters. `${VAR}`
+    /// references are expanded. Falls back to `[nostr] secret_key`, then to
+    /// a random key generated at startup, with a warning (receipts then
+    /// only verify against the public key of that process).
+    pub secret_key: Option<ApiKey>,
+}
+
+/// Prompt archive for offline duplicate analysis (`[archive]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArchiveConfig {
+    /// Store each request's messages, Brotli-compressed, in the
+    /// `prompt_archive` table for `arbstr analyze duplicates`. Off by
+    /// default: the archive holds full prompt text, so it cannot be
+    /// enabled with `[privacy] strip_prompts`.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Streaming content filter (`[content_filter]`).
+///
+/// Delta text of streamed replies is scanned as it arrives; on a match the
+/// stream is ended with a `content_policy_violation` error event and the
+/// request logged with the rule that matched.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContentFilterConfig {
+    /// Terms blocked wherever they appear, case-insensitively.
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+    /// Regular expressions blocked on a match.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// Cost anomaly detection (`[anomaly]`).
+///
+/// Every `interval_secs` the spend in the last `bucket_minutes` is compared,
+/// per model, provider, and tenant, with the previous `baseline_buckets`
+/// buckets. Spend above the baseline mean by more than `z_threshold`
+/// standard deviations (and at least `min_cost_sats`) is an anomaly: it is
+/// logged, POSTed to `webhook_url`, and listed in `/v1/stats` while active.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnomalyConfig {
+    /// Seconds between checks. Default: 300.
+    #[serde(default = "default_anomaly_interval_secs")]
+    pub interval_secs: u64,
+    /// Width of a spend bucket in minutes; must divide a day. Default: 60.
+    #[serde(default = "default_anomaly_bucket_minutes")]
+    pub bucket_minutes: u64,
+    /// Buckets before the current one that form the baseline. Default: 24.
+    #[serde(default = "default_anomaly_baseline_buckets")]
+    pub baseline_buckets: u64,
+    /// Standard deviations above the baseline mean that flag a spike.
+    /// Default: 3.0.
+    #[serde(default = "default_anomaly_z_threshold")]
+    pub z_threshold: f64,
+    /// Smallest bucket spend (sats) that can be flagged. Default: 100.
+    #[serde(default = "default_anomaly_min_cost_sats")]
+    pub min_cost_sats: f64,
+    /// Request tag key that names the tenant (e.g. `team`); tenants are
+    /// not checked without one.
+    pub tenant_tag: Option<String>,
+    /// URL that receives a JSON POST when an anomaly starts or ends.
+    pub webhook_url: Option<String>,
+}
+
+fn default_anomaly_interval_secs() -> u64 {
+    300
+}
+
+fn default_anomaly_bucket_minutes() -> u64 {
+    60
+}
+
+fn default_anomaly_baseline_buckets() -> u64 {
+    24
+}
+
+fn default_anomaly_z_threshold() -> f64 {
+    3.0
+}
+
+fn default_anomaly_min_cost_sats() -> f64 {
+    100.0
+}
+
+/// Whether `key` is a usable secret key: 64 hex characters, or a `${VAR}`
+/// reference not yet expanded (`parse_str`).
+fn valid_secret_key(key: &str) -> bool {
+    key.contains("${") || (key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn default_nostr_name() -> String {
+    "arbstr".to_string()
+}
+
+fn default_nostr_interval_secs() -> u64 {
+    3600
+}
+
+/// Logging configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    /// Log level
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Whether to log requests to database
+    #[serde(default = "default_true")]
+    pub log_requests: bool,
+    /// What happens when a completed request's record can't be saved.
+    #[serde(default)]
+    pub mode: LoggingMode,
+    /// Strict mode: how long a request waits for its record to be written
+    /// before it is rejected.
+    #[serde(default = "default_strict_timeout_ms")]
+    pub strict_timeout_ms: u64,
+    /// Which requests get full tracing and prompt archiving.
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+}
+
+/// Request trace sampling (`[logging.sampling]`).
+///
+/// Unsampled requests log only warnings and errors, and their prompts are
+/// archived only when they fail. Failed requests are always logged.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SamplingConfig {
+    /// Share of requests (0.0-1.0) traced in full. Default: 1.0.
+    #[serde(default = "default_sampling_success_rate")]
+    pub success_rate: f64,
+    /// Whether `x-arbstr-debug: true` forces a request to be sampled.
+    /// Default: true.
+    #[serde(default = "default_true")]
+    pub debug_header: bool,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            success_rate: default_sampling_success_rate(),
+            debug_header: true,
+        }
+    }
+}
+
+fn default_sampling_success_rate() -> f64 {
+    1.0
+}
+
+/// Failure semantics for request logging (`logging.mode`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoggingMode {
+    /// Records are queued without waiting; a failed write is logged and
+    /// the request still succeeds.
+    #[default]
+    BestEffort,
+    /// Each completed request waits for its billing record to be written,
+    /// and is rejected with 503 when that fails.
+    Strict,
+}
+
+fn default_strict_timeout_ms() -> u64 {
+    2000
 }
 
 fn default_log_level() -> String {
@@ -375,6 +2265,9 @@ impl Default for LoggingConfig {
         Self {
             level: default_log_level(),
             log_requests: true,
+            mode: LoggingMode::default(),
+            strict_timeout_ms: default_strict_timeout_ms(),
+            sampling: SamplingConfig::default(),
         }
     }
 }
@@ -424,6 +2317,59 @@ impl Config {
             tracing::warn!("No providers configured - proxy will reject all requests");
         }
 
+        if self.server.listen.is_empty() {
+            return Err(ConfigError::Validation(
+                "server.listen needs at least one address".to_string(),
+            ));
+        }
+        let addrs = self
+            .server
+            .listen_addrs()
+            .map_err(|e| ConfigError::Validation(format!("server.listen {}", e)))?;
+        for (i, addr) in addrs.iter().enumerate() {
+            if addrs[..i].contains(addr) {
+                return Err(ConfigError::Validation(format!(
+                    "server.listen has '{}' twice",
+                    addr
+                )));
+            }
+        }
+        let analytics_addrs = self
+            .server
+            .analytics_listen_addrs()
+            .map_err(|e| ConfigError::Validation(format!("server.analytics_listen {}", e)))?;
+        for (i, addr) in analytics_addrs.iter().enumerate() {
+            if analytics_addrs[..i].contains(addr) || addrs.contains(addr) {
+                return Err(ConfigError::Validation(format!(
+                    "server.analytics_listen has '{}' more than once across listen and analytics_listen",
+                    addr
+                )));
+            }
+        }
+        if let Some(cors) = &self.server.cors {
+            cors.validate()
+                .map_err(|e| ConfigError::Validation(format!("server.cors {}", e)))?;
+        }
+        if self.server.admin_token.is_some() && self.server.admin_token == self.server.auth_token {
+            return Err(ConfigError::Validation(
+                "server.admin_token must differ from server.auth_token".to_string(),
+            ));
+        }
+        if self.server.sse_keepalive_secs == Some(0) {
+            return Err(ConfigError::Validation(
+                "server.sse_keepalive_secs must be greater than 0".to_string(),
+            ));
+        }
+        let limits = &self.server.limits;
+        if limits.max_body_bytes == Some(0)
+            || limits.request_timeout_secs == Some(0)
+            || limits.max_concurrent_requests == Some(0)
+        {
+            return Err(ConfigError::Validation(
+                "server.limits values must be greater than 0".to_string(),
+            ));
+        }
+
         for provider in &self.providers {
             if provider.url.is_empty() {
                 return Err(ConfigError::Validation(format!(
@@ -431,294 +2377,2530 @@ impl Config {
                     provider.name
                 )));
             }
+            for (name, value) in &provider.extra_headers {
+                if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                    || reqwest::header::HeaderValue::from_str(value).is_err()
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}' has invalid extra header '{}'",
+                        provider.name, name
+                    )));
+                }
+            }
+            if provider.canary && !(1..=100).contains(&provider.canary_percent) {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}' canary_percent must be 1-100, got {}",
+                    provider.name, provider.canary_percent
+                )));
+            }
+            for (field, value) in [
+                ("input_rate", provider.input_rate),
+                ("output_rate", provider.output_rate),
+                ("base_fee", provider.base_fee),
+                ("min_charge_sats", provider.min_charge_sats),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}' {} must be a non-negative number, got {}",
+                        provider.name, field, value
+                    )));
+                }
+                if !is_whole_msats(value) {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}' {} must be a whole number of millisats (at most 3 decimal places), got {}",
+                        provider.name, field, value
+                    )));
+                }
+            }
+            if let Some(balance) = provider.balance_sats {
+                if !balance.is_finite() || balance < 0.0 {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}' balance_sats must be a non-negative number, got {}",
+                        provider.name, balance
+                    )));
+                }
+            }
+            if let Some(reset) = &provider.balance_reset {
+                let invalid = |msg: &str| {
+                    Err(ConfigError::Validation(format!(
+                        "Provider '{}' balance_reset {}",
+                        provider.name, msg
+                    )))
+                };
+                if provider.balance_sats.is_none() {
+                    return invalid("requires balance_sats");
+                }
+                let rolling = reset.schedule == BalanceSchedule::Rolling;
+                if reset.window_hours.is_some() && !rolling {
+                    return invalid("window_hours only applies to the rolling schedule");
+                }
+                if reset.window_hours == Some(0) {
+                    return invalid("window_hours must be at least 1");
+                }
+                if reset.rollover && rolling {
+                    return invalid("rollover does not apply to the rolling schedule");
+                }
+                if let Some(max) = reset.max_rollover_sats {
+                    if !reset.rollover {
+                        return invalid("max_rollover_sats requires rollover = true");
+                    }
+                    if !max.is_finite() || max < 0.0 {
+                        return invalid("max_rollover_sats must be a non-negative number");
+                    }
+                }
+            }
+            if provider
+                .region
+                .as_ref()
+                .is_some_and(|r| r.trim().is_empty())
+            {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}' region must not be empty",
+                    provider.name
+                )));
+            }
+            if provider.requests_per_minute == Some(0) || provider.tokens_per_minute == Some(0) {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}' requests_per_minute and tokens_per_minute must be greater than 0",
+                    provider.name
+                )));
+            }
+            if let Some(host) = provider.resolve.keys().find(|h| h.trim().is_empty()) {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}' has invalid resolve hostname '{}'",
+                    provider.name, host
+                )));
+            }
+            if let Some(pool) = &provider.pool {
+                let durations = [
+                    ("idle_timeout_secs", pool.idle_timeout_secs),
+                    (
+                        "http2_keep_alive_interval_secs",
+                        pool.http2_keep_alive_interval_secs,
+                    ),
+                    (
+                        "http2_keep_alive_timeout_secs",
+                        pool.http2_keep_alive_timeout_secs,
+                    ),
+                    ("tcp_keepalive_secs", pool.tcp_keepalive_secs),
+                ];
+                if let Some((field, _)) = durations.iter().find(|(_, v)| *v == Some(0)) {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}' pool.{} must be at least 1",
+                        provider.name, field
+                    )));
+                }
+            }
         }
 
-        Ok(())
-    }
+        if self.dns.resolver == DnsResolverKind::Doh && !self.dns.doh_url.starts_with("https://") {
+            return Err(ConfigError::Validation(format!(
+                "dns.doh_url must be an https:// URL, got '{}'",
+                self.dns.doh_url
+            )));
+        }
 
-    /// Get database config with defaults.
-    pub fn database(&self) -> DatabaseConfig {
-        self.database.clone().unwrap_or_default()
-    }
-}
+        if self.chaos.enabled {
+            let rates = [
+                ("error_rate", self.chaos.error_rate),
+                ("drop_stream_rate", self.chaos.drop_stream_rate),
+                ("malformed_sse_rate", self.chaos.malformed_sse_rate),
+            ];
+            if let Some((field, rate)) = rates.iter().find(|(_, r)| !(0.0..=1.0).contains(r)) {
+                return Err(ConfigError::Validation(format!(
+                    "chaos.{} must be 0.0-1.0, got {}",
+                    field, rate
+                )));
+            }
+            if !(500..=599).contains(&self.chaos.error_status) {
+                return Err(ConfigError::Validation(format!(
+                    "chaos.error_status must be a 5xx status, got {}",
+                    self.chaos.error_status
+                )));
+            }
+            if let Some(name) = self
+                .chaos
+                .providers
+                .iter()
+                .find(|n| !self.providers.iter().any(|p| &p.name == *n))
+            {
+                return Err(ConfigError::Validation(format!(
+                    "chaos.providers names unknown provider '{}'",
+                    name
+                )));
+            }
+        }
 
-/// Configuration errors.
-#[derive(Debug, thiserror::Error)]
-pub enum ConfigError {
-    #[error("Failed to read config file '{path}': {source}")]
-    Io {
-        path: String,
-        #[source]
-        source: std::io::Error,
-    },
+        if self.warmup.enabled && self.warmup.timeout_ms == 0 {
+            return Err(ConfigError::Validation(
+                "warmup.timeout_ms must be greater than 0".to_string(),
+            ));
+        }
 
-    #[error("Failed to parse config: {0}")]
-    Parse(#[from] toml::de::Error),
+        if self.stats.rollups && self.stats.rollup_interval_secs == 0 {
+            return Err(ConfigError::Validation(
+                "stats.rollup_interval_secs must be greater than 0".to_string(),
+            ));
+        }
 
-    #[error("Configuration validation error: {0}")]
-    Validation(String),
+        if self.privacy.correlation_retention_days == Some(0) {
+            return Err(ConfigError::Validation(
+                "privacy.correlation_retention_days must be greater than 0".to_string(),
+            ));
+        }
+        if self.privacy.salt.is_some() && self.privacy.mode != ClientIdMode::Hash {
+            return Err(ConfigError::Validation(
+                "privacy.salt is only used with mode = \"hash\"".to_string(),
+            ));
+        }
+        if self.privacy.strip_prompts && self.archive.enabled {
+            return Err(ConfigError::Validation(
+                "archive.enabled stores prompts and cannot be used with privacy.strip_prompts"
+                    .to_string(),
+            ));
+        }
+        if self.privacy.strip_prompts
+            && matches!(&self.recording, Some(r) if r.mode == RecordingMode::Record)
+        {
+            return Err(ConfigError::Validation(
+                "recording mode = \"record\" stores prompts and replies and cannot be used with privacy.strip_prompts"
+                    .to_string(),
+            ));
+        }
 
-    #[error("Environment variable '{var}' not set for provider '{provider}': {message}")]
-    EnvVar {
-        var: String,
-        provider: String,
-        message: String,
-    },
-}
+        if let Some(currency) = &self.currency {
+            if currency.code.len() != 3 || !currency.code.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(ConfigError::Validation(format!(
+                    "currency.code must be a 3-letter ISO 4217 code, got '{}'",
+                    currency.code
+                )));
+            }
+            if currency.btc_price.is_none() && currency.price_url.is_none() {
+                return Err(ConfigError::Validation(
+                    "currency needs btc_price or price_url".to_string(),
+                ));
+            }
+            if let Some(price) = currency.btc_price {
+                if !price.is_finite() || price <= 0.0 {
+                    return Err(ConfigError::Validation(format!(
+                        "currency.btc_price must be a positive number, got {}",
+                        price
+                    )));
+                }
+            }
+            if currency.price_url.is_some() && currency.refresh_secs == 0 {
+                return Err(ConfigError::Validation(
+                    "currency.refresh_secs must be greater than 0".to_string(),
+                ));
+            }
+        }
 
-/// Raw provider config deserialized directly from TOML.
-/// api_key is `Option<String>` so it may contain `${VAR}` references not yet expanded.
-#[derive(Deserialize)]
-pub struct RawProviderConfig {
-    name: String,
-    url: String,
-    api_key: Option<String>,
-    #[serde(default)]
-    models: Vec<String>,
-    #[serde(default)]
-    input_rate: u64,
-    #[serde(default)]
-    output_rate: u64,
-    #[serde(default)]
-    base_fee: u64,
-    #[serde(default)]
-    tier: Tier,
-    #[serde(default)]
-    auto_discover: bool,
-}
+        if let Some(db) = &self.database {
+            if db.backend == StorageBackend::Memory && db.max_rows == 0 {
+                return Err(ConfigError::Validation(
+                    "database.max_rows must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if let Some(backup) = self.database.as_ref().and_then(|d| d.backup.as_ref()) {
+            if backup.dir.is_empty() {
+                return Err(ConfigError::Validation(
+                    "database.backup.dir must not be empty".to_string(),
+                ));
+            }
+            if backup.keep == 0 {
+                return Err(ConfigError::Validation(
+                    "database.backup.keep must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if self.logging.mode == LoggingMode::Strict && self.logging.strict_timeout_ms == 0 {
+            return Err(ConfigError::Validation(
+                "logging.strict_timeout_ms must be > 0".to_string(),
+            ));
+        }
+
+        for rule in &self.policies.rules {
+            crate::router::Schedule::from_policy(rule)
+                .map_err(|e| ConfigError::Validation(format!("Policy '{}': {}", rule.name, e)))?;
+            crate::router::ResponseValidator::from_policy(rule)
+                .map_err(|e| ConfigError::Validation(format!("Policy '{}': {}", rule.name, e)))?;
+            crate::router::SystemPrompt::from_policy(rule)
+                .map_err(|e| ConfigError::Validation(format!("Policy '{}': {}", rule.name, e)))?;
+            if rule.allowed_regions.iter().any(|r| r.trim().is_empty()) {
+                return Err(ConfigError::Validation(format!(
+                    "Policy '{}' has an empty entry in allowed_regions",
+                    rule.name
+                )));
+            }
+            if rule.max_output_tokens == Some(0) || rule.default_max_tokens == Some(0) {
+                return Err(ConfigError::Validation(format!(
+                    "Policy '{}': max_output_tokens and default_max_tokens must be > 0",
+                    rule.name
+                )));
+            }
+            if let Some(trim) = &rule.trim {
+                if trim.context_tokens == 0 {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': trim.context_tokens must be > 0",
+                        rule.name
+                    )));
+                }
+                if trim.strategy == TrimStrategy::Summarize && trim.summary_model.is_none() {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': trim.strategy = \"summarize\" needs trim.summary_model",
+                        rule.name
+                    )));
+                }
+            }
+            if let Some(compress) = &rule.compress {
+                if compress
+                    .model
+                    .as_deref()
+                    .is_some_and(|m| m.trim().is_empty())
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': compress.model must not be empty",
+                        rule.name
+                    )));
+                }
+            }
+            if let Some(capture) = &rule.dataset_capture {
+                if capture.path.trim().is_empty() {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': dataset_capture.path must not be empty",
+                        rule.name
+                    )));
+                }
+                if !(0.0..=1.0).contains(&capture.sample_rate) {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': dataset_capture.sample_rate must be 0.0-1.0, got {}",
+                        rule.name, capture.sample_rate
+                    )));
+                }
+                if capture.max_file_bytes == 0 {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': dataset_capture.max_file_bytes must be > 0",
+                        rule.name
+                    )));
+                }
+                if self.privacy.strip_prompts {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': dataset_capture stores prompts and cannot be used with privacy.strip_prompts",
+                        rule.name
+                    )));
+                }
+            }
+            for (name, value) in &rule.response_headers {
+                let lower = name.to_ascii_lowercase();
+                if reqwest::header::HeaderName::from_bytes(lower.as_bytes()).is_err()
+                    || reqwest::header::HeaderValue::from_str(value).is_err()
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}' has invalid response header '{}'",
+                        rule.name, name
+                    )));
+                }
+                if NEVER_FORWARDED_HEADERS.contains(&lower.as_str())
+                    || lower.starts_with("x-arbstr-")
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': response header '{}' cannot be set",
+                        rule.name, name
+                    )));
+                }
+            }
+            for name in &rule.omit_headers {
+                let lower = name.to_ascii_lowercase();
+                if !lower.starts_with("x-arbstr-") || lower == "x-arbstr-request-id" {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': omit_headers entry '{}' must be an x-arbstr-* header other than x-arbstr-request-id",
+                        rule.name, name
+                    )));
+                }
+            }
+            if rule.max_prompt_tokens == Some(0) {
+                return Err(ConfigError::Validation(format!(
+                    "Policy '{}': max_prompt_tokens must be > 0",
+                    rule.name
+                )));
+            }
+            if let (Some(min), Some(max)) = (rule.min_prompt_tokens, rule.max_prompt_tokens) {
+                if min > max {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': min_prompt_tokens ({}) exceeds max_prompt_tokens ({})",
+                        rule.name, min, max
+                    )));
+                }
+            }
+            if let (Some(default), Some(max)) = (rule.default_max_tokens, rule.max_output_tokens) {
+                if default > max {
+                    return Err(ConfigError::Validation(format!(
+                        "Policy '{}': default_max_tokens ({}) exceeds max_output_tokens ({})",
+                        rule.name, default, max
+                    )));
+                }
+            }
+        }
+
+        let backoffs = std::iter::once(("routing.backoff".to_string(), &self.routing.backoff))
+            .chain(self.providers.iter().filter_map(|p| {
+                p.backoff
+                    .as_ref()
+                    .map(|b| (format!("Provider '{}' backoff", p.name), b))
+            }))
+            .chain(self.policies.rules.iter().filter_map(|r| {
+                r.backoff
+                    .as_ref()
+                    .map(|b| (format!("Policy '{}' backoff", r.name), b))
+            }));
+        for (label, backoff) in backoffs {
+            if backoff.multiplier.is_nan() || backoff.multiplier < 1.0 {
+                return Err(ConfigError::Validation(format!(
+                    "{}: multiplier must be >= 1.0, got {}",
+                    label, backoff.multiplier
+                )));
+            }
+            if backoff.max_ms < backoff.base_ms {
+                return Err(ConfigError::Validation(format!(
+                    "{}: max_ms ({}) must be >= base_ms ({})",
+                    label, backoff.max_ms, backoff.base_ms
+                )));
+            }
+        }
+
+        if let Some(ref budget) = self.routing.retry_budget {
+            if !(0.0..=1.0).contains(&budget.ratio) {
+                return Err(ConfigError::Validation(format!(
+                    "routing.retry_budget.ratio must be 0.0-1.0, got {}",
+                    budget.ratio
+                )));
+            }
+            if budget.window_secs == 0 {
+                return Err(ConfigError::Validation(
+                    "routing.retry_budget.window_secs must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if self.routing.auth_quarantine.retry_after_secs == 0 {
+            return Err(ConfigError::Validation(
+                "routing.auth_quarantine.retry_after_secs must be at least 1".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.logging.sampling.success_rate) {
+            return Err(ConfigError::Validation(format!(
+                "logging.sampling.success_rate must be 0.0-1.0, got {}",
+                self.logging.sampling.success_rate
+            )));
+        }
+
+        let slo = &self.routing.slo;
+        if slo.window_secs == 0 || slo.alert_window_secs == 0 || slo.min_requests == 0 {
+            return Err(ConfigError::Validation(
+                "routing.slo window_secs, alert_window_secs and min_requests must be at least 1"
+                    .to_string(),
+            ));
+        }
+        if slo.alert_window_secs > slo.window_secs {
+            return Err(ConfigError::Validation(format!(
+                "routing.slo.alert_window_secs ({}) must not exceed window_secs ({})",
+                slo.alert_window_secs, slo.window_secs
+            )));
+        }
+        if slo.burn_rate_threshold.is_nan() || slo.burn_rate_threshold <= 0.0 {
+            return Err(ConfigError::Validation(format!(
+                "routing.slo.burn_rate_threshold must be positive, got {}",
+                slo.burn_rate_threshold
+            )));
+        }
+        for provider in &self.providers {
+            let Some(ref slo) = provider.slo else {
+                continue;
+            };
+            if slo.p95_latency_ms.is_none() && slo.success_rate.is_none() {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}': slo needs p95_latency_ms and/or success_rate",
+                    provider.name
+                )));
+            }
+            if slo.p95_latency_ms == Some(0) {
+                return Err(ConfigError::Validation(format!(
+                    "Provider '{}': slo.p95_latency_ms must be at least 1",
+                    provider.name
+                )));
+            }
+            if let Some(rate) = slo.success_rate {
+                if !(rate > 0.0 && rate < 1.0) {
+                    return Err(ConfigError::Validation(format!(
+                        "Provider '{}': slo.success_rate must be between 0.0 and 1.0 (exclusive), got {}",
+                        provider.name, rate
+                    )));
+                }
+            }
+        }
+
+        let breaker = &self.routing.circuit_breaker;
+        if breaker.failure_threshold == 0 {
+            return Err(ConfigError::Validation(
+                "routing.circuit_breaker.failure_threshold must be at least 1".to_string(),
+            ));
+        }
+        for (error_type, threshold) in &breaker.thresholds {
+            if !CIRCUIT_ERROR_TYPES.contains(&error_type.as_str()) {
+                return Err(ConfigError::Validation(format!(
+                    "routing.circuit_breaker.thresholds: unknown error type '{}' (expected one of: {})",
+                    error_type,
+                    CIRCUIT_ERROR_TYPES.join(", ")
+                )));
+            }
+            if *threshold == 0 {
+                return Err(ConfigError::Validation(format!(
+                    "routing.circuit_breaker.thresholds.{} must be at least 1",
+                    error_type
+                )));
+            }
+        }
+
+        if let Some(ref storm) = self.routing.circuit_breaker.trip_storm {
+            if storm.max_trips == 0 || storm.window_secs == 0 || storm.cooldown_secs == 0 {
+                return Err(ConfigError::Validation(
+                    "routing.circuit_breaker.trip_storm values must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        let mut tie_breakers = HashSet::new();
+        for tie_breaker in &self.routing.tie_breakers {
+            if !tie_breakers.insert(tie_breaker) {
+                return Err(ConfigError::Validation(format!(
+                    "routing.tie_breakers lists '{}' more than once",
+                    tie_breaker.as_str()
+                )));
+            }
+        }
+
+        if let Some(ref reputation) = self.routing.reputation {
+            if reputation.window == 0 || reputation.min_requests > reputation.window {
+                return Err(ConfigError::Validation(format!(
+                    "routing.reputation.min_requests ({}) must be between 1 and window ({})",
+                    reputation.min_requests, reputation.window
+                )));
+            }
+            if !(0.0..=1.0).contains(&reputation.max_error_rate) {
+                return Err(ConfigError::Validation(format!(
+                    "routing.reputation.max_error_rate must be 0.0-1.0, got {}",
+                    reputation.max_error_rate
+                )));
+            }
+            if reputation.cost_multiplier.is_nan() || reputation.cost_multiplier < 1.0 {
+                return Err(ConfigError::Validation(format!(
+                    "routing.reputation.cost_multiplier must be >= 1.0, got {}",
+                    reputation.cost_multiplier
+                )));
+            }
+        }
+
+        for (direction, names) in [
+            ("forward_request", &self.headers.forward_request),
+            ("forward_response", &self.headers.forward_response),
+        ] {
+            for name in names {
+                let lower = name.to_ascii_lowercase();
+                if reqwest::header::HeaderName::from_bytes(lower.as_bytes()).is_err() {
+                    return Err(ConfigError::Validation(format!(
+                        "headers.{} has invalid header name '{}'",
+                        direction, name
+                    )));
+                }
+                if NEVER_FORWARDED_HEADERS.contains(&lower.as_str())
+                    || lower.starts_with("x-arbstr-")
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "headers.{}: '{}' cannot be forwarded",
+                        direction, name
+                    )));
+                }
+            }
+        }
+
+        let canary = &self.routing.canary;
+        if canary.min_requests == 0 {
+            return Err(ConfigError::Validation(
+                "routing.canary.min_requests must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&canary.min_success_rate) {
+            return Err(ConfigError::Validation(format!(
+                "routing.canary.min_success_rate must be 0.0-1.0, got {}",
+                canary.min_success_rate
+            )));
+        }
+
+        if let Some(ref reports) = self.reports {
+            if reports.hour_utc > 23 {
+                return Err(ConfigError::Validation(format!(
+                    "reports.hour_utc must be 0-23, got {}",
+                    reports.hour_utc
+                )));
+            }
+            if reports.webhook_url.is_none() && reports.smtp.is_none() {
+                return Err(ConfigError::Validation(
+                    "[reports] requires webhook_url and/or [reports.smtp]".to_string(),
+                ));
+            }
+            if let Some(ref smtp) = reports.smtp {
+                if smtp.to.is_empty() {
+                    return Err(ConfigError::Validation(
+                        "reports.smtp.to must list at least one recipient".to_string(),
+                    ));
+                }
+            }
+        }
+
+        let arbitrage = &self.arbitrage;
+        if arbitrage.interval_secs == 0 {
+            return Err(ConfigError::Validation(
+                "arbitrage.interval_secs must be at least 1".to_string(),
+            ));
+        }
+        if !(1..=MAX_ARBITRAGE_WINDOW_HOURS).contains(&arbitrage.window_hours) {
+            return Err(ConfigError::Validation(format!(
+                "arbitrage.window_hours must be 1-{}, got {}",
+                MAX_ARBITRAGE_WINDOW_HOURS, arbitrage.window_hours
+            )));
+        }
+        if !arbitrage.min_savings_sats_per_day.is_finite()
+            || arbitrage.min_savings_sats_per_day < 0.0
+        {
+            return Err(ConfigError::Validation(
+                "arbitrage.min_savings_sats_per_day must be a non-negative number".to_string(),
+            ));
+        }
+
+        if let Some(ref nostr) = self.nostr {
+            if !valid_secret_key(nostr.secret_key.expose_secret()) {
+                return Err(ConfigError::Validation(
+                    "nostr.secret_key must be 64 hex characters".to_string(),
+                ));
+            }
+            if nostr.relays.is_empty() {
+                return Err(ConfigError::Validation(
+                    "nostr.relays must list at least one relay".to_string(),
+                ));
+            }
+            if let Some(relay) = nostr
+                .relays
+                .iter()
+                .find(|r| !r.starts_with("wss://") && !r.starts_with("ws://"))
+            {
+                return Err(ConfigError::Validation(format!(
+                    "nostr relay '{}' must be a ws:// or wss:// URL",
+                    relay
+                )));
+            }
+            if nostr.interval_secs < 60 {
+                return Err(ConfigError::Validation(format!(
+                    "nostr.interval_secs must be at least 60, got {}",
+                    nostr.interval_secs
+                )));
+            }
+        }
+
+        if let Some(ref key) = self.receipts.secret_key {
+            if !valid_secret_key(key.expose_secret()) {
+                return Err(ConfigError::Validation(
+                    "receipts.secret_key must be 64 hex characters".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref filter) = self.content_filter {
+            if filter.blocklist.is_empty() && filter.patterns.is_empty() {
+                return Err(ConfigError::Validation(
+                    "[content_filter] needs a blocklist or patterns".to_string(),
+                ));
+            }
+            if filter.blocklist.iter().any(|term| term.trim().is_empty()) {
+                return Err(ConfigError::Validation(
+                    "content_filter.blocklist entries must not be empty".to_string(),
+                ));
+            }
+            for pattern in &filter.patterns {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    return Err(ConfigError::Validation(format!(
+                        "content_filter pattern '{}' is invalid: {}",
+                        pattern, e
+                    )));
+                }
+            }
+        }
+
+        if let Some(ref anomaly) = self.anomaly {
+            if anomaly.interval_secs == 0 {
+                return Err(ConfigError::Validation(
+                    "anomaly.interval_secs must be at least 1".to_string(),
+                ));
+            }
+            if anomaly.bucket_minutes == 0 || 1440 % anomaly.bucket_minutes != 0 {
+                return Err(ConfigError::Validation(format!(
+                    "anomaly.bucket_minutes must divide a day (1440), got {}",
+                    anomaly.bucket_minutes
+                )));
+            }
+            if anomaly.baseline_buckets < 2 {
+                return Err(ConfigError::Validation(format!(
+                    "anomaly.baseline_buckets must be at least 2, got {}",
+                    anomaly.baseline_buckets
+                )));
+            }
+            if !anomaly.z_threshold.is_finite() || anomaly.z_threshold <= 0.0 {
+                return Err(ConfigError::Validation(
+                    "anomaly.z_threshold must be a positive number".to_string(),
+                ));
+            }
+            if !anomaly.min_cost_sats.is_finite() || anomaly.min_cost_sats < 0.0 {
+                return Err(ConfigError::Validation(
+                    "anomaly.min_cost_sats must be a non-negative number".to_string(),
+                ));
+            }
+            if anomaly
+                .tenant_tag
+                .as_deref()
+                .is_some_and(|tag| tag.trim().is_empty())
+            {
+                return Err(ConfigError::Validation(
+                    "anomaly.tenant_tag must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref evaluation) = self.evaluation {
+            if evaluation.hour_utc > 23 {
+                return Err(ConfigError::Validation(format!(
+                    "evaluation.hour_utc must be 0-23, got {}",
+                    evaluation.hour_utc
+                )));
+            }
+            if let Some(min) = evaluation.min_quality_score {
+                if !(0.0..=1.0).contains(&min) {
+                    return Err(ConfigError::Validation(format!(
+                        "evaluation.min_quality_score must be 0.0-1.0, got {}",
+                        min
+                    )));
+                }
+            }
+            if evaluation.prompts.is_empty() {
+                return Err(ConfigError::Validation(
+                    "[evaluation] requires at least one [[evaluation.prompts]]".to_string(),
+                ));
+            }
+            for prompt in &evaluation.prompts {
+                if let Some(ref pattern) = prompt.pattern {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        return Err(ConfigError::Validation(format!(
+                            "evaluation prompt '{}': invalid pattern: {}",
+                            prompt.name, e
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get database config with defaults.
+    pub fn database(&self) -> DatabaseConfig {
+        self.database.clone().unwrap_or_default()
+    }
+
+    /// Whether request history is kept only in memory and lost on restart.
+    pub fn ephemeral_storage(&self) -> bool {
+        self.database
+            .as_ref()
+            .is_some_and(|d| d.backend == StorageBackend::Memory)
+    }
+}
+
+/// Configuration errors.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse config: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Configuration validation error: {0}")]
+    Validation(String),
+
+    #[error("Environment variable '{var}' not set for provider '{provider}': {message}")]
+    EnvVar {
+        var: String,
+        provider: String,
+        message: String,
+    },
+}
+
+/// Raw provider config deserialized directly from TOML.
+/// api_key is `Option<String>` so it may contain `${VAR}` references not yet expanded.
+#[derive(Deserialize)]
+pub struct RawProviderConfig {
+    name: String,
+    url: String,
+    api_key: Option<String>,
+    #[serde(default)]
+    models: Vec<String>,
+    #[serde(default)]
+    input_rate: f64,
+    #[serde(default)]
+    output_rate: f64,
+    #[serde(default)]
+    base_fee: f64,
+    #[serde(default)]
+    min_charge_sats: f64,
+    #[serde(default)]
+    tier: Tier,
+    #[serde(default)]
+    auto_discover: bool,
+    #[serde(default)]
+    canary: bool,
+    #[serde(default = "default_canary_percent")]
+    canary_percent: u8,
+    #[serde(default)]
+    auth_scheme: AuthScheme,
+    #[serde(default)]
+    extra_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pool: Option<PoolConfig>,
+    #[serde(default)]
+    resolve: BTreeMap<String, IpAddr>,
+    #[serde(default)]
+    backoff: Option<BackoffConfig>,
+    #[serde(default)]
+    balance_sats: Option<f64>,
+    #[serde(default)]
+    balance_reset: Option<BalanceResetConfig>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    requests_per_minute: Option<u32>,
+    #[serde(default)]
+    tokens_per_minute: Option<u64>,
+    #[serde(default)]
+    structured_output: bool,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    maintenance_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    slo: Option<ProviderSloConfig>,
+    #[serde(default)]
+    normalize_stream: bool,
+}
+
+/// Raw configuration deserialized directly from TOML.
+/// Provider api_key values may contain `${VAR}` references not yet expanded.
+#[derive(Deserialize)]
+pub struct RawConfig {
+    server: ServerConfig,
+    database: Option<DatabaseConfig>,
+    vault: Option<VaultConfig>,
+    #[serde(default)]
+    providers: Vec<RawProviderConfig>,
+    #[serde(default)]
+    policies: PoliciesConfig,
+    #[serde(default)]
+    logging: LoggingConfig,
+    #[serde(default)]
+    routing: RoutingConfig,
+    reports: Option<ReportsConfig>,
+    #[serde(default)]
+    headers: HeadersConfig,
+    #[serde(default)]
+    dns: DnsConfig,
+    #[serde(default)]
+    chaos: ChaosConfig,
+    #[serde(default)]
+    warmup: WarmupConfig,
+    #[serde(default)]
+    stats: StatsConfig,
+    currency: Option<CurrencyConfig>,
+    #[serde(default)]
+    privacy: PrivacyConfig,
+    #[serde(default)]
+    recording: Option<RecordingConfig>,
+    #[serde(default)]
+    evaluation: Option<EvaluationConfig>,
+    #[serde(default)]
+    arbitrage: ArbitrageConfig,
+    #[serde(default)]
+    nostr: Option<NostrConfig>,
+    #[serde(default)]
+    receipts: ReceiptsConfig,
+    #[serde(default)]
+    archive: ArchiveConfig,
+    content_filter: Option<ContentFilterConfig>,
+    anomaly: Option<AnomalyConfig>,
+}
+
+/// Expand all `${VAR}` references in a string using a custom lookup function.
+///
+/// The closure-based design makes this testable without touching global env state.
+/// Supports multiple `${VAR}` in one string (e.g., `${SCHEME}://${HOST}/v1`).
+/// Fails on first missing variable, unclosed `${`, or empty variable name.
+fn expand_env_vars_with<F>(
+    input: &str,
+    provider_name: &str,
+    lookup: F,
+) -> Result<String, ConfigError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if !input.contains("${") {
+        return Ok(input.to_string());
+    }
+
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let end = after.find('}').ok_or_else(|| ConfigError::EnvVar {
+            var: "<unclosed>".to_string(),
+            provider: provider_name.to_string(),
+            message: format!("Unclosed '${{' in config value: {}", input),
+        })?;
+
+        let var_name = &after[..end];
+        if var_name.is_empty() {
+            return Err(ConfigError::EnvVar {
+                var: "".to_string(),
+                provider: provider_name.to_string(),
+                message: "Empty variable name in '${}' reference".to_string(),
+            });
+        }
+
+        let value = lookup(var_name).ok_or_else(|| ConfigError::EnvVar {
+            var: var_name.to_string(),
+            provider: provider_name.to_string(),
+            message: format!(
+                "Environment variable '{}' is not set (referenced in provider '{}')",
+                var_name, provider_name
+            ),
+        })?;
+
+        result.push_str(&value);
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Derive the convention-based env var name for a provider.
+///
+/// Transforms provider name to `ARBSTR_<UPPER_SNAKE_NAME>_API_KEY`:
+/// - "alpha" -> "ARBSTR_ALPHA_API_KEY"
+/// - "provider-beta" -> "ARBSTR_PROVIDER_BETA_API_KEY"
+/// - "my_service" -> "ARBSTR_MY_SERVICE_API_KEY"
+pub fn convention_env_var_name(provider_name: &str) -> String {
+    let upper_snake = provider_name.to_uppercase().replace(['-', ' '], "_");
+    format!("ARBSTR_{}_API_KEY", upper_snake)
+}
+
+impl Config {
+    /// Convert raw (deserialized) config to final config with env var expansion.
+    ///
+    /// Uses real environment variables via `std::env::var`.
+    pub fn from_raw(raw: RawConfig) -> Result<(Self, Vec<(String, KeySource)>), ConfigError> {
+        Self::from_raw_with_lookup(raw, |name| std::env::var(name).ok())
+    }
+
+    /// Convert raw config with a custom env var lookup function.
+    ///
+    /// For each provider:
+    /// - If `api_key` contains `${VAR}`: expand using `env_lookup`, source = `EnvExpanded`
+    /// - If `api_key` is a literal string: wrap directly, source = `Literal`
+    /// - If `api_key` is absent: try convention lookup via `env_lookup`,
+    ///   source = `Convention(var_name)` or `KeySource::None`
+    pub fn from_raw_with_lookup<F>(
+        raw: RawConfig,
+        env_lookup: F,
+    ) -> Result<(Self, Vec<(String, KeySource)>), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut providers = Vec::with_capacity(raw.providers.len());
+        let mut key_sources = Vec::with_capacity(raw.providers.len());
+
+        for rp in raw.providers {
+            let (api_key, source) = match rp.api_key {
+                Some(ref raw_key) if raw_key.contains("${") => {
+                    let expanded = expand_env_vars_with(raw_key, &rp.name, &env_lookup)?;
+                    (Some(ApiKey::from(expanded)), KeySource::EnvExpanded)
+                }
+                Some(ref raw_key) => (Some(ApiKey::from(raw_key.as_str())), KeySource::Literal),
+                None => {
+                    let var_name = convention_env_var_name(&rp.name);
+                    match env_lookup(&var_name) {
+                        Some(value) => (Some(ApiKey::from(value)), KeySource::Convention(var_name)),
+                        None => (None, KeySource::None),
+                    }
+                }
+            };
+
+            key_sources.push((rp.name.clone(), source));
+
+            let mut extra_headers = BTreeMap::new();
+            for (name, value) in rp.extra_headers {
+                let value = if value.contains("${") {
+                    expand_env_vars_with(&value, &rp.name, &env_lookup)?
+                } else {
+                    value
+                };
+                extra_headers.insert(name, value);
+            }
+
+            providers.push(ProviderConfig {
+                name: rp.name,
+                url: rp.url,
+                api_key,
+                models: rp.models,
+                input_rate: rp.input_rate,
+                output_rate: rp.output_rate,
+                base_fee: rp.base_fee,
+                min_charge_sats: rp.min_charge_sats,
+                tier: rp.tier,
+                auto_discover: rp.auto_discover,
+                canary: rp.canary,
+                canary_percent: rp.canary_percent,
+                auth_scheme: rp.auth_scheme,
+                extra_headers,
+                pool: rp.pool,
+                resolve: rp.resolve,
+                backoff: rp.backoff,
+                balance_sats: rp.balance_sats,
+                balance_reset: rp.balance_reset,
+                region: rp.region,
+                requests_per_minute: rp.requests_per_minute,
+                tokens_per_minute: rp.tokens_per_minute,
+                structured_output: rp.structured_output,
+                enabled: rp.enabled,
+                maintenance_until: rp.maintenance_until,
+                slo: rp.slo,
+                normalize_stream: rp.normalize_stream,
+            });
+        }
+
+        let nostr = match raw.nostr {
+            Some(mut nostr) => {
+                let key = nostr.secret_key.expose_secret();
+                if key.contains("${") {
+                    let expanded = expand_env_vars_with(key, "nostr", &env_lookup)?;
+                    nostr.secret_key = ApiKey::from(expanded);
+                }
+                Some(nostr)
+            }
+            None => None,
+        };
+
+        let mut receipts = raw.receipts;
+        if let Some(key) = receipts
+            .secret_key
+            .as_ref()
+            .map(|k| k.expose_secret())
+            .filter(|k| k.contains("${"))
+        {
+            let expanded = expand_env_vars_with(key, "receipts", &env_lookup)?;
+            receipts.secret_key = Some(ApiKey::from(expanded));
+        }
+
+        let config = Config {
+            server: raw.server,
+            database: raw.database,
+            vault: raw.vault,
+            providers,
+            policies: raw.policies,
+            logging: raw.logging,
+            routing: raw.routing,
+            reports: raw.reports,
+            headers: raw.headers,
+            dns: raw.dns,
+            chaos: raw.chaos,
+            warmup: raw.warmup,
+            stats: raw.stats,
+            currency: raw.currency,
+            privacy: raw.privacy,
+            recording: raw.recording,
+            evaluation: raw.evaluation,
+            arbitrage: raw.arbitrage,
+            nostr,
+            receipts,
+            archive: raw.archive,
+            content_filter: raw.content_filter,
+            anomaly: raw.anomaly,
+        };
+
+        Ok((config, key_sources))
+    }
+
+    /// Load configuration from a TOML file with environment variable expansion.
+    ///
+    /// This is the env-var-aware entry point. It:
+    /// 1. Reads the file
+    /// 2. Parses as `RawConfig` (api_key as plain String)
+    /// 3. Expands `${VAR}` references and applies convention lookup
+    /// 4. Validates the resulting config
+    ///
+    /// Returns the config and per-provider key source information.
+    pub fn from_file_with_env(
+        path: impl AsRef<Path>,
+    ) -> Result<(Self, Vec<(String, KeySource)>), ConfigError> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| ConfigError::Io {
+            path: path.as_ref().display().to_string(),
+            source: e,
+        })?;
+
+        Self::parse_str_with_env(&content)
+    }
+
+    /// Parse configuration from a TOML string with environment variable
+    /// expansion, as [`Config::from_file_with_env`] does for a file.
+    pub fn parse_str_with_env(
+        content: &str,
+    ) -> Result<(Self, Vec<(String, KeySource)>), ConfigError> {
+        let raw: RawConfig = toml::from_str(content).map_err(ConfigError::Parse)?;
+        let (config, key_sources) = Self::from_raw(raw)?;
+        config.validate()?;
+
+        Ok((config, key_sources))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_minimal_config() {
+        let toml = r#"
+            [server]
+            listen = "127.0.0.1:9000"
+        "#;
+
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.server.listen, vec!["127.0.0.1:9000"]);
+        assert!(config.providers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let toml = r#"
+            [server]
+            listen = "0.0.0.0:8080"
+
+            [database]
+            path = "./test.db"
+
+            [[providers]]
+            name = "test-provider"
+            url = "https://example.com/v1"
+            models = ["gpt-4o", "claude-3.5-sonnet"]
+            input_rate = 10
+            output_rate = 30
+            base_fee = 1
+
+            [policies]
+            default_strategy = "cheapest"
+
+            [[policies.rules]]
+            name = "code"
+            allowed_models = ["gpt-4o"]
+            strategy = "lowest_cost"
+            max_sats_per_1k_output = 50
+            keywords = ["code", "function"]
+
+            [logging]
+            level = "debug"
+            log_requests = true
+        "#;
+
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.providers.len(), 1);
+        assert_eq!(config.providers[0].name, "test-provider");
+        assert_eq!(config.providers[0].input_rate, 10.0);
+        assert_eq!(config.policies.rules.len(), 1);
+        assert_eq!(config.policies.rules[0].name, "code");
+    }
+
+    #[test]
+    fn test_parse_reports_config() {
+        let toml = r#"
+            [server]
+            listen = "127.0.0.1:9000"
+
+            [reports]
+            schedule = "weekly"
+            hour_utc = 6
+            weekday = "fri"
+            webhook_url = "https://hooks.example.com/arbstr"
+
+            [reports.smtp]
+            host = "localhost"
+            from = "arbstr@example.com"
+            to = ["finance@example.com"]
+        "#;
+
+        let config = Config::parse_str(toml).unwrap();
+        let reports = config.reports.unwrap();
+        assert_eq!(reports.schedule, ReportSchedule::Weekly);
+        assert_eq!(reports.hour_utc, 6);
+        assert_eq!(reports.weekday, chrono::Weekday::Fri);
+        assert_eq!(reports.smtp.unwrap().port, 25);
+    }
+
+    #[test]
+    fn test_reports_config_validation() {
+        let no_target = r#"
+            [server]
+            [reports]
+            schedule = "daily"
+        "#;
+        assert!(Config::parse_str(no_target).is_err());
+
+        let bad_hour = r#"
+            [server]
+            [reports]
+            hour_utc = 24
+            webhook_url = "https://hooks.example.com/arbstr"
+        "#;
+        assert!(Config::parse_str(bad_hour).is_err());
+    }
+
+    #[test]
+    fn test_parse_evaluation_config() {
+        let toml = r#"
+            [server]
+            [evaluation]
+            hour_utc = 2
+
+            [[evaluation.prompts]]
+            name = "arithmetic"
+            prompt = "What is 2+2? Reply with the number only."
+            expect = "4"
+
+            [[evaluation.prompts]]
+            name = "greeting"
+            prompt = "Say hello."
+            pattern = "(?i)hello"
+        "#;
+
+        let config = Config::parse_str(toml).unwrap();
+        let evaluation = config.evaluation.unwrap();
+        assert_eq!(evaluation.hour_utc, 2);
+        assert_eq!(evaluation.max_tokens, 64);
+        assert_eq!(evaluation.prompts.len(), 2);
+        assert_eq!(evaluation.prompts[0].expect.as_deref(), Some("4"));
+
+        let bad_pattern = r#"
+            [server]
+            [[evaluation.prompts]]
+            name = "broken"
+            prompt = "hi"
+            pattern = "("
+        "#;
+        assert!(Config::parse_str(bad_pattern).is_err());
+
+        let no_prompts = r#"
+            [server]
+            [evaluation]
+            prompts = []
+        "#;
+        assert!(Config::parse_str(no_prompts).is_err());
+    }
+
+    #[test]
+    fn test_parse_arbitrage_config() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(!config.arbitrage.enabled);
+        assert_eq!(config.arbitrage.window_hours, 24);
+
+        let toml = r#"
+            [server]
+            [arbitrage]
+            enabled = true
+            window_hours = 6
+            min_savings_sats_per_day = 50
+            webhook_url = "https://hooks.example.com/arbstr"
+        "#;
+        let arbitrage = Config::parse_str(toml).unwrap().arbitrage;
+        assert!(arbitrage.enabled);
+        assert_eq!(arbitrage.window_hours, 6);
+        assert_eq!(arbitrage.min_savings_sats_per_day, 50.0);
+
+        let bad_window = r#"
+            [server]
+            [arbitrage]
+            window_hours = 0
+        "#;
+        assert!(Config::parse_str(bad_window).is_err());
+    }
+
+    #[test]
+    fn test_parse_nostr_config() {
+        let toml = r#"
+            [server]
+            [nostr]
+            secret_key = "${NOSTR_KEY}"
+            relays = ["wss://relay.example.com"]
+            public_url = "https://arbstr.example.com/v1"
+        "#;
+        let raw: RawConfig = toml::from_str(toml).unwrap();
+        let key = "ab".repeat(32);
+        let (config, _) =
+            Config::from_raw_with_lookup(raw, |name| (name == "NOSTR_KEY").then(|| key.clone()))
+                .unwrap();
+        config.validate().unwrap();
+        let nostr = config.nostr.unwrap();
+        assert_eq!(nostr.secret_key.expose_secret(), key);
+        assert!(nostr.announce);
+        assert_eq!(nostr.interval_secs, 3600);
+        assert_eq!(nostr.name, "arbstr");
+
+        let bad_key = r#"
+            [server]
+            [nostr]
+            secret_key = "nsec1notsupported"
+            relays = ["wss://relay.example.com"]
+            public_url = "https://arbstr.example.com/v1"
+        "#;
+        assert!(Config::parse_str(bad_key).is_err());
+
+        let bad_relay = toml.replace("wss://relay.example.com", "https://relay.example.com");
+        assert!(Config::parse_str(&bad_relay).is_err());
+    }
+
+    #[test]
+    fn test_parse_reputation_config() {
+        let toml = r#"
+            [server]
+            [routing.reputation]
+            max_error_rate = 0.5
+            max_avg_latency_ms = 8000
+            action = "exclude"
+        "#;
+
+        let config = Config::parse_str(toml).unwrap();
+        let reputation = config.routing.reputation.unwrap();
+        assert_eq!(reputation.window, 50);
+        assert_eq!(reputation.min_requests, 10);
+        assert_eq!(reputation.max_avg_latency_ms, Some(8000));
+        assert_eq!(reputation.action, ReputationAction::Exclude);
+        assert_eq!(reputation.penalty_secs, 300);
+    }
+
+    #[test]
+    fn test_reputation_config_validation() {
+        let bad_min = r#"
+            [server]
+            [routing.reputation]
+            window = 5
+            min_requests = 10
+        "#;
+        assert!(Config::parse_str(bad_min).is_err());
+
+        let bad_rate = r#"
+            [server]
+            [routing.reputation]
+            max_error_rate = 1.5
+        "#;
+        assert!(Config::parse_str(bad_rate).is_err());
+
+        let bad_multiplier = r#"
+            [server]
+            [routing.reputation]
+            cost_multiplier = 0.5
+        "#;
+        assert!(Config::parse_str(bad_multiplier).is_err());
+    }
+
+    #[test]
+    fn test_parse_auth_scheme_and_extra_headers() {
+        let toml = r#"
+            [server]
+            [[providers]]
+            name = "anthropic-style"
+            url = "https://api.example.com/v1"
+            api_key = "sk-test"
+            auth_scheme = "x-api-key"
+            extra_headers = { "anthropic-version" = "2023-06-01", "x-org" = "acme" }
+
+            [[providers]]
+            name = "default"
+            url = "https://api.example.com/v1"
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        let provider = &config.providers[0];
+        assert_eq!(provider.auth_scheme, AuthScheme::XApiKey);
+        assert_eq!(provider.extra_headers["anthropic-version"], "2023-06-01");
+        assert_eq!(provider.extra_headers.len(), 2);
+        assert_eq!(config.providers[1].auth_scheme, AuthScheme::Bearer);
+        assert!(config.providers[1].extra_headers.is_empty());
+
+        let (name, value) = provider
+            .auth_scheme
+            .header(provider.api_key.as_ref().unwrap())
+            .unwrap();
+        assert_eq!((name, value.as_str()), ("x-api-key", "sk-test"));
+        assert!(AuthScheme::None.header(&ApiKey::from("k")).is_none());
+    }
+
+    #[test]
+    fn test_invalid_extra_header_rejected() {
+        let toml = r#"
+            [server]
+            [[providers]]
+            name = "bad"
+            url = "https://api.example.com/v1"
+            extra_headers = { "bad header" = "x" }
+        "#;
+        let err = Config::parse_str(toml).unwrap_err().to_string();
+        assert!(err.contains("bad header"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_pool_config() {
+        let toml = r#"
+            [server]
+            [[providers]]
+            name = "pooled"
+            url = "https://api.example.com/v1"
+            [providers.pool]
+            max_idle_per_host = 32
+            idle_timeout_secs = 90
+            http2_prior_knowledge = true
+            http2_keep_alive_interval_secs = 20
+
+            [[providers]]
+            name = "plain"
+            url = "https://other.example.com/v1"
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        let pool = config.providers[0].pool.as_ref().unwrap();
+        assert_eq!(pool.max_idle_per_host, Some(32));
+        assert_eq!(pool.idle_timeout_secs, Some(90));
+        assert!(pool.http2_prior_knowledge);
+        assert_eq!(pool.http2_keep_alive_interval_secs, Some(20));
+        assert!(pool.tcp_keepalive_secs.is_none());
+        assert!(config.providers[1].pool.is_none());
+
+        let zero = toml.replace("idle_timeout_secs = 90", "idle_timeout_secs = 0");
+        let err = Config::parse_str(&zero).unwrap_err().to_string();
+        assert!(err.contains("pool.idle_timeout_secs"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_dns_config_and_resolve() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.dns.resolver, DnsResolverKind::System);
+
+        let toml = r#"
+            [server]
+            [dns]
+            resolver = "doh"
+            doh_url = "https://1.1.1.1/dns-query"
+            [[providers]]
+            name = "pinned"
+            url = "https://node.routstr.example/v1"
+            resolve = { "node.routstr.example" = "203.0.113.7" }
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.dns.resolver, DnsResolverKind::Doh);
+        assert_eq!(
+            config.providers[0].resolve["node.routstr.example"],
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+
+        let bad_ip = toml.replace("203.0.113.7", "not-an-ip");
+        assert!(Config::parse_str(&bad_ip).is_err());
+        let plain_http = toml.replace("https://1.1.1.1", "http://1.1.1.1");
+        let err = Config::parse_str(&plain_http).unwrap_err().to_string();
+        assert!(err.contains("dns.doh_url"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_retry_budget() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config.routing.retry_budget.is_none());
+
+        let toml = r#"
+            [server]
+            [routing.retry_budget]
+            ratio = 0.1
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        let budget = config.routing.retry_budget.unwrap();
+        assert_eq!(budget.ratio, 0.1);
+        assert_eq!(budget.window_secs, 60);
+        assert_eq!(budget.min_retries, 10);
+
+        let err = Config::parse_str(&toml.replace("0.1", "1.5"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("retry_budget.ratio"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_auth_quarantine() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config.routing.auth_quarantine.enabled);
+        assert_eq!(config.routing.auth_quarantine.retry_after_secs, 600);
+
+        let toml = r#"
+            [server]
+            [routing.auth_quarantine]
+            retry_after_secs = 60
+            webhook_url = "https://hooks.example.com/arbstr"
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.routing.auth_quarantine.retry_after_secs, 60);
+        assert!(config.routing.auth_quarantine.webhook_url.is_some());
+
+        let err = Config::parse_str(&toml.replace("= 60", "= 0"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("auth_quarantine.retry_after_secs"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_logging_sampling() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.logging.sampling, SamplingConfig::default());
+
+        let toml = r#"
+            [server]
+            [logging.sampling]
+            success_rate = 0.1
+            debug_header = false
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.logging.sampling.success_rate, 0.1);
+        assert!(!config.logging.sampling.debug_header);
+
+        let err = Config::parse_str(&toml.replace("0.1", "10"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("logging.sampling.success_rate"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_provider_slo() {
+        let toml = r#"
+            [server]
+            [routing.slo]
+            alert_window_secs = 600
+            webhook_url = "https://hooks.example.com/arbstr"
+
+            [[providers]]
+            name = "alpha"
+            url = "https://alpha.example/v1"
+            slo = { p95_latency_ms = 4000, success_rate = 0.99 }
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.routing.slo.window_secs, 3600);
+        assert_eq!(config.routing.slo.alert_window_secs, 600);
+        assert_eq!(
+            config.providers[0].slo,
+            Some(ProviderSloConfig {
+                p95_latency_ms: Some(4000),
+                success_rate: Some(0.99),
+            })
+        );
+
+        let err = Config::parse_str(&toml.replace("0.99", "99.0"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("slo.success_rate"), "{}", err);
+        let err = Config::parse_str(&toml.replace("= 600", "= 7200"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("alert_window_secs"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_circuit_thresholds() {
+        let toml = r#"
+            [server]
+            [routing.circuit_breaker]
+            failure_threshold = 5
+            [routing.circuit_breaker.thresholds]
+            timeout = 2
+            connect = 1
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        let breaker = &config.routing.circuit_breaker;
+        assert_eq!(breaker.failure_threshold, 5);
+        assert_eq!(breaker.thresholds["timeout"], 2);
+        assert_eq!(breaker.thresholds["connect"], 1);
+
+        let err = Config::parse_str(&toml.replace("connect", "auth"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown error type 'auth'"), "{}", err);
+        let err = Config::parse_str(&toml.replace("= 2", "= 0"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("thresholds.timeout"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_trip_storm() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config.routing.circuit_breaker.trip_storm.is_none());
+        assert!(!config.routing.circuit_breaker.synthetic_probe);
+        assert_eq!(config.routing.circuit_breaker.failure_threshold, 3);
+        assert!(config.routing.circuit_breaker.thresholds.is_empty());
+
+        let toml = r#"
+            [server]
+            [routing.circuit_breaker.trip_storm]
+            max_trips = 3
+            webhook_url = "https://hooks.example.com/arbstr"
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        let storm = config.routing.circuit_breaker.trip_storm.unwrap();
+        assert_eq!(storm.max_trips, 3);
+        assert_eq!(storm.window_secs, 3600);
+        assert_eq!(storm.cooldown_secs, 7200);
+        assert!(storm.webhook_url.is_some());
+
+        let err = Config::parse_str(&toml.replace("= 3", "= 0"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("trip_storm"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_content_filter() {
+        let config = Config::parse_str(
+            r#"
+[server]
+[content_filter]
+blocklist = ["project zeus"]
+patterns = ['\b\d{3}-\d{2}-\d{4}\b']
+"#,
+        )
+        .unwrap();
+        let filter = config.content_filter.unwrap();
+        assert_eq!(filter.blocklist, ["project zeus"]);
+        assert_eq!(filter.patterns.len(), 1);
+        assert!(Config::parse_str("[server]")
+            .unwrap()
+            .content_filter
+            .is_none());
+
+        for bad in [
+            "[server]\n[content_filter]",
+            "[server]\n[content_filter]\nblocklist = [\" \"]",
+            "[server]\n[content_filter]\npatterns = [\"(\"]",
+        ] {
+            assert!(Config::parse_str(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_anomaly() {
+        let config = Config::parse_str(
+            r#"
+[server]
+[anomaly]
+bucket_minutes = 15
+tenant_tag = "team"
+"#,
+        )
+        .unwrap();
+        let anomaly = config.anomaly.unwrap();
+        assert_eq!(anomaly.bucket_minutes, 15);
+        assert_eq!(anomaly.baseline_buckets, 24);
+        assert_eq!(anomaly.z_threshold, 3.0);
+        assert_eq!(anomaly.tenant_tag.as_deref(), Some("team"));
+        assert!(Config::parse_str("[server]").unwrap().anomaly.is_none());
+
+        for bad in [
+            "bucket_minutes = 7",
+            "baseline_buckets = 1",
+            "z_threshold = 0.0",
+            "min_cost_sats = -1.0",
+            "tenant_tag = \" \"",
+        ] {
+            let toml = format!("[server]\n[anomaly]\n{}", bad);
+            assert!(Config::parse_str(&toml).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_balance_reset() {
+        let provider = |extra: &str| {
+            format!(
+                "[server]\n[[providers]]\nname = \"p\"\nurl = \"http://localhost\"\n{}",
+                extra
+            )
+        };
+        let config = Config::parse_str(&provider(
+            "balance_sats = 1000.0\n[providers.balance_reset]\nschedule = \"monthly\"\nrollover = true\nmax_rollover_sats = 500.0",
+        ))
+        .unwrap();
+        let reset = config.providers[0].balance_reset.clone().unwrap();
+        assert_eq!(reset.schedule, BalanceSchedule::Monthly);
+        assert!(reset.rollover);
+        assert_eq!(reset.max_rollover_sats, Some(500.0));
+
+        let config = Config::parse_str(&provider(
+            "balance_sats = 1000.0\n[providers.balance_reset]\nschedule = \"rolling\"",
+        ))
+        .unwrap();
+        let reset = config.providers[0].balance_reset.clone().unwrap();
+        assert_eq!(reset.window(), chrono::Duration::hours(24));
+
+        for bad in [
+            // No balance to renew
+            "[providers.balance_reset]\nschedule = \"daily\"",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"weekly\"",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"daily\"\nwindow_hours = 6",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"rolling\"\nwindow_hours = 0",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"rolling\"\nrollover = true",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"daily\"\nmax_rollover_sats = 5.0",
+            "balance_sats = 1.0\n[providers.balance_reset]\nschedule = \"daily\"\nrollover = true\nmax_rollover_sats = -5.0",
+        ] {
+            assert!(Config::parse_str(&provider(bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_decimal_rates() {
+        let provider = |rates: &str| {
+            format!(
+                "[server]\n[[providers]]\nname = \"p\"\nurl = \"http://localhost\"\n{}",
+                rates
+            )
+        };
+        // Integers still parse; decimals keep millisat precision
+        let config = Config::parse_str(&provider("input_rate = 10\noutput_rate = 30")).unwrap();
+        assert_eq!(config.providers[0].input_rate, 10.0);
+        let config = Config::parse_str(&provider(
+            "input_rate = 0.15\noutput_rate = 0.6\nbase_fee = 0.001",
+        ))
+        .unwrap();
+        let p = &config.providers[0];
+        assert_eq!(
+            (p.input_rate, p.output_rate, p.base_fee),
+            (0.15, 0.6, 0.001)
+        );
+
+        let config = Config::parse_str(&provider("min_charge_sats = 2.5")).unwrap();
+        assert_eq!(config.providers[0].min_charge_sats, 2.5);
+
+        for bad in [
+            "input_rate = -1",
+            "output_rate = 0.0005",
+            "base_fee = 1.2345",
+            "min_charge_sats = -1",
+            "output_rate = inf",
+        ] {
+            let err = Config::parse_str(&provider(bad)).unwrap_err().to_string();
+            assert!(err.contains("Provider 'p'"), "{}: {}", bad, err);
+        }
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(!config.archive.enabled);
+        let config = Config::parse_str("[server]\n[archive]\nenabled = true").unwrap();
+        assert!(config.archive.enabled);
+    }
+
+    #[test]
+    fn test_parse_max_fallback_providers() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.routing.max_fallback_providers, 1);
+
+        let toml = r#"
+            [server]
+            [routing]
+            max_fallback_providers = 3
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.routing.max_fallback_providers, 3);
+        assert_eq!(config.routing.route_cache_ttl_ms, 0);
+
+        let toml = "[server]\n[routing]\nroute_cache_ttl_ms = 250";
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.routing.route_cache_ttl_ms, 250);
+    }
+
+    #[test]
+    fn test_parse_tie_breakers() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config.routing.tie_breakers.is_empty());
+
+        let toml = r#"
+            [server]
+            [routing]
+            tie_breakers = ["latency", "success_rate", "random"]
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(
+            config.routing.tie_breakers,
+            vec![
+                TieBreaker::Latency,
+                TieBreaker::SuccessRate,
+                TieBreaker::Random
+            ]
+        );
+
+        for bad in [
+            r#"tie_breakers = ["cheapest"]"#,
+            r#"tie_breakers = ["latency", "latency"]"#,
+        ] {
+            let toml = format!("[server]\n[routing]\n{}", bad);
+            assert!(Config::parse_str(&toml).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_backoff() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.routing.backoff, BackoffConfig::default());
+
+        let toml = r#"
+            [server]
+            [routing.backoff]
+            base_ms = 250
+            jitter = false
+
+            [[providers]]
+            name = "alpha"
+            url = "https://alpha.test/v1"
+            backoff = { base_ms = 50, multiplier = 3.0, max_ms = 500 }
+
+            [[policies.rules]]
+            name = "fast"
+            backoff = { base_ms = 10, max_ms = 10 }
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.routing.backoff.base_ms, 250);
+        assert_eq!(config.routing.backoff.max_ms, 4000);
+        assert!(!config.routing.backoff.jitter);
+        let provider = config.providers[0].backoff.as_ref().unwrap();
+        assert_eq!(provider.multiplier, 3.0);
+        assert!(provider.jitter);
+        assert_eq!(
+            config.policies.rules[0].backoff.as_ref().unwrap().max_ms,
+            10
+        );
+
+        let err = Config::parse_str(&toml.replace("max_ms = 500", "max_ms = 5"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Provider 'alpha' backoff"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_chaos() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(!config.chaos.enabled);
+        assert_eq!(config.chaos.error_status, 503);
+
+        let toml = r#"
+            [server]
+            [chaos]
+            enabled = true
+            providers = ["alpha"]
+            error_rate = 0.2
+            drop_stream_rate = 0.1
+            latency_ms = 300
 
-/// Raw configuration deserialized directly from TOML.
-/// Provider api_key values may contain `${VAR}` references not yet expanded.
-#[derive(Deserialize)]
-pub struct RawConfig {
-    server: ServerConfig,
-    database: Option<DatabaseConfig>,
-    vault: Option<VaultConfig>,
-    #[serde(default)]
-    providers: Vec<RawProviderConfig>,
-    #[serde(default)]
-    policies: PoliciesConfig,
-    #[serde(default)]
-    logging: LoggingConfig,
-    #[serde(default)]
-    routing: RoutingConfig,
-}
+            [[providers]]
+            name = "alpha"
+            url = "https://alpha.test/v1"
+        "#;
+        let config = Config::parse_str(toml).unwrap();
+        assert_eq!(config.chaos.providers, vec!["alpha".to_string()]);
+        assert_eq!(config.chaos.error_rate, 0.2);
+        assert_eq!(config.chaos.latency_ms, 300);
+
+        let err = Config::parse_str(&toml.replace("error_rate = 0.2", "error_rate = 2.0"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("chaos.error_rate"), "{}", err);
+        let err = Config::parse_str(&toml.replace("[\"alpha\"]", "[\"gamma\"]"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown provider 'gamma'"), "{}", err);
+        let err = Config::parse_str(&toml.replace("latency_ms = 300", "error_status = 404"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("chaos.error_status"), "{}", err);
+    }
 
-/// Expand all `${VAR}` references in a string using a custom lookup function.
-///
-/// The closure-based design makes this testable without touching global env state.
-/// Supports multiple `${VAR}` in one string (e.g., `${SCHEME}://${HOST}/v1`).
-/// Fails on first missing variable, unclosed `${`, or empty variable name.
-fn expand_env_vars_with<F>(
-    input: &str,
-    provider_name: &str,
-    lookup: F,
-) -> Result<String, ConfigError>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    if !input.contains("${") {
-        return Ok(input.to_string());
+    #[test]
+    fn test_parse_warmup() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(!config.warmup.enabled);
+        assert_eq!(config.warmup.timeout_ms, 5000);
+
+        let config =
+            Config::parse_str("[server]\n[warmup]\nenabled = true\ncompletion = true").unwrap();
+        assert!(config.warmup.enabled);
+        assert!(config.warmup.completion);
+
+        let err = Config::parse_str("[server]\n[warmup]\nenabled = true\ntimeout_ms = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("warmup.timeout_ms"), "{}", err);
     }
 
-    let mut result = String::with_capacity(input.len());
-    let mut rest = input;
+    #[test]
+    fn test_parse_stats_rollups() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(!config.stats.rollups);
+        assert_eq!(config.stats.rollup_interval_secs, 300);
+        assert_eq!(config.stats.rollup_min_range_hours, 24);
+
+        let config = Config::parse_str(
+            "[server]\n[stats]\nrollups = true\nrollup_interval_secs = 60\nrollup_min_range_hours = 72",
+        )
+        .unwrap();
+        assert!(config.stats.rollups);
+        assert_eq!(config.stats.rollup_interval_secs, 60);
+        assert_eq!(config.stats.rollup_min_range_hours, 72);
+
+        let err = Config::parse_str("[server]\n[stats]\nrollups = true\nrollup_interval_secs = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("stats.rollup_interval_secs"), "{}", err);
+    }
 
-    while let Some(start) = rest.find("${") {
-        result.push_str(&rest[..start]);
-        let after = &rest[start + 2..];
+    #[test]
+    fn test_parse_currency() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config.currency.is_none());
+
+        let config =
+            Config::parse_str("[server]\n[currency]\ncode = \"USD\"\nbtc_price = 60000.0").unwrap();
+        let currency = config.currency.unwrap();
+        assert_eq!(currency.code, "USD");
+        assert_eq!(currency.btc_price, Some(60000.0));
+        assert_eq!(currency.price_pointer, "/data/amount");
+        assert_eq!(currency.refresh_secs, 300);
+
+        let err = Config::parse_str("[server]\n[currency]\ncode = \"USD\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("btc_price or price_url"), "{}", err);
+
+        let err = Config::parse_str("[server]\n[currency]\ncode = \"dollars\"\nbtc_price = 1.0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("currency.code"), "{}", err);
+    }
 
-        let end = after.find('}').ok_or_else(|| ConfigError::EnvVar {
-            var: "<unclosed>".to_string(),
-            provider: provider_name.to_string(),
-            message: format!("Unclosed '${{' in config value: {}", input),
-        })?;
+    #[test]
+    fn test_parse_regions() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[[providers]]
+name = "eu-node"
+url = "https://eu.example.com/v1"
+region = "eu"
+
+[[policies.rules]]
+name = "gdpr"
+allowed_regions = ["eu"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.providers[0].region.as_deref(), Some("eu"));
+        assert_eq!(config.policies.rules[0].allowed_regions, vec!["eu"]);
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"gdpr\"\nallowed_regions = [\" \"]",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("allowed_regions"), "{}", err);
+    }
 
-        let var_name = &after[..end];
-        if var_name.is_empty() {
-            return Err(ConfigError::EnvVar {
-                var: "".to_string(),
-                provider: provider_name.to_string(),
-                message: "Empty variable name in '${}' reference".to_string(),
-            });
-        }
+    #[test]
+    fn test_parse_logging_mode() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.logging.mode, LoggingMode::BestEffort);
+
+        let config =
+            Config::parse_str("[server]\n[logging]\nmode = \"strict\"\nstrict_timeout_ms = 500")
+                .unwrap();
+        assert_eq!(config.logging.mode, LoggingMode::Strict);
+        assert_eq!(config.logging.strict_timeout_ms, 500);
+
+        let err =
+            Config::parse_str("[server]\n[logging]\nmode = \"strict\"\nstrict_timeout_ms = 0")
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("strict_timeout_ms"), "{}", err);
+    }
 
-        let value = lookup(var_name).ok_or_else(|| ConfigError::EnvVar {
-            var: var_name.to_string(),
-            provider: provider_name.to_string(),
-            message: format!(
-                "Environment variable '{}' is not set (referenced in provider '{}')",
-                var_name, provider_name
+    #[test]
+    fn test_parse_policy_prompt_bounds() {
+        let config = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"long\"\nmin_prompt_tokens = 4001\nmax_prompt_tokens = 120000",
+        )
+        .unwrap();
+        let rule = &config.policies.rules[0];
+        assert_eq!(rule.min_prompt_tokens, Some(4001));
+        assert_eq!(rule.max_prompt_tokens, Some(120000));
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"odd\"\nmin_prompt_tokens = 5000\nmax_prompt_tokens = 4000",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("exceeds max_prompt_tokens"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_policy_response_headers() {
+        let config = Config::parse_str(
+            r#"
+[server]
+[[policies.rules]]
+name = "search"
+omit_headers = ["x-arbstr-cost-sats"]
+
+[policies.rules.response_headers]
+x-team = "search"
+"#,
+        )
+        .unwrap();
+        let rule = &config.policies.rules[0];
+        assert_eq!(rule.response_headers["x-team"], "search");
+        assert_eq!(rule.omit_headers, ["x-arbstr-cost-sats"]);
+
+        for (rule, expected) in [
+            (
+                "response_headers = { x-arbstr-team = \"a\" }",
+                "cannot be set",
             ),
-        })?;
+            ("response_headers = { set-cookie = \"a\" }", "cannot be set"),
+            (
+                "response_headers = { \"bad header\" = \"a\" }",
+                "invalid response header",
+            ),
+            ("omit_headers = [\"x-arbstr-request-id\"]", "omit_headers"),
+            ("omit_headers = [\"server\"]", "omit_headers"),
+        ] {
+            let err = Config::parse_str(&format!(
+                "[server]\n[[policies.rules]]\nname = \"p\"\n{}",
+                rule
+            ))
+            .unwrap_err()
+            .to_string();
+            assert!(err.contains(expected), "{}: {}", rule, err);
+        }
+    }
 
-        result.push_str(&value);
-        rest = &after[end + 1..];
+    #[test]
+    fn test_parse_policy_dataset_capture() {
+        let config = Config::parse_str(
+            r#"
+[server]
+[[policies.rules]]
+name = "search"
+
+[policies.rules.dataset_capture]
+path = "./datasets/search.jsonl"
+sample_rate = 0.1
+"#,
+        )
+        .unwrap();
+        let capture = config.policies.rules[0].dataset_capture.as_ref().unwrap();
+        assert_eq!(capture.path, "./datasets/search.jsonl");
+        assert_eq!(capture.sample_rate, 0.1);
+        assert_eq!(capture.max_file_bytes, 100 * 1024 * 1024);
+        assert_eq!(capture.keep, 5);
+        assert!(capture.sanitize);
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"p\"\ndataset_capture = { path = \"d.jsonl\", sample_rate = 1.5 }",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("sample_rate"), "{}", err);
+
+        let err = Config::parse_str(
+            "[server]\n[privacy]\nstrip_prompts = true\n[[policies.rules]]\nname = \"p\"\ndataset_capture = { path = \"d.jsonl\" }",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("strip_prompts"), "{}", err);
     }
 
-    result.push_str(rest);
-    Ok(result)
-}
+    #[test]
+    fn test_parse_policy_compress() {
+        let config = Config::parse_str(
+            r#"
+[server]
+[[policies.rules]]
+name = "rag"
+
+[policies.rules.compress]
+min_prompt_tokens = 4000
+model = "gpt-4o-mini"
+"#,
+        )
+        .unwrap();
+        let compress = config.policies.rules[0].compress.as_ref().unwrap();
+        assert_eq!(compress.min_prompt_tokens, 4000);
+        assert!(compress.whitespace && compress.dedupe);
+        assert_eq!(compress.model.as_deref(), Some("gpt-4o-mini"));
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"rag\"\ncompress = { model = \" \" }",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("compress.model"), "{}", err);
+    }
 
-/// Derive the convention-based env var name for a provider.
-///
-/// Transforms provider name to `ARBSTR_<UPPER_SNAKE_NAME>_API_KEY`:
-/// - "alpha" -> "ARBSTR_ALPHA_API_KEY"
-/// - "provider-beta" -> "ARBSTR_PROVIDER_BETA_API_KEY"
-/// - "my_service" -> "ARBSTR_MY_SERVICE_API_KEY"
-pub fn convention_env_var_name(provider_name: &str) -> String {
-    let upper_snake = provider_name.to_uppercase().replace(['-', ' '], "_");
-    format!("ARBSTR_{}_API_KEY", upper_snake)
-}
+    #[test]
+    fn test_parse_policy_trim() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[[policies.rules]]
+name = "chat"
+[policies.rules.trim]
+context_tokens = 8192
+strategy = "summarize"
+summary_model = "gpt-4o-mini"
+"#,
+        )
+        .unwrap();
+        let trim = config.policies.rules[0].trim.as_ref().unwrap();
+        assert_eq!(trim.context_tokens, 8192);
+        assert_eq!(trim.strategy, TrimStrategy::Summarize);
+        assert_eq!(trim.summary_model.as_deref(), Some("gpt-4o-mini"));
+
+        let config = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"chat\"\ntrim = { context_tokens = 4096 }",
+        )
+        .unwrap();
+        assert_eq!(
+            config.policies.rules[0].trim.as_ref().unwrap().strategy,
+            TrimStrategy::DropOldest
+        );
 
-impl Config {
-    /// Convert raw (deserialized) config to final config with env var expansion.
-    ///
-    /// Uses real environment variables via `std::env::var`.
-    pub fn from_raw(raw: RawConfig) -> Result<(Self, Vec<(String, KeySource)>), ConfigError> {
-        Self::from_raw_with_lookup(raw, |name| std::env::var(name).ok())
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"chat\"\ntrim = { context_tokens = 4096, strategy = \"summarize\" }",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("summary_model"), "{}", err);
     }
 
-    /// Convert raw config with a custom env var lookup function.
-    ///
-    /// For each provider:
-    /// - If `api_key` contains `${VAR}`: expand using `env_lookup`, source = `EnvExpanded`
-    /// - If `api_key` is a literal string: wrap directly, source = `Literal`
-    /// - If `api_key` is absent: try convention lookup via `env_lookup`,
-    ///   source = `Convention(var_name)` or `KeySource::None`
-    pub fn from_raw_with_lookup<F>(
-        raw: RawConfig,
-        env_lookup: F,
-    ) -> Result<(Self, Vec<(String, KeySource)>), ConfigError>
-    where
-        F: Fn(&str) -> Option<String>,
-    {
-        let mut providers = Vec::with_capacity(raw.providers.len());
-        let mut key_sources = Vec::with_capacity(raw.providers.len());
-
-        for rp in raw.providers {
-            let (api_key, source) = match rp.api_key {
-                Some(ref raw_key) if raw_key.contains("${") => {
-                    let expanded = expand_env_vars_with(raw_key, &rp.name, &env_lookup)?;
-                    (Some(ApiKey::from(expanded)), KeySource::EnvExpanded)
-                }
-                Some(ref raw_key) => (Some(ApiKey::from(raw_key.as_str())), KeySource::Literal),
-                None => {
-                    let var_name = convention_env_var_name(&rp.name);
-                    match env_lookup(&var_name) {
-                        Some(value) => (Some(ApiKey::from(value)), KeySource::Convention(var_name)),
-                        None => (None, KeySource::None),
-                    }
-                }
-            };
+    #[test]
+    fn test_parse_policy_max_tokens() {
+        let config = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"chat\"\nmax_output_tokens = 1024\ndefault_max_tokens = 256",
+        )
+        .unwrap();
+        assert_eq!(config.policies.rules[0].max_output_tokens, Some(1024));
+        assert_eq!(config.policies.rules[0].default_max_tokens, Some(256));
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"chat\"\nmax_output_tokens = 100\ndefault_max_tokens = 200",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("exceeds max_output_tokens"), "{}", err);
+    }
 
-            key_sources.push((rp.name.clone(), source));
+    #[test]
+    fn test_parse_multiple_listen_addresses() {
+        let config = Config::parse_str(
+            r#"
+[server]
+listen = ["127.0.0.1:8080", "[::1]:8080", "unix:/run/arbstr.sock"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.server.listen_addrs().unwrap(),
+            vec![
+                ListenAddr::Tcp("127.0.0.1:8080".to_string()),
+                ListenAddr::Tcp("[::1]:8080".to_string()),
+                ListenAddr::Unix("/run/arbstr.sock".into()),
+            ]
+        );
 
-            providers.push(ProviderConfig {
-                name: rp.name,
-                url: rp.url,
-                api_key,
-                models: rp.models,
-                input_rate: rp.input_rate,
-                output_rate: rp.output_rate,
-                base_fee: rp.base_fee,
-                tier: rp.tier,
-                auto_discover: rp.auto_discover,
-            });
+        for (listen, expected) in [
+            ("[]", "at least one"),
+            (r#"["localhost"]"#, "not host:port"),
+            (r#"["unix:"]"#, "no socket path"),
+            (r#"["127.0.0.1:80", "127.0.0.1:80"]"#, "twice"),
+        ] {
+            let err = Config::parse_str(&format!("[server]\nlisten = {}", listen))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains(expected), "{}: {}", listen, err);
         }
 
-        let config = Config {
-            server: raw.server,
-            database: raw.database,
-            vault: raw.vault,
-            providers,
-            policies: raw.policies,
-            logging: raw.logging,
-            routing: raw.routing,
-        };
-
-        Ok((config, key_sources))
+        let config = Config::parse_str(
+            "[server]\nlisten = \"127.0.0.1:8080\"\nanalytics_listen = \"0.0.0.0:9090\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.server.analytics_listen_addrs().unwrap(),
+            vec![ListenAddr::Tcp("0.0.0.0:9090".to_string())]
+        );
+        let err = Config::parse_str(
+            "[server]\nlisten = \"127.0.0.1:8080\"\nanalytics_listen = [\"127.0.0.1:8080\"]",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("more than once"), "{}", err);
     }
 
-    /// Load configuration from a TOML file with environment variable expansion.
-    ///
-    /// This is the env-var-aware entry point. It:
-    /// 1. Reads the file
-    /// 2. Parses as `RawConfig` (api_key as plain String)
-    /// 3. Expands `${VAR}` references and applies convention lookup
-    /// 4. Validates the resulting config
-    ///
-    /// Returns the config and per-provider key source information.
-    pub fn from_file_with_env(
-        path: impl AsRef<Path>,
-    ) -> Result<(Self, Vec<(String, KeySource)>), ConfigError> {
-        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| ConfigError::Io {
-            path: path.as_ref().display().to_string(),
-            source: e,
-        })?;
+    #[test]
+    fn test_admin_token_must_differ_from_auth_token() {
+        let config =
+            Config::parse_str("[server]\nauth_token = \"client\"\nadmin_token = \"operator\"")
+                .unwrap();
+        assert_eq!(config.server.admin_token.as_deref(), Some("operator"));
+
+        let err = Config::parse_str("[server]\nauth_token = \"same\"\nadmin_token = \"same\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("must differ"), "{}", err);
+    }
 
-        let raw: RawConfig = toml::from_str(&content).map_err(ConfigError::Parse)?;
-        let (config, key_sources) = Self::from_raw(raw)?;
-        config.validate()?;
+    #[test]
+    fn test_parse_sse_keepalive() {
+        let config = Config::parse_str("[server]\nsse_keepalive_secs = 15").unwrap();
+        assert_eq!(config.server.sse_keepalive_secs, Some(15));
+        assert!(Config::parse_str("[server]")
+            .unwrap()
+            .server
+            .sse_keepalive_secs
+            .is_none());
+        let err = Config::parse_str("[server]\nsse_keepalive_secs = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("sse_keepalive_secs"), "{}", err);
+    }
 
-        Ok((config, key_sources))
+    #[test]
+    fn test_parse_recording() {
+        let config = Config::parse_str("[server]\n[recording]\nmode = \"replay\"").unwrap();
+        let recording = config.recording.unwrap();
+        assert_eq!(recording.mode, RecordingMode::Replay);
+        assert_eq!(recording.dir, "./recordings");
+        assert!(!recording.realtime);
+        assert!(Config::parse_str("[server]").unwrap().recording.is_none());
+        assert!(Config::parse_str("[server]\n[recording]\nmode = \"rewind\"").is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_parse_limits() {
+        let config = Config::parse_str(
+            r#"
+[server.limits]
+max_body_bytes = 1048576
+request_timeout_secs = 300
+max_concurrent_requests = 64
+"#,
+        )
+        .unwrap();
+        let limits = &config.server.limits;
+        assert_eq!(limits.max_body_bytes, Some(1_048_576));
+        assert_eq!(limits.request_timeout_secs, Some(300));
+        assert_eq!(limits.max_concurrent_requests, Some(64));
+        assert!(limits.is_enabled());
+        assert!(!Config::parse_str("[server]")
+            .unwrap()
+            .server
+            .limits
+            .is_enabled());
+
+        let err = Config::parse_str("[server.limits]\nmax_concurrent_requests = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("greater than 0"), "{}", err);
+    }
 
     #[test]
-    fn test_parse_minimal_config() {
-        let toml = r#"
-            [server]
-            listen = "127.0.0.1:9000"
-        "#;
+    fn test_parse_cors() {
+        let config = Config::parse_str(
+            r#"
+[server.cors]
+allowed_origins = ["http://localhost:3000"]
+allow_credentials = true
+max_age_secs = 600
+"#,
+        )
+        .unwrap();
+        let cors = config.server.cors.unwrap();
+        assert_eq!(cors.allowed_origins, vec!["http://localhost:3000"]);
+        assert_eq!(cors.allowed_methods, vec!["GET", "POST"]);
+        assert_eq!(cors.allowed_headers, vec!["authorization", "content-type"]);
+        assert!(cors
+            .expose_headers
+            .contains(&"x-arbstr-cost-sats".to_string()));
+        assert!(cors.allow_credentials);
+        assert_eq!(cors.max_age_secs, Some(600));
+        assert!(Config::parse_str("[server]").unwrap().server.cors.is_none());
+
+        for (section, expected) in [
+            ("allowed_origins = []", "at least one origin"),
+            ("allowed_origins = [\"localhost:3000\"]", "invalid origin"),
+            (
+                "allowed_origins = [\"*\"]\nallow_credentials = true",
+                "allow_credentials",
+            ),
+            (
+                "allowed_origins = [\"https://a.example\"]\nexpose_headers = [\"*\"]\nallow_credentials = true",
+                "expose_headers cannot contain",
+            ),
+            ("allowed_origins = [\"*\", \"https://a.example\"]", "mixes"),
+            (
+                "allowed_origins = [\"*\"]\nallowed_methods = [\"GE T\"]",
+                "invalid method",
+            ),
+        ] {
+            let err = Config::parse_str(&format!("[server.cors]\n{}", section))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains(expected), "{}: {}", section, err);
+        }
+    }
 
-        let config = Config::parse_str(toml).unwrap();
-        assert_eq!(config.server.listen, "127.0.0.1:9000");
-        assert!(config.providers.is_empty());
+    #[test]
+    fn test_parse_provider_quotas() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[[providers]]
+name = "limited"
+url = "https://limited.example.com/v1"
+requests_per_minute = 60
+tokens_per_minute = 90000
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.providers[0].requests_per_minute, Some(60));
+        assert_eq!(config.providers[0].tokens_per_minute, Some(90000));
+
+        let err = Config::parse_str(
+            "[server]\n[[providers]]\nname = \"p\"\nurl = \"http://p\"\nrequests_per_minute = 0",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("requests_per_minute"), "{}", err);
     }
 
     #[test]
-    fn test_parse_full_config() {
-        let toml = r#"
-            [server]
-            listen = "0.0.0.0:8080"
+    fn test_parse_structured_output() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[routing]
+validate_structured_output = true
+
+[[providers]]
+name = "json"
+url = "https://json.example.com/v1"
+structured_output = true
+
+[[providers]]
+name = "plain"
+url = "https://plain.example.com/v1"
+"#,
+        )
+        .unwrap();
+        assert!(config.routing.validate_structured_output);
+        assert!(config.providers[0].structured_output);
+        assert!(!config.providers[1].structured_output);
+    }
 
-            [database]
-            path = "./test.db"
+    #[test]
+    fn test_parse_provider_maintenance() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[[providers]]
+name = "off"
+url = "https://off.example.com/v1"
+enabled = false
+
+[[providers]]
+name = "window"
+url = "https://window.example.com/v1"
+maintenance_until = "2025-07-01T00:00:00Z"
+
+[[providers]]
+name = "on"
+url = "https://on.example.com/v1"
+"#,
+        )
+        .unwrap();
+        assert!(!config.providers[0].enabled);
+        assert!(config.providers[1].enabled);
+        assert_eq!(
+            config.providers[1].maintenance_until.unwrap().to_rfc3339(),
+            "2025-07-01T00:00:00+00:00"
+        );
+        assert!(config.providers[2].enabled);
+        assert!(config.providers[2].maintenance_until.is_none());
+    }
 
-            [[providers]]
-            name = "test-provider"
-            url = "https://example.com/v1"
-            models = ["gpt-4o", "claude-3.5-sonnet"]
-            input_rate = 10
-            output_rate = 30
-            base_fee = 1
+    #[test]
+    fn test_parse_response_validation() {
+        let config = Config::parse_str(
+            r#"
+[server]
+
+[[policies.rules]]
+name = "structured"
+
+[policies.rules.validate]
+json = true
+min_length = 20
+pattern = '"status"'
+"#,
+        )
+        .unwrap();
+        let validate = config.policies.rules[0].validate.as_ref().unwrap();
+        assert!(validate.json);
+        assert_eq!(validate.min_length, Some(20));
+        assert_eq!(validate.pattern.as_deref(), Some("\"status\""));
+
+        let err = Config::parse_str(
+            "[server]\n[[policies.rules]]\nname = \"s\"\n[policies.rules.validate]\npattern = \"(\"",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("validate.pattern"), "{}", err);
+    }
 
-            [policies]
-            default_strategy = "cheapest"
+    #[test]
+    fn test_parse_privacy() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.privacy.mode, ClientIdMode::Off);
+        assert!(!config.privacy.strip_prompts);
+
+        let config = Config::parse_str(
+            "[server]\n[privacy]\nmode = \"hash\"\nsalt = \"pepper\"\nstrip_prompts = true\ncorrelation_retention_days = 30",
+        )
+        .unwrap();
+        assert_eq!(config.privacy.mode, ClientIdMode::Hash);
+        assert_eq!(config.privacy.salt.unwrap().expose_secret(), "pepper");
+        assert!(config.privacy.strip_prompts);
+        assert_eq!(config.privacy.correlation_retention_days, Some(30));
+
+        let err = Config::parse_str("[server]\n[privacy]\ncorrelation_retention_days = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("correlation_retention_days"), "{}", err);
+        let err = Config::parse_str("[server]\n[privacy]\nmode = \"omit\"\nsalt = \"x\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("privacy.salt"), "{}", err);
+        let err = Config::parse_str(
+            "[server]\n[privacy]\nstrip_prompts = true\n[archive]\nenabled = true",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("archive.enabled"), "{}", err);
+        let err = Config::parse_str(
+            "[server]\n[privacy]\nstrip_prompts = true\n[recording]\nmode = \"record\"",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("recording"), "{}", err);
+        // Replaying sends nothing to disk
+        Config::parse_str(
+            "[server]\n[privacy]\nstrip_prompts = true\n[recording]\nmode = \"replay\"",
+        )
+        .unwrap();
+    }
 
-            [[policies.rules]]
-            name = "code"
-            allowed_models = ["gpt-4o"]
-            strategy = "lowest_cost"
-            max_sats_per_1k_output = 50
-            keywords = ["code", "function"]
+    #[test]
+    fn test_parse_memory_storage_backend() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert_eq!(config.database().backend, StorageBackend::Sqlite);
+
+        let config =
+            Config::parse_str("[server]\n[database]\nbackend = \"memory\"\nmax_rows = 500")
+                .unwrap();
+        let db = config.database();
+        assert_eq!(db.backend, StorageBackend::Memory);
+        assert_eq!(db.max_rows, 500);
+
+        let err = Config::parse_str("[server]\n[database]\nbackend = \"memory\"\nmax_rows = 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("database.max_rows"), "{}", err);
+    }
 
-            [logging]
-            level = "debug"
-            log_requests = true
+    #[test]
+    fn test_headers_config_defaults_and_validation() {
+        let config = Config::parse_str("[server]").unwrap();
+        assert!(config
+            .headers
+            .forward_request
+            .contains(&"openai-organization".to_string()));
+        assert!(config
+            .headers
+            .forward_response
+            .contains(&"x-ratelimit-remaining-tokens".to_string()));
+
+        let custom = r#"
+            [server]
+            [headers]
+            forward_request = ["X-Trace-Id"]
         "#;
-
-        let config = Config::parse_str(toml).unwrap();
-        assert_eq!(config.providers.len(), 1);
-        assert_eq!(config.providers[0].name, "test-provider");
-        assert_eq!(config.providers[0].input_rate, 10);
-        assert_eq!(config.policies.rules.len(), 1);
-        assert_eq!(config.policies.rules[0].name, "code");
+        let config = Config::parse_str(custom).unwrap();
+        assert_eq!(config.headers.forward_request, vec!["X-Trace-Id"]);
+        // Unset list keeps its default
+        assert!(!config.headers.forward_response.is_empty());
+
+        for bad in [
+            "Authorization",
+            "x-arbstr-provider",
+            "content-length",
+            "bad header",
+        ] {
+            let toml = format!("[server]\n[headers]\nforward_response = [\"{}\"]", bad);
+            assert!(
+                Config::parse_str(&toml).is_err(),
+                "{} should be rejected",
+                bad
+            );
+        }
     }
 
     #[test]
@@ -764,11 +4946,29 @@ mod tests {
             url: "https://example.com/v1".to_string(),
             api_key: Some(ApiKey::from("cashuABCD1234secret")),
             models: vec![],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         };
         let debug_output = format!("{:?}", config);
         assert!(
@@ -941,9 +5141,16 @@ mod tests {
     fn make_raw_config(provider_name: &str, api_key: Option<String>) -> RawConfig {
         RawConfig {
             server: ServerConfig {
-                listen: "127.0.0.1:9000".to_string(),
+                listen: vec!["127.0.0.1:9000".to_string()],
+                analytics_listen: vec![],
                 rate_limit_rps: None,
                 auth_token: None,
+                admin_token: None,
+                stream_body_threshold_bytes: None,
+                cors: None,
+                limits: Default::default(),
+                metadata_mode: Default::default(),
+                sse_keepalive_secs: None,
             },
             database: None,
             vault: None,
@@ -952,15 +5159,49 @@ mod tests {
                 url: "https://example.com/v1".to_string(),
                 api_key,
                 models: vec![],
-                input_rate: 0,
-                output_rate: 0,
-                base_fee: 0,
+                input_rate: 0.0,
+                output_rate: 0.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             }],
             policies: PoliciesConfig::default(),
             logging: LoggingConfig::default(),
             routing: RoutingConfig::default(),
+            reports: None,
+            headers: Default::default(),
+            dns: Default::default(),
+            chaos: Default::default(),
+            warmup: Default::default(),
+            stats: Default::default(),
+            currency: None,
+            privacy: Default::default(),
+            recording: None,
+            evaluation: None,
+            arbitrage: Default::default(),
+            nostr: None,
+            receipts: Default::default(),
+            archive: Default::default(),
+            content_filter: None,
+            anomaly: None,
         }
     }
 
@@ -1060,6 +5301,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_raw_expands_extra_header_values() {
+        let mut env = std::collections::HashMap::new();
+        env.insert("ORG_ID".to_string(), "org-123".to_string());
+
+        let mut raw = make_raw_config("test-headers", None);
+        raw.providers[0]
+            .extra_headers
+            .insert("x-org".to_string(), "${ORG_ID}".to_string());
+        let (config, _) = Config::from_raw_with_lookup(raw, |name| env.get(name).cloned()).unwrap();
+        assert_eq!(config.providers[0].extra_headers["x-org"], "org-123");
+
+        let mut raw = make_raw_config("test-headers", None);
+        raw.providers[0]
+            .extra_headers
+            .insert("x-org".to_string(), "${MISSING_ORG}".to_string());
+        assert!(Config::from_raw_with_lookup(raw, |_| None).is_err());
+    }
+
     // ── Masked prefix tests ──
 
     #[test]
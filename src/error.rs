@@ -1,7 +1,10 @@
 //! Error types for arbstr.
 
+use std::error::Error as _;
+
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use serde::Serialize;
 
 /// Result type alias for arbstr operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -21,6 +24,12 @@ pub enum Error {
     #[error("Provider error: {0}")]
     Provider(String),
 
+    #[error("Provider error: {message}")]
+    ProviderFailed {
+        kind: ProviderErrorKind,
+        message: String,
+    },
+
     #[error("Upstream request failed: {0}")]
     Upstream(#[from] reqwest::Error),
 
@@ -30,6 +39,15 @@ pub enum Error {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payment required: {0}")]
+    PaymentRequired(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -44,6 +62,125 @@ pub enum Error {
 
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
+
+    /// Strict logging could not save the request's billing record.
+    #[error("Request could not be recorded: {0}")]
+    Unrecorded(String),
+}
+
+/// How a request to a provider failed.
+///
+/// Each kind has its own circuit breaker `error_type`, client-facing error
+/// code, and `error_type` value in the requests table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorKind {
+    /// No response before the upstream timeout.
+    Timeout,
+    /// Connection refused, reset, or DNS failure.
+    Connect,
+    /// TLS handshake or certificate failure.
+    Tls,
+    /// Provider rejected arbstr's credentials (401/403).
+    Auth,
+    /// Provider rate limit (429).
+    RateLimited,
+    /// Provider 5xx.
+    Server,
+    /// Any other provider 4xx.
+    Client,
+    /// Response body could not be parsed.
+    Malformed,
+}
+
+impl ProviderErrorKind {
+    /// All kinds, in the order stats report them.
+    pub const ALL: [ProviderErrorKind; 8] = [
+        Self::Timeout,
+        Self::Connect,
+        Self::Tls,
+        Self::Auth,
+        Self::RateLimited,
+        Self::Server,
+        Self::Client,
+        Self::Malformed,
+    ];
+
+    /// Classify an HTTP error status from a provider.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::Auth,
+            429 => Self::RateLimited,
+            500..=599 => Self::Server,
+            _ => Self::Client,
+        }
+    }
+
+    /// Classify a transport error from reqwest.
+    pub fn from_reqwest(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return Self::Timeout;
+        }
+        if e.is_decode() || e.is_body() {
+            return Self::Malformed;
+        }
+        // reqwest has no TLS predicate; look for it in the source chain
+        let mut source = e.source();
+        while let Some(err) = source {
+            let text = err.to_string().to_lowercase();
+            if ["tls", "ssl", "certificate", "handshake"]
+                .iter()
+                .any(|needle| text.contains(needle))
+            {
+                return Self::Tls;
+            }
+            source = err.source();
+        }
+        Self::Connect
+    }
+
+    /// Circuit breaker and requests-table `error_type` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connect => "connect",
+            Self::Tls => "tls",
+            Self::Auth => "auth",
+            Self::RateLimited => "rate_limited",
+            Self::Server => "5xx",
+            Self::Client => "4xx",
+            Self::Malformed => "malformed",
+        }
+    }
+
+    /// Client-facing `error.code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Timeout => "provider_timeout",
+            Self::Connect => "provider_unreachable",
+            Self::Tls => "provider_tls_error",
+            Self::Auth => "provider_auth_failed",
+            Self::RateLimited => "provider_rate_limited",
+            Self::Server => "provider_server_error",
+            Self::Client => "provider_rejected",
+            Self::Malformed => "provider_malformed_response",
+        }
+    }
+
+    /// Status returned to the client.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Whether this failure counts against the provider's circuit breaker.
+    /// Auth, rate-limit, and other 4xx responses mean the provider is up.
+    pub fn is_circuit_failure(&self) -> bool {
+        !matches!(self, Self::Auth | Self::RateLimited | Self::Client)
+    }
 }
 
 impl IntoResponse for Error {
@@ -54,23 +191,93 @@ impl IntoResponse for Error {
             Error::NoPolicyMatch => (StatusCode::BAD_REQUEST, self.to_string()),
             Error::NoTierMatch { .. } => (StatusCode::BAD_REQUEST, self.to_string()),
             Error::Provider(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
+            Error::ProviderFailed { kind, .. } => (kind.status(), self.to_string()),
             Error::Upstream(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
             Error::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             Error::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            Error::Conflict(_) => (StatusCode::CONFLICT, self.to_string()),
+            Error::PaymentRequired(_) => (StatusCode::PAYMENT_REQUIRED, self.to_string()),
+            Error::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
             Error::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             Error::CircuitOpen { .. } => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
             Error::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            Error::Unrecorded(_) => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
         };
 
-        // Return OpenAI-compatible error format
-        let body = serde_json::json!({
-            "error": {
-                "message": message,
-                "type": "arbstr_error",
-                "code": status.as_u16()
-            }
-        });
+        // Return OpenAI-compatible error format; provider failures carry
+        // a code naming the failure mode
+        let body = match &self {
+            Error::ProviderFailed { kind, .. } => serde_json::json!({
+                "error": {
+                    "message": message,
+                    "type": "provider_error",
+                    "code": kind.code()
+                }
+            }),
+            _ => serde_json::json!({
+                "error": {
+                    "message": message,
+                    "type": "arbstr_error",
+                    "code": status.as_u16()
+                }
+            }),
+        };
 
         (status, axum::Json(body)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_provider_statuses() {
+        assert_eq!(ProviderErrorKind::from_status(401), ProviderErrorKind::Auth);
+        assert_eq!(ProviderErrorKind::from_status(403), ProviderErrorKind::Auth);
+        assert_eq!(
+            ProviderErrorKind::from_status(429),
+            ProviderErrorKind::RateLimited
+        );
+        assert_eq!(
+            ProviderErrorKind::from_status(503),
+            ProviderErrorKind::Server
+        );
+        assert_eq!(
+            ProviderErrorKind::from_status(400),
+            ProviderErrorKind::Client
+        );
+    }
+
+    #[test]
+    fn kinds_have_distinct_names_and_codes() {
+        let names: std::collections::HashSet<_> =
+            ProviderErrorKind::ALL.iter().map(|k| k.as_str()).collect();
+        let codes: std::collections::HashSet<_> =
+            ProviderErrorKind::ALL.iter().map(|k| k.code()).collect();
+        assert_eq!(names.len(), ProviderErrorKind::ALL.len());
+        assert_eq!(codes.len(), ProviderErrorKind::ALL.len());
+    }
+
+    #[test]
+    fn circuit_failures_exclude_client_side_kinds() {
+        assert!(ProviderErrorKind::Timeout.is_circuit_failure());
+        assert!(ProviderErrorKind::Tls.is_circuit_failure());
+        assert!(ProviderErrorKind::Malformed.is_circuit_failure());
+        assert!(!ProviderErrorKind::Auth.is_circuit_failure());
+        assert!(!ProviderErrorKind::RateLimited.is_circuit_failure());
+    }
+
+    #[tokio::test]
+    async fn connect_error_is_classified() {
+        let err = reqwest::Client::new()
+            .get("http://127.0.0.1:1/")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            ProviderErrorKind::from_reqwest(&err),
+            ProviderErrorKind::Connect
+        );
+    }
+}
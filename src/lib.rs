@@ -3,10 +3,17 @@
 //! This library provides the core functionality for the arbstr proxy,
 //! including configuration, routing, and provider management.
 
+pub mod analyze;
+pub mod bench;
 pub mod config;
 pub mod error;
+pub mod marketplace;
+pub mod mock_provider;
+pub mod pricing;
 pub mod proxy;
 pub mod router;
+pub mod selftest;
+pub mod status;
 pub mod storage;
 
 pub use config::Config;
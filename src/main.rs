@@ -4,11 +4,17 @@
 //! cheapest provider while respecting quality constraints.
 
 use clap::{Parser, Subcommand};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 use arbstr::config::{Config, KeySource};
+use arbstr::mock_provider::{LatencyDistribution, MockProviderConfig};
 use arbstr::proxy::run_server;
 
+/// Counts heap allocations for `arbstr bench` (`--features alloc-stats`).
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: arbstr::bench::CountingAllocator = arbstr::bench::CountingAllocator;
+
 #[derive(Parser)]
 #[command(name = "arbstr")]
 #[command(about = "Intelligent LLM routing and cost arbitrage for Routstr")]
@@ -40,10 +46,189 @@ enum Commands {
         /// Path to configuration file
         #[arg(short, long, default_value = "config.toml")]
         config: String,
+
+        /// Also compare provider rates with a reference price table; exits 1 if any look off
+        #[arg(long)]
+        pricing: bool,
+
+        /// Reference price table (URL or JSON file) instead of the bundled one
+        #[arg(long, requires = "pricing")]
+        reference: Option<String>,
     },
 
     /// Show configured providers and their rates
     Providers {
+        /// Path to configuration file
+        #[arg(short, long, default_value = "config.toml", global = true)]
+        config: String,
+
+        #[command(subcommand)]
+        command: Option<ProvidersCommands>,
+    },
+
+    /// Run a fake OpenAI-compatible provider for testing
+    MockProvider {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 9999)]
+        port: u16,
+
+        /// Address to bind
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+
+        /// Response latency: fixed (200ms), uniform range (100ms-500ms), or exponential mean (exp:200ms)
+        #[arg(long, default_value = "0ms")]
+        latency: LatencyDistribution,
+
+        /// Fraction of requests (0.0-1.0) answered with --error-status
+        #[arg(long, default_value_t = 0.0)]
+        error_rate: f64,
+
+        /// HTTP status returned for injected errors
+        #[arg(long, default_value_t = 503)]
+        error_status: u16,
+
+        /// Stream every response, even when the request did not ask for it
+        #[arg(long)]
+        stream: bool,
+
+        /// Comma-separated models listed by /v1/models
+        #[arg(long, value_delimiter = ',')]
+        models: Vec<String>,
+    },
+
+    /// Load-test a proxy and report throughput, latency, and allocations
+    Bench {
+        /// Total requests to send
+        #[arg(short = 'n', long, default_value_t = 1000)]
+        requests: usize,
+
+        /// Requests in flight at once
+        #[arg(short, long, default_value_t = 50)]
+        concurrency: usize,
+
+        /// Model to request
+        #[arg(short, long, default_value = "gpt-4o")]
+        model: String,
+
+        /// Send streaming requests
+        #[arg(long)]
+        stream: bool,
+
+        /// Run an in-process proxy against built-in mock providers instead of --target
+        #[arg(long)]
+        mock: bool,
+
+        /// Base URL of a running proxy
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        target: String,
+
+        /// Mock provider latency (with --mock): 200ms, 100ms-500ms, or exp:200ms
+        #[arg(long, default_value = "0ms")]
+        latency: LatencyDistribution,
+
+        /// Run an in-process proxy from this config instead of --target,
+        /// e.g. one with [recording] mode = "replay" for repeatable runs
+        #[arg(long, conflicts_with = "mock")]
+        config: Option<String>,
+    },
+
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+
+    /// Offline analysis of logged requests
+    Analyze {
+        #[command(subcommand)]
+        command: AnalyzeCommands,
+    },
+
+    /// Check a running proxy's readiness and circuits; exits 1 unless all is well
+    Status {
+        /// Base URL of the proxy
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        url: String,
+
+        /// Seconds to wait for each response
+        #[arg(long, default_value_t = 5)]
+        timeout: u64,
+    },
+
+    /// Smoke-test this build: run an in-process proxy against mock providers and check its responses
+    Selftest,
+
+    /// Send a real minimal completion to one configured provider, bypassing routing
+    TestProvider {
+        /// Provider name from the config
+        name: String,
+
+        /// Model to request (default: the provider's first listed model)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Stream the completion
+        #[arg(long)]
+        stream: bool,
+
+        /// Seconds to wait for the completion
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+
+        /// Path to configuration file
+        #[arg(short, long, default_value = "config.toml")]
+        config: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProvidersCommands {
+    /// List providers from a Routstr marketplace and optionally add them to the config
+    Discover {
+        /// URL of the marketplace (or node) provider listing
+        #[arg(long, required_unless_present = "relay", conflicts_with = "relay")]
+        marketplace: Option<String>,
+
+        /// Nostr relay to read provider announcements from instead (wss://...)
+        #[arg(long)]
+        relay: Option<String>,
+
+        /// Comma-separated listed provider names to append to the config
+        #[arg(long, value_delimiter = ',')]
+        add: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Write an online backup of the database (safe while the proxy is running)
+    Backup {
+        /// Destination file (must not exist)
+        path: String,
+
+        /// Path to configuration file
+        #[arg(short, long, default_value = "config.toml")]
+        config: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnalyzeCommands {
+    /// Cluster near-duplicate archived prompts and report the spend a response cache would save
+    Duplicates {
+        /// Time range: last_1h, last_24h, last_7d, last_30d
+        #[arg(long, default_value = "last_30d")]
+        range: String,
+
+        /// Minimum word-trigram similarity (0.0-1.0) for prompts to count as duplicates
+        #[arg(long, default_value_t = 0.9)]
+        threshold: f64,
+
+        /// Clusters to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
         /// Path to configuration file
         #[arg(short, long, default_value = "config.toml")]
         config: String,
@@ -52,13 +237,23 @@ enum Commands {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
+    let cli = Cli::parse();
+
+    // Initialize tracing; bench keeps per-request logs out of its report,
+    // and selftest's provider errors are deliberate
+    let default_filter = match cli.command {
+        Commands::Bench { .. } => "arbstr=error",
+        Commands::Selftest => "arbstr=off",
+        Commands::TestProvider { .. } => "arbstr=warn",
+        _ => "arbstr=info,tower_http=info",
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "arbstr=info,tower_http=info".into()),
+                .unwrap_or_else(|_| default_filter.into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        // Unsampled requests keep only warnings and errors
+        .with(tracing_subscriber::fmt::layer().with_filter(arbstr::proxy::sampling::SampledFilter))
         .init();
 
     // Install panic hook that logs via tracing instead of raw stderr
@@ -82,8 +277,6 @@ async fn main() -> anyhow::Result<()> {
         );
     }));
 
-    let cli = Cli::parse();
-
     match cli.command {
         Commands::Serve {
             config: config_path,
@@ -114,13 +307,13 @@ async fn main() -> anyhow::Result<()> {
                 result
             };
 
-            // Override listen address if specified
+            // Override listen addresses if specified
             if let Some(addr) = listen {
-                config.server.listen = addr;
+                config.server.listen = vec![addr];
             }
 
             tracing::info!(
-                listen = %config.server.listen,
+                listen = %config.server.listen.join(", "),
                 providers = %config.providers.len(),
                 "Configuration loaded"
             );
@@ -155,11 +348,19 @@ async fn main() -> anyhow::Result<()> {
 
         Commands::Check {
             config: config_path,
+            pricing,
+            reference,
         } => {
             match Config::from_file_with_env(&config_path) {
                 Ok((config, key_sources)) => {
                     println!("Configuration is valid!");
-                    println!("  Listen: {}", config.server.listen);
+                    println!("  Listen: {}", config.server.listen.join(", "));
+                    if !config.server.analytics_listen.is_empty() {
+                        println!(
+                            "  Analytics listen: {}",
+                            config.server.analytics_listen.join(", ")
+                        );
+                    }
                     println!("  Providers: {}", config.providers.len());
                     println!("  Policy rules: {}", config.policies.rules.len());
 
@@ -196,6 +397,19 @@ async fn main() -> anyhow::Result<()> {
                             }
                         }
                     }
+
+                    if pricing {
+                        let table = match reference {
+                            Some(source) => arbstr::pricing::load(&source).await?,
+                            None => arbstr::pricing::bundled(),
+                        };
+                        let report = arbstr::pricing::lint(&config.providers, &table);
+                        println!();
+                        println!("{}", report);
+                        if !report.is_clean() {
+                            std::process::exit(1);
+                        }
+                    }
                     Ok(())
                 }
                 Err(e) => {
@@ -207,6 +421,32 @@ async fn main() -> anyhow::Result<()> {
 
         Commands::Providers {
             config: config_path,
+            command:
+                Some(ProvidersCommands::Discover {
+                    marketplace,
+                    relay,
+                    add,
+                }),
+        } => {
+            let (listed, source) = match (marketplace, relay) {
+                (Some(url), _) => (
+                    arbstr::marketplace::fetch(&reqwest::Client::new(), &url).await?,
+                    url,
+                ),
+                (None, Some(relay)) => {
+                    let events = arbstr::proxy::nostr::fetch_announcements(&relay)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("{}: {}", relay, e))?;
+                    (arbstr::proxy::nostr::listed_providers(&events), relay)
+                }
+                (None, None) => unreachable!("clap requires --marketplace or --relay"),
+            };
+            discover_providers(&config_path, listed, &source, &add)
+        }
+
+        Commands::Providers {
+            config: config_path,
+            command: None,
         } => {
             let (config, _key_sources) = Config::from_file_with_env(&config_path)?;
 
@@ -223,7 +463,7 @@ async fn main() -> anyhow::Result<()> {
                         "    Rates: {} sats/1k input, {} sats/1k output",
                         provider.input_rate, provider.output_rate
                     );
-                    if provider.base_fee > 0 {
+                    if provider.base_fee > 0.0 {
                         println!("    Base fee: {} sats", provider.base_fee);
                     }
                     if let Some(ref api_key) = provider.api_key {
@@ -234,7 +474,321 @@ async fn main() -> anyhow::Result<()> {
             }
             Ok(())
         }
+
+        Commands::MockProvider {
+            port,
+            host,
+            latency,
+            error_rate,
+            error_status,
+            stream,
+            models,
+        } => {
+            if !(0.0..=1.0).contains(&error_rate) {
+                anyhow::bail!("--error-rate must be 0.0-1.0, got {}", error_rate);
+            }
+            let mut config = MockProviderConfig {
+                latency,
+                error_rate,
+                error_status,
+                force_stream: stream,
+                ..Default::default()
+            };
+            if !models.is_empty() {
+                config.models = models;
+            }
+            arbstr::mock_provider::run(std::net::SocketAddr::new(host, port), config).await
+        }
+
+        Commands::Bench {
+            requests,
+            concurrency,
+            model,
+            stream,
+            mock,
+            target,
+            latency,
+            config,
+        } => {
+            let target = if mock {
+                start_mock_proxy(latency).await?
+            } else if let Some(path) = config {
+                let (config, _) = Config::from_file_with_env(&path)?;
+                start_proxy(config).await?
+            } else {
+                target
+            };
+            let options = arbstr::bench::BenchOptions {
+                target,
+                requests,
+                concurrency,
+                model,
+                stream,
+            };
+            println!(
+                "Benchmarking {} ({} requests, concurrency {}, model {}{})\n",
+                options.target,
+                options.requests,
+                options.concurrency,
+                options.model,
+                if options.stream { ", streaming" } else { "" }
+            );
+            let report = arbstr::bench::run(&reqwest::Client::new(), &options).await;
+            print!("{}", report);
+            Ok(())
+        }
+
+        Commands::Db {
+            command:
+                DbCommands::Backup {
+                    path,
+                    config: config_path,
+                },
+        } => {
+            let (config, _key_sources) = Config::from_file_with_env(&config_path)?;
+            let db_path = config.database().path;
+            let pool = arbstr::storage::init_pool(&db_path).await?;
+
+            let start = std::time::Instant::now();
+            let size =
+                arbstr::storage::backup::backup_to(&pool, std::path::Path::new(&path)).await?;
+            println!(
+                "Backed up {} to {} ({} bytes, {} ms)",
+                db_path,
+                path,
+                size,
+                start.elapsed().as_millis()
+            );
+            Ok(())
+        }
+
+        Commands::Analyze {
+            command:
+                AnalyzeCommands::Duplicates {
+                    range,
+                    threshold,
+                    top,
+                    config: config_path,
+                },
+        } => {
+            if !(0.0..=1.0).contains(&threshold) {
+                anyhow::bail!("--threshold must be between 0.0 and 1.0");
+            }
+            let (config, _key_sources) = Config::from_file_with_env(&config_path)?;
+            if !config.archive.enabled {
+                eprintln!(
+                    "Note: [archive] is not enabled; only previously archived prompts are analyzed"
+                );
+            }
+            let (since, until) =
+                arbstr::proxy::stats::resolve_time_range(Some(&range), None, None)?;
+            let pool = arbstr::storage::init_pool(&config.database().path).await?;
+            let prompts = arbstr::storage::archive::query_range(
+                &pool,
+                &since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                &until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            )
+            .await?;
+            print!(
+                "{}",
+                arbstr::analyze::find_duplicates(&prompts, threshold, &range, top)
+            );
+            Ok(())
+        }
+
+        Commands::Status { url, timeout } => {
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(timeout))
+                .build()?;
+            match arbstr::status::fetch(&client, &url).await {
+                Ok(report) => {
+                    println!("{}", report);
+                    if !report.is_healthy() {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    println!("unreachable: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+
+        Commands::TestProvider {
+            name,
+            model,
+            stream,
+            timeout,
+            config: config_path,
+        } => {
+            let (config, _key_sources) = Config::from_file_with_env(&config_path)?;
+            let options = arbstr::proxy::provider_test::ProviderTestOptions {
+                provider: name,
+                model,
+                stream,
+                timeout: std::time::Duration::from_secs(timeout),
+            };
+            let report = arbstr::proxy::provider_test::run(&config, &options).await?;
+            println!("{}", report);
+            if !report.success() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+
+        Commands::Selftest => {
+            let healthy = spawn_mock_provider(MockProviderConfig::default()).await?;
+            let failing = spawn_mock_provider(MockProviderConfig {
+                error_rate: 1.0,
+                ..Default::default()
+            })
+            .await?;
+            let db_path =
+                std::env::temp_dir().join(format!("arbstr-selftest-{}.db", std::process::id()));
+            let db_path = db_path.to_string_lossy().into_owned();
+            let config = arbstr::selftest::config(&healthy, &failing, &db_path);
+
+            println!("arbstr {} selftest\n", env!("CARGO_PKG_VERSION"));
+            let target = start_proxy(config).await?;
+            let report = arbstr::selftest::run(&reqwest::Client::new(), &target).await;
+            print!("{}", report);
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", db_path, suffix));
+            }
+            if !report.success() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `arbstr providers discover`: print the providers listed at `source`,
+/// then append the ones named in `add` to the config file.
+fn discover_providers(
+    config_path: &str,
+    listed: Vec<arbstr::marketplace::ListedProvider>,
+    source: &str,
+    add: &[String],
+) -> anyhow::Result<()> {
+    let existing = match std::fs::read_to_string(config_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let configured: Vec<String> = Config::parse_str(&existing)
+        .map(|config| config.providers.into_iter().map(|p| p.name).collect())
+        .unwrap_or_default();
+
+    if listed.is_empty() {
+        println!("No providers listed at {}.", source);
+    } else {
+        println!("Providers listed at {}:\n", source);
+        for provider in &listed {
+            let note = if configured.contains(&provider.name) {
+                " [configured]"
+            } else {
+                ""
+            };
+            println!("  {} ({}){}", provider.name, provider.url, note);
+            if !provider.models.is_empty() {
+                println!("    Models: {}", provider.models.join(", "));
+            }
+            println!(
+                "    Rates: {} sats/1k input, {} sats/1k output",
+                provider.input_rate, provider.output_rate
+            );
+            if provider.base_fee > 0.0 {
+                println!("    Base fee: {} sats", provider.base_fee);
+            }
+            println!();
+        }
+    }
+
+    if add.is_empty() {
+        return Ok(());
+    }
+    let mut selected = Vec::with_capacity(add.len());
+    for name in add {
+        let Some(provider) = listed.iter().find(|p| &p.name == name) else {
+            anyhow::bail!("'{}' is not listed at {}", name, source);
+        };
+        if configured.contains(name) {
+            println!("Skipping '{}': already configured", name);
+        } else if !selected.contains(&provider) {
+            selected.push(provider);
+        }
+    }
+    if selected.is_empty() {
+        return Ok(());
+    }
+    let updated = arbstr::marketplace::append_providers(&existing, &selected, source)
+        .map_err(|e| anyhow::anyhow!("{} is not valid TOML: {}", config_path, e))?;
+    std::fs::write(config_path, updated)?;
+    for provider in selected {
+        println!(
+            "Added '{}' to {} (set {} to its API key)",
+            provider.name,
+            config_path,
+            arbstr::config::convention_env_var_name(&provider.name)
+        );
+    }
+    Ok(())
+}
+
+/// Start mock providers and a proxy using the mock configuration in this
+/// process, on ephemeral ports. Returns the proxy's base URL once it is up.
+async fn start_mock_proxy(latency: LatencyDistribution) -> anyhow::Result<String> {
+    let mut config = mock_config();
+    for provider in &mut config.providers {
+        provider.url = spawn_mock_provider(MockProviderConfig {
+            latency,
+            ..Default::default()
+        })
+        .await?;
+    }
+    start_proxy(config).await
+}
+
+/// Serve a mock provider on an ephemeral port; returns its `/v1` base URL.
+async fn spawn_mock_provider(config: MockProviderConfig) -> anyhow::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/v1", listener.local_addr()?);
+    let router = arbstr::mock_provider::create_router(config);
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(url)
+}
+
+/// Run `config` in-process on a free local port for `arbstr bench` and
+/// `arbstr selftest`.
+async fn start_proxy(mut config: Config) -> anyhow::Result<String> {
+    // run_server binds by address, so reserve a free port and release it
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    config.server.listen = vec![format!("127.0.0.1:{}", port)];
+    config.server.analytics_listen.clear();
+    let base_url = format!("http://127.0.0.1:{}", port);
+    tokio::spawn(async move {
+        if let Err(e) = run_server(config).await {
+            tracing::error!(error = %e, "In-process proxy failed");
+        }
+    });
+
+    let client = reqwest::Client::new();
+    for _ in 0..100 {
+        if client
+            .get(format!("{}/health", base_url))
+            .send()
+            .await
+            .is_ok()
+        {
+            return Ok(base_url);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
     }
+    anyhow::bail!("In-process proxy did not start on {}", base_url)
 }
 
 /// Create a mock configuration for testing without real providers.
@@ -243,12 +797,20 @@ fn mock_config() -> Config {
 
     Config {
         server: ServerConfig {
-            listen: "127.0.0.1:8080".to_string(),
+            listen: vec!["127.0.0.1:8080".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: Some(DatabaseConfig {
-            path: ":memory:".to_string(),
+            backend: StorageBackend::Memory,
+            ..Default::default()
         }),
         vault: None,
         providers: vec![
@@ -261,22 +823,58 @@ fn mock_config() -> Config {
                     "gpt-4o-mini".to_string(),
                     "claude-3.5-sonnet".to_string(),
                 ],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 0,
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "mock-expensive".to_string(),
                 url: "http://localhost:9998/v1".to_string(),
                 api_key: Some(ApiKey::from("mock-test-key-expensive")),
                 models: vec!["gpt-4o".to_string(), "claude-3.5-sonnet".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 1,
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 1.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ],
         policies: PoliciesConfig {
@@ -285,18 +883,54 @@ fn mock_config() -> Config {
                 name: "code".to_string(),
                 allowed_models: vec!["gpt-4o".to_string(), "claude-3.5-sonnet".to_string()],
                 strategy: "lowest_cost".to_string(),
-                max_sats_per_1k_output: Some(50),
+                max_sats_per_1k_output: Some(50.0),
                 keywords: vec![
                     "code".to_string(),
                     "function".to_string(),
                     "implement".to_string(),
                 ],
+                backoff: None,
+                active_hours: None,
+                days: vec![],
+                utc_offset: None,
+                off_hours_tier: Default::default(),
+                allowed_regions: vec![],
+                validate: None,
+                max_output_tokens: None,
+                default_max_tokens: None,
+                system_prompt_prepend: None,
+                system_prompt_bypass_token: None,
+                trim: None,
+                min_prompt_tokens: None,
+                max_prompt_tokens: None,
+                tier: None,
+                accumulate: false,
+                compress: None,
+                response_headers: Default::default(),
+                omit_headers: vec![],
+                dataset_capture: None,
             }],
         },
         logging: LoggingConfig {
             level: "debug".to_string(),
-            log_requests: true,
+            ..Default::default()
         },
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     }
 }
@@ -0,0 +1,261 @@
+//! Provider discovery from a Routstr marketplace listing
+//! (`arbstr providers discover`).
+//!
+//! The listing is fetched as JSON: either an array of providers or an
+//! object with a `providers` array. Each provider has a `name` (or `id`),
+//! a `url` (or `endpoint_url`), and `models` given as ids or as model
+//! objects with Routstr `sats_pricing` (sats per token for `prompt` and
+//! `completion`, sats per request for `request`). Explicit `input_rate`,
+//! `output_rate` and `base_fee` (sats per 1k tokens / per request) win
+//! over model pricing. arbstr has one rate per provider, so the dearest
+//! model's price is used: routing never underestimates a request's cost.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Timeout for fetching the listing.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A provider from the listing, in arbstr's terms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedProvider {
+    /// Config-friendly name (lowercase, `-` separated).
+    pub name: String,
+    pub url: String,
+    pub models: Vec<String>,
+    /// Sats per 1k input tokens.
+    pub input_rate: f64,
+    /// Sats per 1k output tokens.
+    pub output_rate: f64,
+    /// Sats per request.
+    pub base_fee: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Listing {
+    Wrapped { providers: Vec<RawProvider> },
+    Bare(Vec<RawProvider>),
+}
+
+#[derive(Deserialize)]
+struct RawProvider {
+    #[serde(alias = "id")]
+    name: String,
+    #[serde(alias = "endpoint_url")]
+    url: String,
+    #[serde(default)]
+    models: Vec<RawModel>,
+    input_rate: Option<f64>,
+    output_rate: Option<f64>,
+    base_fee: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawModel {
+    Id(String),
+    Priced {
+        id: String,
+        #[serde(default)]
+        sats_pricing: SatsPricing,
+    },
+}
+
+/// Routstr per-model pricing in sats.
+#[derive(Default, Deserialize)]
+struct SatsPricing {
+    /// Per input token.
+    #[serde(default)]
+    prompt: f64,
+    /// Per output token.
+    #[serde(default)]
+    completion: f64,
+    /// Per request.
+    #[serde(default)]
+    request: f64,
+}
+
+/// Lowercase `name`, with runs of other characters replaced by `-`.
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Round sats up to whole millisats, ignoring float noise (e.g. 0.007
+/// sats/token * 1000 = 7.000000000000001).
+fn whole_msats(sats: f64) -> f64 {
+    ((sats * 1e6).round() / 1e3).ceil() / 1000.0
+}
+
+/// Parse a listing body.
+pub fn parse_listing(body: &str) -> Result<Vec<ListedProvider>, String> {
+    let raw = match serde_json::from_str::<Listing>(body)
+        .map_err(|e| format!("unrecognized marketplace listing: {}", e))?
+    {
+        Listing::Wrapped { providers } | Listing::Bare(providers) => providers,
+    };
+
+    Ok(raw.into_iter().map(convert).collect())
+}
+
+/// Parse a single provider object, e.g. the content of a Nostr
+/// announcement (see `proxy::nostr`).
+pub fn parse_provider(body: &str) -> Result<ListedProvider, String> {
+    serde_json::from_str::<RawProvider>(body)
+        .map(convert)
+        .map_err(|e| format!("unrecognized provider listing: {}", e))
+}
+
+fn convert(p: RawProvider) -> ListedProvider {
+    let mut models = Vec::with_capacity(p.models.len());
+    let mut pricing = SatsPricing::default();
+    for model in p.models {
+        match model {
+            RawModel::Id(id) => models.push(id),
+            RawModel::Priced { id, sats_pricing } => {
+                models.push(id);
+                pricing.prompt = pricing.prompt.max(sats_pricing.prompt);
+                pricing.completion = pricing.completion.max(sats_pricing.completion);
+                pricing.request = pricing.request.max(sats_pricing.request);
+            }
+        }
+    }
+    ListedProvider {
+        name: slug(&p.name),
+        url: p.url,
+        models,
+        input_rate: p.input_rate.unwrap_or(whole_msats(pricing.prompt * 1000.0)),
+        output_rate: p
+            .output_rate
+            .unwrap_or(whole_msats(pricing.completion * 1000.0)),
+        base_fee: p.base_fee.unwrap_or(whole_msats(pricing.request)),
+    }
+}
+
+/// Fetch and parse the listing at `url`.
+pub async fn fetch(client: &reqwest::Client, url: &str) -> anyhow::Result<Vec<ListedProvider>> {
+    let response = client.get(url).timeout(FETCH_TIMEOUT).send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} returned HTTP {}", url, response.status());
+    }
+    let body = response.text().await?;
+    parse_listing(&body).map_err(anyhow::Error::msg)
+}
+
+/// A `[[providers]]` block for `provider`. No `api_key` is written: the
+/// key comes from the provider's convention env var.
+pub fn to_toml(provider: &ListedProvider, source: &str) -> String {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+    let mut block = String::new();
+    let _ = writeln!(
+        block,
+        "# Added by `arbstr providers discover` from {}",
+        source
+    );
+    let _ = writeln!(block, "[[providers]]");
+    let _ = writeln!(block, "name = {}", quote(&provider.name));
+    let _ = writeln!(block, "url = {}", quote(&provider.url));
+    let models: Vec<String> = provider.models.iter().map(|m| quote(m)).collect();
+    let _ = writeln!(block, "models = [{}]", models.join(", "));
+    let _ = writeln!(block, "input_rate = {}", provider.input_rate);
+    let _ = writeln!(block, "output_rate = {}", provider.output_rate);
+    let _ = writeln!(block, "base_fee = {}", provider.base_fee);
+    block
+}
+
+/// Append `providers` to the config text `existing`, checking that the
+/// result is still valid TOML.
+pub fn append_providers(
+    existing: &str,
+    providers: &[&ListedProvider],
+    source: &str,
+) -> Result<String, String> {
+    let mut config = existing.to_string();
+    if !config.is_empty() && !config.ends_with('\n') {
+        config.push('\n');
+    }
+    for provider in providers {
+        config.push('\n');
+        config.push_str(&to_toml(provider, source));
+    }
+    toml::from_str::<toml::Value>(&config).map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_priced_models_at_the_dearest_rate() {
+        let listing = r#"{"providers": [{
+            "id": "Sats Node #1",
+            "endpoint_url": "https://node.routstr.example/v1",
+            "models": [
+                {"id": "gpt-4o", "sats_pricing": {"prompt": 0.004, "completion": 0.0121, "request": 0.2}},
+                {"id": "gpt-4o-mini", "sats_pricing": {"prompt": 0.007, "completion": 0.002}}
+            ]
+        }]}"#;
+        let providers = parse_listing(listing).unwrap();
+        assert_eq!(
+            providers,
+            vec![ListedProvider {
+                name: "sats-node-1".to_string(),
+                url: "https://node.routstr.example/v1".to_string(),
+                models: vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
+                input_rate: 7.0,
+                output_rate: 12.1,
+                base_fee: 0.2,
+            }]
+        );
+    }
+
+    #[test]
+    fn explicit_rates_and_bare_arrays() {
+        let listing = r#"[{
+            "name": "cheap",
+            "url": "https://cheap.example/v1",
+            "models": ["llama-3"],
+            "input_rate": 2,
+            "output_rate": 6
+        }]"#;
+        let providers = parse_listing(listing).unwrap();
+        assert_eq!(providers[0].models, ["llama-3"]);
+        assert_eq!(
+            (
+                providers[0].input_rate,
+                providers[0].output_rate,
+                providers[0].base_fee
+            ),
+            (2.0, 6.0, 0.0)
+        );
+
+        assert!(parse_listing(r#"{"data": []}"#).is_err());
+    }
+
+    #[test]
+    fn appended_blocks_parse_as_providers() {
+        let provider = ListedProvider {
+            name: "node-a".to_string(),
+            url: "https://a.example/v1".to_string(),
+            models: vec!["gpt-4o".to_string()],
+            input_rate: 4.0,
+            output_rate: 12.0,
+            base_fee: 0.0,
+        };
+        let existing = "[server]\nlisten = \"127.0.0.1:8080\"";
+        let config = append_providers(existing, &[&provider], "https://market.example").unwrap();
+        assert!(config.starts_with(existing));
+
+        let parsed = crate::Config::parse_str(&config).unwrap();
+        assert_eq!(parsed.providers[0].name, "node-a");
+        assert_eq!(parsed.providers[0].models, ["gpt-4o"]);
+        assert_eq!(parsed.providers[0].output_rate, 12.0);
+    }
+}
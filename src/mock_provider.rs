@@ -0,0 +1,384 @@
+//! Built-in OpenAI-compatible fake provider (`arbstr mock-provider`).
+//!
+//! Serves `/v1/chat/completions` (JSON or SSE), `/v1/models`, and `/health`
+//! with configurable latency, injected errors, and streaming, so arbstr
+//! setups and client apps can be integration-tested without real providers.
+//! `arbstr serve --mock` expects instances on ports 9999 and 9998.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use rand::Rng;
+use serde_json::{json, Value};
+
+/// Reply text; streamed one word per chunk.
+const MOCK_CONTENT: &str = "This is a mock response from the arbstr mock provider.";
+
+/// Delay between streamed chunks.
+const CHUNK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to wait before answering each request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyDistribution {
+    /// Always the same delay, e.g. `200ms`.
+    Fixed(Duration),
+    /// Uniform between two bounds, e.g. `100ms-500ms`.
+    Uniform(Duration, Duration),
+    /// Exponential with the given mean, e.g. `exp:200ms`.
+    Exponential(Duration),
+}
+
+impl LatencyDistribution {
+    /// Draw one delay.
+    pub fn sample(&self) -> Duration {
+        let mut rng = rand::thread_rng();
+        match *self {
+            Self::Fixed(d) => d,
+            Self::Uniform(low, high) => {
+                Duration::from_millis(rng.gen_range(low.as_millis()..=high.as_millis()) as u64)
+            }
+            Self::Exponential(mean) => {
+                let u: f64 = rng.gen();
+                mean.mul_f64(-(1.0 - u).ln())
+            }
+        }
+    }
+}
+
+impl Default for LatencyDistribution {
+    fn default() -> Self {
+        Self::Fixed(Duration::ZERO)
+    }
+}
+
+impl FromStr for LatencyDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(mean) = s.strip_prefix("exp:") {
+            return Ok(Self::Exponential(parse_duration(mean)?));
+        }
+        if let Some((low, high)) = s.split_once('-') {
+            let (low, high) = (parse_duration(low)?, parse_duration(high)?);
+            if low > high {
+                return Err(format!("latency range '{}' has low > high", s));
+            }
+            return Ok(Self::Uniform(low, high));
+        }
+        Ok(Self::Fixed(parse_duration(s)?))
+    }
+}
+
+/// Parse `250ms`, `2s`, or a bare millisecond count.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1000.0)
+    } else {
+        (s, 1.0)
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+        .map(|n| Duration::from_millis((n * scale) as u64))
+        .ok_or_else(|| format!("invalid duration '{}' (expected e.g. 200ms or 1.5s)", s))
+}
+
+/// Mock provider behaviour.
+#[derive(Debug, Clone)]
+pub struct MockProviderConfig {
+    pub latency: LatencyDistribution,
+    /// Fraction of chat requests (0.0-1.0) answered with `error_status`.
+    pub error_rate: f64,
+    pub error_status: u16,
+    /// Stream every response, even when the request did not ask for it.
+    pub force_stream: bool,
+    /// Models listed by `/v1/models`.
+    pub models: Vec<String>,
+}
+
+impl Default for MockProviderConfig {
+    fn default() -> Self {
+        Self {
+            latency: LatencyDistribution::default(),
+            error_rate: 0.0,
+            error_status: 503,
+            force_stream: false,
+            models: vec![
+                "gpt-4o".to_string(),
+                "gpt-4o-mini".to_string(),
+                "claude-3.5-sonnet".to_string(),
+            ],
+        }
+    }
+}
+
+/// Build the mock provider's router.
+pub fn create_router(config: MockProviderConfig) -> Router {
+    Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/models", get(list_models))
+        .route("/health", get(|| async { Json(json!({"status": "ok"})) }))
+        .with_state(Arc::new(config))
+}
+
+/// Serve the mock provider on `addr` until Ctrl-C.
+pub async fn run(addr: SocketAddr, config: MockProviderConfig) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(
+        addr = %listener.local_addr()?,
+        latency = ?config.latency,
+        error_rate = config.error_rate,
+        force_stream = config.force_stream,
+        "Mock provider listening"
+    );
+    axum::serve(listener, create_router(config))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+async fn list_models(State(config): State<Arc<MockProviderConfig>>) -> Json<Value> {
+    let data: Vec<Value> = config
+        .models
+        .iter()
+        .map(|id| json!({"id": id, "object": "model", "owned_by": "arbstr-mock"}))
+        .collect();
+    Json(json!({"object": "list", "data": data}))
+}
+
+async fn chat_completions(
+    State(config): State<Arc<MockProviderConfig>>,
+    Json(request): Json<Value>,
+) -> Response {
+    tokio::time::sleep(config.latency.sample()).await;
+
+    if config.error_rate > 0.0 && rand::thread_rng().gen::<f64>() < config.error_rate {
+        let status =
+            StatusCode::from_u16(config.error_status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+        return (
+            status,
+            Json(json!({
+                "error": {
+                    "message": "Injected error from arbstr mock provider",
+                    "type": "mock_error",
+                    "code": status.as_u16()
+                }
+            })),
+        )
+            .into_response();
+    }
+
+    let model = request["model"]
+        .as_str()
+        .unwrap_or("mock-model")
+        .to_string();
+    let prompt_tokens = estimate_prompt_tokens(&request);
+    let completion_tokens = MOCK_CONTENT.split_whitespace().count() as u64;
+    let usage = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens
+    });
+    let id = format!("chatcmpl-mock-{}", uuid::Uuid::new_v4().simple());
+
+    let stream = config.force_stream || request["stream"].as_bool().unwrap_or(false);
+    if !stream {
+        return Json(json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": chrono::Utc::now().timestamp(),
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": MOCK_CONTENT},
+                "finish_reason": "stop"
+            }],
+            "usage": usage
+        }))
+        .into_response();
+    }
+
+    let include_usage = request["stream_options"]["include_usage"]
+        .as_bool()
+        .unwrap_or(false);
+    let events = sse_events(&id, &model, include_usage.then_some(usage));
+    let body = futures::stream::unfold(events.into_iter(), |mut events| async move {
+        let event = events.next()?;
+        tokio::time::sleep(CHUNK_INTERVAL).await;
+        Some((Ok::<_, Infallible>(Bytes::from(event)), events))
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// SSE frames for one streamed completion, ending with `[DONE]`.
+fn sse_events(id: &str, model: &str, usage: Option<Value>) -> Vec<String> {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    };
+
+    let mut chunks = vec![chunk(json!({"role": "assistant"}), Value::Null)];
+    for (i, word) in MOCK_CONTENT.split_whitespace().enumerate() {
+        let text = if i == 0 {
+            word.to_string()
+        } else {
+            format!(" {}", word)
+        };
+        chunks.push(chunk(json!({"content": text}), Value::Null));
+    }
+    chunks.push(chunk(json!({}), json!("stop")));
+    if let Some(usage) = usage {
+        chunks.push(json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "model": model,
+            "choices": [],
+            "usage": usage
+        }));
+    }
+
+    let mut events: Vec<String> = chunks.iter().map(|c| format!("data: {}\n\n", c)).collect();
+    events.push("data: [DONE]\n\n".to_string());
+    events
+}
+
+/// Rough prompt size: four characters per token.
+fn estimate_prompt_tokens(request: &Value) -> u64 {
+    let chars: usize = request["messages"]
+        .as_array()
+        .map(|messages| {
+            messages
+                .iter()
+                .filter_map(|m| m["content"].as_str())
+                .map(str::len)
+                .sum()
+        })
+        .unwrap_or(0);
+    (chars as u64).div_ceil(4).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    async fn post_chat(config: MockProviderConfig, body: Value) -> (StatusCode, String) {
+        let response = create_router(config)
+            .oneshot(
+                axum::http::Request::post("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn parses_latency_distributions() {
+        assert_eq!(
+            "200ms".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Fixed(Duration::from_millis(200))
+        );
+        assert_eq!(
+            "1.5s".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Fixed(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            "100ms-300ms".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Uniform(Duration::from_millis(100), Duration::from_millis(300))
+        );
+        assert_eq!(
+            "exp:50ms".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Exponential(Duration::from_millis(50))
+        );
+        assert!("fast".parse::<LatencyDistribution>().is_err());
+        assert!("300ms-100ms".parse::<LatencyDistribution>().is_err());
+    }
+
+    #[test]
+    fn uniform_latency_stays_in_range() {
+        let dist =
+            LatencyDistribution::Uniform(Duration::from_millis(10), Duration::from_millis(20));
+        for _ in 0..100 {
+            let d = dist.sample();
+            assert!(d >= Duration::from_millis(10) && d <= Duration::from_millis(20));
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_completion_with_usage() {
+        let (status, body) = post_chat(
+            MockProviderConfig::default(),
+            json!({"model": "gpt-4o", "messages": [{"role": "user", "content": "hello there"}]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["model"], "gpt-4o");
+        assert_eq!(json["choices"][0]["message"]["content"], MOCK_CONTENT);
+        assert_eq!(json["usage"]["prompt_tokens"], 3);
+        assert!(json["usage"]["completion_tokens"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn injects_errors() {
+        let config = MockProviderConfig {
+            error_rate: 1.0,
+            error_status: 429,
+            ..Default::default()
+        };
+        let (status, _) = post_chat(config, json!({"model": "gpt-4o", "messages": []})).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn streams_chunks_and_usage() {
+        let (status, body) = post_chat(
+            MockProviderConfig::default(),
+            json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "hi"}],
+                "stream": true,
+                "stream_options": {"include_usage": true}
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"finish_reason\":\"stop\""));
+        assert!(body.contains("\"usage\""));
+        assert!(body.ends_with("data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,457 @@
+//! Provider rate sanity checks behind `arbstr check --pricing`.
+//!
+//! Configured rates are compared with a reference price table: the bundled
+//! one below, or a JSON table fetched from a URL or read from a file. A rate
+//! more than [`MAGNITUDE`] times off the reference for the provider's models
+//! is flagged, which catches the common unit mistakes (millisats entered as
+//! sats, per-1M or per-token prices entered as per-1k). Providers that list
+//! models with a zero input or output rate are flagged too, since routing
+//! would treat them as free.
+//!
+//! arbstr has one rate per provider, priced at its dearest model (see
+//! `marketplace`), so a rate is too high only when it is off against the
+//! dearest listed model with a reference price, and too low only against
+//! the cheapest. Models without a reference price are reported but not
+//! checked.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::ProviderConfig;
+
+/// How far (as a factor) a rate may be from the reference before it is
+/// flagged.
+pub const MAGNITUDE: f64 = 10.0;
+
+/// Timeout for fetching a reference table.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Bundled reference prices in sats per 1k tokens (input, output), taken
+/// from list prices at 1 BTC = $100,000, where sats per 1k tokens equal
+/// dollars per 1M tokens. Only the order of magnitude matters here, so
+/// they don't need to track the bitcoin price closely.
+const BUNDLED: &[(&str, f64, f64)] = &[
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("o1", 15.0, 60.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3.5-sonnet", 3.0, 15.0),
+    ("claude-3.5-haiku", 0.8, 4.0),
+    ("claude-3.7-sonnet", 3.0, 15.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-1.5-flash", 0.075, 0.3),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("mistral-large", 2.0, 6.0),
+    ("mistral-small", 0.1, 0.3),
+    ("deepseek-chat", 0.27, 1.1),
+    ("deepseek-reasoner", 0.55, 2.19),
+];
+
+/// A model's reference price in sats per 1k tokens.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReferencePrice {
+    pub model: String,
+    pub input_rate: f64,
+    pub output_rate: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Table {
+    Wrapped { models: Vec<ReferencePrice> },
+    Bare(Vec<ReferencePrice>),
+}
+
+/// The bundled reference table.
+pub fn bundled() -> Vec<ReferencePrice> {
+    BUNDLED
+        .iter()
+        .map(|&(model, input_rate, output_rate)| ReferencePrice {
+            model: model.to_string(),
+            input_rate,
+            output_rate,
+        })
+        .collect()
+}
+
+/// Parse a reference table: an array of `{model, input_rate, output_rate}`
+/// objects (sats per 1k tokens), or an object with a `models` array.
+pub fn parse_table(body: &str) -> Result<Vec<ReferencePrice>, String> {
+    let prices = match serde_json::from_str::<Table>(body)
+        .map_err(|e| format!("unrecognized price table: {}", e))?
+    {
+        Table::Wrapped { models } | Table::Bare(models) => models,
+    };
+    if let Some(bad) = prices.iter().find(|p| {
+        !p.input_rate.is_finite()
+            || !p.output_rate.is_finite()
+            || p.input_rate < 0.0
+            || p.output_rate < 0.0
+    }) {
+        return Err(format!("invalid rates for model '{}'", bad.model));
+    }
+    Ok(prices)
+}
+
+/// Load a reference table from an `http(s)://` URL or a file path.
+pub async fn load(source: &str) -> anyhow::Result<Vec<ReferencePrice>> {
+    let body = if source.starts_with("http://") || source.starts_with("https://") {
+        let response = reqwest::Client::new()
+            .get(source)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} returned HTTP {}", source, response.status());
+        }
+        response.text().await?
+    } else {
+        std::fs::read_to_string(source)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {}", source, e))?
+    };
+    parse_table(&body).map_err(anyhow::Error::msg)
+}
+
+/// Model name as compared with the table: lowercase, without a vendor
+/// prefix (`openai/`), with `.` as `-` so `claude-3.5-sonnet` and
+/// `claude-3-5-sonnet` match.
+fn normalize(model: &str) -> String {
+    model
+        .rsplit('/')
+        .next()
+        .unwrap_or(model)
+        .to_ascii_lowercase()
+        .replace('.', "-")
+}
+
+/// The reference price for `model`: the longest table entry that is the
+/// whole name or a prefix of it ending at a `-` or `:` (dated snapshots
+/// like `gpt-4o-2024-08-06`, tags like `deepseek-chat:free`).
+pub fn lookup<'a>(reference: &'a [ReferencePrice], model: &str) -> Option<&'a ReferencePrice> {
+    let model = normalize(model);
+    reference
+        .iter()
+        .filter(|price| {
+            let name = normalize(&price.model);
+            model == name
+                || model
+                    .strip_prefix(&name)
+                    .is_some_and(|rest| rest.starts_with(['-', ':']))
+        })
+        .max_by_key(|price| price.model.len())
+}
+
+/// Which rate a finding is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rate {
+    Input,
+    Output,
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rate::Input => "input_rate",
+            Rate::Output => "output_rate",
+        })
+    }
+}
+
+/// What looks wrong with a rate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// Zero while the provider lists models.
+    Zero,
+    /// More than [`MAGNITUDE`] times the reference for `model`.
+    TooHigh { model: String, reference: f64 },
+    /// Less than 1/[`MAGNITUDE`] of the reference for `model`.
+    TooLow { model: String, reference: f64 },
+}
+
+/// A flagged provider rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub provider: String,
+    pub rate: Rate,
+    pub value: f64,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} = {}", self.provider, self.rate, self.value)?;
+        match &self.problem {
+            Problem::Zero => write!(f, " (models listed with a zero rate are routed as free)"),
+            Problem::TooHigh { model, reference } => write!(
+                f,
+                " is {:.0}x the reference for {} ({} sats/1k); millisats entered as sats?",
+                self.value / reference,
+                model,
+                reference
+            ),
+            Problem::TooLow { model, reference } => write!(
+                f,
+                " is 1/{:.0} of the reference for {} ({} sats/1k); rates are sats per 1k tokens",
+                reference / self.value,
+                model,
+                reference
+            ),
+        }
+    }
+}
+
+/// Result of a pricing lint.
+#[derive(Debug, Clone, Default)]
+pub struct PricingReport {
+    pub findings: Vec<Finding>,
+    /// Models compared with the table, as (provider, model).
+    pub checked: Vec<(String, String)>,
+    /// Models without a reference price, as (provider, model).
+    pub unknown: Vec<(String, String)>,
+}
+
+impl PricingReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl fmt::Display for PricingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Pricing: {} model(s) checked against the reference, {} without a reference price",
+            self.checked.len(),
+            self.unknown.len()
+        )?;
+        for (provider, model) in &self.unknown {
+            writeln!(f, "  {}: no reference price for {}", provider, model)?;
+        }
+        if self.is_clean() {
+            write!(f, "  No rates look off.")
+        } else {
+            for (i, finding) in self.findings.iter().enumerate() {
+                if i > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "  WARNING: {}", finding)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Check one rate against the dearest and cheapest reference prices of
+/// the provider's models.
+fn check_rate(
+    provider: &str,
+    rate: Rate,
+    value: f64,
+    prices: &[(&str, f64)],
+    findings: &mut Vec<Finding>,
+) {
+    if value == 0.0 {
+        findings.push(Finding {
+            provider: provider.to_string(),
+            rate,
+            value,
+            problem: Problem::Zero,
+        });
+        return;
+    }
+    let dearest = prices
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|(_, reference)| *reference > 0.0);
+    let cheapest = prices
+        .iter()
+        .filter(|(_, reference)| *reference > 0.0)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some(&(model, reference)) = dearest {
+        if value > reference * MAGNITUDE {
+            findings.push(Finding {
+                provider: provider.to_string(),
+                rate,
+                value,
+                problem: Problem::TooHigh {
+                    model: model.to_string(),
+                    reference,
+                },
+            });
+            return;
+        }
+    }
+    if let Some(&(model, reference)) = cheapest {
+        if value < reference / MAGNITUDE {
+            findings.push(Finding {
+                provider: provider.to_string(),
+                rate,
+                value,
+                problem: Problem::TooLow {
+                    model: model.to_string(),
+                    reference,
+                },
+            });
+        }
+    }
+}
+
+/// Compare each provider's rates with `reference`.
+pub fn lint(providers: &[ProviderConfig], reference: &[ReferencePrice]) -> PricingReport {
+    let mut report = PricingReport::default();
+    for provider in providers {
+        if provider.models.is_empty() {
+            continue;
+        }
+        let mut input = Vec::new();
+        let mut output = Vec::new();
+        for model in &provider.models {
+            match lookup(reference, model) {
+                Some(price) => {
+                    input.push((model.as_str(), price.input_rate));
+                    output.push((model.as_str(), price.output_rate));
+                    report.checked.push((provider.name.clone(), model.clone()));
+                }
+                None => report.unknown.push((provider.name.clone(), model.clone())),
+            }
+        }
+        check_rate(
+            &provider.name,
+            Rate::Input,
+            provider.input_rate,
+            &input,
+            &mut report.findings,
+        );
+        check_rate(
+            &provider.name,
+            Rate::Output,
+            provider.output_rate,
+            &output,
+            &mut report.findings,
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, models: &[&str], input_rate: f64, output_rate: f64) -> ProviderConfig {
+        ProviderConfig {
+            url: "https://example.com/v1".to_string(),
+            models: models.iter().map(|m| m.to_string()).collect(),
+            input_rate,
+            output_rate,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    #[test]
+    fn lookup_matches_snapshots_prefixes_and_spellings() {
+        let table = bundled();
+        let model = |name: &str| lookup(&table, name).map(|p| p.model.as_str());
+        assert_eq!(model("gpt-4o"), Some("gpt-4o"));
+        assert_eq!(model("gpt-4o-2024-08-06"), Some("gpt-4o"));
+        assert_eq!(model("gpt-4o-mini"), Some("gpt-4o-mini"));
+        assert_eq!(model("openai/GPT-4o-mini"), Some("gpt-4o-mini"));
+        assert_eq!(
+            model("claude-3-5-sonnet-20241022"),
+            Some("claude-3.5-sonnet")
+        );
+        assert_eq!(model("o1-preview"), Some("o1"));
+        assert_eq!(model("o1x"), None);
+        assert_eq!(model("my-local-model"), None);
+    }
+
+    #[test]
+    fn flags_order_of_magnitude_and_zero_rates() {
+        let table = bundled();
+        let providers = vec![
+            // Sensible: priced at the dearest model
+            provider("fine", &["gpt-4o", "gpt-4o-mini"], 3.0, 10.0),
+            // Millisats entered as sats
+            provider("msats", &["gpt-4o"], 2500.0, 10000.0),
+            // Far below the reference, and free output
+            provider("cheap", &["claude-3-opus"], 1.0, 0.0),
+            // Nothing to compare with; only zero rates are flagged
+            provider("local", &["llama-local"], 0.0, 0.0),
+        ];
+        let report = lint(&providers, &table);
+
+        let flagged: Vec<(&str, Rate)> = report
+            .findings
+            .iter()
+            .map(|f| (f.provider.as_str(), f.rate))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                ("msats", Rate::Input),
+                ("msats", Rate::Output),
+                ("cheap", Rate::Input),
+                ("cheap", Rate::Output),
+                ("local", Rate::Input),
+                ("local", Rate::Output),
+            ]
+        );
+        assert_eq!(
+            report.findings[0].problem,
+            Problem::TooHigh {
+                model: "gpt-4o".to_string(),
+                reference: 2.5
+            }
+        );
+        assert!(report.findings[0]
+            .to_string()
+            .contains("1000x the reference for gpt-4o"));
+        assert!(matches!(report.findings[2].problem, Problem::TooLow { .. }));
+        assert_eq!(report.findings[3].problem, Problem::Zero);
+        assert_eq!(report.checked.len(), 4);
+        assert_eq!(
+            report.unknown,
+            vec![("local".to_string(), "llama-local".to_string())]
+        );
+    }
+
+    #[test]
+    fn sub_sat_rates_are_checked_at_millisat_precision() {
+        let report = lint(
+            &[provider("flash", &["gemini-1.5-flash"], 0.08, 0.3)],
+            &bundled(),
+        );
+        assert!(report.is_clean(), "{}", report);
+        // A whole sat is over 10x gemini-1.5-flash's input price
+        let report = lint(
+            &[provider("flash", &["gemini-1.5-flash"], 1.0, 0.3)],
+            &bundled(),
+        );
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].rate, Rate::Input);
+    }
+
+    #[test]
+    fn parses_bare_and_wrapped_tables() {
+        let bare = r#"[{"model": "m", "input_rate": 1, "output_rate": 2.5}]"#;
+        let wrapped = r#"{"models": [{"model": "m", "input_rate": 1, "output_rate": 2.5}]}"#;
+        assert_eq!(parse_table(bare).unwrap(), parse_table(wrapped).unwrap());
+        assert!(parse_table(r#"[{"model": "m", "input_rate": -1, "output_rate": 1}]"#).is_err());
+        assert!(parse_table("{}").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! `GET /v1/about`: what this instance is running, for fleet inventory.
+//!
+//! Reports the version and commit it was built from, a hash of its
+//! effective configuration (so instances sharing a config can be grouped),
+//! uptime, provider and policy counts, and which optional features are on.
+
+use std::collections::BTreeMap;
+
+use axum::{extract::State, Json};
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::nostr::encode_hex;
+use super::server::AppState;
+use crate::config::{Config, StorageBackend};
+
+/// Response body for `GET /v1/about`.
+#[derive(Debug, Serialize)]
+pub struct AboutResponse {
+    pub version: &'static str,
+    /// Short git commit of the build, or `unknown`.
+    pub commit: &'static str,
+    /// See [`config_hash`].
+    pub config_hash: String,
+    pub started_at: String,
+    pub uptime_secs: u64,
+    pub providers: usize,
+    pub policies: usize,
+    /// `sqlite`, `memory`, or absent without a database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<&'static str>,
+    /// Optional features and whether each is enabled.
+    pub features: BTreeMap<&'static str, bool>,
+}
+
+/// Handle GET /v1/about.
+pub async fn about_handler(State(state): State<AppState>) -> Json<AboutResponse> {
+    let config = &state.config;
+    let uptime = state.started_at.elapsed();
+    let started_at = Utc::now()
+        - chrono::Duration::from_std(uptime).unwrap_or_else(|_| chrono::Duration::zero());
+    let storage = state.db.as_ref().map(|_| match config.database().backend {
+        StorageBackend::Sqlite => "sqlite",
+        StorageBackend::Memory => "memory",
+    });
+
+    let features = BTreeMap::from([
+        ("auth", config.server.auth_token.is_some()),
+        ("admin_auth", config.server.admin_token.is_some()),
+        ("db", state.db.is_some()),
+        ("vault", state.vault.is_some()),
+        ("stats_cache", config.stats.cache_ttl_secs > 0),
+        ("archive", config.archive.enabled),
+        ("recording", config.recording.is_some()),
+        ("evaluation", config.evaluation.is_some()),
+        ("arbitrage", config.arbitrage.enabled),
+        ("nostr", config.nostr.is_some()),
+        ("currency", config.currency.is_some()),
+        ("warmup", config.warmup.enabled),
+        ("chaos", config.chaos.enabled),
+    ]);
+
+    Json(AboutResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("ARBSTR_BUILD_COMMIT"),
+        config_hash: config_hash(config),
+        started_at: started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        uptime_secs: uptime.as_secs(),
+        providers: config.providers.len(),
+        policies: config.policies.rules.len(),
+        storage,
+        features,
+    })
+}
+
+/// First 16 hex digits of the SHA-256 of the effective configuration, with
+/// the server tokens masked (API keys already print as `[REDACTED]`).
+/// Instances of the same arbstr version with the same settings share a
+/// hash; it is not comparable across versions.
+pub fn config_hash(config: &Config) -> String {
+    let mut config = config.clone();
+    for token in [
+        &mut config.server.auth_token,
+        &mut config.server.admin_token,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *token = "[REDACTED]".to_string();
+    }
+    let digest = Sha256::digest(format!("{:?}", config).as_bytes());
+    encode_hex(&digest[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_hash_tracks_settings_but_not_tokens() {
+        let config = Config::parse_str("[server]\nlisten = \"127.0.0.1:9000\"\n").unwrap();
+        let hash = config_hash(&config);
+        assert_eq!(hash.len(), 16);
+        assert_eq!(config_hash(&config.clone()), hash);
+
+        let mut with_token = config.clone();
+        with_token.server.auth_token = Some("secret-a".to_string());
+        let mut other_token = config.clone();
+        other_token.server.auth_token = Some("secret-b".to_string());
+        assert_ne!(config_hash(&with_token), hash);
+        assert_eq!(config_hash(&with_token), config_hash(&other_token));
+
+        let mut other = config;
+        other.server.listen = vec!["127.0.0.1:9001".to_string()];
+        assert_ne!(config_hash(&other), hash);
+    }
+}
@@ -0,0 +1,295 @@
+//! Response buffering for clients that can't parse SSE.
+//!
+//! With `X-Arbstr-Accumulate: true` (or `accumulate = true` on the matched
+//! policy), a non-streaming request is sent to the provider as a stream,
+//! and arbstr reads the stream to its end and answers with one
+//! `chat.completion` JSON body, so clients get the provider's streaming
+//! price and latency without handling SSE. Such requests take the
+//! streaming path (a single attempt, no response checks). Requests that
+//! ask for `stream: true` are streamed as usual, and the header value
+//! `false` opts a request out of its policy's setting.
+
+use std::collections::BTreeMap;
+
+use axum::{body::Body, http::HeaderMap};
+use serde_json::{Map, Value};
+
+use super::handlers::ARBSTR_ACCUMULATE_HEADER;
+use super::stream::SseEvents;
+use crate::error::{Error, ProviderErrorKind};
+
+/// Whether a request should be accumulated: the header when present,
+/// otherwise the policy's `accumulate`.
+pub(super) fn requested(headers: &HeaderMap, policy_accumulates: bool) -> bool {
+    match headers
+        .get(ARBSTR_ACCUMULATE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("true" | "1") => true,
+        Some("false" | "0") => false,
+        _ => policy_accumulates,
+    }
+}
+
+/// A streamed completion read to its end.
+#[derive(Debug)]
+pub(super) struct Accumulated {
+    /// The `chat.completion` body.
+    pub body: Value,
+    /// Prompt and completion tokens, when the stream reported usage.
+    pub usage: Option<(u32, u32)>,
+}
+
+/// Read an SSE chat completion body (as produced by the streaming path)
+/// into one non-streaming completion.
+///
+/// The body ends with arbstr's own `[DONE]`, so a stream the provider
+/// finished has two; with fewer the provider's stream was cut short and a
+/// `provider_malformed_response` error is returned. An `error` event from
+/// the provider is returned as a provider error.
+pub(super) async fn accumulate(body: Body) -> Result<Accumulated, Error> {
+    let mut completion = Completion::default();
+    let mut done = 0;
+    let mut events = SseEvents::new(body);
+    while let Some(data) = events.next().await {
+        if data == "[DONE]" {
+            done += 1;
+            continue;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(&data) else {
+            tracing::warn!(data = %data, "Skipping malformed SSE chunk while accumulating");
+            continue;
+        };
+        if let Some(error) = chunk.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(Error::ProviderFailed {
+                kind: ProviderErrorKind::Server,
+                message: format!("Provider stream failed: {}", message),
+            });
+        }
+        completion.add(&chunk);
+    }
+    if done < 2 {
+        return Err(Error::ProviderFailed {
+            kind: ProviderErrorKind::Malformed,
+            message: "Provider stream ended before completion".to_string(),
+        });
+    }
+    Ok(completion.finish())
+}
+
+/// Running state of the completion being assembled.
+#[derive(Debug, Default)]
+struct Completion {
+    /// `id`, `created`, `model` and `system_fingerprint`, from the first
+    /// chunk carrying each.
+    fields: Map<String, Value>,
+    choices: BTreeMap<u64, Choice>,
+    usage: Option<Value>,
+}
+
+#[derive(Debug, Default)]
+struct Choice {
+    role: Option<String>,
+    content: Option<String>,
+    /// Tool calls by their `index`, with `function.arguments` joined.
+    tool_calls: BTreeMap<u64, Value>,
+    finish_reason: Option<Value>,
+}
+
+impl Completion {
+    fn add(&mut self, chunk: &Value) {
+        for key in ["id", "created", "model", "system_fingerprint"] {
+            if let Some(value) = chunk.get(key).filter(|v| !v.is_null()) {
+                self.fields
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        if let Some(usage) = chunk.get("usage").filter(|v| v.is_object()) {
+            self.usage = Some(usage.clone());
+        }
+        // The arbstr trailing event has no choices and is left out; body
+        // metadata is added again for the JSON response
+        for delta_choice in chunk
+            .get("choices")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let index = delta_choice
+                .get("index")
+                .and_then(Value::as_u64)
+                .unwrap_or(0);
+            let choice = self.choices.entry(index).or_default();
+            if let Some(reason) = delta_choice.get("finish_reason").filter(|v| !v.is_null()) {
+                choice.finish_reason = Some(reason.clone());
+            }
+            let Some(delta) = delta_choice.get("delta") else {
+                continue;
+            };
+            if let Some(role) = delta.get("role").and_then(Value::as_str) {
+                choice.role = Some(role.to_string());
+            }
+            if let Some(content) = delta.get("content").and_then(Value::as_str) {
+                choice
+                    .content
+                    .get_or_insert_with(String::new)
+                    .push_str(content);
+            }
+            for call in delta
+                .get("tool_calls")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let call_index = call.get("index").and_then(Value::as_u64).unwrap_or(0);
+                merge_tool_call(choice.tool_calls.entry(call_index).or_default(), call);
+            }
+        }
+    }
+
+    fn finish(self) -> Accumulated {
+        let usage = self.usage.as_ref().and_then(|usage| {
+            let prompt = usage.get("prompt_tokens")?.as_u64()?;
+            let completion = usage.get("completion_tokens")?.as_u64()?;
+            Some((prompt as u32, completion as u32))
+        });
+        let choices: Vec<Value> = self
+            .choices
+            .into_iter()
+            .map(|(index, choice)| {
+                let mut message = Map::new();
+                message.insert(
+                    "role".to_string(),
+                    Value::from(choice.role.unwrap_or_else(|| "assistant".to_string())),
+                );
+                message.insert(
+                    "content".to_string(),
+                    choice.content.map(Value::from).unwrap_or(Value::Null),
+                );
+                if !choice.tool_calls.is_empty() {
+                    message.insert(
+                        "tool_calls".to_string(),
+                        Value::Array(choice.tool_calls.into_values().collect()),
+                    );
+                }
+                serde_json::json!({
+                    "index": index,
+                    "message": message,
+                    "finish_reason": choice.finish_reason,
+                })
+            })
+            .collect();
+
+        let mut body = self.fields;
+        body.insert("object".to_string(), Value::from("chat.completion"));
+        body.insert("choices".to_string(), Value::Array(choices));
+        if let Some(usage) = self.usage {
+            body.insert("usage".to_string(), usage);
+        }
+        Accumulated {
+            body: Value::Object(body),
+            usage,
+        }
+    }
+}
+
+/// Fold one streamed tool call fragment into the call assembled so far.
+fn merge_tool_call(call: &mut Value, delta: &Value) {
+    if !call.is_object() {
+        *call = serde_json::json!({"type": "function", "function": {"arguments": ""}});
+    }
+    for key in ["id", "type"] {
+        if let Some(value) = delta.get(key).filter(|v| !v.is_null()) {
+            call[key] = value.clone();
+        }
+    }
+    let Some(function) = delta.get("function") else {
+        return;
+    };
+    if let Some(name) = function.get("name").and_then(Value::as_str) {
+        call["function"]["name"] = Value::from(name);
+    }
+    if let Some(arguments) = function.get("arguments").and_then(Value::as_str) {
+        let joined = format!(
+            "{}{}",
+            call["function"]["arguments"].as_str().unwrap_or(""),
+            arguments
+        );
+        call["function"]["arguments"] = Value::from(joined);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sse(events: &[&str]) -> Body {
+        Body::from(
+            events
+                .iter()
+                .map(|e| format!("data: {}\n\n", e))
+                .collect::<String>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn joins_content_and_tool_calls() {
+        let body = sse(&[
+            r#"{"id":"c1","created":7,"model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"{"id":"c1","choices":[{"index":0,"delta":{"content":"lo"}}]}"#,
+            r#"{"id":"c1","choices":[{"index":1,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":"{\"q\":"}}]}}]}"#,
+            r#"{"id":"c1","choices":[{"index":1,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"x\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"c1","choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+            "[DONE]",
+            r#"{"arbstr":{"cost_sats":1.0}}"#,
+            "[DONE]",
+        ]);
+        let accumulated = accumulate(body).await.unwrap();
+        assert_eq!(accumulated.usage, Some((3, 2)));
+        let body = accumulated.body;
+        assert_eq!(body["id"], "c1");
+        assert_eq!(body["object"], "chat.completion");
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello");
+        assert_eq!(body["choices"][0]["finish_reason"], "stop");
+        let call = &body["choices"][1]["message"]["tool_calls"][0];
+        assert_eq!(call["id"], "call_1");
+        assert_eq!(call["function"]["name"], "lookup");
+        assert_eq!(call["function"]["arguments"], r#"{"q":"x"}"#);
+        assert!(body["choices"][1]["message"]["content"].is_null());
+        assert!(body.get("arbstr").is_none());
+    }
+
+    #[tokio::test]
+    async fn cut_short_streams_fail() {
+        let body = sse(&[
+            r#"{"id":"c1","choices":[{"index":0,"delta":{"content":"Hel"}}]}"#,
+            "[DONE]",
+        ]);
+        let err = accumulate(body).await.unwrap_err();
+        assert!(
+            err.to_string().contains("ended before completion"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn header_overrides_policy() {
+        let mut headers = HeaderMap::new();
+        assert!(!requested(&headers, false));
+        assert!(requested(&headers, true));
+        headers.insert(ARBSTR_ACCUMULATE_HEADER, "false".parse().unwrap());
+        assert!(!requested(&headers, true));
+        headers.insert(ARBSTR_ACCUMULATE_HEADER, "true".parse().unwrap());
+        assert!(requested(&headers, false));
+    }
+}
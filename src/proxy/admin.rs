@@ -0,0 +1,118 @@
+//! Operator endpoints under `/admin`.
+//!
+//! These routes act on the local database rather than proxying inference,
+//! and are protected by `server.admin_token` (or, without one,
+//! `server.auth_token`) whenever one is configured.
+
+use std::collections::BTreeMap;
+
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::Serialize;
+
+use super::server::AppState;
+use crate::config::LoggingMode;
+use crate::error::Error;
+use crate::storage::{backup, info, ConfirmedWriteStats};
+
+/// Response for GET /admin/db.
+#[derive(Debug, Serialize)]
+pub struct DbInfoResponse {
+    pub path: String,
+    /// Main database file size (None for in-memory databases).
+    pub file_size_bytes: Option<u64>,
+    /// Write-ahead log size (None when no WAL file exists).
+    pub wal_size_bytes: Option<u64>,
+    pub tables: BTreeMap<String, i64>,
+    pub requests: info::RequestSpan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_queue: Option<WriteQueueSection>,
+    pub migration: Option<info::MigrationInfo>,
+}
+
+/// Pending writes in the bounded DB writer channel.
+#[derive(Debug, Serialize)]
+pub struct WriteQueueSection {
+    pub depth: usize,
+    pub capacity: usize,
+    /// `logging.mode`.
+    pub mode: LoggingMode,
+    /// Writes requests waited for under strict logging, and the latency
+    /// that added.
+    pub confirmed: ConfirmedWriteStats,
+}
+
+/// Handle GET /admin/db -- database size, row counts, and writer backlog.
+pub async fn db_info_handler(State(state): State<AppState>) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .or(state.db.as_ref())
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let path = if state.config.ephemeral_storage() {
+        ":memory:".to_string()
+    } else {
+        state.config.database().path
+    };
+    let tables = info::table_row_counts(pool).await?;
+    let requests = info::request_span(pool).await?;
+    let migration = info::last_migration(pool).await?;
+
+    Ok(Json(DbInfoResponse {
+        file_size_bytes: info::file_size(&path).await,
+        wal_size_bytes: info::file_size(&format!("{}-wal", path)).await,
+        path,
+        tables,
+        requests,
+        write_queue: state.db_writer.as_ref().map(|w| WriteQueueSection {
+            depth: w.queue_depth(),
+            capacity: w.queue_capacity(),
+            mode: state.config.logging.mode,
+            confirmed: w.confirmed_stats(),
+        }),
+        migration,
+    }))
+}
+
+/// Handle POST /admin/db/backup -- write an online backup and rotate.
+///
+/// Backups go to `[database.backup].dir`; the request cannot choose a path.
+pub async fn db_backup_handler(State(state): State<AppState>) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let backup_config = state.config.database().backup.ok_or_else(|| {
+        Error::BadRequest(
+            "Backups are not configured. Set [database.backup] dir in config".to_string(),
+        )
+    })?;
+
+    let result = backup::run_backup(pool, &backup_config)
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?;
+
+    tracing::info!(
+        path = %result.path,
+        size_bytes = result.size_bytes,
+        duration_ms = result.duration_ms,
+        rotated = result.rotated.len(),
+        "On-demand database backup complete"
+    );
+
+    Ok(Json(result))
+}
+
+/// Handle POST /admin/db/checkpoint -- fold the WAL back into the main file.
+pub async fn db_checkpoint_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let result = backup::checkpoint(pool).await?;
+    Ok(Json(result))
+}
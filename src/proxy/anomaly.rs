@@ -0,0 +1,339 @@
+//! Cost anomaly detection (`[anomaly]`).
+//!
+//! A background task buckets recent spend by model, provider, and tenant
+//! (the `tenant_tag` request tag) every `interval_secs`. The newest bucket
+//! of each series is compared with the `baseline_buckets` before it: spend
+//! above the baseline mean plus `z_threshold` standard deviations, and at
+//! least `min_cost_sats`, is an anomaly. New anomalies are logged and
+//! POSTed to `webhook_url`, active ones are listed in `/v1/stats`, and an
+//! anomaly whose series falls back inside its band is reported as resolved.
+//! State is in memory and resets on restart.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::server::AppState;
+use crate::config::AnomalyConfig;
+use crate::storage::stats::{query_spend_buckets, SpendBucketRow, SpendGrouping};
+
+/// Timeout for the alert webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A series whose latest spend is outside its baseline band.
+#[derive(Debug, Clone, Serialize)]
+pub struct CostAnomaly {
+    /// `model`, `provider`, or `tenant`.
+    pub dimension: &'static str,
+    pub key: String,
+    /// Spend in the latest bucket.
+    pub cost_sats: f64,
+    pub baseline_mean_sats: f64,
+    pub baseline_stddev_sats: f64,
+    /// Standard deviations above the mean (null for a flat baseline).
+    pub z_score: Option<f64>,
+    /// Spend above which the bucket is anomalous.
+    pub threshold_sats: f64,
+    pub bucket_minutes: u64,
+    /// When the anomaly was first detected (RFC 3339).
+    pub detected_at: String,
+}
+
+/// Anomalies currently active, keyed by dimension and series.
+#[derive(Debug, Default)]
+pub struct CostAnomalies {
+    active: Mutex<BTreeMap<(&'static str, String), CostAnomaly>>,
+}
+
+impl CostAnomalies {
+    /// Active anomalies, by dimension then key.
+    pub fn active(&self) -> Vec<CostAnomaly> {
+        self.active
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Replace the active set with `found`, keeping the detection time of
+    /// anomalies still in progress. Returns those that started and those
+    /// that resolved.
+    fn update(&self, found: Vec<CostAnomaly>) -> (Vec<CostAnomaly>, Vec<CostAnomaly>) {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let mut previous = std::mem::take(&mut *active);
+        let mut started = Vec::new();
+        for mut anomaly in found {
+            let key = (anomaly.dimension, anomaly.key.clone());
+            match previous.remove(&key) {
+                Some(earlier) => anomaly.detected_at = earlier.detected_at,
+                None => started.push(anomaly.clone()),
+            }
+            active.insert(key, anomaly);
+        }
+        (started, previous.into_values().collect())
+    }
+}
+
+/// Check the latest bucket of each series in `rows` against its baseline.
+///
+/// Bucket 0 is the latest; buckets 1 to `baseline_buckets` form the
+/// baseline, with missing buckets counted as no spend.
+pub fn detect(
+    config: &AnomalyConfig,
+    dimension: &'static str,
+    rows: &[SpendBucketRow],
+) -> Vec<CostAnomaly> {
+    let baseline_len = config.baseline_buckets as usize;
+    let mut series: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for row in rows {
+        let Ok(bucket) = usize::try_from(row.bucket) else {
+            continue;
+        };
+        if bucket > baseline_len {
+            continue;
+        }
+        series
+            .entry(row.group_key.as_str())
+            .or_insert_with(|| vec![0.0; baseline_len + 1])[bucket] += row.cost_sats;
+    }
+
+    let detected_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    series
+        .into_iter()
+        .filter_map(|(key, buckets)| {
+            let cost = buckets[0];
+            let baseline = &buckets[1..];
+            let mean = baseline.iter().sum::<f64>() / baseline_len as f64;
+            let variance =
+                baseline.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / baseline_len as f64;
+            let stddev = variance.sqrt();
+            let threshold = mean + config.z_threshold * stddev;
+            (cost >= config.min_cost_sats && cost > threshold).then(|| CostAnomaly {
+                dimension,
+                key: key.to_string(),
+                cost_sats: cost,
+                baseline_mean_sats: mean,
+                baseline_stddev_sats: stddev,
+                z_score: (stddev > 0.0).then(|| (cost - mean) / stddev),
+                threshold_sats: threshold,
+                bucket_minutes: config.bucket_minutes,
+                detected_at: detected_at.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Run one check over every dimension, alerting on anomalies that started
+/// or resolved since the previous one.
+pub async fn check(
+    state: &AppState,
+    pool: &SqlitePool,
+    config: &AnomalyConfig,
+) -> Result<(), sqlx::Error> {
+    let bucket = chrono::Duration::minutes(config.bucket_minutes as i64);
+    let until = Utc::now();
+    let since = until - bucket * (config.baseline_buckets as i32 + 1);
+    let (since, until) = (since.to_rfc3339(), until.to_rfc3339());
+    let buckets_per_day = (1440 / config.bucket_minutes) as i64;
+
+    let mut dimensions = vec![
+        ("model", SpendGrouping::Model),
+        ("provider", SpendGrouping::Provider),
+    ];
+    if let Some(tag) = config.tenant_tag.as_deref() {
+        dimensions.push(("tenant", SpendGrouping::Tag(tag)));
+    }
+
+    let mut found = Vec::new();
+    for (dimension, grouping) in dimensions {
+        let rows = query_spend_buckets(
+            pool,
+            &since,
+            &until,
+            buckets_per_day,
+            grouping,
+            None,
+            None,
+            None,
+        )
+        .await?;
+        found.extend(detect(config, dimension, &rows));
+    }
+
+    let (started, resolved) = state.anomalies.update(found);
+    for anomaly in &started {
+        alert(state, config, anomaly, true);
+    }
+    for anomaly in &resolved {
+        alert(state, config, anomaly, false);
+    }
+    Ok(())
+}
+
+/// Background task: check for anomalies every `interval_secs`.
+pub async fn anomaly_loop(
+    state: AppState,
+    pool: SqlitePool,
+    config: AnomalyConfig,
+    cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if let Err(e) = check(&state, &pool, &config).await {
+                    tracing::warn!(error = %e, "Cost anomaly check failed");
+                }
+            }
+            _ = super::vault::cancel_wait(&cancel) => {
+                tracing::info!("Cost anomaly task shutting down");
+                break;
+            }
+        }
+    }
+}
+
+/// Alert body POSTed to the webhook.
+#[derive(Debug, Serialize)]
+struct Alert<'a> {
+    /// `cost_anomaly` or `cost_anomaly_resolved`.
+    event: &'static str,
+    #[serde(flatten)]
+    anomaly: &'a CostAnomaly,
+    message: String,
+}
+
+/// Log an anomaly (or its end) and POST it to the configured webhook.
+fn alert(state: &AppState, config: &AnomalyConfig, anomaly: &CostAnomaly, started: bool) {
+    let message = if started {
+        format!(
+            "Spend on {} '{}' is {:.0} sats in the last {} minutes, above its baseline of {:.0} sats",
+            anomaly.dimension,
+            anomaly.key,
+            anomaly.cost_sats,
+            config.bucket_minutes,
+            anomaly.baseline_mean_sats
+        )
+    } else {
+        format!(
+            "Spend on {} '{}' is back within its baseline",
+            anomaly.dimension, anomaly.key
+        )
+    };
+    if started {
+        tracing::warn!(dimension = anomaly.dimension, key = %anomaly.key, cost_sats = anomaly.cost_sats, z_score = ?anomaly.z_score, "{}", message);
+    } else {
+        tracing::info!(dimension = anomaly.dimension, key = %anomaly.key, "{}", message);
+    }
+
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+    let request = state
+        .http_client
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&Alert {
+            event: if started {
+                "cost_anomaly"
+            } else {
+                "cost_anomaly_resolved"
+            },
+            anomaly,
+            message,
+        });
+    tokio::spawn(async move {
+        match request.send().await {
+            Ok(r) if r.status().is_success() => {}
+            Ok(r) => tracing::warn!(status = %r.status(), "Cost anomaly webhook failed"),
+            Err(e) => tracing::warn!(error = %e, "Cost anomaly webhook failed"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AnomalyConfig {
+        AnomalyConfig {
+            interval_secs: 300,
+            bucket_minutes: 60,
+            baseline_buckets: 4,
+            z_threshold: 3.0,
+            min_cost_sats: 100.0,
+            tenant_tag: None,
+            webhook_url: None,
+        }
+    }
+
+    fn row(bucket: i64, key: &str, cost_sats: f64) -> SpendBucketRow {
+        SpendBucketRow {
+            bucket,
+            group_key: key.to_string(),
+            cost_sats,
+        }
+    }
+
+    #[test]
+    fn flags_spikes_above_the_band_only() {
+        let rows = vec![
+            // Steady 100-140 sats/hour, then 1000
+            row(0, "gpt-4o", 1000.0),
+            row(1, "gpt-4o", 100.0),
+            row(2, "gpt-4o", 140.0),
+            row(3, "gpt-4o", 100.0),
+            row(4, "gpt-4o", 140.0),
+            // Noisy but within its band
+            row(0, "gpt-4o-mini", 300.0),
+            row(1, "gpt-4o-mini", 50.0),
+            row(2, "gpt-4o-mini", 400.0),
+            // New spend below min_cost_sats
+            row(0, "claude", 50.0),
+            // Outside the window
+            row(5, "gpt-4o", 5000.0),
+        ];
+        let anomalies = detect(&config(), "model", &rows);
+        assert_eq!(anomalies.len(), 1);
+        let anomaly = &anomalies[0];
+        assert_eq!(anomaly.key, "gpt-4o");
+        assert_eq!(anomaly.baseline_mean_sats, 120.0);
+        assert_eq!(anomaly.baseline_stddev_sats, 20.0);
+        assert_eq!(anomaly.threshold_sats, 180.0);
+        assert_eq!(anomaly.z_score, Some(44.0));
+    }
+
+    #[test]
+    fn flat_baseline_has_no_z_score() {
+        let anomalies = detect(&config(), "tenant", &[row(0, "team-a", 500.0)]);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].threshold_sats, 0.0);
+        assert_eq!(anomalies[0].z_score, None);
+    }
+
+    #[test]
+    fn update_reports_starts_and_resolutions() {
+        let anomalies = CostAnomalies::default();
+        let spike = |key: &str, detected_at: &str| CostAnomaly {
+            detected_at: detected_at.to_string(),
+            ..detect(&config(), "model", &[row(0, key, 500.0)])[0].clone()
+        };
+
+        let (started, resolved) = anomalies.update(vec![spike("a", "t1"), spike("b", "t1")]);
+        assert_eq!((started.len(), resolved.len()), (2, 0));
+
+        let (started, resolved) = anomalies.update(vec![spike("b", "t2"), spike("c", "t2")]);
+        assert_eq!(started[0].key, "c");
+        assert_eq!(resolved[0].key, "a");
+        let active = anomalies.active();
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[0].key, "b");
+        assert_eq!(active[0].detected_at, "t1");
+    }
+}
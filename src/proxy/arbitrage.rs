@@ -0,0 +1,380 @@
+//! Arbitrage opportunity detection (`GET /v1/arbitrage`, `[arbitrage]`).
+//!
+//! Recent successful traffic for each model is re-priced at every healthy
+//! provider that serves it (circuit not open, not auth-quarantined). When
+//! moving the model's traffic to the cheapest of them would save at least
+//! `min_savings_sats_per_day`, the model is reported as an opportunity,
+//! e.g. because a policy, canary, or reputation demotion kept traffic on a
+//! dearer provider. With `enabled = true` the analysis also runs in the
+//! background and each new opportunity is logged and sent to `webhook_url`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::circuit_breaker::CircuitState;
+use super::server::AppState;
+use crate::config::{ArbitrageConfig, ProviderConfig, MAX_ARBITRAGE_WINDOW_HOURS};
+use crate::error::Error;
+use crate::storage::stats::{query_grouped_by_model_provider, ModelProviderRow};
+
+/// Timeout for the alert webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Query parameters for GET /v1/arbitrage.
+#[derive(Debug, Deserialize)]
+pub struct ArbitrageQuery {
+    /// Hours of traffic to analyse; defaults to `[arbitrage] window_hours`.
+    pub window_hours: Option<u64>,
+}
+
+/// Response for GET /v1/arbitrage.
+#[derive(Debug, Serialize)]
+pub struct ArbitrageReport {
+    pub since: String,
+    pub until: String,
+    pub window_hours: u64,
+    pub min_savings_sats_per_day: f64,
+    /// Models whose projected savings reach the threshold.
+    pub opportunities: usize,
+    /// Every model with successful traffic, largest savings first.
+    pub models: Vec<ModelArbitrage>,
+}
+
+/// Cost analysis for one model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelArbitrage {
+    pub model: String,
+    pub requests: i64,
+    pub cost_sats: f64,
+    /// Cheapest healthy provider serving the model, if any.
+    pub cheapest_provider: Option<String>,
+    /// The same traffic priced at the cheapest provider's rates.
+    pub cheapest_cost_sats: Option<f64>,
+    pub savings_sats_per_day: f64,
+    pub opportunity: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub providers: Vec<ProviderShare>,
+}
+
+/// One provider's share of a model's traffic.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderShare {
+    pub provider: String,
+    pub requests: i64,
+    /// Fraction of the model's successful requests (0.0-1.0).
+    pub share: f64,
+    pub cost_sats: f64,
+}
+
+/// Cost of the given traffic at `provider`'s rates.
+///
+/// Only totals are known, so the minimum charge is applied to the traffic as
+/// a whole (`requests * min_charge_sats`) rather than per request.
+fn reprice(provider: &ProviderConfig, input_tokens: f64, output_tokens: f64, requests: i64) -> f64 {
+    let cost = (input_tokens * provider.input_rate + output_tokens * provider.output_rate) / 1000.0
+        + requests as f64 * provider.base_fee;
+    cost.max(requests as f64 * provider.min_charge_sats)
+}
+
+/// Analyse per-(model, provider) traffic from a `window_hours` window.
+///
+/// `healthy` says whether a provider could take traffic right now.
+pub fn analyze(
+    providers: &[ProviderConfig],
+    rows: &[ModelProviderRow],
+    window_hours: u64,
+    min_savings_sats_per_day: f64,
+    healthy: impl Fn(&str) -> bool,
+) -> Vec<ModelArbitrage> {
+    let mut by_model: BTreeMap<&str, Vec<&ModelProviderRow>> = BTreeMap::new();
+    for row in rows {
+        by_model.entry(row.model.as_str()).or_default().push(row);
+    }
+
+    let mut models: Vec<ModelArbitrage> = by_model
+        .into_iter()
+        .map(|(model, rows)| {
+            let requests: i64 = rows.iter().map(|r| r.success_count).sum();
+            let cost_sats: f64 = rows.iter().map(|r| r.total_cost_sats).sum();
+            let input: f64 = rows.iter().map(|r| r.total_input_tokens).sum();
+            let output: f64 = rows.iter().map(|r| r.total_output_tokens).sum();
+
+            let cheapest = providers
+                .iter()
+                .filter(|p| p.models.iter().any(|m| m == model) && healthy(&p.name))
+                .map(|p| (p, reprice(p, input, output, requests)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let savings = cheapest.map_or(0.0, |(_, cost)| (cost_sats - cost).max(0.0));
+            let savings_sats_per_day = savings * 24.0 / window_hours as f64;
+            let opportunity =
+                cheapest.is_some() && savings_sats_per_day >= min_savings_sats_per_day;
+
+            let mut shares: Vec<ProviderShare> = rows
+                .iter()
+                .map(|r| ProviderShare {
+                    provider: r.provider.clone(),
+                    requests: r.success_count,
+                    share: r.success_count as f64 / requests.max(1) as f64,
+                    cost_sats: r.total_cost_sats,
+                })
+                .collect();
+            shares.sort_by_key(|s| std::cmp::Reverse(s.requests));
+
+            let message = cheapest.filter(|_| opportunity).map(|(cheap, _)| {
+                let advice = format!(
+                    "routing {} to {} would save ~{:.0} sats/day",
+                    model, cheap.name, savings_sats_per_day
+                );
+                match shares.iter().find(|s| s.provider != cheap.name) {
+                    Some(dearer) => format!(
+                        "{} served {:.0}% of {} traffic while {} was healthy; {}",
+                        dearer.provider,
+                        dearer.share * 100.0,
+                        model,
+                        cheap.name,
+                        advice
+                    ),
+                    None => advice,
+                }
+            });
+
+            ModelArbitrage {
+                model: model.to_string(),
+                requests,
+                cost_sats,
+                cheapest_provider: cheapest.map(|(p, _)| p.name.clone()),
+                cheapest_cost_sats: cheapest.map(|(_, cost)| cost),
+                savings_sats_per_day,
+                opportunity,
+                message,
+                providers: shares,
+            }
+        })
+        .collect();
+    models.sort_by(|a, b| b.savings_sats_per_day.total_cmp(&a.savings_sats_per_day));
+    models
+}
+
+/// Build the report for the `window_hours` ending now.
+pub async fn build_report(
+    state: &AppState,
+    pool: &SqlitePool,
+    window_hours: u64,
+) -> Result<ArbitrageReport, sqlx::Error> {
+    let config = &state.config.arbitrage;
+    let until = Utc::now();
+    let since = until - chrono::Duration::hours(window_hours as i64);
+    let since = since.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let until = until.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let rows = query_grouped_by_model_provider(pool, &since, &until).await?;
+    let models = analyze(
+        state.router.providers(),
+        &rows,
+        window_hours,
+        config.min_savings_sats_per_day,
+        |name| {
+            state.circuit_breakers.state(name) != Some(CircuitState::Open)
+                && !state.maintenance.is_unavailable(name)
+                && !state.auth_quarantine.is_quarantined(name)
+        },
+    );
+    Ok(ArbitrageReport {
+        since,
+        until,
+        window_hours,
+        min_savings_sats_per_day: config.min_savings_sats_per_day,
+        opportunities: models.iter().filter(|m| m.opportunity).count(),
+        models,
+    })
+}
+
+/// Handle GET /v1/arbitrage.
+pub async fn arbitrage_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ArbitrageQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+    let window_hours = params
+        .window_hours
+        .unwrap_or(state.config.arbitrage.window_hours);
+    if !(1..=MAX_ARBITRAGE_WINDOW_HOURS).contains(&window_hours) {
+        return Err(Error::BadRequest(format!(
+            "window_hours must be 1-{}",
+            MAX_ARBITRAGE_WINDOW_HOURS
+        )));
+    }
+    Ok(Json(build_report(&state, pool, window_hours).await?))
+}
+
+/// Alert body POSTed to the webhook.
+#[derive(Debug, Serialize)]
+struct Alert<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    model: &'a ModelArbitrage,
+}
+
+/// Background task: analyse every `interval_secs` and alert on models that
+/// became opportunities since the previous analysis.
+pub async fn arbitrage_loop(
+    state: AppState,
+    pool: SqlitePool,
+    config: ArbitrageConfig,
+    cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // Models already alerted on; cleared once they stop being opportunities
+    let mut alerted: HashSet<String> = HashSet::new();
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                match build_report(&state, &pool, config.window_hours).await {
+                    Ok(report) => {
+                        let current: HashSet<String> = report
+                            .models
+                            .iter()
+                            .filter(|m| m.opportunity)
+                            .map(|m| m.model.clone())
+                            .collect();
+                        for model in report.models.iter().filter(|m| m.opportunity) {
+                            if !alerted.contains(&model.model) {
+                                alert(&state, &config, model);
+                            }
+                        }
+                        alerted = current;
+                    }
+                    Err(e) => tracing::warn!(error = %e, "Arbitrage analysis failed"),
+                }
+            }
+            _ = super::vault::cancel_wait(&cancel) => {
+                tracing::info!("Arbitrage task shutting down");
+                break;
+            }
+        }
+    }
+}
+
+fn alert(state: &AppState, config: &ArbitrageConfig, model: &ModelArbitrage) {
+    tracing::warn!(
+        model = %model.model,
+        cheapest_provider = ?model.cheapest_provider,
+        savings_sats_per_day = model.savings_sats_per_day,
+        "{}",
+        model.message.as_deref().unwrap_or("Arbitrage opportunity")
+    );
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+    let request = state
+        .http_client
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&Alert {
+            event: "arbitrage_opportunity",
+            model,
+        });
+    tokio::spawn(async move {
+        match request.send().await {
+            Ok(r) if r.status().is_success() => {}
+            Ok(r) => tracing::warn!(status = %r.status(), "Arbitrage alert webhook failed"),
+            Err(e) => tracing::warn!(error = %e, "Arbitrage alert webhook failed"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, output_rate: f64) -> ProviderConfig {
+        ProviderConfig {
+            models: vec!["gpt-4o".to_string()],
+            output_rate,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    fn row(
+        provider: &str,
+        requests: i64,
+        output_tokens: f64,
+        output_rate: f64,
+    ) -> ModelProviderRow {
+        ModelProviderRow {
+            model: "gpt-4o".to_string(),
+            provider: provider.to_string(),
+            success_count: requests,
+            total_cost_sats: output_tokens * output_rate / 1000.0,
+            total_input_tokens: 0.0,
+            total_output_tokens: output_tokens,
+        }
+    }
+
+    #[test]
+    fn traffic_on_a_dearer_provider_is_an_opportunity() {
+        let providers = [
+            provider("mock-expensive", 30.0),
+            provider("mock-cheap", 10.0),
+        ];
+        // 60 requests / 600k output tokens on expensive, 40 / 400k on cheap
+        let rows = [
+            row("mock-cheap", 40, 400_000.0, 10.0),
+            row("mock-expensive", 60, 600_000.0, 30.0),
+        ];
+
+        let models = analyze(&providers, &rows, 24, 100.0, |_| true);
+        let model = &models[0];
+        assert_eq!(model.requests, 100);
+        assert_eq!(model.cost_sats, 22_000.0);
+        assert_eq!(model.cheapest_provider.as_deref(), Some("mock-cheap"));
+        assert_eq!(model.cheapest_cost_sats, Some(10_000.0));
+        assert_eq!(model.savings_sats_per_day, 12_000.0);
+        assert!(model.opportunity);
+        assert_eq!(model.providers[0].provider, "mock-expensive");
+        assert_eq!(model.providers[0].share, 0.6);
+        assert!(model.message.as_deref().unwrap().starts_with(
+            "mock-expensive served 60% of gpt-4o traffic while mock-cheap was healthy"
+        ));
+
+        // A 48h window halves the daily figure
+        let models = analyze(&providers, &rows, 48, 100.0, |_| true);
+        assert_eq!(models[0].savings_sats_per_day, 6_000.0);
+    }
+
+    #[test]
+    fn unhealthy_providers_are_not_alternatives() {
+        let providers = [
+            provider("mock-expensive", 30.0),
+            provider("mock-cheap", 10.0),
+        ];
+        let rows = [row("mock-expensive", 60, 600_000.0, 30.0)];
+
+        let models = analyze(&providers, &rows, 24, 100.0, |name| name != "mock-cheap");
+        assert_eq!(
+            models[0].cheapest_provider.as_deref(),
+            Some("mock-expensive")
+        );
+        assert_eq!(models[0].savings_sats_per_day, 0.0);
+        assert!(!models[0].opportunity);
+        assert!(models[0].message.is_none());
+
+        // Below the threshold is reported but not flagged
+        let models = analyze(&providers, &rows, 24, 1e9, |_| true);
+        assert!(!models[0].opportunity);
+    }
+}
@@ -0,0 +1,297 @@
+//! Pass-through of large request bodies (`server.stream_body_threshold_bytes`).
+//!
+//! Instead of buffering a big (typically multimodal) request to parse it,
+//! arbstr reads only until it has seen the top-level `model` key, routes on
+//! that, and streams the buffered prefix plus the rest of the client body to
+//! the provider unchanged. [`BodyScanner`] does the partial parsing. A
+//! request with a top-level `response_format` in the bytes read is buffered
+//! instead, since structured output narrows the providers it may go to and
+//! its response is checked.
+
+use axum::http::{header, HeaderMap};
+use bytes::Bytes;
+
+/// Most bytes read while looking for `model` before giving up and
+/// buffering the whole request instead.
+pub const MAX_PREFIX_BYTES: usize = 64 * 1024;
+
+/// Longest top-level key worth capturing; longer keys are not `model`,
+/// `stream` or `response_format`, so only their length matters.
+const MAX_KEY_LEN: usize = 16;
+
+/// Whether a request with these headers has its body streamed through.
+///
+/// Needs a threshold, a JSON body with a `Content-Length` above it, no
+/// vault billing and no voucher (reservations and voucher balance checks
+/// are sized from the parsed messages).
+pub fn should_stream(threshold: Option<u64>, vault_enabled: bool, headers: &HeaderMap) -> bool {
+    let Some(threshold) = threshold else {
+        return false;
+    };
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let voucher = headers.contains_key(super::vouchers::VOUCHER_HEADER);
+    !vault_enabled && !voucher && is_json && length.is_some_and(|len| len > threshold)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Phase {
+    /// Expecting a key (after `{` or `,`).
+    #[default]
+    Key,
+    /// Key read, expecting `:`.
+    Colon,
+    /// Expecting or reading a value.
+    Value,
+    /// Value finished, expecting `,` or `}`.
+    AfterValue,
+}
+
+/// Incremental scanner that extracts the top-level `model` string and
+/// `stream` flag, and notes a top-level `response_format`, from a JSON
+/// object fed to it in arbitrary chunks, without materialising anything
+/// else.
+#[derive(Debug, Default)]
+pub struct BodyScanner {
+    depth: usize,
+    phase: Phase,
+    in_string: bool,
+    escaped: bool,
+    key: Vec<u8>,
+    key_truncated: bool,
+    value: Vec<u8>,
+    model: Option<String>,
+    stream: Option<bool>,
+    response_format: bool,
+}
+
+impl BodyScanner {
+    /// The top-level `model`, once seen.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// The top-level `stream` flag, if seen so far.
+    pub fn stream(&self) -> Option<bool> {
+        self.stream
+    }
+
+    /// Whether a top-level `response_format` key has been seen so far.
+    pub fn has_response_format(&self) -> bool {
+        self.response_format
+    }
+
+    /// Scan the next chunk of the body.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.in_string {
+                self.string_byte(b);
+                continue;
+            }
+            match b {
+                b'"' => {
+                    self.in_string = true;
+                    if self.depth == 1 && self.phase == Phase::Key {
+                        self.key.clear();
+                        self.key_truncated = false;
+                    }
+                    if self.depth == 1 && self.phase == Phase::Value {
+                        self.value.clear();
+                    }
+                }
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    if self.depth == 1 {
+                        self.phase = Phase::Key;
+                    }
+                }
+                b'}' | b']' => {
+                    if self.depth == 1 {
+                        self.finish_literal();
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 1 {
+                        self.phase = Phase::AfterValue;
+                    }
+                }
+                b':' if self.depth == 1 && self.phase == Phase::Colon => {
+                    self.phase = Phase::Value;
+                    self.value.clear();
+                }
+                b',' if self.depth == 1 => {
+                    self.finish_literal();
+                    self.phase = Phase::Key;
+                }
+                b' ' | b'\t' | b'\r' | b'\n' if self.depth == 1 && !self.value.is_empty() => {
+                    self.finish_literal();
+                }
+                b' ' | b'\t' | b'\r' | b'\n' => {}
+                _ if self.depth == 1 && self.phase == Phase::Value => self.value.push(b),
+                _ => {}
+            }
+        }
+    }
+
+    fn string_byte(&mut self, b: u8) {
+        let capture = self.depth == 1;
+        if self.escaped {
+            self.escaped = false;
+        } else if b == b'\\' {
+            self.escaped = true;
+        } else if b == b'"' {
+            self.in_string = false;
+            if capture {
+                self.end_string();
+            }
+            return;
+        }
+        if !capture {
+            return;
+        }
+        match self.phase {
+            Phase::Key if self.key.len() < MAX_KEY_LEN => self.key.push(b),
+            Phase::Key => self.key_truncated = true,
+            Phase::Value if self.key_is("model") => self.value.push(b),
+            _ => {}
+        }
+    }
+
+    fn end_string(&mut self) {
+        match self.phase {
+            Phase::Key => {
+                self.response_format |= self.key_is("response_format");
+                self.phase = Phase::Colon;
+            }
+            Phase::Value => {
+                if self.key_is("model") {
+                    // Re-quote so escapes are decoded by serde_json
+                    let mut quoted = Vec::with_capacity(self.value.len() + 2);
+                    quoted.push(b'"');
+                    quoted.extend_from_slice(&self.value);
+                    quoted.push(b'"');
+                    self.model = serde_json::from_slice(&quoted).ok();
+                }
+                self.value.clear();
+                self.phase = Phase::AfterValue;
+            }
+            _ => {}
+        }
+    }
+
+    /// End a bare top-level value (`true`, `false`, numbers, `null`).
+    fn finish_literal(&mut self) {
+        if self.phase != Phase::Value || self.value.is_empty() {
+            return;
+        }
+        if self.key_is("stream") {
+            self.stream = match self.value.as_slice() {
+                b"true" => Some(true),
+                b"false" | b"null" => Some(false),
+                _ => None,
+            };
+        }
+        self.value.clear();
+        self.phase = Phase::AfterValue;
+    }
+
+    fn key_is(&self, name: &str) -> bool {
+        !self.key_truncated && self.key == name.as_bytes()
+    }
+}
+
+/// Chain the chunks read while scanning back in front of the rest of the body.
+pub fn rejoin<S, E>(prefix: Vec<Bytes>, rest: S) -> impl futures::Stream<Item = Result<Bytes, E>>
+where
+    S: futures::Stream<Item = Result<Bytes, E>>,
+{
+    use futures::StreamExt;
+    futures::stream::iter(prefix.into_iter().map(Ok)).chain(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(body: &str, chunk_size: usize) -> BodyScanner {
+        let mut scanner = BodyScanner::default();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            scanner.feed(chunk);
+        }
+        scanner
+    }
+
+    #[test]
+    fn finds_model_and_stream_at_any_chunking() {
+        let body = r#"{"model": "gpt-4o", "stream": true, "messages": [{"role": "user", "content": "hi"}]}"#;
+        for size in [1, 3, 7, body.len()] {
+            let scanner = scan(body, size);
+            assert_eq!(scanner.model(), Some("gpt-4o"), "chunk size {}", size);
+            assert_eq!(scanner.stream(), Some(true), "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn ignores_nested_keys() {
+        let body = r#"{"messages":[{"role":"user","content":"x","model":"nested","stream":true}],"metadata":{"model":"also-nested"},"stream":false,"model":"claude-3.5-sonnet"}"#;
+        let scanner = scan(body, 5);
+        assert_eq!(scanner.model(), Some("claude-3.5-sonnet"));
+        assert_eq!(scanner.stream(), Some(false));
+    }
+
+    #[test]
+    fn handles_escapes_and_brackets_in_strings() {
+        let body = r#"{"messages":[{"content":"a \"quoted\" } ] { [ \\"}],"model":"gpt-\u0034o"}"#;
+        let scanner = scan(body, 2);
+        assert_eq!(scanner.model(), Some("gpt-4o"));
+        assert_eq!(scanner.stream(), None);
+    }
+
+    #[test]
+    fn notes_top_level_response_format() {
+        let body = r#"{"model":"gpt-4o","response_format":{"type":"json_object"},"messages":[]}"#;
+        assert!(scan(body, 3).has_response_format());
+        let body = r#"{"model":"gpt-4o","messages":[{"content":"x","response_format":1}],"metadata":{"response_format":2}}"#;
+        assert!(!scan(body, 3).has_response_format());
+    }
+
+    #[test]
+    fn model_not_yet_seen() {
+        let scanner = scan(r#"{"messages":[{"role":"user","content":"long"#, 4);
+        assert_eq!(scanner.model(), None);
+    }
+
+    #[test]
+    fn streams_only_large_json_without_vault() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        headers.insert(header::CONTENT_LENGTH, "2048".parse().unwrap());
+        assert!(should_stream(Some(1024), false, &headers));
+        assert!(!should_stream(Some(4096), false, &headers));
+        assert!(!should_stream(None, false, &headers));
+        assert!(!should_stream(Some(1024), true, &headers));
+
+        let mut with_voucher = headers.clone();
+        with_voucher.insert(
+            crate::proxy::vouchers::VOUCHER_HEADER,
+            "v-1".parse().unwrap(),
+        );
+        assert!(!should_stream(Some(1024), false, &with_voucher));
+
+        headers.remove(header::CONTENT_LENGTH);
+        assert!(!should_stream(Some(1024), false, &headers));
+    }
+
+    #[test]
+    fn stream_literal_before_closing_brace() {
+        let scanner = scan(r#"{"model":"m","stream":true}"#, 1);
+        assert_eq!(scanner.stream(), Some(true));
+        let scanner = scan("{\"stream\" :\n  false ,\"model\":\"m\"}", 1);
+        assert_eq!(scanner.stream(), Some(false));
+    }
+}
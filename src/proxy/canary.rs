@@ -0,0 +1,380 @@
+//! Canary onboarding for new providers.
+//!
+//! A provider marked `canary = true` only receives `canary_percent` of the
+//! requests it is eligible for. Each request it serves is recorded; once
+//! its success rate over the last `[routing.canary].min_requests` requests
+//! reaches `min_success_rate` it is promoted and routed like any other
+//! provider (or, with `auto_promote = false`, a warning is logged so an
+//! operator can promote it in config).
+
+use std::collections::VecDeque;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::config::{CanaryConfig, ProviderConfig};
+use crate::router::SelectedProvider;
+use crate::storage::routing_state;
+
+/// `routing_state` kind for saved canary progress.
+const STATE_KIND: &str = "canary";
+
+/// A canary's progress as saved across restarts.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCanary {
+    seen: u64,
+    outcomes: Vec<bool>,
+    promoted: bool,
+}
+
+/// Per-canary sampling counter and outcome window.
+#[derive(Debug)]
+struct CanaryState {
+    percent: u8,
+    /// Eligible requests seen, used to spread the canary slice evenly.
+    seen: u64,
+    outcomes: VecDeque<bool>,
+    promoted: bool,
+    ready_logged: bool,
+}
+
+impl CanaryState {
+    fn success_rate(&self) -> Option<f64> {
+        if self.outcomes.is_empty() {
+            return None;
+        }
+        let successes = self.outcomes.iter().filter(|s| **s).count();
+        Some(successes as f64 / self.outcomes.len() as f64)
+    }
+
+    /// Whether the next eligible request falls in the canary slice.
+    ///
+    /// Uses the integer floor of `seen * percent / 100`, which ticks over
+    /// exactly `percent` times per 100 requests, evenly spaced.
+    fn take_slice(&mut self) -> bool {
+        let before = self.seen * self.percent as u64 / 100;
+        self.seen += 1;
+        let after = self.seen * self.percent as u64 / 100;
+        after > before
+    }
+}
+
+/// Canary status of one provider, for `/providers` and `/v1/route/explain`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryStatus {
+    pub percent: u8,
+    pub promoted: bool,
+    /// Outcomes in the promotion window.
+    pub samples: usize,
+    pub success_rate: Option<f64>,
+}
+
+/// Tracks canary providers and limits their share of traffic.
+#[derive(Debug, Default)]
+pub struct CanaryTracker {
+    config: CanaryConfig,
+    canaries: DashMap<String, CanaryState>,
+}
+
+impl CanaryTracker {
+    /// Create a tracker for every provider with `canary = true`.
+    pub fn new(providers: &[ProviderConfig], config: CanaryConfig) -> Self {
+        let canaries = DashMap::new();
+        for provider in providers.iter().filter(|p| p.canary) {
+            tracing::info!(
+                provider = %provider.name,
+                percent = provider.canary_percent,
+                "Canary provider registered"
+            );
+            canaries.insert(
+                provider.name.clone(),
+                CanaryState {
+                    percent: provider.canary_percent,
+                    seen: 0,
+                    outcomes: VecDeque::new(),
+                    promoted: false,
+                    ready_logged: false,
+                },
+            );
+        }
+        Self { config, canaries }
+    }
+
+    /// Limit unpromoted canaries to their traffic slice.
+    ///
+    /// A canary whose slice comes up is moved to the front so the request
+    /// actually reaches it; otherwise it is dropped from the candidate list,
+    /// unless that would leave no candidates. The half-open probe provider
+    /// is never touched.
+    pub fn apply(
+        &self,
+        candidates: Vec<SelectedProvider>,
+        probe_provider: Option<&str>,
+    ) -> Vec<SelectedProvider> {
+        if self.canaries.is_empty() {
+            return candidates;
+        }
+
+        let mut chosen = Vec::new();
+        let mut regular = Vec::new();
+        let mut held_back = Vec::new();
+        for candidate in candidates {
+            if probe_provider == Some(candidate.name.as_str()) {
+                regular.push(candidate);
+                continue;
+            }
+            match self.canaries.get_mut(&candidate.name) {
+                Some(mut state) if !state.promoted => {
+                    if state.take_slice() {
+                        chosen.push(candidate);
+                    } else {
+                        held_back.push(candidate);
+                    }
+                }
+                _ => regular.push(candidate),
+            }
+        }
+
+        if chosen.is_empty() && regular.is_empty() {
+            return held_back;
+        }
+        // Keep a half-open probe ahead of any canary
+        let probe_first = regular
+            .first()
+            .is_some_and(|c| probe_provider == Some(c.name.as_str()));
+        if probe_first {
+            let probe = regular.remove(0);
+            chosen.insert(0, probe);
+        }
+        chosen.extend(regular);
+        chosen
+    }
+
+    /// Record an outcome for `provider` if it is an unpromoted canary.
+    pub fn record(&self, provider: &str, success: bool) {
+        let Some(mut state) = self.canaries.get_mut(provider) else {
+            return;
+        };
+        if state.promoted {
+            return;
+        }
+        if state.outcomes.len() == self.config.min_requests {
+            state.outcomes.pop_front();
+        }
+        state.outcomes.push_back(success);
+        if state.outcomes.len() < self.config.min_requests {
+            return;
+        }
+
+        let rate = state.success_rate().unwrap_or(0.0);
+        if rate < self.config.min_success_rate {
+            return;
+        }
+        if self.config.auto_promote {
+            tracing::info!(
+                provider = %provider,
+                success_rate = rate,
+                requests = state.outcomes.len(),
+                "Canary provider promoted"
+            );
+            state.promoted = true;
+        } else if !state.ready_logged {
+            tracing::warn!(
+                provider = %provider,
+                success_rate = rate,
+                requests = state.outcomes.len(),
+                "Canary provider ready for promotion (auto_promote disabled)"
+            );
+            state.ready_logged = true;
+        }
+    }
+
+    /// Save every canary's progress to the database. Returns the number of
+    /// canaries saved.
+    pub async fn persist(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let entries: Vec<(String, String)> = self
+            .canaries
+            .iter()
+            .filter_map(|entry| {
+                let state = entry.value();
+                let saved = SavedCanary {
+                    seen: state.seen,
+                    outcomes: state.outcomes.iter().copied().collect(),
+                    promoted: state.promoted,
+                };
+                Some((entry.key().clone(), serde_json::to_string(&saved).ok()?))
+            })
+            .collect();
+        routing_state::save(pool, STATE_KIND, &entries, &routing_state::now()).await?;
+        Ok(entries.len())
+    }
+
+    /// Reload progress saved by [`persist`](Self::persist) for providers
+    /// still configured as canaries, so a promoted canary stays promoted.
+    /// Returns the number of canaries restored.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let mut restored = 0;
+        for saved in routing_state::load(pool, STATE_KIND).await? {
+            let Some(mut state) = self.canaries.get_mut(&saved.provider) else {
+                continue;
+            };
+            let Ok(progress) = serde_json::from_str::<SavedCanary>(&saved.state) else {
+                tracing::warn!(provider = %saved.provider, "Discarding unreadable saved canary state");
+                continue;
+            };
+            let skip = progress
+                .outcomes
+                .len()
+                .saturating_sub(self.config.min_requests);
+            state.seen = progress.seen;
+            state.outcomes = progress.outcomes.into_iter().skip(skip).collect();
+            state.promoted = progress.promoted;
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Canary status of `provider`, or None if it is not a canary.
+    pub fn status(&self, provider: &str) -> Option<CanaryStatus> {
+        let state = self.canaries.get(provider)?;
+        Some(CanaryStatus {
+            percent: state.percent,
+            promoted: state.promoted,
+            samples: state.outcomes.len(),
+            success_rate: state.success_rate(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_config(name: &str, canary: bool, canary_percent: u8) -> ProviderConfig {
+        ProviderConfig {
+            url: "https://example.test/v1".to_string(),
+            output_rate: 10.0,
+            canary,
+            canary_percent,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    fn candidates(names: &[&str]) -> Vec<SelectedProvider> {
+        names
+            .iter()
+            .map(|n| SelectedProvider::from(&provider_config(n, false, 5)))
+            .collect()
+    }
+
+    fn names(candidates: &[SelectedProvider]) -> Vec<&str> {
+        candidates.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn config(min_requests: usize, auto_promote: bool) -> CanaryConfig {
+        CanaryConfig {
+            min_requests,
+            min_success_rate: 0.9,
+            auto_promote,
+        }
+    }
+
+    #[test]
+    fn canary_receives_its_percent_evenly() {
+        let tracker = CanaryTracker::new(
+            &[
+                provider_config("stable", false, 5),
+                provider_config("new", true, 10),
+            ],
+            config(50, true),
+        );
+        let mut hits = Vec::new();
+        for i in 0..100 {
+            let ordered = tracker.apply(candidates(&["new", "stable"]), None);
+            if ordered[0].name == "new" {
+                assert_eq!(names(&ordered), vec!["new", "stable"]);
+                hits.push(i);
+            } else {
+                assert_eq!(names(&ordered), vec!["stable"]);
+            }
+        }
+        assert_eq!(hits, vec![9, 19, 29, 39, 49, 59, 69, 79, 89, 99]);
+    }
+
+    #[test]
+    fn sole_canary_still_serves() {
+        let tracker = CanaryTracker::new(&[provider_config("new", true, 5)], config(50, true));
+        let ordered = tracker.apply(candidates(&["new"]), None);
+        assert_eq!(names(&ordered), vec!["new"]);
+    }
+
+    #[test]
+    fn probe_provider_stays_first() {
+        let tracker = CanaryTracker::new(&[provider_config("new", true, 100)], config(50, true));
+        let ordered = tracker.apply(candidates(&["stable", "new"]), Some("stable"));
+        assert_eq!(names(&ordered), vec!["stable", "new"]);
+    }
+
+    #[test]
+    fn promotes_after_healthy_window() {
+        let tracker = CanaryTracker::new(&[provider_config("new", true, 5)], config(10, true));
+        tracker.record("new", false);
+        for _ in 0..9 {
+            tracker.record("new", true);
+        }
+        // 9/10 meets 0.9
+        let status = tracker.status("new").unwrap();
+        assert!(status.promoted);
+
+        // Promoted canaries are routed normally
+        let ordered = tracker.apply(candidates(&["new", "stable"]), None);
+        assert_eq!(names(&ordered), vec!["new", "stable"]);
+    }
+
+    #[test]
+    fn unhealthy_canary_stays_limited() {
+        let tracker = CanaryTracker::new(&[provider_config("new", true, 5)], config(10, true));
+        for i in 0..30 {
+            tracker.record("new", i % 3 != 0);
+        }
+        let status = tracker.status("new").unwrap();
+        assert!(!status.promoted);
+        assert_eq!(status.samples, 10);
+    }
+
+    #[test]
+    fn no_auto_promote_keeps_canary() {
+        let tracker = CanaryTracker::new(&[provider_config("new", true, 5)], config(5, false));
+        for _ in 0..10 {
+            tracker.record("new", true);
+        }
+        assert!(!tracker.status("new").unwrap().promoted);
+        assert!(tracker.status("stable").is_none());
+    }
+
+    #[tokio::test]
+    async fn promotion_survives_persist_and_restore() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let providers = [
+            provider_config("new", true, 5),
+            provider_config("trial", true, 10),
+        ];
+        let tracker = CanaryTracker::new(&providers, config(3, true));
+        for _ in 0..3 {
+            tracker.record("new", true);
+        }
+        tracker.record("trial", false);
+        assert_eq!(tracker.persist(&pool).await.unwrap(), 2);
+
+        // "trial" is no longer a canary after the restart
+        let restarted = CanaryTracker::new(&providers[..1], config(3, true));
+        assert_eq!(restarted.restore(&pool).await.unwrap(), 1);
+        let status = restarted.status("new").unwrap();
+        assert!(status.promoted);
+        assert_eq!(status.samples, 3);
+        assert!(restarted.status("trial").is_none());
+    }
+}
@@ -0,0 +1,156 @@
+//! Fault injection for rehearsing provider failures (`[chaos]`).
+//!
+//! Injected latency and 5xx errors happen before the upstream request is
+//! sent, so they flow through the same retry, fallback, circuit breaker, and
+//! logging paths as real failures. Stream faults are applied to a real
+//! upstream stream: it is either cut off after a few chunks or has an
+//! unparseable SSE event spliced in.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use rand::Rng;
+
+use crate::config::ChaosConfig;
+
+/// Chunks passed through before a stream fault is applied.
+const FAULT_AFTER_CHUNKS: usize = 2;
+
+/// Event spliced into streams by [`StreamFault::Malformed`].
+const MALFORMED_EVENT: &[u8] = b"data: {\"id\":\"chaos\",\"choices\":[{\"delta\":\n\n";
+
+/// Fault applied to one streaming response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFault {
+    /// End the stream early, without a final chunk or `[DONE]`.
+    Drop,
+    /// Insert an event whose JSON payload is truncated.
+    Malformed,
+}
+
+/// Whether chaos applies to requests sent to `provider`.
+pub fn targets(config: &ChaosConfig, provider: &str) -> bool {
+    config.enabled
+        && (config.providers.is_empty() || config.providers.iter().any(|p| p == provider))
+}
+
+/// Delay to add before sending to `provider`, if any.
+pub fn latency(config: &ChaosConfig, provider: &str) -> Option<Duration> {
+    if !targets(config, provider) || config.latency_ms + config.latency_jitter_ms == 0 {
+        return None;
+    }
+    let jitter = rand::thread_rng().gen_range(0..=config.latency_jitter_ms);
+    Some(Duration::from_millis(config.latency_ms + jitter))
+}
+
+/// Status to fail this request to `provider` with, if one is injected.
+pub fn injected_error(config: &ChaosConfig, provider: &str) -> Option<u16> {
+    (targets(config, provider) && roll(config.error_rate)).then_some(config.error_status)
+}
+
+/// Fault to apply to this stream from `provider`, if any.
+pub fn stream_fault(config: &ChaosConfig, provider: &str) -> Option<StreamFault> {
+    if !targets(config, provider) {
+        None
+    } else if roll(config.drop_stream_rate) {
+        Some(StreamFault::Drop)
+    } else if roll(config.malformed_sse_rate) {
+        Some(StreamFault::Malformed)
+    } else {
+        None
+    }
+}
+
+fn roll(rate: f64) -> bool {
+    rate > 0.0 && rand::thread_rng().gen::<f64>() < rate
+}
+
+/// Apply `fault` to an upstream byte stream. Streams shorter than
+/// [`FAULT_AFTER_CHUNKS`] pass through unchanged.
+pub fn apply_stream_fault<S, E>(
+    stream: S,
+    fault: Option<StreamFault>,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    stream
+        .enumerate()
+        .take_while(move |(i, _)| {
+            futures::future::ready(fault != Some(StreamFault::Drop) || *i < FAULT_AFTER_CHUNKS)
+        })
+        .flat_map(move |(i, item)| {
+            let mut items = Vec::with_capacity(2);
+            if fault == Some(StreamFault::Malformed) && i == FAULT_AFTER_CHUNKS {
+                items.push(Ok(Bytes::from_static(MALFORMED_EVENT)));
+            }
+            items.push(item);
+            futures::stream::iter(items)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(n: usize) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+        futures::stream::iter((0..n).map(|i| Ok(Bytes::from(format!("data: {}\n\n", i)))))
+    }
+
+    async fn collect(fault: Option<StreamFault>, n: usize) -> Vec<Bytes> {
+        apply_stream_fault(chunks(n), fault)
+            .map(|r| r.unwrap())
+            .collect()
+            .await
+    }
+
+    #[test]
+    fn targets_selected_providers_when_enabled() {
+        let mut config = ChaosConfig {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(injected_error(&config, "alpha"), None);
+
+        config.enabled = true;
+        assert_eq!(injected_error(&config, "alpha"), Some(503));
+
+        config.providers = vec!["beta".to_string()];
+        assert!(!targets(&config, "alpha"));
+        assert!(targets(&config, "beta"));
+    }
+
+    #[test]
+    fn latency_includes_jitter() {
+        let config = ChaosConfig {
+            enabled: true,
+            latency_ms: 100,
+            latency_jitter_ms: 50,
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let d = latency(&config, "alpha").unwrap();
+            assert!(d >= Duration::from_millis(100) && d <= Duration::from_millis(150));
+        }
+        assert!(latency(&ChaosConfig::default(), "alpha").is_none());
+    }
+
+    #[tokio::test]
+    async fn drop_cuts_stream_short() {
+        assert_eq!(collect(None, 5).await.len(), 5);
+        assert_eq!(
+            collect(Some(StreamFault::Drop), 5).await.len(),
+            FAULT_AFTER_CHUNKS
+        );
+        assert_eq!(collect(Some(StreamFault::Drop), 1).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn malformed_splices_bad_event() {
+        let out = collect(Some(StreamFault::Malformed), 5).await;
+        assert_eq!(out.len(), 6);
+        assert_eq!(out[FAULT_AFTER_CHUNKS], Bytes::from_static(MALFORMED_EVENT));
+        assert_eq!(out[5], Bytes::from("data: 4\n\n"));
+    }
+}
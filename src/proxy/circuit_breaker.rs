@@ -10,17 +10,50 @@
 //! - Concurrent registry (`CircuitBreakerRegistry`) backed by DashMap
 //! - Queue-and-wait probe signaling via `tokio::sync::watch`
 //! - RAII `ProbeGuard` to prevent stuck probe_in_flight flags
-
+//! - Trip-storm cool-down (`[routing.circuit_breaker.trip_storm]`): a
+//!   circuit that keeps re-opening stays Open for hours and raises an alert
+//! - Per-error-type thresholds (`[routing.circuit_breaker.thresholds]`):
+//!   each consecutive failure counts `1 / threshold` of its type toward
+//!   tripping, so e.g. timeouts can trip faster than 5xx responses
+//! - Synthetic probes (`[routing.circuit_breaker] synthetic_probe`): the
+//!   registry probes a recovering provider with a 1-token completion, so no
+//!   user request is sent to a provider that may still be down
+
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::watch;
 
-/// Number of consecutive failures required to trip the circuit.
-const FAILURE_THRESHOLD: u32 = 3;
+use super::server::AppState;
+use crate::config::{ProviderConfig, TripStormConfig};
+use crate::error::ProviderErrorKind;
+use crate::storage::routing_state;
+
+/// Default number of consecutive failures required to trip the circuit.
+pub const FAILURE_THRESHOLD: u32 = 3;
 
 /// Duration the circuit stays Open before transitioning to Half-Open.
 const OPEN_DURATION: Duration = Duration::from_secs(30);
 
+/// Number of recent trips kept per provider for the scorecard.
+const TRIP_HISTORY_LEN: usize = 20;
+
+/// Timeout for the trip-storm alert webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `routing_state` kind for saved circuits.
+const STATE_KIND: &str = "circuit";
+
+/// How often open circuits are checked for a due synthetic probe.
+const SYNTHETIC_PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timeout for a synthetic probe completion.
+const SYNTHETIC_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// The three states of the circuit breaker.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CircuitState {
@@ -49,6 +82,30 @@ pub struct CircuitSnapshot {
     pub name: String,
     pub state: CircuitState,
     pub failure_count: u32,
+    /// Consecutive failures by error type (`"timeout"`, `"5xx"`, ...).
+    pub failures_by_type: BTreeMap<String, u32>,
+}
+
+/// A single Closed -> Open transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TripRecord {
+    /// Wall-clock time the circuit opened.
+    pub at: DateTime<Utc>,
+    /// The error that tripped the circuit, as `"<type>: <message>"`.
+    pub reason: String,
+}
+
+/// Detailed view of one provider's circuit breaker.
+#[derive(Debug, Clone)]
+pub struct CircuitDetails {
+    pub state: CircuitState,
+    pub failure_count: u32,
+    /// Consecutive failures by error type.
+    pub failures_by_type: BTreeMap<String, u32>,
+    pub trip_count: u32,
+    pub last_error: Option<LastError>,
+    /// Most recent trips, newest first (at most `TRIP_HISTORY_LEN`).
+    pub recent_trips: Vec<TripRecord>,
 }
 
 /// Result of a probe request in Half-Open state.
@@ -76,7 +133,7 @@ pub enum CheckResult {
 }
 
 /// Information about the last error that caused a state transition.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LastError {
     /// Category of the error (e.g., "5xx", "timeout").
     pub error_type: String,
@@ -116,6 +173,12 @@ pub(crate) struct CircuitBreakerInner {
     pub(crate) state: CircuitState,
     /// Consecutive failure count (resets on success).
     pub(crate) failure_count: u32,
+    /// Consecutive failures by error type (resets on success).
+    pub(crate) failures_by_type: BTreeMap<String, u32>,
+    /// Failures that trip the circuit, for error types without their own.
+    pub(crate) failure_threshold: u32,
+    /// Per-error-type trip thresholds.
+    pub(crate) type_thresholds: BTreeMap<String, u32>,
     /// When the circuit transitioned to Open (for timeout calculation).
     pub(crate) opened_at: Option<tokio::time::Instant>,
     /// When the last failure was recorded.
@@ -126,8 +189,21 @@ pub(crate) struct CircuitBreakerInner {
     pub(crate) last_error: Option<LastError>,
     /// Total number of times this circuit has tripped open.
     pub(crate) trip_count: u32,
+    /// Most recent trips, oldest first, capped at `TRIP_HISTORY_LEN`.
+    pub(crate) trip_history: VecDeque<TripRecord>,
     /// Whether a probe request is currently in flight (Half-Open single-permit).
     pub(crate) probe_in_flight: bool,
+    /// Trip-storm detection settings. Disabled when None.
+    pub(crate) trip_storm: Option<TripStormConfig>,
+    /// Open transitions within the trip-storm window, oldest first.
+    pub(crate) recent_openings: VecDeque<tokio::time::Instant>,
+    /// End of the trip-storm cool-down; the circuit stays Open until then.
+    pub(crate) cooldown_until: Option<tokio::time::Instant>,
+    /// Openings that started a cool-down, until the registry raises the alert.
+    pub(crate) pending_storm_alert: Option<u32>,
+    /// Whether the registry probes this circuit itself (`synthetic_probe`)
+    /// instead of letting a user request through as the probe.
+    pub(crate) synthetic_probe: bool,
 }
 
 impl CircuitBreakerInner {
@@ -136,12 +212,62 @@ impl CircuitBreakerInner {
         Self {
             state: CircuitState::Closed,
             failure_count: 0,
+            failures_by_type: BTreeMap::new(),
+            failure_threshold: FAILURE_THRESHOLD,
+            type_thresholds: BTreeMap::new(),
             opened_at: None,
             last_failure_time: None,
             last_success_time: None,
             last_error: None,
             trip_count: 0,
+            trip_history: VecDeque::new(),
             probe_in_flight: false,
+            trip_storm: None,
+            recent_openings: VecDeque::new(),
+            cooldown_until: None,
+            pending_storm_alert: None,
+            synthetic_probe: false,
+        }
+    }
+
+    /// Remaining trip-storm cool-down, if one is in progress.
+    pub(crate) fn cooldown_remaining(&self) -> Option<Duration> {
+        let remaining = self
+            .cooldown_until?
+            .saturating_duration_since(tokio::time::Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Count an Open transition toward trip-storm detection. Once more than
+    /// `max_trips` fall within the window, the circuit is held Open for
+    /// `cooldown_secs` and the count starts over.
+    fn note_opening(&mut self, provider_name: &str) {
+        let Some(storm) = &self.trip_storm else {
+            return;
+        };
+        let now = tokio::time::Instant::now();
+        let window = Duration::from_secs(storm.window_secs);
+        while self
+            .recent_openings
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= window)
+        {
+            self.recent_openings.pop_front();
+        }
+        self.recent_openings.push_back(now);
+
+        let openings = self.recent_openings.len() as u32;
+        if openings > storm.max_trips {
+            self.cooldown_until = Some(now + Duration::from_secs(storm.cooldown_secs));
+            self.recent_openings.clear();
+            self.pending_storm_alert = Some(openings);
+            tracing::error!(
+                provider = %provider_name,
+                openings = openings,
+                window_secs = storm.window_secs,
+                cooldown_secs = storm.cooldown_secs,
+                "circuit trip storm: holding circuit open for cool-down",
+            );
         }
     }
 
@@ -151,25 +277,34 @@ impl CircuitBreakerInner {
     pub(crate) fn check_state(&mut self) -> CheckResult {
         match self.state {
             CircuitState::Closed => CheckResult::Allowed,
+            // With synthetic probes the registry moves the circuit on; user
+            // requests are rejected until its probe succeeds.
+            CircuitState::Open if self.synthetic_probe => CheckResult::Rejected,
             CircuitState::Open => {
-                if let Some(opened_at) = self.opened_at {
-                    if tokio::time::Instant::now().duration_since(opened_at) >= OPEN_DURATION {
-                        // Lazy transition: Open -> HalfOpen
-                        self.state = CircuitState::HalfOpen;
-                        self.probe_in_flight = false;
-                        tracing::info!("circuit entering Half-Open: timeout expired");
-                        self.try_acquire_probe()
-                    } else {
-                        CheckResult::Rejected
-                    }
+                if self.open_timeout_expired() {
+                    // Lazy transition: Open -> HalfOpen
+                    self.state = CircuitState::HalfOpen;
+                    self.probe_in_flight = false;
+                    tracing::info!("circuit entering Half-Open: timeout expired");
+                    self.try_acquire_probe()
                 } else {
                     CheckResult::Rejected
                 }
             }
+            CircuitState::HalfOpen if self.synthetic_probe => CheckResult::Rejected,
             CircuitState::HalfOpen => self.try_acquire_probe(),
         }
     }
 
+    /// Whether an Open circuit has waited out its timeout and any trip-storm
+    /// cool-down.
+    fn open_timeout_expired(&self) -> bool {
+        self.cooldown_remaining().is_none()
+            && self.opened_at.is_some_and(|opened_at| {
+                tokio::time::Instant::now().duration_since(opened_at) >= OPEN_DURATION
+            })
+    }
+
     /// Try to acquire the single probe permit in Half-Open state.
     fn try_acquire_probe(&mut self) -> CheckResult {
         if !self.probe_in_flight {
@@ -180,26 +315,58 @@ impl CircuitBreakerInner {
         }
     }
 
+    /// Trip threshold for `error_type`.
+    fn threshold(&self, error_type: &str) -> u32 {
+        self.type_thresholds
+            .get(error_type)
+            .copied()
+            .unwrap_or(self.failure_threshold)
+            .max(1)
+    }
+
+    /// Progress toward tripping: the sum of each type's consecutive
+    /// failures over its threshold. The circuit trips at 1.
+    fn failure_weight(&self) -> f64 {
+        self.failures_by_type
+            .iter()
+            .map(|(error_type, count)| *count as f64 / self.threshold(error_type) as f64)
+            .sum()
+    }
+
     /// Record a failure in Closed state. Only call when circuit is Closed.
     ///
-    /// Increments consecutive failure counter. If threshold reached,
-    /// transitions to Open state.
+    /// Increments the consecutive failure counters. Once the weighted
+    /// failures reach the threshold, transitions to Open state.
     pub(crate) fn record_failure(&mut self, provider_name: &str, error_type: &str, message: &str) {
         self.failure_count += 1;
+        *self
+            .failures_by_type
+            .entry(error_type.to_string())
+            .or_insert(0) += 1;
         self.last_failure_time = Some(tokio::time::Instant::now());
         self.last_error = Some(LastError {
             error_type: error_type.to_string(),
             message: message.to_string(),
         });
 
-        if self.failure_count >= FAILURE_THRESHOLD {
+        // Tolerance for sums like 1/3 + 1/3 + 1/3
+        if self.failure_weight() >= 1.0 - 1e-9 {
             self.state = CircuitState::Open;
             self.opened_at = Some(tokio::time::Instant::now());
             self.trip_count += 1;
+            if self.trip_history.len() == TRIP_HISTORY_LEN {
+                self.trip_history.pop_front();
+            }
+            self.trip_history.push_back(TripRecord {
+                at: Utc::now(),
+                reason: format!("{}: {}", error_type, message),
+            });
+            self.note_opening(provider_name);
 
             tracing::warn!(
                 provider = %provider_name,
                 failure_count = self.failure_count,
+                failures_by_type = ?self.failures_by_type,
                 last_error = ?self.last_error,
                 trip_count = self.trip_count,
                 "circuit OPENED: {} consecutive failures",
@@ -211,6 +378,7 @@ impl CircuitBreakerInner {
     /// Record a success in Closed state. Resets the failure counter.
     pub(crate) fn record_success(&mut self, provider_name: &str) {
         self.failure_count = 0;
+        self.failures_by_type.clear();
         self.last_success_time = Some(tokio::time::Instant::now());
 
         tracing::debug!(
@@ -225,7 +393,9 @@ impl CircuitBreakerInner {
     pub(crate) fn record_probe_success(&mut self, provider_name: &str) {
         self.state = CircuitState::Closed;
         self.failure_count = 0;
+        self.failures_by_type.clear();
         self.probe_in_flight = false;
+        self.cooldown_until = None;
         self.last_success_time = Some(tokio::time::Instant::now());
 
         tracing::info!(
@@ -257,9 +427,28 @@ impl CircuitBreakerInner {
             trip_count = self.trip_count,
             "circuit REOPENED: probe failed",
         );
+        self.note_opening(provider_name);
     }
 }
 
+/// A circuit as saved by [`CircuitBreakerRegistry::persist`]. A Half-Open
+/// circuit is saved as Open with its timeout expired, so the restarted
+/// process probes it afresh.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCircuit {
+    open: bool,
+    failure_count: u32,
+    trip_count: u32,
+    #[serde(default)]
+    opened_secs_ago: Option<f64>,
+    #[serde(default)]
+    cooldown_secs: Option<f64>,
+    #[serde(default)]
+    last_error: Option<LastError>,
+    #[serde(default)]
+    failures_by_type: BTreeMap<String, u32>,
+}
+
 // ── Permit type ──────────────────────────────────────────────────────
 
 /// Type of permit returned by [`CircuitBreakerRegistry::acquire_permit`].
@@ -302,6 +491,21 @@ impl ProviderCircuitBreaker {
 /// probing.
 pub struct CircuitBreakerRegistry {
     breakers: DashMap<String, ProviderCircuitBreaker>,
+    /// Client and webhook URL for trip-storm alerts.
+    storm_webhook: Option<(reqwest::Client, String)>,
+    /// State changes across all circuits, see [`transitions`](Self::transitions).
+    transitions: AtomicU64,
+}
+
+/// Alert body POSTed to the trip-storm webhook.
+#[derive(Debug, Serialize)]
+struct StormAlert<'a> {
+    event: &'static str,
+    provider: &'a str,
+    openings: u32,
+    window_secs: u64,
+    cooldown_secs: u64,
+    message: String,
 }
 
 impl CircuitBreakerRegistry {
@@ -313,7 +517,110 @@ impl CircuitBreakerRegistry {
         for name in provider_names {
             breakers.insert(name.clone(), ProviderCircuitBreaker::new());
         }
-        Self { breakers }
+        Self {
+            breakers,
+            storm_webhook: None,
+            transitions: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of circuit state changes so far. Anything derived from
+    /// circuit states is stale once this moves.
+    pub fn transitions(&self) -> u64 {
+        self.transitions.load(Ordering::Relaxed)
+    }
+
+    fn note_transition(&self, before: CircuitState, after: CircuitState) {
+        if before != after {
+            self.transitions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Enable trip-storm cool-downs on every breaker. Alerts are POSTed to
+    /// the configured webhook with `client`.
+    pub fn with_trip_storm(
+        mut self,
+        config: Option<TripStormConfig>,
+        client: reqwest::Client,
+    ) -> Self {
+        self.storm_webhook = config
+            .as_ref()
+            .and_then(|c| c.webhook_url.clone())
+            .map(|url| (client, url));
+        for mut entry in self.breakers.iter_mut() {
+            let inner = entry
+                .value_mut()
+                .inner
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner());
+            inner.trip_storm = config.clone();
+        }
+        self
+    }
+
+    /// Set how many consecutive failures trip each circuit: `by_type`
+    /// thresholds for the error types it lists, `default` for the rest.
+    pub fn with_failure_thresholds(self, default: u32, by_type: &BTreeMap<String, u32>) -> Self {
+        for mut entry in self.breakers.iter_mut() {
+            let inner = entry
+                .value_mut()
+                .inner
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner());
+            inner.failure_threshold = default;
+            inner.type_thresholds = by_type.clone();
+        }
+        self
+    }
+
+    /// Probe recovering circuits with synthetic requests instead of user
+    /// requests. Only providers with at least one model can be probed; the
+    /// rest keep using the first user request as the probe.
+    pub fn with_synthetic_probe(self, enabled: bool, providers: &[ProviderConfig]) -> Self {
+        if !enabled {
+            return self;
+        }
+        for provider in providers.iter().filter(|p| !p.models.is_empty()) {
+            if let Some(mut entry) = self.breakers.get_mut(&provider.name) {
+                let inner = entry
+                    .value_mut()
+                    .inner
+                    .get_mut()
+                    .unwrap_or_else(|e| e.into_inner());
+                inner.synthetic_probe = true;
+            }
+        }
+        self
+    }
+
+    /// Raise the alert for a cool-down that `inner` just started, if any.
+    fn raise_storm_alert(&self, provider_name: &str, inner: &mut CircuitBreakerInner) {
+        let (Some(openings), Some(storm)) = (inner.pending_storm_alert.take(), &inner.trip_storm)
+        else {
+            return;
+        };
+        let Some((client, url)) = &self.storm_webhook else {
+            return;
+        };
+        let alert = StormAlert {
+            event: "provider_trip_storm",
+            provider: provider_name,
+            openings,
+            window_secs: storm.window_secs,
+            cooldown_secs: storm.cooldown_secs,
+            message: format!(
+                "Provider '{}' circuit opened {} times within {}s and is held open for {}s",
+                provider_name, openings, storm.window_secs, storm.cooldown_secs
+            ),
+        };
+        let request = client.post(url).timeout(WEBHOOK_TIMEOUT).json(&alert);
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(r) if r.status().is_success() => {}
+                Ok(r) => tracing::warn!(status = %r.status(), "Trip storm alert webhook failed"),
+                Err(e) => tracing::warn!(error = %e, "Trip storm alert webhook failed"),
+            }
+        });
     }
 
     /// Check whether a request to `provider_name` should proceed.
@@ -342,7 +649,9 @@ impl CircuitBreakerRegistry {
         // CRITICAL: Mutex and DashMap entry are dropped before any .await.
         let (check_result, error_info, mut rx) = {
             let mut inner = cb.inner.lock().unwrap_or_else(|e| e.into_inner());
+            let before = inner.state;
             let result = inner.check_state();
+            self.note_transition(before, inner.state);
             let err_info = (
                 inner
                     .last_error
@@ -407,7 +716,9 @@ impl CircuitBreakerRegistry {
                 .inner
                 .lock()
                 .unwrap_or_else(|e| e.into_inner());
+            let before = inner.state;
             inner.record_success(provider_name);
+            self.note_transition(before, inner.state);
         }
     }
 
@@ -421,7 +732,10 @@ impl CircuitBreakerRegistry {
                 .inner
                 .lock()
                 .unwrap_or_else(|e| e.into_inner());
+            let before = inner.state;
             inner.record_failure(provider_name, error_type, message);
+            self.note_transition(before, inner.state);
+            self.raise_storm_alert(provider_name, &mut inner);
         }
     }
 
@@ -435,7 +749,9 @@ impl CircuitBreakerRegistry {
         if let Some(entry) = self.breakers.get(provider_name) {
             let cb = entry.value();
             let mut inner = cb.inner.lock().unwrap_or_else(|e| e.into_inner());
+            let before = inner.state;
             inner.record_probe_success(provider_name);
+            self.note_transition(before, inner.state);
             let _ = cb.probe_watch.send(ProbeResult::Success);
         }
     }
@@ -449,11 +765,44 @@ impl CircuitBreakerRegistry {
         if let Some(entry) = self.breakers.get(provider_name) {
             let cb = entry.value();
             let mut inner = cb.inner.lock().unwrap_or_else(|e| e.into_inner());
+            let before = inner.state;
             inner.record_probe_failure(provider_name, error_type, message);
+            self.note_transition(before, inner.state);
+            self.raise_storm_alert(provider_name, &mut inner);
             let _ = cb.probe_watch.send(ProbeResult::Failed);
         }
     }
 
+    /// Move every synthetically probed circuit whose open timeout has expired
+    /// to Half-Open with the probe permit taken, and return their providers.
+    /// The caller must resolve each probe, e.g. through a [`ProbeGuard`].
+    pub fn take_synthetic_probes(&self) -> Vec<String> {
+        self.breakers
+            .iter()
+            .filter_map(|entry| {
+                let mut inner = entry
+                    .value()
+                    .inner
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if !inner.synthetic_probe
+                    || inner.state != CircuitState::Open
+                    || !inner.open_timeout_expired()
+                {
+                    return None;
+                }
+                inner.state = CircuitState::HalfOpen;
+                inner.probe_in_flight = true;
+                self.note_transition(CircuitState::Open, CircuitState::HalfOpen);
+                tracing::info!(
+                    provider = %entry.key(),
+                    "circuit entering Half-Open: sending synthetic probe"
+                );
+                Some(entry.key().clone())
+            })
+            .collect()
+    }
+
     /// Return a snapshot of all provider circuit states.
     ///
     /// Uses DashMap::iter() which acquires per-shard locks (not a global lock).
@@ -470,6 +819,7 @@ impl CircuitBreakerRegistry {
                     name: entry.key().clone(),
                     state: inner.state,
                     failure_count: inner.failure_count,
+                    failures_by_type: inner.failures_by_type.clone(),
                 }
             })
             .collect()
@@ -499,6 +849,37 @@ impl CircuitBreakerRegistry {
         })
     }
 
+    /// Full circuit details for one provider, including recent trips.
+    pub fn details(&self, provider_name: &str) -> Option<CircuitDetails> {
+        self.breakers.get(provider_name).map(|entry| {
+            let inner = entry
+                .value()
+                .inner
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            CircuitDetails {
+                state: inner.state,
+                failure_count: inner.failure_count,
+                failures_by_type: inner.failures_by_type.clone(),
+                trip_count: inner.trip_count,
+                last_error: inner.last_error.clone(),
+                recent_trips: inner.trip_history.iter().rev().cloned().collect(),
+            }
+        })
+    }
+
+    /// Remaining trip-storm cool-down for `provider_name`, if one is in progress.
+    pub fn cooldown_remaining(&self, provider_name: &str) -> Option<Duration> {
+        self.breakers.get(provider_name).and_then(|entry| {
+            entry
+                .value()
+                .inner
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .cooldown_remaining()
+        })
+    }
+
     /// Read-only accessor for cumulative trip count (for Phase 15).
     pub fn trip_count(&self, provider_name: &str) -> Option<u32> {
         self.breakers.get(provider_name).map(|entry| {
@@ -510,6 +891,90 @@ impl CircuitBreakerRegistry {
                 .trip_count
         })
     }
+
+    /// Save every circuit's state, open time, trip count and cool-down to
+    /// the database. Returns the number of circuits saved.
+    pub async fn persist(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let entries: Vec<(String, String)> = self
+            .breakers
+            .iter()
+            .filter_map(|entry| {
+                let inner = entry
+                    .value()
+                    .inner
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                let opened_secs_ago = match inner.state {
+                    CircuitState::Closed => None,
+                    CircuitState::Open => inner.opened_at.map(|at| at.elapsed().as_secs_f64()),
+                    CircuitState::HalfOpen => Some(OPEN_DURATION.as_secs_f64()),
+                };
+                let saved = SavedCircuit {
+                    open: inner.state != CircuitState::Closed,
+                    failure_count: inner.failure_count,
+                    trip_count: inner.trip_count,
+                    opened_secs_ago,
+                    cooldown_secs: inner.cooldown_remaining().map(|d| d.as_secs_f64()),
+                    last_error: inner.last_error.clone(),
+                    failures_by_type: inner.failures_by_type.clone(),
+                };
+                let state = serde_json::to_string(&saved).ok()?;
+                Some((entry.key().clone(), state))
+            })
+            .collect();
+        routing_state::save(pool, STATE_KIND, &entries, &routing_state::now()).await?;
+        Ok(entries.len())
+    }
+
+    /// Reload circuits saved by [`persist`](Self::persist), counting the
+    /// downtime as time served: a circuit whose 30 second timeout (or
+    /// trip-storm cool-down) ran out while the process was down goes
+    /// Half-Open on its next request. Providers no longer configured are
+    /// skipped. Returns the number of circuits restored.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let mut restored = 0;
+        for saved in routing_state::load(pool, STATE_KIND).await? {
+            let Some(entry) = self.breakers.get(&saved.provider) else {
+                continue;
+            };
+            let Ok(state) = serde_json::from_str::<SavedCircuit>(&saved.state) else {
+                tracing::warn!(provider = %saved.provider, "Discarding unreadable saved circuit");
+                continue;
+            };
+            let age = saved.age();
+            let now = tokio::time::Instant::now();
+            let mut inner = entry
+                .value()
+                .inner
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            inner.failure_count = state.failure_count;
+            inner.failures_by_type = state.failures_by_type;
+            inner.trip_count = state.trip_count;
+            inner.last_error = state.last_error;
+            if state.open {
+                let opened_ago =
+                    Duration::from_secs_f64(state.opened_secs_ago.unwrap_or(0.0)) + age;
+                self.note_transition(inner.state, CircuitState::Open);
+                inner.state = CircuitState::Open;
+                inner.opened_at = Some(now.checked_sub(opened_ago).unwrap_or(now));
+                inner.cooldown_until = state
+                    .cooldown_secs
+                    .map(Duration::from_secs_f64)
+                    .and_then(|remaining| remaining.checked_sub(age))
+                    .filter(|remaining| !remaining.is_zero())
+                    .map(|remaining| now + remaining);
+                tracing::info!(
+                    provider = %saved.provider,
+                    opened_secs_ago = opened_ago.as_secs(),
+                    trip_count = state.trip_count,
+                    "circuit restored Open"
+                );
+            }
+            restored += 1;
+        }
+        Ok(restored)
+    }
 }
 
 // ── ProbeGuard RAII ──────────────────────────────────────────────────
@@ -565,6 +1030,89 @@ impl<'a> Drop for ProbeGuard<'a> {
     }
 }
 
+// ── Synthetic probes ─────────────────────────────────────────────────
+
+/// Send a synthetic probe to every circuit that is due one and record the
+/// results. Returns the providers probed.
+pub async fn run_synthetic_probes(state: &AppState) -> Vec<String> {
+    let due = state.circuit_breakers.take_synthetic_probes();
+    futures::future::join_all(due.iter().map(|name| async move {
+        let guard = ProbeGuard::new(&state.circuit_breakers, name.clone());
+        let Some(provider) = state.config.providers.iter().find(|p| &p.name == name) else {
+            return guard.failure("not_configured", "provider is no longer configured");
+        };
+        match send_synthetic_probe(state, provider).await {
+            Ok(()) => guard.success(),
+            Err((kind, message)) => {
+                tracing::warn!(provider = %name, error = %message, "Synthetic probe failed");
+                guard.failure(kind.as_str(), &message);
+            }
+        }
+    }))
+    .await;
+    due
+}
+
+/// A 1-token completion against the provider's first model. Any status
+/// other than 2xx fails the probe.
+async fn send_synthetic_probe(
+    state: &AppState,
+    provider: &ProviderConfig,
+) -> Result<(), (ProviderErrorKind, String)> {
+    let client = state
+        .provider_clients
+        .get(&provider.name)
+        .unwrap_or(&state.http_client);
+    let model = provider.models.first().map(String::as_str).unwrap_or("");
+    let request = super::handlers::apply_provider_headers(
+        client
+            .post(format!(
+                "{}/chat/completions",
+                provider.url.trim_end_matches('/')
+            ))
+            .timeout(SYNTHETIC_PROBE_TIMEOUT)
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "ping"}],
+                "max_tokens": 1
+            })),
+        provider.api_key.as_ref(),
+        provider.auth_scheme,
+        &provider.extra_headers,
+    );
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => {
+            let status = response.status();
+            Err((
+                ProviderErrorKind::from_status(status.as_u16()),
+                format!("synthetic probe returned {}", status),
+            ))
+        }
+        Err(e) => Err((ProviderErrorKind::from_reqwest(&e), e.to_string())),
+    }
+}
+
+/// Run [`run_synthetic_probes`] every second until cancelled.
+pub async fn synthetic_probe_loop(state: AppState, mut cancel: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(SYNTHETIC_PROBE_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                run_synthetic_probes(&state).await;
+            }
+            changed = cancel.changed() => {
+                if changed.is_err() || *cancel.borrow() {
+                    tracing::info!("Synthetic probe task shutting down");
+                    break;
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -619,6 +1167,49 @@ mod tests {
         assert!(cb.opened_at.is_some());
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_per_type_thresholds() {
+        let mut cb = CircuitBreakerInner::new();
+        cb.failure_threshold = 5;
+        cb.type_thresholds =
+            BTreeMap::from([("timeout".to_string(), 2), ("connect".to_string(), 1)]);
+
+        for _ in 0..4 {
+            cb.record_failure("test-provider", "5xx", "Internal Server Error");
+        }
+        assert_eq!(cb.state, CircuitState::Closed);
+        cb.record_success("test-provider");
+        assert!(cb.failures_by_type.is_empty());
+
+        cb.record_failure("test-provider", "timeout", "timed out");
+        assert_eq!(cb.state, CircuitState::Closed);
+        cb.record_failure("test-provider", "timeout", "timed out");
+        assert_eq!(cb.state, CircuitState::Open);
+
+        let mut cb = CircuitBreakerInner::new();
+        cb.type_thresholds = BTreeMap::from([("connect".to_string(), 1)]);
+        cb.record_failure("test-provider", "connect", "connection refused");
+        assert_eq!(cb.state, CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mixed_failure_types_add_up() {
+        let mut cb = CircuitBreakerInner::new();
+        cb.failure_threshold = 4;
+        cb.type_thresholds = BTreeMap::from([("timeout".to_string(), 2)]);
+
+        // 1/2 for the timeout, 1/4 for each 5xx
+        cb.record_failure("test-provider", "timeout", "timed out");
+        cb.record_failure("test-provider", "5xx", "Bad Gateway");
+        assert_eq!(cb.state, CircuitState::Closed);
+        cb.record_failure("test-provider", "5xx", "Bad Gateway");
+        assert_eq!(cb.state, CircuitState::Open);
+        assert_eq!(
+            cb.failures_by_type,
+            BTreeMap::from([("5xx".to_string(), 2), ("timeout".to_string(), 1)])
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn test_success_resets_failure_count() {
         let mut cb = CircuitBreakerInner::new();
@@ -913,6 +1504,26 @@ mod tests {
         assert_eq!(registry.failure_count("alpha"), Some(2));
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_registry_counts_transitions() {
+        let registry = CircuitBreakerRegistry::new(&["alpha".to_string()]);
+        registry.record_failure("alpha", "5xx", "Error 1");
+        registry.record_success("alpha");
+        assert_eq!(registry.transitions(), 0);
+
+        // Closed -> Open -> Half-Open -> Closed
+        trip_registry(&registry, "alpha");
+        assert_eq!(registry.transitions(), 1);
+        tokio::time::advance(OPEN_DURATION).await;
+        assert_eq!(
+            registry.acquire_permit("alpha").await.unwrap(),
+            PermitType::Probe
+        );
+        assert_eq!(registry.transitions(), 2);
+        registry.record_probe_success("alpha");
+        assert_eq!(registry.transitions(), 3);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn test_registry_probe_permit_after_timeout() {
         let registry = CircuitBreakerRegistry::new(&["alpha".to_string()]);
@@ -927,6 +1538,37 @@ mod tests {
         assert_eq!(registry.state("alpha"), Some(CircuitState::HalfOpen));
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_synthetic_probe_keeps_user_requests_out() {
+        let registry = CircuitBreakerRegistry::new(&["alpha".to_string()]);
+        registry
+            .breakers
+            .get("alpha")
+            .unwrap()
+            .inner
+            .lock()
+            .unwrap()
+            .synthetic_probe = true;
+        trip_registry(&registry, "alpha");
+        assert!(registry.take_synthetic_probes().is_empty());
+
+        tokio::time::advance(Duration::from_secs(31)).await;
+        // User requests never become the probe
+        assert!(registry.acquire_permit("alpha").await.is_err());
+        assert_eq!(registry.state("alpha"), Some(CircuitState::Open));
+
+        assert_eq!(registry.take_synthetic_probes(), ["alpha"]);
+        assert_eq!(registry.state("alpha"), Some(CircuitState::HalfOpen));
+        assert!(registry.take_synthetic_probes().is_empty());
+        assert!(registry.acquire_permit("alpha").await.is_err());
+
+        registry.record_probe_success("alpha");
+        assert_eq!(
+            registry.acquire_permit("alpha").await.unwrap(),
+            PermitType::Normal
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn test_registry_queue_and_wait_success() {
         let registry = std::sync::Arc::new(CircuitBreakerRegistry::new(&["alpha".to_string()]));
@@ -1106,4 +1748,173 @@ mod tests {
             "Waiter should see failure, not stale success from previous cycle"
         );
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_details_records_trip_history_newest_first() {
+        let registry = CircuitBreakerRegistry::new(&["alpha".to_string()]);
+        assert!(registry.details("alpha").unwrap().recent_trips.is_empty());
+        assert!(registry.details("unknown").is_none());
+
+        trip_registry(&registry, "alpha");
+        tokio::time::advance(Duration::from_secs(31)).await;
+        assert_eq!(
+            registry.acquire_permit("alpha").await.unwrap(),
+            PermitType::Probe
+        );
+        registry.record_probe_success("alpha");
+        for _ in 0..FAILURE_THRESHOLD {
+            registry.record_failure("alpha", "timeout", "took too long");
+        }
+
+        let details = registry.details("alpha").unwrap();
+        assert_eq!(details.state, CircuitState::Open);
+        assert_eq!(details.trip_count, 2);
+        assert_eq!(details.recent_trips.len(), 2);
+        assert_eq!(details.recent_trips[0].reason, "timeout: took too long");
+        assert_eq!(details.recent_trips[1].reason, "5xx: Internal Server Error");
+        assert_eq!(details.last_error.unwrap().error_type, "timeout");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trip_history_is_capped() {
+        let mut cb = CircuitBreakerInner::new();
+        for _ in 0..TRIP_HISTORY_LEN + 5 {
+            trip_circuit(&mut cb);
+            cb.state = CircuitState::Closed;
+            cb.failure_count = 0;
+            cb.failures_by_type.clear();
+        }
+        assert_eq!(cb.trip_count as usize, TRIP_HISTORY_LEN + 5);
+        assert_eq!(cb.trip_history.len(), TRIP_HISTORY_LEN);
+    }
+
+    fn storm_config() -> TripStormConfig {
+        TripStormConfig {
+            max_trips: 2,
+            window_secs: 3600,
+            cooldown_secs: 7200,
+            webhook_url: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trip_storm_holds_circuit_open() {
+        let registry = CircuitBreakerRegistry::new(&["alpha".to_string()])
+            .with_trip_storm(Some(storm_config()), reqwest::Client::new());
+
+        // Trip, then fail one probe: two openings, still within max_trips
+        for _ in 0..FAILURE_THRESHOLD {
+            registry.record_failure("alpha", "5xx", "Internal Server Error");
+        }
+        tokio::time::advance(OPEN_DURATION).await;
+        assert_eq!(
+            registry.acquire_permit("alpha").await.unwrap(),
+            PermitType::Probe
+        );
+        registry.record_probe_failure("alpha", "5xx", "still down");
+        assert!(registry.cooldown_remaining("alpha").is_none());
+
+        // The third opening starts the cool-down
+        tokio::time::advance(OPEN_DURATION).await;
+        registry.acquire_permit("alpha").await.unwrap();
+        registry.record_probe_failure("alpha", "5xx", "still down");
+        let remaining = registry.cooldown_remaining("alpha").unwrap();
+        assert_eq!(remaining, Duration::from_secs(7200));
+
+        // No probes during the cool-down
+        tokio::time::advance(Duration::from_secs(3600)).await;
+        assert!(registry.acquire_permit("alpha").await.is_err());
+        assert_eq!(registry.state("alpha"), Some(CircuitState::Open));
+
+        // Afterwards the circuit half-opens as usual and a success closes it
+        tokio::time::advance(Duration::from_secs(3600)).await;
+        assert_eq!(
+            registry.acquire_permit("alpha").await.unwrap(),
+            PermitType::Probe
+        );
+        registry.record_probe_success("alpha");
+        assert_eq!(registry.state("alpha"), Some(CircuitState::Closed));
+        assert!(registry.cooldown_remaining("alpha").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trip_storm_openings_expire_from_window() {
+        let mut cb = CircuitBreakerInner::new();
+        cb.trip_storm = Some(storm_config());
+        for _ in 0..5 {
+            trip_circuit(&mut cb);
+            cb.state = CircuitState::Closed;
+            cb.failure_count = 0;
+            cb.failures_by_type.clear();
+            tokio::time::advance(Duration::from_secs(1800)).await;
+        }
+        // Never more than two openings in any hour
+        assert!(cb.cooldown_until.is_none());
+        assert!(cb.pending_storm_alert.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_trip_storm_without_config() {
+        let mut cb = CircuitBreakerInner::new();
+        for _ in 0..10 {
+            trip_circuit(&mut cb);
+            cb.state = CircuitState::Closed;
+            cb.failure_count = 0;
+            cb.failures_by_type.clear();
+        }
+        assert!(cb.cooldown_remaining().is_none());
+    }
+
+    async fn state_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn open_circuit_survives_persist_and_restore() {
+        let pool = state_pool().await;
+        let names = vec!["alpha".to_string(), "beta".to_string()];
+        let registry = CircuitBreakerRegistry::new(&names);
+        trip_registry(&registry, "alpha");
+        registry.record_failure("beta", "timeout", "timed out");
+        assert_eq!(registry.persist(&pool).await.unwrap(), 2);
+
+        let restarted = CircuitBreakerRegistry::new(&names);
+        assert_eq!(restarted.restore(&pool).await.unwrap(), 2);
+        assert_eq!(restarted.state("alpha"), Some(CircuitState::Open));
+        assert_eq!(restarted.trip_count("alpha"), Some(1));
+        let err = restarted.acquire_permit("alpha").await.unwrap_err();
+        assert!(err.reason.contains("Internal Server Error"), "{}", err);
+        assert_eq!(restarted.state("beta"), Some(CircuitState::Closed));
+        assert_eq!(restarted.failure_count("beta"), Some(1));
+
+        // Providers no longer configured are skipped
+        let other = CircuitBreakerRegistry::new(&["gamma".to_string()]);
+        assert_eq!(other.restore(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn downtime_counts_toward_the_open_timeout() {
+        let pool = state_pool().await;
+        let names = vec!["alpha".to_string()];
+        let registry = CircuitBreakerRegistry::new(&names);
+        trip_registry(&registry, "alpha");
+        registry.persist(&pool).await.unwrap();
+        let saved = routing_state::load(&pool, STATE_KIND).await.unwrap();
+        let entries = vec![(saved[0].provider.clone(), saved[0].state.clone())];
+        let a_minute_ago = (Utc::now() - chrono::Duration::seconds(60))
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        routing_state::save(&pool, STATE_KIND, &entries, &a_minute_ago)
+            .await
+            .unwrap();
+
+        let restarted = CircuitBreakerRegistry::new(&names);
+        restarted.restore(&pool).await.unwrap();
+        assert_eq!(restarted.state("alpha"), Some(CircuitState::Open));
+        assert_eq!(
+            restarted.acquire_permit("alpha").await.unwrap(),
+            PermitType::Probe
+        );
+    }
 }
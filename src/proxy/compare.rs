@@ -0,0 +1,223 @@
+//! Multi-model fan-out comparison (`POST /v1/compare`).
+//!
+//! One prompt is sent to several models (optionally pinned to a provider)
+//! in parallel, and every response comes back with its cost and latency.
+//! Each response is logged as its own request, tagged `comparison=<id>`,
+//! and the set is stored in `comparisons` for `GET /v1/compare/{id}`.
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use super::types::ChatCompletionRequest;
+use crate::error::Error;
+use crate::storage::comparisons::{self, Comparison, ComparisonResult};
+
+/// Most targets a single comparison may fan out to.
+pub const MAX_TARGETS: usize = 8;
+
+/// Log tag key linking each response's request row to its comparison.
+pub const COMPARISON_TAG: &str = "comparison";
+
+/// A model to compare, optionally pinned to one provider.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompareTarget {
+    pub model: String,
+    pub provider: Option<String>,
+}
+
+/// Split a `/v1/compare` body into its targets, the chat request sent to
+/// each target, and the shared request without targets (for storage).
+///
+/// The body is a chat completion request with `targets` in place of
+/// `model`; `stream` is ignored.
+pub fn parse_request(
+    mut body: serde_json::Value,
+) -> Result<
+    (
+        Vec<CompareTarget>,
+        Vec<ChatCompletionRequest>,
+        serde_json::Value,
+    ),
+    Error,
+> {
+    let object = body
+        .as_object_mut()
+        .ok_or_else(|| Error::BadRequest("Request body must be a JSON object".to_string()))?;
+    let targets: Vec<CompareTarget> = object
+        .remove("targets")
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| Error::BadRequest(format!("Invalid targets: {}", e)))?
+        .unwrap_or_default();
+    if targets.is_empty() {
+        return Err(Error::BadRequest(
+            "targets must list at least one model".to_string(),
+        ));
+    }
+    if targets.len() > MAX_TARGETS {
+        return Err(Error::BadRequest(format!(
+            "targets lists {} models, at most {} are allowed",
+            targets.len(),
+            MAX_TARGETS
+        )));
+    }
+    object.remove("model");
+    object.remove("stream");
+    object.remove("stream_options");
+
+    let requests = targets
+        .iter()
+        .map(|target| {
+            let mut request = body.clone();
+            request["model"] = serde_json::Value::String(target.model.clone());
+            serde_json::from_value::<ChatCompletionRequest>(request)
+                .map_err(|e| Error::BadRequest(format!("Invalid request: {}", e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((targets, requests, body))
+}
+
+/// Response for `POST /v1/compare` and `GET /v1/compare/{id}`.
+#[derive(Debug, Serialize)]
+pub struct CompareResponse {
+    pub id: String,
+    pub timestamp: String,
+    /// Whether the set was stored for `GET /v1/compare/{id}`.
+    pub persisted: bool,
+    pub results: Vec<CompareResult>,
+}
+
+/// One target's response, in target order.
+#[derive(Debug, Serialize)]
+pub struct CompareResult {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Correlation ID of this response's request log row.
+    pub request_id: String,
+    pub success: bool,
+    pub latency_ms: i64,
+    pub cost_sats: Option<f64>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    /// The assistant's reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// The provider's full response body (`POST` only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CompareError>,
+}
+
+/// Why a target failed.
+#[derive(Debug, Serialize)]
+pub struct CompareError {
+    pub status: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl CompareResult {
+    pub fn new(stored: ComparisonResult, response: Option<serde_json::Value>) -> Self {
+        let error = (!stored.success).then_some(CompareError {
+            status: stored.error_status,
+            message: stored.error_message,
+        });
+        Self {
+            model: stored.model,
+            provider: stored.provider,
+            request_id: stored.correlation_id,
+            success: stored.success,
+            latency_ms: stored.latency_ms,
+            cost_sats: stored.cost_sats,
+            input_tokens: stored.input_tokens,
+            output_tokens: stored.output_tokens,
+            content: stored.content,
+            response,
+            error,
+        }
+    }
+}
+
+/// Handle GET /v1/compare/{id} -- a stored comparison set.
+pub async fn comparison_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+    let comparison = comparisons::query_comparison(pool, &id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Comparison '{}' not found", id)))?;
+    Ok(Json(CompareResponse {
+        id: comparison.id,
+        timestamp: comparison.timestamp,
+        persisted: true,
+        results: comparison
+            .results
+            .into_iter()
+            .map(|r| CompareResult::new(r, None))
+            .collect(),
+    }))
+}
+
+/// Store `comparison`, returning whether it was written.
+pub async fn persist(state: &AppState, comparison: &Comparison) -> bool {
+    let Some(pool) = &state.db else {
+        return false;
+    };
+    match comparison.insert(pool).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(comparison = %comparison.id, error = %e, "Failed to store comparison");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_replace_the_model() {
+        let (targets, requests, shared) = parse_request(serde_json::json!({
+            "targets": [{"model": "gpt-4o"}, {"model": "llama-3", "provider": "local"}],
+            "model": "ignored",
+            "stream": true,
+            "max_tokens": 50,
+            "messages": [{"role": "user", "content": "hi"}]
+        }))
+        .unwrap();
+        assert_eq!(targets[1].provider.as_deref(), Some("local"));
+        assert_eq!(requests[0].model, "gpt-4o");
+        assert_eq!(requests[1].model, "llama-3");
+        assert_eq!(requests[1].max_tokens, Some(50));
+        assert_eq!(requests[1].stream, None);
+        assert!(shared.get("targets").is_none());
+        assert!(shared.get("model").is_none());
+    }
+
+    #[test]
+    fn rejects_missing_or_too_many_targets() {
+        let messages = serde_json::json!([{"role": "user", "content": "hi"}]);
+        for targets in [
+            serde_json::json!([]),
+            serde_json::json!(vec![serde_json::json!({"model": "m"}); MAX_TARGETS + 1]),
+            serde_json::json!(["gpt-4o"]),
+        ] {
+            let body = serde_json::json!({"targets": targets, "messages": messages});
+            assert!(matches!(parse_request(body), Err(Error::BadRequest(_))));
+        }
+        let body = serde_json::json!({"targets": [{"model": "gpt-4o"}]});
+        assert!(matches!(parse_request(body), Err(Error::BadRequest(_))));
+    }
+}
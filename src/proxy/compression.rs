@@ -0,0 +1,257 @@
+//! End-to-end compression and bytes-saved counters.
+//!
+//! Client request bodies sent with `Content-Encoding: gzip|br` are
+//! decompressed before reaching handlers, and responses are compressed when
+//! the client sends `Accept-Encoding` (SSE streams and tiny bodies are left
+//! alone by tower-http's default predicate). Upstream compression is
+//! negotiated by reqwest itself.
+//!
+//! Counting middleware on either side of each tower-http layer records the
+//! encoded and decoded byte counts, served by `GET /v1/stats/compression`
+//! along with the token savings of prompt compression
+//! (see [`super::prompt_compression`]).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header::CONTENT_ENCODING,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+
+use super::server::AppState;
+
+/// Process-lifetime compression byte counters.
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    requests_decompressed: AtomicU64,
+    request_bytes_compressed: AtomicU64,
+    request_bytes_decompressed: AtomicU64,
+    responses_compressed: AtomicU64,
+    response_bytes_uncompressed: AtomicU64,
+    response_bytes_compressed: AtomicU64,
+    prompts_compressed: AtomicU64,
+    prompt_tokens_original: AtomicU64,
+    prompt_tokens_compressed: AtomicU64,
+    prompt_msats_saved: AtomicU64,
+}
+
+/// Response for GET /v1/stats/compression.
+#[derive(Debug, Serialize)]
+pub struct CompressionSnapshot {
+    pub requests_decompressed: u64,
+    pub request_bytes_compressed: u64,
+    pub request_bytes_decompressed: u64,
+    pub request_bytes_saved: u64,
+    pub responses_compressed: u64,
+    pub response_bytes_uncompressed: u64,
+    pub response_bytes_compressed: u64,
+    pub response_bytes_saved: u64,
+    pub prompt: PromptCompressionSnapshot,
+}
+
+/// Prompt compression totals (`[policies.rules.compress]`).
+#[derive(Debug, Serialize)]
+pub struct PromptCompressionSnapshot {
+    pub requests: u64,
+    /// Estimated prompt tokens before and after compression.
+    pub tokens_original: u64,
+    pub tokens_compressed: u64,
+    pub tokens_saved: u64,
+    /// Input cost of the tokens saved, for requests that succeeded.
+    pub sats_saved: f64,
+}
+
+impl CompressionStats {
+    /// Current counter values.
+    pub fn snapshot(&self) -> CompressionSnapshot {
+        let request_bytes_compressed = self.request_bytes_compressed.load(Ordering::Relaxed);
+        let request_bytes_decompressed = self.request_bytes_decompressed.load(Ordering::Relaxed);
+        let response_bytes_uncompressed = self.response_bytes_uncompressed.load(Ordering::Relaxed);
+        let response_bytes_compressed = self.response_bytes_compressed.load(Ordering::Relaxed);
+        let prompt_tokens_original = self.prompt_tokens_original.load(Ordering::Relaxed);
+        let prompt_tokens_compressed = self.prompt_tokens_compressed.load(Ordering::Relaxed);
+        CompressionSnapshot {
+            requests_decompressed: self.requests_decompressed.load(Ordering::Relaxed),
+            request_bytes_compressed,
+            request_bytes_decompressed,
+            request_bytes_saved: request_bytes_decompressed
+                .saturating_sub(request_bytes_compressed),
+            responses_compressed: self.responses_compressed.load(Ordering::Relaxed),
+            response_bytes_uncompressed,
+            response_bytes_compressed,
+            response_bytes_saved: response_bytes_uncompressed
+                .saturating_sub(response_bytes_compressed),
+            prompt: PromptCompressionSnapshot {
+                requests: self.prompts_compressed.load(Ordering::Relaxed),
+                tokens_original: prompt_tokens_original,
+                tokens_compressed: prompt_tokens_compressed,
+                tokens_saved: prompt_tokens_original.saturating_sub(prompt_tokens_compressed),
+                sats_saved: self.prompt_msats_saved.load(Ordering::Relaxed) as f64 / 1000.0,
+            },
+        }
+    }
+
+    /// Record a compressed prompt's estimated token counts.
+    pub fn record_prompt(&self, original_tokens: u32, compressed_tokens: u32) {
+        self.prompts_compressed.fetch_add(1, Ordering::Relaxed);
+        self.prompt_tokens_original
+            .fetch_add(original_tokens as u64, Ordering::Relaxed);
+        self.prompt_tokens_compressed
+            .fetch_add(compressed_tokens as u64, Ordering::Relaxed);
+    }
+
+    /// Record the input cost saved by compressing a prompt.
+    pub fn record_prompt_savings(&self, sats: f64) {
+        self.prompt_msats_saved
+            .fetch_add((sats * 1000.0).round() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Marks a request whose body arrived compressed.
+#[derive(Clone)]
+struct CompressedRequest;
+
+/// Running size of a response body before compression.
+#[derive(Clone)]
+struct UncompressedBytes(Arc<AtomicU64>);
+
+/// Records a compressed response once its body is dropped (fully sent or
+/// abandoned), when both byte counts are final.
+struct ResponseTally {
+    stats: Arc<CompressionStats>,
+    uncompressed: Arc<AtomicU64>,
+    compressed: Arc<AtomicU64>,
+}
+
+impl Drop for ResponseTally {
+    fn drop(&mut self) {
+        self.stats
+            .responses_compressed
+            .fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .response_bytes_uncompressed
+            .fetch_add(self.uncompressed.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.stats
+            .response_bytes_compressed
+            .fetch_add(self.compressed.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+/// Wrap `body` so every data chunk's length is passed to `count`.
+fn count_body(body: Body, count: impl Fn(u64) + Send + Sync + 'static) -> Body {
+    Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            count(bytes.len() as u64);
+        }
+    }))
+}
+
+/// Outside request decompression: count encoded request bytes.
+async fn count_encoded_request(
+    stats: Arc<CompressionStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !request.headers().contains_key(CONTENT_ENCODING) {
+        return next.run(request).await;
+    }
+    stats.requests_decompressed.fetch_add(1, Ordering::Relaxed);
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(CompressedRequest);
+    let body = count_body(body, move |n| {
+        stats
+            .request_bytes_compressed
+            .fetch_add(n, Ordering::Relaxed);
+    });
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Inside request decompression: count decoded request bytes.
+async fn count_decoded_request(
+    stats: Arc<CompressionStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.extensions().get::<CompressedRequest>().is_none() {
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let body = count_body(body, move |n| {
+        stats
+            .request_bytes_decompressed
+            .fetch_add(n, Ordering::Relaxed);
+    });
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Inside response compression: count bytes produced by the handler.
+async fn count_plain_response(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let counter = Arc::new(AtomicU64::new(0));
+    let (mut parts, body) = response.into_parts();
+    parts.extensions.insert(UncompressedBytes(counter.clone()));
+    let body = count_body(body, move |n| {
+        counter.fetch_add(n, Ordering::Relaxed);
+    });
+    Response::from_parts(parts, body)
+}
+
+/// Outside response compression: count bytes sent when compression applied.
+async fn count_encoded_response(
+    stats: Arc<CompressionStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if !response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+    let Some(UncompressedBytes(uncompressed)) = response.extensions().get().cloned() else {
+        return response;
+    };
+    let compressed = Arc::new(AtomicU64::new(0));
+    let tally = ResponseTally {
+        stats,
+        uncompressed,
+        compressed: compressed.clone(),
+    };
+    let (parts, body) = response.into_parts();
+    let body = count_body(body, move |n| {
+        let _ = &tally;
+        compressed.fetch_add(n, Ordering::Relaxed);
+    });
+    Response::from_parts(parts, body)
+}
+
+/// Add request decompression and response compression to `router`, with
+/// counting middleware recording into `stats`.
+pub(crate) fn layer(router: Router, stats: Arc<CompressionStats>) -> Router {
+    let (req_outer, req_inner, resp_outer) = (stats.clone(), stats.clone(), stats);
+    router
+        .layer(middleware::from_fn(count_plain_response))
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(move |req, next| {
+            count_encoded_response(resp_outer.clone(), req, next)
+        }))
+        .layer(middleware::from_fn(move |req, next| {
+            count_decoded_request(req_inner.clone(), req, next)
+        }))
+        .layer(RequestDecompressionLayer::new())
+        .layer(middleware::from_fn(move |req, next| {
+            count_encoded_request(req_outer.clone(), req, next)
+        }))
+}
+
+/// Handle GET /v1/stats/compression.
+pub async fn compression_stats_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.compression.snapshot())
+}
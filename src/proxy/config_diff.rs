@@ -0,0 +1,409 @@
+//! Structured config diffs and the dry-run reload check.
+//!
+//! arbstr reads its config file once at startup, so an edited file is
+//! checked with a dry run: `POST /admin/config/diff` takes the TOML that
+//! would be loaded, validates it like `arbstr check`, and reports what
+//! loading it would change (providers added or removed, rate and model
+//! changes, policy changes, and which other sections differ) while the
+//! running config stays as it is. A file that fails validation is refused
+//! with a 400. Admin updates that are applied, such as maintenance
+//! changes, record their diff too. The latest diff is logged and kept for
+//! `GET /admin/config/diff`.
+
+use std::fmt;
+
+use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+
+use super::server::AppState;
+use crate::config::{Config, PolicyRule, ProviderConfig};
+use crate::error::Error;
+
+/// One changed setting, with both values in Rust debug notation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// Changed settings of a provider or policy present in both configs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryDiff {
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// What changes between two configs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigDiff {
+    pub providers_added: Vec<String>,
+    pub providers_removed: Vec<String>,
+    pub providers_changed: Vec<EntryDiff>,
+    pub policies_added: Vec<String>,
+    pub policies_removed: Vec<String>,
+    pub policies_changed: Vec<EntryDiff>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_strategy: Option<FieldChange>,
+    /// Other top-level sections (`routing`, `server`, ...) that differ.
+    pub sections_changed: Vec<&'static str>,
+}
+
+/// Compare the listed fields of `$old` and `$new` by their debug output.
+macro_rules! field_changes {
+    ($old:expr, $new:expr, [$($field:ident),* $(,)?]) => {{
+        let mut changes = Vec::new();
+        $(
+            let from = format!("{:?}", $old.$field);
+            let to = format!("{:?}", $new.$field);
+            if from != to {
+                changes.push(FieldChange {
+                    field: stringify!($field),
+                    from,
+                    to,
+                });
+            }
+        )*
+        changes
+    }};
+}
+
+/// A change to a secret setting, reported without its values.
+fn redacted(field: &'static str) -> FieldChange {
+    FieldChange {
+        field,
+        from: "[REDACTED]".to_string(),
+        to: "[REDACTED]".to_string(),
+    }
+}
+
+impl ConfigDiff {
+    /// The changes that loading `new` would make to `old`.
+    pub fn between(old: &Config, new: &Config) -> Self {
+        let (providers_added, providers_removed, providers_changed) = diff_entries(
+            &old.providers,
+            &new.providers,
+            |p| &p.name,
+            provider_changes,
+        );
+        let (policies_added, policies_removed, policies_changed) = diff_entries(
+            &old.policies.rules,
+            &new.policies.rules,
+            |p| &p.name,
+            policy_changes,
+        );
+        let default_strategy = field_changes!(old.policies, new.policies, [default_strategy])
+            .into_iter()
+            .next();
+
+        let mut sections_changed = Vec::new();
+        macro_rules! sections {
+            ($($section:ident),* $(,)?) => {
+                $(
+                    if format!("{:?}", old.$section) != format!("{:?}", new.$section) {
+                        sections_changed.push(stringify!($section));
+                    }
+                )*
+            };
+        }
+        sections!(
+            server, database, vault, logging, routing, reports, headers, dns, chaos, warmup, stats,
+            currency, privacy, recording, evaluation, arbitrage, nostr, receipts, archive,
+        );
+
+        Self {
+            providers_added,
+            providers_removed,
+            providers_changed,
+            policies_added,
+            policies_removed,
+            policies_changed,
+            default_strategy,
+            sections_changed,
+        }
+    }
+
+    /// Whether nothing changes.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Split two lists of named entries into added and removed names, and the
+/// changes to entries in both.
+fn diff_entries<T>(
+    old: &[T],
+    new: &[T],
+    name: impl Fn(&T) -> &String,
+    changes: impl Fn(&T, &T) -> Vec<FieldChange>,
+) -> (Vec<String>, Vec<String>, Vec<EntryDiff>) {
+    let contains = |list: &[T], wanted: &String| list.iter().any(|e| name(e) == wanted);
+    let added = new
+        .iter()
+        .filter(|e| !contains(old, name(e)))
+        .map(|e| name(e).clone())
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|e| !contains(new, name(e)))
+        .map(|e| name(e).clone())
+        .collect();
+    let changed = new
+        .iter()
+        .filter_map(|e| {
+            let previous = old.iter().find(|o| name(o) == name(e))?;
+            let changes = changes(previous, e);
+            (!changes.is_empty()).then(|| EntryDiff {
+                name: name(e).clone(),
+                changes,
+            })
+        })
+        .collect();
+    (added, removed, changed)
+}
+
+fn provider_changes(old: &ProviderConfig, new: &ProviderConfig) -> Vec<FieldChange> {
+    let mut changes = field_changes!(
+        old,
+        new,
+        [
+            url,
+            input_rate,
+            output_rate,
+            base_fee,
+            min_charge_sats,
+            tier,
+            auto_discover,
+            canary,
+            canary_percent,
+            auth_scheme,
+            pool,
+            resolve,
+            backoff,
+            balance_sats,
+            balance_reset,
+            region,
+            requests_per_minute,
+            tokens_per_minute,
+            structured_output,
+            enabled,
+            maintenance_until,
+        ]
+    );
+    // The running list of a discovering provider is what its endpoint
+    // returned, not what the file says
+    if !new.auto_discover {
+        changes.extend(field_changes!(old, new, [models]));
+    }
+    if old.api_key.as_ref().map(|k| k.expose_secret())
+        != new.api_key.as_ref().map(|k| k.expose_secret())
+    {
+        changes.push(redacted("api_key"));
+    }
+    if old.extra_headers != new.extra_headers {
+        changes.push(redacted("extra_headers"));
+    }
+    changes
+}
+
+fn policy_changes(old: &PolicyRule, new: &PolicyRule) -> Vec<FieldChange> {
+    let mut changes = field_changes!(
+        old,
+        new,
+        [
+            allowed_models,
+            strategy,
+            max_sats_per_1k_output,
+            keywords,
+            backoff,
+            active_hours,
+            days,
+            utc_offset,
+            off_hours_tier,
+            allowed_regions,
+            validate,
+            max_output_tokens,
+            default_max_tokens,
+            system_prompt_prepend,
+            trim,
+            min_prompt_tokens,
+            max_prompt_tokens,
+            tier,
+            accumulate,
+        ]
+    );
+    if old.system_prompt_bypass_token != new.system_prompt_bypass_token {
+        changes.push(redacted("system_prompt_bypass_token"));
+    }
+    changes
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let changes = |kind: &str, entries: &[EntryDiff]| -> Vec<String> {
+            entries
+                .iter()
+                .map(|e| {
+                    let fields: Vec<String> = e
+                        .changes
+                        .iter()
+                        .map(|c| format!("{} {} -> {}", c.field, c.from, c.to))
+                        .collect();
+                    format!("{} {}: {}", kind, e.name, fields.join(", "))
+                })
+                .collect()
+        };
+        let mut parts = Vec::new();
+        for (what, names) in [
+            ("providers added", &self.providers_added),
+            ("providers removed", &self.providers_removed),
+            ("policies added", &self.policies_added),
+            ("policies removed", &self.policies_removed),
+        ] {
+            if !names.is_empty() {
+                parts.push(format!("{}: {}", what, names.join(", ")));
+            }
+        }
+        parts.extend(changes("provider", &self.providers_changed));
+        parts.extend(changes("policy", &self.policies_changed));
+        if let Some(c) = &self.default_strategy {
+            parts.push(format!("default_strategy {} -> {}", c.from, c.to));
+        }
+        if !self.sections_changed.is_empty() {
+            parts.push(format!(
+                "sections changed: {}",
+                self.sections_changed.join(", ")
+            ));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// A diff as recorded for `GET /admin/config/diff`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffRecord {
+    pub created_at: String,
+    /// `dry_run` for a checked config file, `admin` for an applied admin
+    /// update.
+    pub source: &'static str,
+    /// Whether the changes took effect.
+    pub applied: bool,
+    /// `X-Arbstr-Actor` of the request.
+    pub actor: String,
+    pub summary: String,
+    pub diff: ConfigDiff,
+}
+
+impl DiffRecord {
+    pub fn new(source: &'static str, applied: bool, headers: &HeaderMap, diff: ConfigDiff) -> Self {
+        Self {
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            source,
+            applied,
+            actor: super::config_versions::actor(headers).to_string(),
+            summary: diff.to_string(),
+            diff,
+        }
+    }
+}
+
+/// Handle GET /admin/config/diff -- the latest recorded diff.
+pub async fn last_diff_handler(State(state): State<AppState>) -> Result<Json<DiffRecord>, Error> {
+    state
+        .config_versions
+        .last_diff()
+        .map(Json)
+        .ok_or_else(|| Error::NotFound("No config diff recorded yet".to_string()))
+}
+
+/// Handle POST /admin/config/diff -- validate a candidate config file (the
+/// TOML body) and report what loading it would change, without applying
+/// it.
+pub async fn dry_run_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, Error> {
+    let candidate = match Config::parse_str_with_env(&body) {
+        Ok((config, _key_sources)) => config,
+        Err(e) => {
+            tracing::warn!(error = %e, "Candidate config rejected, keeping the current config");
+            return Err(Error::BadRequest(format!(
+                "Config rejected, keeping the current config: {}",
+                e
+            )));
+        }
+    };
+    let diff = ConfigDiff::between(&state.config, &candidate);
+    let record = DiffRecord::new("dry_run", false, &headers, diff);
+    state.config_versions.record_diff(record.clone());
+    Ok(Json(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+[server]
+listen = "127.0.0.1:8080"
+
+[[providers]]
+name = "alpha"
+url = "https://alpha.example/v1"
+api_key = "cashuA-alpha"
+models = ["gpt-4o"]
+input_rate = 5
+output_rate = 15
+
+[[providers]]
+name = "beta"
+url = "https://beta.example/v1"
+models = ["gpt-4o"]
+
+[[policies.rules]]
+name = "code"
+allowed_models = ["gpt-4o"]
+strategy = "cheapest"
+"#;
+
+    #[test]
+    fn reports_added_removed_and_changed_entries() {
+        let old = Config::parse_str(BASE).unwrap();
+        assert!(ConfigDiff::between(&old, &old.clone()).is_empty());
+
+        let new = Config::parse_str(
+            &BASE
+                .replace("output_rate = 15", "output_rate = 20")
+                .replace("cashuA-alpha", "cashuA-rotated")
+                .replace("name = \"beta\"", "name = \"gamma\"")
+                .replace("strategy = \"cheapest\"", "strategy = \"lowest_latency\"")
+                .replace("[server]", "[stats]\ncache_ttl_secs = 30\n\n[server]"),
+        )
+        .unwrap();
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(diff.providers_added, vec!["gamma"]);
+        assert_eq!(diff.providers_removed, vec!["beta"]);
+        assert_eq!(diff.providers_changed.len(), 1);
+        let alpha = &diff.providers_changed[0];
+        assert_eq!(alpha.name, "alpha");
+        assert_eq!(
+            alpha.changes[0],
+            FieldChange {
+                field: "output_rate",
+                from: "15.0".to_string(),
+                to: "20.0".to_string(),
+            }
+        );
+        assert_eq!(alpha.changes[1], redacted("api_key"));
+        assert_eq!(diff.policies_changed[0].changes[0].field, "strategy");
+        assert_eq!(diff.sections_changed, vec!["stats"]);
+        assert!(diff.to_string().contains(
+            "provider alpha: output_rate 15.0 -> 20.0, api_key [REDACTED] -> [REDACTED]"
+        ));
+    }
+}
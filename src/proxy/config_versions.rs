@@ -0,0 +1,160 @@
+//! Config versioning and change audit.
+//!
+//! The running configuration has a version number from the
+//! `config_versions` table, stamped on every request row so cost and
+//! latency changes can be lined up with config edits. A startup with a
+//! changed config, or a change through the admin API (currently
+//! `PUT /admin/providers/{name}/maintenance`), starts a new version;
+//! admin changes record who made them from the `X-Arbstr-Actor` header.
+//! `GET /admin/config/versions` lists the history. Without a database
+//! nothing is versioned and request rows carry no version.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::config_diff::DiffRecord;
+use super::server::AppState;
+use crate::config::Config;
+use crate::error::Error;
+use crate::storage::config_versions::{self, ConfigVersionRow};
+
+/// Header naming who made an admin change, recorded with the new version.
+pub const ARBSTR_ACTOR_HEADER: &str = "x-arbstr-actor";
+
+/// Actor recorded when an admin request has no `X-Arbstr-Actor`.
+const DEFAULT_ACTOR: &str = "admin";
+
+/// Default and maximum rows returned by `GET /admin/config/versions`.
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 1000;
+
+/// The config version requests are dispatched under.
+#[derive(Debug, Default)]
+pub struct ConfigVersions {
+    /// 0 until a version is restored from the database.
+    current: AtomicI64,
+    /// Latest diff, for `GET /admin/config/diff`.
+    last_diff: Mutex<Option<DiffRecord>>,
+}
+
+impl ConfigVersions {
+    /// The active version; None when versions are not tracked.
+    pub fn current(&self) -> Option<i64> {
+        Some(self.current.load(Ordering::Relaxed)).filter(|v| *v > 0)
+    }
+
+    /// Pick up or start the version for `config` at startup.
+    pub async fn restore(&self, pool: &SqlitePool, config: &Config) -> Result<i64, sqlx::Error> {
+        let version =
+            config_versions::restore(pool, &super::about::config_hash(config), &now()).await?;
+        self.current.fetch_max(version, Ordering::Relaxed);
+        Ok(version)
+    }
+
+    /// Record an admin change and make its version current. A no-op
+    /// without a database.
+    pub async fn record_change(
+        &self,
+        pool: Option<&SqlitePool>,
+        config: &Config,
+        headers: &HeaderMap,
+        change: &str,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let Some(pool) = pool else {
+            return Ok(None);
+        };
+        let actor = actor(headers);
+        let version = config_versions::insert(
+            pool,
+            &now(),
+            &super::about::config_hash(config),
+            "admin",
+            Some(actor),
+            Some(change),
+        )
+        .await?;
+        // Concurrent changes may finish out of order; keep the newest
+        self.current.fetch_max(version, Ordering::Relaxed);
+        tracing::info!(version, actor, change, "Config version recorded");
+        Ok(Some(version))
+    }
+
+    /// Log `record` and keep it as the latest diff.
+    pub fn record_diff(&self, record: DiffRecord) {
+        tracing::info!(
+            source = record.source,
+            applied = record.applied,
+            actor = %record.actor,
+            providers_added = ?record.diff.providers_added,
+            providers_removed = ?record.diff.providers_removed,
+            providers_changed = record.diff.providers_changed.len(),
+            policies_changed = record.diff.policies_added.len()
+                + record.diff.policies_removed.len()
+                + record.diff.policies_changed.len(),
+            sections_changed = ?record.diff.sections_changed,
+            diff = %record.summary,
+            "Config diff"
+        );
+        *self.last_diff.lock().unwrap() = Some(record);
+    }
+
+    /// The latest recorded diff.
+    pub fn last_diff(&self) -> Option<DiffRecord> {
+        self.last_diff.lock().unwrap().clone()
+    }
+}
+
+/// Who made an admin change: the `X-Arbstr-Actor` header, or `admin`.
+pub fn actor(headers: &HeaderMap) -> &str {
+    headers
+        .get(ARBSTR_ACTOR_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .unwrap_or(DEFAULT_ACTOR)
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Query parameters for `GET /admin/config/versions`.
+#[derive(Debug, Deserialize)]
+pub struct VersionsQuery {
+    pub limit: Option<u32>,
+}
+
+/// Response for `GET /admin/config/versions`.
+#[derive(Debug, Serialize)]
+pub struct VersionsResponse {
+    pub current: Option<i64>,
+    /// Newest first.
+    pub versions: Vec<ConfigVersionRow>,
+}
+
+/// Handle GET /admin/config/versions -- the config change history.
+pub async fn versions_handler(
+    State(state): State<AppState>,
+    Query(params): Query<VersionsQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .or(state.db.as_ref())
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    Ok(Json(VersionsResponse {
+        current: state.config_versions.current(),
+        versions: config_versions::history(pool, limit).await?,
+    }))
+}
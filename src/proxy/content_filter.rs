@@ -0,0 +1,280 @@
+//! Streaming content filter (`[content_filter]`).
+//!
+//! Streamed replies are held back to whole SSE events and the delta text of
+//! each choice is checked against the configured blocklist and patterns
+//! before the event is forwarded. Text is matched together with the last
+//! [`CARRY_BYTES`] of the choice's earlier deltas, so a term split across
+//! tokens is still caught. On a match the offending event is withheld, a
+//! `content_policy_violation` error event is sent in its place, and the
+//! stream ends; the rule that matched is published through a
+//! [`ContentFilterHandle`] for the request log.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+use bytes::Bytes;
+use futures::Stream;
+use regex::Regex;
+
+use super::stream::{event_data, find_event_end};
+use crate::config::ContentFilterConfig;
+
+/// Earlier delta text kept per choice for matches across tokens.
+const CARRY_BYTES: usize = 1024;
+
+/// Sent to the client in place of the event that matched.
+pub const VIOLATION_EVENT: &[u8] = b"data: {\"error\":{\"message\":\"Response stopped by content filter\",\"type\":\"content_policy_violation\",\"code\":\"content_filter\"}}\n\n";
+
+/// The rule that stopped a stream, once one has.
+pub type ContentFilterHandle = Arc<OnceLock<String>>;
+
+/// Compiled `[content_filter]` rules.
+#[derive(Debug, Clone)]
+pub struct ContentFilter {
+    /// Each rule as configured (`blocklist:<term>` or `pattern:<regex>`),
+    /// with its matcher.
+    rules: Vec<(String, Regex)>,
+}
+
+impl ContentFilter {
+    /// Compile `config`. Patterns are checked at config load; one that
+    /// still fails to compile is skipped.
+    pub fn new(config: &ContentFilterConfig) -> Self {
+        let blocklist = config.blocklist.iter().filter_map(|term| {
+            Regex::new(&format!("(?i){}", regex::escape(term.trim())))
+                .ok()
+                .map(|re| (format!("blocklist:{}", term.trim()), re))
+        });
+        let patterns = config.patterns.iter().filter_map(|pattern| {
+            Regex::new(pattern)
+                .ok()
+                .map(|re| (format!("pattern:{}", pattern), re))
+        });
+        Self {
+            rules: blocklist.chain(patterns).collect(),
+        }
+    }
+
+    /// The first rule matching `text`.
+    pub fn check(&self, text: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(_, re)| re.is_match(text))
+            .map(|(rule, _)| rule.as_str())
+    }
+}
+
+/// Per-stream scanning state.
+struct Scanner {
+    filter: ContentFilter,
+    buffer: Vec<u8>,
+    /// Recent delta text per choice index.
+    carry: BTreeMap<u64, String>,
+}
+
+impl Scanner {
+    /// Forwardable bytes completed by `chunk`, or the rule it broke.
+    fn push(&mut self, chunk: &[u8]) -> Result<Bytes, String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut end = 0;
+        while let Some(len) = find_event_end(&self.buffer[end..]) {
+            scan(&self.filter, &mut self.carry, &self.buffer[end..end + len])?;
+            end += len;
+        }
+        Ok(Bytes::from(self.buffer.drain(..end).collect::<Vec<u8>>()))
+    }
+
+    /// The unterminated final event, if it passes.
+    fn finish(&mut self) -> Result<Bytes, String> {
+        let event = std::mem::take(&mut self.buffer);
+        scan(&self.filter, &mut self.carry, &event)?;
+        Ok(Bytes::from(event))
+    }
+}
+
+/// Check one event's delta text, with `carry` holding each choice's
+/// recent text.
+fn scan(
+    filter: &ContentFilter,
+    carry: &mut BTreeMap<u64, String>,
+    event: &[u8],
+) -> Result<(), String> {
+    let Some(chunk) =
+        event_data(event).and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+    else {
+        return Ok(());
+    };
+    let Some(choices) = chunk.get("choices").and_then(|c| c.as_array()) else {
+        return Ok(());
+    };
+    for (i, choice) in choices.iter().enumerate() {
+        let Some(delta) = choice.get("delta") else {
+            continue;
+        };
+        let index = choice
+            .get("index")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(i as u64);
+        for field in ["content", "refusal"] {
+            let Some(text) = delta.get(field).and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let carry = carry.entry(index).or_default();
+            carry.push_str(text);
+            if let Some(rule) = filter.check(carry) {
+                return Err(rule.to_string());
+            }
+            if carry.len() > CARRY_BYTES {
+                let mut cut = carry.len() - CARRY_BYTES;
+                while !carry.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                carry.drain(..cut);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Filter an SSE body through `filter`, or pass it through when `None`.
+///
+/// Returns the filtered stream and a handle that holds the matching rule
+/// once the stream has been stopped.
+pub fn filter_sse_stream<S, E>(
+    stream: S,
+    filter: Option<ContentFilter>,
+) -> (impl Stream<Item = Result<Bytes, E>>, ContentFilterHandle)
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    use futures::StreamExt;
+
+    let handle = ContentFilterHandle::default();
+    let blocked = handle.clone();
+    let scanner = filter.map(|filter| Scanner {
+        filter,
+        buffer: Vec::new(),
+        carry: BTreeMap::new(),
+    });
+    // State: the upstream, the scanner, and whether the stream has ended
+    let filtered = futures::stream::unfold(
+        (Box::pin(stream), scanner, false),
+        move |(mut stream, mut scanner, done)| {
+            let blocked = blocked.clone();
+            async move {
+                if done {
+                    return None;
+                }
+                loop {
+                    let item = stream.next().await;
+                    let Some(active) = scanner.as_mut() else {
+                        return item.map(|item| (item, (stream, scanner, false)));
+                    };
+                    let (result, ended) = match item {
+                        Some(Ok(bytes)) => (active.push(&bytes), false),
+                        Some(Err(e)) => return Some((Err(e), (stream, scanner, false))),
+                        None => (active.finish(), true),
+                    };
+                    match result {
+                        Err(rule) => {
+                            tracing::warn!(rule = %rule, "Content filter stopped stream");
+                            let _ = blocked.set(rule);
+                            let violation = Bytes::from_static(VIOLATION_EVENT);
+                            return Some((Ok(violation), (stream, scanner, true)));
+                        }
+                        Ok(bytes) if bytes.is_empty() => {
+                            if ended {
+                                return None;
+                            }
+                        }
+                        Ok(bytes) => return Some((Ok(bytes), (stream, scanner, ended))),
+                    }
+                }
+            }
+        },
+    );
+    (filtered, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn filter() -> ContentFilter {
+        ContentFilter::new(&ContentFilterConfig {
+            blocklist: vec!["Project Zeus".to_string()],
+            patterns: vec![r"\b\d{3}-\d{2}-\d{4}\b".to_string()],
+        })
+    }
+
+    fn delta(content: &str) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::json!({"choices": [{"index": 0, "delta": {"content": content}}]})
+        )
+    }
+
+    async fn run(chunks: Vec<String>) -> (String, Option<String>) {
+        let stream = futures::stream::iter(
+            chunks
+                .into_iter()
+                .map(|c| Ok::<_, std::io::Error>(Bytes::from(c))),
+        );
+        let (filtered, handle) = filter_sse_stream(stream, Some(filter()));
+        let body: Vec<u8> = filtered
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+        (String::from_utf8(body).unwrap(), handle.get().cloned())
+    }
+
+    #[test]
+    fn matches_blocklist_case_insensitively_and_patterns() {
+        let filter = filter();
+        assert_eq!(
+            filter.check("all about project zeus"),
+            Some("blocklist:Project Zeus")
+        );
+        assert!(filter
+            .check("SSN 123-45-6789")
+            .unwrap()
+            .starts_with("pattern:"));
+        assert_eq!(filter.check("nothing to see"), None);
+    }
+
+    #[tokio::test]
+    async fn stops_on_a_term_split_across_deltas() {
+        let first = delta("Tell me about Proj");
+        let (body, rule) = run(vec![
+            first.clone(),
+            delta("ect Ze"),
+            delta("us plans"),
+            "data: [DONE]\n\n".to_string(),
+        ])
+        .await;
+        assert_eq!(rule.as_deref(), Some("blocklist:Project Zeus"));
+        let expected = format!(
+            "{}{}{}",
+            first,
+            delta("ect Ze"),
+            String::from_utf8_lossy(VIOLATION_EVENT)
+        );
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn clean_streams_pass_through_unchanged() {
+        let chunks = vec![
+            ": keep-alive\r\n\r\n".to_string(),
+            delta("Hello"),
+            // An event split across chunks is forwarded once complete
+            "data: {\"choices\":[{\"index\":0,".to_string(),
+            "\"delta\":{\"content\":\" there\"}}]}\n\ndata: [DONE]".to_string(),
+        ];
+        let (body, rule) = run(chunks.clone()).await;
+        assert_eq!(rule, None);
+        assert_eq!(body, chunks.concat());
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-conversation cost attribution.
+//!
+//! Clients name the conversation a request belongs to with the
+//! `X-Arbstr-Conversation-Id` header or `metadata.conversation_id` in the
+//! request body (the header wins). The ID is stored on the request row,
+//! grouped by `/v1/stats?group_by=conversation`, and totalled by
+//! `GET /v1/conversations/{id}` so a chat application can check what a
+//! conversation has cost so far before sending its next turn.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Serialize;
+
+use super::server::AppState;
+use super::types::ChatCompletionRequest;
+use crate::error::Error;
+use crate::storage;
+
+/// Header naming the conversation a request belongs to.
+pub const ARBSTR_CONVERSATION_ID_HEADER: &str = "x-arbstr-conversation-id";
+
+/// Maximum length of a conversation ID (bytes).
+pub const MAX_CONVERSATION_ID_LEN: usize = 128;
+
+/// Validate a conversation ID, trimming surrounding whitespace.
+pub fn validate(id: &str) -> Result<String, Error> {
+    let id = id.trim();
+    if id.is_empty() {
+        return Err(Error::BadRequest("Conversation ID is empty".to_string()));
+    }
+    if id.len() > MAX_CONVERSATION_ID_LEN {
+        return Err(Error::BadRequest(format!(
+            "Conversation ID exceeds the {} character limit",
+            MAX_CONVERSATION_ID_LEN
+        )));
+    }
+    if id.chars().any(char::is_control) {
+        return Err(Error::BadRequest(
+            "Conversation ID must not contain control characters".to_string(),
+        ));
+    }
+    Ok(id.to_string())
+}
+
+/// Conversation ID from the `X-Arbstr-Conversation-Id` header, if sent.
+pub fn from_headers(headers: &HeaderMap) -> Result<Option<String>, Error> {
+    headers
+        .get(ARBSTR_CONVERSATION_ID_HEADER)
+        .map(|v| {
+            v.to_str()
+                .map_err(|_| {
+                    Error::BadRequest("X-Arbstr-Conversation-Id must be valid ASCII".to_string())
+                })
+                .and_then(validate)
+        })
+        .transpose()
+}
+
+/// Conversation ID from `metadata.conversation_id` in the body, if set.
+pub fn from_body(request: &ChatCompletionRequest) -> Result<Option<String>, Error> {
+    match request
+        .extra
+        .get("metadata")
+        .and_then(|m| m.get("conversation_id"))
+    {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(id)) => validate(id).map(Some),
+        Some(_) => Err(Error::BadRequest(
+            "metadata.conversation_id must be a string".to_string(),
+        )),
+    }
+}
+
+/// Response for GET /v1/conversations/{id}.
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    pub conversation_id: String,
+    pub requests: i64,
+    pub success: i64,
+    pub error: i64,
+    pub total_cost_sats: f64,
+    pub total_input_tokens: i64,
+    pub total_output_tokens: i64,
+    /// Timestamps of the first and latest request, absent before the first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_request_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_request_at: Option<String>,
+}
+
+/// Handle GET /v1/conversations/{id}: cumulative usage of one conversation
+/// over all logged requests. A conversation with no requests yet reports
+/// zeros, as does every conversation under `[privacy] mode = "omit"`.
+pub async fn conversation_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, Error> {
+    let id = validate(&id)?;
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+    let row = match state.privacy.stored_id(&id) {
+        Some(stored) => storage::stats::query_conversation(pool, &stored).await?,
+        None => Default::default(),
+    };
+    Ok(Json(ConversationResponse {
+        conversation_id: id,
+        requests: row.total_requests,
+        success: row.success_count,
+        error: row.error_count,
+        total_cost_sats: row.total_cost_sats,
+        total_input_tokens: row.total_input_tokens as i64,
+        total_output_tokens: row.total_output_tokens as i64,
+        first_request_at: row.first_request_at,
+        last_request_at: row.last_request_at,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(metadata: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "metadata": metadata,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_the_body_metadata() {
+        let id = from_body(&request(serde_json::json!({"conversation_id": " chat-1 "})));
+        assert_eq!(id.unwrap().as_deref(), Some("chat-1"));
+        assert_eq!(
+            from_body(&request(serde_json::json!({"user": "u"}))).unwrap(),
+            None
+        );
+        assert!(from_body(&request(serde_json::json!({"conversation_id": 7}))).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_ids() {
+        assert!(validate("  ").is_err());
+        assert!(validate(&"x".repeat(MAX_CONVERSATION_ID_LEN + 1)).is_err());
+        assert!(validate("a\nb").is_err());
+        assert_eq!(validate("conv-42").unwrap(), "conv-42");
+    }
+}
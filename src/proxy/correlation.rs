@@ -0,0 +1,107 @@
+//! Client-supplied correlation IDs.
+//!
+//! A client may send its own `x-arbstr-request-id` (a UUID) so its logs and
+//! arbstr's share one identifier; it is then used as the correlation ID
+//! instead of a generated one. Values that are not UUIDs are ignored and an
+//! ID is generated as usual. An ID may only be used once within
+//! [`DUPLICATE_WINDOW`]: a repeat is rejected, since two requests sharing a
+//! correlation ID would merge their logs, tags and attempts.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use uuid::Uuid;
+
+use super::handlers::ARBSTR_REQUEST_ID_HEADER;
+
+/// How long a client-supplied ID stays reserved.
+pub const DUPLICATE_WINDOW: Duration = Duration::from_secs(600);
+
+/// Most IDs remembered; beyond this the oldest are forgotten early.
+const MAX_TRACKED: usize = 100_000;
+
+/// A valid client-supplied ID from `headers`, if any.
+pub fn client_request_id(headers: &HeaderMap) -> Option<Uuid> {
+    let value = headers.get(ARBSTR_REQUEST_ID_HEADER)?.to_str().ok()?;
+    match Uuid::parse_str(value.trim()) {
+        Ok(id) => Some(id),
+        Err(_) => {
+            tracing::debug!(value, "Ignoring x-arbstr-request-id that is not a UUID");
+            None
+        }
+    }
+}
+
+/// Client-supplied IDs seen within [`DUPLICATE_WINDOW`].
+#[derive(Debug, Default)]
+pub struct ClientRequestIds {
+    inner: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    ids: HashSet<Uuid>,
+    /// The same IDs, oldest first.
+    order: VecDeque<(Instant, Uuid)>,
+}
+
+impl ClientRequestIds {
+    /// Reserve `id`. Returns false if it was already used within the window.
+    pub fn claim(&self, id: Uuid) -> bool {
+        self.claim_at(id, Instant::now())
+    }
+
+    fn claim_at(&self, id: Uuid, now: Instant) -> bool {
+        let mut seen = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(&(at, old)) = seen.order.front() {
+            if now.duration_since(at) < DUPLICATE_WINDOW && seen.order.len() < MAX_TRACKED {
+                break;
+            }
+            seen.order.pop_front();
+            seen.ids.remove(&old);
+        }
+        if !seen.ids.insert(id) {
+            return false;
+        }
+        seen.order.push_back((now, id));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn parses_uuids_and_ignores_other_values() {
+        let id = Uuid::new_v4();
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_request_id(&headers), None);
+
+        headers.insert(
+            ARBSTR_REQUEST_ID_HEADER,
+            HeaderValue::from_str(&id.simple().to_string()).unwrap(),
+        );
+        assert_eq!(client_request_id(&headers), Some(id));
+
+        headers.insert(ARBSTR_REQUEST_ID_HEADER, HeaderValue::from_static("req-42"));
+        assert_eq!(client_request_id(&headers), None);
+    }
+
+    #[test]
+    fn duplicates_are_rejected_within_the_window() {
+        let ids = ClientRequestIds::default();
+        let id = Uuid::new_v4();
+        let start = Instant::now();
+
+        assert!(ids.claim_at(id, start));
+        assert!(!ids.claim_at(id, start + Duration::from_secs(60)));
+        assert!(ids.claim_at(Uuid::new_v4(), start + Duration::from_secs(60)));
+
+        // Free again once the window has passed
+        assert!(ids.claim_at(id, start + DUPLICATE_WINDOW));
+    }
+}
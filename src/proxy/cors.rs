@@ -0,0 +1,70 @@
+//! CORS for browser clients (`[server.cors]`).
+//!
+//! The layer wraps every route outside auth and rate limiting, so preflight
+//! `OPTIONS` requests are answered without a token and error responses
+//! (401, 429) still carry the headers a browser needs to read them.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer, ExposeHeaders};
+
+use crate::config::CorsConfig;
+
+fn is_wildcard(values: &[String]) -> bool {
+    values.iter().any(|v| v == "*")
+}
+
+/// Build the CORS layer. Entries were checked by config validation, so
+/// unparseable ones are skipped rather than reported here.
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    let origins = if is_wildcard(&config.allowed_origins) {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+    let headers = if is_wildcard(&config.allowed_headers) {
+        AllowHeaders::from(Any)
+    } else {
+        AllowHeaders::list(
+            config
+                .allowed_headers
+                .iter()
+                .filter_map(|h| HeaderName::from_bytes(h.as_bytes()).ok()),
+        )
+    };
+    let methods = if is_wildcard(&config.allowed_methods) {
+        AllowMethods::from(Any)
+    } else {
+        AllowMethods::list(
+            config
+                .allowed_methods
+                .iter()
+                .filter_map(|m| Method::from_bytes(m.as_bytes()).ok()),
+        )
+    };
+    let expose = if is_wildcard(&config.expose_headers) {
+        ExposeHeaders::from(Any)
+    } else {
+        ExposeHeaders::list(
+            config
+                .expose_headers
+                .iter()
+                .filter_map(|h| HeaderName::from_bytes(h.as_bytes()).ok()),
+        )
+    };
+
+    let layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_headers(headers)
+        .allow_methods(methods)
+        .expose_headers(expose)
+        .allow_credentials(config.allow_credentials);
+    match config.max_age_secs {
+        Some(secs) => layer.max_age(std::time::Duration::from_secs(secs)),
+        None => layer,
+    }
+}
@@ -0,0 +1,182 @@
+//! Fiat conversion of sats costs (`[currency]`).
+//!
+//! Holds the current price of one bitcoin in the configured currency, from
+//! a static `btc_price` or polled from `price_url`. Each logged request
+//! stores the price in effect, `/v1/stats` and `/v1/requests` report fiat
+//! costs from those stored prices, and non-streaming responses carry the
+//! converted cost in `x-arbstr-cost-<code>` (e.g. `x-arbstr-cost-usd`).
+
+use std::sync::RwLock;
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue};
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::config::CurrencyConfig;
+
+/// Timeout for one price fetch.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// The exchange rate in effect. Inert when `[currency]` is not configured.
+#[derive(Debug, Default)]
+pub struct ExchangeRate {
+    code: Option<String>,
+    btc_price: RwLock<Option<f64>>,
+}
+
+/// A currency and the price of one bitcoin in it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateSnapshot {
+    pub currency: String,
+    pub btc_price: f64,
+}
+
+impl RateSnapshot {
+    /// Convert a sats amount at this rate.
+    pub fn convert(&self, sats: f64) -> f64 {
+        sats * self.btc_price / SATS_PER_BTC
+    }
+}
+
+impl ExchangeRate {
+    pub fn new(config: Option<&CurrencyConfig>) -> Self {
+        Self {
+            code: config.map(|c| c.code.to_ascii_uppercase()),
+            btc_price: RwLock::new(config.and_then(|c| c.btc_price)),
+        }
+    }
+
+    /// Configured currency code, if any.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// The current rate, once a price is known.
+    pub fn snapshot(&self) -> Option<RateSnapshot> {
+        let currency = self.code.clone()?;
+        let btc_price = (*self.btc_price.read().unwrap_or_else(|e| e.into_inner()))?;
+        Some(RateSnapshot {
+            currency,
+            btc_price,
+        })
+    }
+
+    /// Replace the current price.
+    pub fn set(&self, btc_price: f64) {
+        *self.btc_price.write().unwrap_or_else(|e| e.into_inner()) = Some(btc_price);
+    }
+
+    /// Add `x-arbstr-cost-<code>` for a request that cost `cost_sats`.
+    pub fn apply_header(&self, headers: &mut axum::http::HeaderMap, cost_sats: Option<f64>) {
+        let (Some(rate), Some(cost)) = (self.snapshot(), cost_sats) else {
+            return;
+        };
+        let name = format!("x-arbstr-cost-{}", rate.currency.to_ascii_lowercase());
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::try_from(name),
+            HeaderValue::from_str(&format!("{:.6}", rate.convert(cost))),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Fetch the bitcoin price from `config.price_url`.
+pub async fn fetch_price(client: &Client, config: &CurrencyConfig) -> Result<f64, String> {
+    let url = config
+        .price_url
+        .as_deref()
+        .ok_or_else(|| "no price_url configured".to_string())?;
+    let body: serde_json::Value = client
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let value = body
+        .pointer(&config.price_pointer)
+        .ok_or_else(|| format!("no value at '{}'", config.price_pointer))?;
+    let price = match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("value at '{}' is not a number", config.price_pointer))?;
+
+    if price.is_finite() && price > 0.0 {
+        Ok(price)
+    } else {
+        Err(format!("invalid price {}", price))
+    }
+}
+
+/// Poll `price_url` every `refresh_secs` until cancelled. A failed fetch
+/// keeps the previous price.
+pub async fn rate_loop(
+    client: Client,
+    config: CurrencyConfig,
+    rate: std::sync::Arc<ExchangeRate>,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.refresh_secs));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match fetch_price(&client, &config).await {
+                    Ok(price) => {
+                        tracing::debug!(currency = %config.code, btc_price = price, "Exchange rate updated");
+                        rate.set(price);
+                    }
+                    Err(e) => {
+                        tracing::warn!(currency = %config.code, error = %e, "Exchange rate fetch failed, keeping previous rate");
+                    }
+                }
+            }
+            _ = cancel.changed() => {
+                tracing::info!("Exchange rate task stopping");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(btc_price: Option<f64>) -> CurrencyConfig {
+        CurrencyConfig {
+            code: "usd".to_string(),
+            btc_price,
+            price_url: None,
+            price_pointer: "/data/amount".to_string(),
+            refresh_secs: 300,
+        }
+    }
+
+    #[test]
+    fn converts_at_the_current_price() {
+        assert!(ExchangeRate::default().snapshot().is_none());
+        assert!(ExchangeRate::new(Some(&config(None))).snapshot().is_none());
+
+        let rate = ExchangeRate::new(Some(&config(Some(50_000.0))));
+        let snap = rate.snapshot().unwrap();
+        assert_eq!(snap.currency, "USD");
+        assert_eq!(snap.convert(2_000.0), 1.0);
+
+        rate.set(100_000.0);
+        assert_eq!(rate.snapshot().unwrap().convert(2_000.0), 2.0);
+
+        let mut headers = axum::http::HeaderMap::new();
+        rate.apply_header(&mut headers, Some(2_000.0));
+        assert_eq!(headers["x-arbstr-cost-usd"], "2.000000");
+    }
+}
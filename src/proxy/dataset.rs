@@ -0,0 +1,213 @@
+//! Dataset capture (`[policies.rules.dataset_capture]`).
+//!
+//! A sample of a policy's successful non-streaming requests is appended to
+//! a JSONL file, one [`DatasetRecord`] per line: the messages as forwarded,
+//! the first choice's reply, and the serving provider, tokens, cost and
+//! latency. Files are rotated by size to `<path>.1` (newest) through
+//! `<path>.<keep>`. With `sanitize` on, email addresses and API keys in
+//! message text are redacted and message `name`s are dropped before
+//! anything reaches disk.
+//!
+//! Writes happen off the request path; a failed write is logged and the
+//! record dropped.
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::types::{Message, MessageContent};
+use crate::config::DatasetCaptureConfig;
+
+static EMAIL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}")
+        .expect("email regex")
+});
+
+/// Provider API keys (`sk-...`), bearer tokens, and Nostr secret keys.
+static SECRET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}|bearer\s+[A-Za-z0-9._~+/=-]{16,}|nsec1[a-z0-9]{20,})",
+    )
+    .expect("secret regex")
+});
+
+/// One captured prompt/response pair, a line in the dataset file.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetRecord {
+    pub timestamp: String,
+    pub request_id: String,
+    pub policy: Option<String>,
+    /// Model the client asked for.
+    pub model: String,
+    pub provider: String,
+    /// Messages as forwarded to the provider.
+    pub messages: Vec<Message>,
+    /// The first choice's `message` object.
+    pub response: serde_json::Value,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cost_sats: Option<f64>,
+    pub latency_ms: i64,
+}
+
+impl DatasetRecord {
+    /// Redact email addresses and API keys in message and reply text, and
+    /// drop message `name`s.
+    pub fn sanitize(&mut self) {
+        for message in &mut self.messages {
+            message.name = None;
+            match &mut message.content {
+                MessageContent::Text(text) => *text = redact(text),
+                MessageContent::Parts(parts) => {
+                    for part in parts {
+                        if let Some(text) = part.get_mut("text") {
+                            redact_value(text);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(content) = self.response.get_mut("content") {
+            redact_value(content);
+        }
+    }
+}
+
+/// `text` with email addresses and API keys replaced by placeholders.
+pub fn redact(text: &str) -> String {
+    let text = EMAIL_RE.replace_all(text, "[EMAIL]");
+    SECRET_RE.replace_all(&text, "[REDACTED]").into_owned()
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    if let serde_json::Value::String(text) = value {
+        *text = redact(text);
+    }
+}
+
+/// Whether a request is captured under `config`.
+pub fn sampled(config: &DatasetCaptureConfig) -> bool {
+    config.sample_rate >= 1.0 || rand::random::<f64>() < config.sample_rate
+}
+
+/// Appends records to dataset files. One lock serializes all writes, so
+/// rotation never races an append.
+#[derive(Debug, Default)]
+pub struct DatasetCapture {
+    lock: Mutex<()>,
+}
+
+impl DatasetCapture {
+    /// Append `record` to the dataset file of `config` in the background,
+    /// sanitizing it first when configured.
+    pub fn capture(self: &Arc<Self>, config: &DatasetCaptureConfig, mut record: DatasetRecord) {
+        if config.sanitize {
+            record.sanitize();
+        }
+        let this = self.clone();
+        let path = PathBuf::from(&config.path);
+        let (max_file_bytes, keep) = (config.max_file_bytes, config.keep);
+        tokio::task::spawn_blocking(move || {
+            let result = serde_json::to_vec(&record)
+                .map_err(std::io::Error::from)
+                .and_then(|mut line| {
+                    line.push(b'\n');
+                    let _guard = this.lock.lock().unwrap_or_else(|e| e.into_inner());
+                    append(&path, &line, max_file_bytes, keep)
+                });
+            if let Err(e) = result {
+                tracing::warn!(
+                    path = %path.display(),
+                    request_id = %record.request_id,
+                    error = %e,
+                    "Failed to capture dataset record"
+                );
+            }
+        });
+    }
+}
+
+/// Append `line` to `path`, rotating first if it would grow past
+/// `max_file_bytes`. A file is never rotated while empty, so a single
+/// oversized line still gets written.
+fn append(path: &Path, line: &[u8], max_file_bytes: u64, keep: usize) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > max_file_bytes {
+        rotate(path, keep)?;
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line)
+}
+
+/// `<path>.<n>`.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Shift `<path>.1`..`<path>.<keep - 1>` up by one, dropping `<path>.<keep>`,
+/// and move `path` to `<path>.1`. With `keep = 0` the file is deleted.
+fn rotate(path: &Path, keep: usize) -> std::io::Result<()> {
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+    match std::fs::remove_file(rotated_path(path, keep)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    for n in (1..keep).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            std::fs::rename(from, rotated_path(path, n + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("arbstr-dataset-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn redacts_emails_and_keys() {
+        assert_eq!(
+            redact("Mail jane.doe@example.co.uk with sk-abcdefghijklmnop1234 or Bearer abcdefghijklmnopqrstuv"),
+            "Mail [EMAIL] with [REDACTED] or [REDACTED]"
+        );
+        assert_eq!(redact("no secrets in sk-short"), "no secrets in sk-short");
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_the_newest() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("data.jsonl");
+        for i in 0..5 {
+            append(&path, format!("line-{}\n", i).as_bytes(), 10, 2).unwrap();
+        }
+        let read = |p: PathBuf| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "line-4\n");
+        assert_eq!(read(rotated_path(&path, 1)), "line-3\n");
+        assert_eq!(read(rotated_path(&path, 2)), "line-2\n");
+        assert!(!rotated_path(&path, 3).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
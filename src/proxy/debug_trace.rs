@@ -0,0 +1,216 @@
+//! Per-request decision traces and `/v1/requests/{id}/trace`.
+//!
+//! A request sent with `x-arbstr-debug: true` records how it was routed:
+//! the policy it matched, every tier tried, the candidates considered at
+//! each with their routing and effective cost, circuit state, try order or
+//! the reason they sat out, the failed attempts with their backoff and
+//! timing, and the provider that finally served it. It is the explain
+//! endpoint's view of an executed request. The most recent traces are kept
+//! in memory only, keyed by `x-arbstr-request-id`.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+
+use super::circuit_breaker::CircuitState;
+use super::server::AppState;
+use crate::config::Tier;
+use crate::error::Error;
+use crate::router::SelectedProvider;
+use crate::storage::logging::{AttemptLog, RequestLog};
+
+/// Traces kept before the oldest is dropped.
+const TRACE_CAPACITY: usize = 256;
+
+/// How one request was routed.
+#[derive(Debug, Clone, Serialize)]
+pub struct DecisionTrace {
+    pub request_id: String,
+    /// When the request arrived (RFC 3339).
+    pub timestamp: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    pub streaming: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity_score: Option<f64>,
+    /// Tiers tried in order; more than one when the request escalated.
+    pub tiers: Vec<Tier>,
+    /// Per tier, the eligible candidates in try order, then those that sat out.
+    pub candidates: Vec<CandidateTrace>,
+    /// Failed upstream attempts (retries and fallbacks), in order.
+    pub attempts: Vec<AttemptLog>,
+    /// Provider that served the request, or the last one that failed it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub latency_ms: i64,
+}
+
+/// One candidate provider at one tier.
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateTrace {
+    pub provider: String,
+    pub tier: Tier,
+    /// Configured routing cost (`output_rate + base_fee`).
+    pub routing_cost: f64,
+    /// Routing cost after any reputation multiplier.
+    pub effective_cost: f64,
+    /// Circuit state once its permit was checked.
+    pub circuit_state: String,
+    /// Position in try order, from 1. Absent for skipped candidates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+    /// Whether the request was this provider's half-open probe.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub probe: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+}
+
+impl DecisionTrace {
+    pub fn new(request_id: &str, model: &str, streaming: bool) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            model: model.to_string(),
+            policy: None,
+            streaming,
+            complexity_score: None,
+            tiers: Vec::new(),
+            candidates: Vec::new(),
+            attempts: Vec::new(),
+            provider: None,
+            success: false,
+            error_status: None,
+            error: None,
+            latency_ms: 0,
+        }
+    }
+
+    /// Record the candidates considered at `tier`: `ordered` in try order,
+    /// then the rest of `considered` with `skip_reason`.
+    pub fn record_tier(
+        &mut self,
+        state: &AppState,
+        tier: Tier,
+        considered: &[SelectedProvider],
+        ordered: &[SelectedProvider],
+        probe: Option<&str>,
+        skip_reason: impl Fn(&SelectedProvider) -> String,
+    ) {
+        let entry = |c: &SelectedProvider, rank: Option<usize>, skip_reason: Option<String>| {
+            CandidateTrace {
+                provider: c.name.clone(),
+                tier,
+                routing_cost: c.output_rate + c.base_fee,
+                effective_cost: state.reputation.effective_cost(c),
+                circuit_state: state
+                    .circuit_breakers
+                    .state(&c.name)
+                    .unwrap_or(CircuitState::Closed)
+                    .as_str()
+                    .to_string(),
+                rank,
+                probe: rank.is_some() && probe == Some(c.name.as_str()),
+                skip_reason,
+            }
+        };
+        for (i, c) in ordered.iter().enumerate() {
+            self.candidates.push(entry(c, Some(i + 1), None));
+        }
+        for c in considered
+            .iter()
+            .filter(|c| !ordered.iter().any(|o| o.name == c.name))
+        {
+            self.candidates.push(entry(c, None, Some(skip_reason(c))));
+        }
+    }
+
+    /// Fill in the outcome from the request's log entry.
+    pub fn finish(&self, log: &RequestLog) -> Self {
+        Self {
+            policy: log.policy.clone(),
+            complexity_score: log.complexity_score,
+            attempts: log.attempts.clone(),
+            provider: log.provider.clone(),
+            success: log.success,
+            error_status: log.error_status,
+            error: log.error_message.clone(),
+            latency_ms: log.latency_ms,
+            ..self.clone()
+        }
+    }
+}
+
+/// The most recent traces, oldest first.
+#[derive(Debug, Default)]
+pub struct DebugTraces {
+    traces: Mutex<VecDeque<DecisionTrace>>,
+}
+
+impl DebugTraces {
+    /// Store `trace`, replacing an earlier one for the same request.
+    pub fn record(&self, trace: DecisionTrace) {
+        let mut traces = self.traces.lock().unwrap_or_else(|e| e.into_inner());
+        traces.retain(|t| t.request_id != trace.request_id);
+        if traces.len() == TRACE_CAPACITY {
+            traces.pop_front();
+        }
+        traces.push_back(trace);
+    }
+
+    pub fn get(&self, request_id: &str) -> Option<DecisionTrace> {
+        self.traces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .find(|t| t.request_id == request_id)
+            .cloned()
+    }
+}
+
+/// Handle GET /v1/requests/{id}/trace -- the decision trace of a request
+/// sent with `x-arbstr-debug: true`, by correlation ID.
+pub async fn trace_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<DecisionTrace>, Error> {
+    state.debug_traces.get(&id).map(Json).ok_or_else(|| {
+        Error::NotFound(format!(
+            "No trace for request '{}'; traces are kept for recent requests sent with x-arbstr-debug: true",
+            id
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_newest_traces() {
+        let traces = DebugTraces::default();
+        for i in 0..TRACE_CAPACITY + 1 {
+            traces.record(DecisionTrace::new(&format!("req-{}", i), "gpt-4o", false));
+        }
+        assert!(traces.get("req-0").is_none());
+        assert!(traces.get("req-1").is_some());
+
+        let mut finished = DecisionTrace::new("req-1", "gpt-4o", false);
+        finished.success = true;
+        traces.record(finished);
+        assert!(traces.get("req-1").unwrap().success);
+        assert_eq!(traces.traces.lock().unwrap().len(), TRACE_CAPACITY);
+    }
+}
@@ -3,6 +3,7 @@
 //! Called once during server startup. Providers with `auto_discover = true`
 //! have their static `models` list replaced with the discovered model IDs.
 
+use super::pool::ProviderClients;
 use crate::config::ProviderConfig;
 use reqwest::Client;
 use std::time::Duration;
@@ -22,6 +23,16 @@ struct ModelEntry {
 /// On success, replaces provider.models with discovered ids (exact names from endpoint).
 /// On failure, logs warning and keeps static models (non-blocking startup).
 pub async fn discover_models(providers: &mut [ProviderConfig], client: &Client) {
+    discover_models_with_clients(providers, client, &ProviderClients::default()).await;
+}
+
+/// Like [`discover_models`], but providers with a dedicated client in
+/// `clients` (pool settings or `resolve` overrides) are queried through it.
+pub async fn discover_models_with_clients(
+    providers: &mut [ProviderConfig],
+    client: &Client,
+    clients: &ProviderClients,
+) {
     for provider in providers.iter_mut() {
         if !provider.auto_discover {
             continue;
@@ -30,10 +41,16 @@ pub async fn discover_models(providers: &mut [ProviderConfig], client: &Client)
         let url = format!("{}/models", provider.url.trim_end_matches('/'));
         tracing::info!(provider = %provider.name, url = %url, "Discovering models");
 
-        let mut request = client.get(&url).timeout(Duration::from_secs(5));
-        if let Some(ref api_key) = provider.api_key {
-            request = request.bearer_auth(api_key.expose_secret());
-        }
+        let request = super::handlers::apply_provider_headers(
+            clients
+                .get(&provider.name)
+                .unwrap_or(client)
+                .get(&url)
+                .timeout(Duration::from_secs(5)),
+            provider.api_key.as_ref(),
+            provider.auth_scheme,
+            &provider.extra_headers,
+        );
 
         match request.send().await {
             Ok(resp) if resp.status().is_success() => match resp.json::<ModelsResponse>().await {
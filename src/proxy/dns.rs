@@ -0,0 +1,226 @@
+//! DNS-over-HTTPS resolver for upstream connections.
+//!
+//! Used by every upstream client when `[dns] resolver = "doh"`. Queries the
+//! JSON API (`application/dns-json`, served by Cloudflare and Google) for A
+//! and AAAA records and caches answers for their TTL. Per-provider `resolve`
+//! overrides are applied by reqwest before this resolver is consulted.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::time::Instant;
+
+use crate::config::{DnsConfig, DnsResolverKind};
+
+/// Record types requested from the DoH endpoint.
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+
+/// Timeout for a single DoH query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on how long an answer is cached, whatever its TTL.
+const MAX_CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL", default)]
+    ttl: u64,
+    data: String,
+}
+
+/// Resolves hostnames via a DNS-over-HTTPS JSON endpoint.
+#[derive(Clone)]
+pub struct DohResolver {
+    client: Client,
+    url: String,
+    cache: Arc<DashMap<String, (Vec<IpAddr>, Instant)>>,
+}
+
+impl DohResolver {
+    /// Create a resolver querying `url`. The DoH endpoint itself is reached
+    /// through the system resolver.
+    pub fn new(url: impl Into<String>) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: Client::builder().timeout(QUERY_TIMEOUT).build()?,
+            url: url.into(),
+            cache: Arc::new(DashMap::new()),
+        })
+    }
+
+    /// Resolve `host` to its A and AAAA addresses.
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        if let Some(entry) = self.cache.get(host) {
+            if entry.1 > Instant::now() {
+                return Ok(entry.0.clone());
+            }
+        }
+
+        let (v4, v6) = tokio::join!(self.query(host, RECORD_A), self.query(host, RECORD_AAAA));
+        let mut addrs = Vec::new();
+        let mut ttl = MAX_CACHE_TTL;
+        let mut errors = Vec::new();
+        for result in [v4, v6] {
+            match result {
+                Ok((ips, record_ttl)) => {
+                    addrs.extend(ips);
+                    ttl = ttl.min(record_ttl);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if addrs.is_empty() {
+            return Err(if errors.is_empty() {
+                format!("no addresses found for '{}'", host)
+            } else {
+                errors.join("; ")
+            });
+        }
+
+        tracing::debug!(host = %host, addrs = ?addrs, ttl_secs = ttl.as_secs(), "Resolved via DoH");
+        self.cache
+            .insert(host.to_string(), (addrs.clone(), Instant::now() + ttl));
+        Ok(addrs)
+    }
+
+    /// Query one record type, returning its addresses and the shortest TTL.
+    async fn query(&self, host: &str, record_type: u16) -> Result<(Vec<IpAddr>, Duration), String> {
+        let response = self
+            .client
+            .get(&self.url)
+            .query(&[("name", host), ("type", &record_type.to_string())])
+            .header("accept", "application/dns-json")
+            .send()
+            .await
+            .map_err(|e| format!("DoH query for '{}' failed: {}", host, e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "DoH query for '{}' returned {}",
+                host,
+                response.status()
+            ));
+        }
+        let body: DohResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("invalid DoH response for '{}': {}", host, e))?;
+        if body.status != 0 {
+            return Err(format!(
+                "DoH query for '{}' returned DNS status {}",
+                host, body.status
+            ));
+        }
+
+        let mut ttl = MAX_CACHE_TTL;
+        let ips = body
+            .answer
+            .into_iter()
+            .filter(|a| a.record_type == record_type)
+            .filter_map(|a| {
+                ttl = ttl.min(Duration::from_secs(a.ttl));
+                a.data.parse().ok()
+            })
+            .collect();
+        Ok((ips, ttl))
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Build the configured resolver, or `None` for the system resolver.
+pub fn resolver(config: &DnsConfig) -> Result<Option<Arc<DohResolver>>, reqwest::Error> {
+    match config.resolver {
+        DnsResolverKind::System => Ok(None),
+        DnsResolverKind::Doh => {
+            tracing::info!(url = %config.doh_url, "Using DNS-over-HTTPS resolver");
+            Ok(Some(Arc::new(DohResolver::new(&config.doh_url)?)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_doh(record_type: &str, answer: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("name", "node.example"))
+            .and(query_param("type", record_type))
+            .and(header("accept", "application/dns-json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Status": 0,
+                "Answer": answer
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn resolves_and_caches() {
+        let server = mock_doh(
+            "1",
+            serde_json::json!([
+                {"name": "node.example", "type": 5, "TTL": 60, "data": "alias.example."},
+                {"name": "alias.example", "type": 1, "TTL": 60, "data": "203.0.113.7"}
+            ]),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(query_param("type", "28"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Status": 0
+            })))
+            .mount(&server)
+            .await;
+
+        let resolver = DohResolver::new(server.uri()).unwrap();
+        let expected: Vec<IpAddr> = vec!["203.0.113.7".parse().unwrap()];
+        assert_eq!(resolver.lookup("node.example").await.unwrap(), expected);
+        // Second lookup is served from cache (mock expects one A query)
+        assert_eq!(resolver.lookup("node.example").await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn nxdomain_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Status": 3
+            })))
+            .mount(&server)
+            .await;
+
+        let resolver = DohResolver::new(server.uri()).unwrap();
+        let err = resolver.lookup("missing.example").await.unwrap_err();
+        assert!(err.contains("DNS status 3"), "{}", err);
+    }
+}
@@ -0,0 +1,237 @@
+//! Debug echo endpoint.
+//!
+//! `POST /v1/debug/echo` takes a chat completion request and returns what
+//! arbstr would send upstream for it, without sending anything: the
+//! provider the request would most likely go to, the upstream URL, the
+//! headers, and the body after policy matching, accumulation, token
+//! limits, prompt compression, trimming, system prompt injection and
+//! `stream_options` injection. Provider API keys and `extra_headers`
+//! values are redacted.
+//!
+//! The body goes through the same rewrite functions as a proxied request
+//! ([`rewrite_request`] and [`Rewrites::apply_routed`](super::handlers::Rewrites::apply_routed)),
+//! run without a request context. That keeps it read-only like the routing
+//! explanation: no circuit permits are taken, no vault funds are reserved
+//! and nothing is logged or archived. Trimming only drops messages and
+//! prompt compression only applies its local steps: the summary and
+//! compression model calls are not made.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, HeaderName},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::circuit_breaker::CircuitState;
+use super::handlers::{
+    complexity_override, prompt_length_policy, rewrite_request, upstream_headers,
+    ARBSTR_POLICY_HEADER,
+};
+use super::server::{AppState, RequestId};
+use super::trace::TraceContext;
+use super::types::ChatCompletionRequest;
+use crate::config::Tier;
+use crate::error::Error;
+use crate::router::{score_complexity, score_to_max_tier, SelectedProvider};
+
+/// Client headers redacted even when forwarded by `headers.forward_request`.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+];
+
+/// Query parameters for POST /v1/debug/echo.
+#[derive(Debug, Deserialize)]
+pub struct EchoQuery {
+    /// Echo the request for this candidate instead of the likely first one.
+    pub provider: Option<String>,
+}
+
+/// Response for POST /v1/debug/echo.
+#[derive(Debug, Serialize)]
+pub struct EchoResponse {
+    pub provider: String,
+    pub tier: Tier,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    pub method: &'static str,
+    pub url: String,
+    /// Upstream headers; repeated headers are joined with ", ".
+    pub headers: BTreeMap<String, String>,
+    pub body: serde_json::Value,
+    /// What arbstr changed in the client's request, in order.
+    pub transformations: Vec<String>,
+}
+
+/// Handle POST /v1/debug/echo.
+pub async fn echo_handler(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Extension(trace): Extension<TraceContext>,
+    Query(params): Query<EchoQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<EchoResponse>, Error> {
+    let mut request: ChatCompletionRequest = serde_json::from_slice(&body)
+        .map_err(|e| Error::BadRequest(format!("Invalid chat completion request: {}", e)))?;
+    let correlation_id = request_id.0.to_string();
+
+    let policy = headers
+        .get(ARBSTR_POLICY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .or_else(|| prompt_length_policy(&state, &request));
+    let policy_name = policy.as_deref();
+
+    // The proxy's own rewrite pipeline, run without a request context so
+    // nothing is recorded or sent
+    let mut rewrites = rewrite_request(&state, &headers, policy_name, &mut request, None).await?;
+
+    let candidates = candidates(
+        &state,
+        &request,
+        policy_name,
+        complexity_override(&headers),
+        rewrites.response_format.is_some(),
+    )?;
+    let provider = match &params.provider {
+        Some(name) => candidates.iter().find(|c| &c.name == name).ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Provider '{}' is not an eligible candidate for this request",
+                name
+            ))
+        })?,
+        None => candidates
+            .iter()
+            .find(|c| state.canary.status(&c.name).is_none_or(|s| s.promoted))
+            .unwrap_or(&candidates[0]),
+    };
+
+    rewrites.apply_routed(&state, &headers, &mut request, policy_name, &correlation_id);
+    let mut transformations = rewrites.transformations;
+    if request.stream.unwrap_or(false) {
+        let include_usage = request
+            .stream_options
+            .as_ref()
+            .and_then(|o| o.include_usage);
+        super::types::ensure_stream_options(&mut request);
+        if include_usage.is_none() {
+            transformations.push("stream_options: include_usage".to_string());
+        }
+    }
+
+    let mut forward_headers =
+        super::passthrough::select_headers(&headers, &state.config.headers.forward_request);
+    trace.apply_upstream(&mut forward_headers);
+    let mut echoed: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in &upstream_headers(provider, &correlation_id, &forward_headers) {
+        let value = if value.is_sensitive() || is_secret(name) {
+            "[REDACTED]".to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        echoed
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    Ok(Json(EchoResponse {
+        provider: provider.name.clone(),
+        tier: provider.tier,
+        policy,
+        method: "POST",
+        url: format!("{}/chat/completions", provider.url.trim_end_matches('/')),
+        headers: echoed,
+        body: serde_json::to_value(&request).map_err(|e| Error::Internal(e.to_string()))?,
+        transformations,
+    }))
+}
+
+fn is_secret(name: &HeaderName) -> bool {
+    SECRET_HEADERS.contains(&name.as_str())
+}
+
+/// Eligible providers in likely try order, mirroring `resolve_candidates`
+/// without taking circuit permits: the tier comes from the complexity
+/// override, the policy's tier or the complexity score, and escalates while
+/// no provider at it is available.
+fn candidates(
+    state: &AppState,
+    request: &ChatCompletionRequest,
+    policy_name: Option<&str>,
+    complexity_override: Option<Tier>,
+    structured_output: bool,
+) -> Result<Vec<SelectedProvider>, Error> {
+    let routing = &state.config.routing;
+    let user_prompt = request.user_prompt();
+    let max_tier = complexity_override.unwrap_or_else(|| {
+        let score = score_complexity(&request.messages, &routing.complexity_weights);
+        score_to_max_tier(
+            score,
+            routing.complexity_threshold_low,
+            routing.complexity_threshold_high,
+        )
+    });
+    let mut tier = state
+        .router
+        .policy_tier(policy_name, user_prompt)
+        .unwrap_or(max_tier);
+    loop {
+        let candidates = match state.router.select_candidates(
+            &request.model,
+            policy_name,
+            user_prompt,
+            Some(tier),
+        ) {
+            Ok(candidates) => candidates,
+            Err(Error::NoTierMatch { .. }) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let circuit_state = |name: &str| {
+            state
+                .circuit_breakers
+                .state(name)
+                .unwrap_or(CircuitState::Closed)
+        };
+        let available: Vec<SelectedProvider> = candidates
+            .into_iter()
+            .filter(|c| !structured_output || c.structured_output)
+            .filter(|c| !state.maintenance.is_unavailable(&c.name))
+            .filter(|c| !state.auth_quarantine.is_quarantined(&c.name))
+            .filter(|c| circuit_state(&c.name) != CircuitState::Open)
+            .collect();
+        if !available.is_empty() {
+            let probe_provider = available
+                .iter()
+                .find(|c| circuit_state(&c.name) == CircuitState::HalfOpen)
+                .map(|c| c.name.clone());
+            let ordered = state.tie_breaks.apply(available, probe_provider.as_deref());
+            let ordered = state.reputation.apply(ordered, probe_provider.as_deref());
+            let ordered = state
+                .quality
+                .apply(ordered, &request.model, probe_provider.as_deref());
+            if !ordered.is_empty() {
+                return Ok(ordered);
+            }
+        }
+        match tier.escalate() {
+            Some(next) => tier = next,
+            None => {
+                return Err(Error::NoTierMatch {
+                    tier,
+                    model: request.model.clone(),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,392 @@
+//! Scheduled model evaluation (`[evaluation]`).
+//!
+//! Once a day, at `hour_utc`, every prompt in the suite is sent to each
+//! provider/model pair, outside normal routing: nothing is logged as a
+//! request and circuit breakers are untouched. Each reply is scored on
+//! whether it arrived and passes the prompt's `expect`/`pattern` checks,
+//! with its latency and token cost kept alongside in the `evaluations`
+//! table. A pair's quality score is its mean score over the latest run,
+//! shown in `/v1/route/explain`; with `min_quality_score` set, providers
+//! scoring below it for the requested model are tried after the others.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, SecondsFormat, TimeZone, Utc};
+use sqlx::SqlitePool;
+
+use super::server::AppState;
+use crate::config::{EvaluationConfig, EvaluationPrompt, ProviderConfig};
+use crate::router::SelectedProvider;
+use crate::storage::evaluations::{self, EvaluationRow};
+
+/// Latest quality score (0.0-1.0) per provider/model pair.
+#[derive(Debug, Default)]
+pub struct QualityScores {
+    /// `min_quality_score`; None leaves routing order alone.
+    min_score: Option<f64>,
+    scores: Mutex<HashMap<(String, String), f64>>,
+}
+
+impl QualityScores {
+    pub fn new(config: Option<&EvaluationConfig>) -> Self {
+        Self {
+            min_score: config.and_then(|c| c.min_quality_score),
+            scores: Mutex::default(),
+        }
+    }
+
+    /// Score for `model` on `provider`, if the pair has been evaluated.
+    pub fn get(&self, provider: &str, model: &str) -> Option<f64> {
+        self.scores
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(provider.to_string(), model.to_string()))
+            .copied()
+    }
+
+    /// Replace every score with `scores` (provider, model, score).
+    pub fn replace(&self, scores: impl IntoIterator<Item = (String, String, f64)>) {
+        *self.scores.lock().unwrap_or_else(|e| e.into_inner()) = scores
+            .into_iter()
+            .map(|(provider, model, score)| ((provider, model), score))
+            .collect();
+    }
+
+    /// Move candidates scoring below `min_quality_score` for `model` behind
+    /// the rest, keeping the relative order within each group. A circuit
+    /// probe stays first.
+    pub fn apply(
+        &self,
+        candidates: Vec<SelectedProvider>,
+        model: &str,
+        probe_provider: Option<&str>,
+    ) -> Vec<SelectedProvider> {
+        let Some(min_score) = self.min_score else {
+            return candidates;
+        };
+        let (mut good, poor): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| {
+            if Some(c.name.as_str()) == probe_provider {
+                return true;
+            }
+            match self.get(&c.name, model) {
+                Some(score) if score < min_score => {
+                    tracing::debug!(
+                        provider = %c.name,
+                        model,
+                        score,
+                        "Deprioritizing provider: evaluation quality score below minimum"
+                    );
+                    false
+                }
+                _ => true,
+            }
+        });
+        good.extend(poor);
+        good
+    }
+
+    /// Load the latest run's scores saved by a previous process.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        self.replace(evaluations::latest_scores(pool).await?);
+        Ok(())
+    }
+}
+
+/// Next run time at `hour_utc`, strictly after `now`.
+pub fn next_run(now: DateTime<Utc>, hour_utc: u32) -> DateTime<Utc> {
+    let today = Utc
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), hour_utc, 0, 0)
+        .unwrap();
+    if today > now {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    }
+}
+
+/// Whether `reply` passes `prompt`'s checks; None when it has none.
+pub fn check_reply(prompt: &EvaluationPrompt, reply: &str) -> Option<bool> {
+    if prompt.expect.is_none() && prompt.pattern.is_none() {
+        return None;
+    }
+    let exact = prompt
+        .expect
+        .as_ref()
+        .is_none_or(|expect| reply.trim() == expect.trim());
+    // Patterns are validated at config load
+    let matched = prompt
+        .pattern
+        .as_ref()
+        .is_none_or(|pattern| regex::Regex::new(pattern).is_ok_and(|re| re.is_match(reply)));
+    Some(exact && matched)
+}
+
+/// Run the suite against every provider/model pair, store the results, and
+/// update `state.quality`. Returns the results.
+pub async fn run(state: &AppState, config: &EvaluationConfig) -> Vec<EvaluationRow> {
+    let run_id = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let start = Instant::now();
+    let pairs = state.config.providers.iter().flat_map(|provider| {
+        provider
+            .models
+            .iter()
+            .map(move |model| (provider, model.as_str()))
+    });
+
+    // Pairs run concurrently, each pair's prompts one at a time
+    let rows: Vec<EvaluationRow> =
+        futures::future::join_all(pairs.map(|(provider, model)| async move {
+            let mut rows = Vec::with_capacity(config.prompts.len());
+            for prompt in &config.prompts {
+                rows.push(evaluate(state, config, provider, model, prompt).await);
+            }
+            rows
+        }))
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    state.quality.replace(scores(&rows));
+    if let Some(pool) = &state.db {
+        if let Err(e) = evaluations::insert_run(pool, &run_id, &rows).await {
+            tracing::warn!(error = %e, "Failed to store evaluation results");
+        }
+    }
+    tracing::info!(
+        results = rows.len(),
+        passed = rows.iter().filter(|r| r.score >= 1.0).count(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "Evaluation run complete"
+    );
+    rows
+}
+
+/// Mean score per (provider, model).
+fn scores(rows: &[EvaluationRow]) -> Vec<(String, String, f64)> {
+    let mut totals: HashMap<(&str, &str), (f64, u32)> = HashMap::new();
+    for row in rows {
+        let total = totals
+            .entry((row.provider.as_str(), row.model.as_str()))
+            .or_default();
+        total.0 += row.score;
+        total.1 += 1;
+    }
+    totals
+        .into_iter()
+        .map(|((provider, model), (sum, n))| {
+            (provider.to_string(), model.to_string(), sum / n as f64)
+        })
+        .collect()
+}
+
+async fn evaluate(
+    state: &AppState,
+    config: &EvaluationConfig,
+    provider: &ProviderConfig,
+    model: &str,
+    prompt: &EvaluationPrompt,
+) -> EvaluationRow {
+    let client = state
+        .provider_clients
+        .get(&provider.name)
+        .unwrap_or(&state.http_client);
+    let request = super::handlers::apply_provider_headers(
+        client
+            .post(format!(
+                "{}/chat/completions",
+                provider.url.trim_end_matches('/')
+            ))
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": prompt.prompt}],
+                "max_tokens": config.max_tokens
+            })),
+        provider.api_key.as_ref(),
+        provider.auth_scheme,
+        &provider.extra_headers,
+    );
+
+    let start = Instant::now();
+    let mut row = EvaluationRow {
+        provider: provider.name.clone(),
+        model: model.to_string(),
+        prompt: prompt.name.clone(),
+        success: false,
+        status: None,
+        latency_ms: 0,
+        input_tokens: None,
+        output_tokens: None,
+        cost_sats: None,
+        passed: None,
+        score: 0.0,
+    };
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!(provider = %provider.name, model, prompt = %prompt.name, error = %e, "Evaluation request failed");
+            row.latency_ms = start.elapsed().as_millis() as i64;
+            return row;
+        }
+    };
+    row.status = Some(i64::from(response.status().as_u16()));
+    row.success = response.status().is_success();
+    let body = response.json::<serde_json::Value>().await.ok();
+    row.latency_ms = start.elapsed().as_millis() as i64;
+    if !row.success {
+        return row;
+    }
+
+    let body = body.unwrap_or_default();
+    let usage = &body["usage"];
+    if let (Some(input), Some(output)) = (
+        usage["prompt_tokens"].as_u64(),
+        usage["completion_tokens"].as_u64(),
+    ) {
+        row.input_tokens = Some(input as i64);
+        row.output_tokens = Some(output as i64);
+        row.cost_sats = Some(crate::router::actual_cost_sats(
+            input as u32,
+            output as u32,
+            provider.input_rate,
+            provider.output_rate,
+            provider.base_fee,
+            provider.min_charge_sats,
+        ));
+    }
+    let reply = body["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or_default();
+    row.passed = check_reply(prompt, reply);
+    row.score = match row.passed {
+        Some(false) => 0.0,
+        _ => 1.0,
+    };
+    row
+}
+
+/// Background task: run the suite daily at `hour_utc`.
+pub async fn evaluation_loop(state: AppState, cancel: tokio::sync::watch::Receiver<bool>) {
+    let Some(config) = state.config.evaluation.clone() else {
+        return;
+    };
+
+    loop {
+        let now = Utc::now();
+        let next = next_run(now, config.hour_utc);
+        let wait = (next - now).to_std().unwrap_or_default();
+        tracing::debug!(next = %next, "Next evaluation run");
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {
+                run(&state, &config).await;
+            }
+            _ = super::vault::cancel_wait(&cancel) => {
+                tracing::info!("Evaluation task shutting down");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(expect: Option<&str>, pattern: Option<&str>) -> EvaluationPrompt {
+        EvaluationPrompt {
+            name: "p".to_string(),
+            prompt: "What is 2+2?".to_string(),
+            expect: expect.map(str::to_string),
+            pattern: pattern.map(str::to_string),
+        }
+    }
+
+    fn provider(name: &str) -> ProviderConfig {
+        ProviderConfig {
+            models: vec!["gpt-4o".to_string()],
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    #[test]
+    fn next_run_is_today_or_tomorrow() {
+        let morning = Utc.with_ymd_and_hms(2026, 10, 15, 1, 30, 0).unwrap();
+        assert_eq!(
+            next_run(morning, 3),
+            Utc.with_ymd_and_hms(2026, 10, 15, 3, 0, 0).unwrap()
+        );
+        let at_hour = Utc.with_ymd_and_hms(2026, 10, 15, 3, 0, 0).unwrap();
+        assert_eq!(
+            next_run(at_hour, 3),
+            Utc.with_ymd_and_hms(2026, 10, 16, 3, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn replies_are_checked_against_expect_and_pattern() {
+        assert_eq!(check_reply(&prompt(None, None), "anything"), None);
+        assert_eq!(check_reply(&prompt(Some("4"), None), " 4\n"), Some(true));
+        assert_eq!(check_reply(&prompt(Some("4"), None), "four"), Some(false));
+        assert_eq!(
+            check_reply(&prompt(None, Some(r"\b4\b")), "It is 4."),
+            Some(true)
+        );
+        assert_eq!(
+            check_reply(&prompt(Some("4"), Some("^5$")), "4"),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn low_scoring_providers_are_tried_last() {
+        let config = EvaluationConfig {
+            hour_utc: 3,
+            max_tokens: 64,
+            timeout_secs: 30,
+            min_quality_score: Some(0.8),
+            prompts: vec![prompt(None, None)],
+        };
+        let quality = QualityScores::new(Some(&config));
+        quality.replace([
+            ("alpha".to_string(), "gpt-4o".to_string(), 0.5),
+            ("beta".to_string(), "gpt-4o".to_string(), 0.9),
+        ]);
+        let candidates = || {
+            ["alpha", "beta", "gamma"]
+                .map(|name| SelectedProvider::from(&provider(name)))
+                .to_vec()
+        };
+        let names = |c: Vec<SelectedProvider>| c.into_iter().map(|p| p.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(quality.apply(candidates(), "gpt-4o", None)),
+            ["beta", "gamma", "alpha"]
+        );
+        // Other models and circuit probes are unaffected
+        assert_eq!(
+            names(quality.apply(candidates(), "llama-3", None)),
+            ["alpha", "beta", "gamma"]
+        );
+        assert_eq!(
+            names(quality.apply(candidates(), "gpt-4o", Some("alpha"))),
+            ["alpha", "beta", "gamma"]
+        );
+    }
+
+    #[test]
+    fn quality_scores_are_replaced_per_run() {
+        let quality = QualityScores::default();
+        assert_eq!(quality.get("alpha", "gpt-4o"), None);
+
+        quality.replace([("alpha".to_string(), "gpt-4o".to_string(), 0.5)]);
+        assert_eq!(quality.get("alpha", "gpt-4o"), Some(0.5));
+
+        quality.replace([("beta".to_string(), "gpt-4o".to_string(), 1.0)]);
+        assert_eq!(quality.get("alpha", "gpt-4o"), None);
+        assert_eq!(quality.get("beta", "gpt-4o"), Some(1.0));
+    }
+}
@@ -0,0 +1,181 @@
+//! Routing explanation endpoint.
+//!
+//! Shows how a request for a model would be routed right now: every
+//! candidate provider with its configured routing cost, circuit state,
+//! reputation (rolling error rate, latency, and any active demotion), and
+//! the effective cost used for ordering, plus whether the policy's schedule
+//! lets it route normally. Providers out of rotation for maintenance are
+//! listed as skipped. Read-only: no circuit permits are taken and
+//! nothing is recorded.
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::canary::CanaryStatus;
+use super::circuit_breaker::CircuitState;
+use super::reputation::ReputationSnapshot;
+use super::server::AppState;
+use crate::config::Tier;
+use crate::error::Error;
+use crate::router::{PolicyEvaluation, SelectedProvider};
+
+/// Query parameters for GET /v1/route/explain.
+#[derive(Debug, Deserialize)]
+pub struct ExplainQuery {
+    pub model: String,
+    /// Policy name, as sent in `X-Arbstr-Policy`.
+    pub policy: Option<String>,
+    /// Maximum tier, as sent in `X-Arbstr-Tier`. No tier filter when absent.
+    pub tier: Option<Tier>,
+    /// Evaluate policy schedules at this RFC 3339 time instead of now.
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// Response for GET /v1/route/explain.
+#[derive(Debug, Serialize)]
+pub struct ExplainResponse {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier: Option<Tier>,
+    /// Why the named policy applies in full or only up to its off-hours tier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_status: Option<PolicyEvaluation>,
+    /// Provider the next request would most likely try first, if any is
+    /// eligible. Unpromoted canaries only take their `canary_percent` slice,
+    /// so they are passed over here unless nothing else is eligible.
+    pub selected: Option<String>,
+    /// Eligible providers in try order, followed by skipped providers.
+    pub candidates: Vec<CandidateExplanation>,
+}
+
+/// One candidate provider.
+#[derive(Debug, Serialize)]
+pub struct CandidateExplanation {
+    pub provider: String,
+    pub tier: Tier,
+    /// Configured routing cost (`output_rate + base_fee`).
+    pub routing_cost: f64,
+    /// Routing cost after any reputation multiplier.
+    pub effective_cost: f64,
+    pub circuit_state: String,
+    /// Present when `[routing.reputation]` is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reputation: Option<ReputationSnapshot>,
+    /// Present for providers configured with `canary = true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryStatus>,
+    /// Latest `[evaluation]` score for this model, once evaluated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_score: Option<f64>,
+    pub eligible: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+}
+
+/// Candidates that passed the circuit filter but were dropped by a
+/// reputation exclusion.
+fn closed_excluded<'a>(
+    all: &'a [SelectedProvider],
+    ordered: &[SelectedProvider],
+    open: &[SelectedProvider],
+) -> impl Iterator<Item = &'a SelectedProvider> {
+    let kept: Vec<String> = ordered.iter().chain(open).map(|c| c.name.clone()).collect();
+    all.iter().filter(move |c| !kept.contains(&c.name))
+}
+
+/// Handle GET /v1/route/explain.
+pub async fn explain_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ExplainQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let now = params.at.unwrap_or_else(Utc::now);
+    let policy_status = state
+        .router
+        .evaluate_policy(params.policy.as_deref(), None, now);
+    let candidates = state.router.select_candidates_at(
+        &params.model,
+        params.policy.as_deref(),
+        None,
+        params.tier,
+        now,
+    )?;
+
+    // Disabled providers and providers in a maintenance window sit out
+    let (candidates, unavailable): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .map(|c| {
+            let reason = state.maintenance.unavailable_at(&c.name, now);
+            (c, reason)
+        })
+        .partition(|(_, reason)| reason.is_none());
+    let candidates: Vec<SelectedProvider> = candidates.into_iter().map(|(c, _)| c).collect();
+
+    let circuit_state = |name: &str| {
+        state
+            .circuit_breakers
+            .state(name)
+            .unwrap_or(CircuitState::Closed)
+    };
+
+    // Mirror resolve_candidates: open circuits are skipped, the first
+    // half-open provider would be probed ahead of the rest.
+    let probe_provider = candidates
+        .iter()
+        .find(|c| circuit_state(&c.name) == CircuitState::HalfOpen)
+        .map(|c| c.name.clone());
+    let (closed, open): (Vec<_>, Vec<_>) = candidates
+        .iter()
+        .cloned()
+        .partition(|c| circuit_state(&c.name) != CircuitState::Open);
+    let ordered = state.tie_breaks.apply(closed, probe_provider.as_deref());
+    let ordered = state.reputation.apply(ordered, probe_provider.as_deref());
+    let ordered = state
+        .quality
+        .apply(ordered, &params.model, probe_provider.as_deref());
+
+    let explain = |c: &SelectedProvider, skip_reason: Option<String>| CandidateExplanation {
+        provider: c.name.clone(),
+        tier: c.tier,
+        routing_cost: c.output_rate + c.base_fee,
+        effective_cost: state.reputation.effective_cost(c),
+        circuit_state: circuit_state(&c.name).as_str().to_string(),
+        reputation: state.reputation.snapshot(&c.name),
+        canary: state.canary.status(&c.name),
+        quality_score: state.quality.get(&c.name, &params.model),
+        eligible: skip_reason.is_none(),
+        skip_reason,
+    };
+
+    let mut explained: Vec<CandidateExplanation> =
+        ordered.iter().map(|c| explain(c, None)).collect();
+    for c in &open {
+        explained.push(explain(c, Some("circuit open".to_string())));
+    }
+    // Providers dropped by a reputation exclusion
+    for c in closed_excluded(&candidates, &ordered, &open) {
+        explained.push(explain(c, Some("reputation exclusion".to_string())));
+    }
+    for (c, reason) in &unavailable {
+        explained.push(explain(c, reason.clone()));
+    }
+
+    Ok(Json(ExplainResponse {
+        model: params.model,
+        policy: params.policy,
+        tier: params.tier,
+        policy_status,
+        selected: ordered
+            .iter()
+            .find(|c| state.canary.status(&c.name).is_none_or(|s| s.promoted))
+            .or(ordered.first())
+            .map(|c| c.name.clone()),
+        candidates: explained,
+    }))
+}
@@ -0,0 +1,496 @@
+//! Spending forecast endpoint.
+//!
+//! Fits a least-squares line to recent per-bucket spend (hourly or daily) and
+//! extrapolates it to the end of the current calendar month (UTC). The result
+//! is added to month-to-date spend to give a projected monthly total with
+//! approximate 95% bounds, so budget alerts can fire before a limit is hit.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use super::stats::RangePreset;
+use crate::error::Error;
+use crate::storage;
+use crate::storage::stats::SpendGrouping;
+
+/// z-score for a two-sided 95% interval.
+const Z_95: f64 = 1.96;
+
+/// Query parameters for GET /v1/stats/forecast.
+#[derive(Debug, Deserialize)]
+pub struct ForecastQuery {
+    /// Bucket width: `hourly` or `daily` (default `daily`).
+    pub interval: Option<String>,
+    /// Lookback window preset used to fit the trend.
+    pub range: Option<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    /// Tag filter in `key=value` form.
+    pub tag: Option<String>,
+    /// Split the forecast by `model`, `provider`, or `tag:<key>`.
+    pub group_by: Option<String>,
+    /// Monthly budget in sats to compare the projection against.
+    pub budget_sats: Option<f64>,
+}
+
+/// Bucket width for the burn-rate regression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForecastInterval {
+    Hourly,
+    Daily,
+}
+
+impl ForecastInterval {
+    /// Parse an interval string.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "hourly" => Some(Self::Hourly),
+            "daily" => Some(Self::Daily),
+            _ => None,
+        }
+    }
+
+    /// Name used in responses.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+        }
+    }
+
+    /// Number of buckets per day.
+    pub fn buckets_per_day(&self) -> i64 {
+        match self {
+            Self::Hourly => 24,
+            Self::Daily => 1,
+        }
+    }
+
+    /// Width of one bucket.
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Hourly => Duration::hours(1),
+            Self::Daily => Duration::days(1),
+        }
+    }
+
+    /// Lookback used when no `range` is given.
+    fn default_range(&self) -> RangePreset {
+        match self {
+            Self::Hourly => RangePreset::Last24h,
+            Self::Daily => RangePreset::Last7d,
+        }
+    }
+}
+
+/// Result of extrapolating a spend series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Fitted spend per bucket at the end of the lookback window (>= 0).
+    pub burn_rate: f64,
+    /// Projected spend over the horizon (>= 0).
+    pub remaining: f64,
+    /// Standard deviation of the projected spend over the horizon.
+    pub std_dev: f64,
+}
+
+/// Extrapolate a per-bucket spend series over `horizon` future buckets.
+///
+/// `series` is ordered oldest to newest, one entry per complete bucket.
+/// A least-squares line is fitted through the bucket midpoints and
+/// integrated over the horizon; negative rates are clamped to zero. The
+/// standard deviation treats bucket residuals as independent, so it grows
+/// with the square root of the horizon. Fewer than three buckets give a
+/// zero deviation.
+pub fn project(series: &[f64], horizon: f64) -> Projection {
+    let n = series.len();
+    if n == 0 || horizon <= 0.0 {
+        return Projection {
+            burn_rate: 0.0,
+            remaining: 0.0,
+            std_dev: 0.0,
+        };
+    }
+
+    let nf = n as f64;
+    let mean_x = (nf - 1.0) / 2.0;
+    let mean_y = series.iter().sum::<f64>() / nf;
+
+    let (mut sxx, mut sxy) = (0.0, 0.0);
+    for (i, y) in series.iter().enumerate() {
+        let dx = i as f64 - mean_x;
+        sxx += dx * dx;
+        sxy += dx * (y - mean_y);
+    }
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    let intercept = mean_y - slope * mean_x;
+    let rate_at = |x: f64| intercept + slope * x;
+
+    // Bucket i covers [i - 0.5, i + 0.5]; the horizon starts at n - 0.5.
+    let start = nf - 0.5;
+    let avg_rate = rate_at(start + horizon / 2.0);
+    let remaining = (avg_rate * horizon).max(0.0);
+
+    let std_dev = if n > 2 {
+        let ssr: f64 = series
+            .iter()
+            .enumerate()
+            .map(|(i, y)| (y - rate_at(i as f64)).powi(2))
+            .sum();
+        (ssr / (nf - 2.0)).sqrt() * horizon.sqrt()
+    } else {
+        0.0
+    };
+
+    Projection {
+        burn_rate: rate_at(nf - 1.0).max(0.0),
+        remaining,
+        std_dev,
+    }
+}
+
+/// Start of the calendar month containing `now` and start of the next one (UTC).
+fn month_bounds(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .unwrap();
+    let (next_year, next_month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    let end = Utc
+        .with_ymd_and_hms(next_year, next_month, 1, 0, 0, 0)
+        .unwrap();
+    (start, end)
+}
+
+/// Top-level forecast response.
+#[derive(Debug, Serialize)]
+pub struct ForecastResponse {
+    pub generated_at: String,
+    pub interval: &'static str,
+    pub lookback: LookbackSection,
+    pub period: PeriodSection,
+    pub total: ForecastEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, ForecastEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetSection>,
+}
+
+/// Window the trend was fitted over.
+#[derive(Debug, Serialize)]
+pub struct LookbackSection {
+    pub since: String,
+    pub until: String,
+    pub buckets: usize,
+}
+
+/// Calendar month being forecast.
+#[derive(Debug, Serialize)]
+pub struct PeriodSection {
+    pub start: String,
+    pub end: String,
+}
+
+/// Forecast for one series (the total or a single group).
+#[derive(Debug, Serialize)]
+pub struct ForecastEntry {
+    pub month_to_date_sats: f64,
+    /// Fitted spend per interval at the end of the lookback window.
+    pub burn_rate_sats: f64,
+    pub projected_sats: f64,
+    pub lower_sats: f64,
+    pub upper_sats: f64,
+}
+
+/// Projection compared against a monthly budget.
+#[derive(Debug, Serialize)]
+pub struct BudgetSection {
+    pub limit_sats: f64,
+    /// Projected total exceeds the budget.
+    pub projected_over: bool,
+    /// Upper bound exceeds the budget.
+    pub at_risk: bool,
+}
+
+/// Combine month-to-date spend with a projection into a forecast entry.
+fn forecast_entry(month_to_date: f64, series: &[f64], horizon: f64) -> ForecastEntry {
+    let p = project(series, horizon);
+    let margin = Z_95 * p.std_dev;
+    ForecastEntry {
+        month_to_date_sats: month_to_date,
+        burn_rate_sats: p.burn_rate,
+        projected_sats: month_to_date + p.remaining,
+        lower_sats: month_to_date + (p.remaining - margin).max(0.0),
+        upper_sats: month_to_date + p.remaining + margin,
+    }
+}
+
+/// Time window shared by every series in a forecast.
+struct ForecastWindow<'a> {
+    lookback_since: &'a str,
+    until: &'a str,
+    month_start: &'a str,
+    interval: ForecastInterval,
+    buckets: usize,
+    horizon: f64,
+}
+
+/// Build a forecast entry per group for the given grouping.
+async fn forecast_groups(
+    pool: &sqlx::SqlitePool,
+    window: &ForecastWindow<'_>,
+    grouping: SpendGrouping<'_>,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<BTreeMap<String, ForecastEntry>, Error> {
+    let buckets = window.buckets;
+    let history = storage::stats::query_spend_buckets(
+        pool,
+        window.lookback_since,
+        window.until,
+        window.interval.buckets_per_day(),
+        grouping,
+        model,
+        provider,
+        tag,
+    )
+    .await?;
+    let month = storage::stats::query_spend_buckets(
+        pool,
+        window.month_start,
+        window.until,
+        1,
+        grouping,
+        model,
+        provider,
+        tag,
+    )
+    .await?;
+
+    // Series are ordered oldest to newest; bucket 0 is the most recent.
+    let mut series: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for row in history {
+        let age = row.bucket.clamp(0, buckets as i64 - 1) as usize;
+        series
+            .entry(row.group_key)
+            .or_insert_with(|| vec![0.0; buckets])[buckets - 1 - age] += row.cost_sats;
+    }
+    let mut month_to_date: BTreeMap<String, f64> = BTreeMap::new();
+    for row in month {
+        *month_to_date.entry(row.group_key).or_default() += row.cost_sats;
+    }
+
+    let keys: BTreeSet<String> = series.keys().chain(month_to_date.keys()).cloned().collect();
+    Ok(keys
+        .into_iter()
+        .map(|key| {
+            let s = series.remove(&key).unwrap_or_else(|| vec![0.0; buckets]);
+            let mtd = month_to_date.get(&key).copied().unwrap_or(0.0);
+            let entry = forecast_entry(mtd, &s, window.horizon);
+            (key, entry)
+        })
+        .collect())
+}
+
+/// Handle GET /v1/stats/forecast -- projected end-of-month spend.
+pub async fn forecast_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ForecastQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let interval = match params.interval.as_deref() {
+        Some(s) => ForecastInterval::parse(s).ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Invalid interval '{}'. Supported: hourly, daily",
+                s
+            ))
+        })?,
+        None => ForecastInterval::Daily,
+    };
+
+    let lookback = match params.range.as_deref() {
+        Some(r) => RangePreset::parse(r).ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Invalid range '{}'. Supported: last_1h, last_24h, last_7d, last_30d",
+                r
+            ))
+        })?,
+        None => interval.default_range(),
+    };
+    let buckets = (lookback.duration().num_seconds() / interval.duration().num_seconds()) as usize;
+    if buckets < 2 {
+        return Err(Error::BadRequest(format!(
+            "Range must span at least two {} buckets",
+            interval.as_str()
+        )));
+    }
+
+    if let Some(budget) = params.budget_sats {
+        if !budget.is_finite() || budget < 0.0 {
+            return Err(Error::BadRequest(
+                "budget_sats must be a non-negative number".to_string(),
+            ));
+        }
+    }
+
+    // Validate model/provider filters
+    if let Some(ref model_filter) = params.model {
+        super::validation::validate_model_filter(&state.config, pool, model_filter).await?;
+    }
+    if let Some(ref provider_filter) = params.provider {
+        super::validation::validate_provider_filter(&state.config, pool, provider_filter).await?;
+    }
+
+    let tag_filter = params
+        .tag
+        .as_deref()
+        .map(super::tags::parse_tag_filter)
+        .transpose()?;
+    let tag = tag_filter.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+
+    let group_tag_key = match params.group_by.as_deref() {
+        Some(gb) => super::tags::parse_group_by_tag(gb).transpose()?,
+        None => None,
+    };
+    let grouping = match (params.group_by.as_deref(), &group_tag_key) {
+        (_, Some(key)) => Some(SpendGrouping::Tag(key)),
+        (Some("model"), None) => Some(SpendGrouping::Model),
+        (Some("provider"), None) => Some(SpendGrouping::Provider),
+        (Some(_), None) => {
+            return Err(Error::BadRequest(
+                "Invalid group_by value. Supported: 'model', 'provider', 'tag:<key>'".to_string(),
+            ))
+        }
+        (None, None) => None,
+    };
+
+    let now = Utc::now();
+    let (month_start, month_end) = month_bounds(now);
+    let lookback_since = now - interval.duration() * buckets as i32;
+    let horizon = (month_end - now).num_seconds() as f64 / interval.duration().num_seconds() as f64;
+
+    let now_str = now.to_rfc3339();
+    let lookback_since_str = lookback_since.to_rfc3339();
+    let month_start_str = month_start.to_rfc3339();
+
+    tracing::debug!(
+        interval = interval.as_str(),
+        buckets = buckets,
+        group_by = ?params.group_by,
+        model = ?params.model,
+        provider = ?params.provider,
+        tag = ?params.tag,
+        "Forecast query"
+    );
+
+    let window = ForecastWindow {
+        lookback_since: &lookback_since_str,
+        until: &now_str,
+        month_start: &month_start_str,
+        interval,
+        buckets,
+        horizon,
+    };
+    let model = params.model.as_deref();
+    let provider = params.provider.as_deref();
+
+    let total = forecast_groups(pool, &window, SpendGrouping::Total, model, provider, tag)
+        .await?
+        .remove("total")
+        .unwrap_or_else(|| forecast_entry(0.0, &[], horizon));
+
+    let groups = match grouping {
+        Some(g) => Some(forecast_groups(pool, &window, g, model, provider, tag).await?),
+        None => None,
+    };
+
+    let budget = params.budget_sats.map(|limit| BudgetSection {
+        limit_sats: limit,
+        projected_over: total.projected_sats > limit,
+        at_risk: total.upper_sats > limit,
+    });
+
+    Ok(Json(ForecastResponse {
+        generated_at: now.to_rfc3339(),
+        interval: interval.as_str(),
+        lookback: LookbackSection {
+            since: lookback_since_str,
+            until: now_str,
+            buckets,
+        },
+        period: PeriodSection {
+            start: month_start_str,
+            end: month_end.to_rfc3339(),
+        },
+        total,
+        group_by: groups.as_ref().and(params.group_by.clone()),
+        groups,
+        budget,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_flat_series() {
+        let p = project(&[10.0, 10.0, 10.0, 10.0], 5.0);
+        assert!((p.burn_rate - 10.0).abs() < 1e-9);
+        assert!((p.remaining - 50.0).abs() < 1e-9);
+        assert!(p.std_dev.abs() < 1e-9);
+    }
+
+    #[test]
+    fn project_linear_growth() {
+        // y = i + 1; horizon of 2 buckets covers x in [3.5, 5.5], avg rate 5.5
+        let p = project(&[1.0, 2.0, 3.0, 4.0], 2.0);
+        assert!((p.burn_rate - 4.0).abs() < 1e-9);
+        assert!((p.remaining - 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn project_declining_clamps_to_zero() {
+        let p = project(&[30.0, 20.0, 10.0, 0.0], 10.0);
+        assert_eq!(p.burn_rate, 0.0);
+        assert_eq!(p.remaining, 0.0);
+    }
+
+    #[test]
+    fn project_noisy_series_has_spread() {
+        let p = project(&[5.0, 15.0, 5.0, 15.0, 5.0, 15.0], 4.0);
+        assert!(p.std_dev > 0.0);
+    }
+
+    #[test]
+    fn project_empty_or_no_horizon() {
+        assert_eq!(project(&[], 5.0).remaining, 0.0);
+        assert_eq!(project(&[1.0, 2.0], 0.0).remaining, 0.0);
+    }
+
+    #[test]
+    fn month_bounds_handles_december() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 15, 12, 0, 0).unwrap();
+        let (start, end) = month_bounds(now);
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+    }
+}
@@ -1,28 +1,57 @@
 //! HTTP request handlers.
 
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use axum::{
     body::Body,
-    extract::{Extension, State},
+    extract::{Extension, FromRequest, State},
     http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use futures::future::Either;
 use tokio::time::{timeout_at, Duration, Instant};
 
 use super::circuit_breaker::{CircuitState, PermitType, ProbeGuard};
-use super::retry::{format_retries_header, retry_with_fallback, AttemptRecord, CandidateInfo};
+use super::debug_trace::DecisionTrace;
+use super::race;
+use super::recent::RecentRequest;
+use super::retry::{
+    format_retries_header, retry_with_fallback, AttemptRecord, CandidateInfo, HasStatusCode,
+};
+use super::retry_budget::RETRY_BUDGET_TAG;
 use super::server::{AppState, RequestId};
+use super::structured::ResponseFormat;
+use super::trace::TraceContext;
+use super::trim;
 use super::types::ChatCompletionRequest;
 use super::vault::{SettleMetadata, VaultClient};
-use crate::config::Tier;
-use crate::error::Error;
-use crate::router::{score_complexity, score_to_max_tier};
-use crate::storage::logging::RequestLog;
-
+use crate::config::{
+    ApiKey, AuthScheme, BackoffConfig, LoggingMode, PromptCompressionConfig, Tier, TrimConfig,
+    TrimStrategy,
+};
+use crate::error::{Error, ProviderErrorKind};
+use crate::router::{score_complexity, score_to_max_tier, PromptVars};
+use crate::storage::logging::{AttemptLog, RequestLog};
+use crate::storage::vouchers::VOUCHER_TAG;
+
+pub use super::arbitrage::arbitrage_handler as arbitrage;
+pub use super::compare::comparison_handler as comparison;
+pub use super::compression::compression_stats_handler as compression_stats;
+pub use super::explain::explain_handler as route_explain;
+pub use super::forecast::forecast_handler as forecast;
+pub use super::limits::limits_stats_handler as limits_stats;
 pub use super::logs::logs_handler as logs;
+pub use super::logs::request_detail_handler as request_detail;
+pub use super::receipts::receipt_handler as receipt;
+pub use super::recent::recent_handler as recent_requests;
+pub use super::reliability::reliability_handler as reliability;
+pub use super::scorecard::scorecard_handler as provider_scorecard;
 pub use super::stats::stats_handler as stats;
+pub use super::truncation::truncation_handler as truncation;
+pub use super::warmup::ready_handler as ready;
 
 /// Custom header for policy selection.
 pub const ARBSTR_POLICY_HEADER: &str = "x-arbstr-policy";
@@ -30,7 +59,19 @@ pub const ARBSTR_POLICY_HEADER: &str = "x-arbstr-policy";
 /// Custom header for complexity tier override.
 pub const ARBSTR_COMPLEXITY_HEADER: &str = "x-arbstr-complexity";
 
-/// Response header: correlation ID (UUID v4).
+/// Custom header carrying a policy's `system_prompt_bypass_token`, letting a
+/// trusted client skip the policy's system prompt.
+pub const ARBSTR_SYSTEM_PROMPT_BYPASS_HEADER: &str = "x-arbstr-system-prompt-bypass";
+
+/// Custom header asking for a streamed upstream response to be returned as
+/// one JSON body ("true"), or opting out of a policy's `accumulate` ("false").
+pub const ARBSTR_ACCUMULATE_HEADER: &str = "x-arbstr-accumulate";
+
+/// Custom header for cost allocation tags (e.g. "team=search,env=prod").
+pub const ARBSTR_TAGS_HEADER: &str = "x-arbstr-tags";
+
+/// Response header: correlation ID (UUID v4, or the client's own UUID when
+/// it sent one in this header; see `correlation`).
 pub const ARBSTR_REQUEST_ID_HEADER: &str = "x-arbstr-request-id";
 /// Response header: actual cost in satoshis (decimal, e.g. "42.35").
 pub const ARBSTR_COST_SATS_HEADER: &str = "x-arbstr-cost-sats";
@@ -42,14 +83,46 @@ pub const ARBSTR_PROVIDER_HEADER: &str = "x-arbstr-provider";
 pub const ARBSTR_STREAMING_HEADER: &str = "x-arbstr-streaming";
 /// Response header: retry attempt history (e.g. "2/provider-alpha, 1/provider-beta").
 pub const ARBSTR_RETRIES_HEADER: &str = "x-arbstr-retries";
+/// Response header: "exhausted" when a retry was skipped for lack of retry budget.
+pub const ARBSTR_RETRY_BUDGET_HEADER: &str = "x-arbstr-retry-budget";
 /// Response header: complexity score (3 decimal places, e.g. "0.423").
 pub const ARBSTR_COMPLEXITY_SCORE_HEADER: &str = "x-arbstr-complexity-score";
 /// Response header: complexity tier (local, standard, frontier).
 pub const ARBSTR_TIER_HEADER: &str = "x-arbstr-tier";
+/// Response header: outcome of the policy's response checks ("passed",
+/// "retried" after a higher-tier retry passed, or "failed").
+pub const ARBSTR_VALIDATION_HEADER: &str = "x-arbstr-validation";
+
+/// Response header: outcome of the proxy's `response_format` check
+/// ("passed", "reasked" after a conforming re-ask, or "failed").
+pub const ARBSTR_STRUCTURED_OUTPUT_HEADER: &str = "x-arbstr-structured-output";
+
+/// Response header: "injected" when a policy filled in an omitted
+/// `max_tokens`, or "clamped" when it lowered the client's limit.
+pub const ARBSTR_MAX_TOKENS_HEADER: &str = "x-arbstr-max-tokens";
+
+/// Response header: "injected" when the policy's system prompt was put ahead
+/// of the conversation, or "bypassed" when a trusted client skipped it.
+pub const ARBSTR_SYSTEM_PROMPT_HEADER: &str = "x-arbstr-system-prompt";
+
+/// Log tag key recording a skipped policy system prompt ("bypassed").
+const SYSTEM_PROMPT_TAG: &str = "system_prompt";
+/// Log tag key recording a `max_tokens` adjustment ("injected" or "clamped").
+const MAX_TOKENS_TAG: &str = "max_tokens";
+/// Log tag key marking a streamed response returned as one JSON body ("true").
+const ACCUMULATE_TAG: &str = "accumulated";
+/// Log tag key recording a response check outcome ("failed" or "retried").
+const VALIDATION_TAG: &str = "validation";
+/// Log tag key recording a `response_format` check outcome ("failed" or
+/// "reasked").
+const STRUCTURED_OUTPUT_TAG: &str = "structured_output";
 
 /// Total timeout for the retry+fallback chain (30 seconds).
 const RETRY_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Output tokens assumed when `max_tokens` is unset, for cost estimates.
+pub(super) const ESTIMATE_OUTPUT_TOKENS: u32 = 256;
+
 /// Outcome of a successful request, containing the response and metadata for logging.
 pub(crate) struct RequestOutcome {
     pub(crate) response: Response,
@@ -58,6 +131,13 @@ pub(crate) struct RequestOutcome {
     pub(crate) output_tokens: Option<u32>,
     pub(crate) cost_sats: Option<f64>,
     pub(crate) provider_cost_sats: Option<f64>,
+    /// Upstream finish_reason (non-streaming only; streams record it on completion).
+    pub(crate) finish_reason: Option<String>,
+    /// Streaming only: signalled once the request row insert is queued, so the
+    /// stream completion UPDATE cannot reach the writer ahead of it.
+    pub(crate) row_queued: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Whether the response is an SSE stream.
+    pub(crate) streamed: bool,
 }
 
 /// Outcome of a failed request, containing the error and metadata for logging.
@@ -66,12 +146,20 @@ pub(crate) struct RequestError {
     pub(crate) provider_name: Option<String>,
     pub(crate) status_code: u16,
     pub(crate) message: String,
+    /// Set for failures talking to the provider; internal errors leave it
+    /// unset and are classified by status code.
+    pub(crate) kind: Option<ProviderErrorKind>,
 }
 
 impl super::retry::HasStatusCode for RequestError {
     fn status_code(&self) -> u16 {
         self.status_code
     }
+
+    fn error_kind(&self) -> ProviderErrorKind {
+        self.kind
+            .unwrap_or_else(|| ProviderErrorKind::from_status(self.status_code))
+    }
 }
 
 /// Extract token usage from a provider response.
@@ -85,12 +173,14 @@ fn extract_usage(response: &serde_json::Value) -> Option<(u32, u32)> {
     Some((input, output))
 }
 
-/// Whether an HTTP status code should be recorded as a circuit breaker failure.
-///
-/// Returns true for 5xx server errors (aligned with retry::is_retryable).
-/// Returns false for 4xx client errors and all other codes.
-fn is_circuit_failure(status_code: u16) -> bool {
-    (500..600).contains(&status_code)
+/// Extract `choices[0].finish_reason` from a provider response.
+fn extract_finish_reason(response: &serde_json::Value) -> Option<String> {
+    response
+        .get("choices")?
+        .get(0)?
+        .get("finish_reason")?
+        .as_str()
+        .map(str::to_string)
 }
 
 /// Attach arbstr metadata headers to a response.
@@ -106,6 +196,13 @@ fn attach_arbstr_headers(
     cost_sats: Option<f64>,
     is_streaming: bool,
 ) {
+    let metadata = super::metadata::ResponseMetadata::of(response);
+    metadata.provider = provider.map(str::to_string);
+    if !is_streaming {
+        metadata.latency_ms = Some(latency_ms);
+        metadata.cost_sats = cost_sats;
+    }
+
     let headers = response.headers_mut();
 
     // Always present
@@ -129,7 +226,7 @@ fn attach_arbstr_headers(
         );
         // Non-streaming: include cost if known
         if let Some(cost) = cost_sats {
-            let cost_str = format!("{:.2}", cost);
+            let cost_str = format!("{:.3}", cost);
             if let Ok(val) = HeaderValue::from_str(&cost_str) {
                 headers.insert(HeaderName::from_static(ARBSTR_COST_SATS_HEADER), val);
             } else {
@@ -148,8 +245,25 @@ fn attach_arbstr_headers(
     }
 }
 
+/// Apply a policy's `response_headers` and `omit_headers` to a response.
+fn apply_policy_headers(response: &mut Response, set: &BTreeMap<String, String>, omit: &[String]) {
+    let headers = response.headers_mut();
+    for name in omit {
+        headers.remove(name.to_ascii_lowercase().as_str());
+    }
+    for (name, value) in set {
+        // Validated at config load
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.to_ascii_lowercase().as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
 /// Shared context for a chat completion request.
-struct RequestContext {
+pub(super) struct RequestContext {
     correlation_id: String,
     model: String,
     policy_name: Option<String>,
@@ -157,6 +271,41 @@ struct RequestContext {
     start: std::time::Instant,
     /// Vault reservation ID, present when vault billing is active.
     reservation_id: Option<String>,
+    /// Cost allocation tags from the `X-Arbstr-Tags` header.
+    tags: Vec<(String, String)>,
+    /// Failed upstream attempts, once the retry chain has run.
+    attempts: Vec<AttemptLog>,
+    /// Circuit breaker states when the request was routed (JSON object).
+    circuit_snapshot: Option<String>,
+    /// Client headers allowed through by `headers.forward_request`, plus
+    /// arbstr's `traceparent` and `x-request-id`.
+    forward_headers: HeaderMap,
+    /// Trace ID and client request ID, recorded alongside the correlation ID.
+    trace: TraceContext,
+    /// JSON `response_format` requested by the client, if any.
+    response_format: Option<ResponseFormat>,
+    /// SHA-256 (hex) of the request body, when it was buffered.
+    request_sha256: Option<String>,
+    /// Strict logging: why the record of an earlier billable call (a
+    /// summary, or a response rejected by checks) couldn't be saved.
+    unrecorded: Option<String>,
+    /// Whether a streamed response is read whole and returned as JSON
+    /// (see [`super::accumulate`]).
+    accumulate: bool,
+    /// Estimated prompt tokens removed by `[policies.rules.compress]`.
+    prompt_tokens_saved: Option<u32>,
+    /// Config version active at dispatch.
+    config_version: Option<i64>,
+    /// Client conversation the request belongs to (see [`super::conversation`]).
+    conversation_id: Option<String>,
+    /// Messages of an unsampled request, archived if it fails.
+    archive_on_error: Option<String>,
+    /// Decision trace, for requests sent with `x-arbstr-debug: true`.
+    debug_trace: Option<DecisionTrace>,
+    /// Cap on streamed output from `x-arbstr-max-output-tokens`.
+    max_output_tokens: Option<u32>,
+    /// ID of the voucher paying for the request (`x-arbstr-voucher`).
+    voucher: Option<String>,
 }
 
 /// Result of candidate resolution and circuit breaker filtering.
@@ -167,6 +316,27 @@ struct ResolvedCandidates {
     tier: Tier,
 }
 
+/// Every provider's circuit breaker state, as the JSON object stored in
+/// `requests.circuit_snapshot`.
+fn circuit_snapshot(state: &AppState) -> String {
+    let snapshot: BTreeMap<String, serde_json::Value> = state
+        .circuit_breakers
+        .all_states()
+        .into_iter()
+        .map(|c| {
+            let mut value = serde_json::json!({
+                "state": c.state.as_str(),
+                "failure_count": c.failure_count,
+            });
+            if !c.failures_by_type.is_empty() {
+                value["failures_by_type"] = serde_json::json!(c.failures_by_type);
+            }
+            (c.name, value)
+        })
+        .collect();
+    serde_json::to_string(&snapshot).unwrap_or_default()
+}
+
 /// Map a routing error to an HTTP status code.
 fn routing_error_status(e: &Error) -> u16 {
     match e {
@@ -178,7 +348,8 @@ fn routing_error_status(e: &Error) -> u16 {
     }
 }
 
-/// Log a failed request to the database via the bounded writer.
+/// Log a failed request to the recent-request buffer and, via the bounded
+/// writer, the database.
 #[allow(clippy::too_many_arguments)]
 fn log_error_to_db(
     state: &AppState,
@@ -186,66 +357,195 @@ fn log_error_to_db(
     latency_ms: i64,
     provider: Option<String>,
     status_code: u16,
+    error_type: Option<ProviderErrorKind>,
     message: String,
     complexity_score: Option<f64>,
     tier: Option<String>,
 ) {
+    if let Some(messages) = &ctx.archive_on_error {
+        archive_prompt(state, ctx, messages);
+    }
+    if let Some(provider) = provider.as_deref().filter(|_| status_code >= 500) {
+        state.slo.record(provider, false, latency_ms.max(0) as u64);
+    }
+    let rate = state.exchange_rate.snapshot();
+    let mut log = RequestLog {
+        correlation_id: ctx.correlation_id.clone(),
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        model: ctx.model.clone(),
+        provider,
+        policy: ctx.policy_name.clone(),
+        streaming: ctx.is_streaming,
+        input_tokens: None,
+        output_tokens: None,
+        cost_sats: None,
+        provider_cost_sats: None,
+        latency_ms,
+        success: false,
+        error_status: Some(status_code),
+        error_type: error_type.map(|kind| kind.as_str().to_string()),
+        error_message: Some(message),
+        complexity_score,
+        tier,
+        finish_reason: None,
+        tags: ctx.tags.clone(),
+        attempts: ctx.attempts.clone(),
+        circuit_snapshot: ctx.circuit_snapshot.clone(),
+        trace_id: Some(ctx.trace.trace_id.clone()),
+        client_request_id: Some(ctx.trace.request_id.clone()),
+        fiat_currency: rate.as_ref().map(|r| r.currency.clone()),
+        fiat_rate: rate.map(|r| r.btc_price),
+        request_sha256: ctx.request_sha256.clone(),
+        config_version: ctx.config_version,
+        conversation_id: ctx.conversation_id.clone(),
+    };
+    if let Some(trace) = &ctx.debug_trace {
+        state.debug_traces.record(trace.finish(&log));
+    }
+    state.privacy.apply(&mut log);
+    state.recent.record(RecentRequest::from(&log));
     if let Some(writer) = &state.db_writer {
-        writer.log_write(RequestLog {
-            correlation_id: ctx.correlation_id.clone(),
-            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            model: ctx.model.clone(),
-            provider,
-            policy: ctx.policy_name.clone(),
-            streaming: ctx.is_streaming,
-            input_tokens: None,
-            output_tokens: None,
-            cost_sats: None,
-            provider_cost_sats: None,
-            latency_ms,
-            success: false,
-            error_status: Some(status_code),
-            error_message: Some(message),
-            complexity_score,
-            tier,
-        });
+        writer.log_write(log);
     }
 }
 
-/// Log a successful request outcome to the database via the bounded writer.
-fn log_success_to_db(
+/// Log a successful request outcome to the recent-request buffer and, via
+/// the bounded writer, the database.
+///
+/// With `logging.mode = "strict"`, waits for the row to be written and
+/// returns [`Error::Unrecorded`] when it isn't, so the request can be
+/// rejected. For streaming outcomes, also releases the pending stream
+/// completion update.
+async fn log_success_to_db(
     state: &AppState,
     ctx: &RequestContext,
     latency_ms: i64,
-    outcome: &RequestOutcome,
+    outcome: &mut RequestOutcome,
     complexity_score: Option<f64>,
     tier: Option<String>,
-) {
-    if let Some(writer) = &state.db_writer {
-        writer.log_write(RequestLog {
-            correlation_id: ctx.correlation_id.clone(),
-            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            model: ctx.model.clone(),
-            provider: Some(outcome.provider_name.clone()),
-            policy: ctx.policy_name.clone(),
-            streaming: ctx.is_streaming,
-            input_tokens: outcome.input_tokens,
-            output_tokens: outcome.output_tokens,
-            cost_sats: outcome.cost_sats,
-            provider_cost_sats: outcome.provider_cost_sats,
-            latency_ms,
-            success: true,
-            error_status: None,
-            error_message: None,
-            complexity_score,
-            tier,
-        });
+) -> Result<(), Error> {
+    let rate = state.exchange_rate.snapshot();
+    let mut log = RequestLog {
+        correlation_id: ctx.correlation_id.clone(),
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        model: ctx.model.clone(),
+        provider: Some(outcome.provider_name.clone()),
+        policy: ctx.policy_name.clone(),
+        streaming: ctx.is_streaming,
+        input_tokens: outcome.input_tokens,
+        output_tokens: outcome.output_tokens,
+        cost_sats: outcome.cost_sats,
+        provider_cost_sats: outcome.provider_cost_sats,
+        latency_ms,
+        success: true,
+        error_status: None,
+        error_type: None,
+        error_message: None,
+        complexity_score,
+        tier,
+        finish_reason: outcome.finish_reason.clone(),
+        tags: ctx.tags.clone(),
+        attempts: ctx.attempts.clone(),
+        circuit_snapshot: ctx.circuit_snapshot.clone(),
+        trace_id: Some(ctx.trace.trace_id.clone()),
+        client_request_id: Some(ctx.trace.request_id.clone()),
+        fiat_currency: rate.as_ref().map(|r| r.currency.clone()),
+        fiat_rate: rate.map(|r| r.btc_price),
+        request_sha256: ctx.request_sha256.clone(),
+        config_version: ctx.config_version,
+        conversation_id: ctx.conversation_id.clone(),
+    };
+    if let Some(saved) = ctx.prompt_tokens_saved {
+        let input_rate = state
+            .config
+            .providers
+            .iter()
+            .find(|p| p.name == outcome.provider_name)
+            .map_or(0.0, |p| p.input_rate);
+        let sats = saved as f64 * input_rate / 1000.0;
+        state.compression.record_prompt_savings(sats);
+        log.tags.push((
+            super::prompt_compression::SATS_SAVED_TAG.to_string(),
+            format!("{:.3}", sats),
+        ));
+    }
+    state
+        .slo
+        .record(&outcome.provider_name, true, latency_ms.max(0) as u64);
+    if let Some(trace) = &ctx.debug_trace {
+        state.debug_traces.record(trace.finish(&log));
+    }
+    state.privacy.apply(&mut log);
+    state.recent.record(RecentRequest::from(&log));
+    // Streams are charged once their usage arrives
+    if let Some(cost) = outcome.cost_sats {
+        state.ledger.debit(&outcome.provider_name, cost);
+    }
+    if let Some(voucher) = &ctx.voucher {
+        match outcome.cost_sats {
+            Some(cost) => state.vouchers.debit(voucher, cost),
+            None if outcome.row_queued.is_some() => {
+                state.vouchers.await_stream(&ctx.correlation_id, voucher)
+            }
+            None => {}
+        }
+    }
+    if let (Some(input), Some(output)) = (outcome.input_tokens, outcome.output_tokens) {
+        state
+            .quotas
+            .record_tokens(&outcome.provider_name, input as u64 + output as u64);
+    }
+    let receipt = super::receipts::Receipt::from_log(&log, state.receipts.public_key());
+    let recorded = match (&state.db_writer, state.config.logging.mode) {
+        (Some(writer), LoggingMode::BestEffort) => {
+            writer.log_write(log);
+            Ok(())
+        }
+        (None, LoggingMode::BestEffort) => Ok(()),
+        (Some(writer), LoggingMode::Strict) => writer
+            .log_write_confirmed(
+                log,
+                Duration::from_millis(state.config.logging.strict_timeout_ms),
+            )
+            .await
+            .map_err(Error::Unrecorded),
+        (None, LoggingMode::Strict) => Err(Error::Unrecorded("database not available".to_string())),
+    };
+    match &recorded {
+        Err(e) => {
+            tracing::error!(correlation_id = %ctx.correlation_id, error = %e, "Failed to record completed request");
+        }
+        // Streams are signed for once their usage arrives
+        Ok(()) if outcome.row_queued.is_some() => {
+            state.receipts.await_stream(&ctx.correlation_id, receipt)
+        }
+        Ok(()) => state.receipts.issue(state.db_writer.as_ref(), receipt),
     }
+    if let Some(row_queued) = outcome.row_queued.take() {
+        let _ = row_queued.send(());
+    }
+    recorded
+}
+
+/// Error response for a completed request whose record couldn't be saved
+/// under strict logging.
+fn unrecorded_response(ctx: &RequestContext, latency_ms: i64, error: Error) -> Response {
+    let mut response = error.into_response();
+    attach_arbstr_headers(
+        &mut response,
+        &ctx.correlation_id,
+        latency_ms,
+        None,
+        None,
+        false,
+    );
+    response
 }
 
 /// Attach the `x-arbstr-retries` header if present.
 fn attach_retries_header(response: &mut Response, retries_header: &Option<String>) {
     if let Some(retries_val) = retries_header {
+        super::metadata::ResponseMetadata::of(response).retries = Some(retries_val.clone());
         if let Ok(val) = HeaderValue::from_str(retries_val) {
             response
                 .headers_mut()
@@ -259,17 +559,19 @@ fn attach_retries_header(response: &mut Response, retries_header: &Option<String
 /// Select candidates and filter through circuit breakers.
 ///
 /// Scores the request via the complexity scorer (or uses header override),
-/// selects candidates at the scored tier, filters through circuit breakers,
-/// and escalates one-way (Local -> Standard -> Frontier) if the tier has
-/// no available providers.
+/// selects candidates at the scored tier, drops providers that are
+/// quarantined or whose prepaid balance can't cover `estimated_tokens`
+/// (input, output), filters through circuit breakers, and escalates one-way (Local -> Standard -> Frontier) if the tier has
+/// no available providers. Providers near their rate quota are tried last.
 ///
 /// Returns filtered candidates or an early-return error response.
 async fn resolve_candidates(
     state: &AppState,
-    ctx: &RequestContext,
+    ctx: &mut RequestContext,
     user_prompt: Option<&str>,
     messages: &[crate::proxy::types::Message],
     complexity_override: Option<Tier>,
+    estimated_tokens: (u32, u32),
 ) -> Result<ResolvedCandidates, Response> {
     let routing = &state.config.routing;
 
@@ -285,12 +587,23 @@ async fn resolve_candidates(
         );
         (Some(score), tier)
     };
+    // A policy pinned to a tier routes there whatever the score
+    let max_tier = state
+        .router
+        .policy_tier(ctx.policy_name.as_deref(), user_prompt)
+        .unwrap_or(max_tier);
 
     // Escalation loop wrapping both select_candidates AND circuit breaker filtering
     let mut current_tier = max_tier;
     loop {
+        if let Some(trace) = &mut ctx.debug_trace {
+            trace.tiers.push(current_tier);
+        }
         // Try select_candidates at current tier
-        let candidates = match state.router.select_candidates(
+        let candidates = match state.route_cache.select_candidates(
+            &state.router,
+            std::time::Duration::from_millis(state.config.routing.route_cache_ttl_ms),
+            state.circuit_breakers.transitions(),
             &ctx.model,
             ctx.policy_name.as_deref(),
             user_prompt,
@@ -319,6 +632,7 @@ async fn resolve_candidates(
                     latency_ms,
                     None,
                     400,
+                    None,
                     message,
                     complexity_score,
                     Some(current_tier.to_string()),
@@ -349,6 +663,7 @@ async fn resolve_candidates(
                     latency_ms,
                     None,
                     status_code,
+                    None,
                     message,
                     None,
                     None,
@@ -365,10 +680,114 @@ async fn resolve_candidates(
                 return Err(response);
             }
         };
+        let considered = ctx.debug_trace.is_some().then(|| candidates.clone());
+
+        // JSON response formats need a provider that honours them
+        let candidates = if ctx.response_format.is_some() {
+            let candidates: Vec<_> = candidates
+                .into_iter()
+                .filter(|c| c.structured_output)
+                .collect();
+            if candidates.is_empty() {
+                trace_tier(
+                    state,
+                    ctx,
+                    current_tier,
+                    considered.as_deref(),
+                    &[],
+                    None,
+                    &[],
+                    estimated_tokens,
+                );
+                if let Some(next) = current_tier.escalate() {
+                    current_tier = next;
+                    continue;
+                }
+                let latency_ms = ctx.start.elapsed().as_millis() as i64;
+                let err = Error::BadRequest(format!(
+                    "No provider with structured_output serves model '{}' for response_format",
+                    ctx.model
+                ));
+                log_error_to_db(
+                    state,
+                    ctx,
+                    latency_ms,
+                    None,
+                    400,
+                    None,
+                    err.to_string(),
+                    complexity_score,
+                    Some(current_tier.to_string()),
+                );
+                let mut response = err.into_response();
+                attach_arbstr_headers(
+                    &mut response,
+                    &ctx.correlation_id,
+                    latency_ms,
+                    None,
+                    None,
+                    ctx.is_streaming,
+                );
+                return Err(response);
+            }
+            candidates
+        } else {
+            candidates
+        };
+
+        // Disabled providers and providers in a maintenance window sit out
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|c| {
+                let unavailable = state.maintenance.is_unavailable(&c.name);
+                if unavailable {
+                    tracing::debug!(provider = %c.name, "Skipping provider: maintenance");
+                }
+                !unavailable
+            })
+            .collect();
+
+        // Providers that rejected their API key sit out until re-checked
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|c| {
+                let quarantined = state.auth_quarantine.is_quarantined(&c.name);
+                if quarantined {
+                    tracing::debug!(provider = %c.name, "Skipping provider: auth quarantine");
+                }
+                !quarantined
+            })
+            .collect();
+
+        // Prepaid providers sit out once their balance runs low
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|c| {
+                let (input, output) = estimated_tokens;
+                let cost = crate::router::actual_cost_sats(
+                    input,
+                    output,
+                    c.input_rate,
+                    c.output_rate,
+                    c.base_fee,
+                    c.min_charge_sats,
+                );
+                let covered = state.ledger.can_cover(&c.name, cost);
+                if !covered {
+                    tracing::debug!(
+                        provider = %c.name,
+                        estimated_cost_sats = cost,
+                        "Skipping provider: prepaid balance too low"
+                    );
+                }
+                covered
+            })
+            .collect();
 
         // Circuit breaker filtering
         let mut filtered = Vec::new();
         let mut probe_provider: Option<String> = None;
+        let mut circuit_rejected: Vec<(String, String)> = Vec::new();
         for candidate in &candidates {
             match state.circuit_breakers.acquire_permit(&candidate.name).await {
                 Ok(PermitType::Normal) => filtered.push(candidate.clone()),
@@ -383,11 +802,24 @@ async fn resolve_candidates(
                         streaming = ctx.is_streaming,
                         "Skipping provider: circuit open"
                     );
+                    if ctx.debug_trace.is_some() {
+                        circuit_rejected.push((candidate.name.clone(), open_err.reason));
+                    }
                 }
             }
         }
 
         if filtered.is_empty() {
+            trace_tier(
+                state,
+                ctx,
+                current_tier,
+                considered.as_deref(),
+                &[],
+                None,
+                &circuit_rejected,
+                estimated_tokens,
+            );
             // All providers at this tier are circuit-broken -- try escalating (Pitfall 2)
             if let Some(next) = current_tier.escalate() {
                 tracing::warn!(
@@ -409,6 +841,7 @@ async fn resolve_candidates(
                 latency_ms,
                 None,
                 503,
+                None,
                 message,
                 complexity_score,
                 Some(current_tier.to_string()),
@@ -428,6 +861,29 @@ async fn resolve_candidates(
             return Err(response);
         }
 
+        let filtered = state.tie_breaks.apply(filtered, probe_provider.as_deref());
+        let filtered = state.reputation.apply(filtered, probe_provider.as_deref());
+        let filtered = state.canary.apply(filtered, probe_provider.as_deref());
+        let (input, output) = estimated_tokens;
+        let filtered = state.quotas.apply(
+            filtered,
+            input as u64 + output as u64,
+            probe_provider.as_deref(),
+        );
+        let filtered = state
+            .quality
+            .apply(filtered, &ctx.model, probe_provider.as_deref());
+        trace_tier(
+            state,
+            ctx,
+            current_tier,
+            considered.as_deref(),
+            &filtered,
+            probe_provider.as_deref(),
+            &circuit_rejected,
+            estimated_tokens,
+        );
+
         return Ok(ResolvedCandidates {
             candidates: filtered,
             probe_provider,
@@ -437,6 +893,51 @@ async fn resolve_candidates(
     }
 }
 
+/// Record the candidates considered at `tier` in the request's debug trace,
+/// if it has one. Each candidate missing from `ordered` is given the first
+/// filter in [`resolve_candidates`] that would have removed it.
+#[allow(clippy::too_many_arguments)]
+fn trace_tier(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    tier: Tier,
+    considered: Option<&[crate::router::SelectedProvider]>,
+    ordered: &[crate::router::SelectedProvider],
+    probe: Option<&str>,
+    circuit_rejected: &[(String, String)],
+    estimated_tokens: (u32, u32),
+) {
+    let (Some(trace), Some(considered)) = (&mut ctx.debug_trace, considered) else {
+        return;
+    };
+    let structured = ctx.response_format.is_some();
+    trace.record_tier(state, tier, considered, ordered, probe, |c| {
+        let (input, output) = estimated_tokens;
+        let cost = crate::router::actual_cost_sats(
+            input,
+            output,
+            c.input_rate,
+            c.output_rate,
+            c.base_fee,
+            c.min_charge_sats,
+        );
+        if structured && !c.structured_output {
+            "no structured_output support".to_string()
+        } else if state.maintenance.is_unavailable(&c.name) {
+            "maintenance".to_string()
+        } else if state.auth_quarantine.is_quarantined(&c.name) {
+            "auth quarantine".to_string()
+        } else if !state.ledger.can_cover(&c.name, cost) {
+            "prepaid balance too low".to_string()
+        } else if let Some((_, reason)) = circuit_rejected.iter().find(|(name, _)| *name == c.name)
+        {
+            format!("circuit open: {}", reason)
+        } else {
+            "excluded by reputation, canary, quota or quality".to_string()
+        }
+    });
+}
+
 /// Fire-and-forget vault settle in a background task.
 ///
 /// On failure after vault retries, writes to pending_settlements via direct sqlx.
@@ -533,43 +1034,149 @@ fn spawn_vault_release(
     });
 }
 
-/// Handle POST /v1/chat/completions
-pub async fn chat_completions(
-    State(state): State<AppState>,
-    Extension(request_id): Extension<RequestId>,
-    headers: HeaderMap,
-    Json(request): Json<ChatCompletionRequest>,
-) -> Result<Response, Error> {
-    let start = std::time::Instant::now();
+/// Build the request context from client headers.
+///
+/// Returns the error response to send when `X-Arbstr-Tags` is malformed.
+fn request_context(
+    state: &AppState,
+    request_id: &RequestId,
+    trace: TraceContext,
+    headers: &HeaderMap,
+    model: String,
+    is_streaming: bool,
+    start: std::time::Instant,
+) -> Result<RequestContext, Box<Response>> {
     let correlation_id = request_id.0.to_string();
-    let model = request.model.clone();
-    let is_streaming = request.stream.unwrap_or(false);
 
     let policy_name = headers
         .get(ARBSTR_POLICY_HEADER)
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let user_prompt = request.user_prompt();
+    let mut tags = match headers
+        .get(ARBSTR_TAGS_HEADER)
+        .map(|v| {
+            v.to_str()
+                .map_err(|_| Error::BadRequest("X-Arbstr-Tags must be valid ASCII".to_string()))
+                .and_then(super::tags::parse_tags)
+        })
+        .transpose()
+    {
+        Ok(tags) => tags.unwrap_or_default(),
+        Err(e) => {
+            let mut response = e.into_response();
+            attach_arbstr_headers(
+                &mut response,
+                &correlation_id,
+                start.elapsed().as_millis() as i64,
+                None,
+                None,
+                is_streaming,
+            );
+            return Err(Box::new(response));
+        }
+    };
 
-    tracing::info!(
-        model = %request.model,
-        policy = ?policy_name,
-        stream = ?request.stream,
-        "Received chat completion request"
-    );
+    let conversation_id = match super::conversation::from_headers(headers) {
+        Ok(id) => id,
+        Err(e) => {
+            let mut response = e.into_response();
+            attach_arbstr_headers(
+                &mut response,
+                &correlation_id,
+                start.elapsed().as_millis() as i64,
+                None,
+                None,
+                is_streaming,
+            );
+            return Err(Box::new(response));
+        }
+    };
+
+    let max_output_tokens = match super::output_budget::from_headers(headers) {
+        Ok(max) => max,
+        Err(e) => {
+            let mut response = e.into_response();
+            attach_arbstr_headers(
+                &mut response,
+                &correlation_id,
+                start.elapsed().as_millis() as i64,
+                None,
+                None,
+                is_streaming,
+            );
+            return Err(Box::new(response));
+        }
+    };
+
+    let mut forward_headers =
+        super::passthrough::select_headers(headers, &state.config.headers.forward_request);
+    trace.apply_upstream(&mut forward_headers);
+    let debug_trace = super::sampling::debug_requested(headers)
+        .then(|| DecisionTrace::new(&correlation_id, &model, is_streaming));
 
-    let mut ctx = RequestContext {
+    // Set by redeem_voucher once the request is routed
+    tags.retain(|(k, _)| k != VOUCHER_TAG);
+
+    Ok(RequestContext {
         correlation_id,
         model,
         policy_name,
         is_streaming,
         start,
         reservation_id: None,
+        tags,
+        attempts: Vec::new(),
+        circuit_snapshot: None,
+        forward_headers,
+        trace,
+        response_format: None,
+        request_sha256: None,
+        unrecorded: None,
+        accumulate: false,
+        prompt_tokens_saved: None,
+        config_version: state.config_versions.current(),
+        conversation_id,
+        archive_on_error: None,
+        debug_trace,
+        max_output_tokens,
+        voucher: None,
+    })
+}
+
+/// Redeem the request's `x-arbstr-voucher`, if sent, against its estimated
+/// cost on `provider`, the first to be tried. Done once routed so a request
+/// rejected earlier doesn't use up the voucher.
+fn redeem_voucher(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    headers: &HeaderMap,
+    provider: Option<&crate::router::SelectedProvider>,
+    estimated_tokens: (u32, u32),
+) -> Result<(), Error> {
+    let Some(code) = super::vouchers::from_headers(headers)? else {
+        return Ok(());
     };
+    let (input, output) = estimated_tokens;
+    let estimated_cost = provider.map_or(0.0, |c| {
+        crate::router::actual_cost_sats(
+            input,
+            output,
+            c.input_rate,
+            c.output_rate,
+            c.base_fee,
+            c.min_charge_sats,
+        )
+    });
+    let id = state.vouchers.redeem(code, &ctx.model, estimated_cost)?;
+    set_tag(ctx, VOUCHER_TAG, &id);
+    ctx.voucher = Some(id);
+    Ok(())
+}
 
-    // Parse complexity header override (D-10 through D-14)
-    let complexity_override: Option<Tier> = headers
+/// Parse the `X-Arbstr-Complexity` override (D-10 through D-14).
+pub(super) fn complexity_override(headers: &HeaderMap) -> Option<Tier> {
+    headers
         .get(ARBSTR_COMPLEXITY_HEADER)
         .and_then(|v| v.to_str().ok())
         .and_then(|s| match s.to_lowercase().as_str() {
@@ -577,40 +1184,343 @@ pub async fn chat_completions(
             "medium" => Some(Tier::Standard),
             "low" => Some(Tier::Local),
             _ => None, // D-12: invalid -> fall through to scorer
-        });
+        })
+}
 
-    let resolved = match resolve_candidates(
-        &state,
-        &ctx,
-        user_prompt,
-        &request.messages,
-        complexity_override,
-    )
-    .await
+/// Handle POST /v1/chat/completions
+///
+/// Bodies larger than `server.stream_body_threshold_bytes` are streamed
+/// through to the provider (see [`super::body_stream`]); all others are
+/// parsed in full. Routing metadata is placed according to
+/// `server.metadata_mode` (see [`super::metadata`]).
+pub async fn chat_completions(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Extension(trace): Extension<TraceContext>,
+    request: axum::extract::Request,
+) -> Response {
+    let mode = state.config.server.metadata_mode;
+    let response = route_chat_completion(state, request_id, trace, request).await;
+    super::metadata::apply(mode, response).await
+}
+
+async fn route_chat_completion(
+    state: AppState,
+    request_id: RequestId,
+    trace: TraceContext,
+    request: axum::extract::Request,
+) -> Response {
+    let request = if super::body_stream::should_stream(
+        state.config.server.stream_body_threshold_bytes,
+        state.vault.is_some(),
+        request.headers(),
+    ) {
+        match stream_request_body(&state, &request_id, trace.clone(), request).await {
+            Ok(response) => return response,
+            Err(request) => request,
+        }
+    } else {
+        request
+    };
+
+    let headers = request.headers().clone();
+    let body_hash = super::receipts::BodyHash::default();
+    match Json::<ChatCompletionRequest>::from_request(body_hash.wrap(request), &state).await {
+        Ok(Json(request)) => handle_parsed_request(
+            state,
+            request_id,
+            trace,
+            headers,
+            request,
+            Some(body_hash.finish()),
+        )
+        .await
+        .into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+/// Route on the model found at the start of the body and stream the whole
+/// body to the provider.
+///
+/// Hands the request back, with the bytes already read put back in front,
+/// when no top-level `model` turns up within [`super::body_stream::MAX_PREFIX_BYTES`].
+async fn stream_request_body(
+    state: &AppState,
+    request_id: &RequestId,
+    trace: TraceContext,
+    request: axum::extract::Request,
+) -> Result<Response, axum::extract::Request> {
+    use futures::StreamExt;
+
+    let start = std::time::Instant::now();
+    let (parts, body) = request.into_parts();
+    let mut rest = body.into_data_stream();
+    let mut scanner = super::body_stream::BodyScanner::default();
+    let mut prefix = Vec::new();
+    let mut prefix_len = 0;
+    while scanner.model().is_none() && prefix_len < super::body_stream::MAX_PREFIX_BYTES {
+        match rest.next().await {
+            Some(Ok(chunk)) => {
+                scanner.feed(&chunk);
+                prefix_len += chunk.len();
+                prefix.push(chunk);
+            }
+            Some(Err(e)) => {
+                return Ok(
+                    Error::BadRequest(format!("Failed to read request body: {}", e))
+                        .into_response(),
+                );
+            }
+            None => break,
+        }
+    }
+
+    let Some(model) = scanner.model().map(str::to_string) else {
+        tracing::debug!(
+            prefix_bytes = prefix_len,
+            "No model near the start of the body, buffering request"
+        );
+        let body = Body::from_stream(super::body_stream::rejoin(prefix, rest));
+        return Err(axum::extract::Request::from_parts(parts, body));
+    };
+
+    let mut ctx = match request_context(
+        state,
+        request_id,
+        trace,
+        &parts.headers,
+        model,
+        scanner.stream().unwrap_or(false),
+        start,
+    ) {
+        Ok(ctx) => ctx,
+        Err(response) => return Ok(*response),
+    };
+
+    // Structured output limits the candidates and checks the response
+    if scanner.has_response_format() {
+        tracing::debug!("Request has response_format, buffering request");
+        let body = Body::from_stream(super::body_stream::rejoin(prefix, rest));
+        return Err(axum::extract::Request::from_parts(parts, body));
+    }
+
+    // Without a policy header, prompt-length rules need the whole prompt
+    if ctx.policy_name.is_none() && state.router.has_prompt_length_policies() {
+        tracing::debug!("Policy depends on prompt length, buffering request");
+        let body = Body::from_stream(super::body_stream::rejoin(prefix, rest));
+        return Err(axum::extract::Request::from_parts(parts, body));
+    }
+
+    // A system prompt, trimming, compression, or token limits have to
+    // rewrite the body
+    let policy_name = ctx.policy_name.as_deref();
+    if state.router.system_prompt(policy_name, None).is_some()
+        || state.router.trim_config(policy_name, None).is_some()
+        || state.router.compress_config(policy_name, None).is_some()
+        || state.router.max_tokens_limits(policy_name, None) != (None, None)
     {
+        tracing::debug!(policy = ?ctx.policy_name, "Policy rewrites messages, buffering request");
+        let body = Body::from_stream(super::body_stream::rejoin(prefix, rest));
+        return Err(axum::extract::Request::from_parts(parts, body));
+    }
+
+    tracing::info!(
+        model = %ctx.model,
+        policy = ?ctx.policy_name,
+        stream = ?scanner.stream(),
+        tags = ?ctx.tags,
+        prefix_bytes = prefix_len,
+        "Received chat completion request, streaming body to provider"
+    );
+
+    // Only the prefix has been read, so there is nothing to score, match
+    // keyword policies against, or estimate tokens from: route on the
+    // headers alone.
+    let tier = complexity_override(&parts.headers).unwrap_or(Tier::Frontier);
+    ctx.circuit_snapshot = Some(circuit_snapshot(state));
+    // Requests with a voucher are never streamed through (see
+    // `body_stream::should_stream`), so there is none to redeem here
+    let resolved = match resolve_candidates(state, &mut ctx, None, &[], Some(tier), (0, 0)).await {
         Ok(r) => r,
         Err(response) => return Ok(response),
     };
 
-    // Vault billing: reserve funds before routing
-    let mut request = request;
-    if let Some(vault) = &state.vault {
-        // Check backpressure (too many pending settlements)
-        if vault.is_backpressured() {
-            let latency_ms = ctx.start.elapsed().as_millis() as i64;
-            log_error_to_db(
-                &state,
-                &ctx,
-                latency_ms,
-                None,
-                503,
-                "Payment service backpressure: too many pending settlements".to_string(),
-                resolved.complexity_score,
-                Some(resolved.tier.to_string()),
-            );
-            return Ok(Response::builder()
-                .status(StatusCode::SERVICE_UNAVAILABLE)
-                .header(header::CONTENT_TYPE, "application/json")
+    let policy_headers = state
+        .router
+        .response_headers(ctx.policy_name.as_deref(), None)
+        .map(|(set, omit)| (set.clone(), omit.to_vec()));
+    let body = reqwest::Body::wrap_stream(super::body_stream::rejoin(prefix, rest));
+    let mut response = handle_streaming_path(state.clone(), ctx, UpstreamBody::Raw(body), resolved)
+        .await
+        .into_response();
+    if let Some((set, omit)) = policy_headers {
+        apply_policy_headers(&mut response, &set, &omit);
+    }
+    Ok(response)
+}
+
+/// Handle a chat completion whose body has been parsed.
+pub(super) async fn handle_parsed_request(
+    state: AppState,
+    request_id: RequestId,
+    trace: TraceContext,
+    headers: HeaderMap,
+    request: ChatCompletionRequest,
+    request_sha256: Option<String>,
+) -> Result<Response, Error> {
+    let start = std::time::Instant::now();
+    let is_streaming = request.stream.unwrap_or(false);
+
+    let mut ctx = match request_context(
+        &state,
+        &request_id,
+        trace,
+        &headers,
+        request.model.clone(),
+        is_streaming,
+        start,
+    ) {
+        Ok(ctx) => ctx,
+        Err(response) => return Ok(*response),
+    };
+    ctx.request_sha256 = request_sha256;
+    if ctx.conversation_id.is_none() {
+        match super::conversation::from_body(&request) {
+            Ok(id) => ctx.conversation_id = id,
+            Err(e) => {
+                let mut response = e.into_response();
+                attach_arbstr_headers(
+                    &mut response,
+                    &ctx.correlation_id,
+                    start.elapsed().as_millis() as i64,
+                    None,
+                    None,
+                    is_streaming,
+                );
+                return Ok(response);
+            }
+        }
+    }
+    // Without a policy header, prompt-length rules name the policy
+    if ctx.policy_name.is_none() {
+        ctx.policy_name = prompt_length_policy(&state, &request);
+    }
+
+    let policy_headers = state
+        .router
+        .response_headers(ctx.policy_name.as_deref(), request.user_prompt())
+        .map(|(set, omit)| (set.clone(), omit.to_vec()));
+    let result = dispatch_parsed_request(state, headers, ctx, request).await;
+    match policy_headers {
+        Some((set, omit)) => {
+            let mut response = result.unwrap_or_else(IntoResponse::into_response);
+            apply_policy_headers(&mut response, &set, &omit);
+            Ok(response)
+        }
+        None => result,
+    }
+}
+
+/// Rewrite, route and forward a parsed request once its policy is known.
+async fn dispatch_parsed_request(
+    state: AppState,
+    headers: HeaderMap,
+    mut ctx: RequestContext,
+    mut request: ChatCompletionRequest,
+) -> Result<Response, Error> {
+    let start = ctx.start;
+    let is_streaming = ctx.is_streaming;
+    let policy_name = ctx.policy_name.clone();
+    let mut rewrites = match rewrite_request(
+        &state,
+        &headers,
+        policy_name.as_deref(),
+        &mut request,
+        Some(&mut ctx),
+    )
+    .await
+    {
+        Ok(rewrites) => rewrites,
+        Err(e) => {
+            let mut response = e.into_response();
+            attach_arbstr_headers(
+                &mut response,
+                &ctx.correlation_id,
+                start.elapsed().as_millis() as i64,
+                None,
+                None,
+                is_streaming,
+            );
+            return Ok(response);
+        }
+    };
+    ctx.response_format = rewrites.response_format.take();
+
+    ctx.circuit_snapshot = Some(circuit_snapshot(&state));
+    let estimated_tokens = request.estimate_tokens(ESTIMATE_OUTPUT_TOKENS);
+    let resolved = match resolve_candidates(
+        &state,
+        &mut ctx,
+        request.user_prompt(),
+        &request.messages,
+        complexity_override(&headers),
+        estimated_tokens,
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+    if let Err(e) = redeem_voucher(
+        &state,
+        &mut ctx,
+        &headers,
+        resolved.candidates.first(),
+        estimated_tokens,
+    ) {
+        let mut response = e.into_response();
+        attach_arbstr_headers(
+            &mut response,
+            &ctx.correlation_id,
+            start.elapsed().as_millis() as i64,
+            None,
+            None,
+            is_streaming,
+        );
+        return Ok(response);
+    }
+
+    rewrites.apply_routed(
+        &state,
+        &headers,
+        &mut request,
+        policy_name.as_deref(),
+        &ctx.correlation_id,
+    );
+    if rewrites.system_prompt == Some("bypassed") {
+        set_tag(&mut ctx, SYSTEM_PROMPT_TAG, "bypassed");
+    }
+
+    // Vault billing: reserve funds before routing
+    if let Some(vault) = &state.vault {
+        // Check backpressure (too many pending settlements)
+        if vault.is_backpressured() {
+            let latency_ms = ctx.start.elapsed().as_millis() as i64;
+            log_error_to_db(
+                &state,
+                &ctx,
+                latency_ms,
+                None,
+                503,
+                None,
+                "Payment service backpressure: too many pending settlements".to_string(),
+                resolved.complexity_score,
+                Some(resolved.tier.to_string()),
+            );
+            return Ok(Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(header::CONTENT_TYPE, "application/json")
                 .body(Body::from(
                     serde_json::to_vec(&serde_json::json!({
                         "error": {
@@ -640,6 +1550,7 @@ pub async fn chat_completions(
                     latency_ms,
                     None,
                     401,
+                    None,
                     "Missing bearer token for vault billing".to_string(),
                     resolved.complexity_score,
                     Some(resolved.tier.to_string()),
@@ -661,21 +1572,16 @@ pub async fn chat_completions(
             }
         };
 
-        // Inject max_tokens if absent to cap financial exposure
-        if request.max_tokens.is_none() {
-            request.max_tokens = Some(vault.default_reserve_tokens);
-        }
-
         // Reserve at frontier (worst-case) rates per D-03/D-04.
         // This handles tier escalation safely -- if a local request escalates
         // to frontier on circuit break, the reservation already covers it.
         let (est_input, est_output) = request.estimate_tokens(vault.default_reserve_tokens);
-        let (reserve_input_rate, reserve_output_rate, reserve_base_fee) =
+        let (reserve_input_rate, reserve_output_rate, reserve_base_fee, reserve_min_charge) =
             state.router.frontier_rates(&ctx.model).unwrap_or_else(|| {
                 // Fallback to cheapest candidate if frontier_rates returns None
                 // (should not happen since we already resolved candidates)
                 let c = &resolved.candidates[0];
-                (c.input_rate, c.output_rate, c.base_fee)
+                (c.input_rate, c.output_rate, c.base_fee, c.min_charge_sats)
             });
         let reserve_msats = super::vault::estimate_reserve_msats(
             est_input,
@@ -683,6 +1589,7 @@ pub async fn chat_completions(
             reserve_input_rate,
             reserve_output_rate,
             reserve_base_fee,
+            reserve_min_charge,
         );
 
         // Reserve funds from vault
@@ -713,6 +1620,7 @@ pub async fn chat_completions(
                     latency_ms,
                     None,
                     status_code,
+                    None,
                     message.clone(),
                     resolved.complexity_score,
                     Some(resolved.tier.to_string()),
@@ -738,18 +1646,516 @@ pub async fn chat_completions(
         }
     }
 
-    if is_streaming {
-        handle_streaming_path(state, ctx, request, resolved).await
+    let mut response = if is_streaming || ctx.accumulate {
+        handle_streaming_path(state, ctx, UpstreamBody::Parsed(&request), resolved).await?
     } else {
-        handle_non_streaming_path(state, ctx, request, resolved).await
+        handle_non_streaming_path(state, ctx, request, resolved).await?
+    };
+    if let Some(adjustment) = rewrites.max_tokens {
+        response.headers_mut().insert(
+            HeaderName::from_static(ARBSTR_MAX_TOKENS_HEADER),
+            HeaderValue::from_static(adjustment.as_str()),
+        );
+    }
+    if let Some(outcome) = rewrites.system_prompt {
+        response.headers_mut().insert(
+            HeaderName::from_static(ARBSTR_SYSTEM_PROMPT_HEADER),
+            HeaderValue::from_static(outcome),
+        );
+    }
+    Ok(response)
+}
+
+/// The policy prompt-length rules pick for a request without a policy
+/// header.
+pub(super) fn prompt_length_policy(
+    state: &AppState,
+    request: &ChatCompletionRequest,
+) -> Option<String> {
+    let (prompt_tokens, _) = request.estimate_tokens(0);
+    let policy = state
+        .router
+        .prompt_length_policy(request.user_prompt(), prompt_tokens)?;
+    tracing::debug!(policy, prompt_tokens, "Matched policy by prompt length");
+    Some(policy.to_string())
+}
+
+/// What [`rewrite_request`] and [`Rewrites::apply_routed`] changed.
+#[derive(Default)]
+pub(super) struct Rewrites {
+    /// JSON `response_format` requested by the client, if any.
+    pub response_format: Option<ResponseFormat>,
+    pub max_tokens: Option<super::types::MaxTokensAdjustment>,
+    /// "injected" or "bypassed" when the policy has a system prompt.
+    pub system_prompt: Option<&'static str>,
+    /// Each change, in order, as shown by the debug echo.
+    pub transformations: Vec<String>,
+}
+
+/// Apply the policy's rewrites that come before routing: accumulation,
+/// token limits, prompt compression and trimming.
+///
+/// With a request context this is the live pipeline: the request is
+/// tagged and archived, and the compression and summary model calls are
+/// made. Without one (the debug echo) it has no side effects: only the
+/// local rewrite steps run and nothing is recorded.
+pub(super) async fn rewrite_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    policy_name: Option<&str>,
+    request: &mut ChatCompletionRequest,
+    mut ctx: Option<&mut RequestContext>,
+) -> Result<Rewrites, Error> {
+    let mut rewrites = Rewrites::default();
+
+    // Streamed upstream, answered as one JSON body
+    if !request.stream.unwrap_or(false)
+        && super::accumulate::requested(
+            headers,
+            state.router.accumulates(policy_name, request.user_prompt()),
+        )
+    {
+        request.stream = Some(true);
+        rewrites
+            .transformations
+            .push("accumulate: streamed upstream".to_string());
+        if let Some(ctx) = ctx.as_deref_mut() {
+            ctx.accumulate = true;
+            set_tag(ctx, ACCUMULATE_TAG, "true");
+        }
+    }
+    rewrites.response_format = ResponseFormat::from_request(request)?;
+
+    if let Some(ctx) = ctx.as_deref_mut() {
+        tracing::info!(
+            model = %request.model,
+            policy = ?policy_name,
+            stream = ?request.stream,
+            tags = ?ctx.tags,
+            "Received chat completion request"
+        );
+        if state.config.archive.enabled {
+            match serde_json::to_string(&request.messages) {
+                Ok(messages) if ctx.trace.sampled => archive_prompt(state, ctx, &messages),
+                // Unsampled requests are archived only if they fail
+                Ok(messages) => ctx.archive_on_error = Some(messages),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to serialize messages for the archive")
+                }
+            }
+        }
+    }
+
+    // Policy token limits, applied before cost estimates see max_tokens
+    let (default_max_tokens, max_output_tokens) = state
+        .router
+        .max_tokens_limits(policy_name, request.user_prompt());
+    let requested_max_tokens = request.max_tokens;
+    rewrites.max_tokens =
+        super::types::limit_max_tokens(request, default_max_tokens, max_output_tokens);
+    if let Some(adjustment) = rewrites.max_tokens {
+        rewrites
+            .transformations
+            .push(format!("max_tokens: {}", adjustment.as_str()));
+        if let Some(ctx) = ctx.as_deref_mut() {
+            tracing::info!(
+                requested = ?requested_max_tokens,
+                max_tokens = ?request.max_tokens,
+                adjustment = adjustment.as_str(),
+                "Applied policy max_tokens limit"
+            );
+            set_tag(ctx, MAX_TOKENS_TAG, adjustment.as_str());
+        }
+    }
+
+    if let Some(compress) = state
+        .router
+        .compress_config(policy_name, request.user_prompt())
+        .cloned()
+    {
+        use super::prompt_compression as compression;
+
+        match ctx.as_deref_mut() {
+            Some(ctx) => {
+                let steps = compress_prompt(state, ctx, request, &compress).await;
+                rewrites
+                    .transformations
+                    .extend(steps.map(|steps| format!("prompt_compression: {}", steps)));
+            }
+            None if compression::applies(request, &compress) => {
+                let steps = compression::compress_local(request, &compress);
+                if !steps.is_empty() {
+                    rewrites
+                        .transformations
+                        .push(format!("prompt_compression: {}", steps.join(",")));
+                }
+                if compress.model.is_some() {
+                    rewrites
+                        .transformations
+                        .push("prompt_compression: model compression not applied".to_string());
+                }
+            }
+            None => {}
+        }
+    }
+
+    if let Some(trim) = state
+        .router
+        .trim_config(policy_name, request.user_prompt())
+        .cloned()
+    {
+        let outcome = match ctx {
+            Some(ctx) => trim_conversation(state, ctx, request, &trim).await,
+            None => trim_conversation_locally(request, &trim),
+        };
+        rewrites
+            .transformations
+            .extend(outcome.map(|outcome| format!("trim: {}", outcome)));
+    }
+
+    Ok(rewrites)
+}
+
+impl Rewrites {
+    /// Apply the rewrites that come after routing: the policy system prompt,
+    /// added late so it doesn't sway scoring, and with vault billing a
+    /// default `max_tokens` to cap the reservation.
+    pub(super) fn apply_routed(
+        &mut self,
+        state: &AppState,
+        headers: &HeaderMap,
+        request: &mut ChatCompletionRequest,
+        policy_name: Option<&str>,
+        correlation_id: &str,
+    ) {
+        self.system_prompt =
+            inject_system_prompt(state, headers, request, policy_name, correlation_id);
+        if let Some(outcome) = self.system_prompt {
+            self.transformations
+                .push(format!("system_prompt: {}", outcome));
+        }
+        if let Some(vault) = &state.vault {
+            if request.max_tokens.is_none() {
+                request.max_tokens = Some(vault.default_reserve_tokens);
+                self.transformations
+                    .push("max_tokens: vault reserve".to_string());
+            }
+        }
     }
 }
 
+/// Queue the request's messages (JSON, as sent by the client) for the
+/// prompt archive (`[archive]`).
+fn archive_prompt(state: &AppState, ctx: &RequestContext, messages: &str) {
+    let Some(writer) = &state.db_writer else {
+        return;
+    };
+    writer.archive_prompt(
+        ctx.correlation_id.clone(),
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        ctx.model.clone(),
+        crate::storage::archive::compress(messages),
+    );
+}
+
+/// Add the system prompt of the policy `request` would get, unless a
+/// trusted client bypasses it. Returns "injected" or "bypassed" when the
+/// policy has a system prompt.
+pub(super) fn inject_system_prompt(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &mut ChatCompletionRequest,
+    policy_name: Option<&str>,
+    correlation_id: &str,
+) -> Option<&'static str> {
+    let (policy, prompt) = state
+        .router
+        .system_prompt(policy_name, request.user_prompt())?;
+    let bypass = headers
+        .get(ARBSTR_SYSTEM_PROMPT_BYPASS_HEADER)
+        .and_then(|v| v.to_str().ok());
+    match bypass {
+        Some(token) if prompt.allows_bypass(token) => {
+            tracing::info!(policy, "System prompt bypassed by trusted client");
+            Some("bypassed")
+        }
+        _ => {
+            if bypass.is_some() {
+                tracing::warn!(policy, "Invalid system prompt bypass token, injecting");
+            }
+            let text = prompt.render(&PromptVars {
+                policy,
+                model: &request.model,
+                date: chrono::Utc::now().date_naive(),
+                request_id: correlation_id,
+            });
+            super::types::prepend_system_prompt(request, &text);
+            Some("injected")
+        }
+    }
+}
+
+/// Apply a policy's `[compress]` to a long prompt, recording the estimated
+/// token counts before and after in the request's log tags. Returns the
+/// steps taken, comma-separated.
+async fn compress_prompt(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &mut ChatCompletionRequest,
+    config: &PromptCompressionConfig,
+) -> Option<String> {
+    use super::prompt_compression as compression;
+
+    if !compression::applies(request, config) {
+        return None;
+    }
+    let (original_tokens, _) = request.estimate_tokens(0);
+    let mut steps = compression::compress_local(request, config);
+    if let (Some(model), Some(i)) = (config.model.as_deref(), compression::model_target(request)) {
+        let text = request.messages[i].content.as_str().to_string();
+        if let Some(compressed) = compress_with_model(state, ctx, model, &text).await {
+            if compressed.len() < text.len() {
+                request.messages[i].content = super::types::MessageContent::Text(compressed);
+                steps.push("model");
+            }
+        }
+    }
+    if steps.is_empty() {
+        // Only the model call's tag would remain, naming a step not taken
+        ctx.tags.retain(|(k, _)| k != compression::COMPRESS_TAG);
+        return None;
+    }
+
+    let (compressed_tokens, _) = request.estimate_tokens(0);
+    tracing::info!(
+        original_tokens,
+        compressed_tokens,
+        steps = %steps.join(","),
+        "Compressed prompt"
+    );
+    state
+        .compression
+        .record_prompt(original_tokens, compressed_tokens);
+    set_tag(ctx, compression::COMPRESS_TAG, &steps.join(","));
+    set_tag(
+        ctx,
+        compression::ORIGINAL_TOKENS_TAG,
+        &original_tokens.to_string(),
+    );
+    set_tag(
+        ctx,
+        compression::COMPRESSED_TOKENS_TAG,
+        &compressed_tokens.to_string(),
+    );
+    ctx.prompt_tokens_saved = Some(original_tokens.saturating_sub(compressed_tokens));
+    Some(steps.join(","))
+}
+
+/// Apply a policy's `[trim]` to a conversation that would not fit the
+/// context window, recording the trim in the request's log tags. Returns
+/// what was done, e.g. "dropped 3 messages".
+async fn trim_conversation(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &mut ChatCompletionRequest,
+    trim: &TrimConfig,
+) -> Option<String> {
+    let summary_model = trim_summary_model(trim);
+    let removed = trim::drop_oldest(
+        request,
+        trim.context_tokens,
+        trim_reserve_tokens(request, summary_model),
+    );
+    if removed.is_empty() {
+        return None;
+    }
+
+    let mut outcome = "dropped";
+    if let Some(model) = summary_model {
+        if let Some(summary) = summarize_messages(state, ctx, model, &removed).await {
+            trim::insert_summary(request, &summary);
+            outcome = "summarized";
+        }
+    }
+    tracing::info!(
+        removed = removed.len(),
+        context_tokens = trim.context_tokens,
+        outcome,
+        "Trimmed conversation to fit context window"
+    );
+    set_tag(ctx, trim::TRIM_TAG, outcome);
+    set_tag(ctx, trim::TRIMMED_MESSAGES_TAG, &removed.len().to_string());
+    Some(format!("{} {} messages", outcome, removed.len()))
+}
+
+/// The dropping step of [`trim_conversation`], without the summary call or
+/// any recording. Room for a summary is still left when one would be made.
+fn trim_conversation_locally(
+    request: &mut ChatCompletionRequest,
+    trim: &TrimConfig,
+) -> Option<String> {
+    let summary_model = trim_summary_model(trim);
+    let removed = trim::drop_oldest(
+        request,
+        trim.context_tokens,
+        trim_reserve_tokens(request, summary_model),
+    );
+    if removed.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "dropped {} messages{}",
+        removed.len(),
+        if summary_model.is_some() {
+            " (summary not generated)"
+        } else {
+            ""
+        }
+    ))
+}
+
+/// The model summarizing trimmed messages, if the strategy calls for one.
+fn trim_summary_model(trim: &TrimConfig) -> Option<&str> {
+    match trim.strategy {
+        TrimStrategy::Summarize => trim.summary_model.as_deref(),
+        TrimStrategy::DropOldest => None,
+    }
+}
+
+/// Tokens trimming leaves free for the reply, and for the summary when one
+/// will be inserted.
+fn trim_reserve_tokens(request: &ChatCompletionRequest, summary_model: Option<&str>) -> u32 {
+    let reply_tokens = request.max_tokens.unwrap_or(ESTIMATE_OUTPUT_TOKENS);
+    match summary_model {
+        Some(_) => reply_tokens.saturating_add(trim::SUMMARY_MAX_TOKENS),
+        None => reply_tokens,
+    }
+}
+
+/// Ask `model` to summarize trimmed messages. The call is logged under the
+/// request's ID with a `trim=summary` tag, like a validation retry. `None`
+/// when it fails, in which case the messages are simply dropped.
+async fn summarize_messages(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    model: &str,
+    removed: &[super::types::Message],
+) -> Option<String> {
+    side_completion(
+        state,
+        ctx,
+        &trim::summary_request(model, removed),
+        "summary",
+        (trim::TRIM_TAG, "summary"),
+    )
+    .await
+}
+
+/// Ask `model` to compress `text` for `[policies.rules.compress]`. The
+/// call is logged under the request's ID with a
+/// `prompt_compression=model_call` tag. `None` if no provider serves the
+/// model or the call fails.
+async fn compress_with_model(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    model: &str,
+    text: &str,
+) -> Option<String> {
+    side_completion(
+        state,
+        ctx,
+        &super::prompt_compression::compression_request(model, text),
+        "compress",
+        (super::prompt_compression::COMPRESS_TAG, "model_call"),
+    )
+    .await
+}
+
+/// Send an auxiliary non-streaming `request` made by arbstr on behalf of
+/// the client's request, returning the reply's content. The call gets its
+/// own idempotency key (`<correlation id>-<suffix>`) and is logged under
+/// the request's ID with `tag`.
+async fn side_completion(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &ChatCompletionRequest,
+    suffix: &str,
+    tag: (&str, &str),
+) -> Option<String> {
+    let model = request.model.as_str();
+    let provider = match state.router.select(model, None, None, None) {
+        Ok(provider) => provider,
+        Err(e) => {
+            tracing::warn!(model, error = %e, purpose = suffix, "No provider for auxiliary model");
+            return None;
+        }
+    };
+    let start = std::time::Instant::now();
+    // A separate idempotency key: this is not the client's request
+    let side_id = format!("{}-{}", ctx.correlation_id, suffix);
+    let result = send_to_provider(
+        state,
+        UpstreamBody::Parsed(request),
+        &provider,
+        &side_id,
+        &ctx.forward_headers,
+        false,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await;
+
+    set_tag(ctx, tag.0, tag.1);
+    let main_model = std::mem::replace(&mut ctx.model, model.to_string());
+    let main_streaming = std::mem::replace(&mut ctx.is_streaming, false);
+    // The client's prompt was not sent, so nothing was saved by compressing it
+    let prompt_tokens_saved = ctx.prompt_tokens_saved.take();
+    // The trace follows the client's request, not this call
+    let debug_trace = ctx.debug_trace.take();
+    let latency_ms = start.elapsed().as_millis() as i64;
+    let content = match result {
+        Ok(mut outcome) => {
+            let content = response_content(&mut outcome).await;
+            if let Err(e) =
+                log_success_to_db(state, ctx, latency_ms, &mut outcome, None, None).await
+            {
+                ctx.unrecorded = Some(e.to_string());
+            }
+            Some(content).filter(|s| !s.trim().is_empty())
+        }
+        Err(e) => {
+            tracing::warn!(model, error = %e.message, purpose = suffix, "Auxiliary completion failed");
+            log_error_to_db(
+                state,
+                ctx,
+                latency_ms,
+                e.provider_name,
+                e.status_code,
+                e.kind,
+                e.message,
+                None,
+                None,
+            );
+            None
+        }
+    };
+    ctx.model = main_model;
+    ctx.is_streaming = main_streaming;
+    ctx.prompt_tokens_saved = prompt_tokens_saved;
+    ctx.debug_trace = debug_trace;
+    content
+}
+
 /// Streaming path: single attempt on the cheapest available candidate.
+///
+/// Also serves streamed-through request bodies, which cannot be replayed for
+/// a retry; their response may or may not be a stream.
 async fn handle_streaming_path(
     state: AppState,
-    ctx: RequestContext,
-    request: ChatCompletionRequest,
+    mut ctx: RequestContext,
+    body: UpstreamBody<'_>,
     resolved: ResolvedCandidates,
 ) -> Result<Response, Error> {
     let provider = &resolved.candidates[0];
@@ -766,24 +2172,60 @@ async fn handle_streaming_path(
         .as_ref()
         .map(|name| ProbeGuard::new(&state.circuit_breakers, name.clone()));
 
-    let result = send_to_provider(
-        &state,
-        &request,
-        provider,
-        &ctx.correlation_id,
-        true,
-        ctx.reservation_id.clone(),
-        resolved.complexity_score,
-        Some(resolved.tier.to_string()),
-    )
-    .await;
+    let budget = output_budget(
+        &ctx,
+        match &body {
+            UpstreamBody::Parsed(request) => Some(*request),
+            UpstreamBody::Raw(_) => None,
+        },
+    );
+    let race = match body {
+        UpstreamBody::Parsed(request)
+            if resolved.candidates.len() >= 2
+                && resolved.probe_provider.is_none()
+                && request_strategy(&state, ctx.policy_name.as_deref()) == race::STRATEGY =>
+        {
+            Some(request)
+        }
+        _ => None,
+    };
+    let result = match race {
+        Some(request) => race_first_token(&state, &mut ctx, request, &resolved).await,
+        None => {
+            send_to_provider(
+                &state,
+                body,
+                provider,
+                &ctx.correlation_id,
+                &ctx.forward_headers,
+                true,
+                ctx.reservation_id.clone(),
+                resolved.complexity_score,
+                Some(resolved.tier.to_string()),
+                budget,
+            )
+            .await
+        }
+    };
 
-    // Record circuit breaker outcome
+    // Record circuit breaker and reputation outcome
     match &result {
         Ok(outcome) => {
             state
                 .circuit_breakers
                 .record_success(&outcome.provider_name);
+            state.auth_quarantine.clear(&outcome.provider_name);
+            state.reputation.record(
+                &outcome.provider_name,
+                true,
+                ctx.start.elapsed().as_millis() as u64,
+            );
+            state.tie_breaks.record(
+                &outcome.provider_name,
+                true,
+                ctx.start.elapsed().as_millis() as u64,
+            );
+            state.canary.record(&outcome.provider_name, true);
             if let Some(guard) = probe_guard {
                 if outcome.provider_name == resolved.probe_provider.as_deref().unwrap_or("") {
                     guard.success();
@@ -793,19 +2235,9 @@ async fn handle_streaming_path(
             }
         }
         Err(outcome_err) => {
-            if is_circuit_failure(outcome_err.status_code) {
-                state.circuit_breakers.record_failure(
-                    outcome_err.provider_name.as_deref().unwrap_or("unknown"),
-                    "5xx",
-                    &format!("HTTP {}", outcome_err.status_code),
-                );
-            }
+            record_send_failure(&state, outcome_err);
             if let Some(guard) = probe_guard {
-                if is_circuit_failure(outcome_err.status_code) {
-                    guard.failure("5xx", &format!("HTTP {}", outcome_err.status_code));
-                } else {
-                    guard.failure("non_5xx", &format!("HTTP {}", outcome_err.status_code));
-                }
+                guard.failure(outcome_err.error_kind().as_str(), &outcome_err.message);
             }
         }
     }
@@ -813,30 +2245,71 @@ async fn handle_streaming_path(
     let latency_ms = ctx.start.elapsed().as_millis() as i64;
 
     match result {
-        Ok(outcome) => {
+        Ok(mut outcome) => {
             tracing::info!(
                 complexity_score = ?resolved.complexity_score,
                 tier = %resolved.tier,
                 provider = %outcome.provider_name,
                 "Request routed"
             );
-            log_success_to_db(
+            ctx.is_streaming = outcome.streamed;
+            let recorded = log_success_to_db(
                 &state,
                 &ctx,
                 latency_ms,
-                &outcome,
+                &mut outcome,
                 resolved.complexity_score,
                 Some(resolved.tier.to_string()),
-            );
+            )
+            .await;
+            if let Err(e) = recorded.and(
+                ctx.unrecorded
+                    .take()
+                    .map_or(Ok(()), |e| Err(Error::Unrecorded(e))),
+            ) {
+                return Ok(unrecorded_response(&ctx, latency_ms, e));
+            }
             let mut response = outcome.response;
+            let mut latency_ms = latency_ms;
+            if ctx.accumulate && outcome.streamed {
+                let provider = resolved
+                    .candidates
+                    .iter()
+                    .find(|c| c.name == outcome.provider_name);
+                match accumulate_response(response, provider).await {
+                    Ok((accumulated, cost_sats)) => {
+                        response = accumulated;
+                        outcome.cost_sats = cost_sats;
+                        outcome.streamed = false;
+                        latency_ms = ctx.start.elapsed().as_millis() as i64;
+                    }
+                    Err(e) => {
+                        let mut error_response = e.into_response();
+                        attach_arbstr_headers(
+                            &mut error_response,
+                            &ctx.correlation_id,
+                            ctx.start.elapsed().as_millis() as i64,
+                            Some(&outcome.provider_name),
+                            None,
+                            false,
+                        );
+                        return Ok(error_response);
+                    }
+                }
+            }
             attach_arbstr_headers(
                 &mut response,
                 &ctx.correlation_id,
                 latency_ms,
                 Some(&outcome.provider_name),
                 outcome.cost_sats,
-                true,
+                outcome.streamed,
             );
+            if !outcome.streamed {
+                state
+                    .exchange_rate
+                    .apply_header(response.headers_mut(), outcome.cost_sats);
+            }
             // Complexity headers (known at header-send time for streaming)
             if let Some(score) = resolved.complexity_score {
                 let score_str = format!("{:.3}", score);
@@ -861,6 +2334,7 @@ async fn handle_streaming_path(
                 latency_ms,
                 outcome_err.provider_name.clone(),
                 outcome_err.status_code,
+                outcome_err.kind,
                 outcome_err.message.clone(),
                 resolved.complexity_score,
                 Some(resolved.tier.to_string()),
@@ -883,17 +2357,210 @@ async fn handle_streaming_path(
                 latency_ms,
                 outcome_err.provider_name.as_deref(),
                 None,
-                true,
+                ctx.is_streaming,
             );
             Ok(error_response)
         }
     }
 }
 
+/// Read a streamed response whole into a JSON completion, keeping its
+/// passthrough headers. Returns the cost at `provider`'s rates when the
+/// stream reported usage.
+async fn accumulate_response(
+    response: Response,
+    provider: Option<&crate::router::SelectedProvider>,
+) -> Result<(Response, Option<f64>), Error> {
+    let (parts, body) = response.into_parts();
+    let accumulated = super::accumulate::accumulate(body).await?;
+    let cost_sats = accumulated.usage.zip(provider).map(|((input, output), p)| {
+        crate::router::actual_cost_sats(
+            input,
+            output,
+            p.input_rate,
+            p.output_rate,
+            p.base_fee,
+            p.min_charge_sats,
+        )
+    });
+    let mut headers = parts.headers;
+    headers.remove(header::CONTENT_TYPE);
+    headers.remove(header::CACHE_CONTROL);
+    let mut response = Json(accumulated.body).into_response();
+    response.headers_mut().extend(headers);
+    Ok((response, cost_sats))
+}
+
+/// Record a failed send against the provider's circuit breaker, reputation
+/// and canary, and report auth failures for quarantine.
+fn record_send_failure(state: &AppState, outcome_err: &RequestError) {
+    let kind = outcome_err.error_kind();
+    if kind == ProviderErrorKind::Auth {
+        if let Some(provider_name) = &outcome_err.provider_name {
+            state.auth_quarantine.report(
+                &state.http_client,
+                provider_name,
+                outcome_err.status_code,
+            );
+        }
+    }
+    if kind.is_circuit_failure() {
+        let provider_name = outcome_err.provider_name.as_deref().unwrap_or("unknown");
+        state
+            .circuit_breakers
+            .record_failure(provider_name, kind.as_str(), &outcome_err.message);
+        state.reputation.record(provider_name, false, 0);
+        state.tie_breaks.record(provider_name, false, 0);
+        state.canary.record(provider_name, false);
+    }
+}
+
+/// Strategy for a request: the named policy's, else `default_strategy`.
+fn request_strategy<'a>(state: &'a AppState, policy_name: Option<&str>) -> &'a str {
+    policy_name
+        .and_then(|name| {
+            state
+                .config
+                .policies
+                .rules
+                .iter()
+                .find(|rule| rule.name == name)
+        })
+        .map(|rule| rule.strategy.as_str())
+        .unwrap_or(&state.config.policies.default_strategy)
+}
+
+/// Race the top two candidates for a streaming request and keep whichever
+/// emits its first content token first. The slower stream is dropped,
+/// closing its connection; only the winner's stream is billed and logged,
+/// with the abandoned provider recorded in the `race_abandoned` tag. If one
+/// candidate fails outright, its failure is recorded and the other is used.
+async fn race_first_token(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &ChatCompletionRequest,
+    resolved: &ResolvedCandidates,
+) -> std::result::Result<RequestOutcome, RequestError> {
+    let (first, second) = (&resolved.candidates[0], &resolved.candidates[1]);
+    tracing::info!(
+        first = %first.name,
+        second = %second.name,
+        "Racing providers for first token"
+    );
+
+    let (winner, abandoned) = {
+        let first_attempt = std::pin::pin!(race_attempt(state, ctx, request, first));
+        let second_attempt = std::pin::pin!(race_attempt(state, ctx, request, second));
+        match futures::future::select(first_attempt, second_attempt).await {
+            Either::Left((Ok(winner), _)) => (winner, Some(second)),
+            Either::Right((Ok(winner), _)) => (winner, Some(first)),
+            Either::Left((Err(e), rest)) | Either::Right((Err(e), rest)) => {
+                record_send_failure(state, &e);
+                (rest.await?, None)
+            }
+        }
+    };
+
+    let (provider, upstream_response, stream_start) = winner;
+    if let Some(abandoned) = abandoned {
+        tracing::info!(
+            winner = %provider.name,
+            abandoned = %abandoned.name,
+            "Race won; abandoned slower provider"
+        );
+        set_tag(ctx, "race_abandoned", &abandoned.name);
+    }
+    finish_upstream(
+        state,
+        upstream_response,
+        stream_start,
+        provider,
+        &ctx.correlation_id,
+        false,
+        true,
+        ctx.reservation_id.clone(),
+        resolved.complexity_score,
+        Some(resolved.tier.to_string()),
+        output_budget(ctx, Some(request)),
+    )
+    .await
+}
+
+/// One side of [`race_first_token`]: open the stream and read it up to its
+/// first content token.
+async fn race_attempt<'a>(
+    state: &AppState,
+    ctx: &RequestContext,
+    request: &ChatCompletionRequest,
+    provider: &'a crate::router::SelectedProvider,
+) -> std::result::Result<
+    (
+        &'a crate::router::SelectedProvider,
+        reqwest::Response,
+        std::time::Instant,
+    ),
+    RequestError,
+> {
+    let (response, stream_start) = open_upstream(
+        state,
+        UpstreamBody::Parsed(request),
+        provider,
+        &ctx.correlation_id,
+        &ctx.forward_headers,
+        true,
+    )
+    .await?;
+    let response = race::first_token(response).await.map_err(|e| {
+        let kind = ProviderErrorKind::from_reqwest(&e);
+        RequestError {
+            error: Error::ProviderFailed {
+                kind,
+                message: format!("Stream from provider '{}' failed: {}", provider.name, e),
+            },
+            provider_name: Some(provider.name.clone()),
+            status_code: kind.status().as_u16(),
+            message: format!("Stream failed before first token: {}", e),
+            kind: Some(kind),
+        }
+    })?;
+    Ok((provider, response, stream_start))
+}
+
+/// Backoff for retries on the primary candidate: the provider's own
+/// `backoff`, then the named policy's, then `[routing.backoff]`.
+fn effective_backoff(
+    state: &AppState,
+    resolved: &ResolvedCandidates,
+    policy_name: Option<&str>,
+) -> BackoffConfig {
+    let policy_backoff = policy_name.and_then(|name| {
+        state
+            .config
+            .policies
+            .rules
+            .iter()
+            .find(|rule| rule.name == name)
+            .and_then(|rule| rule.backoff.clone())
+    });
+    resolved
+        .candidates
+        .first()
+        .and_then(|primary| {
+            state
+                .config
+                .providers
+                .iter()
+                .find(|p| p.name == primary.name)
+        })
+        .and_then(|provider| provider.backoff.clone())
+        .or(policy_backoff)
+        .unwrap_or_else(|| state.config.routing.backoff.clone())
+}
+
 /// Non-streaming path: retry with fallback and 30-second deadline.
 async fn handle_non_streaming_path(
     state: AppState,
-    ctx: RequestContext,
+    mut ctx: RequestContext,
     request: ChatCompletionRequest,
     resolved: ResolvedCandidates,
 ) -> Result<Response, Error> {
@@ -913,37 +2580,60 @@ async fn handle_non_streaming_path(
     let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
     let deadline = Instant::now() + RETRY_TIMEOUT;
 
+    // Retry budget: a denial survives timeout cancellation via the shared flag
+    state.retry_budget.record_request();
+    let budget_denied = Arc::new(AtomicBool::new(false));
+    let may_retry = || {
+        let allowed = state.retry_budget.try_acquire();
+        if !allowed {
+            budget_denied.store(true, Ordering::Relaxed);
+        }
+        allowed
+    };
+
+    let backoff = effective_backoff(&state, &resolved, ctx.policy_name.as_deref());
+
     let timeout_result = timeout_at(
         deadline,
-        retry_with_fallback(&candidate_infos, attempts.clone(), |info| {
-            let provider = resolved.candidates.iter().find(|c| c.name == info.name);
-            let provider = match provider {
-                Some(p) => p,
-                None => {
-                    let name = info.name.clone();
-                    return futures::future::Either::Left(async move {
-                        Err(RequestError {
-                            error: Error::Internal(
-                                "candidate info did not match any provider".into(),
-                            ),
-                            provider_name: Some(name),
-                            status_code: 500,
-                            message: "Internal routing error".into(),
-                        })
-                    });
-                }
-            };
-            futures::future::Either::Right(send_to_provider(
-                &state,
-                &request,
-                provider,
-                &ctx.correlation_id,
-                false,
-                None, // reservation_id not needed for non-streaming (settled in handler)
-                resolved.complexity_score,
-                Some(resolved.tier.to_string()),
-            ))
-        }),
+        retry_with_fallback(
+            &candidate_infos,
+            attempts.clone(),
+            &backoff,
+            state.config.routing.max_fallback_providers,
+            may_retry,
+            |info| {
+                let provider = resolved.candidates.iter().find(|c| c.name == info.name);
+                let provider = match provider {
+                    Some(p) => p,
+                    None => {
+                        let name = info.name.clone();
+                        return futures::future::Either::Left(async move {
+                            Err(RequestError {
+                                error: Error::Internal(
+                                    "candidate info did not match any provider".into(),
+                                ),
+                                provider_name: Some(name),
+                                status_code: 500,
+                                message: "Internal routing error".into(),
+                                kind: None,
+                            })
+                        });
+                    }
+                };
+                futures::future::Either::Right(send_to_provider(
+                    &state,
+                    UpstreamBody::Parsed(&request),
+                    provider,
+                    &ctx.correlation_id,
+                    &ctx.forward_headers,
+                    false,
+                    None, // reservation_id not needed for non-streaming (settled in handler)
+                    resolved.complexity_score,
+                    Some(resolved.tier.to_string()),
+                    None,
+                ))
+            },
+        ),
     )
     .await;
 
@@ -951,17 +2641,49 @@ async fn handle_non_streaming_path(
 
     let recorded_attempts = attempts.lock().unwrap_or_else(|e| e.into_inner()).clone();
     let retries_header = format_retries_header(&recorded_attempts);
+    ctx.attempts = recorded_attempts.iter().map(AttemptLog::from).collect();
+
+    let budget_exhausted = budget_denied.load(Ordering::Relaxed);
+    if budget_exhausted {
+        tracing::warn!(
+            attempts = recorded_attempts.len(),
+            "Retry budget exhausted, failing fast"
+        );
+        let (key, value) = RETRY_BUDGET_TAG;
+        ctx.tags.retain(|(k, _)| k != key);
+        ctx.tags.push((key.to_string(), value.to_string()));
+    }
 
-    // Record circuit breaker outcomes for failed attempts
+    // Record circuit breaker and reputation outcomes for failed attempts
     for attempt in &recorded_attempts {
-        if is_circuit_failure(attempt.status_code) {
+        if attempt.kind == ProviderErrorKind::Auth {
+            state.auth_quarantine.report(
+                &state.http_client,
+                &attempt.provider_name,
+                attempt.status_code,
+            );
+        }
+        if attempt.kind.is_circuit_failure() {
             state.circuit_breakers.record_failure(
                 &attempt.provider_name,
-                "5xx",
+                attempt.kind.as_str(),
                 &format!("HTTP {}", attempt.status_code),
             );
+            state.reputation.record(&attempt.provider_name, false, 0);
+            state.tie_breaks.record(&attempt.provider_name, false, 0);
+            state.canary.record(&attempt.provider_name, false);
         }
     }
+    if let Ok(Ok(outcome)) = timeout_result.as_ref().map(|r| &r.result) {
+        state.auth_quarantine.clear(&outcome.provider_name);
+        state
+            .reputation
+            .record(&outcome.provider_name, true, latency_ms as u64);
+        state
+            .tie_breaks
+            .record(&outcome.provider_name, true, latency_ms as u64);
+        state.canary.record(&outcome.provider_name, true);
+    }
 
     // Resolve ProbeGuard before consuming timeout_result
     if let Some(guard) = probe_guard {
@@ -977,8 +2699,8 @@ async fn handle_non_streaming_path(
                         .record_success(&outcome.provider_name);
                     guard.failure("not_reached", "different provider succeeded");
                 }
-                Err(_) => {
-                    guard.failure("5xx", "all providers failed");
+                Err(err) => {
+                    guard.failure(err.error_kind().as_str(), "all providers failed");
                 }
             },
             Err(_) => {
@@ -1008,6 +2730,7 @@ async fn handle_non_streaming_path(
                 latency_ms,
                 last_provider,
                 504,
+                Some(ProviderErrorKind::Timeout),
                 "Request timed out after 30 seconds (retry budget exhausted)".to_string(),
                 resolved.complexity_score,
                 Some(resolved.tier.to_string()),
@@ -1023,11 +2746,11 @@ async fn handle_non_streaming_path(
                 );
             }
 
-            let timeout_error = Error::Provider(
-                "Request timed out after 30 seconds (retry budget exhausted)".to_string(),
-            );
+            let timeout_error = Error::ProviderFailed {
+                kind: ProviderErrorKind::Timeout,
+                message: "Request timed out after 30 seconds (retry budget exhausted)".to_string(),
+            };
             let mut error_response = timeout_error.into_response();
-            *error_response.status_mut() = StatusCode::GATEWAY_TIMEOUT;
             attach_arbstr_headers(
                 &mut error_response,
                 &ctx.correlation_id,
@@ -1040,25 +2763,73 @@ async fn handle_non_streaming_path(
             Ok(error_response)
         }
         Ok(retry_outcome) => match retry_outcome.result {
-            Ok(outcome) => {
+            Ok(mut outcome) => {
+                let mut rejected_cost_sats = 0.0;
+                let mut structured = None;
+                if let (true, Some(format)) = (
+                    state.config.routing.validate_structured_output,
+                    ctx.response_format.clone(),
+                ) {
+                    let (result, cost) = enforce_structured_output(
+                        &state,
+                        &mut ctx,
+                        &request,
+                        &resolved,
+                        &format,
+                        &mut outcome,
+                        latency_ms,
+                    )
+                    .await;
+                    structured = Some(result);
+                    rejected_cost_sats += cost;
+                }
+                let mut validation = None;
+                if let Some(validator) = state
+                    .router
+                    .response_validator(ctx.policy_name.as_deref(), request.user_prompt())
+                {
+                    let (result, cost) = validate_response(
+                        &state,
+                        &mut ctx,
+                        &request,
+                        &resolved,
+                        validator,
+                        &mut outcome,
+                        latency_ms,
+                    )
+                    .await;
+                    validation = Some(result);
+                    rejected_cost_sats += cost;
+                }
+                let latency_ms = ctx.start.elapsed().as_millis() as i64;
+                let total_cost_sats = outcome.cost_sats.map(|c| c + rejected_cost_sats);
+
                 tracing::info!(
                     complexity_score = ?resolved.complexity_score,
                     tier = %resolved.tier,
                     provider = %outcome.provider_name,
                     "Request routed"
                 );
-                log_success_to_db(
+                let recorded = log_success_to_db(
                     &state,
                     &ctx,
                     latency_ms,
-                    &outcome,
+                    &mut outcome,
                     resolved.complexity_score,
                     Some(resolved.tier.to_string()),
+                )
+                .await
+                .and(
+                    ctx.unrecorded
+                        .take()
+                        .map_or(Ok(()), |e| Err(Error::Unrecorded(e))),
                 );
 
-                // Vault: async settle on success
+                // Vault: async settle on success, including any rejected response
                 if let (Some(vault), Some(rid)) = (&state.vault, &ctx.reservation_id) {
-                    let actual_msats = outcome.cost_sats.map(|c| (c * 1000.0) as u64).unwrap_or(0);
+                    let actual_msats = total_cost_sats
+                        .map(|c| (c * 1000.0).round() as u64)
+                        .unwrap_or(0);
                     spawn_vault_settle(
                         vault.clone(),
                         rid.clone(),
@@ -1073,69 +2844,525 @@ async fn handle_non_streaming_path(
                     );
                 }
 
+                // Strict logging: the spend is settled but the client gets
+                // no response without a record
+                if let Err(e) = recorded {
+                    let mut response = unrecorded_response(&ctx, latency_ms, e);
+                    attach_retries_header(&mut response, &retries_header);
+                    return Ok(response);
+                }
+
+                capture_dataset_record(
+                    &state,
+                    &ctx,
+                    &request,
+                    &mut outcome,
+                    total_cost_sats,
+                    latency_ms,
+                )
+                .await;
+
                 let mut response = outcome.response;
                 attach_arbstr_headers(
                     &mut response,
                     &ctx.correlation_id,
                     latency_ms,
                     Some(&outcome.provider_name),
-                    outcome.cost_sats,
+                    total_cost_sats,
+                    false,
+                );
+                state
+                    .exchange_rate
+                    .apply_header(response.headers_mut(), total_cost_sats);
+                if let Some(result) = structured {
+                    response.headers_mut().insert(
+                        HeaderName::from_static(ARBSTR_STRUCTURED_OUTPUT_HEADER),
+                        HeaderValue::from_static(result),
+                    );
+                }
+                if let Some(result) = validation {
+                    response.headers_mut().insert(
+                        HeaderName::from_static(ARBSTR_VALIDATION_HEADER),
+                        HeaderValue::from_static(result),
+                    );
+                }
+                // Complexity headers
+                if let Some(score) = resolved.complexity_score {
+                    let score_str = format!("{:.3}", score);
+                    if let Ok(val) = HeaderValue::from_str(&score_str) {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(ARBSTR_COMPLEXITY_SCORE_HEADER), val);
+                    }
+                }
+                let tier_str = resolved.tier.to_string();
+                if let Ok(val) = HeaderValue::from_str(&tier_str) {
+                    response
+                        .headers_mut()
+                        .insert(HeaderName::from_static(ARBSTR_TIER_HEADER), val);
+                }
+                attach_retries_header(&mut response, &retries_header);
+                Ok(response)
+            }
+            Err(outcome_err) => {
+                log_error_to_db(
+                    &state,
+                    &ctx,
+                    latency_ms,
+                    outcome_err.provider_name.clone(),
+                    outcome_err.status_code,
+                    outcome_err.kind,
+                    outcome_err.message.clone(),
+                    resolved.complexity_score,
+                    Some(resolved.tier.to_string()),
+                );
+
+                // Vault: release on provider error
+                if let (Some(vault), Some(rid)) = (&state.vault, &ctx.reservation_id) {
+                    spawn_vault_release(
+                        vault.clone(),
+                        rid.clone(),
+                        format!("provider_error_{}", outcome_err.status_code),
+                        state.db.clone(),
+                    );
+                }
+
+                let mut error_response = outcome_err.error.into_response();
+                attach_arbstr_headers(
+                    &mut error_response,
+                    &ctx.correlation_id,
+                    latency_ms,
+                    outcome_err.provider_name.as_deref(),
+                    None,
                     false,
                 );
-                // Complexity headers
-                if let Some(score) = resolved.complexity_score {
-                    let score_str = format!("{:.3}", score);
-                    if let Ok(val) = HeaderValue::from_str(&score_str) {
-                        response
-                            .headers_mut()
-                            .insert(HeaderName::from_static(ARBSTR_COMPLEXITY_SCORE_HEADER), val);
-                    }
-                }
-                let tier_str = resolved.tier.to_string();
-                if let Ok(val) = HeaderValue::from_str(&tier_str) {
-                    response
-                        .headers_mut()
-                        .insert(HeaderName::from_static(ARBSTR_TIER_HEADER), val);
-                }
-                attach_retries_header(&mut response, &retries_header);
-                Ok(response)
+                attach_retries_header(&mut error_response, &retries_header);
+                if budget_exhausted {
+                    error_response.headers_mut().insert(
+                        HeaderName::from_static(ARBSTR_RETRY_BUDGET_HEADER),
+                        HeaderValue::from_static("exhausted"),
+                    );
+                }
+                Ok(error_response)
+            }
+        },
+    }
+}
+
+/// Replace tag `key` on the request's log rows.
+fn set_tag(ctx: &mut RequestContext, key: &str, value: &str) {
+    ctx.tags.retain(|(k, _)| k != key);
+    ctx.tags.push((key.to_string(), value.to_string()));
+}
+
+/// The first choice's `message` in a non-streaming response. The body is
+/// read and put back.
+async fn response_message(outcome: &mut RequestOutcome) -> Option<serde_json::Value> {
+    let body = std::mem::take(outcome.response.body_mut());
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let message = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|mut v| {
+            v["choices"]
+                .get_mut(0)?
+                .get_mut("message")
+                .map(std::mem::take)
+        });
+    *outcome.response.body_mut() = Body::from(bytes);
+    message
+}
+
+/// Message content of the first choice in a non-streaming response.
+async fn response_content(outcome: &mut RequestOutcome) -> String {
+    response_message(outcome)
+        .await
+        .and_then(|m| m["content"].as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Append a successful response to the policy's dataset file, if it has
+/// one and the request is sampled.
+async fn capture_dataset_record(
+    state: &AppState,
+    ctx: &RequestContext,
+    request: &ChatCompletionRequest,
+    outcome: &mut RequestOutcome,
+    cost_sats: Option<f64>,
+    latency_ms: i64,
+) {
+    let Some(config) = state
+        .router
+        .dataset_capture(ctx.policy_name.as_deref(), request.user_prompt())
+    else {
+        return;
+    };
+    if !super::dataset::sampled(config) {
+        return;
+    }
+    let Some(response) = response_message(outcome).await else {
+        return;
+    };
+    let record = super::dataset::DatasetRecord {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        request_id: ctx.correlation_id.clone(),
+        policy: ctx.policy_name.clone(),
+        model: ctx.model.clone(),
+        provider: outcome.provider_name.clone(),
+        messages: request.messages.clone(),
+        response,
+        input_tokens: outcome.input_tokens,
+        output_tokens: outcome.output_tokens,
+        cost_sats,
+        latency_ms,
+    };
+    state.datasets.capture(config, record);
+}
+
+/// Check a non-streaming response against the requested `response_format`.
+///
+/// A non-conforming reply is sent back to the same provider once, with the
+/// reply and the reason appended to the conversation. When the re-ask
+/// succeeds, the rejected response is logged as its own row tagged
+/// `structured_output=failed` and `outcome` is replaced by the re-ask,
+/// tagged `structured_output=reasked` when it conforms. Returns the
+/// [`ARBSTR_STRUCTURED_OUTPUT_HEADER`] value and the cost of the rejected
+/// response.
+async fn enforce_structured_output(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &ChatCompletionRequest,
+    resolved: &ResolvedCandidates,
+    format: &ResponseFormat,
+    outcome: &mut RequestOutcome,
+    latency_ms: i64,
+) -> (&'static str, f64) {
+    let reply = response_content(outcome).await;
+    let reason = match format.check(&reply) {
+        Ok(()) => return ("passed", 0.0),
+        Err(reason) => reason,
+    };
+    let Some(provider) = resolved
+        .candidates
+        .iter()
+        .find(|c| c.name == outcome.provider_name)
+    else {
+        set_tag(ctx, STRUCTURED_OUTPUT_TAG, "failed");
+        return ("failed", 0.0);
+    };
+
+    tracing::warn!(
+        provider = %provider.name,
+        reason = %reason,
+        "Response does not match response_format, re-asking provider"
+    );
+    let reask = super::structured::reask_request(request, &reply, &reason);
+    let reask_start = std::time::Instant::now();
+    let retry = tokio::time::timeout(
+        RETRY_TIMEOUT,
+        send_to_provider(
+            state,
+            UpstreamBody::Parsed(&reask),
+            provider,
+            &ctx.correlation_id,
+            &ctx.forward_headers,
+            false,
+            None,
+            resolved.complexity_score,
+            Some(resolved.tier.to_string()),
+            None,
+        ),
+    )
+    .await;
+    let mut reasked = match retry {
+        Ok(Ok(reasked)) => reasked,
+        Ok(Err(err)) => {
+            if err.kind.is_some_and(|kind| kind.is_circuit_failure()) {
+                state.circuit_breakers.record_failure(
+                    &provider.name,
+                    err.error_kind().as_str(),
+                    &format!("HTTP {}", err.status_code),
+                );
+                state.reputation.record(&provider.name, false, 0);
+                state.tie_breaks.record(&provider.name, false, 0);
+            }
+            tracing::warn!(provider = %provider.name, error = %err.message, "Re-ask failed, returning original response");
+            set_tag(ctx, STRUCTURED_OUTPUT_TAG, "failed");
+            return ("failed", 0.0);
+        }
+        Err(_) => {
+            tracing::warn!(provider = %provider.name, "Re-ask timed out, returning original response");
+            set_tag(ctx, STRUCTURED_OUTPUT_TAG, "failed");
+            return ("failed", 0.0);
+        }
+    };
+    state.circuit_breakers.record_success(&provider.name);
+    state.reputation.record(
+        &provider.name,
+        true,
+        reask_start.elapsed().as_millis() as u64,
+    );
+    state.tie_breaks.record(
+        &provider.name,
+        true,
+        reask_start.elapsed().as_millis() as u64,
+    );
+
+    set_tag(ctx, STRUCTURED_OUTPUT_TAG, "failed");
+    if let Err(e) = log_success_to_db(
+        state,
+        ctx,
+        latency_ms,
+        outcome,
+        resolved.complexity_score,
+        Some(resolved.tier.to_string()),
+    )
+    .await
+    {
+        ctx.unrecorded = Some(e.to_string());
+    }
+    let rejected_cost_sats = outcome.cost_sats.unwrap_or(0.0);
+
+    let result = match format.check(&response_content(&mut reasked).await) {
+        Ok(()) => "reasked",
+        Err(reason) => {
+            tracing::warn!(provider = %provider.name, reason = %reason, "Re-asked response does not match response_format either");
+            "failed"
+        }
+    };
+    set_tag(ctx, STRUCTURED_OUTPUT_TAG, result);
+    *outcome = reasked;
+    (result, rejected_cost_sats)
+}
+
+/// Check a non-streaming response against the policy's validator.
+///
+/// On failure the request is sent once to the cheapest available provider
+/// of a higher tier than the one that answered. If that succeeds, the
+/// rejected response is logged as its own row tagged `validation=failed`
+/// (it was still paid for) and `outcome` is replaced by the retry, tagged
+/// `validation=retried` when it passes. Returns the
+/// [`ARBSTR_VALIDATION_HEADER`] value and the cost of the rejected response.
+async fn validate_response(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &ChatCompletionRequest,
+    resolved: &ResolvedCandidates,
+    validator: &crate::router::ResponseValidator,
+    outcome: &mut RequestOutcome,
+    latency_ms: i64,
+) -> (&'static str, f64) {
+    let reason = match validator.check(&response_content(outcome).await) {
+        Ok(()) => return ("passed", 0.0),
+        Err(reason) => reason,
+    };
+
+    let failed_tier = resolved
+        .candidates
+        .iter()
+        .find(|c| c.name == outcome.provider_name)
+        .map_or(resolved.tier, |c| c.tier);
+    let next = state
+        .router
+        .select_candidates(
+            &ctx.model,
+            ctx.policy_name.as_deref(),
+            request.user_prompt(),
+            None,
+        )
+        .ok()
+        .and_then(|candidates| {
+            candidates.into_iter().find(|c| {
+                c.tier > failed_tier
+                    && !state.maintenance.is_unavailable(&c.name)
+                    && !state.auth_quarantine.is_quarantined(&c.name)
+                    && state
+                        .circuit_breakers
+                        .state(&c.name)
+                        .is_none_or(|s| s == CircuitState::Closed)
+            })
+        });
+    let Some(next) = next else {
+        tracing::warn!(
+            provider = %outcome.provider_name,
+            reason = %reason,
+            "Response failed validation, no higher-tier provider to retry on"
+        );
+        set_tag(ctx, VALIDATION_TAG, "failed");
+        return ("failed", 0.0);
+    };
+
+    tracing::warn!(
+        provider = %outcome.provider_name,
+        retry_provider = %next.name,
+        retry_tier = %next.tier,
+        reason = %reason,
+        "Response failed validation, retrying on higher tier"
+    );
+    let retry_start = std::time::Instant::now();
+    let retry = tokio::time::timeout(
+        RETRY_TIMEOUT,
+        send_to_provider(
+            state,
+            UpstreamBody::Parsed(request),
+            &next,
+            &ctx.correlation_id,
+            &ctx.forward_headers,
+            false,
+            None,
+            resolved.complexity_score,
+            Some(resolved.tier.to_string()),
+            None,
+        ),
+    )
+    .await;
+    let mut retried = match retry {
+        Ok(Ok(retried)) => retried,
+        Ok(Err(err)) => {
+            if err.kind.is_some_and(|kind| kind.is_circuit_failure()) {
+                state.circuit_breakers.record_failure(
+                    &next.name,
+                    err.error_kind().as_str(),
+                    &format!("HTTP {}", err.status_code),
+                );
+                state.reputation.record(&next.name, false, 0);
+                state.tie_breaks.record(&next.name, false, 0);
             }
-            Err(outcome_err) => {
-                log_error_to_db(
-                    &state,
-                    &ctx,
-                    latency_ms,
-                    outcome_err.provider_name.clone(),
-                    outcome_err.status_code,
-                    outcome_err.message.clone(),
-                    resolved.complexity_score,
-                    Some(resolved.tier.to_string()),
-                );
+            tracing::warn!(provider = %next.name, error = %err.message, "Validation retry failed, returning original response");
+            set_tag(ctx, VALIDATION_TAG, "failed");
+            return ("failed", 0.0);
+        }
+        Err(_) => {
+            tracing::warn!(provider = %next.name, "Validation retry timed out, returning original response");
+            set_tag(ctx, VALIDATION_TAG, "failed");
+            return ("failed", 0.0);
+        }
+    };
+    state.circuit_breakers.record_success(&next.name);
+    state
+        .reputation
+        .record(&next.name, true, retry_start.elapsed().as_millis() as u64);
+    state
+        .tie_breaks
+        .record(&next.name, true, retry_start.elapsed().as_millis() as u64);
+
+    set_tag(ctx, VALIDATION_TAG, "failed");
+    if let Err(e) = log_success_to_db(
+        state,
+        ctx,
+        latency_ms,
+        outcome,
+        resolved.complexity_score,
+        Some(resolved.tier.to_string()),
+    )
+    .await
+    {
+        ctx.unrecorded = Some(e.to_string());
+    }
+    let rejected_cost_sats = outcome.cost_sats.unwrap_or(0.0);
 
-                // Vault: release on provider error
-                if let (Some(vault), Some(rid)) = (&state.vault, &ctx.reservation_id) {
-                    spawn_vault_release(
-                        vault.clone(),
-                        rid.clone(),
-                        format!("provider_error_{}", outcome_err.status_code),
-                        state.db.clone(),
-                    );
-                }
+    let result = match validator.check(&response_content(&mut retried).await) {
+        Ok(()) => "retried",
+        Err(reason) => {
+            tracing::warn!(provider = %next.name, reason = %reason, "Retried response also failed validation");
+            "failed"
+        }
+    };
+    set_tag(ctx, VALIDATION_TAG, result);
+    *outcome = retried;
+    (result, rejected_cost_sats)
+}
 
-                let mut error_response = outcome_err.error.into_response();
-                attach_arbstr_headers(
-                    &mut error_response,
-                    &ctx.correlation_id,
-                    latency_ms,
-                    outcome_err.provider_name.as_deref(),
-                    None,
-                    false,
-                );
-                attach_retries_header(&mut error_response, &retries_header);
-                Ok(error_response)
+/// Attach a provider's configured extra headers and API key.
+///
+/// Extra headers go first so the auth header wins if both name the same
+/// header. Shared with model discovery.
+pub(crate) fn apply_provider_headers(
+    mut request: reqwest::RequestBuilder,
+    api_key: Option<&ApiKey>,
+    auth_scheme: AuthScheme,
+    extra_headers: &BTreeMap<String, String>,
+) -> reqwest::RequestBuilder {
+    for (name, value) in extra_headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    if let Some((name, value)) = api_key.and_then(|key| auth_scheme.header(key)) {
+        request = request.header(name, value);
+    }
+    request
+}
+
+/// Headers of an upstream chat completion request: content type, the
+/// `Idempotency-Key`, client passthrough headers (unless the provider config
+/// sets the same header), the provider's `extra_headers`, then its auth
+/// header. Provider header values are marked sensitive.
+pub(super) fn upstream_headers(
+    provider: &crate::router::SelectedProvider,
+    correlation_id: &str,
+    forward_headers: &HeaderMap,
+) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    if let Ok(value) = HeaderValue::from_str(correlation_id) {
+        headers.insert(HeaderName::from_static("idempotency-key"), value);
+    }
+    for (name, value) in forward_headers {
+        let overridden = provider
+            .extra_headers
+            .keys()
+            .any(|extra| extra.eq_ignore_ascii_case(name.as_str()));
+        if !overridden {
+            headers.append(name, value.clone());
+        }
+    }
+    let auth = provider
+        .api_key
+        .as_ref()
+        .and_then(|key| provider.auth_scheme.header(key))
+        .map(|(name, value)| (name.to_string(), value));
+    for (name, value) in provider
+        .extra_headers
+        .iter()
+        .chain(auth.as_ref().map(|(n, v)| (n, v)))
+    {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(mut value)) => {
+                value.set_sensitive(true);
+                headers.append(name, value);
             }
-        },
+            _ => {
+                tracing::warn!(provider = %provider.name, header = %name, "Skipping invalid provider header")
+            }
+        }
     }
+    headers
+}
+
+/// The request's `x-arbstr-max-output-tokens` budget, priced with the
+/// prompt of `request` when it was parsed.
+fn output_budget(
+    ctx: &RequestContext,
+    request: Option<&ChatCompletionRequest>,
+) -> Option<super::output_budget::OutputBudget> {
+    ctx.max_output_tokens
+        .map(|max_tokens| super::output_budget::OutputBudget {
+            max_tokens,
+            prompt_tokens: request.map_or(0, |r| r.estimate_tokens(0).0),
+        })
+}
+
+/// Body of an upstream chat completion request.
+enum UpstreamBody<'a> {
+    Parsed(&'a ChatCompletionRequest),
+    /// The client's body, streamed through unmodified.
+    Raw(reqwest::Body),
 }
 
 /// Send a request to a specific provider and handle the response.
@@ -1143,60 +3370,133 @@ async fn handle_non_streaming_path(
 /// This is the core provider-calling logic used by both the streaming
 /// and non-streaming (retry) paths. Adds an `Idempotency-Key` header
 /// with the correlation ID to allow providers to deduplicate retried
-/// requests.
+/// requests. Upstream response headers on `headers.forward_response` are
+/// copied onto the outcome's response. For a [`UpstreamBody::Raw`] body the
+/// upstream content type decides whether the response is handled as a stream.
 #[allow(clippy::too_many_arguments)]
 async fn send_to_provider(
     state: &AppState,
-    request: &ChatCompletionRequest,
+    body: UpstreamBody<'_>,
     provider: &crate::router::SelectedProvider,
     correlation_id: &str,
+    forward_headers: &HeaderMap,
     is_streaming: bool,
     reservation_id: Option<String>,
     complexity_score: Option<f64>,
     tier: Option<String>,
+    budget: Option<super::output_budget::OutputBudget>,
 ) -> std::result::Result<RequestOutcome, RequestError> {
+    let raw_body = matches!(body, UpstreamBody::Raw(_));
+    let (upstream_response, stream_start) = open_upstream(
+        state,
+        body,
+        provider,
+        correlation_id,
+        forward_headers,
+        is_streaming,
+    )
+    .await?;
+    finish_upstream(
+        state,
+        upstream_response,
+        stream_start,
+        provider,
+        correlation_id,
+        raw_body,
+        is_streaming,
+        reservation_id,
+        complexity_score,
+        tier,
+        budget,
+    )
+    .await
+}
+
+/// Send a request to `provider` and return its successful response with the
+/// time the send started. Error statuses are read and returned as errors.
+async fn open_upstream(
+    state: &AppState,
+    body: UpstreamBody<'_>,
+    provider: &crate::router::SelectedProvider,
+    correlation_id: &str,
+    forward_headers: &HeaderMap,
+    is_streaming: bool,
+) -> std::result::Result<(reqwest::Response, std::time::Instant), RequestError> {
     // Build upstream URL
     let upstream_url = format!("{}/chat/completions", provider.url.trim_end_matches('/'));
 
-    // Inject stream_options for streaming requests (at send time, per user decision)
-    let request_body = if is_streaming {
-        let mut modified = request.clone();
-        crate::proxy::types::ensure_stream_options(&mut modified);
-        modified
-    } else {
-        request.clone()
+    // Forward request to provider
+    let client = state
+        .provider_clients
+        .get(&provider.name)
+        .unwrap_or(&state.http_client);
+    let mut upstream_request = client.post(&upstream_url);
+    upstream_request = match body {
+        // Inject stream_options for streaming requests (at send time, per user decision)
+        UpstreamBody::Parsed(request) if is_streaming => {
+            let mut modified = request.clone();
+            crate::proxy::types::ensure_stream_options(&mut modified);
+            upstream_request.json(&modified)
+        }
+        UpstreamBody::Parsed(request) => upstream_request.json(request),
+        UpstreamBody::Raw(body) => upstream_request.body(body),
     };
 
-    // Forward request to provider
-    let mut upstream_request = state
-        .http_client
-        .post(&upstream_url)
-        .header(header::CONTENT_TYPE, "application/json")
-        .header("Idempotency-Key", correlation_id)
-        .json(&request_body);
+    upstream_request =
+        upstream_request.headers(upstream_headers(provider, correlation_id, forward_headers));
 
-    if let Some(api_key) = &provider.api_key {
-        upstream_request = upstream_request.header(
-            header::AUTHORIZATION,
-            format!("Bearer {}", api_key.expose_secret()),
-        );
+    let chaos = &state.config.chaos;
+    if let Some(delay) = super::chaos::latency(chaos, &provider.name) {
+        tracing::warn!(provider = %provider.name, delay_ms = delay.as_millis() as u64, "Chaos: injecting latency");
+        tokio::time::sleep(delay).await;
+    }
+    if let Some(status) = super::chaos::injected_error(chaos, &provider.name) {
+        tracing::warn!(provider = %provider.name, status = status, "Chaos: injecting provider error");
+        let kind = ProviderErrorKind::from_status(status);
+        return Err(RequestError {
+            error: Error::ProviderFailed {
+                kind,
+                message: format!(
+                    "Provider '{}' returned {} (injected by chaos mode)",
+                    provider.name, status
+                ),
+            },
+            provider_name: Some(provider.name.clone()),
+            status_code: status,
+            message: format!("Provider returned {} (chaos)", status),
+            kind: Some(kind),
+        });
     }
 
     // Capture stream start time before send for streaming requests
     let stream_start = std::time::Instant::now();
 
-    let upstream_response = upstream_request.send().await.map_err(|e| {
-        tracing::error!(error = %e, provider = %provider.name, "Failed to reach provider");
-        RequestError {
-            error: Error::Provider(format!(
-                "Failed to reach provider '{}': {}",
-                provider.name, e
-            )),
-            provider_name: Some(provider.name.clone()),
-            status_code: 502,
-            message: format!("Failed to reach provider: {}", e),
-        }
-    })?;
+    state.quotas.record_request(&provider.name);
+    let connection = state.provider_clients.start(&provider.name);
+    let upstream_response = state
+        .recording
+        .send(&provider.name, upstream_request)
+        .await
+        .map_err(|e| {
+            let kind = e.kind();
+            tracing::error!(
+                error = %e,
+                error_type = kind.as_str(),
+                provider = %provider.name,
+                "Failed to reach provider"
+            );
+            RequestError {
+                error: Error::ProviderFailed {
+                    kind,
+                    message: format!("Failed to reach provider '{}': {}", provider.name, e),
+                },
+                provider_name: Some(provider.name.clone()),
+                status_code: kind.status().as_u16(),
+                message: format!("Failed to reach provider: {}", e),
+                kind: Some(kind),
+            }
+        })?;
+    connection.headers_received(upstream_response.version());
 
     let status = upstream_response.status();
     if !status.is_success() {
@@ -1207,34 +3507,98 @@ async fn send_to_provider(
             body = %error_body,
             "Provider returned error"
         );
+        let kind = ProviderErrorKind::from_status(status.as_u16());
         return Err(RequestError {
-            error: Error::Provider(format!(
-                "Provider '{}' returned {}: {}",
-                provider.name, status, error_body
-            )),
+            error: Error::ProviderFailed {
+                kind,
+                message: format!(
+                    "Provider '{}' returned {}: {}",
+                    provider.name, status, error_body
+                ),
+            },
             provider_name: Some(provider.name.clone()),
             status_code: status.as_u16(),
             message: format!("Provider returned {}", status),
+            kind: Some(kind),
         });
     }
 
-    if is_streaming {
+    Ok((upstream_response, stream_start))
+}
+
+/// Turn a successful upstream response into a [`RequestOutcome`], streaming
+/// it through to the client or reading it whole.
+#[allow(clippy::too_many_arguments)]
+async fn finish_upstream(
+    state: &AppState,
+    upstream_response: reqwest::Response,
+    stream_start: std::time::Instant,
+    provider: &crate::router::SelectedProvider,
+    correlation_id: &str,
+    raw_body: bool,
+    is_streaming: bool,
+    reservation_id: Option<String>,
+    complexity_score: Option<f64>,
+    tier: Option<String>,
+    budget: Option<super::output_budget::OutputBudget>,
+) -> std::result::Result<RequestOutcome, RequestError> {
+    let passthrough = super::passthrough::select_headers(
+        upstream_response.headers(),
+        &state.config.headers.forward_response,
+    );
+
+    let is_streaming = if raw_body {
+        upstream_response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("text/event-stream"))
+    } else {
+        is_streaming
+    };
+
+    let mut outcome = if is_streaming {
         handle_streaming_response(
             upstream_response,
             provider,
             correlation_id.to_string(),
             state.db_writer.clone(),
+            state.recent.clone(),
+            state.ledger.clone(),
+            state.vouchers.clone(),
+            state.receipts.clone(),
+            state.quotas.clone(),
             state.vault.clone(),
             reservation_id,
             state.db.clone(),
             stream_start,
             complexity_score,
             tier,
+            super::chaos::stream_fault(&state.config.chaos, &provider.name),
+            state
+                .config
+                .providers
+                .iter()
+                .any(|p| p.name == provider.name && p.normalize_stream),
+            state
+                .config
+                .content_filter
+                .as_ref()
+                .map(super::content_filter::ContentFilter::new),
+            budget,
+            state.config.server.metadata_mode == crate::config::MetadataMode::Body,
+            state
+                .config
+                .server
+                .sse_keepalive_secs
+                .map(std::time::Duration::from_secs),
         )
-        .await
+        .await?
     } else {
-        handle_non_streaming_response(upstream_response, provider).await
-    }
+        handle_non_streaming_response(upstream_response, provider).await?
+    };
+    outcome.response.headers_mut().extend(passthrough);
+    Ok(outcome)
 }
 
 /// Handle a non-streaming provider response.
@@ -1244,16 +3608,21 @@ async fn handle_non_streaming_response(
     upstream_response: reqwest::Response,
     provider: &crate::router::SelectedProvider,
 ) -> std::result::Result<RequestOutcome, RequestError> {
-    let mut response: serde_json::Value = upstream_response.json().await.map_err(|e| {
+    let response: serde_json::Value = upstream_response.json().await.map_err(|e| {
         tracing::error!(error = %e, "Failed to parse provider response");
+        let kind = match ProviderErrorKind::from_reqwest(&e) {
+            ProviderErrorKind::Timeout => ProviderErrorKind::Timeout,
+            _ => ProviderErrorKind::Malformed,
+        };
         RequestError {
-            error: Error::Provider(format!(
-                "Failed to parse response from '{}': {}",
-                provider.name, e
-            )),
+            error: Error::ProviderFailed {
+                kind,
+                message: format!("Failed to parse response from '{}': {}", provider.name, e),
+            },
             provider_name: Some(provider.name.clone()),
-            status_code: 502,
+            status_code: kind.status().as_u16(),
             message: format!("Failed to parse response: {}", e),
+            kind: Some(kind),
         }
     })?;
 
@@ -1272,6 +3641,7 @@ async fn handle_non_streaming_response(
             provider.input_rate,
             provider.output_rate,
             provider.base_fee,
+            provider.min_charge_sats,
         )),
         _ => None,
     };
@@ -1282,19 +3652,14 @@ async fn handle_non_streaming_response(
         .and_then(|u| u.get("total_cost"))
         .and_then(|v| v.as_f64());
 
-    // Add arbstr metadata to response
-    if let Some(obj) = response.as_object_mut() {
-        obj.insert(
-            "arbstr_provider".to_string(),
-            serde_json::Value::String(provider.name.clone()),
-        );
-    }
+    let finish_reason = extract_finish_reason(&response);
 
     let body_bytes = serde_json::to_vec(&response).map_err(|e| RequestError {
         error: Error::Internal(format!("Failed to serialize response: {e}")),
         provider_name: Some(provider.name.clone()),
         status_code: 500,
         message: format!("Failed to serialize response: {e}"),
+        kind: None,
     })?;
 
     let http_response = Response::builder()
@@ -1306,6 +3671,7 @@ async fn handle_non_streaming_response(
             provider_name: Some(provider.name.clone()),
             status_code: 500,
             message: format!("Failed to build response: {e}"),
+            kind: None,
         })?;
 
     Ok(RequestOutcome {
@@ -1315,16 +3681,25 @@ async fn handle_non_streaming_response(
         output_tokens,
         cost_sats,
         provider_cost_sats,
+        finish_reason,
+        row_queued: None,
+        streamed: false,
     })
 }
 
 /// Handle a streaming provider response.
 ///
 /// Uses a channel-based body with a background task that:
-/// 1. Wraps the upstream byte stream with `wrap_sse_stream` for observation
-/// 2. Forwards chunks to the client via mpsc channel
+/// 1. Wraps the upstream byte stream with `wrap_sse_stream` for observation,
+///    after `normalize_sse_stream` when the provider has `normalize_stream`
+///    and `filter_sse_stream` when `[content_filter]` is configured
+/// 2. Forwards chunks to the client via mpsc channel, with a `: keep-alive`
+///    comment between events whenever the provider is idle for `keepalive`,
+///    until the observer's delta token estimate passes the client's
+///    `x-arbstr-max-output-tokens` budget
 /// 3. After stream ends: extracts usage, computes cost, sends trailing SSE event
-/// 4. Fires DB UPDATE with tokens/cost/duration/completion status
+/// 4. Fires DB UPDATE with tokens/cost/duration/finish_reason/completion status
+///    once the caller has queued the row insert (see `RequestOutcome::row_queued`)
 ///
 /// The response is returned immediately with the channel-backed body.
 /// Tokens/cost are filled by the background task's DB UPDATE, not the return value.
@@ -1334,12 +3709,23 @@ async fn handle_streaming_response(
     provider: &crate::router::SelectedProvider,
     correlation_id: String,
     db_writer: Option<crate::storage::DbWriter>,
+    recent: Arc<super::recent::RecentRequests>,
+    ledger: Arc<super::ledger::ProviderLedger>,
+    vouchers: Arc<super::vouchers::Vouchers>,
+    receipts: Arc<super::receipts::ReceiptSigner>,
+    quotas: Arc<super::quota::ProviderQuotas>,
     vault: Option<VaultClient>,
     reservation_id: Option<String>,
     db_pool: Option<sqlx::SqlitePool>,
     stream_start: std::time::Instant,
     complexity_score: Option<f64>,
     tier: Option<String>,
+    chaos_fault: Option<super::chaos::StreamFault>,
+    normalize: bool,
+    content_filter: Option<super::content_filter::ContentFilter>,
+    budget: Option<super::output_budget::OutputBudget>,
+    trailing_metadata: bool,
+    keepalive: Option<std::time::Duration>,
 ) -> std::result::Result<RequestOutcome, RequestError> {
     let provider_name = provider.name.clone();
 
@@ -1347,28 +3733,113 @@ async fn handle_streaming_response(
     let input_rate = provider.input_rate;
     let output_rate = provider.output_rate;
     let base_fee = provider.base_fee;
+    let min_charge_sats = provider.min_charge_sats;
     let provider_name_for_vault = provider.name.clone();
 
     // Create mpsc channel for streaming body
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<bytes::Bytes, std::io::Error>>(32);
 
+    // Short streams can finish before the caller logs the row; hold the
+    // completion UPDATE until the insert is queued ahead of it.
+    let (row_queued_tx, row_queued_rx) = tokio::sync::oneshot::channel::<()>();
+
     // Wrap upstream byte stream with SSE observer
+    if let Some(fault) = chaos_fault {
+        tracing::warn!(provider = %provider_name, fault = ?fault, "Chaos: injecting stream fault");
+    }
+    let (filtered_stream, filter_handle) = super::content_filter::filter_sse_stream(
+        crate::proxy::stream::normalize_sse_stream(
+            super::chaos::apply_stream_fault(upstream_response.bytes_stream(), chaos_fault),
+            normalize,
+        ),
+        content_filter,
+    );
+    let delta_tokens = crate::proxy::stream::DeltaTokenCounter::default();
     let (observed_stream, result_handle) =
-        crate::proxy::stream::wrap_sse_stream(upstream_response.bytes_stream());
+        crate::proxy::stream::wrap_sse_stream_counting(filtered_stream, delta_tokens.clone());
 
     // Spawn background task for stream forwarding and post-stream work
     let cid = correlation_id.clone();
     tokio::spawn(async move {
         use futures::StreamExt;
 
-        futures::pin_mut!(observed_stream);
+        let mut observed_stream = Box::pin(observed_stream);
 
         let mut client_connected = true;
+        let mut boundary = crate::proxy::stream::EventBoundary::default();
+        let mut over_budget = false;
+        // Under a budget only whole events are forwarded, so the event that
+        // crosses it can be withheld even when it arrives split across chunks.
+        let mut partial_event: Vec<u8> = Vec::new();
 
         // Forward loop: relay chunks to client, continue consuming on disconnect
-        while let Some(chunk_result) = observed_stream.next().await {
+        loop {
+            let next = match keepalive {
+                Some(interval) if client_connected => {
+                    match tokio::time::timeout(interval, observed_stream.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            // Provider idle: keep the connection warm. The
+                            // comment goes to the client only, never the
+                            // observer, and only between events.
+                            if boundary.at_boundary()
+                                && tx
+                                    .send(Ok(bytes::Bytes::from_static(
+                                        crate::proxy::stream::KEEPALIVE_COMMENT,
+                                    )))
+                                    .await
+                                    .is_err()
+                            {
+                                client_connected = false;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                _ => observed_stream.next().await,
+            };
+            let Some(chunk_result) = next else {
+                break;
+            };
             match chunk_result {
+                Ok(_)
+                    if budget.is_some_and(|b| {
+                        delta_tokens.load(std::sync::atomic::Ordering::Relaxed) > b.max_tokens
+                    }) =>
+                {
+                    // Withhold the chunk that crossed the client's budget,
+                    // with any partial event held back before it, and stop
+                    // reading; dropping the stream below aborts the
+                    // upstream request.
+                    over_budget = true;
+                    partial_event.clear();
+                    tracing::info!(
+                        correlation_id = %cid,
+                        max_output_tokens = budget.map(|b| b.max_tokens),
+                        "Output token budget reached, aborting stream"
+                    );
+                    if client_connected {
+                        let _ = tx.send(Ok(super::output_budget::finish_event())).await;
+                    }
+                    break;
+                }
                 Ok(bytes) => {
+                    let bytes = if budget.is_some() {
+                        partial_event.extend_from_slice(&bytes);
+                        let mut end = 0;
+                        while let Some(len) =
+                            crate::proxy::stream::find_event_end(&partial_event[end..])
+                        {
+                            end += len;
+                        }
+                        bytes::Bytes::from(partial_event.drain(..end).collect::<Vec<u8>>())
+                    } else {
+                        bytes
+                    };
+                    if bytes.is_empty() {
+                        continue;
+                    }
+                    boundary.observe(&bytes);
                     if client_connected && tx.send(Ok(bytes)).await.is_err() {
                         client_connected = false;
                         tracing::info!(
@@ -1391,9 +3862,20 @@ async fn handle_streaming_response(
             }
         }
 
+        // An unterminated last event still goes out as the provider sent it
+        if !partial_event.is_empty() && client_connected {
+            let rest = bytes::Bytes::from(std::mem::take(&mut partial_event));
+            boundary.observe(&rest);
+            let _ = tx.send(Ok(rest)).await;
+        }
+
         // Stream ended -- measure duration
         let stream_duration_ms = stream_start.elapsed().as_millis() as i64;
 
+        // The observer publishes its result when dropped; release the stream
+        // before reading the handle.
+        drop(observed_stream);
+
         // Read result from handle
         let stream_result = result_handle
             .lock()
@@ -1410,6 +3892,7 @@ async fn handle_streaming_response(
                         input_rate,
                         output_rate,
                         base_fee,
+                        min_charge_sats,
                     );
                     (
                         Some(usage.prompt_tokens),
@@ -1421,9 +3904,49 @@ async fn handle_streaming_response(
             },
             None => (None, None, None),
         };
+        // Cut short at the client's budget, the provider's usage never
+        // arrives: price the estimated tokens generated so far
+        let (input_tokens, output_tokens, cost_sats) = match budget {
+            Some(budget) if over_budget && cost_sats.is_none() => {
+                let output = delta_tokens.load(std::sync::atomic::Ordering::Relaxed);
+                (
+                    Some(budget.prompt_tokens),
+                    Some(output),
+                    Some(crate::router::actual_cost_sats(
+                        budget.prompt_tokens,
+                        output,
+                        input_rate,
+                        output_rate,
+                        base_fee,
+                        min_charge_sats,
+                    )),
+                )
+            }
+            _ => (input_tokens, output_tokens, cost_sats),
+        };
+
+        // A stream stopped by the content filter ends without [DONE]
+        let filtered_by = filter_handle.get().cloned();
+        let finish_reason = if filtered_by.is_some() {
+            Some("content_filter".to_string())
+        } else if over_budget {
+            Some(super::output_budget::FINISH_REASON.to_string())
+        } else {
+            stream_result
+                .as_ref()
+                .and_then(|sr| sr.finish_reason.clone())
+        };
+        let tokens_per_second = stream_result.as_ref().and_then(|sr| sr.tokens_per_second());
 
         // Determine completion status
         let (success, error_message) = match &stream_result {
+            _ if filtered_by.is_some() => (
+                false,
+                filtered_by
+                    .as_ref()
+                    .map(|rule| format!("content_filter: {}", rule)),
+            ),
+            _ if over_budget => (true, None),
             Some(sr) if sr.done_received => {
                 if client_connected {
                     (true, None) // Normal completion
@@ -1434,19 +3957,54 @@ async fn handle_streaming_response(
             _ => (false, Some("stream_incomplete".to_string())),
         };
 
-        // Emit trailing SSE event if client is still connected
-        if client_connected {
-            let trailing = build_trailing_sse_event(
-                cost_sats,
-                stream_duration_ms,
-                complexity_score,
-                tier.clone(),
-            );
+        // Emit trailing SSE event if client is still connected and the
+        // stream wasn't stopped; without body metadata it is only the
+        // closing [DONE]
+        if client_connected && filtered_by.is_none() {
+            let trailing = if trailing_metadata {
+                build_trailing_sse_event(
+                    cost_sats,
+                    stream_duration_ms,
+                    complexity_score,
+                    tier.clone(),
+                )
+            } else {
+                b"data: [DONE]\n\n".to_vec()
+            };
             let _ = tx.send(Ok(bytes::Bytes::from(trailing))).await;
         }
         // tx is dropped here, closing the channel and signaling end-of-body
 
-        // Fire DB UPDATE via bounded writer (always, regardless of client status)
+        // Fire DB UPDATE via bounded writer (always, regardless of client status).
+        // A dropped sender means the outcome was discarded without logging; the
+        // update then finds no row and warns.
+        let _ = row_queued_rx.await;
+        if let Some(cost) = cost_sats {
+            ledger.debit(&provider_name_for_vault, cost);
+        }
+        vouchers.settle_stream(&cid, cost_sats);
+        receipts.settle_stream(
+            db_writer.as_ref(),
+            &cid,
+            success,
+            input_tokens,
+            output_tokens,
+            cost_sats,
+        );
+        if let (Some(input), Some(output)) = (input_tokens, output_tokens) {
+            quotas.record_tokens(&provider_name_for_vault, input as u64 + output as u64);
+        }
+        recent.complete_stream(
+            &cid,
+            super::recent::StreamCompletion {
+                input_tokens,
+                output_tokens,
+                cost_sats,
+                duration_ms: stream_duration_ms,
+                success,
+                finish_reason: finish_reason.clone(),
+            },
+        );
         if let Some(writer) = &db_writer {
             writer.stream_completion_update(
                 cid.clone(),
@@ -1458,6 +4016,8 @@ async fn handle_streaming_response(
                 error_message.clone(),
                 complexity_score,
                 tier.clone(),
+                finish_reason,
+                tokens_per_second,
             );
         }
 
@@ -1465,7 +4025,7 @@ async fn handle_streaming_response(
         if let (Some(vault), Some(rid)) = (vault, reservation_id) {
             if success || cost_sats.is_some() {
                 // Stream completed OR usage data available: settle at actual cost
-                let actual_msats = cost_sats.map(|c| (c * 1000.0) as u64).unwrap_or(0);
+                let actual_msats = cost_sats.map(|c| (c * 1000.0).round() as u64).unwrap_or(0);
                 match vault
                     .settle(
                         &rid,
@@ -1569,6 +4129,7 @@ async fn handle_streaming_response(
             provider_name: Some(provider_name.clone()),
             status_code: 500,
             message: format!("Failed to build streaming response: {e}"),
+            kind: None,
         })?;
 
     // Tokens/cost will be filled by DB UPDATE from the spawned task
@@ -1579,6 +4140,9 @@ async fn handle_streaming_response(
         output_tokens: None,
         cost_sats: None,
         provider_cost_sats: None,
+        finish_reason: None,
+        row_queued: Some(row_queued_tx),
+        streamed: true,
     })
 }
 
@@ -1645,6 +4209,11 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
 pub struct HealthResponse {
     pub status: String,
     pub providers: std::collections::HashMap<String, ProviderHealth>,
+    /// Retry budget usage, when `[routing.retry_budget]` is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_budget: Option<super::retry_budget::RetryBudgetSnapshot>,
+    /// Request log anonymization in effect (`[privacy]`).
+    pub privacy: super::privacy::PrivacyStatus,
 }
 
 /// Per-provider health entry in the `/health` response.
@@ -1652,7 +4221,21 @@ pub struct HealthResponse {
 pub struct ProviderHealth {
     pub state: String,
     pub failure_count: u32,
+    /// Consecutive failures by error type; omitted when there are none.
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub failures_by_type: std::collections::BTreeMap<String, u32>,
     pub tier: String,
+    /// Observed upstream connection stats and any dedicated pool settings.
+    pub connections: super::pool::ConnectionStats,
+    /// Present while the provider is suspended for rejecting its API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_quarantine: Option<super::quarantine::QuarantineSnapshot>,
+    /// Remaining rate quota, for providers that declare one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<super::quota::QuotaSnapshot>,
+    /// Seconds left in a trip-storm cool-down, while the circuit is held open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storm_cooldown_secs: Option<u64>,
 }
 
 /// Handle GET /health
@@ -1683,7 +4266,15 @@ pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
                 ProviderHealth {
                     state: snap.state.as_str().to_string(),
                     failure_count: snap.failure_count,
+                    failures_by_type: snap.failures_by_type.clone(),
                     tier,
+                    connections: state.provider_clients.stats(&snap.name),
+                    auth_quarantine: state.auth_quarantine.snapshot(&snap.name),
+                    quota: state.quotas.snapshot(&snap.name),
+                    storm_cooldown_secs: state
+                        .circuit_breakers
+                        .cooldown_remaining(&snap.name)
+                        .map(|d| d.as_secs()),
                 },
             )
         })
@@ -1707,6 +4298,8 @@ pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
         Json(HealthResponse {
             status: status_text.to_string(),
             providers,
+            retry_budget: state.retry_budget.snapshot(),
+            privacy: state.privacy.status(),
         }),
     )
 }
@@ -1729,6 +4322,15 @@ pub async fn cost_estimate(
 
     // Extract user prompt for heuristic policy matching
     let prompt = request.user_prompt().map(|s| s.to_string());
+    let (estimated_input_tokens, estimated_output_tokens) =
+        request.estimate_tokens(ESTIMATE_OUTPUT_TOKENS);
+    // Prompt-length rules apply as for chat completions
+    let policy_name = policy_name.or_else(|| {
+        state
+            .router
+            .prompt_length_policy(prompt.as_deref(), estimated_input_tokens)
+            .map(str::to_string)
+    });
 
     // Select cheapest provider via router (no upstream call)
     let provider = state.router.select(
@@ -1738,9 +4340,6 @@ pub async fn cost_estimate(
         None,
     )?;
 
-    // Estimate tokens using shared estimation logic (256 default for display)
-    let (estimated_input_tokens, estimated_output_tokens) = request.estimate_tokens(256);
-
     // Calculate estimated cost
     let estimated_cost_sats = crate::router::actual_cost_sats(
         estimated_input_tokens,
@@ -1748,6 +4347,7 @@ pub async fn cost_estimate(
         provider.input_rate,
         provider.output_rate,
         provider.base_fee,
+        provider.min_charge_sats,
     );
 
     Ok(Json(serde_json::json!({
@@ -1760,10 +4360,221 @@ pub async fn cost_estimate(
             "input_rate_sats_per_1k": provider.input_rate,
             "output_rate_sats_per_1k": provider.output_rate,
             "base_fee_sats": provider.base_fee,
+            "min_charge_sats": provider.min_charge_sats,
         }
     })))
 }
 
+/// Handle POST /v1/compare - send one prompt to several models in parallel.
+///
+/// Each target is routed and logged as its own request (correlation ID
+/// `<comparison id>-<n>`, tagged `comparison=<id>`); retries and fallbacks
+/// are skipped so every response comes from the model and provider asked
+/// for. See [`super::compare`].
+pub async fn compare(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Result<impl IntoResponse, Error> {
+    use super::compare::{self, CompareResponse, CompareResult, COMPARISON_TAG};
+    use crate::storage::comparisons::{Comparison, ComparisonResult};
+
+    if state.vault.is_some() {
+        return Err(Error::BadRequest(
+            "Comparisons are not available when the vault is enabled".to_string(),
+        ));
+    }
+    let start = std::time::Instant::now();
+    let comparison_id = request_id.0.to_string();
+    let (targets, requests, shared) = compare::parse_request(body)?;
+
+    // Route every target before sending any of them
+    let mut sends = Vec::with_capacity(targets.len());
+    for (i, (target, request)) in targets.iter().zip(requests).enumerate() {
+        let mut ctx = request_context(
+            &state,
+            &request_id,
+            trace.clone(),
+            &headers,
+            target.model.clone(),
+            false,
+            start,
+        )
+        .map_err(|_| Error::BadRequest("X-Arbstr-Tags is malformed".to_string()))?;
+        ctx.correlation_id = format!("{}-{}", comparison_id, i + 1);
+        set_tag(&mut ctx, COMPARISON_TAG, &comparison_id);
+
+        let candidates = state
+            .router
+            .select_candidates(
+                &request.model,
+                ctx.policy_name.as_deref(),
+                request.user_prompt(),
+                None,
+            )?
+            .into_iter()
+            .filter(|c| !state.maintenance.is_unavailable(&c.name));
+        let provider = match &target.provider {
+            Some(name) => candidates
+                .into_iter()
+                .find(|p| &p.name == name)
+                .ok_or_else(|| {
+                    Error::BadRequest(format!(
+                        "Provider '{}' cannot serve model '{}'",
+                        name, target.model
+                    ))
+                })?,
+            None => candidates.into_iter().next().ok_or(Error::NoProviders {
+                model: target.model.clone(),
+            })?,
+        };
+        redeem_voucher(
+            &state,
+            &mut ctx,
+            &headers,
+            Some(&provider),
+            request.estimate_tokens(ESTIMATE_OUTPUT_TOKENS),
+        )?;
+        sends.push((ctx, request, provider));
+    }
+
+    let results =
+        futures::future::join_all(sends.into_iter().map(|(mut ctx, request, provider)| {
+            let state = &state;
+            async move {
+                let sent = std::time::Instant::now();
+                let result = tokio::time::timeout(
+                    RETRY_TIMEOUT,
+                    send_to_provider(
+                        state,
+                        UpstreamBody::Parsed(&request),
+                        &provider,
+                        &ctx.correlation_id,
+                        &ctx.forward_headers,
+                        false,
+                        None,
+                        None,
+                        None,
+                        None,
+                    ),
+                )
+                .await;
+                let result = match result {
+                    Ok(result) => result,
+                    Err(_elapsed) => Err(RequestError {
+                        error: Error::Provider("Request timed out".to_string()),
+                        provider_name: Some(provider.name.clone()),
+                        status_code: 504,
+                        message: "Request timed out".to_string(),
+                        kind: Some(ProviderErrorKind::Timeout),
+                    }),
+                };
+                let latency_ms = sent.elapsed().as_millis() as i64;
+                ctx.start = sent;
+
+                let mut stored = ComparisonResult {
+                    model: ctx.model.clone(),
+                    provider: Some(provider.name.clone()),
+                    correlation_id: ctx.correlation_id.clone(),
+                    success: false,
+                    latency_ms,
+                    cost_sats: None,
+                    input_tokens: None,
+                    output_tokens: None,
+                    content: None,
+                    error_status: None,
+                    error_message: None,
+                };
+                let mut response = None;
+                match result {
+                    Ok(mut outcome) => {
+                        state
+                            .circuit_breakers
+                            .record_success(&outcome.provider_name);
+                        state
+                            .reputation
+                            .record(&outcome.provider_name, true, latency_ms as u64);
+                        state
+                            .tie_breaks
+                            .record(&outcome.provider_name, true, latency_ms as u64);
+                        let body = std::mem::take(outcome.response.body_mut());
+                        let bytes = axum::body::to_bytes(body, usize::MAX)
+                            .await
+                            .unwrap_or_default();
+                        let json = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+                        let recorded =
+                            log_success_to_db(state, &ctx, latency_ms, &mut outcome, None, None)
+                                .await;
+                        stored.cost_sats = outcome.cost_sats;
+                        stored.input_tokens = outcome.input_tokens.map(i64::from);
+                        stored.output_tokens = outcome.output_tokens.map(i64::from);
+                        match recorded {
+                            Ok(()) => {
+                                stored.success = true;
+                                stored.content = json.as_ref().and_then(|v| {
+                                    v["choices"][0]["message"]["content"]
+                                        .as_str()
+                                        .map(str::to_string)
+                                });
+                                response = json;
+                            }
+                            Err(e) => {
+                                stored.error_status = Some(503);
+                                stored.error_message = Some(e.to_string());
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        record_send_failure(state, &e);
+                        stored.error_status = Some(i64::from(e.status_code));
+                        stored.error_message = Some(e.message.clone());
+                        log_error_to_db(
+                            state,
+                            &ctx,
+                            latency_ms,
+                            e.provider_name,
+                            e.status_code,
+                            e.kind,
+                            e.message,
+                            None,
+                            None,
+                        );
+                    }
+                }
+                (stored, response)
+            }
+        }))
+        .await;
+
+    let strip = state.privacy.status().strip_prompts;
+    let comparison = Comparison {
+        id: comparison_id.clone(),
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        request: (!strip).then(|| shared.to_string()),
+        results: results
+            .iter()
+            .map(|(stored, _)| ComparisonResult {
+                content: stored.content.clone().filter(|_| !strip),
+                error_message: stored.error_message.clone().filter(|_| !strip),
+                ..stored.clone()
+            })
+            .collect(),
+    };
+    let persisted = compare::persist(&state, &comparison).await;
+
+    Ok(Json(CompareResponse {
+        id: comparison_id,
+        timestamp: comparison.timestamp,
+        persisted,
+        results: results
+            .into_iter()
+            .map(|(stored, response)| CompareResult::new(stored, response))
+            .collect(),
+    }))
+}
+
 /// Handle GET /providers - arbstr extension to list providers
 pub async fn list_providers(State(state): State<AppState>) -> impl IntoResponse {
     let providers: Vec<serde_json::Value> = state
@@ -1771,18 +4582,32 @@ pub async fn list_providers(State(state): State<AppState>) -> impl IntoResponse
         .providers()
         .iter()
         .map(|p| {
-            serde_json::json!({
+            let mut entry = serde_json::json!({
                 "name": p.name,
                 "models": p.models,
                 "input_rate_sats_per_1k": p.input_rate,
                 "output_rate_sats_per_1k": p.output_rate,
                 "base_fee_sats": p.base_fee,
+                "min_charge_sats": p.min_charge_sats,
                 "tier": p.tier.to_string(),
                 "api_key": match &p.api_key {
                     Some(key) => serde_json::Value::String(key.masked_prefix()),
                     None => serde_json::Value::Null,
                 },
-            })
+            });
+            if let Some(region) = &p.region {
+                entry["region"] = serde_json::json!(region);
+            }
+            if let Some(canary) = state.canary.status(&p.name) {
+                entry["canary"] = serde_json::json!(canary);
+            }
+            let maintenance = state.maintenance.status(&p.name);
+            entry["status"] = serde_json::json!(maintenance.status);
+            entry["enabled"] = serde_json::json!(maintenance.enabled);
+            if let Some(until) = maintenance.maintenance_until {
+                entry["maintenance_until"] = serde_json::json!(until);
+            }
+            entry
         })
         .collect();
 
@@ -1844,6 +4669,18 @@ mod tests {
         assert_eq!(usage, None);
     }
 
+    #[test]
+    fn test_extract_finish_reason() {
+        let response = serde_json::json!({
+            "choices": [{"message": {"content": "hi"}, "finish_reason": "length"}]
+        });
+        assert_eq!(extract_finish_reason(&response).as_deref(), Some("length"));
+
+        let response = serde_json::json!({"choices": [{"finish_reason": null}]});
+        assert_eq!(extract_finish_reason(&response), None);
+        assert_eq!(extract_finish_reason(&serde_json::json!({})), None);
+    }
+
     #[test]
     fn test_attach_headers_non_streaming() {
         let mut response = Response::builder()
@@ -1864,7 +4701,7 @@ mod tests {
             "550e8400-e29b-41d4-a716-446655440000"
         );
         assert_eq!(headers.get("x-arbstr-latency-ms").unwrap(), "1523");
-        assert_eq!(headers.get("x-arbstr-cost-sats").unwrap(), "42.35");
+        assert_eq!(headers.get("x-arbstr-cost-sats").unwrap(), "42.350");
         assert_eq!(headers.get("x-arbstr-provider").unwrap(), "provider-alpha");
         assert!(headers.get("x-arbstr-streaming").is_none());
     }
@@ -1955,12 +4792,12 @@ mod tests {
             "00000000-0000-0000-0000-000000000000",
             100,
             Some("provider"),
-            Some(0.10), // should format as "0.10" not "0.1"
+            Some(0.10), // should format as "0.100" not "0.1"
             false,
         );
         assert_eq!(
             response.headers().get("x-arbstr-cost-sats").unwrap(),
-            "0.10"
+            "0.100"
         );
     }
 
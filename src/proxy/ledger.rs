@@ -0,0 +1,675 @@
+//! Spend ledger for prepaid providers and the `/admin/ledger` endpoints.
+//!
+//! Providers with `balance_sats` set (Cashu or credits-based Routstr nodes)
+//! get an account: the configured starting balance plus manual top-ups,
+//! minus the `cost_sats` of every request routed to them. Routing skips a
+//! provider once its remaining balance can't cover a request's estimated
+//! cost. With a database the ledger is restored on startup; without one it
+//! starts over from the configured balance.
+//!
+//! With `balance_reset`, `balance_sats` is a recurring budget instead: it
+//! renews daily or monthly (UTC) or covers a rolling window, optionally
+//! carrying the previous period's unused balance forward.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::server::AppState;
+use crate::config::{BalanceResetConfig, BalanceSchedule, ProviderConfig};
+use crate::error::Error;
+use crate::storage::ledger::{self, LedgerTotals};
+
+/// Running balances of prepaid providers. Empty when none are configured.
+#[derive(Debug, Default)]
+pub struct ProviderLedger {
+    accounts: Mutex<BTreeMap<String, Account>>,
+}
+
+/// One provider's account.
+#[derive(Debug)]
+struct Account {
+    /// Credits and spend since the ledger was opened or, with a reset
+    /// schedule, in the current period or window.
+    totals: LedgerTotals,
+    reset: Option<BalanceResetConfig>,
+    /// Start of the current `daily`/`monthly` period.
+    period_start: Option<DateTime<Utc>>,
+    /// Unused balance carried over from the previous period.
+    rollover_sats: f64,
+    /// Top-ups and spend inside the rolling window, oldest first.
+    window: VecDeque<(DateTime<Utc>, f64, f64)>,
+}
+
+/// One provider's account, for `/admin/ledger`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LedgerSnapshot {
+    pub provider: String,
+    pub opened_at: String,
+    pub opening_sats: f64,
+    pub topped_up_sats: f64,
+    pub spent_sats: f64,
+    pub remaining_sats: f64,
+    /// When the balance renews (`balance_reset`). Top-ups and spend then
+    /// cover the current period or window only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<BalanceSchedule>,
+    /// Start of the current period or rolling window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_start: Option<String>,
+    /// Start of the next `daily`/`monthly` period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resets_at: Option<String>,
+    /// Unused balance carried over from the previous period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollover_sats: Option<f64>,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Start of the `daily`/`monthly` period containing `at`.
+fn period_start(schedule: BalanceSchedule, at: DateTime<Utc>) -> DateTime<Utc> {
+    let date = at.date_naive();
+    let date = match schedule {
+        BalanceSchedule::Monthly => date.with_day(1).unwrap_or(date),
+        BalanceSchedule::Daily | BalanceSchedule::Rolling => date,
+    };
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Start of the period after the one starting at `start`.
+fn next_period(schedule: BalanceSchedule, start: DateTime<Utc>) -> DateTime<Utc> {
+    match schedule {
+        BalanceSchedule::Monthly => start
+            .checked_add_months(Months::new(1))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        BalanceSchedule::Daily | BalanceSchedule::Rolling => start + Duration::days(1),
+    }
+}
+
+/// Start of the period before the one starting at `start`.
+fn previous_period(schedule: BalanceSchedule, start: DateTime<Utc>) -> DateTime<Utc> {
+    match schedule {
+        BalanceSchedule::Monthly => start
+            .checked_sub_months(Months::new(1))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        BalanceSchedule::Daily | BalanceSchedule::Rolling => start - Duration::days(1),
+    }
+}
+
+/// Carried-over sats for an `unused` balance, within `max_rollover_sats`.
+fn rollover(reset: &BalanceResetConfig, unused: f64) -> f64 {
+    let unused = unused.max(0.0);
+    reset
+        .max_rollover_sats
+        .map_or(unused, |max| unused.min(max))
+}
+
+impl Account {
+    fn new(totals: LedgerTotals, reset: Option<BalanceResetConfig>, now: DateTime<Utc>) -> Self {
+        let period_start = reset
+            .as_ref()
+            .filter(|r| r.schedule != BalanceSchedule::Rolling)
+            .map(|r| period_start(r.schedule, now));
+        Self {
+            totals,
+            reset,
+            period_start,
+            rollover_sats: 0.0,
+            window: VecDeque::new(),
+        }
+    }
+
+    fn remaining(&self) -> f64 {
+        self.totals.opening_sats + self.rollover_sats + self.totals.topped_up_sats
+            - self.totals.spent_sats
+    }
+
+    /// Move to the period or rolling window containing `now`.
+    fn advance(&mut self, now: DateTime<Utc>) {
+        let Some(reset) = &self.reset else {
+            return;
+        };
+        if reset.schedule == BalanceSchedule::Rolling {
+            let cutoff = now - reset.window();
+            while let Some(&(at, topped_up, spent)) = self.window.front() {
+                if at >= cutoff {
+                    break;
+                }
+                self.window.pop_front();
+                self.totals.topped_up_sats -= topped_up;
+                self.totals.spent_sats -= spent;
+            }
+            if self.window.is_empty() {
+                self.totals.topped_up_sats = 0.0;
+                self.totals.spent_sats = 0.0;
+            }
+            return;
+        }
+
+        let start = period_start(reset.schedule, now);
+        let Some(current) = self.period_start.filter(|current| *current < start) else {
+            return;
+        };
+        self.rollover_sats = if reset.rollover {
+            // A period skipped without traffic left its whole balance unused
+            let unused = if next_period(reset.schedule, current) == start {
+                self.totals.opening_sats + self.totals.topped_up_sats - self.totals.spent_sats
+            } else {
+                self.totals.opening_sats
+            };
+            rollover(reset, unused)
+        } else {
+            0.0
+        };
+        self.totals.topped_up_sats = 0.0;
+        self.totals.spent_sats = 0.0;
+        self.period_start = Some(start);
+    }
+
+    /// Add a top-up and/or spend made at `now`.
+    fn record(&mut self, now: DateTime<Utc>, topped_up: f64, spent: f64) {
+        self.advance(now);
+        self.totals.topped_up_sats += topped_up;
+        self.totals.spent_sats += spent;
+        if self
+            .reset
+            .as_ref()
+            .is_some_and(|r| r.schedule == BalanceSchedule::Rolling)
+        {
+            self.window.push_back((now, topped_up, spent));
+        }
+    }
+
+    fn snapshot(&self, provider: &str, now: DateTime<Utc>) -> LedgerSnapshot {
+        let schedule = self.reset.as_ref().map(|r| r.schedule);
+        let period_start = match &self.reset {
+            Some(r) if r.schedule == BalanceSchedule::Rolling => Some(now - r.window()),
+            _ => self.period_start,
+        };
+        LedgerSnapshot {
+            provider: provider.to_string(),
+            opened_at: self.totals.opened_at.clone(),
+            opening_sats: self.totals.opening_sats,
+            topped_up_sats: self.totals.topped_up_sats,
+            spent_sats: self.totals.spent_sats,
+            remaining_sats: self.remaining(),
+            schedule,
+            period_start: period_start.map(timestamp),
+            resets_at: schedule
+                .zip(self.period_start)
+                .map(|(s, start)| timestamp(next_period(s, start))),
+            rollover_sats: self
+                .reset
+                .as_ref()
+                .filter(|r| r.rollover)
+                .map(|_| self.rollover_sats),
+        }
+    }
+}
+
+/// Restore one account from the database: totals since the ledger opened,
+/// or with a reset schedule those of the current period (plus the previous
+/// one's unused balance for rollover) or rolling window.
+async fn restore_account(
+    pool: &SqlitePool,
+    provider: &str,
+    opening_sats: f64,
+    reset: Option<BalanceResetConfig>,
+    now: DateTime<Utc>,
+) -> Result<Account, sqlx::Error> {
+    let totals = ledger::restore(pool, provider, opening_sats, &timestamp(now)).await?;
+    let Some(reset) = reset else {
+        return Ok(Account::new(totals, None, now));
+    };
+    let opened_at = DateTime::parse_from_rfc3339(&totals.opened_at)
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or(now);
+    let mut account = Account::new(
+        LedgerTotals {
+            topped_up_sats: 0.0,
+            spent_sats: 0.0,
+            ..totals
+        },
+        Some(reset.clone()),
+        now,
+    );
+
+    if reset.schedule == BalanceSchedule::Rolling {
+        let since = (now - reset.window()).max(opened_at);
+        for (minute, topped_up, spent) in
+            ledger::activity_by_minute(pool, provider, &timestamp(since)).await?
+        {
+            let Ok(at) = NaiveDateTime::parse_from_str(&minute, "%Y-%m-%dT%H:%M") else {
+                continue;
+            };
+            account.record(at.and_utc(), topped_up, spent);
+        }
+        account.advance(now);
+        return Ok(account);
+    }
+
+    let start = period_start(reset.schedule, now);
+    let (topped_up, spent) = ledger::totals_between(
+        pool,
+        provider,
+        &timestamp(start.max(opened_at)),
+        &timestamp(next_period(reset.schedule, start)),
+    )
+    .await?;
+    account.totals.topped_up_sats = topped_up;
+    account.totals.spent_sats = spent;
+    if reset.rollover && opened_at < start {
+        let previous = previous_period(reset.schedule, start);
+        let (topped_up, spent) = ledger::totals_between(
+            pool,
+            provider,
+            &timestamp(previous.max(opened_at)),
+            &timestamp(start),
+        )
+        .await?;
+        account.rollover_sats = rollover(&reset, opening_sats + topped_up - spent);
+    }
+    Ok(account)
+}
+
+impl ProviderLedger {
+    /// Open an account at the configured balance for every prepaid provider.
+    pub fn new(providers: &[ProviderConfig]) -> Self {
+        let now = Utc::now();
+        let opened_at = timestamp(now);
+        let accounts = providers
+            .iter()
+            .filter_map(|p| {
+                let opening_sats = p.balance_sats?;
+                let totals = LedgerTotals {
+                    opened_at: opened_at.clone(),
+                    opening_sats,
+                    topped_up_sats: 0.0,
+                    spent_sats: 0.0,
+                };
+                Some((
+                    p.name.clone(),
+                    Account::new(totals, p.balance_reset.clone(), now),
+                ))
+            })
+            .collect();
+        Self {
+            accounts: Mutex::new(accounts),
+        }
+    }
+
+    /// Replace every account with its persisted state.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        self.restore_at(pool, Utc::now()).await
+    }
+
+    async fn restore_at(&self, pool: &SqlitePool, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let opening: Vec<(String, f64, Option<BalanceResetConfig>)> = self
+            .lock()
+            .iter()
+            .map(|(name, account)| {
+                (
+                    name.clone(),
+                    account.totals.opening_sats,
+                    account.reset.clone(),
+                )
+            })
+            .collect();
+        for (name, opening_sats, reset) in opening {
+            let account = restore_account(pool, &name, opening_sats, reset, now).await?;
+            tracing::info!(
+                provider = %name,
+                opened_at = %account.totals.opened_at,
+                remaining_sats = account.remaining(),
+                "Provider ledger restored"
+            );
+            self.lock().insert(name, account);
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Account>> {
+        self.accounts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `provider` can pay for a request costing `cost_sats`.
+    /// Providers without a ledger always can.
+    pub fn can_cover(&self, provider: &str, cost_sats: f64) -> bool {
+        self.lock().get_mut(provider).is_none_or(|account| {
+            account.advance(Utc::now());
+            account.remaining() >= cost_sats
+        })
+    }
+
+    /// Charge a completed request to `provider`.
+    pub fn debit(&self, provider: &str, cost_sats: f64) {
+        if let Some(account) = self.lock().get_mut(provider) {
+            account.record(Utc::now(), 0.0, cost_sats);
+        }
+    }
+
+    /// Add `amount_sats` to `provider`'s balance, recording it in the
+    /// database when one is available.
+    pub async fn top_up(
+        &self,
+        pool: Option<&SqlitePool>,
+        provider: &str,
+        amount_sats: f64,
+        note: Option<&str>,
+    ) -> Result<LedgerSnapshot, Error> {
+        if !self.lock().contains_key(provider) {
+            return Err(Error::NotFound(format!(
+                "Provider '{}' has no ledger (set balance_sats in its config)",
+                provider
+            )));
+        }
+        let now = Utc::now();
+        if let Some(pool) = pool {
+            ledger::insert_topup(pool, provider, amount_sats, note, &timestamp(now)).await?;
+        }
+        let mut accounts = self.lock();
+        let account = accounts
+            .get_mut(provider)
+            .ok_or_else(|| Error::Internal(format!("Ledger for '{}' disappeared", provider)))?;
+        account.record(now, amount_sats, 0.0);
+        Ok(account.snapshot(provider, now))
+    }
+
+    /// All accounts, by provider name.
+    pub fn snapshots(&self) -> Vec<LedgerSnapshot> {
+        let now = Utc::now();
+        self.lock()
+            .iter_mut()
+            .map(|(name, account)| {
+                account.advance(now);
+                account.snapshot(name, now)
+            })
+            .collect()
+    }
+}
+
+/// Response for GET /admin/ledger.
+#[derive(Debug, Serialize)]
+pub struct LedgerResponse {
+    pub providers: Vec<LedgerSnapshot>,
+}
+
+/// Body for POST /admin/ledger/{provider}/topup.
+#[derive(Debug, Deserialize)]
+pub struct TopUpRequest {
+    pub amount_sats: f64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Handle GET /admin/ledger -- balances of prepaid providers.
+pub async fn ledger_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(LedgerResponse {
+        providers: state.ledger.snapshots(),
+    })
+}
+
+/// Handle POST /admin/ledger/{provider}/topup -- credit a prepaid provider.
+pub async fn topup_handler(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Json(body): Json<TopUpRequest>,
+) -> Result<impl IntoResponse, Error> {
+    if !body.amount_sats.is_finite() || body.amount_sats <= 0.0 {
+        return Err(Error::BadRequest(
+            "amount_sats must be a positive number".to_string(),
+        ));
+    }
+    let snapshot = state
+        .ledger
+        .top_up(
+            state.db.as_ref(),
+            &provider,
+            body.amount_sats,
+            body.note.as_deref(),
+        )
+        .await?;
+    tracing::info!(
+        provider = %provider,
+        amount_sats = body.amount_sats,
+        remaining_sats = snapshot.remaining_sats,
+        "Provider ledger topped up"
+    );
+    Ok(Json(snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, balance_sats: Option<f64>) -> ProviderConfig {
+        ProviderConfig {
+            balance_sats,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    #[tokio::test]
+    async fn debits_and_topups_move_the_balance() {
+        let ledger =
+            ProviderLedger::new(&[provider("prepaid", Some(10.0)), provider("open", None)]);
+
+        assert!(ledger.can_cover("prepaid", 10.0));
+        assert!(ledger.can_cover("open", 1e9));
+
+        ledger.debit("prepaid", 7.0);
+        ledger.debit("open", 7.0);
+        assert!(ledger.can_cover("prepaid", 3.0));
+        assert!(!ledger.can_cover("prepaid", 3.5));
+
+        let snap = ledger.top_up(None, "prepaid", 5.0, None).await.unwrap();
+        assert_eq!(snap.remaining_sats, 8.0);
+        assert!(ledger.can_cover("prepaid", 3.5));
+
+        assert!(matches!(
+            ledger.top_up(None, "open", 5.0, None).await,
+            Err(Error::NotFound(_))
+        ));
+        let snaps = ledger.snapshots();
+        assert_eq!(snaps.len(), 1);
+        assert_eq!(snaps[0].spent_sats, 7.0);
+        assert_eq!(snaps[0].topped_up_sats, 5.0);
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn reset(
+        schedule: BalanceSchedule,
+        rollover: bool,
+        max_rollover_sats: Option<f64>,
+    ) -> BalanceResetConfig {
+        BalanceResetConfig {
+            schedule,
+            window_hours: None,
+            rollover,
+            max_rollover_sats,
+        }
+    }
+
+    fn account(reset: BalanceResetConfig, now: &str) -> Account {
+        let totals = LedgerTotals {
+            opened_at: now.to_string(),
+            opening_sats: 100.0,
+            topped_up_sats: 0.0,
+            spent_sats: 0.0,
+        };
+        Account::new(totals, Some(reset), at(now))
+    }
+
+    #[test]
+    fn periods_start_at_utc_midnight_regardless_of_dst() {
+        use BalanceSchedule::{Daily, Monthly};
+
+        // US and EU daylight saving changes in 2026: every UTC day is 24h
+        for day in ["2026-03-08", "2026-03-29", "2026-10-25", "2026-11-01"] {
+            let start = at(&format!("{}T00:00:00Z", day));
+            assert_eq!(
+                period_start(Daily, at(&format!("{}T23:59:59Z", day))),
+                start
+            );
+            assert_eq!(period_start(Daily, start), start);
+            assert_eq!(next_period(Daily, start) - start, Duration::hours(24));
+            assert_eq!(start - previous_period(Daily, start), Duration::hours(24));
+        }
+
+        let jan = at("2026-01-01T00:00:00Z");
+        assert_eq!(period_start(Monthly, at("2026-01-31T23:59:59Z")), jan);
+        assert_eq!(next_period(Monthly, jan), at("2026-02-01T00:00:00Z"));
+        assert_eq!(previous_period(Monthly, jan), at("2025-12-01T00:00:00Z"));
+        let leap = at("2028-02-01T00:00:00Z");
+        assert_eq!(period_start(Monthly, at("2028-02-29T12:00:00Z")), leap);
+        assert_eq!(next_period(Monthly, leap), at("2028-03-01T00:00:00Z"));
+        assert_eq!(previous_period(Monthly, at("2028-03-01T00:00:00Z")), leap);
+        assert_eq!(
+            next_period(Monthly, at("2026-12-01T00:00:00Z")),
+            at("2027-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn daily_reset_carries_capped_rollover() {
+        let mut account = account(
+            reset(BalanceSchedule::Daily, true, Some(60.0)),
+            "2026-03-08T00:00:00Z",
+        );
+        account.record(at("2026-03-08T12:00:00Z"), 0.0, 70.0);
+        account.advance(at("2026-03-08T23:59:59Z"));
+        assert_eq!(account.remaining(), 30.0);
+
+        // Midnight belongs to the new day: 30 unused sats carry over
+        account.advance(at("2026-03-09T00:00:00Z"));
+        assert_eq!(account.rollover_sats, 30.0);
+        assert_eq!(account.remaining(), 130.0);
+
+        // Rollover doesn't compound, and is capped at 60
+        account.record(at("2026-03-09T08:00:00Z"), 5.0, 10.0);
+        account.advance(at("2026-03-10T00:00:01Z"));
+        assert_eq!(account.rollover_sats, 60.0);
+        assert_eq!(account.totals.spent_sats, 0.0);
+
+        // A whole day without traffic leaves its balance unused
+        account.record(at("2026-03-10T08:00:00Z"), 0.0, 100.0);
+        account.advance(at("2026-03-12T09:00:00Z"));
+        assert_eq!(account.rollover_sats, 60.0);
+        let snapshot = account.snapshot("alpha", at("2026-03-12T09:00:00Z"));
+        assert_eq!(
+            snapshot.period_start.as_deref(),
+            Some("2026-03-12T00:00:00Z")
+        );
+        assert_eq!(snapshot.resets_at.as_deref(), Some("2026-03-13T00:00:00Z"));
+        assert_eq!(snapshot.rollover_sats, Some(60.0));
+    }
+
+    #[test]
+    fn monthly_reset_without_rollover_starts_over() {
+        let mut account = account(
+            reset(BalanceSchedule::Monthly, false, None),
+            "2026-01-15T00:00:00Z",
+        );
+        account.record(at("2026-01-31T23:59:59Z"), 0.0, 80.0);
+        account.advance(at("2026-01-31T23:59:59Z"));
+        assert_eq!(account.remaining(), 20.0);
+        account.advance(at("2026-02-01T00:00:00Z"));
+        assert_eq!(account.remaining(), 100.0);
+        let snapshot = account.snapshot("alpha", at("2026-02-01T00:00:00Z"));
+        assert_eq!(snapshot.resets_at.as_deref(), Some("2026-03-01T00:00:00Z"));
+        assert_eq!(snapshot.rollover_sats, None);
+    }
+
+    #[test]
+    fn rolling_window_ages_out_spend() {
+        let mut account = account(
+            reset(BalanceSchedule::Rolling, false, None),
+            "2026-03-28T00:00:00Z",
+        );
+        account.record(at("2026-03-28T12:00:00Z"), 0.0, 60.0);
+        // Across the EU clock change, still 24 hours
+        account.record(at("2026-03-29T01:30:00Z"), 20.0, 50.0);
+        account.advance(at("2026-03-29T12:00:00Z"));
+        assert_eq!(account.remaining(), 10.0);
+        account.advance(at("2026-03-29T12:00:01Z"));
+        assert_eq!(account.remaining(), 70.0);
+        account.advance(at("2026-03-30T01:30:01Z"));
+        assert_eq!(account.remaining(), 100.0);
+        assert_eq!(
+            account
+                .snapshot("alpha", at("2026-03-30T01:30:01Z"))
+                .resets_at,
+            None
+        );
+    }
+
+    async fn seed(pool: &SqlitePool, id: &str, timestamp: &str, cost: f64) {
+        sqlx::query(
+            "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+             cost_sats, latency_ms, success) VALUES (?, ?, 'gpt-4o', 'alpha', 0, ?, 100, 1)",
+        )
+        .bind(id)
+        .bind(timestamp)
+        .bind(cost)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn restore_rebuilds_the_current_period() {
+        let pool = crate::storage::memory::init_pool().await.unwrap();
+        ledger::restore(&pool, "alpha", 100.0, "2026-03-01T00:00:00Z")
+            .await
+            .unwrap();
+        seed(&pool, "old", "2026-03-07T10:00:00Z", 90.0).await;
+        seed(&pool, "yesterday", "2026-03-08T23:59:59Z", 75.0).await;
+        seed(&pool, "today-1", "2026-03-09T00:00:00Z", 10.0).await;
+        seed(&pool, "today-2", "2026-03-09T05:59:00Z", 5.0).await;
+        ledger::insert_topup(&pool, "alpha", 8.0, None, "2026-03-09T01:00:00Z")
+            .await
+            .unwrap();
+
+        let now = at("2026-03-09T06:00:00Z");
+        let mut daily = provider("alpha", Some(100.0));
+        daily.balance_reset = Some(reset(BalanceSchedule::Daily, true, None));
+        let ledger = ProviderLedger::new(&[daily]);
+        ledger.restore_at(&pool, now).await.unwrap();
+        let snapshot = ledger.lock()["alpha"].snapshot("alpha", now);
+        assert_eq!(snapshot.opened_at, "2026-03-01T00:00:00Z");
+        assert_eq!(snapshot.spent_sats, 15.0);
+        assert_eq!(snapshot.topped_up_sats, 8.0);
+        assert_eq!(snapshot.rollover_sats, Some(25.0));
+        assert_eq!(snapshot.remaining_sats, 118.0);
+
+        let mut rolling = provider("alpha", Some(100.0));
+        rolling.balance_reset = Some(BalanceResetConfig {
+            window_hours: Some(12),
+            ..reset(BalanceSchedule::Rolling, false, None)
+        });
+        let ledger = ProviderLedger::new(&[rolling]);
+        ledger.restore_at(&pool, now).await.unwrap();
+        let snapshot = ledger.lock()["alpha"].snapshot("alpha", now);
+        assert_eq!(snapshot.spent_sats, 90.0);
+        assert_eq!(snapshot.topped_up_sats, 8.0);
+        assert_eq!(snapshot.remaining_sats, 18.0);
+        assert_eq!(
+            snapshot.period_start.as_deref(),
+            Some("2026-03-08T18:00:00Z")
+        );
+    }
+}
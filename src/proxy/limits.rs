@@ -0,0 +1,234 @@
+//! Edge limits (`[server.limits]`) and their rejection counters.
+//!
+//! Body size, request timeout, and concurrency limits wrap every route,
+//! outside auth and rate limiting, so oversized or excess requests are
+//! turned away before any routing work. Request bodies are counted after
+//! decompression, so a small gzip body can't expand past the limit.
+//!
+//! Rejections are counted per limit and served by `GET /v1/stats/limits`.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::server::AppState;
+use crate::config::LimitsConfig;
+
+/// Process-lifetime edge limit counters.
+#[derive(Debug, Default)]
+pub struct LimitStats {
+    in_flight: AtomicU64,
+    body_too_large: AtomicU64,
+    timed_out: AtomicU64,
+    over_capacity: AtomicU64,
+}
+
+/// Requests rejected by each limit.
+#[derive(Debug, Serialize)]
+pub struct RejectedCounts {
+    pub body_too_large: u64,
+    pub timed_out: u64,
+    pub over_capacity: u64,
+}
+
+/// Response for GET /v1/stats/limits.
+#[derive(Debug, Serialize)]
+pub struct LimitsSnapshot {
+    pub max_body_bytes: Option<u64>,
+    pub request_timeout_secs: Option<u64>,
+    pub max_concurrent_requests: Option<usize>,
+    /// Requests currently holding a concurrency slot (0 without
+    /// `max_concurrent_requests`).
+    pub in_flight: u64,
+    pub rejected: RejectedCounts,
+}
+
+impl LimitStats {
+    /// Current counter values alongside the configured limits.
+    pub fn snapshot(&self, config: &LimitsConfig) -> LimitsSnapshot {
+        LimitsSnapshot {
+            max_body_bytes: config.max_body_bytes,
+            request_timeout_secs: config.request_timeout_secs,
+            max_concurrent_requests: config.max_concurrent_requests,
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            rejected: RejectedCounts {
+                body_too_large: self.body_too_large.load(Ordering::Relaxed),
+                timed_out: self.timed_out.load(Ordering::Relaxed),
+                over_capacity: self.over_capacity.load(Ordering::Relaxed),
+            },
+        }
+    }
+}
+
+/// OpenAI-style error response for a rejected request.
+fn reject(status: StatusCode, code: &str, message: String) -> Response {
+    let body = serde_json::json!({
+        "error": {
+            "message": message,
+            "type": "arbstr_error",
+            "code": code
+        }
+    });
+    (status, Json(body)).into_response()
+}
+
+fn body_too_large(stats: &LimitStats, max: u64) -> Response {
+    stats.body_too_large.fetch_add(1, Ordering::Relaxed);
+    reject(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "request_too_large",
+        format!("Request body exceeds {} bytes", max),
+    )
+}
+
+/// Reject bodies over `max` bytes: up front from `Content-Length`, or as
+/// they are read when the length isn't declared.
+async fn limit_body(max: u64, stats: Arc<LimitStats>, request: Request, next: Next) -> Response {
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    match declared {
+        Some(len) if len > max => return body_too_large(&stats, max),
+        // hyper enforces a declared length
+        Some(_) => return next.run(request).await,
+        None => {}
+    }
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let (parts, body) = request.into_parts();
+    let body = {
+        let exceeded = exceeded.clone();
+        let mut seen = 0u64;
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let bytes = chunk?;
+            seen += bytes.len() as u64;
+            if seen > max {
+                exceeded.store(true, Ordering::Relaxed);
+                return Err(axum::Error::new(std::io::Error::other(
+                    "request body too large",
+                )));
+            }
+            Ok(bytes)
+        }))
+    };
+    let response = next.run(Request::from_parts(parts, body)).await;
+    if exceeded.load(Ordering::Relaxed) {
+        return body_too_large(&stats, max);
+    }
+    response
+}
+
+/// Fail requests whose response headers take longer than `timeout`.
+async fn limit_time(
+    timeout: Duration,
+    stats: Arc<LimitStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            stats.timed_out.fetch_add(1, Ordering::Relaxed);
+            reject(
+                StatusCode::GATEWAY_TIMEOUT,
+                "request_timeout",
+                format!("Request timed out after {}s", timeout.as_secs()),
+            )
+        }
+    }
+}
+
+/// A concurrency slot, held until the response body is dropped.
+struct Slot {
+    stats: Arc<LimitStats>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Reject requests once every slot is taken, rather than queueing them.
+async fn limit_concurrency(
+    slots: Arc<Semaphore>,
+    stats: Arc<LimitStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Ok(permit) = slots.try_acquire_owned() else {
+        stats.over_capacity.fetch_add(1, Ordering::Relaxed);
+        let mut response = reject(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server_overloaded",
+            "Too many concurrent requests".to_string(),
+        );
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, header::HeaderValue::from_static("1"));
+        return response;
+    };
+    stats.in_flight.fetch_add(1, Ordering::Relaxed);
+    let slot = Slot {
+        stats,
+        _permit: permit,
+    };
+
+    let response = next.run(request).await;
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &slot;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// Add the configured edge limits to `router`, recording rejections into
+/// `stats`. The concurrency limit is outermost so rejected requests cost
+/// nothing; the timeout covers reading the body.
+pub(crate) fn layer(mut router: Router, config: &LimitsConfig, stats: Arc<LimitStats>) -> Router {
+    if let Some(max) = config.max_body_bytes {
+        let stats = stats.clone();
+        router = router
+            .layer(DefaultBodyLimit::max(
+                usize::try_from(max).unwrap_or(usize::MAX),
+            ))
+            .layer(middleware::from_fn(move |req, next| {
+                limit_body(max, stats.clone(), req, next)
+            }));
+    }
+    if let Some(secs) = config.request_timeout_secs {
+        let stats = stats.clone();
+        let timeout = Duration::from_secs(secs);
+        router = router.layer(middleware::from_fn(move |req, next| {
+            limit_time(timeout, stats.clone(), req, next)
+        }));
+    }
+    if let Some(max) = config.max_concurrent_requests {
+        let slots = Arc::new(Semaphore::new(max));
+        router = router.layer(middleware::from_fn(move |req, next| {
+            limit_concurrency(slots.clone(), stats.clone(), req, next)
+        }));
+    }
+    router
+}
+
+/// Handle GET /v1/stats/limits.
+pub async fn limits_stats_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.limits.snapshot(&state.config.server.limits))
+}
@@ -0,0 +1,208 @@
+//! Serving one router on several listen addresses.
+//!
+//! TCP addresses go through `axum::serve`. Unix domain sockets get a small
+//! accept loop on hyper-util, since `axum::serve` only takes TCP listeners.
+//! Every listener shares the same router (and so the same `AppState`) and
+//! stops accepting when the shutdown signal fires, letting in-flight
+//! requests finish.
+
+use std::io;
+
+use axum::Router;
+use tokio::sync::watch;
+
+use crate::config::ListenAddr;
+
+/// A bound listen address.
+pub enum Listener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    /// Bind `addr`. A stale socket file left by a previous run is removed
+    /// first; anything else at a socket path, including a socket another
+    /// process is still listening on, is an error.
+    pub async fn bind(addr: &ListenAddr) -> io::Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => Ok(Self::Tcp(tokio::net::TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                remove_stale_socket(path)?;
+                Ok(Self::Unix(
+                    tokio::net::UnixListener::bind(path)?,
+                    path.clone(),
+                ))
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    /// The bound address, for logging (resolves port 0).
+    pub fn local_addr(&self) -> String {
+        match self {
+            Self::Tcp(listener) => listener
+                .local_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_default(),
+            #[cfg(unix)]
+            Self::Unix(_, path) => format!("unix:{}", path.display()),
+        }
+    }
+
+    /// Serve `app` until `shutdown` turns true.
+    pub async fn serve(self, app: Router, shutdown: watch::Receiver<bool>) -> io::Result<()> {
+        match self {
+            Self::Tcp(listener) => {
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(wait_for(shutdown))
+                    .await
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, path) => {
+                let result = serve_unix(listener, app, shutdown).await;
+                let _ = std::fs::remove_file(&path);
+                result
+            }
+        }
+    }
+}
+
+/// Remove the socket file at `path` if nothing accepts connections on it.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", path.display()),
+        ));
+    }
+    std::fs::remove_file(path)?;
+    tracing::debug!(path = %path.display(), "Removed stale socket");
+    Ok(())
+}
+
+/// Resolve once `shutdown` is true (or its sender is gone).
+async fn wait_for(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+#[cfg(unix)]
+async fn serve_unix(
+    listener: tokio::net::UnixListener,
+    app: Router,
+    shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::server::graceful::GracefulShutdown;
+    use hyper_util::service::TowerToHyperService;
+
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    let stop = wait_for(shutdown);
+    tokio::pin!(stop);
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to accept Unix socket connection");
+                    continue;
+                }
+            },
+            _ = &mut stop => break,
+        };
+        let service = TowerToHyperService::new(app.clone());
+        let conn = builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+            .into_owned();
+        let conn = graceful.watch(conn);
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                tracing::debug!(error = %e, "Unix socket connection ended with error");
+            }
+        });
+    }
+
+    graceful.shutdown().await;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn serves_http_on_unix_socket_until_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("arbstr.sock");
+        // A leftover socket from a previous run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = Listener::bind(&ListenAddr::Unix(path.clone()))
+            .await
+            .unwrap();
+        let app = Router::new().route("/ping", axum::routing::get(|| async { "pong" }));
+        let (tx, rx) = watch::channel(false);
+        let server = tokio::spawn(listener.serve(app, rx));
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /ping HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("pong"));
+
+        tx.send(true).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn refuses_files_and_live_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, b"keep me").unwrap();
+        let err = Listener::bind(&ListenAddr::Unix(file.clone()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&file).unwrap(), b"keep me");
+
+        let live = dir.path().join("live.sock");
+        let _other = std::os::unix::net::UnixListener::bind(&live).unwrap();
+        let err = Listener::bind(&ListenAddr::Unix(live.clone()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert!(live.exists());
+    }
+}
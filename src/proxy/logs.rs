@@ -1,12 +1,15 @@
-//! Request log listing endpoint types and handler.
+//! Request log listing and detail endpoint types and handlers.
+
+use std::collections::BTreeMap;
 
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
 
+use super::currency::RateSnapshot;
 use super::server::AppState;
 use super::stats::resolve_time_range;
 use crate::error::Error;
@@ -26,6 +29,14 @@ pub struct LogsQuery {
     pub per_page: Option<u32>,
     pub sort: Option<String>,
     pub order: Option<String>,
+    /// Tag filter in `key=value` form.
+    pub tag: Option<String>,
+    /// W3C trace ID filter (32 lowercase hex chars).
+    pub trace_id: Option<String>,
+    /// Case-insensitive substring of the error message.
+    pub error_contains: Option<String>,
+    /// Error status filter: an exact code (`502`) or a class (`5xx`).
+    pub status: Option<String>,
 }
 
 /// Paginated response for GET /v1/requests.
@@ -38,6 +49,9 @@ pub struct LogsResponse {
     pub total_pages: u32,
     pub since: String,
     pub until: String,
+    /// `memory` when logs come from the non-persistent in-memory backend.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<&'static str>,
 }
 
 /// A single request log entry with nested sections.
@@ -50,11 +64,28 @@ pub struct LogEntry {
     pub provider: Option<String>,
     pub streaming: bool,
     pub success: bool,
+    /// Upstream finish_reason, recorded when a stream completes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// W3C trace ID for correlating with distributed traces.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Client `x-request-id` (defaults to arbstr's correlation ID).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Config version active at dispatch (`GET /admin/config/versions`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_version: Option<i64>,
+    /// Client conversation (`X-Arbstr-Conversation-Id`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
     pub tokens: TokensSection,
     pub cost: CostSection,
     pub timing: TimingSection,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ErrorSection>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
 }
 
 /// Token counts for a request.
@@ -68,6 +99,18 @@ pub struct TokensSection {
 #[derive(Debug, Serialize)]
 pub struct CostSection {
     pub sats: Option<f64>,
+    /// Cost converted at the rate stored with the request (`[currency]`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatCost>,
+}
+
+/// A request's cost in fiat.
+#[derive(Debug, Serialize)]
+pub struct FiatCost {
+    pub currency: String,
+    pub amount: f64,
+    /// Price of one bitcoin in `currency` when the request was logged.
+    pub btc_price: f64,
 }
 
 /// Timing information for a request.
@@ -76,6 +119,9 @@ pub struct TimingSection {
     pub latency_ms: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream_duration_ms: Option<i64>,
+    /// Streams only: output tokens per second while generating.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_per_second: Option<f64>,
 }
 
 /// Error details for a failed request.
@@ -85,6 +131,125 @@ pub struct ErrorSection {
     pub message: Option<String>,
 }
 
+/// Response for GET /v1/requests/{id}: the full log record, the failed
+/// upstream attempts behind it, and the circuit states it was routed under.
+///
+/// Request and response bodies are not stored, so they are not included.
+#[derive(Debug, Serialize)]
+pub struct RequestDetail {
+    pub correlation_id: String,
+    #[serde(flatten)]
+    pub entry: LogEntry,
+    /// Policy the request matched, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity_score: Option<f64>,
+    /// Failure classification for failed requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    /// Failed attempts in order (retries and fallbacks); empty when the
+    /// first attempt succeeded.
+    pub attempts: Vec<AttemptEntry>,
+    /// Each provider's circuit breaker state when the request was routed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_snapshot: Option<serde_json::Value>,
+}
+
+/// A failed upstream attempt.
+#[derive(Debug, Serialize)]
+pub struct AttemptEntry {
+    pub attempt: i64,
+    pub provider: String,
+    pub status: i64,
+    pub error_type: String,
+    /// Backoff waited before the attempt.
+    pub backoff_ms: i64,
+    /// When the attempt was sent.
+    pub timestamp: String,
+}
+
+impl LogEntry {
+    fn from_row(row: storage::LogRow, tags: BTreeMap<String, String>) -> Self {
+        let error = if row.error_status.is_some() || row.error_message.is_some() {
+            Some(ErrorSection {
+                status: row.error_status,
+                message: row.error_message,
+            })
+        } else {
+            None
+        };
+
+        LogEntry {
+            id: row.id,
+            timestamp: row.timestamp,
+            model: row.model,
+            provider: row.provider,
+            streaming: row.streaming,
+            success: row.success,
+            finish_reason: row.finish_reason,
+            trace_id: row.trace_id,
+            request_id: row.client_request_id,
+            config_version: row.config_version,
+            conversation_id: row.conversation_id,
+            tokens: TokensSection {
+                input: row.input_tokens,
+                output: row.output_tokens,
+            },
+            cost: CostSection {
+                sats: row.cost_sats,
+                fiat: match (row.cost_sats, row.fiat_currency, row.fiat_rate) {
+                    (Some(sats), Some(currency), Some(btc_price)) => {
+                        let rate = RateSnapshot {
+                            currency,
+                            btc_price,
+                        };
+                        Some(FiatCost {
+                            amount: rate.convert(sats),
+                            currency: rate.currency,
+                            btc_price,
+                        })
+                    }
+                    _ => None,
+                },
+            },
+            timing: TimingSection {
+                latency_ms: row.latency_ms,
+                stream_duration_ms: row.stream_duration_ms,
+                tokens_per_second: row.tokens_per_second,
+            },
+            error,
+            tags,
+        }
+    }
+}
+
+/// Parse a `status` filter into an inclusive range of error status codes.
+///
+/// Accepts an exact code (`502`) or a class (`4xx`, `5xx`).
+fn parse_status_filter(status: &str) -> Result<(i32, i32), Error> {
+    let invalid = || {
+        Error::BadRequest(format!(
+            "Invalid status '{}'. Use a status code (e.g. 502) or a class (e.g. 5xx)",
+            status
+        ))
+    };
+    let lower = status.to_ascii_lowercase();
+    if let Some(class) = lower.strip_suffix("xx") {
+        let class: i32 = class.parse().map_err(|_| invalid())?;
+        if !(1..=5).contains(&class) {
+            return Err(invalid());
+        }
+        return Ok((class * 100, class * 100 + 99));
+    }
+    match lower.parse::<i32>() {
+        Ok(code) if (100..=599).contains(&code) => Ok((code, code)),
+        _ => Err(invalid()),
+    }
+}
+
 /// Validate the sort field against the allowed whitelist.
 ///
 /// Returns the validated column name as a &'static str for safe SQL interpolation.
@@ -144,6 +309,10 @@ pub async fn logs_handler(
         per_page = ?params.per_page,
         sort = ?params.sort,
         order = ?params.order,
+        tag = ?params.tag,
+        trace_id = ?params.trace_id,
+        error_contains = ?params.error_contains,
+        status = ?params.status,
         "Logs query"
     );
 
@@ -157,6 +326,21 @@ pub async fn logs_handler(
         super::validation::validate_provider_filter(&state.config, pool, provider_filter).await?;
     }
 
+    // Validate tag filter (key=value)
+    let tag_filter = params
+        .tag
+        .as_deref()
+        .map(super::tags::parse_tag_filter)
+        .transpose()?;
+    let tag = tag_filter.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+
+    // Validate error status filter (exact code or class)
+    let status = params
+        .status
+        .as_deref()
+        .map(parse_status_filter)
+        .transpose()?;
+
     // Validate sort field (default: timestamp)
     let sort_column = match &params.sort {
         Some(field) => validate_sort_field(field)?,
@@ -182,6 +366,10 @@ pub async fn logs_handler(
         params.provider.as_deref(),
         params.success,
         params.streaming,
+        tag,
+        params.trace_id.as_deref(),
+        params.error_contains.as_deref(),
+        status,
     )
     .await?;
 
@@ -202,6 +390,10 @@ pub async fn logs_handler(
         params.provider.as_deref(),
         params.success,
         params.streaming,
+        tag,
+        params.trace_id.as_deref(),
+        params.error_contains.as_deref(),
+        status,
         sort_column,
         sort_direction,
         per_page,
@@ -209,39 +401,20 @@ pub async fn logs_handler(
     )
     .await?;
 
+    // Fetch tags for the page and index by row id
+    let ids: Vec<i64> = rows.iter().map(|r| r.id).collect();
+    let mut tags_by_id: std::collections::HashMap<i64, BTreeMap<String, String>> =
+        std::collections::HashMap::new();
+    for (id, key, value) in storage::logs::query_tags_for_ids(pool, &ids).await? {
+        tags_by_id.entry(id).or_default().insert(key, value);
+    }
+
     // Map LogRow -> LogEntry
     let data: Vec<LogEntry> = rows
         .into_iter()
         .map(|row| {
-            let error = if row.error_status.is_some() || row.error_message.is_some() {
-                Some(ErrorSection {
-                    status: row.error_status,
-                    message: row.error_message,
-                })
-            } else {
-                None
-            };
-
-            LogEntry {
-                id: row.id,
-                timestamp: row.timestamp,
-                model: row.model,
-                provider: row.provider,
-                streaming: row.streaming,
-                success: row.success,
-                tokens: TokensSection {
-                    input: row.input_tokens,
-                    output: row.output_tokens,
-                },
-                cost: CostSection {
-                    sats: row.cost_sats,
-                },
-                timing: TimingSection {
-                    latency_ms: row.latency_ms,
-                    stream_duration_ms: row.stream_duration_ms,
-                },
-                error,
-            }
+            let tags = tags_by_id.remove(&row.id).unwrap_or_default();
+            LogEntry::from_row(row, tags)
         })
         .collect();
 
@@ -253,5 +426,57 @@ pub async fn logs_handler(
         total_pages,
         since: since_dt.to_rfc3339(),
         until: until_dt.to_rfc3339(),
+        storage: state.config.ephemeral_storage().then_some("memory"),
+    }))
+}
+
+/// Handle GET /v1/requests/{id} -- the full record of a single request.
+///
+/// `id` is a correlation ID (the `x-arbstr-request-id` response header) or
+/// a row id from GET /v1/requests.
+pub async fn request_detail_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let mut row = storage::logs::query_detail(pool, &id).await?;
+    if let (None, Ok(row_id)) = (&row, id.parse::<i64>()) {
+        row = storage::logs::query_detail_by_id(pool, row_id).await?;
+    }
+    let row = row.ok_or_else(|| Error::NotFound(format!("Request '{}' not found", id)))?;
+
+    let tags = storage::logs::query_tags_for_ids(pool, &[row.log.id])
+        .await?
+        .into_iter()
+        .map(|(_, key, value)| (key, value))
+        .collect();
+    let attempts = storage::logs::query_attempts(pool, &row.correlation_id)
+        .await?
+        .into_iter()
+        .map(|a| AttemptEntry {
+            attempt: a.attempt,
+            provider: a.provider,
+            status: a.status,
+            error_type: a.error_type,
+            backoff_ms: a.backoff_ms,
+            timestamp: a.timestamp,
+        })
+        .collect();
+
+    Ok(Json(RequestDetail {
+        correlation_id: row.correlation_id,
+        entry: LogEntry::from_row(row.log, tags),
+        policy: row.policy,
+        tier: row.tier,
+        complexity_score: row.complexity_score,
+        error_type: row.error_type,
+        attempts,
+        circuit_snapshot: row
+            .circuit_snapshot
+            .and_then(|s| serde_json::from_str(&s).ok()),
     }))
 }
@@ -0,0 +1,254 @@
+//! Provider maintenance mode.
+//!
+//! A provider with `enabled = false`, or with `maintenance_until` in the
+//! future, stays in the config but is left out of candidate selection. Its
+//! circuit breaker is untouched, so it comes back with the state it had.
+//! Both flags start from the provider's config and can be replaced at
+//! runtime through `PUT /admin/providers/{name}/maintenance`; a maintenance
+//! window ends on its own once `maintenance_until` passes.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use super::config_diff::{ConfigDiff, DiffRecord, EntryDiff, FieldChange};
+use super::server::AppState;
+use crate::config::ProviderConfig;
+use crate::error::Error;
+
+/// A provider's maintenance flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct MaintenanceSetting {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub maintenance_until: Option<DateTime<Utc>>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for MaintenanceSetting {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            maintenance_until: None,
+        }
+    }
+}
+
+/// Whether a provider is routed to, for `/providers` and the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    /// `active`, `disabled`, or `maintenance`.
+    pub status: &'static str,
+    pub enabled: bool,
+    /// End of the current maintenance window, if one is in progress.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_until: Option<String>,
+}
+
+/// Maintenance flags of providers that are not simply enabled.
+#[derive(Debug, Default)]
+pub struct ProviderMaintenance {
+    settings: DashMap<String, MaintenanceSetting>,
+}
+
+impl ProviderMaintenance {
+    /// Start from the flags in each provider's config.
+    pub fn new(providers: &[ProviderConfig]) -> Self {
+        let settings = providers
+            .iter()
+            .map(|p| {
+                let setting = MaintenanceSetting {
+                    enabled: p.enabled,
+                    maintenance_until: p.maintenance_until,
+                };
+                (p.name.clone(), setting)
+            })
+            .filter(|(_, setting)| *setting != MaintenanceSetting::default())
+            .collect();
+        Self { settings }
+    }
+
+    /// Why `provider` is out of rotation at `now`, or None when it routes.
+    pub fn unavailable_at(&self, provider: &str, now: DateTime<Utc>) -> Option<String> {
+        let setting = self.settings.get(provider)?;
+        if !setting.enabled {
+            return Some("disabled".to_string());
+        }
+        setting
+            .maintenance_until
+            .filter(|until| *until > now)
+            .map(|until| format!("maintenance until {}", rfc3339(until)))
+    }
+
+    /// Whether `provider` is out of rotation right now.
+    pub fn is_unavailable(&self, provider: &str) -> bool {
+        self.unavailable_at(provider, Utc::now()).is_some()
+    }
+
+    /// Current status of `provider`.
+    pub fn status(&self, provider: &str) -> MaintenanceStatus {
+        let setting = self.setting(provider);
+        let until = setting
+            .maintenance_until
+            .filter(|until| *until > Utc::now());
+        MaintenanceStatus {
+            status: match (setting.enabled, until) {
+                (false, _) => "disabled",
+                (true, Some(_)) => "maintenance",
+                (true, None) => "active",
+            },
+            enabled: setting.enabled,
+            maintenance_until: until.map(rfc3339),
+        }
+    }
+
+    /// Current flags of `provider`.
+    pub fn setting(&self, provider: &str) -> MaintenanceSetting {
+        self.settings.get(provider).map(|s| *s).unwrap_or_default()
+    }
+
+    /// Replace the flags of `provider`.
+    pub fn set(&self, provider: &str, setting: MaintenanceSetting) {
+        if setting == MaintenanceSetting::default() {
+            self.settings.remove(provider);
+        } else {
+            self.settings.insert(provider.to_string(), setting);
+        }
+    }
+}
+
+/// The config fields a maintenance update changes.
+fn field_changes(old: MaintenanceSetting, new: MaintenanceSetting) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    if old.enabled != new.enabled {
+        changes.push(FieldChange {
+            field: "enabled",
+            from: format!("{:?}", old.enabled),
+            to: format!("{:?}", new.enabled),
+        });
+    }
+    if old.maintenance_until != new.maintenance_until {
+        changes.push(FieldChange {
+            field: "maintenance_until",
+            from: format!("{:?}", old.maintenance_until),
+            to: format!("{:?}", new.maintenance_until),
+        });
+    }
+    changes
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Handle PUT /admin/providers/{name}/maintenance -- replace a provider's
+/// `enabled` and `maintenance_until` flags (omitted fields reset to
+/// enabled, no window).
+pub async fn set_maintenance_handler(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(setting): Json<MaintenanceSetting>,
+) -> Result<impl IntoResponse, Error> {
+    if !state.router.providers().iter().any(|p| p.name == provider) {
+        return Err(Error::NotFound(format!(
+            "Provider '{}' not found",
+            provider
+        )));
+    }
+    let previous = state.maintenance.setting(&provider);
+    state.maintenance.set(&provider, setting);
+    let changes = field_changes(previous, setting);
+    if !changes.is_empty() {
+        let diff = ConfigDiff {
+            providers_changed: vec![EntryDiff {
+                name: provider.clone(),
+                changes,
+            }],
+            ..Default::default()
+        };
+        state
+            .config_versions
+            .record_diff(DiffRecord::new("admin", true, &headers, diff));
+    }
+    let status = state.maintenance.status(&provider);
+    tracing::info!(
+        provider = %provider,
+        status = status.status,
+        maintenance_until = ?status.maintenance_until,
+        "Provider maintenance flags updated"
+    );
+    let change = format!(
+        "provider {}: enabled={}, maintenance_until={}",
+        provider,
+        setting.enabled,
+        setting
+            .maintenance_until
+            .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_else(|| "none".to_string())
+    );
+    if let Err(e) = state
+        .config_versions
+        .record_change(state.db.as_ref(), &state.config, &headers, &change)
+        .await
+    {
+        tracing::warn!(error = %e, "Failed to record config version for maintenance change");
+    }
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_end_on_their_own() {
+        let now = Utc::now();
+        let maintenance = ProviderMaintenance::default();
+        assert_eq!(maintenance.unavailable_at("alpha", now), None);
+        assert_eq!(maintenance.status("alpha").status, "active");
+
+        let until = now + chrono::Duration::hours(1);
+        maintenance.set(
+            "alpha",
+            MaintenanceSetting {
+                enabled: true,
+                maintenance_until: Some(until),
+            },
+        );
+        assert!(maintenance
+            .unavailable_at("alpha", now)
+            .unwrap()
+            .starts_with("maintenance until"));
+        assert_eq!(maintenance.status("alpha").status, "maintenance");
+        assert_eq!(
+            maintenance.unavailable_at("alpha", until + chrono::Duration::seconds(1)),
+            None
+        );
+
+        maintenance.set(
+            "alpha",
+            MaintenanceSetting {
+                enabled: false,
+                maintenance_until: None,
+            },
+        );
+        assert_eq!(
+            maintenance.unavailable_at("alpha", now).as_deref(),
+            Some("disabled")
+        );
+
+        maintenance.set("alpha", MaintenanceSetting::default());
+        assert!(maintenance.settings.is_empty());
+    }
+}
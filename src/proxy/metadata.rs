@@ -0,0 +1,200 @@
+//! Placement of routing metadata on chat completion responses
+//! (`server.metadata_mode`).
+//!
+//! Handlers always set the `x-arbstr-*` headers and record the same values
+//! in a [`ResponseMetadata`] extension. [`apply`] then runs once per
+//! response: in `body` mode it adds a single `arbstr` object to successful
+//! JSON bodies, and in `none` mode it strips every metadata header except
+//! `x-arbstr-request-id`.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderName},
+    response::Response,
+};
+use serde::Serialize;
+
+use super::handlers::{ARBSTR_REQUEST_ID_HEADER, ARBSTR_STREAMING_HEADER};
+use crate::config::MetadataMode;
+
+/// Metadata for the `arbstr` body object, recorded alongside the headers.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResponseMetadata {
+    pub provider: Option<String>,
+    pub cost_sats: Option<f64>,
+    pub latency_ms: Option<i64>,
+    /// `x-arbstr-retries` value (`"2/alpha, 1/beta"`).
+    pub retries: Option<String>,
+}
+
+impl ResponseMetadata {
+    /// The metadata recorded on `response`, created if absent.
+    pub(crate) fn of(response: &mut Response) -> &mut Self {
+        let extensions = response.extensions_mut();
+        if extensions.get::<Self>().is_none() {
+            extensions.insert(Self::default());
+        }
+        extensions.get_mut::<Self>().expect("inserted above")
+    }
+}
+
+/// The `arbstr` object added to JSON bodies.
+#[derive(Debug, Serialize)]
+struct BodyMetadata<'a> {
+    provider: Option<&'a str>,
+    cost_sats: Option<f64>,
+    latency_ms: Option<i64>,
+    /// Failed attempts before the response, per provider.
+    retries: Vec<RetryCount<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct RetryCount<'a> {
+    provider: &'a str,
+    failures: u32,
+}
+
+/// Parse an `x-arbstr-retries` value into per-provider counts.
+fn retry_counts(header: &str) -> Vec<RetryCount<'_>> {
+    header
+        .split(", ")
+        .filter_map(|entry| {
+            let (count, provider) = entry.split_once('/')?;
+            Some(RetryCount {
+                provider,
+                failures: count.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Whether `response` is a successful, non-streaming JSON body.
+fn is_json_completion(response: &Response) -> bool {
+    response.status().is_success()
+        && !response.headers().contains_key(ARBSTR_STREAMING_HEADER)
+        && response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// Apply `mode` to a chat completion response.
+pub(crate) async fn apply(mode: MetadataMode, response: Response) -> Response {
+    match mode {
+        MetadataMode::Body if is_json_completion(&response) => add_body_metadata(response).await,
+        MetadataMode::Body | MetadataMode::Headers => response,
+        MetadataMode::None => strip_headers(response),
+    }
+}
+
+async fn add_body_metadata(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let Some(metadata) = parts.extensions.get::<ResponseMetadata>() else {
+        return Response::from_parts(parts, body);
+    };
+    // Completion bodies are already buffered in memory
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to read response body for metadata");
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let mut json = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(json @ serde_json::Value::Object(_)) => json,
+        _ => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    let object = BodyMetadata {
+        provider: metadata.provider.as_deref(),
+        cost_sats: metadata.cost_sats,
+        latency_ms: metadata.latency_ms,
+        retries: metadata
+            .retries
+            .as_deref()
+            .map(retry_counts)
+            .unwrap_or_default(),
+    };
+    json["arbstr"] = serde_json::to_value(&object).expect("metadata serializes");
+    match serde_json::to_vec(&json) {
+        Ok(body) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(body))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+fn strip_headers(mut response: Response) -> Response {
+    let metadata: Vec<HeaderName> = response
+        .headers()
+        .keys()
+        .filter(|name| {
+            name.as_str().starts_with("x-arbstr-") && name.as_str() != ARBSTR_REQUEST_ID_HEADER
+        })
+        .cloned()
+        .collect();
+    for name in metadata {
+        response.headers_mut().remove(name);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(metadata: ResponseMetadata) -> Response {
+        let mut response = Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(ARBSTR_REQUEST_ID_HEADER, "req-1")
+            .header("x-arbstr-provider", "beta")
+            .body(Body::from(r#"{"id":"chatcmpl-1","choices":[]}"#))
+            .unwrap();
+        *ResponseMetadata::of(&mut response) = metadata;
+        response
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn body_mode_adds_a_single_arbstr_object() {
+        let response = completion(ResponseMetadata {
+            provider: Some("beta".to_string()),
+            cost_sats: Some(0.125),
+            latency_ms: Some(42),
+            retries: Some("2/alpha, 1/gamma".to_string()),
+        });
+        let json = body_json(apply(MetadataMode::Body, response).await).await;
+        assert_eq!(json["id"], "chatcmpl-1");
+        assert_eq!(
+            json["arbstr"],
+            serde_json::json!({
+                "provider": "beta",
+                "cost_sats": 0.125,
+                "latency_ms": 42,
+                "retries": [
+                    {"provider": "alpha", "failures": 2},
+                    {"provider": "gamma", "failures": 1}
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn headers_and_none_modes_leave_the_body_alone() {
+        let response = apply(MetadataMode::Headers, completion(Default::default())).await;
+        assert!(response.headers().contains_key("x-arbstr-provider"));
+        assert!(body_json(response).await.get("arbstr").is_none());
+
+        let response = apply(MetadataMode::None, completion(Default::default())).await;
+        assert!(response.headers().contains_key(ARBSTR_REQUEST_ID_HEADER));
+        assert!(!response.headers().contains_key("x-arbstr-provider"));
+        assert!(body_json(response).await.get("arbstr").is_none());
+    }
+}
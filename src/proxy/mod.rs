@@ -3,24 +3,93 @@
 //! This module provides the OpenAI-compatible HTTP API that accepts
 //! requests and forwards them to selected providers.
 
+pub mod about;
+pub(crate) mod accumulate;
+pub mod admin;
+pub mod anomaly;
+pub mod arbitrage;
+pub(crate) mod body_stream;
+pub mod canary;
+pub mod chaos;
+pub mod compare;
+pub mod config_diff;
+pub mod config_versions;
+pub mod content_filter;
+pub mod conversation;
+pub mod correlation;
+pub mod dataset;
+pub mod debug_trace;
 pub mod discovery;
+pub mod dns;
+pub mod echo;
+pub mod evaluation;
+pub mod explain;
+pub mod forecast;
 mod handlers;
+pub mod ledger;
+pub mod limits;
+pub(crate) mod listen;
 pub mod logs;
+pub mod maintenance;
+pub(crate) mod metadata;
+pub mod nostr;
+pub mod output_budget;
+pub(crate) mod passthrough;
+pub mod pool;
+pub mod privacy;
+pub mod prompt_compression;
+pub mod provider_test;
+pub mod quarantine;
+pub mod quota;
+pub mod race;
+pub mod receipts;
+pub mod recent;
+pub mod recording;
+pub mod reliability;
+pub mod reports;
+pub mod reputation;
 pub mod retry;
+pub mod retry_budget;
+pub mod route_cache;
+pub mod sampling;
+pub mod scorecard;
 mod server;
+pub mod slo;
 pub mod stats;
 pub mod stream;
+pub mod structured;
+pub mod tags;
+pub mod tie_break;
+pub mod trace;
+pub mod trim;
+pub mod truncation;
 pub mod types;
 pub(crate) mod validation;
 pub mod vault;
+pub mod vouchers;
+pub mod warmup;
+pub mod websocket;
 
-pub use server::{create_router, run_server, AppState, RequestId};
+pub use server::{
+    create_router, create_scoped_router, run_server, AppState, RequestId, RouterScope,
+};
 pub mod circuit_breaker;
+pub mod compression;
+pub(crate) mod cors;
+pub mod currency;
+pub use canary::CanaryTracker;
 pub use circuit_breaker::{
     CircuitBreakerRegistry, CircuitOpenError, CircuitSnapshot, CircuitState, PermitType, ProbeGuard,
 };
+pub use pool::ProviderClients;
+pub use quarantine::AuthQuarantine;
+pub use reputation::ReputationTracker;
+pub use retry_budget::RetryBudget;
 pub use stream::{wrap_sse_stream, StreamResult, StreamResultHandle, StreamUsage};
+pub use tie_break::TieBreakTracker;
+pub use trace::TraceContext;
 pub use types::{
     ensure_stream_options, ChatCompletionRequest, ChatCompletionResponse, Message, MessageContent,
     StreamOptions,
 };
+pub use warmup::WarmupTracker;
@@ -0,0 +1,478 @@
+//! Nostr announcements of the model catalogue (`[nostr]`).
+//!
+//! arbstr publishes a NIP-89 handler information event (kind 31990)
+//! whose content is a provider listing in the marketplace format: the
+//! proxy's public URL and, for each model it can route, the cheapest
+//! configured rates as Routstr `sats_pricing`. The event is replaceable,
+//! so each announcement supersedes the last one from the same key.
+//!
+//! The same events are read back for discovery
+//! (`arbstr providers discover --relay`): any announcement tagged
+//! `routstr` whose content parses as a provider listing is offered.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::server::AppState;
+use crate::config::{NostrConfig, ProviderConfig};
+use crate::marketplace::{self, ListedProvider};
+
+/// NIP-89 handler information event kind.
+pub const HANDLER_KIND: u64 = 31990;
+
+/// Topic (`t` tag) marking Routstr provider announcements.
+pub const TOPIC: &str = "routstr";
+
+/// `d` tag of arbstr's announcement; one replaceable event per key.
+const IDENTIFIER: &str = "arbstr";
+
+/// Timeout for connecting to a relay and for each reply.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most announcements requested from a relay during discovery.
+const DISCOVERY_LIMIT: usize = 500;
+
+/// A BIP-340 signing key, used for announcements and request receipts.
+pub struct Keys {
+    signing: SigningKey,
+}
+
+impl Keys {
+    /// Parse a secret key given as 64 hex characters.
+    pub fn from_hex(secret: &str) -> Result<Self, String> {
+        let bytes = decode_hex(secret)
+            .filter(|b| b.len() == 32)
+            .ok_or_else(|| "Nostr secret key must be 64 hex characters".to_string())?;
+        let signing = SigningKey::from_bytes(&bytes).map_err(|_| "Invalid Nostr secret key")?;
+        Ok(Self { signing })
+    }
+
+    /// A fresh random key.
+    pub fn random() -> Self {
+        Self {
+            signing: SigningKey::random(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// The x-only public key, hex encoded.
+    pub fn public_key(&self) -> String {
+        encode_hex(&self.signing.verifying_key().to_bytes())
+    }
+
+    /// BIP-340 signature of a 32-byte digest, hex encoded.
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> String {
+        let aux: [u8; 32] = rand::random();
+        let sig = self
+            .signing
+            .sign_prehash_with_aux_rand(digest, &aux)
+            .expect("BIP-340 signing with a valid key cannot fail");
+        encode_hex(&sig.to_bytes())
+    }
+}
+
+/// Whether `sig` (hex) is a valid BIP-340 signature of `digest` by the
+/// x-only public key `pubkey` (hex).
+pub fn verify_digest(pubkey: &str, digest: &[u8; 32], sig: &str) -> bool {
+    let key = decode_hex(pubkey).and_then(|b| VerifyingKey::from_bytes(&b).ok());
+    let sig = decode_hex(sig).and_then(|b| Signature::try_from(b.as_slice()).ok());
+    match (key, sig) {
+        (Some(key), Some(sig)) => key.verify_raw(digest, &sig).is_ok(),
+        _ => false,
+    }
+}
+
+/// A signed Nostr event (NIP-01).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub pubkey: String,
+    pub created_at: u64,
+    pub kind: u64,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: String,
+}
+
+impl Event {
+    /// Build and sign an event.
+    pub fn sign(
+        keys: &Keys,
+        created_at: u64,
+        kind: u64,
+        tags: Vec<Vec<String>>,
+        content: String,
+    ) -> Self {
+        let pubkey = keys.public_key();
+        let id = event_id(&pubkey, created_at, kind, &tags, &content);
+        Self {
+            id: encode_hex(&id),
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content,
+            sig: keys.sign_digest(&id),
+        }
+    }
+
+    /// Whether `id` matches the content and `sig` is valid for `pubkey`.
+    pub fn verify(&self) -> bool {
+        let id = event_id(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        );
+        encode_hex(&id) == self.id && verify_digest(&self.pubkey, &id, &self.sig)
+    }
+
+    /// First value of the tag named `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| t.first().map(String::as_str) == Some(name))
+            .and_then(|t| t.get(1))
+            .map(String::as_str)
+    }
+}
+
+/// NIP-01 event id: SHA-256 of `[0, pubkey, created_at, kind, tags, content]`.
+fn event_id(
+    pubkey: &str,
+    created_at: u64,
+    kind: u64,
+    tags: &[Vec<String>],
+    content: &str,
+) -> [u8; 32] {
+    let serialized = serde_json::json!([0, pubkey, created_at, kind, tags, content]).to_string();
+    Sha256::digest(serialized.as_bytes()).into()
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Announcement content: a provider listing (see `marketplace`).
+#[derive(Debug, Serialize)]
+struct Announcement<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    about: Option<&'a str>,
+    url: &'a str,
+    models: Vec<AnnouncedModel>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnnouncedModel {
+    id: String,
+    sats_pricing: SatsPricing,
+}
+
+/// Routstr pricing: sats per token, and per request.
+#[derive(Debug, Serialize)]
+struct SatsPricing {
+    prompt: f64,
+    completion: f64,
+    request: f64,
+}
+
+/// Each model's cheapest rates across `providers`, by model name.
+fn catalogue(providers: &[ProviderConfig]) -> Vec<AnnouncedModel> {
+    let mut cheapest: BTreeMap<&str, &ProviderConfig> = BTreeMap::new();
+    let cost = |p: &ProviderConfig| (p.input_rate + p.output_rate, p.base_fee);
+    for provider in providers {
+        for model in &provider.models {
+            let entry = cheapest.entry(model.as_str()).or_insert(provider);
+            if cost(provider) < cost(entry) {
+                *entry = provider;
+            }
+        }
+    }
+    cheapest
+        .into_iter()
+        .map(|(model, p)| AnnouncedModel {
+            id: model.to_string(),
+            sats_pricing: SatsPricing {
+                prompt: p.input_rate / 1000.0,
+                completion: p.output_rate / 1000.0,
+                request: p.base_fee,
+            },
+        })
+        .collect()
+}
+
+/// The signed announcement of `providers`' catalogue.
+pub fn announcement(
+    keys: &Keys,
+    config: &NostrConfig,
+    providers: &[ProviderConfig],
+    created_at: u64,
+) -> Event {
+    let content = Announcement {
+        name: &config.name,
+        about: config.about.as_deref(),
+        url: &config.public_url,
+        models: catalogue(providers),
+    };
+    let tags = vec![
+        vec!["d".to_string(), IDENTIFIER.to_string()],
+        vec!["t".to_string(), TOPIC.to_string()],
+        vec!["web".to_string(), config.public_url.clone()],
+    ];
+    Event::sign(
+        keys,
+        created_at,
+        HANDLER_KIND,
+        tags,
+        serde_json::to_string(&content).expect("announcement serializes"),
+    )
+}
+
+type Relay =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect(relay: &str) -> Result<Relay, String> {
+    match tokio::time::timeout(RELAY_TIMEOUT, tokio_tungstenite::connect_async(relay)).await {
+        Ok(Ok((socket, _))) => Ok(socket),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("connection timed out".to_string()),
+    }
+}
+
+/// Next JSON array message from the relay, skipping pings and other frames.
+async fn next_message(socket: &mut Relay) -> Result<Vec<serde_json::Value>, String> {
+    loop {
+        let message = tokio::time::timeout(RELAY_TIMEOUT, socket.next())
+            .await
+            .map_err(|_| "relay timed out".to_string())?
+            .ok_or_else(|| "relay closed the connection".to_string())?
+            .map_err(|e| e.to_string())?;
+        match message {
+            Message::Text(text) => {
+                if let Ok(serde_json::Value::Array(items)) = serde_json::from_str(&text) {
+                    return Ok(items);
+                }
+            }
+            Message::Close(_) => return Err("relay closed the connection".to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Publish `event` to `relay`, waiting for the relay's `OK`.
+pub async fn publish(relay: &str, event: &Event) -> Result<(), String> {
+    let mut socket = connect(relay).await?;
+    let request = serde_json::json!(["EVENT", event]).to_string();
+    socket
+        .send(Message::Text(request))
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = loop {
+        let message = next_message(&mut socket).await?;
+        if message.first().and_then(|v| v.as_str()) == Some("OK")
+            && message.get(1).and_then(|v| v.as_str()) == Some(event.id.as_str())
+        {
+            break match message.get(2).and_then(|v| v.as_bool()) {
+                Some(true) => Ok(()),
+                _ => Err(format!(
+                    "relay rejected the event: {}",
+                    message.get(3).and_then(|v| v.as_str()).unwrap_or("")
+                )),
+            };
+        }
+    };
+    let _ = socket.close(None).await;
+    result
+}
+
+/// Fetch provider announcements from `relay`. Events with a bad signature
+/// are dropped, and only the newest event per key is kept.
+pub async fn fetch_announcements(relay: &str) -> Result<Vec<Event>, String> {
+    let mut socket = connect(relay).await?;
+    let subscription = "arbstr-discover";
+    let filter = serde_json::json!({
+        "kinds": [HANDLER_KIND],
+        "#t": [TOPIC],
+        "limit": DISCOVERY_LIMIT,
+    });
+    let request = serde_json::json!(["REQ", subscription, filter]).to_string();
+    socket
+        .send(Message::Text(request))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut newest: HashMap<(String, String), Event> = HashMap::new();
+    loop {
+        let message = next_message(&mut socket).await?;
+        if message.get(1).and_then(|v| v.as_str()) != Some(subscription) {
+            continue;
+        }
+        match message.first().and_then(|v| v.as_str()) {
+            Some("EVENT") => {
+                let Some(event) = message
+                    .get(2)
+                    .and_then(|v| serde_json::from_value::<Event>(v.clone()).ok())
+                    .filter(|e| e.kind == HANDLER_KIND && e.verify())
+                else {
+                    continue;
+                };
+                let key = (
+                    event.pubkey.clone(),
+                    event.tag("d").unwrap_or_default().to_string(),
+                );
+                if newest
+                    .get(&key)
+                    .is_none_or(|e| e.created_at < event.created_at)
+                {
+                    newest.insert(key, event);
+                }
+            }
+            Some("EOSE") => break,
+            Some("CLOSED") => {
+                return Err(format!(
+                    "relay closed the subscription: {}",
+                    message.get(2).and_then(|v| v.as_str()).unwrap_or("")
+                ))
+            }
+            _ => {}
+        }
+    }
+    let close = serde_json::json!(["CLOSE", subscription]).to_string();
+    let _ = socket.send(Message::Text(close)).await;
+    let _ = socket.close(None).await;
+
+    let mut events: Vec<Event> = newest.into_values().collect();
+    events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(events)
+}
+
+/// Providers listed by `events`; content that is not a provider listing
+/// is skipped.
+pub fn listed_providers(events: &[Event]) -> Vec<ListedProvider> {
+    events
+        .iter()
+        .filter_map(|e| marketplace::parse_provider(&e.content).ok())
+        .collect()
+}
+
+/// Publish the announcement to every relay now and every `interval_secs`.
+pub async fn announce_loop(
+    state: AppState,
+    config: NostrConfig,
+    keys: Keys,
+    cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let event = announcement(
+                    &keys,
+                    &config,
+                    state.router.providers(),
+                    chrono::Utc::now().timestamp() as u64,
+                );
+                let results = futures::future::join_all(
+                    config.relays.iter().map(|relay| publish(relay, &event)),
+                )
+                .await;
+                let mut published = 0;
+                for (relay, result) in config.relays.iter().zip(results) {
+                    match result {
+                        Ok(()) => published += 1,
+                        Err(e) => tracing::warn!(relay = %relay, error = %e, "Nostr announcement failed"),
+                    }
+                }
+                tracing::info!(
+                    event_id = %event.id,
+                    relays = published,
+                    "Announced model catalogue on Nostr"
+                );
+            }
+            _ = super::vault::cancel_wait(&cancel) => {
+                tracing::info!("Nostr announcement task shutting down");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0000000000000000000000000000000000000000000000000000000000000003";
+
+    fn provider(name: &str, model: &str, input_rate: f64, output_rate: f64) -> ProviderConfig {
+        ProviderConfig {
+            url: format!("https://{}.example/v1", name),
+            models: vec![model.to_string()],
+            input_rate,
+            output_rate,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    #[test]
+    fn signs_bip340_events() {
+        let keys = Keys::from_hex(SECRET).unwrap();
+        // BIP-340 test vector 0: secret key 3
+        assert_eq!(
+            keys.public_key(),
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+        );
+
+        let mut event = Event::sign(&keys, 1_700_000_000, 1, vec![], "hello".to_string());
+        assert!(event.verify());
+        event.content = "tampered".to_string();
+        assert!(!event.verify());
+
+        assert!(Keys::from_hex("abcd").is_err());
+        assert!(Keys::from_hex(&"0".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn announcement_lists_cheapest_rates_and_reads_back() {
+        let keys = Keys::from_hex(SECRET).unwrap();
+        let config: NostrConfig = toml::from_str(&format!(
+            "secret_key = \"{}\"\nrelays = [\"wss://relay.example\"]\npublic_url = \"https://arbstr.example/v1\"",
+            SECRET
+        ))
+        .unwrap();
+        let providers = [
+            provider("dear", "gpt-4o", 10.0, 30.0),
+            provider("cheap", "gpt-4o", 5.0, 15.0),
+            provider("local", "llama-3", 0.0, 0.0),
+        ];
+
+        let event = announcement(&keys, &config, &providers, 1_700_000_000);
+        assert!(event.verify());
+        assert_eq!(event.kind, HANDLER_KIND);
+        assert_eq!(event.tag("t"), Some(TOPIC));
+
+        let listed = listed_providers(&[event]);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "arbstr");
+        assert_eq!(listed[0].url, "https://arbstr.example/v1");
+        assert_eq!(listed[0].models, ["gpt-4o", "llama-3"]);
+        assert_eq!((listed[0].input_rate, listed[0].output_rate), (5.0, 15.0));
+    }
+}
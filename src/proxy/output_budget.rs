@@ -0,0 +1,75 @@
+//! Client output token budgets (`x-arbstr-max-output-tokens`).
+//!
+//! A streamed request may carry a cap on the tokens it is willing to
+//! receive. The stream observer estimates the tokens in each delta as it
+//! passes; once the running count goes over the cap, the chunk that crossed
+//! it is withheld, the upstream request is dropped, and the client gets a
+//! closing chunk with `finish_reason: "length"`. The request is logged with
+//! finish reason `max_output_tokens` and a cost prorated to the estimated
+//! tokens, since the provider's usage report never arrives.
+
+use axum::http::HeaderMap;
+use bytes::Bytes;
+
+use crate::error::Error;
+
+/// Request header: most output tokens a streamed reply may carry.
+pub const MAX_OUTPUT_TOKENS_HEADER: &str = "x-arbstr-max-output-tokens";
+
+/// Finish reason logged for a stream stopped at its budget.
+pub const FINISH_REASON: &str = "max_output_tokens";
+
+/// The cap on a streamed reply, with what is needed to price it if the
+/// stream is cut short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBudget {
+    pub max_tokens: u32,
+    /// Estimated prompt tokens, billed alongside the output.
+    pub prompt_tokens: u32,
+}
+
+/// The `x-arbstr-max-output-tokens` value, if sent.
+pub fn from_headers(headers: &HeaderMap) -> Result<Option<u32>, Error> {
+    let Some(value) = headers.get(MAX_OUTPUT_TOKENS_HEADER) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .filter(|&n| n > 0)
+        .map(Some)
+        .ok_or_else(|| {
+            Error::BadRequest(format!(
+                "{} must be a positive integer",
+                MAX_OUTPUT_TOKENS_HEADER
+            ))
+        })
+}
+
+/// Closing chunk sent to the client when its budget stops a stream.
+pub fn finish_event() -> Bytes {
+    let chunk = serde_json::json!({
+        "object": "chat.completion.chunk",
+        "choices": [{"index": 0, "delta": {}, "finish_reason": "length"}],
+    });
+    Bytes::from(format!("data: {}\n\n", chunk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn parses_positive_budgets_only() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers).unwrap(), None);
+        headers.insert(MAX_OUTPUT_TOKENS_HEADER, HeaderValue::from_static("256"));
+        assert_eq!(from_headers(&headers).unwrap(), Some(256));
+        for bad in ["0", "-1", "lots"] {
+            headers.insert(MAX_OUTPUT_TOKENS_HEADER, HeaderValue::from_static(bad));
+            assert!(from_headers(&headers).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Header passthrough between clients and providers.
+//!
+//! arbstr builds upstream requests and client responses from scratch, so
+//! headers are dropped unless named in `[headers]`. Config validation keeps
+//! credentials and framing headers out of the allow-lists.
+
+use axum::http::{HeaderMap, HeaderName};
+
+/// Copy the headers named in `allow` from `source`.
+///
+/// Names are matched case-insensitively; repeated headers keep all values.
+/// Unparseable names are skipped (config validation rejects them).
+pub(crate) fn select_headers(source: &HeaderMap, allow: &[String]) -> HeaderMap {
+    let mut selected = HeaderMap::new();
+    for name in allow {
+        let Ok(name) = HeaderName::from_bytes(name.to_ascii_lowercase().as_bytes()) else {
+            continue;
+        };
+        for value in source.get_all(&name) {
+            selected.append(name.clone(), value.clone());
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn selects_only_allowed_headers() {
+        let mut source = HeaderMap::new();
+        source.insert("openai-organization", HeaderValue::from_static("org-1"));
+        source.insert("authorization", HeaderValue::from_static("Bearer x"));
+        source.append("x-trace", HeaderValue::from_static("a"));
+        source.append("x-trace", HeaderValue::from_static("b"));
+
+        let allow = vec!["OpenAI-Organization".to_string(), "x-trace".to_string()];
+        let selected = select_headers(&source, &allow);
+
+        assert_eq!(selected.len(), 3);
+        assert_eq!(selected["openai-organization"], "org-1");
+        let traces: Vec<_> = selected.get_all("x-trace").iter().collect();
+        assert_eq!(traces, vec!["a", "b"]);
+        assert!(selected.get("authorization").is_none());
+    }
+
+    #[test]
+    fn missing_headers_are_skipped() {
+        let selected = select_headers(&HeaderMap::new(), &["traceparent".to_string()]);
+        assert!(selected.is_empty());
+    }
+}
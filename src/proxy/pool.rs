@@ -0,0 +1,263 @@
+//! Per-provider HTTP clients and connection stats.
+//!
+//! Providers with a `[providers.pool]` section or `resolve` overrides get
+//! their own reqwest client built from those settings; everyone else shares
+//! the default client.
+//! reqwest does not expose pool internals, so the stats here are what arbstr
+//! can observe around each upstream call: requests sent, requests in flight,
+//! time to response headers (which includes any connection setup), and the
+//! negotiated HTTP version.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use reqwest::{Client, ClientBuilder};
+use serde::Serialize;
+
+use super::dns::DohResolver;
+use crate::config::{PoolConfig, ProviderConfig};
+
+/// Timeouts shared by every upstream client.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Observed connection stats for one provider.
+#[derive(Debug, Default)]
+struct ConnectionCounters {
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+    errors: AtomicU64,
+    headers_ms_total: AtomicU64,
+    last_http_version: Mutex<Option<String>>,
+}
+
+/// Connection stats for `/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStats {
+    pub requests: u64,
+    pub in_flight: u64,
+    /// Requests that failed before response headers arrived.
+    pub errors: u64,
+    /// Average time from send to response headers, over successful sends.
+    pub avg_time_to_headers_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+    /// Dedicated pool settings, when configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<PoolConfig>,
+}
+
+/// Per-provider clients and connection counters.
+#[derive(Debug, Default)]
+pub struct ProviderClients {
+    clients: HashMap<String, Client>,
+    pools: HashMap<String, PoolConfig>,
+    counters: DashMap<String, ConnectionCounters>,
+}
+
+impl ProviderClients {
+    /// Build a dedicated client for every provider with pool settings or
+    /// `resolve` overrides. `resolver` is the DoH resolver, if configured.
+    pub fn new(
+        providers: &[ProviderConfig],
+        resolver: Option<&Arc<DohResolver>>,
+    ) -> Result<Self, reqwest::Error> {
+        let mut clients = HashMap::new();
+        let mut pools = HashMap::new();
+        for provider in providers {
+            if provider.pool.is_none() && provider.resolve.is_empty() {
+                continue;
+            }
+            tracing::info!(
+                provider = %provider.name,
+                pool = ?provider.pool,
+                resolve = ?provider.resolve,
+                "Dedicated upstream client"
+            );
+            clients.insert(provider.name.clone(), build_client(provider, resolver)?);
+            if let Some(pool) = &provider.pool {
+                pools.insert(provider.name.clone(), pool.clone());
+            }
+        }
+        Ok(Self {
+            clients,
+            pools,
+            counters: DashMap::new(),
+        })
+    }
+
+    /// The dedicated client for `provider`, if it has one.
+    pub fn get(&self, provider: &str) -> Option<&Client> {
+        self.clients.get(provider)
+    }
+
+    /// Mark a request to `provider` as sent; the guard records its outcome.
+    pub fn start(&self, provider: &str) -> SendGuard<'_> {
+        let counters = self.counters.entry(provider.to_string()).or_default();
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        SendGuard {
+            clients: self,
+            provider: provider.to_string(),
+            start: std::time::Instant::now(),
+            done: false,
+        }
+    }
+
+    /// Observed stats for `provider`.
+    pub fn stats(&self, provider: &str) -> ConnectionStats {
+        let pool = self.pools.get(provider).cloned();
+        match self.counters.get(provider) {
+            Some(c) => {
+                let requests = c.requests.load(Ordering::Relaxed);
+                let in_flight = c.in_flight.load(Ordering::Relaxed);
+                let errors = c.errors.load(Ordering::Relaxed);
+                let completed = requests.saturating_sub(in_flight + errors);
+                ConnectionStats {
+                    requests,
+                    in_flight,
+                    errors,
+                    avg_time_to_headers_ms: (completed > 0).then(|| {
+                        c.headers_ms_total.load(Ordering::Relaxed) as f64 / completed as f64
+                    }),
+                    http_version: c
+                        .last_http_version
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .clone(),
+                    pool,
+                }
+            }
+            None => ConnectionStats {
+                requests: 0,
+                in_flight: 0,
+                errors: 0,
+                avg_time_to_headers_ms: None,
+                http_version: None,
+                pool,
+            },
+        }
+    }
+}
+
+/// Tracks one in-flight upstream request. Dropping it without calling
+/// [`SendGuard::headers_received`] counts the request as an error.
+pub struct SendGuard<'a> {
+    clients: &'a ProviderClients,
+    provider: String,
+    start: std::time::Instant,
+    done: bool,
+}
+
+impl SendGuard<'_> {
+    /// Record that response headers arrived with the given HTTP version.
+    pub fn headers_received(mut self, version: reqwest::Version) {
+        self.done = true;
+        if let Some(c) = self.clients.counters.get(&self.provider) {
+            c.in_flight.fetch_sub(1, Ordering::Relaxed);
+            c.headers_ms_total
+                .fetch_add(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+            *c.last_http_version
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(format!("{:?}", version));
+        }
+    }
+}
+
+impl Drop for SendGuard<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        if let Some(c) = self.clients.counters.get(&self.provider) {
+            c.in_flight.fetch_sub(1, Ordering::Relaxed);
+            c.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Builder with the shared upstream timeouts and DNS resolver.
+pub(crate) fn client_builder(resolver: Option<&Arc<DohResolver>>) -> ClientBuilder {
+    let builder = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT);
+    match resolver {
+        Some(resolver) => builder.dns_resolver(resolver.clone()),
+        None => builder,
+    }
+}
+
+/// Build a provider's dedicated client from its pool settings and
+/// `resolve` overrides.
+fn build_client(
+    provider: &ProviderConfig,
+    resolver: Option<&Arc<DohResolver>>,
+) -> Result<Client, reqwest::Error> {
+    let mut builder = client_builder(resolver);
+    for (host, ip) in &provider.resolve {
+        // Port 0: reqwest keeps the port from the request URL
+        builder = builder.resolve(host, SocketAddr::new(*ip, 0));
+    }
+    let Some(pool) = &provider.pool else {
+        return builder.build();
+    };
+    if let Some(max_idle) = pool.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(secs) = pool.idle_timeout_secs {
+        builder = builder.pool_idle_timeout(Duration::from_secs(secs));
+    }
+    if pool.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    if let Some(secs) = pool.http2_keep_alive_interval_secs {
+        builder = builder
+            .http2_keep_alive_interval(Duration::from_secs(secs))
+            .http2_keep_alive_while_idle(true);
+    }
+    if let Some(secs) = pool.http2_keep_alive_timeout_secs {
+        builder = builder.http2_keep_alive_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = pool.tcp_keepalive_secs {
+        builder = builder.tcp_keepalive(Duration::from_secs(secs));
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_success_and_error() {
+        let clients = ProviderClients::default();
+        clients
+            .start("alpha")
+            .headers_received(reqwest::Version::HTTP_2);
+        {
+            let _guard = clients.start("alpha");
+            let stats = clients.stats("alpha");
+            assert_eq!(stats.in_flight, 1);
+        }
+
+        let stats = clients.stats("alpha");
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.errors, 1);
+        assert!(stats.avg_time_to_headers_ms.is_some());
+        assert_eq!(stats.http_version.as_deref(), Some("HTTP/2.0"));
+        assert!(stats.pool.is_none());
+    }
+
+    #[test]
+    fn unknown_provider_has_empty_stats() {
+        let clients = ProviderClients::default();
+        let stats = clients.stats("nobody");
+        assert_eq!(stats.requests, 0);
+        assert!(stats.avg_time_to_headers_ms.is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! Request log anonymization (`[privacy]`).
+//!
+//! Every request log passes through [`Anonymizer::apply`] before it reaches
+//! the recent-request buffer or the database: client identifiers are hashed
+//! or dropped, and upstream error bodies (which may echo prompt content) are
+//! stripped. A background task clears trace, client request, and
+//! conversation IDs once they are older than `correlation_retention_days`. The settings in effect
+//! are reported under `privacy` in `/health` so they can be audited.
+
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+
+use crate::config::{ClientIdMode, PrivacyConfig};
+use crate::storage::logging::RequestLog;
+
+/// How often the retention task clears expired correlation IDs.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Prefix marking a hashed identifier.
+const HASH_PREFIX: &str = "h:";
+
+/// Applies `[privacy]` to request logs. Inert by default.
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    mode: ClientIdMode,
+    key: Vec<u8>,
+    salted: bool,
+    strip_prompts: bool,
+    correlation_retention_days: Option<u32>,
+}
+
+/// Privacy settings in effect, for `/health`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrivacyStatus {
+    pub mode: ClientIdMode,
+    /// False when hashes use a per-process random key.
+    pub salted: bool,
+    pub strip_prompts: bool,
+    pub correlation_retention_days: Option<u32>,
+}
+
+impl Anonymizer {
+    pub fn new(config: &PrivacyConfig) -> Self {
+        let key = match &config.salt {
+            Some(salt) => salt.expose_secret().as_bytes().to_vec(),
+            None if config.mode == ClientIdMode::Hash => {
+                tracing::warn!(
+                    "privacy.mode = \"hash\" without privacy.salt: hashes will not match across restarts"
+                );
+                rand::random::<[u8; 32]>().to_vec()
+            }
+            None => Vec::new(),
+        };
+        Self {
+            mode: config.mode,
+            key,
+            salted: config.salt.is_some(),
+            strip_prompts: config.strip_prompts,
+            correlation_retention_days: config.correlation_retention_days,
+        }
+    }
+
+    /// Anonymize `log` in place.
+    pub fn apply(&self, log: &mut RequestLog) {
+        match self.mode {
+            ClientIdMode::Off => {}
+            ClientIdMode::Hash => {
+                log.trace_id = log.trace_id.as_deref().map(|id| self.hash(id));
+                log.client_request_id = log.client_request_id.as_deref().map(|id| self.hash(id));
+                log.conversation_id = log.conversation_id.as_deref().map(|id| self.hash(id));
+            }
+            ClientIdMode::Omit => {
+                log.trace_id = None;
+                log.client_request_id = None;
+                log.conversation_id = None;
+            }
+        }
+        if self.strip_prompts {
+            log.error_message = None;
+        }
+    }
+
+    /// The form a client identifier is stored in, for looking it up again;
+    /// `None` when identifiers are not stored.
+    pub fn stored_id(&self, id: &str) -> Option<String> {
+        match self.mode {
+            ClientIdMode::Off => Some(id.to_string()),
+            ClientIdMode::Hash => Some(self.hash(id)),
+            ClientIdMode::Omit => None,
+        }
+    }
+
+    /// Keyed hash of an identifier: `h:` followed by 32 hex digits.
+    fn hash(&self, value: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let mut out = String::with_capacity(HASH_PREFIX.len() + 32);
+        out.push_str(HASH_PREFIX);
+        for byte in &digest[..16] {
+            out.push_str(&format!("{:02x}", byte));
+        }
+        out
+    }
+
+    pub fn status(&self) -> PrivacyStatus {
+        PrivacyStatus {
+            mode: self.mode,
+            salted: self.salted,
+            strip_prompts: self.strip_prompts,
+            correlation_retention_days: self.correlation_retention_days,
+        }
+    }
+}
+
+/// Clear trace, client request, and conversation IDs from rows logged more than
+/// `retention_days` ago, every hour until cancelled.
+pub async fn retention_loop(
+    pool: SqlitePool,
+    retention_days: u32,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(RETENTION_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+                match crate::storage::logging::clear_correlation_ids(&pool, &cutoff.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)).await {
+                    Ok(0) => {}
+                    Ok(cleared) => tracing::info!(rows = cleared, retention_days, "Cleared expired correlation IDs"),
+                    Err(e) => tracing::warn!(error = %e, "Failed to clear expired correlation IDs"),
+                }
+            }
+            changed = cancel.changed() => {
+                if changed.is_err() || *cancel.borrow() {
+                    tracing::info!("Privacy retention task shutting down");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: ClientIdMode, salt: Option<&str>, strip_prompts: bool) -> PrivacyConfig {
+        PrivacyConfig {
+            mode,
+            salt: salt.map(|s| s.to_string().into()),
+            strip_prompts,
+            correlation_retention_days: None,
+        }
+    }
+
+    fn log() -> RequestLog {
+        RequestLog {
+            correlation_id: "corr-1".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: None,
+            policy: None,
+            streaming: false,
+            input_tokens: None,
+            output_tokens: None,
+            cost_sats: None,
+            provider_cost_sats: None,
+            latency_ms: 10,
+            success: false,
+            error_status: Some(400),
+            error_type: None,
+            error_message: Some("upstream echoed: my secret prompt".to_string()),
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string()),
+            client_request_id: Some("client-42".to_string()),
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: Some("chat-7".to_string()),
+        }
+    }
+
+    #[test]
+    fn hashes_or_omits_identifiers() {
+        let mut entry = log();
+        Anonymizer::default().apply(&mut entry);
+        assert_eq!(entry.client_request_id.as_deref(), Some("client-42"));
+        assert_eq!(entry.conversation_id.as_deref(), Some("chat-7"));
+        assert!(entry.error_message.is_some());
+
+        let salted = Anonymizer::new(&config(ClientIdMode::Hash, Some("pepper"), true));
+        let mut first = log();
+        let mut second = log();
+        salted.apply(&mut first);
+        salted.apply(&mut second);
+        let hashed = first.client_request_id.clone().unwrap();
+        assert!(hashed.starts_with("h:") && hashed.len() == 34, "{}", hashed);
+        assert_eq!(first.client_request_id, second.client_request_id);
+        assert_eq!(first.conversation_id, second.conversation_id);
+        assert_eq!(first.conversation_id, salted.stored_id("chat-7"));
+        assert_ne!(first.trace_id, first.client_request_id);
+        assert!(first.error_message.is_none());
+        assert!(salted.status().salted);
+
+        let other = Anonymizer::new(&config(ClientIdMode::Hash, Some("salt"), false));
+        let mut third = log();
+        other.apply(&mut third);
+        assert_ne!(third.client_request_id, first.client_request_id);
+
+        let omit = Anonymizer::new(&config(ClientIdMode::Omit, None, false));
+        let mut entry = log();
+        omit.apply(&mut entry);
+        assert!(entry.trace_id.is_none() && entry.client_request_id.is_none());
+        assert!(entry.conversation_id.is_none() && omit.stored_id("chat-7").is_none());
+        assert_eq!(entry.correlation_id, "corr-1");
+    }
+}
@@ -0,0 +1,243 @@
+//! Prompt compression (`[policies.rules.compress]`).
+//!
+//! Long prompts are compressed before they are forwarded: runs of spaces
+//! and blank lines are collapsed (code fences keep their indentation and
+//! alignment), and paragraphs repeated from earlier in the conversation
+//! are replaced with [`DUPLICATE_MARKER`]. With `model` set, the longest
+//! message before the latest one is also rewritten by that model,
+//! LLMLingua-style, keeping the rewrite only if it is shorter.
+//!
+//! The estimated prompt tokens before and after are recorded as log tags on
+//! the request; once the request succeeds, the input cost of the tokens
+//! removed at the serving provider's rate is tagged as well and added to
+//! the `prompt` counters of `/v1/stats/compression`.
+
+use std::collections::HashSet;
+
+use super::trim::text_message;
+use super::types::{ChatCompletionRequest, MessageContent};
+use crate::config::PromptCompressionConfig;
+
+/// Log tag listing the compression steps that changed the prompt.
+pub const COMPRESS_TAG: &str = "prompt_compression";
+
+/// Log tag with the estimated prompt tokens before compression.
+pub const ORIGINAL_TOKENS_TAG: &str = "prompt_tokens_original";
+
+/// Log tag with the estimated prompt tokens after compression.
+pub const COMPRESSED_TOKENS_TAG: &str = "prompt_tokens_compressed";
+
+/// Log tag with the input cost saved, in sats, at the serving provider.
+pub const SATS_SAVED_TAG: &str = "prompt_sats_saved";
+
+/// Replaces a paragraph repeated from earlier in the conversation.
+pub const DUPLICATE_MARKER: &str = "[repeated context omitted]";
+
+/// Paragraphs shorter than this (in bytes) are never deduplicated.
+const MIN_DUPLICATE_LEN: usize = 80;
+
+/// Whether `request` is long enough to be compressed under `config`.
+pub fn applies(request: &ChatCompletionRequest, config: &PromptCompressionConfig) -> bool {
+    request.estimate_tokens(0).0 >= config.min_prompt_tokens
+}
+
+/// Apply the whitespace and dedupe steps, returning the ones that changed
+/// the prompt.
+pub fn compress_local(
+    request: &mut ChatCompletionRequest,
+    config: &PromptCompressionConfig,
+) -> Vec<&'static str> {
+    let mut steps = Vec::new();
+    if config.whitespace && collapse_all_whitespace(request) {
+        steps.push("whitespace");
+    }
+    if config.dedupe && dedupe_paragraphs(request) {
+        steps.push("dedupe");
+    }
+    steps
+}
+
+fn collapse_all_whitespace(request: &mut ChatCompletionRequest) -> bool {
+    let mut changed = false;
+    for message in &mut request.messages {
+        if let MessageContent::Text(text) = &mut message.content {
+            let collapsed = collapse_whitespace(text);
+            if collapsed != *text {
+                *text = collapsed;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Trim trailing whitespace from every line, allow at most one blank line
+/// in a row, and outside code fences collapse runs of spaces and tabs
+/// after a line's indentation to one space.
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    let mut blank_run = 0;
+    for line in text.trim().lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 && !in_fence {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        if in_fence {
+            out.push_str(line);
+            continue;
+        }
+        let body = line.trim_start();
+        out.push_str(&line[..line.len() - body.len()]);
+        let mut previous_space = false;
+        for c in body.chars() {
+            let space = c == ' ' || c == '\t';
+            if !(space && previous_space) {
+                out.push(if space { ' ' } else { c });
+            }
+            previous_space = space;
+        }
+    }
+    out
+}
+
+/// Replace paragraphs already seen earlier in the conversation with
+/// [`DUPLICATE_MARKER`]. Returns whether any were replaced.
+fn dedupe_paragraphs(request: &mut ChatCompletionRequest) -> bool {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut changed = false;
+    for message in &mut request.messages {
+        let MessageContent::Text(text) = &mut message.content else {
+            continue;
+        };
+        let mut replaced = false;
+        let paragraphs: Vec<&str> = text
+            .split("\n\n")
+            .map(|paragraph| {
+                let key = paragraph.trim();
+                if key.len() < MIN_DUPLICATE_LEN || seen.insert(key.to_string()) {
+                    paragraph
+                } else {
+                    replaced = true;
+                    DUPLICATE_MARKER
+                }
+            })
+            .collect();
+        if replaced {
+            *text = paragraphs.join("\n\n");
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Index of the message `model` compresses: the longest text message
+/// before the latest one.
+pub fn model_target(request: &ChatCompletionRequest) -> Option<usize> {
+    let last = request.messages.len().checked_sub(1)?;
+    request.messages[..last]
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| matches!(m.content, MessageContent::Text(_)))
+        .max_by_key(|(_, m)| m.content.char_len())
+        .map(|(i, _)| i)
+}
+
+/// Request asking `model` to compress `text`.
+pub fn compression_request(model: &str, text: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            text_message(
+                "system",
+                "Compress the following text so another language model can use it as \
+                 context with fewer tokens. Remove filler words, redundancy and \
+                 formatting; keep every fact, number, name, instruction and code snippet. \
+                 Reply with the compressed text only.",
+            ),
+            text_message("user", text),
+        ],
+        temperature: Some(0.0),
+        max_tokens: Some((text.len() / 4).max(1) as u32),
+        stream: Some(false),
+        stream_options: None,
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        stop: None,
+        user: None,
+        extra: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PromptCompressionConfig {
+        PromptCompressionConfig {
+            min_prompt_tokens: 0,
+            whitespace: true,
+            dedupe: true,
+            model: None,
+        }
+    }
+
+    fn conversation(texts: &[(&str, &str)]) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: texts
+                .iter()
+                .map(|(role, text)| text_message(role, text))
+                .collect(),
+            ..compression_request("gpt-4o", "")
+        }
+    }
+
+    #[test]
+    fn collapses_whitespace_outside_code_fences() {
+        let text = "  Hello   there,\t\tfriend.  \n\n\n\n    indented  line\n```\nlet  x =   1;\n\n\n```\n";
+        assert_eq!(
+            collapse_whitespace(text),
+            "Hello there, friend.\n\n    indented line\n```\nlet  x =   1;\n\n\n```"
+        );
+    }
+
+    #[test]
+    fn replaces_repeated_paragraphs() {
+        let doc = "The quarterly report shows revenue grew twelve percent while costs stayed flat overall.";
+        let mut request = conversation(&[
+            ("user", &format!("{}\n\nSummarize it.", doc)),
+            ("assistant", "Revenue is up."),
+            ("user", &format!("{}\n\nWhat about costs?", doc)),
+        ]);
+        assert_eq!(compress_local(&mut request, &config()), ["dedupe"]);
+        assert_eq!(
+            request.messages[2].content.as_str(),
+            format!("{}\n\nWhat about costs?", DUPLICATE_MARKER)
+        );
+        assert!(request.messages[0].content.as_str().starts_with(doc));
+        assert!(compress_local(&mut request, &config()).is_empty());
+    }
+
+    #[test]
+    fn model_compresses_the_longest_earlier_message() {
+        let request = conversation(&[
+            ("system", "Be brief."),
+            ("user", &"context ".repeat(50)),
+            ("user", &"question ".repeat(100)),
+        ]);
+        assert_eq!(model_target(&request), Some(1));
+        assert_eq!(model_target(&conversation(&[("user", "hi")])), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! One-off provider check behind `arbstr test-provider`.
+//!
+//! Sends a minimal chat completion straight to one configured provider,
+//! bypassing routing, circuit breakers and the request log, with the same
+//! client settings (`pool`, `resolve`, `[dns]`) and headers (`auth_scheme`,
+//! `extra_headers`) the proxy would use. The report gives the status,
+//! latency, token usage, the cost at the provider's configured rates, and
+//! the start of the reply, so a newly added provider can be verified before
+//! any traffic is routed to it.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use super::handlers::apply_provider_headers;
+use super::pool::{self, ProviderClients};
+use crate::config::Config;
+use crate::router::actual_cost_sats;
+
+/// Prompt sent to the provider.
+const PROMPT: &str = "Reply with a short greeting.";
+
+/// Output cap for the test completion.
+const MAX_TOKENS: u32 = 32;
+
+/// Characters of the reply (or error body) shown in the report.
+const SNIPPET_CHARS: usize = 120;
+
+/// What to send in a provider test.
+#[derive(Debug, Clone)]
+pub struct ProviderTestOptions {
+    pub provider: String,
+    /// Model to request; defaults to the provider's first listed model.
+    pub model: Option<String>,
+    pub stream: bool,
+    pub timeout: Duration,
+}
+
+/// Result of a provider test.
+#[derive(Debug, Clone)]
+pub struct ProviderTestReport {
+    pub provider: String,
+    pub url: String,
+    pub model: String,
+    pub stream: bool,
+    /// Whether the provider's configured models include `model`.
+    pub model_listed: bool,
+    pub status: u16,
+    /// Time until response headers arrived.
+    pub headers_ms: u64,
+    /// Time until the first streamed content.
+    pub first_token_ms: Option<u64>,
+    /// Time until the response was read in full.
+    pub total_ms: u64,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    /// Cost at the provider's configured rates, when usage was reported.
+    pub cost_sats: Option<f64>,
+    /// Start of the reply, or of the error body for a failed request.
+    pub snippet: String,
+    /// Why the response didn't look like a completion, if it didn't.
+    pub problem: Option<String>,
+}
+
+impl ProviderTestReport {
+    /// Whether the provider answered with a usable completion.
+    pub fn success(&self) -> bool {
+        (200..300).contains(&self.status) && self.problem.is_none()
+    }
+}
+
+impl fmt::Display for ProviderTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Provider: {} ({})", self.provider, self.url)?;
+        writeln!(
+            f,
+            "Model:    {}{}{}",
+            self.model,
+            if self.stream { ", streaming" } else { "" },
+            if self.model_listed {
+                ""
+            } else {
+                " (not in the provider's models)"
+            }
+        )?;
+        writeln!(
+            f,
+            "Status:   {} {}",
+            self.status,
+            reqwest::StatusCode::from_u16(self.status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("")
+        )?;
+        match self.first_token_ms {
+            Some(first) => writeln!(
+                f,
+                "Latency:  {} ms (headers {} ms, first token {} ms)",
+                self.total_ms, self.headers_ms, first
+            )?,
+            None => writeln!(
+                f,
+                "Latency:  {} ms (headers {} ms)",
+                self.total_ms, self.headers_ms
+            )?,
+        }
+        match (self.input_tokens, self.output_tokens) {
+            (Some(input), Some(output)) => {
+                writeln!(f, "Usage:    {} input, {} output tokens", input, output)?
+            }
+            _ => writeln!(f, "Usage:    not reported")?,
+        }
+        match self.cost_sats {
+            Some(cost) => writeln!(f, "Cost:     {:.3} sats", cost)?,
+            None => writeln!(f, "Cost:     unknown (no usage)")?,
+        }
+        writeln!(f, "Response: {:?}", self.snippet)?;
+        match &self.problem {
+            Some(problem) => write!(f, "\nFAILED: {}", problem),
+            None if self.success() => write!(f, "\nOK"),
+            None => write!(f, "\nFAILED: HTTP {}", self.status),
+        }
+    }
+}
+
+/// The first [`SNIPPET_CHARS`] characters of `text`, marked when cut.
+fn snippet(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// `prompt_tokens` and `completion_tokens` of a `usage` object.
+fn usage(value: &Value) -> Option<(u32, u32)> {
+    let input = value["usage"]["prompt_tokens"].as_u64()?;
+    let output = value["usage"]["completion_tokens"].as_u64()?;
+    Some((input as u32, output as u32))
+}
+
+/// Send one test completion to the provider named in `options`.
+///
+/// Errors are for requests that couldn't be made (unknown provider, no
+/// model, connection failures); an error status from the provider is
+/// reported in the returned report.
+pub async fn run(
+    config: &Config,
+    options: &ProviderTestOptions,
+) -> anyhow::Result<ProviderTestReport> {
+    let provider = config
+        .providers
+        .iter()
+        .find(|p| p.name == options.provider)
+        .with_context(|| {
+            let names: Vec<&str> = config.providers.iter().map(|p| p.name.as_str()).collect();
+            format!(
+                "no provider named '{}' (configured: {})",
+                options.provider,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })?;
+    let model = options
+        .model
+        .clone()
+        .or_else(|| provider.models.first().cloned())
+        .with_context(|| format!("provider '{}' lists no models; pass --model", provider.name))?;
+
+    let resolver = super::dns::resolver(&config.dns)?;
+    let clients = ProviderClients::new(std::slice::from_ref(provider), resolver.as_ref())?;
+    let shared = pool::client_builder(resolver.as_ref()).build()?;
+    let client = clients.get(&provider.name).unwrap_or(&shared);
+
+    let mut body = json!({
+        "model": model,
+        "messages": [{"role": "user", "content": PROMPT}],
+        "max_tokens": MAX_TOKENS,
+        "stream": options.stream,
+    });
+    if options.stream {
+        body["stream_options"] = json!({"include_usage": true});
+    }
+    let url = format!("{}/chat/completions", provider.url.trim_end_matches('/'));
+    let request = apply_provider_headers(
+        client.post(&url).timeout(options.timeout).json(&body),
+        provider.api_key.as_ref(),
+        provider.auth_scheme,
+        &provider.extra_headers,
+    );
+
+    let start = Instant::now();
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("request to {} failed", url))?;
+    let headers_ms = start.elapsed().as_millis() as u64;
+    let status = response.status();
+
+    let mut report = ProviderTestReport {
+        provider: provider.name.clone(),
+        url,
+        model_listed: provider.models.iter().any(|m| m == &model),
+        model,
+        stream: options.stream,
+        status: status.as_u16(),
+        headers_ms,
+        first_token_ms: None,
+        total_ms: headers_ms,
+        input_tokens: None,
+        output_tokens: None,
+        cost_sats: None,
+        snippet: String::new(),
+        problem: None,
+    };
+
+    let is_sse = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let mut tokens = None;
+    if status.is_success() && is_sse {
+        let mut content = String::new();
+        let mut buffer = String::new();
+        let mut done = false;
+        let mut chunks = response.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.context("reading the stream failed")?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = buffer.find('\n') {
+                let line = buffer[..end].trim().to_string();
+                buffer.drain(..=end);
+                let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    done = true;
+                    continue;
+                }
+                let Ok(event) = serde_json::from_str::<Value>(data) else {
+                    continue;
+                };
+                if let Some(delta) = event["choices"][0]["delta"]["content"].as_str() {
+                    if report.first_token_ms.is_none() && !delta.is_empty() {
+                        report.first_token_ms = Some(start.elapsed().as_millis() as u64);
+                    }
+                    content.push_str(delta);
+                }
+                tokens = usage(&event).or(tokens);
+            }
+        }
+        report.snippet = snippet(&content);
+        if !done {
+            report.problem = Some("stream ended without [DONE]".to_string());
+        } else if content.is_empty() {
+            report.problem = Some("stream carried no content".to_string());
+        }
+    } else {
+        let text = response
+            .text()
+            .await
+            .context("reading the response failed")?;
+        match serde_json::from_str::<Value>(&text) {
+            Ok(value) if status.is_success() => {
+                tokens = usage(&value);
+                match value["choices"][0]["message"]["content"].as_str() {
+                    Some(content) => report.snippet = snippet(content),
+                    None => {
+                        report.snippet = snippet(&text);
+                        report.problem = Some("response has no choices[0].message".to_string());
+                    }
+                }
+            }
+            _ => {
+                report.snippet = snippet(&text);
+                if status.is_success() {
+                    report.problem = Some("response is not a JSON completion".to_string());
+                }
+            }
+        }
+    }
+    report.total_ms = start.elapsed().as_millis() as u64;
+
+    if let Some((input, output)) = tokens {
+        report.input_tokens = Some(input);
+        report.output_tokens = Some(output);
+        report.cost_sats = Some(actual_cost_sats(
+            input,
+            output,
+            provider.input_rate,
+            provider.output_rate,
+            provider.base_fee,
+            provider.min_charge_sats,
+        ));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_cuts_long_text_on_char_boundaries() {
+        assert_eq!(snippet("  hello \n"), "hello");
+        let long = "é".repeat(SNIPPET_CHARS + 5);
+        let cut = snippet(&long);
+        assert_eq!(cut.chars().count(), SNIPPET_CHARS + 3);
+        assert!(cut.ends_with("..."));
+    }
+}
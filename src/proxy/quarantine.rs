@@ -0,0 +1,217 @@
+//! Quarantine for providers that reject arbstr's API key.
+//!
+//! A 401 or 403 means the provider's key is wrong or expired, which no
+//! retry will fix. The provider is dropped from routing at once, separately
+//! from its circuit breaker, and an alert is logged (and POSTed to
+//! `[routing.auth_quarantine] webhook_url` when set) naming the env var to
+//! fix. After `retry_after_secs` one request is let through; a success lifts
+//! the quarantine, another rejection renews it without a second alert.
+
+use std::time::Duration;
+
+use dashmap::DashMap;
+use reqwest::Client;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::config::{convention_env_var_name, AuthQuarantineConfig};
+
+/// Timeout for the alert webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Entry {
+    status: u16,
+    since: chrono::DateTime<chrono::Utc>,
+    until: Instant,
+}
+
+/// Quarantined providers. Inert when disabled.
+#[derive(Debug, Default)]
+pub struct AuthQuarantine {
+    config: Option<AuthQuarantineConfig>,
+    entries: DashMap<String, Entry>,
+}
+
+/// Quarantine state of one provider, for `/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantineSnapshot {
+    /// Status the provider answered with (401 or 403).
+    pub status: u16,
+    pub since: String,
+    /// Seconds until the next request is let through.
+    pub retry_in_secs: u64,
+    /// Convention env var for the provider's key.
+    pub env_var: String,
+}
+
+/// Alert body POSTed to the webhook.
+#[derive(Debug, Serialize)]
+struct Alert<'a> {
+    event: &'static str,
+    provider: &'a str,
+    status: u16,
+    env_var: String,
+    message: String,
+}
+
+impl AuthQuarantine {
+    pub fn new(config: AuthQuarantineConfig) -> Self {
+        Self {
+            config: config.enabled.then_some(config),
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Whether `provider` should be skipped. Once the quarantine period is
+    /// over, the first caller gets `false` and the period is renewed for
+    /// everyone else while that request checks the key.
+    pub fn is_quarantined(&self, provider: &str) -> bool {
+        let Some(config) = &self.config else {
+            return false;
+        };
+        let Some(mut entry) = self.entries.get_mut(provider) else {
+            return false;
+        };
+        let now = Instant::now();
+        if entry.until > now {
+            return true;
+        }
+        entry.until = now + Duration::from_secs(config.retry_after_secs);
+        tracing::info!(provider = %provider, "Retrying quarantined provider");
+        false
+    }
+
+    /// Quarantine `provider` after it answered `status`. Returns true when
+    /// it was not already quarantined.
+    pub fn quarantine(&self, provider: &str, status: u16) -> bool {
+        let Some(config) = &self.config else {
+            return false;
+        };
+        let until = Instant::now() + Duration::from_secs(config.retry_after_secs);
+        let mut newly = false;
+        self.entries
+            .entry(provider.to_string())
+            .and_modify(|e| {
+                e.status = status;
+                e.until = until;
+            })
+            .or_insert_with(|| {
+                newly = true;
+                Entry {
+                    status,
+                    since: chrono::Utc::now(),
+                    until,
+                }
+            });
+        newly
+    }
+
+    /// Lift the quarantine after `provider` accepted a request.
+    pub fn clear(&self, provider: &str) {
+        if self.entries.remove(provider).is_some() {
+            tracing::info!(provider = %provider, "Provider accepted its API key, quarantine lifted");
+        }
+    }
+
+    /// Current quarantine of `provider`, if any.
+    pub fn snapshot(&self, provider: &str) -> Option<QuarantineSnapshot> {
+        let entry = self.entries.get(provider)?;
+        Some(QuarantineSnapshot {
+            status: entry.status,
+            since: entry
+                .since
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            retry_in_secs: entry
+                .until
+                .saturating_duration_since(Instant::now())
+                .as_secs(),
+            env_var: convention_env_var_name(provider),
+        })
+    }
+
+    /// Quarantine `provider` and, if it was not already, log an alert with
+    /// fix-up guidance and POST it to the configured webhook.
+    pub fn report(&self, client: &Client, provider: &str, status: u16) {
+        if !self.quarantine(provider, status) {
+            return;
+        }
+        let env_var = convention_env_var_name(provider);
+        let message = format!(
+            "Provider '{}' rejected its API key (HTTP {}) and is suspended from routing. \
+             Set {} (or fix api_key for this provider), then run `arbstr check` and restart.",
+            provider, status, env_var
+        );
+        tracing::error!(
+            provider = %provider,
+            status = status,
+            env_var = %env_var,
+            "{}",
+            message
+        );
+
+        let Some(url) = self.config.as_ref().and_then(|c| c.webhook_url.clone()) else {
+            return;
+        };
+        let alert = Alert {
+            event: "provider_auth_quarantined",
+            provider,
+            status,
+            env_var,
+            message,
+        };
+        let request = client.post(&url).timeout(WEBHOOK_TIMEOUT).json(&alert);
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(r) if r.status().is_success() => {}
+                Ok(r) => tracing::warn!(status = %r.status(), "Quarantine alert webhook failed"),
+                Err(e) => tracing::warn!(error = %e, "Quarantine alert webhook failed"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarantine(retry_after_secs: u64) -> AuthQuarantine {
+        AuthQuarantine::new(AuthQuarantineConfig {
+            retry_after_secs,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quarantines_until_retry_after() {
+        let q = quarantine(60);
+        assert!(!q.is_quarantined("alpha"));
+        assert!(q.quarantine("alpha", 401));
+        assert!(!q.quarantine("alpha", 401));
+        assert!(q.is_quarantined("alpha"));
+
+        let snapshot = q.snapshot("alpha").unwrap();
+        assert_eq!(snapshot.status, 401);
+        assert_eq!(snapshot.retry_in_secs, 60);
+        assert_eq!(snapshot.env_var, "ARBSTR_ALPHA_API_KEY");
+
+        // After the period, exactly one caller is let through
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert!(!q.is_quarantined("alpha"));
+        assert!(q.is_quarantined("alpha"));
+
+        q.clear("alpha");
+        assert!(!q.is_quarantined("alpha"));
+        assert!(q.snapshot("alpha").is_none());
+    }
+
+    #[test]
+    fn disabled_never_quarantines() {
+        let q = AuthQuarantine::new(AuthQuarantineConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        assert!(!q.quarantine("alpha", 403));
+        assert!(!q.is_quarantined("alpha"));
+    }
+}
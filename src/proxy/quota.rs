@@ -0,0 +1,265 @@
+//! Provider-advertised rate quotas.
+//!
+//! Providers may declare `requests_per_minute` and `tokens_per_minute`.
+//! Every request sent to such a provider, and the tokens it reports using,
+//! are counted over a rolling one-minute window. A provider whose next
+//! request would exceed either quota is moved behind the other candidates,
+//! so traffic shifts away before the provider starts answering 429. It is
+//! still tried as a last resort. Remaining quota is reported in `/health`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::config::ProviderConfig;
+use crate::router::SelectedProvider;
+
+/// Length of the rolling quota window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Consumption of one provider over the last minute.
+#[derive(Debug)]
+struct Usage {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u64>,
+    requests: VecDeque<Instant>,
+    tokens: VecDeque<(Instant, u64)>,
+}
+
+impl Usage {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while self
+            .tokens
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) >= WINDOW)
+        {
+            self.tokens.pop_front();
+        }
+    }
+
+    fn tokens_used(&self) -> u64 {
+        self.tokens.iter().map(|(_, n)| n).sum()
+    }
+
+    /// Whether one more request of `estimated_tokens` fits in the quota.
+    fn has_headroom(&self, estimated_tokens: u64) -> bool {
+        let requests_ok = self
+            .requests_per_minute
+            .is_none_or(|limit| (self.requests.len() as u64) < limit as u64);
+        let tokens_ok = self
+            .tokens_per_minute
+            .is_none_or(|limit| self.tokens_used() + estimated_tokens <= limit);
+        requests_ok && tokens_ok
+    }
+}
+
+/// Remaining quota of one provider, for `/health`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaSnapshot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_remaining: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_remaining: Option<u64>,
+}
+
+/// Rolling quota usage of providers with declared quotas. Empty when none
+/// are configured.
+#[derive(Debug, Default)]
+pub struct ProviderQuotas {
+    usage: Mutex<BTreeMap<String, Usage>>,
+}
+
+impl ProviderQuotas {
+    pub fn new(providers: &[ProviderConfig]) -> Self {
+        let usage = providers
+            .iter()
+            .filter(|p| p.requests_per_minute.is_some() || p.tokens_per_minute.is_some())
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    Usage {
+                        requests_per_minute: p.requests_per_minute,
+                        tokens_per_minute: p.tokens_per_minute,
+                        requests: VecDeque::new(),
+                        tokens: VecDeque::new(),
+                    },
+                )
+            })
+            .collect();
+        Self {
+            usage: Mutex::new(usage),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Usage>> {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `provider` can take another request of `estimated_tokens`
+    /// without exceeding its quota. Providers without quotas always can.
+    pub fn has_headroom(&self, provider: &str, estimated_tokens: u64) -> bool {
+        let mut usage = self.lock();
+        let Some(usage) = usage.get_mut(provider) else {
+            return true;
+        };
+        usage.prune(Instant::now());
+        usage.has_headroom(estimated_tokens)
+    }
+
+    /// Move candidates without headroom for `estimated_tokens` behind the
+    /// rest, keeping the relative order within each group. A circuit probe
+    /// stays first.
+    pub fn apply(
+        &self,
+        candidates: Vec<SelectedProvider>,
+        estimated_tokens: u64,
+        probe_provider: Option<&str>,
+    ) -> Vec<SelectedProvider> {
+        let (mut ready, exhausted): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| {
+            let ok = Some(c.name.as_str()) == probe_provider
+                || self.has_headroom(&c.name, estimated_tokens);
+            if !ok {
+                tracing::debug!(
+                    provider = %c.name,
+                    estimated_tokens,
+                    "Deprioritizing provider: rate quota nearly exhausted"
+                );
+            }
+            ok
+        });
+        ready.extend(exhausted);
+        ready
+    }
+
+    /// Count a request sent to `provider`.
+    pub fn record_request(&self, provider: &str) {
+        if let Some(usage) = self.lock().get_mut(provider) {
+            let now = Instant::now();
+            usage.prune(now);
+            usage.requests.push_back(now);
+        }
+    }
+
+    /// Count tokens `provider` reported for a completed request.
+    pub fn record_tokens(&self, provider: &str, tokens: u64) {
+        if let Some(usage) = self.lock().get_mut(provider) {
+            let now = Instant::now();
+            usage.prune(now);
+            usage.tokens.push_back((now, tokens));
+        }
+    }
+
+    /// Remaining quota of `provider`, or `None` when it declares none.
+    pub fn snapshot(&self, provider: &str) -> Option<QuotaSnapshot> {
+        let mut usage = self.lock();
+        let usage = usage.get_mut(provider)?;
+        usage.prune(Instant::now());
+        let requests = usage.requests.len() as u32;
+        let tokens = usage.tokens_used();
+        Some(QuotaSnapshot {
+            requests_per_minute: usage.requests_per_minute,
+            requests_remaining: usage
+                .requests_per_minute
+                .map(|limit| limit.saturating_sub(requests)),
+            tokens_per_minute: usage.tokens_per_minute,
+            tokens_remaining: usage
+                .tokens_per_minute
+                .map(|limit| limit.saturating_sub(tokens)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, rpm: Option<u32>, tpm: Option<u64>) -> ProviderConfig {
+        ProviderConfig {
+            requests_per_minute: rpm,
+            tokens_per_minute: tpm,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quotas_fill_and_recover() {
+        let quotas = ProviderQuotas::new(&[
+            provider("rpm", Some(2), None),
+            provider("tpm", None, Some(1000)),
+            provider("open", None, None),
+        ]);
+
+        quotas.record_request("rpm");
+        assert!(quotas.has_headroom("rpm", 0));
+        quotas.record_request("rpm");
+        assert!(!quotas.has_headroom("rpm", 0));
+
+        quotas.record_tokens("tpm", 800);
+        assert!(quotas.has_headroom("tpm", 200));
+        assert!(!quotas.has_headroom("tpm", 201));
+
+        quotas.record_request("open");
+        assert!(quotas.has_headroom("open", u64::MAX));
+        assert_eq!(quotas.snapshot("open"), None);
+
+        assert_eq!(
+            quotas.snapshot("tpm"),
+            Some(QuotaSnapshot {
+                requests_per_minute: None,
+                requests_remaining: None,
+                tokens_per_minute: Some(1000),
+                tokens_remaining: Some(200),
+            })
+        );
+
+        tokio::time::advance(WINDOW).await;
+        assert!(quotas.has_headroom("rpm", 0));
+        assert_eq!(quotas.snapshot("rpm").unwrap().requests_remaining, Some(2));
+        assert_eq!(quotas.snapshot("tpm").unwrap().tokens_remaining, Some(1000));
+    }
+
+    #[tokio::test]
+    async fn exhausted_providers_move_last() {
+        let configs = [
+            provider("a", Some(1), None),
+            provider("b", None, None),
+            provider("c", Some(1), None),
+        ];
+        let quotas = ProviderQuotas::new(&configs);
+        let candidates: Vec<SelectedProvider> = configs.iter().map(Into::into).collect();
+        quotas.record_request("a");
+        quotas.record_request("c");
+
+        let names = |list: Vec<SelectedProvider>| -> Vec<String> {
+            list.into_iter().map(|c| c.name).collect()
+        };
+        assert_eq!(
+            names(quotas.apply(candidates.clone(), 0, None)),
+            ["b", "a", "c"]
+        );
+
+        let probe_first = vec![
+            candidates[2].clone(),
+            candidates[0].clone(),
+            candidates[1].clone(),
+        ];
+        assert_eq!(
+            names(quotas.apply(probe_first, 0, Some("c"))),
+            ["c", "b", "a"]
+        );
+    }
+}
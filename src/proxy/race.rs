@@ -0,0 +1,149 @@
+//! `race_first_token` routing strategy.
+//!
+//! A streaming request is opened against the top two candidates at once.
+//! [`first_token`] reads each upstream SSE stream until its first content
+//! token; the handler keeps whichever stream gets there first and drops the
+//! other, which closes its connection. Bytes read while waiting are put back
+//! in front of the rest of the stream, so the winner's response is passed on
+//! (and billed) exactly as if it had been the only attempt. A stream that
+//! sends [`MAX_SCAN_BYTES`] without a token is treated as if it had
+//! produced one, so a provider padding its stream can't grow the buffer
+//! without bound.
+
+use bytes::Bytes;
+use futures::StreamExt;
+
+/// Strategy name selecting the race, in `default_strategy` or a policy rule.
+pub const STRATEGY: &str = "race_first_token";
+
+/// Bytes read from one stream while waiting for its first token before the
+/// race commits to that stream anyway.
+pub const MAX_SCAN_BYTES: usize = 64 * 1024;
+
+/// Read `response` up to its first content token (or the end of the stream,
+/// or [`MAX_SCAN_BYTES`]) and return it with the bytes read so far restored.
+pub async fn first_token(response: reqwest::Response) -> Result<reqwest::Response, reqwest::Error> {
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let mut stream = response.bytes_stream();
+
+    let mut scanner = TokenScanner::default();
+    let mut buffered: Vec<Bytes> = Vec::new();
+    let mut read = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let found = scanner.push(&chunk);
+        read += chunk.len();
+        buffered.push(chunk);
+        if found {
+            break;
+        }
+        if read >= MAX_SCAN_BYTES {
+            tracing::debug!(read, "No token within scan limit, committing to stream");
+            break;
+        }
+    }
+
+    let body = futures::stream::iter(buffered.into_iter().map(Ok)).chain(stream);
+    let mut rebuilt = axum::http::Response::new(reqwest::Body::wrap_stream(body));
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(reqwest::Response::from(rebuilt))
+}
+
+/// Line-buffered search for the first SSE event carrying content.
+#[derive(Default)]
+struct TokenScanner {
+    pending: Vec<u8>,
+}
+
+impl TokenScanner {
+    /// Feed a chunk; returns true once a content token has been seen.
+    fn push(&mut self, chunk: &[u8]) -> bool {
+        self.pending.extend_from_slice(chunk);
+        let mut found = false;
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            found |= is_content_line(&line);
+        }
+        found
+    }
+}
+
+/// Whether an SSE line is a `data:` event with a non-empty content or tool
+/// call delta, or the `[DONE]` terminator.
+fn is_content_line(line: &[u8]) -> bool {
+    let Ok(line) = std::str::from_utf8(line) else {
+        return false;
+    };
+    let Some(data) = line.trim().strip_prefix("data:") else {
+        return false;
+    };
+    let data = data.trim();
+    if data == "[DONE]" {
+        return true;
+    }
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else {
+        return false;
+    };
+    event["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|choice| {
+            let delta = &choice["delta"];
+            delta["content"].as_str().is_some_and(|c| !c.is_empty())
+                || delta["tool_calls"].is_array()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_only_deltas_are_not_content() {
+        let mut scanner = TokenScanner::default();
+        assert!(!scanner.push(
+            b"data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n"
+        ));
+        assert!(!scanner.push(b": keep-alive\n\n"));
+    }
+
+    #[test]
+    fn content_split_across_chunks_is_found() {
+        let mut scanner = TokenScanner::default();
+        assert!(!scanner.push(b"data: {\"choices\":[{\"index\":0,\"delta\":{\"con"));
+        assert!(scanner.push(b"tent\":\"Hi\"}}]}\n\n"));
+    }
+
+    #[test]
+    fn tool_calls_and_done_count_as_content() {
+        assert!(is_content_line(
+            b"data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[]}}]}"
+        ));
+        assert!(is_content_line(b"data: [DONE]"));
+    }
+
+    #[tokio::test]
+    async fn stream_without_tokens_is_committed_at_the_limit() {
+        let keep_alive = Bytes::from_static(b": keep-alive\n\n");
+        let chunks = MAX_SCAN_BYTES.div_ceil(keep_alive.len());
+        // Keep-alives up to the limit, then a stream that never sends more
+        let body = futures::stream::repeat(keep_alive)
+            .take(chunks)
+            .chain(futures::stream::pending())
+            .map(Ok::<_, std::io::Error>);
+        let response =
+            reqwest::Response::from(axum::http::Response::new(reqwest::Body::wrap_stream(body)));
+
+        let response =
+            tokio::time::timeout(std::time::Duration::from_secs(5), first_token(response))
+                .await
+                .expect("scan stopped at the limit")
+                .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
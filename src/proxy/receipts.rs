@@ -0,0 +1,331 @@
+//! Signed per-request receipts (`GET /v1/requests/{id}/receipt`).
+//!
+//! A receipt states what a completed request cost: the SHA-256 of the
+//! request body as received, the provider that served it, tokens,
+//! `cost_sats` and the log timestamp, plus the public key of the arbstr
+//! instance. `digest` is the SHA-256 of the `receipt` object serialized as
+//! compact JSON (fields in the order shown) and `signature` a BIP-340
+//! Schnorr signature of `digest` by `instance`, so billing between teams
+//! or customers can check receipts without trusting the database.
+//!
+//! A receipt is signed when its request completes and stored as signed; a
+//! stream's receipt waits for the stream's final token counts and cost.
+//! Fetching returns the stored receipt, so later changes to the request
+//! log don't alter what was signed. Without a configured key each process
+//! signs with a fresh random key, and its receipts can only be checked
+//! against that process's public key.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::nostr::{encode_hex, verify_digest, Keys};
+use super::server::AppState;
+use crate::config::Config;
+use crate::error::Error;
+use crate::storage::{self, DbWriter, RequestLog};
+
+/// Receipt format version.
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// Signature scheme named in every receipt.
+pub const ALGORITHM: &str = "bip340-sha256";
+
+/// The instance key receipts are signed with, and the receipts of streams
+/// awaiting their usage.
+pub struct ReceiptSigner {
+    keys: Keys,
+    /// Unsigned receipts by correlation ID, completed once the stream ends.
+    streams: Mutex<HashMap<String, Receipt>>,
+}
+
+impl Default for ReceiptSigner {
+    /// A signer with a random key.
+    fn default() -> Self {
+        Self::with_keys(Keys::random())
+    }
+}
+
+impl ReceiptSigner {
+    /// The signer for `config`: `[receipts] secret_key`, else
+    /// `[nostr] secret_key`, else a random key.
+    pub fn new(config: &Config) -> Self {
+        let secret = config
+            .receipts
+            .secret_key
+            .as_ref()
+            .or(config.nostr.as_ref().map(|n| &n.secret_key));
+        match secret.map(|key| Keys::from_hex(key.expose_secret())) {
+            Some(Ok(keys)) => Self::with_keys(keys),
+            Some(Err(e)) => {
+                tracing::warn!(error = %e, "Invalid receipt key, signing receipts with a random key");
+                Self::default()
+            }
+            None => {
+                tracing::warn!(
+                    "No [receipts] secret_key, signing receipts with a random key for this \
+                     process; they will not verify against the key of a restarted instance"
+                );
+                Self::default()
+            }
+        }
+    }
+
+    fn with_keys(keys: Keys) -> Self {
+        Self {
+            keys,
+            streams: Mutex::default(),
+        }
+    }
+
+    /// The instance public key (x-only, hex).
+    pub fn public_key(&self) -> String {
+        self.keys.public_key()
+    }
+
+    /// Sign `receipt`.
+    pub fn sign(&self, receipt: Receipt) -> SignedReceipt {
+        let digest = receipt.digest();
+        SignedReceipt {
+            signature: self.keys.sign_digest(&digest),
+            digest: encode_hex(&digest),
+            algorithm: ALGORITHM.to_string(),
+            receipt,
+        }
+    }
+
+    /// Sign the receipt of a completed request and queue it for storage.
+    pub fn issue(&self, writer: Option<&DbWriter>, receipt: Receipt) {
+        let Some(writer) = writer else {
+            return;
+        };
+        let signed = self.sign(receipt);
+        match serde_json::to_string(&signed) {
+            Ok(json) => writer.store_receipt(signed.receipt.request_id, json),
+            Err(e) => tracing::warn!(error = %e, "Failed to serialize receipt"),
+        }
+    }
+
+    /// Hold the receipt of the stream `correlation_id` until its usage
+    /// arrives (see [`ReceiptSigner::settle_stream`]).
+    pub fn await_stream(&self, correlation_id: &str, receipt: Receipt) {
+        self.streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(correlation_id.to_string(), receipt);
+    }
+
+    /// Complete and issue a finished stream's receipt, if it has one
+    /// waiting and the stream succeeded.
+    pub fn settle_stream(
+        &self,
+        writer: Option<&DbWriter>,
+        correlation_id: &str,
+        success: bool,
+        input_tokens: Option<u32>,
+        output_tokens: Option<u32>,
+        cost_sats: Option<f64>,
+    ) {
+        let pending = self
+            .streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(correlation_id);
+        if let Some(receipt) = pending.filter(|_| success) {
+            self.issue(
+                writer,
+                Receipt {
+                    input_tokens: input_tokens.map(i64::from),
+                    output_tokens: output_tokens.map(i64::from),
+                    cost_sats,
+                    ..receipt
+                },
+            );
+        }
+    }
+}
+
+/// What a completed request cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub version: u32,
+    /// Correlation ID (`x-arbstr-request-id`).
+    pub request_id: String,
+    /// SHA-256 (hex) of the request body; null when it was streamed through.
+    pub request_sha256: Option<String>,
+    pub model: String,
+    pub provider: Option<String>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cost_sats: Option<f64>,
+    /// When the request was logged (RFC 3339).
+    pub timestamp: String,
+    /// Public key of the signing arbstr instance (x-only, hex).
+    pub instance: String,
+}
+
+impl Receipt {
+    /// The receipt for the request logged as `log`, signed by `instance`.
+    pub fn from_log(log: &RequestLog, instance: String) -> Self {
+        Self {
+            version: RECEIPT_VERSION,
+            request_id: log.correlation_id.clone(),
+            request_sha256: log.request_sha256.clone(),
+            model: log.model.clone(),
+            provider: log.provider.clone(),
+            input_tokens: log.input_tokens.map(i64::from),
+            output_tokens: log.output_tokens.map(i64::from),
+            cost_sats: log.cost_sats,
+            timestamp: log.timestamp.clone(),
+            instance,
+        }
+    }
+
+    fn digest(&self) -> [u8; 32] {
+        let serialized = serde_json::to_vec(self).expect("receipt serializes");
+        Sha256::digest(&serialized).into()
+    }
+}
+
+/// Response for `GET /v1/requests/{id}/receipt`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReceipt {
+    pub receipt: Receipt,
+    /// SHA-256 (hex) of `receipt` as compact JSON.
+    pub digest: String,
+    /// BIP-340 signature (hex) of `digest` by `receipt.instance`.
+    pub signature: String,
+    pub algorithm: String,
+}
+
+impl SignedReceipt {
+    /// Whether `digest` matches the receipt and is signed by its instance.
+    pub fn verify(&self) -> bool {
+        let digest = self.receipt.digest();
+        encode_hex(&digest) == self.digest
+            && verify_digest(&self.receipt.instance, &digest, &self.signature)
+    }
+}
+
+/// Running SHA-256 of a request body, fed as the body is read.
+#[derive(Clone, Default)]
+pub(crate) struct BodyHash(Arc<Mutex<Sha256>>);
+
+impl BodyHash {
+    /// Wrap `request`'s body so every chunk read is hashed.
+    pub(crate) fn wrap(&self, request: axum::extract::Request) -> axum::extract::Request {
+        let hash = self.clone();
+        request.map(|body| {
+            Body::from_stream(body.into_data_stream().map(move |chunk| {
+                if let Ok(bytes) = &chunk {
+                    hash.0.lock().unwrap().update(bytes);
+                }
+                chunk
+            }))
+        })
+    }
+
+    /// Hex digest of everything read so far.
+    pub(crate) fn finish(&self) -> String {
+        encode_hex(&self.0.lock().unwrap().clone().finalize())
+    }
+}
+
+/// Handle GET /v1/requests/{id}/receipt -- the signed receipt of a
+/// completed request, as stored when it completed.
+///
+/// `id` is a correlation ID or a row id, as for GET /v1/requests/{id}.
+pub async fn receipt_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let mut row = storage::logs::query_detail(pool, &id).await?;
+    if let (None, Ok(row_id)) = (&row, id.parse::<i64>()) {
+        row = storage::logs::query_detail_by_id(pool, row_id).await?;
+    }
+    let row = row.ok_or_else(|| Error::NotFound(format!("Request '{}' not found", id)))?;
+    if !row.log.success {
+        return Err(Error::NotFound(format!(
+            "Request '{}' did not complete; receipts are only issued for completed requests",
+            id
+        )));
+    }
+
+    // A stream's receipt is stored once the stream ends
+    let stored = storage::receipts::get(pool, &row.correlation_id)
+        .await?
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "Receipt for request '{}' not found yet",
+                row.correlation_id
+            ))
+        })?;
+    let signed: SignedReceipt = serde_json::from_str(&stored)
+        .map_err(|e| Error::Internal(format!("Stored receipt is unreadable: {}", e)))?;
+    Ok(Json(signed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(signer: &ReceiptSigner) -> Receipt {
+        Receipt {
+            version: RECEIPT_VERSION,
+            request_id: "req-1".to_string(),
+            request_sha256: Some("ab".repeat(32)),
+            model: "gpt-4o".to_string(),
+            provider: Some("alpha".to_string()),
+            input_tokens: Some(10),
+            output_tokens: Some(20),
+            cost_sats: Some(0.35),
+            timestamp: "2026-10-01T00:00:00Z".to_string(),
+            instance: signer.public_key(),
+        }
+    }
+
+    #[test]
+    fn signed_receipts_verify_and_detect_tampering() {
+        let signer = ReceiptSigner::default();
+        let signed = signer.sign(receipt(&signer));
+        assert!(signed.verify());
+        assert_eq!(signed.algorithm, ALGORITHM);
+
+        let mut tampered = signed.clone();
+        tampered.receipt.cost_sats = Some(0.01);
+        assert!(!tampered.verify());
+
+        // Re-signed by another key but still claiming the first instance
+        let other = ReceiptSigner::default();
+        let mut forged = other.sign(tampered.receipt.clone());
+        forged.receipt.instance = signer.public_key();
+        assert!(!forged.verify());
+    }
+
+    #[tokio::test]
+    async fn body_hash_covers_the_body_read() {
+        use axum::extract::FromRequest;
+
+        let hash = BodyHash::default();
+        let request = axum::extract::Request::new(Body::from("{\"model\":\"gpt-4o\"}"));
+        let bytes = axum::body::Bytes::from_request(hash.wrap(request), &())
+            .await
+            .unwrap();
+        assert_eq!(bytes.as_ref(), b"{\"model\":\"gpt-4o\"}");
+        assert_eq!(hash.finish(), encode_hex(&Sha256::digest(&bytes)));
+    }
+}
@@ -0,0 +1,315 @@
+//! In-memory ring buffer of the most recent requests and `/v1/requests/recent`.
+//!
+//! Every request logged by the handlers is also recorded here, whether or not
+//! a database is configured, so live traffic can be inspected without any DB
+//! reads. Writers claim a slot with an atomic counter and lock only that
+//! slot, so concurrent requests do not contend with each other.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use crate::storage::logging::RequestLog;
+
+/// Requests kept by default.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// Entries returned by `/v1/requests/recent` when no `limit` is given.
+const DEFAULT_LIMIT: usize = 100;
+
+/// Summary of one request.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RecentRequest {
+    pub correlation_id: String,
+    pub timestamp: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    pub streaming: bool,
+    /// True for a stream whose response is still being relayed.
+    pub in_progress: bool,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    pub latency_ms: i64,
+    /// Streams only: time until the last chunk, once complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_duration_ms: Option<i64>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cost_sats: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl From<&RequestLog> for RecentRequest {
+    fn from(log: &RequestLog) -> Self {
+        Self {
+            correlation_id: log.correlation_id.clone(),
+            timestamp: log.timestamp.clone(),
+            model: log.model.clone(),
+            provider: log.provider.clone(),
+            policy: log.policy.clone(),
+            tier: log.tier.clone(),
+            streaming: log.streaming,
+            in_progress: log.streaming && log.success,
+            success: log.success,
+            error_status: log.error_status,
+            error_type: log.error_type.clone(),
+            latency_ms: log.latency_ms,
+            stream_duration_ms: None,
+            input_tokens: log.input_tokens,
+            output_tokens: log.output_tokens,
+            cost_sats: log.cost_sats,
+            finish_reason: log.finish_reason.clone(),
+            tags: log.tags.iter().cloned().collect(),
+        }
+    }
+}
+
+/// What a completed stream adds to its entry.
+#[derive(Debug, Clone)]
+pub struct StreamCompletion {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cost_sats: Option<f64>,
+    pub duration_ms: i64,
+    pub success: bool,
+    pub finish_reason: Option<String>,
+}
+
+/// A ring slot: the entry and the sequence number it was written with.
+type Slot = Mutex<Option<(u64, RecentRequest)>>;
+
+/// Fixed-size ring of the last requests, newest overwriting oldest.
+#[derive(Debug)]
+pub struct RecentRequests {
+    /// Sequence numbers let readers tell a current entry from one left over
+    /// from a previous lap.
+    slots: Box<[Slot]>,
+    next: AtomicU64,
+}
+
+impl Default for RecentRequests {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RecentRequests {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity.max(1)).map(|_| Mutex::new(None)).collect(),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Requests recorded since startup, including those since overwritten.
+    pub fn recorded(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    pub fn record(&self, entry: RecentRequest) {
+        let seq = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut slot = self.slot(seq).lock().unwrap_or_else(|e| e.into_inner());
+        // A writer a full lap ahead may have got here first
+        if slot.as_ref().is_none_or(|(current, _)| *current < seq) {
+            *slot = Some((seq, entry));
+        }
+    }
+
+    /// Fill in a stream's entry once it completes. No-op if the entry has
+    /// already been overwritten.
+    pub fn complete_stream(&self, correlation_id: &str, completion: StreamCompletion) {
+        self.for_each_newest_first(|entry| {
+            if entry.correlation_id != correlation_id {
+                return true;
+            }
+            entry.in_progress = false;
+            entry.success = completion.success;
+            entry.stream_duration_ms = Some(completion.duration_ms);
+            entry.input_tokens = completion.input_tokens;
+            entry.output_tokens = completion.output_tokens;
+            entry.cost_sats = completion.cost_sats;
+            entry.finish_reason = completion.finish_reason.clone();
+            false
+        });
+    }
+
+    /// Up to `limit` entries matching `filter`, newest first.
+    pub fn snapshot(
+        &self,
+        limit: usize,
+        filter: impl Fn(&RecentRequest) -> bool,
+    ) -> Vec<RecentRequest> {
+        let mut out = Vec::new();
+        if limit == 0 {
+            return out;
+        }
+        self.for_each_newest_first(|entry| {
+            if filter(entry) {
+                out.push(entry.clone());
+            }
+            out.len() < limit
+        });
+        out
+    }
+
+    fn slot(&self, seq: u64) -> &Slot {
+        &self.slots[(seq % self.slots.len() as u64) as usize]
+    }
+
+    /// Visit live entries from newest to oldest until `visit` returns false.
+    fn for_each_newest_first(&self, mut visit: impl FnMut(&mut RecentRequest) -> bool) {
+        let next = self.next.load(Ordering::Relaxed);
+        let oldest = next.saturating_sub(self.slots.len() as u64);
+        for seq in (oldest..next).rev() {
+            let mut slot = self.slot(seq).lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((current, entry)) = slot.as_mut() {
+                if *current == seq && !visit(entry) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Query parameters for GET /v1/requests/recent.
+#[derive(Debug, Deserialize)]
+pub struct RecentQuery {
+    /// Default 100, capped at the buffer capacity.
+    pub limit: Option<usize>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub success: Option<bool>,
+}
+
+/// Response for GET /v1/requests/recent.
+#[derive(Debug, Serialize)]
+pub struct RecentResponse {
+    pub data: Vec<RecentRequest>,
+    pub capacity: usize,
+    pub recorded: u64,
+}
+
+/// Handle GET /v1/requests/recent: the in-memory request buffer, newest first.
+pub async fn recent_handler(
+    State(state): State<AppState>,
+    Query(query): Query<RecentQuery>,
+) -> impl IntoResponse {
+    let recent = &state.recent;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(recent.capacity());
+    let data = recent.snapshot(limit, |entry| {
+        query
+            .model
+            .as_ref()
+            .is_none_or(|m| entry.model.eq_ignore_ascii_case(m))
+            && query.provider.as_ref().is_none_or(|p| {
+                entry
+                    .provider
+                    .as_ref()
+                    .is_some_and(|ep| ep.eq_ignore_ascii_case(p))
+            })
+            && query.success.is_none_or(|s| entry.success == s)
+    });
+    Json(RecentResponse {
+        data,
+        capacity: recent.capacity(),
+        recorded: recent.recorded(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, model: &str) -> RecentRequest {
+        RecentRequest {
+            correlation_id: id.to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            model: model.to_string(),
+            provider: Some("alpha".to_string()),
+            policy: None,
+            tier: None,
+            streaming: true,
+            in_progress: true,
+            success: true,
+            error_status: None,
+            error_type: None,
+            latency_ms: 5,
+            stream_duration_ms: None,
+            input_tokens: None,
+            output_tokens: None,
+            cost_sats: None,
+            finish_reason: None,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    fn ids(entries: &[RecentRequest]) -> Vec<&str> {
+        entries.iter().map(|e| e.correlation_id.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_newest_entries_up_to_capacity() {
+        let ring = RecentRequests::new(3);
+        for i in 0..5 {
+            ring.record(entry(&i.to_string(), "gpt-4o"));
+        }
+        assert_eq!(ring.recorded(), 5);
+        assert_eq!(ids(&ring.snapshot(10, |_| true)), vec!["4", "3", "2"]);
+        assert_eq!(ids(&ring.snapshot(1, |_| true)), vec!["4"]);
+    }
+
+    #[test]
+    fn snapshot_filters_before_limiting() {
+        let ring = RecentRequests::new(10);
+        ring.record(entry("a", "gpt-4o"));
+        ring.record(entry("b", "claude"));
+        ring.record(entry("c", "gpt-4o"));
+        let only_gpt = ring.snapshot(10, |e| e.model == "gpt-4o");
+        assert_eq!(ids(&only_gpt), vec!["c", "a"]);
+    }
+
+    #[test]
+    fn stream_completion_updates_entry() {
+        let ring = RecentRequests::new(4);
+        ring.record(entry("s1", "gpt-4o"));
+        ring.complete_stream(
+            "s1",
+            StreamCompletion {
+                input_tokens: Some(10),
+                output_tokens: Some(20),
+                cost_sats: Some(1.5),
+                duration_ms: 300,
+                success: true,
+                finish_reason: Some("stop".to_string()),
+            },
+        );
+        let e = &ring.snapshot(1, |_| true)[0];
+        assert!(!e.in_progress);
+        assert_eq!(e.output_tokens, Some(20));
+        assert_eq!(e.stream_duration_ms, Some(300));
+        assert_eq!(e.finish_reason.as_deref(), Some("stop"));
+    }
+}
@@ -0,0 +1,447 @@
+//! Provider interaction recording and replay (`[recording]`).
+//!
+//! In record mode requests go to providers as usual, and each completed
+//! exchange is appended to `<dir>/<provider>.jsonl`: the request body, the
+//! response status and headers, and the body chunks with their timing. In
+//! replay mode those files answer requests without touching the network,
+//! so tests and `arbstr bench` see realistic provider behavior
+//! deterministically.
+//!
+//! Replayed interactions are matched by provider and a SHA-256 of the
+//! request body; a request recorded several times is answered with each
+//! recorded response in turn, cycling. Requests whose bodies are streamed
+//! to the provider (`server.stream_body_threshold_bytes`) are neither
+//! recorded nor replayable.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{RecordingConfig, RecordingMode};
+use crate::error::ProviderErrorKind;
+
+/// Response headers never written to recordings. Content encoding and
+/// framing are dropped because recorded bodies are already decoded.
+const DROPPED_HEADERS: &[&str] = &[
+    "set-cookie",
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+];
+
+/// One recorded provider exchange, a line in `<provider>.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    recorded_at: String,
+    provider: String,
+    request_sha256: String,
+    /// The request body, as JSON when it parses.
+    request: serde_json::Value,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    /// Time from sending the request to response headers.
+    first_byte_ms: u64,
+    chunks: Vec<Chunk>,
+}
+
+/// A response body chunk, with its time since the request was sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    offset_ms: u64,
+    data: String,
+}
+
+/// Recorded interactions for one provider and request body.
+#[derive(Debug)]
+struct Tape {
+    interactions: Vec<Interaction>,
+    next: AtomicUsize,
+}
+
+/// Why an upstream request failed.
+#[derive(Debug)]
+pub enum SendError {
+    /// The provider could not be reached or the request was invalid.
+    Upstream(reqwest::Error),
+    /// Replay mode has no recording for the request.
+    NotRecorded(String),
+}
+
+impl SendError {
+    /// Failure kind, for error codes and circuit breakers. A replay miss
+    /// counts as an unreachable provider.
+    pub fn kind(&self) -> ProviderErrorKind {
+        match self {
+            Self::Upstream(e) => ProviderErrorKind::from_reqwest(e),
+            Self::NotRecorded(_) => ProviderErrorKind::Connect,
+        }
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Upstream(e) => e.fmt(f),
+            Self::NotRecorded(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Appends interactions to per-provider files.
+#[derive(Debug)]
+struct TapeWriter {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl TapeWriter {
+    async fn append(self: Arc<Self>, interaction: Interaction) {
+        let provider = interaction.provider.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            use std::io::Write;
+            let mut line = serde_json::to_vec(&interaction)?;
+            line.push(b'\n');
+            let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(tape_path(&self.dir, &interaction.provider))?
+                .write_all(&line)
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                tracing::warn!(provider = %provider, error = %e, "Failed to record provider interaction")
+            }
+            Err(e) => {
+                tracing::warn!(provider = %provider, error = %e, "Recording task failed")
+            }
+        }
+    }
+}
+
+/// `<dir>/<provider>.jsonl`, with characters unsafe in file names replaced.
+fn tape_path(dir: &Path, provider: &str) -> PathBuf {
+    let name: String = provider
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{}.jsonl", name))
+}
+
+fn body_sha256(body: &[u8]) -> String {
+    Sha256::digest(body)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn tape_key(provider: &str, sha256: &str) -> String {
+    format!("{}:{}", provider, sha256)
+}
+
+/// Records or replays provider interactions. Inert (requests are sent
+/// unchanged) without `[recording]`.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    mode: Option<RecordingMode>,
+    realtime: bool,
+    writer: Option<Arc<TapeWriter>>,
+    tapes: HashMap<String, Tape>,
+}
+
+impl Recorder {
+    /// Create a recorder. Record mode creates `dir`; replay mode loads
+    /// every `*.jsonl` file in it.
+    pub fn new(config: Option<&RecordingConfig>) -> std::io::Result<Self> {
+        let Some(config) = config else {
+            return Ok(Self::default());
+        };
+        let dir = PathBuf::from(&config.dir);
+        let mut recorder = Self {
+            mode: Some(config.mode),
+            realtime: config.realtime,
+            ..Default::default()
+        };
+        match config.mode {
+            RecordingMode::Record => {
+                std::fs::create_dir_all(&dir)?;
+                tracing::warn!(dir = %dir.display(), "Recording provider interactions");
+                recorder.writer = Some(Arc::new(TapeWriter {
+                    dir,
+                    lock: Mutex::new(()),
+                }));
+            }
+            RecordingMode::Replay => {
+                recorder.tapes = load_tapes(&dir)?;
+                let count: usize = recorder.tapes.values().map(|t| t.interactions.len()).sum();
+                tracing::warn!(
+                    dir = %dir.display(),
+                    interactions = count,
+                    "Replaying recorded provider interactions; providers will not be contacted"
+                );
+            }
+        }
+        Ok(recorder)
+    }
+
+    /// Send `request` to `provider`, recording the exchange or answering
+    /// it from a recording depending on the mode.
+    pub async fn send(
+        &self,
+        provider: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, SendError> {
+        let (client, request) = request.build_split();
+        let request = request.map_err(SendError::Upstream)?;
+        let Some(mode) = self.mode else {
+            return client.execute(request).await.map_err(SendError::Upstream);
+        };
+        let body = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| b.to_vec());
+
+        match (mode, body) {
+            (RecordingMode::Replay, Some(body)) => self.replay(provider, &body).await,
+            (RecordingMode::Replay, None) => Err(SendError::NotRecorded(
+                "streamed request bodies cannot be replayed".to_string(),
+            )),
+            (RecordingMode::Record, Some(body)) => {
+                let start = Instant::now();
+                let response = client.execute(request).await.map_err(SendError::Upstream)?;
+                Ok(self.record(provider, &body, start, response))
+            }
+            (RecordingMode::Record, None) => {
+                client.execute(request).await.map_err(SendError::Upstream)
+            }
+        }
+    }
+
+    /// Pass `response` through, appending the exchange once its body has
+    /// been read to the end.
+    fn record(
+        &self,
+        provider: &str,
+        body: &[u8],
+        start: Instant,
+        response: reqwest::Response,
+    ) -> reqwest::Response {
+        let Some(writer) = self.writer.clone() else {
+            return response;
+        };
+        let mut interaction = Interaction {
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            provider: provider.to_string(),
+            request_sha256: body_sha256(body),
+            request: serde_json::from_slice(body)
+                .unwrap_or_else(|_| String::from_utf8_lossy(body).into()),
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .filter(|(name, _)| !DROPPED_HEADERS.contains(&name.as_str()))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            first_byte_ms: start.elapsed().as_millis() as u64,
+            chunks: Vec::new(),
+        };
+
+        let mut builder = axum::http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        for (name, value) in response.headers() {
+            builder = builder.header(name, value);
+        }
+
+        let chunks: Arc<Mutex<Option<Vec<Chunk>>>> = Arc::new(Mutex::new(Some(Vec::new())));
+        let tap = chunks.clone();
+        let observed = response.bytes_stream().map(move |chunk| {
+            let mut tap = tap.lock().unwrap_or_else(|e| e.into_inner());
+            match &chunk {
+                Ok(bytes) => {
+                    if let Some(recorded) = tap.as_mut() {
+                        recorded.push(Chunk {
+                            offset_ms: start.elapsed().as_millis() as u64,
+                            data: String::from_utf8_lossy(bytes).into_owned(),
+                        });
+                    }
+                }
+                // A failed body is not a replayable response
+                Err(_) => *tap = None,
+            }
+            chunk
+        });
+        let finish = futures::stream::once(async move {
+            let recorded = chunks.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(recorded) = recorded {
+                interaction.chunks = recorded;
+                writer.append(interaction).await;
+            }
+        })
+        .filter_map(|()| async { None });
+
+        let body = reqwest::Body::wrap_stream(observed.chain(finish));
+        builder
+            .body(body)
+            .map(reqwest::Response::from)
+            .expect("headers copied from a valid response")
+    }
+
+    /// Answer from the next recorded interaction for this request.
+    async fn replay(&self, provider: &str, body: &[u8]) -> Result<reqwest::Response, SendError> {
+        let Some(tape) = self.tapes.get(&tape_key(provider, &body_sha256(body))) else {
+            return Err(SendError::NotRecorded(format!(
+                "no recorded interaction matches this request to '{}'",
+                provider
+            )));
+        };
+        let turn = tape.next.fetch_add(1, Ordering::Relaxed) % tape.interactions.len();
+        let interaction = tape.interactions[turn].clone();
+
+        if self.realtime {
+            tokio::time::sleep(Duration::from_millis(interaction.first_byte_ms)).await;
+        }
+        let mut builder = axum::http::Response::builder().status(interaction.status);
+        for (name, value) in &interaction.headers {
+            builder = builder.header(name, value);
+        }
+
+        let realtime = self.realtime;
+        let mut elapsed = interaction.first_byte_ms;
+        let chunks = futures::stream::iter(interaction.chunks).then(move |chunk| {
+            let wait = chunk.offset_ms.saturating_sub(elapsed);
+            elapsed = elapsed.max(chunk.offset_ms);
+            async move {
+                if realtime && wait > 0 {
+                    tokio::time::sleep(Duration::from_millis(wait)).await;
+                }
+                Ok::<_, std::io::Error>(Bytes::from(chunk.data))
+            }
+        });
+        builder
+            .body(reqwest::Body::wrap_stream(chunks))
+            .map(reqwest::Response::from)
+            .map_err(|e| SendError::NotRecorded(format!("unusable recording: {}", e)))
+    }
+}
+
+/// Load every `*.jsonl` recording in `dir`, keyed by provider and request.
+fn load_tapes(dir: &Path) -> std::io::Result<HashMap<String, Tape>> {
+    let mut interactions: HashMap<String, Vec<Interaction>> = HashMap::new();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let content = std::fs::read_to_string(&path)?;
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let interaction: Interaction = serde_json::from_str(line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path.display(), i + 1, e),
+                )
+            })?;
+            interactions
+                .entry(tape_key(&interaction.provider, &interaction.request_sha256))
+                .or_default()
+                .push(interaction);
+        }
+    }
+    Ok(interactions
+        .into_iter()
+        .map(|(key, interactions)| {
+            (
+                key,
+                Tape {
+                    interactions,
+                    next: AtomicUsize::new(0),
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tape_paths_are_safe_file_names() {
+        let dir = Path::new("/tmp/tapes");
+        assert_eq!(tape_path(dir, "openai-prod"), dir.join("openai-prod.jsonl"));
+        assert_eq!(tape_path(dir, "../a b"), dir.join(".._a_b.jsonl"));
+    }
+
+    #[tokio::test]
+    async fn replay_cycles_through_recorded_responses() {
+        let dir = std::env::temp_dir().join(format!("arbstr-tapes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let body = br#"{"model":"gpt-4o"}"#;
+        let lines: Vec<String> = [(500, "busy"), (200, "ok")]
+            .iter()
+            .map(|(status, data)| {
+                serde_json::to_string(&Interaction {
+                    recorded_at: "2026-01-01T00:00:00Z".to_string(),
+                    provider: "alpha".to_string(),
+                    request_sha256: body_sha256(body),
+                    request: serde_json::json!({"model": "gpt-4o"}),
+                    status: *status,
+                    headers: BTreeMap::new(),
+                    first_byte_ms: 0,
+                    chunks: vec![Chunk {
+                        offset_ms: 0,
+                        data: data.to_string(),
+                    }],
+                })
+                .unwrap()
+            })
+            .collect();
+        std::fs::write(dir.join("alpha.jsonl"), lines.join("\n")).unwrap();
+
+        let recorder = Recorder::new(Some(&RecordingConfig {
+            mode: RecordingMode::Replay,
+            dir: dir.display().to_string(),
+            realtime: false,
+        }))
+        .unwrap();
+        let replay = |provider: &'static str| {
+            let request = reqwest::Client::new()
+                .post("http://replay.invalid/v1/chat/completions")
+                .body(body.to_vec());
+            recorder.send(provider, request)
+        };
+
+        for expected in [(500, "busy"), (200, "ok"), (500, "busy")] {
+            let response = replay("alpha").await.unwrap();
+            assert_eq!(response.status().as_u16(), expected.0);
+            assert_eq!(response.text().await.unwrap(), expected.1);
+        }
+        assert!(matches!(
+            replay("beta").await,
+            Err(SendError::NotRecorded(_))
+        ));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,279 @@
+//! Retry and fallback analytics endpoint.
+//!
+//! Reports how many requests needed more than one upstream attempt, how
+//! often falling back to another provider rescued a request, and how much
+//! latency retried requests carry over first-attempt successes. Failed
+//! attempts come from the `request_attempts` table, broken down by the
+//! provider that failed them.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use super::stats::resolve_time_range;
+use crate::error::Error;
+use crate::storage;
+use crate::storage::stats::{AttemptErrorRow, ReliabilityRow};
+
+/// Query parameters for GET /v1/stats/reliability.
+#[derive(Debug, Deserialize)]
+pub struct ReliabilityQuery {
+    pub range: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub model: Option<String>,
+    /// Tag filter in `key=value` form.
+    pub tag: Option<String>,
+}
+
+/// Response for GET /v1/stats/reliability.
+#[derive(Debug, Serialize)]
+pub struct ReliabilityResponse {
+    pub since: String,
+    pub until: String,
+    pub totals: ReliabilitySummary,
+    /// One entry per provider, most failed attempts first.
+    pub providers: Vec<ProviderReliability>,
+}
+
+/// Retry and fallback figures for a set of requests.
+#[derive(Debug, Default, Serialize)]
+pub struct ReliabilitySummary {
+    pub requests: i64,
+    /// Requests with at least one failed attempt.
+    pub retried: i64,
+    /// `retried / requests` (0 when there are no requests).
+    pub retry_rate: f64,
+    /// Requests with a failed attempt on another provider.
+    pub fallbacks: i64,
+    pub fallback_successes: i64,
+    /// `fallback_successes / fallbacks` (null when nothing fell back).
+    pub fallback_success_rate: Option<f64>,
+    /// Mean latency of successful retried requests minus that of
+    /// first-attempt successes (null unless both exist).
+    pub avg_extra_latency_ms: Option<f64>,
+    /// Failed upstream attempts, including those on other providers.
+    pub failed_attempts: i64,
+    /// Failed attempts by error type (e.g. `5xx`, `timeout`).
+    pub attempt_errors: BTreeMap<String, i64>,
+    #[serde(skip)]
+    latency: LatencySums,
+}
+
+/// Latency sums behind `avg_extra_latency_ms`.
+#[derive(Debug, Default, Clone, Copy)]
+struct LatencySums {
+    retried_ms: i64,
+    retried: i64,
+    direct_ms: i64,
+    direct: i64,
+}
+
+impl ReliabilitySummary {
+    fn add_requests(&mut self, row: &ReliabilityRow) {
+        self.requests += row.requests;
+        self.retried += row.retried;
+        self.fallbacks += row.fallbacks;
+        self.fallback_successes += row.fallback_successes;
+        self.latency.retried_ms += row.retried_latency_ms;
+        self.latency.retried += row.retried_successes;
+        self.latency.direct_ms += row.direct_latency_ms;
+        self.latency.direct += row.direct_successes;
+
+        self.retry_rate = self.retried as f64 / self.requests as f64;
+        self.fallback_success_rate =
+            (self.fallbacks > 0).then(|| self.fallback_successes as f64 / self.fallbacks as f64);
+        let LatencySums {
+            retried_ms,
+            retried,
+            direct_ms,
+            direct,
+        } = self.latency;
+        self.avg_extra_latency_ms = (retried > 0 && direct > 0)
+            .then(|| retried_ms as f64 / retried as f64 - direct_ms as f64 / direct as f64);
+    }
+
+    fn add_attempts(&mut self, error_type: &str, count: i64) {
+        self.failed_attempts += count;
+        *self
+            .attempt_errors
+            .entry(error_type.to_string())
+            .or_insert(0) += count;
+    }
+}
+
+/// Reliability figures for one provider. Request counts cover requests
+/// logged against the provider (the one that served them, or the last one
+/// tried); failed attempts are those the provider itself returned.
+#[derive(Debug, Serialize)]
+pub struct ProviderReliability {
+    pub provider: String,
+    #[serde(flatten)]
+    pub summary: ReliabilitySummary,
+}
+
+/// Fold per-provider request and attempt rows into totals and provider
+/// entries.
+///
+/// Providers are ordered by failed attempts (descending), then retry rate
+/// (descending), then name.
+pub fn summarize(
+    requests: &[ReliabilityRow],
+    attempts: &[AttemptErrorRow],
+) -> (ReliabilitySummary, Vec<ProviderReliability>) {
+    let mut totals = ReliabilitySummary::default();
+    let mut providers: BTreeMap<String, ReliabilitySummary> = BTreeMap::new();
+
+    for row in requests {
+        totals.add_requests(row);
+        providers
+            .entry(row.provider.clone())
+            .or_default()
+            .add_requests(row);
+    }
+    for row in attempts {
+        totals.add_attempts(&row.error_type, row.count);
+        providers
+            .entry(row.provider.clone())
+            .or_default()
+            .add_attempts(&row.error_type, row.count);
+    }
+
+    let mut providers: Vec<ProviderReliability> = providers
+        .into_iter()
+        .map(|(provider, summary)| ProviderReliability { provider, summary })
+        .collect();
+    providers.sort_by(|a, b| {
+        b.summary
+            .failed_attempts
+            .cmp(&a.summary.failed_attempts)
+            .then(b.summary.retry_rate.total_cmp(&a.summary.retry_rate))
+            .then_with(|| a.provider.cmp(&b.provider))
+    });
+
+    (totals, providers)
+}
+
+/// Handle GET /v1/stats/reliability -- retry and fallback analytics.
+pub async fn reliability_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ReliabilityQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let (since_dt, until_dt) = resolve_time_range(
+        params.range.as_deref(),
+        params.since.as_deref(),
+        params.until.as_deref(),
+    )?;
+    let since_str = since_dt.to_rfc3339();
+    let until_str = until_dt.to_rfc3339();
+
+    if let Some(ref model_filter) = params.model {
+        super::validation::validate_model_filter(&state.config, pool, model_filter).await?;
+    }
+
+    let tag_filter = params
+        .tag
+        .as_deref()
+        .map(super::tags::parse_tag_filter)
+        .transpose()?;
+    let tag = tag_filter.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+
+    let model = params.model.as_deref();
+    let requests =
+        storage::stats::query_reliability(pool, &since_str, &until_str, model, tag).await?;
+    let attempts =
+        storage::stats::query_attempt_errors(pool, &since_str, &until_str, model, tag).await?;
+    let (totals, providers) = summarize(&requests, &attempts);
+
+    Ok(Json(ReliabilityResponse {
+        since: since_str,
+        until: until_str,
+        totals,
+        providers,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requests(
+        provider: &str,
+        requests: i64,
+        retried: i64,
+        fallbacks: (i64, i64),
+        retried_latency: (i64, i64),
+        direct_latency: (i64, i64),
+    ) -> ReliabilityRow {
+        ReliabilityRow {
+            provider: provider.to_string(),
+            requests,
+            retried,
+            fallbacks: fallbacks.0,
+            fallback_successes: fallbacks.1,
+            retried_latency_ms: retried_latency.0,
+            retried_successes: retried_latency.1,
+            direct_latency_ms: direct_latency.0,
+            direct_successes: direct_latency.1,
+        }
+    }
+
+    fn attempts(provider: &str, error_type: &str, count: i64) -> AttemptErrorRow {
+        AttemptErrorRow {
+            provider: provider.to_string(),
+            error_type: error_type.to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn summarize_computes_rates_and_orders_by_failed_attempts() {
+        let (totals, providers) = summarize(
+            &[
+                requests("alpha", 8, 2, (0, 0), (1000, 2), (600, 6)),
+                requests("beta", 4, 3, (3, 2), (1200, 2), (100, 1)),
+            ],
+            &[
+                attempts("alpha", "5xx", 5),
+                attempts("alpha", "timeout", 1),
+                attempts("gamma", "rate_limit", 2),
+            ],
+        );
+
+        assert_eq!(totals.requests, 12);
+        assert_eq!(totals.retried, 5);
+        assert!((totals.retry_rate - 5.0 / 12.0).abs() < 1e-9);
+        assert_eq!(totals.fallback_success_rate, Some(2.0 / 3.0));
+        // (2200 / 4) - (700 / 7)
+        assert_eq!(totals.avg_extra_latency_ms, Some(450.0));
+        assert_eq!(totals.failed_attempts, 8);
+        assert_eq!(totals.attempt_errors["5xx"], 5);
+
+        let order: Vec<&str> = providers.iter().map(|p| p.provider.as_str()).collect();
+        assert_eq!(order, vec!["alpha", "gamma", "beta"]);
+        assert_eq!(providers[0].summary.fallback_success_rate, None);
+        assert_eq!(providers[0].summary.avg_extra_latency_ms, Some(400.0));
+        assert_eq!(providers[1].summary.requests, 0);
+        assert_eq!(providers[2].summary.avg_extra_latency_ms, Some(500.0));
+    }
+
+    #[test]
+    fn summarize_empty() {
+        let (totals, providers) = summarize(&[], &[]);
+        assert_eq!(totals.requests, 0);
+        assert_eq!(totals.retry_rate, 0.0);
+        assert_eq!(totals.avg_extra_latency_ms, None);
+        assert!(providers.is_empty());
+    }
+}
@@ -0,0 +1,567 @@
+//! Scheduled cost and reliability reports.
+//!
+//! When `[reports]` is configured, a background task wakes at the configured
+//! hour (daily, or on a given weekday for weekly reports), summarises the
+//! previous period from the request log, and delivers it to a webhook as
+//! JSON and/or to a local SMTP relay as plain text.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::config::{Config, ReportSchedule, ReportsConfig, SmtpConfig};
+use crate::storage;
+
+/// Number of models listed in the "top models" section.
+const TOP_MODELS: usize = 5;
+
+/// Minimum errors in a period before a provider can be flagged as spiking.
+const SPIKE_MIN_ERRORS: i64 = 5;
+
+/// Minimum error rate before a provider can be flagged as spiking.
+const SPIKE_MIN_RATE: f64 = 0.1;
+
+/// Timeout for a single delivery attempt (webhook or SMTP).
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A rendered cost and reliability summary for one period.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub schedule: String,
+    pub since: String,
+    pub until: String,
+    pub totals: ReportTotals,
+    pub top_models: Vec<ModelSpend>,
+    pub providers: Vec<ProviderSummary>,
+    /// Estimated sats saved versus routing every request to the most
+    /// expensive configured provider for its model.
+    pub savings_sats: f64,
+}
+
+/// Request and cost totals for the period.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportTotals {
+    pub requests: i64,
+    pub success: i64,
+    pub errors: i64,
+    pub cost_sats: f64,
+}
+
+/// Spend attributed to a single model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelSpend {
+    pub model: String,
+    pub requests: i64,
+    pub cost_sats: f64,
+}
+
+/// Spend and reliability for a single provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSummary {
+    pub provider: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub cost_sats: f64,
+    pub error_rate: f64,
+    /// Error rate over the preceding period of equal length.
+    pub previous_error_rate: f64,
+    /// Error rate jumped relative to the preceding period.
+    pub error_spike: bool,
+}
+
+/// Length of the period covered by a report.
+fn period_length(schedule: ReportSchedule) -> chrono::Duration {
+    match schedule {
+        ReportSchedule::Daily => chrono::Duration::days(1),
+        ReportSchedule::Weekly => chrono::Duration::days(7),
+    }
+}
+
+/// Compute the next time a report should be sent, strictly after `now`.
+pub fn next_run(now: DateTime<Utc>, reports: &ReportsConfig) -> DateTime<Utc> {
+    let mut candidate = Utc
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), reports.hour_utc, 0, 0)
+        .unwrap();
+    loop {
+        let day_matches = match reports.schedule {
+            ReportSchedule::Daily => true,
+            ReportSchedule::Weekly => candidate.weekday() == reports.weekday,
+        };
+        if day_matches && candidate > now {
+            return candidate;
+        }
+        candidate += chrono::Duration::days(1);
+    }
+}
+
+/// Whether a provider's error rate counts as a spike.
+fn is_error_spike(errors: i64, rate: f64, previous_rate: f64) -> bool {
+    errors >= SPIKE_MIN_ERRORS && rate >= SPIKE_MIN_RATE && rate >= 2.0 * previous_rate
+}
+
+/// Estimate savings versus the most expensive configured provider per model.
+///
+/// Uses token totals and successful request counts from the period. Models
+/// with no configured provider contribute nothing.
+fn estimate_savings(config: &Config, models: &[storage::stats::ModelRow]) -> f64 {
+    models
+        .iter()
+        .map(|m| {
+            let worst = config
+                .providers
+                .iter()
+                .filter(|p| p.models.iter().any(|pm| pm == &m.model))
+                .map(|p| {
+                    let cost = (m.total_input_tokens * p.input_rate
+                        + m.total_output_tokens * p.output_rate)
+                        / 1000.0
+                        + m.success_count as f64 * p.base_fee;
+                    cost.max(m.success_count as f64 * p.min_charge_sats)
+                })
+                .reduce(f64::max);
+            worst.map_or(0.0, |w| (w - m.total_cost_sats).max(0.0))
+        })
+        .sum()
+}
+
+/// Build a report for the period ending at `until`.
+pub async fn build_report(
+    pool: &SqlitePool,
+    config: &Config,
+    schedule: ReportSchedule,
+    until: DateTime<Utc>,
+) -> Result<Report, sqlx::Error> {
+    let length = period_length(schedule);
+    let since = until - length;
+    let previous_since = since - length;
+
+    let until_str = until.to_rfc3339();
+    let since_str = since.to_rfc3339();
+    let previous_since_str = previous_since.to_rfc3339();
+
+    let agg =
+        storage::stats::query_aggregate(pool, &since_str, &until_str, None, None, None).await?;
+    let mut models =
+        storage::stats::query_grouped_by_model(pool, &since_str, &until_str, None, None).await?;
+    let current = storage::stats::query_grouped_by_provider(pool, &since_str, &until_str).await?;
+    let previous =
+        storage::stats::query_grouped_by_provider(pool, &previous_since_str, &since_str).await?;
+
+    let savings_sats = estimate_savings(config, &models);
+
+    models.sort_by(|a, b| b.total_cost_sats.total_cmp(&a.total_cost_sats));
+    let top_models = models
+        .iter()
+        .take(TOP_MODELS)
+        .map(|m| ModelSpend {
+            model: m.model.clone(),
+            requests: m.total_requests,
+            cost_sats: m.total_cost_sats,
+        })
+        .collect();
+
+    let mut providers: Vec<ProviderSummary> = current
+        .iter()
+        .map(|p| {
+            let rate = |errors: i64, total: i64| {
+                if total > 0 {
+                    errors as f64 / total as f64
+                } else {
+                    0.0
+                }
+            };
+            let error_rate = rate(p.error_count, p.total_requests);
+            let previous_error_rate = previous
+                .iter()
+                .find(|prev| prev.provider == p.provider)
+                .map_or(0.0, |prev| rate(prev.error_count, prev.total_requests));
+            ProviderSummary {
+                provider: p.provider.clone(),
+                requests: p.total_requests,
+                errors: p.error_count,
+                cost_sats: p.total_cost_sats,
+                error_rate,
+                previous_error_rate,
+                error_spike: is_error_spike(p.error_count, error_rate, previous_error_rate),
+            }
+        })
+        .collect();
+    providers.sort_by(|a, b| b.cost_sats.total_cmp(&a.cost_sats));
+
+    Ok(Report {
+        schedule: schedule.to_string(),
+        since: since_str,
+        until: until_str,
+        totals: ReportTotals {
+            requests: agg.total_requests,
+            success: agg.success_count,
+            errors: agg.error_count,
+            cost_sats: agg.total_cost_sats,
+        },
+        top_models,
+        providers,
+        savings_sats,
+    })
+}
+
+impl Report {
+    /// One-line summary used as the email subject.
+    pub fn subject(&self) -> String {
+        format!(
+            "arbstr {} report: {:.2} sats across {} requests",
+            self.schedule, self.totals.cost_sats, self.totals.requests
+        )
+    }
+
+    /// Render the report as plain text.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "arbstr {} report\n{} to {}\n\n",
+            self.schedule, self.since, self.until
+        ));
+        out.push_str(&format!(
+            "Requests: {} ({} ok, {} errors)\nSpend: {:.2} sats\nEstimated savings: {:.2} sats\n",
+            self.totals.requests,
+            self.totals.success,
+            self.totals.errors,
+            self.totals.cost_sats,
+            self.savings_sats
+        ));
+
+        out.push_str("\nTop models\n");
+        if self.top_models.is_empty() {
+            out.push_str("  (no traffic)\n");
+        }
+        for m in &self.top_models {
+            out.push_str(&format!(
+                "  {:<32} {:>8} req {:>12.2} sats\n",
+                m.model, m.requests, m.cost_sats
+            ));
+        }
+
+        out.push_str("\nSpend by provider\n");
+        if self.providers.is_empty() {
+            out.push_str("  (no traffic)\n");
+        }
+        for p in &self.providers {
+            out.push_str(&format!(
+                "  {:<32} {:>8} req {:>12.2} sats {:>6.1}% errors{}\n",
+                p.provider,
+                p.requests,
+                p.cost_sats,
+                p.error_rate * 100.0,
+                if p.error_spike { "  [SPIKE]" } else { "" }
+            ));
+        }
+
+        out
+    }
+}
+
+/// POST the report as JSON to a webhook.
+pub async fn send_webhook(client: &Client, url: &str, report: &Report) -> Result<(), String> {
+    let response = client
+        .post(url)
+        .timeout(DELIVERY_TIMEOUT)
+        .json(report)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("Webhook returned HTTP {}", response.status()))
+    }
+}
+
+/// Read one (possibly multi-line) SMTP reply and check its status code.
+async fn smtp_expect<R>(reader: &mut R, expected: &[u16]) -> Result<(), String>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    loop {
+        let mut line = String::new();
+        let n = reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| format!("SMTP read failed: {}", e))?;
+        if n == 0 {
+            return Err("SMTP connection closed".to_string());
+        }
+        let code: u16 = line
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| format!("Malformed SMTP reply: {}", line.trim_end()))?;
+        // "250-..." continues a multi-line reply; "250 ..." ends it.
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if expected.contains(&code) {
+            return Ok(());
+        }
+        return Err(format!("Unexpected SMTP reply: {}", line.trim_end()));
+    }
+}
+
+/// Send a plain-text email through an SMTP relay (no TLS, no AUTH).
+pub async fn send_smtp(smtp: &SmtpConfig, subject: &str, body: &str) -> Result<(), String> {
+    let stream = TcpStream::connect((smtp.host.as_str(), smtp.port))
+        .await
+        .map_err(|e| format!("SMTP connect to {}:{} failed: {}", smtp.host, smtp.port, e))?;
+    let (read_half, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn send<W: AsyncWriteExt + Unpin>(writer: &mut W, line: &str) -> Result<(), String> {
+        writer
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .await
+            .map_err(|e| format!("SMTP write failed: {}", e))
+    }
+
+    smtp_expect(&mut reader, &[220]).await?;
+    send(&mut writer, "EHLO arbstr").await?;
+    smtp_expect(&mut reader, &[250]).await?;
+    send(&mut writer, &format!("MAIL FROM:<{}>", smtp.from)).await?;
+    smtp_expect(&mut reader, &[250]).await?;
+    for rcpt in &smtp.to {
+        send(&mut writer, &format!("RCPT TO:<{}>", rcpt)).await?;
+        smtp_expect(&mut reader, &[250, 251]).await?;
+    }
+    send(&mut writer, "DATA").await?;
+    smtp_expect(&mut reader, &[354]).await?;
+
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        smtp.from,
+        smtp.to.join(", "),
+        subject,
+        Utc::now().to_rfc2822()
+    );
+    for line in body.lines() {
+        // Dot-stuffing (RFC 5321 section 4.5.2)
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push('.');
+    send(&mut writer, &message).await?;
+    smtp_expect(&mut reader, &[250]).await?;
+
+    send(&mut writer, "QUIT").await?;
+    let _ = smtp_expect(&mut reader, &[221]).await;
+    Ok(())
+}
+
+/// Build and deliver one report. Failures are logged, not returned.
+pub async fn send_report(
+    pool: &SqlitePool,
+    config: &Config,
+    reports: &ReportsConfig,
+    client: &Client,
+    until: DateTime<Utc>,
+) {
+    let report = match build_report(pool, config, reports.schedule, until).await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to build scheduled report");
+            return;
+        }
+    };
+
+    if let Some(ref url) = reports.webhook_url {
+        match send_webhook(client, url, &report).await {
+            Ok(()) => tracing::info!(schedule = %reports.schedule, "Report delivered to webhook"),
+            Err(e) => tracing::warn!(error = %e, "Report webhook delivery failed"),
+        }
+    }
+
+    if let Some(ref smtp) = reports.smtp {
+        let result = tokio::time::timeout(
+            DELIVERY_TIMEOUT,
+            send_smtp(smtp, &report.subject(), &report.render_text()),
+        )
+        .await
+        .unwrap_or_else(|_| Err("SMTP delivery timed out".to_string()));
+        match result {
+            Ok(()) => tracing::info!(schedule = %reports.schedule, "Report delivered via SMTP"),
+            Err(e) => tracing::warn!(error = %e, "Report SMTP delivery failed"),
+        }
+    }
+}
+
+/// Background report task.
+///
+/// Sleeps until the next scheduled time, sends a report covering the
+/// preceding period, and repeats. Stops when the cancellation token is
+/// triggered (graceful shutdown).
+pub async fn report_loop(
+    config: std::sync::Arc<Config>,
+    pool: SqlitePool,
+    client: Client,
+    cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let Some(reports) = config.reports.clone() else {
+        return;
+    };
+
+    loop {
+        let now = Utc::now();
+        let next = next_run(now, &reports);
+        let wait = (next - now).to_std().unwrap_or_default();
+        tracing::debug!(next = %next, "Next scheduled report");
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {
+                send_report(&pool, &config, &reports, &client, next).await;
+            }
+            _ = super::vault::cancel_wait(&cancel) => {
+                tracing::info!("Report task shutting down");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn reports_config(schedule: ReportSchedule) -> ReportsConfig {
+        ReportsConfig {
+            schedule,
+            hour_utc: 8,
+            weekday: chrono::Weekday::Mon,
+            webhook_url: None,
+            smtp: None,
+        }
+    }
+
+    #[test]
+    fn next_run_daily_later_today() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 6, 30, 0).unwrap();
+        let next = next_run(now, &reports_config(ReportSchedule::Daily));
+        assert_eq!(next, Utc.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn next_run_daily_rolls_to_tomorrow() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap();
+        let next = next_run(now, &reports_config(ReportSchedule::Daily));
+        assert_eq!(next, Utc.with_ymd_and_hms(2026, 10, 16, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn next_run_weekly_finds_weekday() {
+        // 2026-10-15 is a Thursday; next Monday is 2026-10-19
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap();
+        let next = next_run(now, &reports_config(ReportSchedule::Weekly));
+        assert_eq!(next, Utc.with_ymd_and_hms(2026, 10, 19, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn error_spike_thresholds() {
+        assert!(is_error_spike(10, 0.5, 0.1));
+        assert!(!is_error_spike(2, 0.5, 0.0)); // too few errors
+        assert!(!is_error_spike(10, 0.05, 0.0)); // rate too low
+        assert!(!is_error_spike(10, 0.3, 0.2)); // not double the previous rate
+    }
+
+    #[test]
+    fn render_text_marks_spikes() {
+        let report = Report {
+            schedule: "daily".to_string(),
+            since: "a".to_string(),
+            until: "b".to_string(),
+            totals: ReportTotals {
+                requests: 10,
+                success: 4,
+                errors: 6,
+                cost_sats: 12.5,
+            },
+            top_models: vec![],
+            providers: vec![ProviderSummary {
+                provider: "alpha".to_string(),
+                requests: 10,
+                errors: 6,
+                cost_sats: 12.5,
+                error_rate: 0.6,
+                previous_error_rate: 0.0,
+                error_spike: true,
+            }],
+            savings_sats: 0.0,
+        };
+        let text = report.render_text();
+        assert!(text.contains("Spend: 12.50 sats"));
+        assert!(text.contains("(no traffic)"));
+        assert!(text.contains("[SPIKE]"));
+    }
+
+    #[tokio::test]
+    async fn send_smtp_speaks_protocol() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (r, mut w) = stream.into_split();
+            let mut reader = BufReader::new(r);
+            let mut received = Vec::new();
+            w.write_all(b"220 test ready\r\n").await.unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                received.push(line.clone());
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        continue;
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-test\r\n250 OK\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go\r\n"
+                } else if line == "QUIT" {
+                    w.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                w.write_all(reply).await.unwrap();
+            }
+            received
+        });
+
+        let smtp = SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            from: "arbstr@example.com".to_string(),
+            to: vec!["finance@example.com".to_string()],
+        };
+        send_smtp(&smtp, "Subject line", "hello\n.dotted")
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert!(received.contains(&"MAIL FROM:<arbstr@example.com>".to_string()));
+        assert!(received.contains(&"RCPT TO:<finance@example.com>".to_string()));
+        assert!(received.contains(&"Subject: Subject line".to_string()));
+        assert!(received.contains(&"..dotted".to_string()));
+    }
+}
@@ -0,0 +1,493 @@
+//! Reliability-based provider demotion.
+//!
+//! The circuit breaker only reacts to consecutive failures. A provider that
+//! fails one request in three, or answers every request slowly, keeps a
+//! closed circuit while still hurting clients. The reputation tracker keeps
+//! a rolling window of outcomes per provider and, when the error rate or
+//! average latency crosses `[routing.reputation]` thresholds, demotes the
+//! provider for a fixed period: either by inflating its routing cost (the
+//! multiplier decays linearly back to 1.0) or by excluding it outright.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::time::Instant;
+
+use crate::config::{ReputationAction, ReputationConfig};
+use crate::router::SelectedProvider;
+use crate::storage::routing_state;
+
+/// `routing_state` kind for saved reputation.
+const STATE_KIND: &str = "reputation";
+
+/// One recorded request outcome.
+#[derive(Debug, Clone, Copy)]
+struct Outcome {
+    success: bool,
+    latency_ms: u64,
+}
+
+/// Per-provider rolling window and active demotion.
+#[derive(Debug, Default)]
+struct ProviderReputation {
+    outcomes: VecDeque<Outcome>,
+    penalized_at: Option<Instant>,
+    reason: Option<String>,
+}
+
+impl ProviderReputation {
+    fn error_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        let errors = self.outcomes.iter().filter(|o| !o.success).count();
+        errors as f64 / self.outcomes.len() as f64
+    }
+
+    fn avg_latency_ms(&self) -> Option<f64> {
+        let latencies: Vec<u64> = self
+            .outcomes
+            .iter()
+            .filter(|o| o.success)
+            .map(|o| o.latency_ms)
+            .collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        Some(latencies.iter().sum::<u64>() as f64 / latencies.len() as f64)
+    }
+}
+
+/// A provider's reputation as saved across restarts. Demotion start is
+/// stored as an age so it can be carried over to a new process clock.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedReputation {
+    outcomes: Vec<(bool, u64)>,
+    #[serde(default)]
+    penalized_secs_ago: Option<f64>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// An active demotion, as seen at a point in time.
+#[derive(Debug, Clone, Serialize)]
+pub struct Penalty {
+    /// Current routing cost multiplier (1.0 when excluded).
+    pub cost_multiplier: f64,
+    /// Whether the provider is dropped from candidates.
+    pub excluded: bool,
+    /// Seconds until the demotion expires.
+    pub remaining_secs: u64,
+    /// Which threshold was crossed.
+    pub reason: String,
+}
+
+/// Rolling reputation for one provider, for `/v1/route/explain`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReputationSnapshot {
+    pub samples: usize,
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub penalty: Option<Penalty>,
+}
+
+/// Tracks rolling outcomes per provider and applies demotions.
+///
+/// A tracker built without config is inert: nothing is recorded and
+/// candidates pass through unchanged.
+#[derive(Debug, Default)]
+pub struct ReputationTracker {
+    config: Option<ReputationConfig>,
+    providers: DashMap<String, ProviderReputation>,
+}
+
+impl ReputationTracker {
+    /// Create a tracker from `[routing.reputation]`.
+    pub fn new(config: Option<ReputationConfig>) -> Self {
+        Self {
+            config,
+            providers: DashMap::new(),
+        }
+    }
+
+    /// Whether reputation tracking is configured.
+    pub fn enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Record a request outcome for `provider` and demote it if the rolling
+    /// window now breaches a threshold.
+    ///
+    /// The window is cleared when a demotion starts, so the provider has to
+    /// build a fresh record of `min_requests` before it can be judged again.
+    pub fn record(&self, provider: &str, success: bool, latency_ms: u64) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        let mut rep = self.providers.entry(provider.to_string()).or_default();
+
+        if rep.outcomes.len() == config.window {
+            rep.outcomes.pop_front();
+        }
+        rep.outcomes.push_back(Outcome {
+            success,
+            latency_ms,
+        });
+        if rep.outcomes.len() < config.min_requests {
+            return;
+        }
+
+        let error_rate = rep.error_rate();
+        let reason = if error_rate > config.max_error_rate {
+            Some(format!(
+                "error rate {:.0}% over last {} requests exceeds {:.0}%",
+                error_rate * 100.0,
+                rep.outcomes.len(),
+                config.max_error_rate * 100.0
+            ))
+        } else {
+            match (rep.avg_latency_ms(), config.max_avg_latency_ms) {
+                (Some(avg), Some(max)) if avg > max as f64 => {
+                    Some(format!("average latency {:.0}ms exceeds {}ms", avg, max))
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(reason) = reason {
+            tracing::warn!(
+                provider = %provider,
+                reason = %reason,
+                action = ?config.action,
+                penalty_secs = config.penalty_secs,
+                "Provider demoted on reputation"
+            );
+            rep.penalized_at = Some(Instant::now());
+            rep.reason = Some(reason);
+            rep.outcomes.clear();
+        }
+    }
+
+    /// Save every provider's window and demotion to the database.
+    /// Returns the number of providers saved.
+    pub async fn persist(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        if !self.enabled() {
+            return Ok(0);
+        }
+        let entries: Vec<(String, String)> = self
+            .providers
+            .iter()
+            .filter_map(|entry| {
+                let rep = entry.value();
+                let saved = SavedReputation {
+                    outcomes: rep
+                        .outcomes
+                        .iter()
+                        .map(|o| (o.success, o.latency_ms))
+                        .collect(),
+                    penalized_secs_ago: rep.penalized_at.map(|at| at.elapsed().as_secs_f64()),
+                    reason: rep.reason.clone(),
+                };
+                let state = serde_json::to_string(&saved).ok()?;
+                Some((entry.key().clone(), state))
+            })
+            .collect();
+        routing_state::save(pool, STATE_KIND, &entries, &routing_state::now()).await?;
+        Ok(entries.len())
+    }
+
+    /// Reload state saved by [`persist`](Self::persist). Windows are cut to
+    /// the configured size; demotions resume with the downtime counted as
+    /// served. Returns the number of providers restored.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let Some(config) = &self.config else {
+            return Ok(0);
+        };
+        let period = Duration::from_secs(config.penalty_secs);
+        let mut restored = 0;
+        for saved in routing_state::load(pool, STATE_KIND).await? {
+            let Ok(state) = serde_json::from_str::<SavedReputation>(&saved.state) else {
+                tracing::warn!(provider = %saved.provider, "Discarding unreadable saved reputation");
+                continue;
+            };
+            let mut outcomes: VecDeque<Outcome> = state
+                .outcomes
+                .into_iter()
+                .map(|(success, latency_ms)| Outcome {
+                    success,
+                    latency_ms,
+                })
+                .collect();
+            while outcomes.len() > config.window {
+                outcomes.pop_front();
+            }
+            let penalized_at = state
+                .penalized_secs_ago
+                .map(|ago| Duration::from_secs_f64(ago) + saved.age())
+                .filter(|age| *age < period)
+                .map(|age| Instant::now().checked_sub(age).unwrap_or_else(Instant::now));
+            let reason = penalized_at.and(state.reason);
+            self.providers.insert(
+                saved.provider,
+                ProviderReputation {
+                    outcomes,
+                    penalized_at,
+                    reason,
+                },
+            );
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Active demotion for `provider`, if any.
+    pub fn penalty(&self, provider: &str) -> Option<Penalty> {
+        let config = self.config.as_ref()?;
+        let rep = self.providers.get(provider)?;
+        penalty_for(config, &rep)
+    }
+
+    /// Rolling stats and any active demotion for `provider`.
+    ///
+    /// Returns None when tracking is disabled.
+    pub fn snapshot(&self, provider: &str) -> Option<ReputationSnapshot> {
+        let config = self.config.as_ref()?;
+        Some(match self.providers.get(provider) {
+            Some(rep) => ReputationSnapshot {
+                samples: rep.outcomes.len(),
+                error_rate: rep.error_rate(),
+                avg_latency_ms: rep.avg_latency_ms(),
+                penalty: penalty_for(config, &rep),
+            },
+            None => ReputationSnapshot {
+                samples: 0,
+                error_rate: 0.0,
+                avg_latency_ms: None,
+                penalty: None,
+            },
+        })
+    }
+
+    /// Effective routing cost of a candidate after any cost penalty.
+    pub fn effective_cost(&self, candidate: &SelectedProvider) -> f64 {
+        let base = candidate.output_rate + candidate.base_fee;
+        match self.penalty(&candidate.name) {
+            Some(p) => base * p.cost_multiplier,
+            None => base,
+        }
+    }
+
+    /// Reorder (and possibly filter) circuit-approved candidates.
+    ///
+    /// Penalized providers are re-sorted by effective cost; excluded
+    /// providers are dropped unless that would leave no candidates. The
+    /// half-open probe provider, if any, stays in front so the probe still
+    /// runs.
+    pub fn apply(
+        &self,
+        candidates: Vec<SelectedProvider>,
+        probe_provider: Option<&str>,
+    ) -> Vec<SelectedProvider> {
+        if !self.enabled() || !candidates.iter().any(|c| self.penalty(&c.name).is_some()) {
+            return candidates;
+        }
+
+        let (mut kept, excluded): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| {
+            probe_provider == Some(c.name.as_str())
+                || !self.penalty(&c.name).is_some_and(|p| p.excluded)
+        });
+        if kept.is_empty() {
+            tracing::debug!("All candidates excluded on reputation, ignoring exclusions");
+            kept = excluded;
+        } else {
+            for c in &excluded {
+                tracing::debug!(provider = %c.name, "Skipping provider: reputation exclusion");
+            }
+        }
+
+        // Stable sort keeps config order among equal costs.
+        kept.sort_by(|a, b| {
+            let a_probe = probe_provider == Some(a.name.as_str());
+            let b_probe = probe_provider == Some(b.name.as_str());
+            b_probe
+                .cmp(&a_probe)
+                .then(self.effective_cost(a).total_cmp(&self.effective_cost(b)))
+        });
+        kept
+    }
+}
+
+fn penalty_for(config: &ReputationConfig, rep: &ProviderReputation) -> Option<Penalty> {
+    let penalized_at = rep.penalized_at?;
+    let period = Duration::from_secs(config.penalty_secs);
+    let elapsed = penalized_at.elapsed();
+    if elapsed >= period {
+        return None;
+    }
+    let remaining = period - elapsed;
+    let (cost_multiplier, excluded) = match config.action {
+        ReputationAction::Penalize => {
+            let fraction = remaining.as_secs_f64() / period.as_secs_f64();
+            (1.0 + (config.cost_multiplier - 1.0) * fraction, false)
+        }
+        ReputationAction::Exclude => (1.0, true),
+    };
+    Some(Penalty {
+        cost_multiplier,
+        excluded,
+        remaining_secs: remaining.as_secs_f64().ceil() as u64,
+        reason: rep.reason.clone().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Tier;
+
+    fn config(action: ReputationAction) -> ReputationConfig {
+        ReputationConfig {
+            window: 10,
+            min_requests: 4,
+            max_error_rate: 0.25,
+            max_avg_latency_ms: Some(1000),
+            action,
+            cost_multiplier: 3.0,
+            penalty_secs: 100,
+        }
+    }
+
+    fn provider(name: &str, output_rate: f64) -> SelectedProvider {
+        SelectedProvider {
+            name: name.to_string(),
+            url: "https://example.test/v1".to_string(),
+            api_key: None,
+            input_rate: 0.0,
+            output_rate,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
+            tier: Tier::Standard,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            structured_output: false,
+        }
+    }
+
+    fn names(candidates: &[SelectedProvider]) -> Vec<&str> {
+        candidates.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn penalty_triggers_on_error_rate_and_decays() {
+        let tracker = ReputationTracker::new(Some(config(ReputationAction::Penalize)));
+        for success in [true, true, false] {
+            tracker.record("alpha", success, 100);
+        }
+        // Below min_requests: no judgement yet
+        assert!(tracker.penalty("alpha").is_none());
+
+        tracker.record("alpha", false, 100);
+        let penalty = tracker.penalty("alpha").unwrap();
+        assert!((penalty.cost_multiplier - 3.0).abs() < 1e-9);
+        assert!(!penalty.excluded);
+        assert!(penalty.reason.contains("error rate 50%"));
+        // Window cleared on demotion
+        assert_eq!(tracker.snapshot("alpha").unwrap().samples, 0);
+
+        tokio::time::advance(Duration::from_secs(50)).await;
+        let penalty = tracker.penalty("alpha").unwrap();
+        assert!((penalty.cost_multiplier - 2.0).abs() < 1e-6);
+        assert_eq!(penalty.remaining_secs, 50);
+
+        tokio::time::advance(Duration::from_secs(50)).await;
+        assert!(tracker.penalty("alpha").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn penalty_triggers_on_latency() {
+        let tracker = ReputationTracker::new(Some(config(ReputationAction::Penalize)));
+        for _ in 0..4 {
+            tracker.record("slow", true, 1500);
+        }
+        let penalty = tracker.penalty("slow").unwrap();
+        assert!(penalty.reason.contains("average latency 1500ms"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn penalized_provider_loses_cost_ordering() {
+        let tracker = ReputationTracker::new(Some(config(ReputationAction::Penalize)));
+        for _ in 0..4 {
+            tracker.record("cheap", false, 100);
+        }
+        let ordered = tracker.apply(
+            vec![provider("cheap", 10.0), provider("pricier", 20.0)],
+            None,
+        );
+        assert_eq!(names(&ordered), vec!["pricier", "cheap"]);
+
+        // Probe provider stays first regardless of penalty
+        let ordered = tracker.apply(
+            vec![provider("cheap", 10.0), provider("pricier", 20.0)],
+            Some("cheap"),
+        );
+        assert_eq!(names(&ordered), vec!["cheap", "pricier"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn excluded_provider_dropped_unless_last() {
+        let tracker = ReputationTracker::new(Some(config(ReputationAction::Exclude)));
+        for _ in 0..4 {
+            tracker.record("bad", false, 100);
+        }
+        let ordered = tracker.apply(vec![provider("bad", 10.0), provider("good", 20.0)], None);
+        assert_eq!(names(&ordered), vec!["good"]);
+
+        let ordered = tracker.apply(vec![provider("bad", 10.0)], None);
+        assert_eq!(names(&ordered), vec!["bad"]);
+    }
+
+    #[tokio::test]
+    async fn disabled_tracker_is_inert() {
+        let tracker = ReputationTracker::default();
+        for _ in 0..20 {
+            tracker.record("alpha", false, 100);
+        }
+        assert!(tracker.penalty("alpha").is_none());
+        assert!(tracker.snapshot("alpha").is_none());
+        let ordered = tracker.apply(vec![provider("alpha", 10.0), provider("beta", 5.0)], None);
+        assert_eq!(names(&ordered), vec!["alpha", "beta"]);
+    }
+
+    #[tokio::test]
+    async fn state_survives_persist_and_restore() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let tracker = ReputationTracker::new(Some(config(ReputationAction::Exclude)));
+        for _ in 0..4 {
+            tracker.record("alpha", false, 100);
+        }
+        for latency in [100, 200, 300] {
+            tracker.record("beta", true, latency);
+        }
+        assert_eq!(tracker.persist(&pool).await.unwrap(), 2);
+
+        let restarted = ReputationTracker::new(Some(config(ReputationAction::Exclude)));
+        assert_eq!(restarted.restore(&pool).await.unwrap(), 2);
+        let penalty = restarted.penalty("alpha").unwrap();
+        assert!(penalty.excluded);
+        assert!(penalty.remaining_secs > 90);
+        assert!(penalty.reason.contains("error rate"));
+        let beta = restarted.snapshot("beta").unwrap();
+        assert_eq!(beta.samples, 3);
+        assert_eq!(beta.avg_latency_ms, Some(200.0));
+
+        // Without tracking configured, nothing is restored
+        let disabled = ReputationTracker::new(None);
+        assert_eq!(disabled.restore(&pool).await.unwrap(), 0);
+    }
+}
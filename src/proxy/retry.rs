@@ -1,33 +1,51 @@
 //! Retry and fallback logic for non-streaming requests.
 //!
 //! This module encapsulates the retry-with-fallback algorithm:
-//! - Up to `MAX_RETRIES` retries on the primary provider with exponential backoff
-//! - Single fallback attempt on the next candidate if primary exhausts retries
+//! - Up to `MAX_RETRIES` retries on the primary provider with configurable
+//!   exponential backoff and full jitter (`BackoffConfig`)
+//! - One attempt on each of up to `max_fallbacks` further candidates, in
+//!   routing order, once the primary exhausts its retries
 //! - Attempt tracking via shared `Arc<Mutex<Vec<AttemptRecord>>>` that survives timeout cancellation
 //! - Header formatting for `x-arbstr-retries`
 
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-/// Fixed exponential backoff: 1s, 2s, 4s.
-///
-/// Matches the locked decision from CONTEXT.md. With `MAX_RETRIES=2`, only the
-/// first two slots (1s, 2s) are used at runtime. The 4s entry documents the full
-/// sequence and would be used if `MAX_RETRIES` were increased.
-const BACKOFF_DURATIONS: [Duration; 3] = [
-    Duration::from_secs(1),
-    Duration::from_secs(2),
-    Duration::from_secs(4),
-];
+use chrono::{DateTime, Utc};
+use rand::Rng;
+
+use crate::config::BackoffConfig;
+use crate::error::ProviderErrorKind;
+use crate::storage::AttemptLog;
 
 /// Maximum number of retries on the primary provider (3 total attempts).
 const MAX_RETRIES: u32 = 2;
 
-/// Record of a single failed attempt for building the `x-arbstr-retries` header.
+/// Record of a single failed attempt, for the `x-arbstr-retries` header and
+/// the `request_attempts` table.
 #[derive(Debug, Clone)]
 pub struct AttemptRecord {
     pub provider_name: String,
     pub status_code: u16,
+    pub kind: ProviderErrorKind,
+    /// Backoff waited before this attempt (zero for first attempts and fallbacks).
+    pub backoff: Duration,
+    /// When the attempt was sent.
+    pub started_at: DateTime<Utc>,
+}
+
+impl From<&AttemptRecord> for AttemptLog {
+    fn from(record: &AttemptRecord) -> Self {
+        Self {
+            provider: record.provider_name.clone(),
+            status: record.status_code,
+            error_type: record.kind.as_str().to_string(),
+            backoff_ms: record.backoff.as_millis() as u64,
+            timestamp: record
+                .started_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        }
+    }
 }
 
 /// Lightweight candidate info for the retry module.
@@ -45,6 +63,11 @@ pub struct CandidateInfo {
 /// depending on `RequestError` directly.
 pub trait HasStatusCode {
     fn status_code(&self) -> u16;
+
+    /// Failure classification; defaults to one derived from the status code.
+    fn error_kind(&self) -> ProviderErrorKind {
+        ProviderErrorKind::from_status(self.status_code())
+    }
 }
 
 /// Outcome of the full retry+fallback sequence.
@@ -69,6 +92,26 @@ pub fn is_retryable(status_code: u16) -> bool {
     matches!(status_code, 500 | 502 | 503 | 504)
 }
 
+/// Upper bound on the wait before retry `retry` (1-based):
+/// `min(max_ms, base_ms * multiplier^(retry-1))`.
+pub fn backoff_ceiling(config: &BackoffConfig, retry: u32) -> Duration {
+    let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+    let ms = config.base_ms as f64 * config.multiplier.powi(exponent);
+    Duration::from_millis(ms.min(config.max_ms as f64) as u64)
+}
+
+/// Wait before retry `retry` (1-based): the ceiling itself, or a uniform
+/// draw from zero to the ceiling when `jitter` is set.
+pub fn backoff_delay(config: &BackoffConfig, retry: u32) -> Duration {
+    let ceiling = backoff_ceiling(config, retry);
+    if config.jitter {
+        let ms = rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64);
+        Duration::from_millis(ms)
+    } else {
+        ceiling
+    }
+}
+
 /// Format attempt records into the `x-arbstr-retries` header value.
 ///
 /// Format: `"2/provider-alpha, 1/provider-beta"` -- count of failed attempts
@@ -103,25 +146,36 @@ pub fn format_retries_header(attempts: &[AttemptRecord]) -> Option<String> {
 /// Execute a request with retry on the primary provider and fallback to the next candidate.
 ///
 /// Algorithm:
-/// 1. Take first candidate as primary, second (if exists) as fallback
-/// 2. Attempt primary up to `MAX_RETRIES + 1` times (3 total)
+/// 1. Take first candidate as primary, the next `max_fallbacks` as fallbacks
+/// 2. Attempt primary up to `MAX_RETRIES + 1` times (3 total), waiting
+///    `backoff_delay(backoff, n)` before retry `n`
 /// 3. On success: return immediately
 /// 4. On error: record attempt in shared vec, check retryability
-/// 5. On non-retryable error: return immediately (no retry, no fallback)
-/// 6. After primary exhausted with retryable errors: try fallback once
-/// 7. If no fallback exists: return last primary error
+/// 5. On non-retryable error: return immediately (no retry, no fallback),
+///    except auth failures (401/403), which skip straight to the fallbacks
+/// 6. After primary exhausted with retryable errors: try each fallback once,
+///    in order, stopping at the first success or non-retryable error
+/// 7. If every fallback fails (or none exists): return the last error
+///
+/// Every retry and fallback attempt first asks `may_retry` for budget
+/// (see `retry_budget`). When it returns false the last error is returned
+/// immediately.
 ///
 /// The `attempts` parameter is an `Arc<Mutex<Vec<AttemptRecord>>>` that the caller
 /// creates and owns. Failed attempts are pushed into this shared vec. This design
 /// allows the caller to read accumulated attempts even if this future is cancelled
 /// by a timeout.
-pub async fn retry_with_fallback<T, E, F, Fut>(
+pub async fn retry_with_fallback<T, E, R, F, Fut>(
     candidates: &[CandidateInfo],
     attempts: Arc<Mutex<Vec<AttemptRecord>>>,
+    backoff: &BackoffConfig,
+    max_fallbacks: usize,
+    may_retry: R,
     send_request: F,
 ) -> RetryOutcome<T, E>
 where
     E: HasStatusCode,
+    R: Fn() -> bool,
     F: Fn(&CandidateInfo) -> Fut,
     Fut: std::future::Future<Output = std::result::Result<T, E>>,
 {
@@ -137,10 +191,16 @@ where
     // Primary provider: up to MAX_RETRIES + 1 total attempts
     for attempt in 0..=MAX_RETRIES {
         // Backoff before retry (not before first attempt)
+        let mut delay = Duration::ZERO;
         if attempt > 0 {
-            tokio::time::sleep(BACKOFF_DURATIONS[(attempt - 1) as usize]).await;
+            if !may_retry() {
+                break;
+            }
+            delay = backoff_delay(backoff, attempt);
+            tokio::time::sleep(delay).await;
         }
 
+        let started_at = Utc::now();
         match send_request(primary).await {
             Ok(value) => {
                 return RetryOutcome { result: Ok(value) };
@@ -155,8 +215,18 @@ where
                     .push(AttemptRecord {
                         provider_name: primary.name.clone(),
                         status_code: err.status_code(),
+                        kind: err.error_kind(),
+                        backoff: delay,
+                        started_at,
                     });
 
+                if err.error_kind() == ProviderErrorKind::Auth {
+                    // Rejected credentials: retrying cannot help, but another
+                    // provider's key may work
+                    last_error = Some(err);
+                    break;
+                }
+
                 if !retryable {
                     // Non-retryable error: fail immediately, no fallback
                     return RetryOutcome { result: Err(err) };
@@ -169,15 +239,20 @@ where
         }
     }
 
-    // Primary exhausted with retryable errors -- try fallback if available
-    if candidates.len() > 1 {
-        let fallback = &candidates[1];
+    // Primary exhausted with retryable errors -- walk the fallback chain
+    for fallback in candidates.iter().skip(1).take(max_fallbacks) {
+        if !may_retry() {
+            break;
+        }
 
+        let started_at = Utc::now();
         match send_request(fallback).await {
             Ok(value) => {
                 return RetryOutcome { result: Ok(value) };
             }
             Err(err) => {
+                let retryable = is_retryable(err.status_code());
+
                 // Record fallback failure
                 attempts
                     .lock()
@@ -185,14 +260,21 @@ where
                     .push(AttemptRecord {
                         provider_name: fallback.name.clone(),
                         status_code: err.status_code(),
+                        kind: err.error_kind(),
+                        backoff: Duration::ZERO,
+                        started_at,
                     });
 
-                return RetryOutcome { result: Err(err) };
+                if !retryable && err.error_kind() != ProviderErrorKind::Auth {
+                    return RetryOutcome { result: Err(err) };
+                }
+
+                last_error = Some(err);
             }
         }
     }
 
-    // No fallback available -- return last primary error
+    // Fallbacks exhausted, none available, or retry budget spent
     // SAFETY: The for loop (0..=MAX_RETRIES) always executes at least once.
     // Every Err branch sets last_error = Some(err). If we reach here,
     // all attempts returned Err, so last_error is always Some.
@@ -218,6 +300,24 @@ mod tests {
         }
     }
 
+    fn failed(provider: &str, status_code: u16) -> AttemptRecord {
+        AttemptRecord {
+            provider_name: provider.to_string(),
+            status_code,
+            kind: ProviderErrorKind::from_status(status_code),
+            backoff: Duration::ZERO,
+            started_at: Utc::now(),
+        }
+    }
+
+    /// Default schedule without jitter: 1s, 2s, 4s.
+    fn fixed_backoff() -> BackoffConfig {
+        BackoffConfig {
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_is_retryable() {
         // Retryable: 5xx server errors
@@ -246,16 +346,7 @@ mod tests {
 
     #[test]
     fn test_format_retries_header_single_provider() {
-        let attempts = vec![
-            AttemptRecord {
-                provider_name: "alpha".to_string(),
-                status_code: 503,
-            },
-            AttemptRecord {
-                provider_name: "alpha".to_string(),
-                status_code: 502,
-            },
-        ];
+        let attempts = vec![failed("alpha", 503), failed("alpha", 502)];
         assert_eq!(
             format_retries_header(&attempts),
             Some("2/alpha".to_string())
@@ -265,18 +356,9 @@ mod tests {
     #[test]
     fn test_format_retries_header_multiple_providers() {
         let attempts = vec![
-            AttemptRecord {
-                provider_name: "alpha".to_string(),
-                status_code: 503,
-            },
-            AttemptRecord {
-                provider_name: "alpha".to_string(),
-                status_code: 503,
-            },
-            AttemptRecord {
-                provider_name: "beta".to_string(),
-                status_code: 500,
-            },
+            failed("alpha", 503),
+            failed("alpha", 503),
+            failed("beta", 500),
         ];
         assert_eq!(
             format_retries_header(&attempts),
@@ -293,15 +375,21 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     cc.fetch_add(1, Ordering::Relaxed);
                     Ok("success".to_string())
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_ok());
         assert_eq!(outcome.result.unwrap(), "success");
@@ -318,8 +406,13 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     let n = cc.fetch_add(1, Ordering::Relaxed);
@@ -329,8 +422,9 @@ mod tests {
                         Ok("recovered".to_string())
                     }
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_ok());
         assert_eq!(outcome.result.unwrap(), "recovered");
@@ -350,15 +444,21 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     cc.fetch_add(1, Ordering::Relaxed);
                     Err(MockError { code: 503 })
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_err());
         assert_eq!(outcome.result.unwrap_err().code, 503);
@@ -386,8 +486,13 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |info| {
                 let cc = call_count_inner.clone();
                 let name = info.name.clone();
                 async move {
@@ -398,8 +503,9 @@ mod tests {
                         Ok("fallback-success".to_string())
                     }
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_ok());
         assert_eq!(outcome.result.unwrap(), "fallback-success");
@@ -427,15 +533,21 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     cc.fetch_add(1, Ordering::Relaxed);
                     Err(MockError { code: 500 })
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_err());
         // 3 primary + 1 fallback = 4 total calls
@@ -463,15 +575,21 @@ mod tests {
         let call_count_inner = call_count.clone();
         let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     cc.fetch_add(1, Ordering::Relaxed);
                     Err(MockError { code: 400 })
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_err());
         assert_eq!(outcome.result.unwrap_err().code, 400);
@@ -494,15 +612,21 @@ mod tests {
 
         let start = tokio::time::Instant::now();
 
-        let outcome: RetryOutcome<String, MockError> =
-            retry_with_fallback(&candidates, attempts.clone(), |_info| {
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || true,
+            |_info| {
                 let cc = call_count_inner.clone();
                 async move {
                     cc.fetch_add(1, Ordering::Relaxed);
                     Err(MockError { code: 503 })
                 }
-            })
-            .await;
+            },
+        )
+        .await;
 
         assert!(outcome.result.is_err());
         assert_eq!(call_count.load(Ordering::Relaxed), 3);
@@ -514,4 +638,222 @@ mod tests {
         let elapsed = start.elapsed();
         assert_eq!(elapsed, Duration::from_secs(3));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_denied_budget_skips_retries_and_fallback() {
+        let candidates = vec![
+            CandidateInfo {
+                name: "alpha".to_string(),
+            },
+            CandidateInfo {
+                name: "beta".to_string(),
+            },
+        ];
+        let call_count = Arc::new(AtomicU32::new(0));
+        let call_count_inner = call_count.clone();
+        let budget_checks = Arc::new(AtomicU32::new(0));
+        let budget_checks_inner = budget_checks.clone();
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            1,
+            || {
+                budget_checks_inner.fetch_add(1, Ordering::Relaxed);
+                false
+            },
+            |_info| {
+                let cc = call_count_inner.clone();
+                async move {
+                    cc.fetch_add(1, Ordering::Relaxed);
+                    Err(MockError { code: 503 })
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(outcome.result.unwrap_err().code, 503);
+        // Only the initial attempt: no retries, no fallback
+        assert_eq!(call_count.load(Ordering::Relaxed), 1);
+        assert_eq!(attempts.lock().unwrap().len(), 1);
+        assert_eq!(budget_checks.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_backoff_ceiling_grows_and_caps() {
+        let config = BackoffConfig {
+            base_ms: 100,
+            multiplier: 3.0,
+            max_ms: 1000,
+            jitter: false,
+        };
+        assert_eq!(backoff_ceiling(&config, 1), Duration::from_millis(100));
+        assert_eq!(backoff_ceiling(&config, 2), Duration::from_millis(300));
+        assert_eq!(backoff_ceiling(&config, 3), Duration::from_millis(900));
+        assert_eq!(backoff_ceiling(&config, 4), Duration::from_millis(1000));
+        assert_eq!(backoff_ceiling(&config, 50), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(&config, 2), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_full_jitter_stays_within_ceiling() {
+        let config = BackoffConfig::default();
+        let delays: Vec<Duration> = (0..200).map(|_| backoff_delay(&config, 2)).collect();
+        assert!(delays.iter().all(|d| *d <= Duration::from_secs(2)));
+        // Jitter actually spreads retries out
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_jittered_backoff_bounded_by_schedule() {
+        let candidates = vec![CandidateInfo {
+            name: "alpha".to_string(),
+        }];
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+        let config = BackoffConfig {
+            base_ms: 200,
+            multiplier: 2.0,
+            max_ms: 300,
+            jitter: true,
+        };
+
+        let start = tokio::time::Instant::now();
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &config,
+            1,
+            || true,
+            |_info| async { Err(MockError { code: 503 }) },
+        )
+        .await;
+
+        assert!(outcome.result.is_err());
+        assert_eq!(attempts.lock().unwrap().len(), 3);
+        // Waits are at most 200ms then min(400, 300) = 300ms
+        assert!(start.elapsed() <= Duration::from_millis(500));
+    }
+
+    fn chain(names: &[&str]) -> Vec<CandidateInfo> {
+        names
+            .iter()
+            .map(|name| CandidateInfo {
+                name: name.to_string(),
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fallback_walks_chain_in_order() {
+        let candidates = chain(&["alpha", "beta", "gamma", "delta"]);
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            3,
+            || true,
+            |info| {
+                let name = info.name.clone();
+                async move {
+                    if name == "gamma" {
+                        Ok(name)
+                    } else {
+                        Err(MockError { code: 502 })
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(outcome.result.unwrap(), "gamma");
+        let recorded = attempts.lock().unwrap().clone();
+        assert_eq!(format_retries_header(&recorded).unwrap(), "3/alpha, 1/beta");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fallback_chain_length_is_capped() {
+        let candidates = chain(&["alpha", "beta", "gamma", "delta"]);
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            2,
+            || true,
+            |_info| async { Err(MockError { code: 503 }) },
+        )
+        .await;
+
+        assert_eq!(outcome.result.unwrap_err().code, 503);
+        let recorded = attempts.lock().unwrap().clone();
+        // delta is beyond the chain length and never tried
+        assert_eq!(
+            format_retries_header(&recorded).unwrap(),
+            "3/alpha, 1/beta, 1/gamma"
+        );
+        // Backoff only precedes primary retries
+        let backoffs: Vec<u64> = recorded
+            .iter()
+            .map(|a| a.backoff.as_millis() as u64)
+            .collect();
+        assert_eq!(backoffs, vec![0, 1000, 2000, 0, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_non_retryable_fallback_error_stops_chain() {
+        let candidates = chain(&["alpha", "beta", "gamma"]);
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            5,
+            || true,
+            |info| {
+                let code = if info.name == "beta" { 400 } else { 503 };
+                async move { Err(MockError { code }) }
+            },
+        )
+        .await;
+
+        assert_eq!(outcome.result.unwrap_err().code, 400);
+        let recorded = attempts.lock().unwrap().clone();
+        assert_eq!(format_retries_header(&recorded).unwrap(), "3/alpha, 1/beta");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_auth_failure_skips_retries_and_falls_back() {
+        let candidates = chain(&["alpha", "beta", "gamma"]);
+        let attempts: Arc<Mutex<Vec<AttemptRecord>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let outcome: RetryOutcome<String, MockError> = retry_with_fallback(
+            &candidates,
+            attempts.clone(),
+            &fixed_backoff(),
+            2,
+            || true,
+            |info| {
+                let name = info.name.clone();
+                async move {
+                    match name.as_str() {
+                        "alpha" => Err(MockError { code: 401 }),
+                        "beta" => Err(MockError { code: 403 }),
+                        _ => Ok(name),
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(outcome.result.unwrap(), "gamma");
+        let recorded = attempts.lock().unwrap().clone();
+        assert_eq!(format_retries_header(&recorded).unwrap(), "1/alpha, 1/beta");
+        assert_eq!(recorded[0].kind, ProviderErrorKind::Auth);
+    }
 }
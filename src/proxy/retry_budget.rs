@@ -0,0 +1,169 @@
+//! Global retry budget.
+//!
+//! Retries on the primary provider and fallback attempts each spend one
+//! unit of budget. Over a rolling `[routing.retry_budget]` window the
+//! budget is `min_retries + ratio * requests`; once it is spent, failed
+//! requests are returned immediately instead of retried, and the request
+//! is logged with the `retry_budget=exhausted` tag.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::config::RetryBudgetConfig;
+
+/// Tag attached to requests that were denied a retry.
+pub const RETRY_BUDGET_TAG: (&str, &str) = ("retry_budget", "exhausted");
+
+#[derive(Debug, Default)]
+struct Window {
+    requests: VecDeque<Instant>,
+    retries: VecDeque<Instant>,
+}
+
+impl Window {
+    fn prune(&mut self, window: Duration, now: Instant) {
+        for events in [&mut self.requests, &mut self.retries] {
+            while events
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= window)
+            {
+                events.pop_front();
+            }
+        }
+    }
+}
+
+/// Rolling retry budget shared by all requests. Inert when unconfigured.
+#[derive(Debug, Default)]
+pub struct RetryBudget {
+    config: Option<RetryBudgetConfig>,
+    window: Mutex<Window>,
+    denied: AtomicU64,
+}
+
+/// Current budget usage.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryBudgetSnapshot {
+    pub requests: usize,
+    pub retries: usize,
+    pub allowed: usize,
+    /// Retries denied since startup.
+    pub denied_total: u64,
+}
+
+impl RetryBudget {
+    pub fn new(config: Option<RetryBudgetConfig>) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Count a request that may go on to retry.
+    pub fn record_request(&self) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.prune(Duration::from_secs(config.window_secs), now);
+        window.requests.push_back(now);
+    }
+
+    /// Spend one retry if the budget allows it.
+    pub fn try_acquire(&self) -> bool {
+        let Some(config) = &self.config else {
+            return true;
+        };
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.prune(Duration::from_secs(config.window_secs), now);
+        if window.retries.len() < allowed(config, window.requests.len()) {
+            window.retries.push_back(now);
+            true
+        } else {
+            self.denied.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    /// Usage over the current window, or `None` when disabled.
+    pub fn snapshot(&self) -> Option<RetryBudgetSnapshot> {
+        let config = self.config.as_ref()?;
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.prune(Duration::from_secs(config.window_secs), Instant::now());
+        Some(RetryBudgetSnapshot {
+            requests: window.requests.len(),
+            retries: window.retries.len(),
+            allowed: allowed(config, window.requests.len()),
+            denied_total: self.denied.load(Ordering::Relaxed),
+        })
+    }
+}
+
+fn allowed(config: &RetryBudgetConfig, requests: usize) -> usize {
+    config.min_retries + (config.ratio * requests as f64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(ratio: f64, min_retries: usize) -> RetryBudget {
+        RetryBudget::new(Some(RetryBudgetConfig {
+            ratio,
+            window_secs: 60,
+            min_retries,
+        }))
+    }
+
+    #[test]
+    fn disabled_budget_always_allows() {
+        let budget = RetryBudget::default();
+        for _ in 0..100 {
+            assert!(budget.try_acquire());
+        }
+        assert!(budget.snapshot().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_limited_to_ratio_of_requests() {
+        let budget = budget(0.2, 0);
+        for _ in 0..10 {
+            budget.record_request();
+        }
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+
+        let snapshot = budget.snapshot().unwrap();
+        assert_eq!(snapshot.requests, 10);
+        assert_eq!(snapshot.retries, 2);
+        assert_eq!(snapshot.allowed, 2);
+        assert_eq!(snapshot.denied_total, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn min_retries_allowed_without_traffic() {
+        let budget = budget(0.2, 3);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn budget_recovers_after_window() {
+        let budget = budget(0.0, 1);
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert!(budget.try_acquire());
+    }
+}
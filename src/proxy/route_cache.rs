@@ -0,0 +1,289 @@
+//! Reuse of candidate orderings across requests (`[routing] route_cache_ttl_ms`).
+//!
+//! Picking candidates filters every provider by model, tier and policy and
+//! sorts the survivors by routing cost. At high request rates the same
+//! (model, policy, tier) comes up over and over, so with a TTL set the
+//! router's ordering is kept and reused until it expires. Cached orderings
+//! are dropped when any circuit changes state. The router itself, with the
+//! provider rates it sorts by, is built once at startup and only changes on
+//! restart; entries are still tied to the router that computed them so an
+//! ordering is never served for another one. Everything applied after the
+//! router (circuit permits, maintenance, balances, reputation, canary,
+//! quotas) still runs on every request. Hits, misses and invalidations are
+//! served by `GET /v1/stats/route-cache`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use axum::{extract::State, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use super::server::AppState;
+use crate::config::Tier;
+use crate::error::Result;
+use crate::router::{Router, SelectedProvider};
+
+/// Most orderings cached at once; the cache is emptied beyond this.
+const MAX_ENTRIES: usize = 1024;
+
+/// What a router ordering depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RouteKey {
+    model: String,
+    /// The policy the request gets and whether its schedule is active.
+    policy: Option<(String, bool)>,
+    max_tier: Option<Tier>,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    /// Router the entries were computed by.
+    router: Weak<Router>,
+    /// Circuit transition count the entries were computed under.
+    circuit_transitions: u64,
+    entries: HashMap<RouteKey, (Instant, Vec<SelectedProvider>)>,
+}
+
+/// Recent candidate orderings and their hit counters.
+#[derive(Debug, Default)]
+pub struct RouteCache {
+    inner: Mutex<CacheInner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Response for GET /v1/stats/route-cache.
+#[derive(Debug, Serialize)]
+pub struct RouteCacheSnapshot {
+    pub enabled: bool,
+    pub ttl_ms: u64,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Times the cached orderings were dropped for a circuit change.
+    pub invalidations: u64,
+    /// `hits / (hits + misses)`; None before the first lookup.
+    pub hit_rate: Option<f64>,
+}
+
+impl RouteCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Drop the entries unless they came from `router` under
+    /// `circuit_transitions`.
+    fn validate(&self, inner: &mut CacheInner, router: &Arc<Router>, circuit_transitions: u64) {
+        if inner.router.as_ptr() == Arc::as_ptr(router)
+            && inner.circuit_transitions == circuit_transitions
+        {
+            return;
+        }
+        if !inner.entries.is_empty() {
+            inner.entries.clear();
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+        inner.router = Arc::downgrade(router);
+        inner.circuit_transitions = circuit_transitions;
+    }
+
+    /// [`Router::select_candidates`], reusing an ordering computed within
+    /// `ttl` by the same router under the same circuit transition count.
+    /// A zero `ttl` bypasses the cache. Errors are never cached.
+    #[allow(clippy::too_many_arguments)]
+    pub fn select_candidates(
+        &self,
+        router: &Arc<Router>,
+        ttl: Duration,
+        circuit_transitions: u64,
+        model: &str,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+        max_tier: Option<Tier>,
+    ) -> Result<Vec<SelectedProvider>> {
+        if ttl.is_zero() {
+            return router.select_candidates(model, policy_name, prompt, max_tier);
+        }
+        let now = Utc::now();
+        let key = RouteKey {
+            model: model.to_string(),
+            policy: router
+                .policy_state(policy_name, prompt, now)
+                .map(|(name, active)| (name.to_string(), active)),
+            max_tier,
+        };
+        {
+            let mut inner = self.lock();
+            self.validate(&mut inner, router, circuit_transitions);
+            if let Some((stored_at, candidates)) = inner.entries.get(&key) {
+                if stored_at.elapsed() < ttl {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(candidates.clone());
+                }
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let candidates = router.select_candidates_at(model, policy_name, prompt, max_tier, now)?;
+        let mut inner = self.lock();
+        self.validate(&mut inner, router, circuit_transitions);
+        inner
+            .entries
+            .retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        if inner.entries.len() >= MAX_ENTRIES {
+            inner.entries.clear();
+        }
+        inner
+            .entries
+            .insert(key, (Instant::now(), candidates.clone()));
+        Ok(candidates)
+    }
+
+    /// Current counters, with `ttl_ms` from `[routing] route_cache_ttl_ms`.
+    pub fn snapshot(&self, ttl_ms: u64) -> RouteCacheSnapshot {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        RouteCacheSnapshot {
+            enabled: ttl_ms > 0,
+            ttl_ms,
+            entries: self.lock().entries.len(),
+            hits,
+            misses,
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+        }
+    }
+}
+
+/// Handle GET /v1/stats/route-cache.
+pub async fn route_cache_stats_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(
+        state
+            .route_cache
+            .snapshot(state.config.routing.route_cache_ttl_ms),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PolicyRule, ProviderConfig};
+
+    fn provider(name: &str, output_rate: f64) -> ProviderConfig {
+        ProviderConfig {
+            models: vec!["gpt-4o".to_string()],
+            output_rate,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    fn router() -> Arc<Router> {
+        let capped = PolicyRule {
+            name: "capped".to_string(),
+            allowed_models: vec![],
+            strategy: "lowest_cost".to_string(),
+            max_sats_per_1k_output: Some(15.0),
+            keywords: vec!["cheap".to_string()],
+            backoff: None,
+            active_hours: None,
+            days: vec![],
+            utc_offset: None,
+            off_hours_tier: Tier::Local,
+            allowed_regions: vec![],
+            validate: None,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: None,
+            system_prompt_bypass_token: None,
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
+        };
+        Arc::new(Router::new(
+            vec![provider("a", 10.0), provider("b", 20.0)],
+            vec![capped],
+            "cheapest".to_string(),
+        ))
+    }
+
+    fn names(candidates: &[SelectedProvider]) -> Vec<&str> {
+        candidates.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    const TTL: Duration = Duration::from_millis(500);
+
+    #[tokio::test(start_paused = true)]
+    async fn repeats_hit_until_the_ttl_runs_out() {
+        let cache = RouteCache::default();
+        let router = router();
+        let select = |prompt| {
+            cache
+                .select_candidates(&router, TTL, 0, "gpt-4o", None, prompt, None)
+                .unwrap()
+        };
+
+        assert_eq!(names(&select(None)), ["a", "b"]);
+        assert_eq!(names(&select(None)), ["a", "b"]);
+        // A keyword-matched policy is its own entry
+        assert_eq!(names(&select(Some("something cheap"))), ["a"]);
+        assert_eq!(names(&select(Some("cheap again"))), ["a"]);
+        let snapshot = cache.snapshot(500);
+        assert_eq!((snapshot.hits, snapshot.misses), (2, 2));
+        assert_eq!(snapshot.entries, 2);
+        assert_eq!(snapshot.hit_rate, Some(0.5));
+
+        tokio::time::advance(TTL).await;
+        select(None);
+        assert_eq!(cache.snapshot(500).misses, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn circuit_changes_and_new_routers_invalidate() {
+        let cache = RouteCache::default();
+        let router = router();
+        let select = |router: &Arc<Router>, transitions| {
+            cache
+                .select_candidates(router, TTL, transitions, "gpt-4o", None, None, None)
+                .unwrap();
+        };
+
+        select(&router, 0);
+        select(&router, 0);
+        select(&router, 1);
+        let replaced = self::router();
+        select(&replaced, 1);
+        select(&replaced, 1);
+        let snapshot = cache.snapshot(500);
+        assert_eq!((snapshot.hits, snapshot.misses), (2, 3));
+        assert_eq!(snapshot.invalidations, 2);
+    }
+
+    #[test]
+    fn zero_ttl_and_errors_bypass_the_cache() {
+        let cache = RouteCache::default();
+        let router = router();
+        cache
+            .select_candidates(&router, Duration::ZERO, 0, "gpt-4o", None, None, None)
+            .unwrap();
+        assert!(cache
+            .select_candidates(&router, TTL, 0, "unknown", None, None, None)
+            .is_err());
+        let snapshot = cache.snapshot(0);
+        assert!(!snapshot.enabled);
+        assert_eq!(
+            (snapshot.hits, snapshot.misses, snapshot.entries),
+            (0, 1, 0)
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Request trace sampling (`[logging.sampling]`).
+//!
+//! Each request is sampled or not when it arrives: always when it carries
+//! `x-arbstr-debug: true`, otherwise with probability `success_rate`. The
+//! decision is recorded as the `sampled` field of the `request` span.
+//! [`SampledFilter`] drops info and debug events inside unsampled requests
+//! while keeping warnings and errors, so failures are always logged with
+//! their request context. The prompt archive follows the same decision: an
+//! unsampled request's messages are archived only if the request fails.
+
+use axum::http::HeaderMap;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Filter};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::SamplingConfig;
+
+/// Header forcing a request to be sampled and recording its decision trace.
+pub const ARBSTR_DEBUG_HEADER: &str = "x-arbstr-debug";
+
+/// Whether `headers` carry `x-arbstr-debug: true` (or `1`).
+pub fn debug_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(ARBSTR_DEBUG_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true") || v.trim() == "1")
+}
+
+/// Whether a request with `headers` is sampled.
+pub fn decide(headers: &HeaderMap, config: &SamplingConfig) -> bool {
+    let forced = config.debug_header && debug_requested(headers);
+    forced || config.success_rate >= 1.0 || rand::random::<f64>() < config.success_rate
+}
+
+/// Marks a span recorded with `sampled = false`.
+struct Unsampled;
+
+/// Per-layer filter that hides info and debug events inside spans recorded
+/// with `sampled = false`. Spans, warnings and errors always pass.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampledFilter;
+
+impl<S> Filter<S> for SampledFilter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        if meta.is_span() || *meta.level() <= Level::WARN {
+            return true;
+        }
+        cx.lookup_current().is_none_or(|span| {
+            span.scope()
+                .all(|s| s.extensions().get::<Unsampled>().is_none())
+        })
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, cx: Context<'_, S>) {
+        let mut visitor = SampledVisitor(true);
+        attrs.record(&mut visitor);
+        if !visitor.0 {
+            if let Some(span) = cx.span(id) {
+                span.extensions_mut().insert(Unsampled);
+            }
+        }
+    }
+}
+
+struct SampledVisitor(bool);
+
+impl Visit for SampledVisitor {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "sampled" {
+            self.0 = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_header_forces_sampling() {
+        let config = SamplingConfig {
+            success_rate: 0.0,
+            debug_header: true,
+        };
+        let mut headers = HeaderMap::new();
+        assert!(!decide(&headers, &config));
+        headers.insert(ARBSTR_DEBUG_HEADER, "true".parse().unwrap());
+        assert!(decide(&headers, &config));
+        assert!(!decide(
+            &headers,
+            &SamplingConfig {
+                debug_header: false,
+                ..config
+            }
+        ));
+        assert!(decide(&HeaderMap::new(), &SamplingConfig::default()));
+    }
+
+    #[test]
+    fn filter_hides_info_in_unsampled_spans() {
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::Layer;
+
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(move || writer.clone())
+                .with_ansi(false)
+                .with_filter(SampledFilter),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request", sampled = false).in_scope(|| {
+                tracing::info!("hidden routing detail");
+                tracing::warn!("kept failure");
+            });
+            tracing::info_span!("request", sampled = true).in_scope(|| {
+                tracing::info!("sampled routing detail");
+            });
+            tracing::info!("outside any request");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("hidden routing detail"), "{}", output);
+        assert!(output.contains("kept failure"), "{}", output);
+        assert!(output.contains("sampled routing detail"), "{}", output);
+        assert!(output.contains("outside any request"), "{}", output);
+    }
+}
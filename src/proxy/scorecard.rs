@@ -0,0 +1,227 @@
+//! Provider scorecard endpoint.
+//!
+//! Combines a provider's configured rates, live circuit breaker state, and
+//! request history over a time window into one response, for deciding
+//! whether a provider is worth keeping.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use super::slo::SloStatus;
+use super::stats::resolve_time_range;
+use crate::error::Error;
+use crate::storage::scorecard;
+
+/// Number of recent errors included in the scorecard.
+const RECENT_ERROR_LIMIT: u32 = 10;
+
+/// Query parameters for GET /v1/providers/{name}/scorecard.
+#[derive(Debug, Deserialize)]
+pub struct ScorecardQuery {
+    pub range: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// Response for GET /v1/providers/{name}/scorecard.
+#[derive(Debug, Serialize)]
+pub struct ScorecardResponse {
+    pub provider: String,
+    pub since: String,
+    pub until: String,
+    pub config: ConfigSection,
+    pub circuit: CircuitSection,
+    pub requests: RequestsSection,
+    pub latency: LatencySection,
+    pub cost: CostSection,
+    /// Most recent failed requests in the window, newest first.
+    pub last_errors: Vec<ErrorEntry>,
+    /// Live SLO compliance and burn rates (process lifetime, not windowed
+    /// by the query). Absent without `[providers.slo]` targets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloStatus>,
+}
+
+/// Configured rates and routing metadata.
+#[derive(Debug, Serialize)]
+pub struct ConfigSection {
+    pub models: Vec<String>,
+    pub input_rate_sats_per_1k: f64,
+    pub output_rate_sats_per_1k: f64,
+    pub base_fee_sats: f64,
+    pub min_charge_sats: f64,
+    pub tier: String,
+}
+
+/// Live circuit breaker state (process lifetime, not windowed).
+#[derive(Debug, Serialize)]
+pub struct CircuitSection {
+    pub state: String,
+    pub failure_count: u32,
+    /// Consecutive failures by error type.
+    pub failures_by_type: BTreeMap<String, u32>,
+    pub trip_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub recent_trips: Vec<TripEntry>,
+}
+
+/// One circuit trip.
+#[derive(Debug, Serialize)]
+pub struct TripEntry {
+    pub at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// Request outcome counts within the window.
+#[derive(Debug, Serialize)]
+pub struct RequestsSection {
+    pub total: i64,
+    pub success: i64,
+    pub error: i64,
+    /// `success / total`, or None when there were no requests.
+    pub success_rate: Option<f64>,
+}
+
+/// Latency over the window. Percentiles cover successful requests only.
+#[derive(Debug, Serialize)]
+pub struct LatencySection {
+    pub avg_ms: f64,
+    pub p50_ms: Option<i64>,
+    pub p95_ms: Option<i64>,
+}
+
+/// Spend over the window.
+#[derive(Debug, Serialize)]
+pub struct CostSection {
+    pub total_sats: f64,
+    /// Average cost of successful requests with a recorded cost.
+    pub avg_sats_per_request: Option<f64>,
+}
+
+/// A failed request.
+#[derive(Debug, Serialize)]
+pub struct ErrorEntry {
+    pub timestamp: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Handle GET /v1/providers/{name}/scorecard.
+///
+/// Returns 404 if the provider is not configured.
+pub async fn scorecard_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<ScorecardQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let provider = state
+        .router
+        .providers()
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(&name))
+        .ok_or_else(|| Error::NotFound(format!("Provider '{}' not found", name)))?;
+
+    let (since_dt, until_dt) = resolve_time_range(
+        params.range.as_deref(),
+        params.since.as_deref(),
+        params.until.as_deref(),
+    )?;
+    let since = since_dt.to_rfc3339();
+    let until = until_dt.to_rfc3339();
+
+    let summary = scorecard::query_provider_summary(pool, &since, &until, &provider.name).await?;
+    let p50 =
+        scorecard::query_latency_percentile(pool, &since, &until, &provider.name, 0.5).await?;
+    let p95 =
+        scorecard::query_latency_percentile(pool, &since, &until, &provider.name, 0.95).await?;
+    let errors =
+        scorecard::query_recent_errors(pool, &since, &until, &provider.name, RECENT_ERROR_LIMIT)
+            .await?;
+
+    let circuit = match state.circuit_breakers.details(&provider.name) {
+        Some(details) => CircuitSection {
+            state: details.state.as_str().to_string(),
+            failure_count: details.failure_count,
+            failures_by_type: details.failures_by_type,
+            trip_count: details.trip_count,
+            last_error: details
+                .last_error
+                .map(|e| format!("{}: {}", e.error_type, e.message)),
+            recent_trips: details
+                .recent_trips
+                .into_iter()
+                .map(|t| TripEntry {
+                    at: t.at,
+                    reason: t.reason,
+                })
+                .collect(),
+        },
+        None => CircuitSection {
+            state: "closed".to_string(),
+            failure_count: 0,
+            failures_by_type: BTreeMap::new(),
+            trip_count: 0,
+            last_error: None,
+            recent_trips: Vec::new(),
+        },
+    };
+
+    Ok(Json(ScorecardResponse {
+        provider: provider.name.clone(),
+        since,
+        until,
+        config: ConfigSection {
+            models: provider.models.clone(),
+            input_rate_sats_per_1k: provider.input_rate,
+            output_rate_sats_per_1k: provider.output_rate,
+            base_fee_sats: provider.base_fee,
+            min_charge_sats: provider.min_charge_sats,
+            tier: provider.tier.to_string(),
+        },
+        circuit,
+        requests: RequestsSection {
+            total: summary.total_requests,
+            success: summary.success_count,
+            error: summary.error_count,
+            success_rate: (summary.total_requests > 0)
+                .then(|| summary.success_count as f64 / summary.total_requests as f64),
+        },
+        latency: LatencySection {
+            avg_ms: summary.avg_latency_ms,
+            p50_ms: p50,
+            p95_ms: p95,
+        },
+        cost: CostSection {
+            total_sats: summary.total_cost_sats,
+            avg_sats_per_request: (summary.costed_requests > 0)
+                .then(|| summary.total_cost_sats / summary.costed_requests as f64),
+        },
+        last_errors: errors
+            .into_iter()
+            .map(|e| ErrorEntry {
+                timestamp: e.timestamp,
+                model: e.model,
+                status: e.error_status,
+                message: e.error_message,
+            })
+            .collect(),
+        slo: state.slo.status(&provider.name),
+    }))
+}
@@ -3,24 +3,54 @@
 use axum::{
     error_handling::HandleErrorLayer,
     middleware,
-    response::Response,
-    routing::{get, post},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
     Router,
 };
 use reqwest::Client;
 use sqlx::SqlitePool;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 
 use super::discovery;
 use uuid::Uuid;
 
+use super::anomaly::CostAnomalies;
+use super::canary::CanaryTracker;
 use super::circuit_breaker::CircuitBreakerRegistry;
+use super::compression::CompressionStats;
+use super::config_versions::ConfigVersions;
+use super::correlation::{client_request_id, ClientRequestIds, DUPLICATE_WINDOW};
+use super::currency::ExchangeRate;
+use super::dataset::DatasetCapture;
+use super::debug_trace::DebugTraces;
+use super::evaluation::QualityScores;
 use super::handlers;
+use super::ledger::ProviderLedger;
+use super::limits::LimitStats;
+use super::listen::Listener;
+use super::maintenance::ProviderMaintenance;
+use super::pool::{self, ProviderClients};
+use super::privacy::Anonymizer;
+use super::quarantine::AuthQuarantine;
+use super::quota::ProviderQuotas;
+use super::receipts::ReceiptSigner;
+use super::recent::RecentRequests;
+use super::recording::Recorder;
+use super::reputation::ReputationTracker;
+use super::retry_budget::RetryBudget;
+use super::route_cache::RouteCache;
+use super::slo::SloTracker;
+use super::stats::StatsCache;
+use super::tie_break::TieBreakTracker;
+use super::trace::TraceContext;
 use super::vault::VaultClient;
-use crate::config::Config;
+use super::vouchers::Vouchers;
+use super::warmup::WarmupTracker;
+use crate::config::{Config, SamplingConfig, StorageBackend};
+use crate::error::Error;
 use crate::router::Router as ProviderRouter;
 use crate::storage::DbWriter;
 
@@ -38,6 +68,64 @@ pub struct AppState {
     pub read_db: Option<SqlitePool>,
     pub db_writer: Option<DbWriter>,
     pub circuit_breakers: Arc<CircuitBreakerRegistry>,
+    /// Rolling per-provider reliability. Inert unless `[routing.reputation]` is set.
+    pub reputation: Arc<ReputationTracker>,
+    /// Ordering of equally priced candidates (`[routing] tie_breakers`).
+    pub tie_breaks: Arc<TieBreakTracker>,
+    /// Recent candidate orderings (`[routing] route_cache_ttl_ms`).
+    pub route_cache: Arc<RouteCache>,
+    /// Traffic limits and promotion state for `canary = true` providers.
+    pub canary: Arc<CanaryTracker>,
+    /// Global retry budget. Inert unless `[routing.retry_budget]` is set.
+    pub retry_budget: Arc<RetryBudget>,
+    /// Providers suspended after rejecting their API key.
+    pub auth_quarantine: Arc<AuthQuarantine>,
+    /// Client-side compression byte counters for `/v1/stats/compression`.
+    pub compression: Arc<CompressionStats>,
+    /// Edge limit rejection counters for `/v1/stats/limits`.
+    pub limits: Arc<LimitStats>,
+    /// Provider recording/replay. Inert unless `[recording]` is set.
+    pub recording: Arc<Recorder>,
+    /// Dedicated clients for providers with `[providers.pool]` settings, plus
+    /// per-provider connection stats for `/health`.
+    pub provider_clients: Arc<ProviderClients>,
+    /// The last requests, for `/v1/requests/recent` without DB reads.
+    pub recent: Arc<RecentRequests>,
+    /// Startup warmup progress and results for `/ready`.
+    pub warmup: Arc<WarmupTracker>,
+    /// Cached `/v1/stats` responses (`[stats] cache_ttl_secs`).
+    pub stats_cache: Arc<StatsCache>,
+    /// Remaining balances of prepaid providers (`balance_sats`).
+    pub ledger: Arc<ProviderLedger>,
+    /// Rolling usage against provider-declared rate quotas.
+    pub quotas: Arc<ProviderQuotas>,
+    /// Current fiat price of bitcoin for `[currency]` conversion.
+    pub exchange_rate: Arc<ExchangeRate>,
+    /// Request log anonymization (`[privacy]`).
+    pub privacy: Arc<Anonymizer>,
+    /// Client-supplied correlation IDs recently used, to reject repeats.
+    pub client_request_ids: Arc<ClientRequestIds>,
+    /// Latest `[evaluation]` quality score per provider/model pair.
+    pub quality: Arc<QualityScores>,
+    /// Providers taken out of rotation (`enabled`, `maintenance_until`).
+    pub maintenance: Arc<ProviderMaintenance>,
+    /// Instance key for signed request receipts.
+    pub receipts: Arc<ReceiptSigner>,
+    /// When this instance started, for uptime in `/v1/about`.
+    pub started_at: Instant,
+    /// Config version stamped on request rows.
+    pub config_versions: Arc<ConfigVersions>,
+    /// Provider SLO windows and burn-rate alerts (`[providers.slo]`).
+    pub slo: Arc<SloTracker>,
+    /// Appends sampled prompt/response pairs to policy dataset files
+    /// (`[policies.rules.dataset_capture]`).
+    pub datasets: Arc<DatasetCapture>,
+    /// Decision traces of recent `x-arbstr-debug: true` requests.
+    pub debug_traces: Arc<DebugTraces>,
+    /// Cost anomalies currently active (`[anomaly]`).
+    pub anomalies: Arc<CostAnomalies>,
+    /// Prepaid request vouchers (`x-arbstr-voucher`).
+    pub vouchers: Arc<Vouchers>,
     /// Vault treasury client. When Some, requests require vault billing.
     /// When None, arbstr runs in free proxy mode.
     pub vault: Option<VaultClient>,
@@ -46,7 +134,8 @@ pub struct AppState {
 /// Middleware that verifies the `Authorization: Bearer <token>` header.
 ///
 /// Returns 401 Unauthorized if the token is missing or incorrect.
-/// Only applied when `server.auth_token` is set in config.
+/// Only applied when `server.auth_token` or `server.admin_token` is set
+/// in config.
 async fn auth_middleware(
     expected_token: Arc<String>,
     request: axum::http::Request<axum::body::Body>,
@@ -78,52 +167,224 @@ async fn auth_middleware(
     }
 }
 
-/// Middleware that generates a correlation ID and stores it in request extensions.
+/// Middleware that picks a correlation ID (the client's `x-arbstr-request-id`
+/// UUID, or a generated one) and trace context, stores them in request
+/// extensions, and echoes `traceparent`/`x-request-id` on the response.
+///
+/// A client ID already used within the duplicate window is rejected with 409.
 async fn inject_request_id(
+    client_ids: Arc<ClientRequestIds>,
+    sampling: SamplingConfig,
     mut request: axum::http::Request<axum::body::Body>,
     next: middleware::Next,
 ) -> Response {
-    let request_id = Uuid::new_v4();
+    let request_id = match client_request_id(request.headers()) {
+        Some(id) if !client_ids.claim(id) => {
+            tracing::warn!(request_id = %id, "Rejecting duplicate x-arbstr-request-id");
+            return Error::Conflict(format!(
+                "x-arbstr-request-id {} was already used in the last {} seconds",
+                id,
+                DUPLICATE_WINDOW.as_secs()
+            ))
+            .into_response();
+        }
+        Some(id) => id,
+        None => Uuid::new_v4(),
+    };
+    let mut trace = TraceContext::from_headers(request.headers(), &request_id.to_string());
+    trace.sampled = super::sampling::decide(request.headers(), &sampling);
     request.extensions_mut().insert(RequestId(request_id));
-    next.run(request).await
+    request.extensions_mut().insert(trace.clone());
+    let mut response = next.run(request).await;
+    trace.apply_response(response.headers_mut());
+    response
+}
+
+/// Middleware for routes a voucher can pay for: a request carrying
+/// `x-arbstr-voucher` is let through on an issued voucher alone (its limits
+/// are enforced when it is redeemed), anything else needs the bearer token
+/// if one is configured.
+async fn voucher_auth_middleware(
+    expected_token: Option<Arc<String>>,
+    vouchers: Arc<Vouchers>,
+    request: axum::http::Request<axum::body::Body>,
+    next: middleware::Next,
+) -> Response {
+    match super::vouchers::from_headers(request.headers()) {
+        Ok(Some(code)) if vouchers.is_known(code) => next.run(request).await,
+        Ok(Some(_)) => Error::PaymentRequired("Unknown voucher".to_string()).into_response(),
+        Err(e) => e.into_response(),
+        Ok(None) => match expected_token {
+            Some(token) => auth_middleware(token, request, next).await,
+            None => next.run(request).await,
+        },
+    }
+}
+
+/// Put `routes` behind a voucher or, failing that, a bearer token if one
+/// is given.
+fn require_voucher_or_token(
+    routes: Router<AppState>,
+    token: Option<String>,
+    vouchers: Arc<Vouchers>,
+) -> Router<AppState> {
+    let token = token.map(Arc::new);
+    routes.layer(middleware::from_fn(move |req, next| {
+        voucher_auth_middleware(token.clone(), vouchers.clone(), req, next)
+    }))
+}
+
+/// Put `routes` behind a bearer token, if one is given.
+fn require_token(routes: Router<AppState>, token: Option<String>) -> Router<AppState> {
+    match token {
+        Some(token) => {
+            let token = Arc::new(token);
+            routes.layer(middleware::from_fn(move |req, next| {
+                let token = token.clone();
+                auth_middleware(token, req, next)
+            }))
+        }
+        None => routes,
+    }
+}
+
+/// Which endpoints a router serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouterScope {
+    /// Every endpoint.
+    All,
+    /// Everything but analytics, for `listen` when `analytics_listen` is set.
+    Proxy,
+    /// Read-only analytics, for `analytics_listen`.
+    Analytics,
 }
 
 /// Create the axum router with all endpoints.
 pub fn create_router(state: AppState) -> Router {
+    create_scoped_router(state, RouterScope::All)
+}
+
+/// Create the axum router for the endpoints in `scope`.
+///
+/// Routes fall in three groups: the client-facing proxy (`auth_token`, or
+/// an `x-arbstr-voucher` on all but `/v1/conversations`), analytics and logs (`admin_token`; open without one), and `/admin`
+/// operations (`admin_token`, else `auth_token`). `/v1/debug/echo` is
+/// analytics guarded like `/admin`. Analytics is every route
+/// in [`RouterScope::Analytics`]; the others are [`RouterScope::Proxy`].
+/// `/health` and `/ready` are in every scope and always open for probes.
+pub fn create_scoped_router(state: AppState, scope: RouterScope) -> Router {
     let rate_limit_rps = state.config.server.rate_limit_rps;
     let auth_token = state.config.server.auth_token.clone();
+    let admin_token = state.config.server.admin_token.clone();
     let has_vault = state.vault.is_some();
+    let compression_stats = state.compression.clone();
+    let cors = state.config.server.cors.clone();
+    let limits = state.config.server.limits.clone();
+    let limit_stats = state.limits.clone();
+    let client_ids = state.client_request_ids.clone();
+    let sampling = state.config.logging.sampling;
 
-    // Proxy endpoints that require auth (when configured)
-    let proxy_routes = Router::new()
+    // Proxy endpoints for clients; those a voucher pays for, or that cost
+    // nothing, also take a voucher in place of the bearer token
+    let voucher_routes = Router::new()
         .route("/v1/chat/completions", post(handlers::chat_completions))
+        .route(
+            "/v1/chat/completions/ws",
+            get(super::websocket::chat_completions_ws),
+        )
         .route("/v1/models", get(handlers::list_models))
-        .route("/v1/cost", post(handlers::cost_estimate));
+        .route("/v1/cost", post(handlers::cost_estimate))
+        .route("/v1/compare", post(handlers::compare));
+    let proxy_routes = Router::new().route(
+        "/v1/conversations/:id",
+        get(super::conversation::conversation_handler),
+    );
 
     // Apply auth middleware only if a token is configured AND vault is not handling auth.
     // When vault is configured, the vault's reserve call validates the agent token.
-    let proxy_routes = if !has_vault {
-        if let Some(token) = auth_token {
-            let token = Arc::new(token);
-            proxy_routes.layer(middleware::from_fn(move |req, next| {
-                let token = token.clone();
-                auth_middleware(token, req, next)
-            }))
-        } else {
-            proxy_routes
-        }
-    } else {
-        proxy_routes
-    };
+    let proxy_token = if has_vault { None } else { auth_token.clone() };
+    let proxy_routes = require_token(proxy_routes, proxy_token.clone()).merge(
+        require_voucher_or_token(voucher_routes, proxy_token, state.vouchers.clone()),
+    );
 
-    let mut app = proxy_routes
-        // arbstr extensions (no auth required)
+    // Stats, logs, and routing introspection: operator-only with an admin token
+    let analytics_routes = Router::new()
+        .route("/v1/about", get(super::about::about_handler))
         .route("/v1/stats", get(handlers::stats))
+        .route("/v1/stats/forecast", get(handlers::forecast))
+        .route("/v1/stats/truncation", get(handlers::truncation))
+        .route("/v1/stats/reliability", get(handlers::reliability))
+        .route("/v1/stats/compression", get(handlers::compression_stats))
+        .route("/v1/stats/limits", get(handlers::limits_stats))
+        .route(
+            "/v1/stats/route-cache",
+            get(super::route_cache::route_cache_stats_handler),
+        )
+        .route("/v1/arbitrage", get(handlers::arbitrage))
         .route("/v1/requests", get(handlers::logs))
-        .route("/health", get(handlers::health))
+        .route("/v1/requests/recent", get(handlers::recent_requests))
+        .route("/v1/requests/:id", get(handlers::request_detail))
+        .route("/v1/requests/:id/receipt", get(handlers::receipt))
+        .route(
+            "/v1/requests/:id/trace",
+            get(super::debug_trace::trace_handler),
+        )
+        .route("/v1/compare/:id", get(handlers::comparison))
+        .route("/v1/route/explain", get(handlers::route_explain))
         .route("/providers", get(handlers::list_providers))
-        // State and middleware
-        .with_state(state);
+        .route(
+            "/v1/providers/:name/scorecard",
+            get(handlers::provider_scorecard),
+        );
+    // The debug echo shows policy system prompts and other injected
+    // content, so like /admin it falls back to the client token
+    let echo_routes = Router::new().route("/v1/debug/echo", post(super::echo::echo_handler));
+    let analytics_routes = require_token(analytics_routes, admin_token.clone()).merge(
+        require_token(echo_routes, admin_token.clone().or(auth_token.clone())),
+    );
+
+    // Operator endpoints: always behind a token when one is configured
+    let admin_routes = Router::new()
+        .route("/admin/db", get(super::admin::db_info_handler))
+        .route("/admin/db/backup", post(super::admin::db_backup_handler))
+        .route(
+            "/admin/db/checkpoint",
+            post(super::admin::db_checkpoint_handler),
+        )
+        .route("/admin/ledger", get(super::ledger::ledger_handler))
+        .route(
+            "/admin/ledger/:provider/topup",
+            post(super::ledger::topup_handler),
+        )
+        .route(
+            "/admin/vouchers",
+            get(super::vouchers::vouchers_handler).post(super::vouchers::create_voucher_handler),
+        )
+        .route(
+            "/admin/providers/:name/maintenance",
+            put(super::maintenance::set_maintenance_handler),
+        )
+        .route(
+            "/admin/config/versions",
+            get(super::config_versions::versions_handler),
+        )
+        .route(
+            "/admin/config/diff",
+            get(super::config_diff::last_diff_handler).post(super::config_diff::dry_run_handler),
+        );
+    let admin_routes = require_token(admin_routes, admin_token.or(auth_token));
+
+    let mut routes = Router::new()
+        .route("/health", get(handlers::health))
+        .route("/ready", get(handlers::ready));
+    if scope != RouterScope::Analytics {
+        routes = routes.merge(proxy_routes).merge(admin_routes);
+    }
+    if scope != RouterScope::Proxy {
+        routes = routes.merge(analytics_routes);
+    }
+    // State and middleware
+    let mut app = routes.with_state(state);
 
     // Apply rate limiting if configured (buffer + rate limit for Clone compatibility)
     if let Some(rps) = rate_limit_rps {
@@ -143,6 +404,24 @@ pub fn create_router(state: AppState) -> Router {
         }
     }
 
+    if limits.is_enabled() {
+        tracing::info!(
+            max_body_bytes = ?limits.max_body_bytes,
+            request_timeout_secs = ?limits.request_timeout_secs,
+            max_concurrent_requests = ?limits.max_concurrent_requests,
+            "Edge limits enabled"
+        );
+        app = super::limits::layer(app, &limits, limit_stats);
+    }
+
+    // Outside auth and rate limiting so preflights pass and errors stay readable
+    if let Some(cors) = &cors {
+        tracing::info!(origins = ?cors.allowed_origins, "CORS enabled");
+        app = app.layer(super::cors::layer(cors));
+    }
+
+    let app = super::compression::layer(app, compression_stats);
+
     app.layer(TraceLayer::new_for_http().make_span_with(
         |request: &axum::http::Request<axum::body::Body>| {
             let request_id = request
@@ -150,29 +429,41 @@ pub fn create_router(state: AppState) -> Router {
                 .get::<RequestId>()
                 .map(|r| r.0)
                 .unwrap_or_else(Uuid::new_v4);
+            let trace = request.extensions().get::<TraceContext>();
             tracing::info_span!(
                 "request",
                 method = %request.method(),
                 uri = %request.uri(),
                 request_id = %request_id,
+                trace_id = %trace.map(|t| t.trace_id.as_str()).unwrap_or_default(),
+                sampled = trace.is_none_or(|t| t.sampled),
             )
         },
     ))
-    .layer(middleware::from_fn(inject_request_id))
+    .layer(middleware::from_fn(move |req, next| {
+        inject_request_id(client_ids.clone(), sampling, req, next)
+    }))
 }
 
 /// Run the HTTP server.
 pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
-    let listen_addr = config.server.listen.clone();
+    let listen_addrs = config
+        .server
+        .listen_addrs()
+        .map_err(|e| anyhow::anyhow!("server.listen {}", e))?;
+    let analytics_addrs = config
+        .server
+        .analytics_listen_addrs()
+        .map_err(|e| anyhow::anyhow!("server.analytics_listen {}", e))?;
 
     // Create HTTP client with reasonable defaults (needed for discovery before router init)
-    let http_client = Client::builder()
-        .timeout(Duration::from_secs(120))
-        .connect_timeout(Duration::from_secs(10))
-        .build()?;
+    let resolver = super::dns::resolver(&config.dns)?;
+    let http_client = pool::client_builder(resolver.as_ref()).build()?;
+    let provider_clients = Arc::new(ProviderClients::new(&config.providers, resolver.as_ref())?);
 
     // Discover models for auto_discover providers
-    discovery::discover_models(&mut config.providers, &http_client).await;
+    discovery::discover_models_with_clients(&mut config.providers, &http_client, &provider_clients)
+        .await;
 
     // Create provider router (after discovery so models are populated)
     let provider_router = ProviderRouter::new(
@@ -182,8 +473,23 @@ pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
     );
 
     // Initialize database pool if configured
-    let db = {
-        let db_config = config.database();
+    let db_config = config.database();
+    let in_memory = db_config.backend == StorageBackend::Memory;
+    let db = if in_memory {
+        match crate::storage::memory::init_pool().await {
+            Ok(pool) => {
+                tracing::warn!(
+                    max_rows = db_config.max_rows,
+                    "In-memory database initialized; request history will not survive a restart"
+                );
+                Some(pool)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to initialize in-memory database, logging disabled");
+                None
+            }
+        }
+    } else {
         match crate::storage::init_pool(&db_config.path).await {
             Ok(pool) => {
                 tracing::info!(path = %db_config.path, "Database initialized");
@@ -198,28 +504,114 @@ pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
 
     // Initialize read-only database pool for stats queries
     let read_db = match &db {
-        Some(_) => {
-            let db_config = config.database();
-            match crate::storage::init_read_pool(&db_config.path).await {
-                Ok(pool) => {
-                    tracing::info!("Read-only database pool initialized");
-                    Some(pool)
-                }
-                Err(e) => {
-                    tracing::warn!(error = %e, "Failed to initialize read-only pool, stats disabled");
-                    None
-                }
+        // The in-memory database exists only on the write pool's connection
+        Some(pool) if in_memory => Some(pool.clone()),
+        Some(_) => match crate::storage::init_read_pool(&db_config.path).await {
+            Ok(pool) => {
+                tracing::info!("Read-only database pool initialized");
+                Some(pool)
             }
-        }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to initialize read-only pool, stats disabled");
+                None
+            }
+        },
         None => None,
     };
 
     // Initialize bounded DB writer if database is available
-    let db_writer = db.as_ref().map(|pool| DbWriter::new(pool.clone()));
+    let db_writer = db.as_ref().map(|pool| {
+        if in_memory {
+            DbWriter::with_row_limit(pool.clone(), db_config.max_rows)
+        } else {
+            DbWriter::new(pool.clone())
+        }
+    });
 
     // Initialize circuit breaker registry with one breaker per provider
     let provider_names: Vec<String> = config.providers.iter().map(|p| p.name.clone()).collect();
-    let circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&provider_names));
+    let circuit_breakers = Arc::new(
+        CircuitBreakerRegistry::new(&provider_names)
+            .with_failure_thresholds(
+                config.routing.circuit_breaker.failure_threshold,
+                &config.routing.circuit_breaker.thresholds,
+            )
+            .with_trip_storm(
+                config.routing.circuit_breaker.trip_storm.clone(),
+                http_client.clone(),
+            )
+            .with_synthetic_probe(
+                config.routing.circuit_breaker.synthetic_probe,
+                &config.providers,
+            ),
+    );
+
+    let reputation = Arc::new(ReputationTracker::new(config.routing.reputation.clone()));
+    let tie_breaks = Arc::new(TieBreakTracker::new(
+        &config.routing.tie_breakers,
+        &config.providers,
+    ));
+    let canary = Arc::new(CanaryTracker::new(
+        &config.providers,
+        config.routing.canary.clone(),
+    ));
+
+    // Routing state only outlives the process in a database file
+    let state_db = db
+        .as_ref()
+        .filter(|_| config.routing.persist_state && !in_memory)
+        .cloned();
+    if let Some(pool) = &state_db {
+        restore_routing_state(pool, &reputation, &canary, &circuit_breakers).await;
+    }
+
+    let retry_budget = Arc::new(RetryBudget::new(config.routing.retry_budget.clone()));
+    let auth_quarantine = Arc::new(AuthQuarantine::new(config.routing.auth_quarantine.clone()));
+    let slo = Arc::new(SloTracker::new(
+        config.routing.slo.clone(),
+        &config.providers,
+        http_client.clone(),
+    ));
+
+    let ledger = Arc::new(ProviderLedger::new(&config.providers));
+    if let Some(pool) = &db {
+        if let Err(e) = ledger.restore(pool).await {
+            tracing::warn!(error = %e, "Failed to restore provider ledger, starting from configured balances");
+        }
+    }
+
+    let vouchers = Arc::new(Vouchers::default());
+    if let Some(pool) = &db {
+        if let Err(e) = vouchers.restore(pool).await {
+            tracing::warn!(error = %e, "Failed to restore vouchers");
+        }
+    }
+
+    let config_versions = Arc::new(ConfigVersions::default());
+    if let Some(pool) = &db {
+        match config_versions.restore(pool, &config).await {
+            Ok(version) => tracing::info!(version, "Config version"),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to record config version, request rows will carry none")
+            }
+        }
+    }
+
+    let quotas = Arc::new(ProviderQuotas::new(&config.providers));
+    let maintenance = Arc::new(ProviderMaintenance::new(&config.providers));
+    let quality = Arc::new(QualityScores::new(config.evaluation.as_ref()));
+    if let Some(pool) = &db {
+        if let Err(e) = quality.restore(pool).await {
+            tracing::warn!(error = %e, "Failed to restore evaluation quality scores");
+        }
+    }
+    let exchange_rate = Arc::new(ExchangeRate::new(config.currency.as_ref()));
+    let privacy = Arc::new(Anonymizer::new(&config.privacy));
+    let receipts = Arc::new(ReceiptSigner::new(&config));
+    let recording = Arc::new(
+        Recorder::new(config.recording.as_ref())
+            .map_err(|e| anyhow::anyhow!("[recording] {}", e))?,
+    );
 
     // Initialize vault client if configured
     let vault = config.vault.as_ref().map(|vault_config| {
@@ -235,6 +627,34 @@ pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
         read_db,
         db_writer,
         circuit_breakers,
+        reputation,
+        tie_breaks,
+        route_cache: Arc::new(RouteCache::default()),
+        canary,
+        retry_budget,
+        auth_quarantine,
+        compression: Arc::new(CompressionStats::default()),
+        limits: Arc::new(LimitStats::default()),
+        recording,
+        provider_clients,
+        recent: Arc::new(RecentRequests::default()),
+        warmup: Arc::new(WarmupTracker::default()),
+        stats_cache: Arc::new(StatsCache::default()),
+        ledger,
+        quotas,
+        exchange_rate,
+        privacy,
+        client_request_ids: Default::default(),
+        quality,
+        maintenance,
+        receipts,
+        started_at: Instant::now(),
+        config_versions,
+        slo,
+        datasets: Arc::new(DatasetCapture::default()),
+        debug_traces: Arc::new(DebugTraces::default()),
+        anomalies: Arc::new(CostAnomalies::default()),
+        vouchers,
         vault,
     };
 
@@ -256,14 +676,288 @@ pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
             None
         };
 
-    let app = create_router(state);
+    // Spawn periodic backup task if configured
+    let backup_cancel = match (state.config.database().backup, &state.db) {
+        (Some(backup_config), Some(db_pool)) if backup_config.interval_hours > 0 => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tracing::info!(
+                dir = %backup_config.dir,
+                interval_hours = backup_config.interval_hours,
+                keep = backup_config.keep,
+                "Database backup task started"
+            );
+            tokio::spawn(crate::storage::backup::backup_loop(
+                db_pool.clone(),
+                backup_config,
+                cancel_rx,
+            ));
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn periodic routing state saves, so a crash keeps open circuits open
+    let routing_state_cancel = match &state_db {
+        Some(pool) if state.config.routing.persist_interval_secs > 0 => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            let interval_secs = state.config.routing.persist_interval_secs;
+            tokio::spawn(routing_state_loop(
+                pool.clone(),
+                state.reputation.clone(),
+                state.canary.clone(),
+                state.circuit_breakers.clone(),
+                Duration::from_secs(interval_secs),
+                cancel_rx,
+            ));
+            tracing::info!(interval_secs, "Routing state save task started");
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn synthetic circuit probes if enabled
+    let synthetic_probe_cancel = if state.config.routing.circuit_breaker.synthetic_probe {
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        tokio::spawn(super::circuit_breaker::synthetic_probe_loop(
+            state.clone(),
+            cancel_rx,
+        ));
+        tracing::info!("Synthetic circuit probe task started");
+        Some(cancel_tx)
+    } else {
+        None
+    };
+
+    // Spawn rollup refresh task if enabled
+    let rollups_cancel = match (&state.db, &state.config.stats) {
+        (Some(db_pool), stats) if stats.rollups => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(crate::storage::rollups::rollup_loop(
+                db_pool.clone(),
+                Duration::from_secs(stats.rollup_interval_secs),
+                cancel_rx,
+            ));
+            tracing::info!(
+                interval_secs = stats.rollup_interval_secs,
+                "Stats rollup task started"
+            );
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn correlation ID retention task if configured and a DB is available
+    let privacy_cancel = match (&state.db, state.config.privacy.correlation_retention_days) {
+        (Some(db_pool), Some(days)) => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::privacy::retention_loop(
+                db_pool.clone(),
+                days,
+                cancel_rx,
+            ));
+            tracing::info!(
+                retention_days = days,
+                "Correlation ID retention task started"
+            );
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn exchange rate polling task if a price source is configured
+    let currency_cancel = match &state.config.currency {
+        Some(currency) if currency.price_url.is_some() => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::currency::rate_loop(
+                state.http_client.clone(),
+                currency.clone(),
+                state.exchange_rate.clone(),
+                cancel_rx,
+            ));
+            tracing::info!(
+                currency = %currency.code,
+                refresh_secs = currency.refresh_secs,
+                "Exchange rate task started"
+            );
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn scheduled report task if reports are configured and DB is available
+    let reports_cancel =
+        if let (Some(reports), Some(read_pool)) = (&state.config.reports, &state.read_db) {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::reports::report_loop(
+                state.config.clone(),
+                read_pool.clone(),
+                state.http_client.clone(),
+                cancel_rx,
+            ));
+            tracing::info!(
+                schedule = %reports.schedule,
+                hour_utc = reports.hour_utc,
+                "Scheduled report task started"
+            );
+            Some(cancel_tx)
+        } else {
+            None
+        };
+
+    // Spawn nightly evaluation task if a prompt suite is configured
+    let evaluation_cancel = match &state.config.evaluation {
+        Some(evaluation) => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::evaluation::evaluation_loop(state.clone(), cancel_rx));
+            tracing::info!(
+                hour_utc = evaluation.hour_utc,
+                prompts = evaluation.prompts.len(),
+                "Evaluation task started"
+            );
+            Some(cancel_tx)
+        }
+        None => None,
+    };
+
+    // Spawn arbitrage analysis task if enabled and a DB is available
+    let arbitrage_cancel = match (&state.read_db, &state.config.arbitrage) {
+        (Some(read_pool), arbitrage) if arbitrage.enabled => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::arbitrage::arbitrage_loop(
+                state.clone(),
+                read_pool.clone(),
+                arbitrage.clone(),
+                cancel_rx,
+            ));
+            tracing::info!(
+                interval_secs = arbitrage.interval_secs,
+                window_hours = arbitrage.window_hours,
+                min_savings_sats_per_day = arbitrage.min_savings_sats_per_day,
+                "Arbitrage analysis task started"
+            );
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
 
-    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
-    tracing::info!(address = %listen_addr, "Starting arbstr proxy server");
+    // Spawn cost anomaly detection if configured and a DB is available
+    let anomaly_cancel = match (&state.read_db, &state.config.anomaly) {
+        (Some(read_pool), Some(anomaly)) => {
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(super::anomaly::anomaly_loop(
+                state.clone(),
+                read_pool.clone(),
+                anomaly.clone(),
+                cancel_rx,
+            ));
+            tracing::info!(
+                interval_secs = anomaly.interval_secs,
+                bucket_minutes = anomaly.bucket_minutes,
+                baseline_buckets = anomaly.baseline_buckets,
+                z_threshold = anomaly.z_threshold,
+                "Cost anomaly task started"
+            );
+            Some(cancel_tx)
+        }
+        _ => None,
+    };
+
+    // Spawn Nostr catalogue announcements if configured
+    let nostr_cancel = match &state.config.nostr {
+        Some(nostr) if nostr.announce => {
+            match super::nostr::Keys::from_hex(nostr.secret_key.expose_secret()) {
+                Ok(keys) => {
+                    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+                    tracing::info!(
+                        pubkey = %keys.public_key(),
+                        relays = nostr.relays.len(),
+                        interval_secs = nostr.interval_secs,
+                        "Nostr announcement task started"
+                    );
+                    tokio::spawn(super::nostr::announce_loop(
+                        state.clone(),
+                        nostr.clone(),
+                        keys,
+                        cancel_rx,
+                    ));
+                    Some(cancel_tx)
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Nostr announcements disabled");
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    let chaos = &state.config.chaos;
+    if chaos.enabled {
+        tracing::warn!(
+            providers = ?chaos.providers,
+            error_rate = chaos.error_rate,
+            drop_stream_rate = chaos.drop_stream_rate,
+            malformed_sse_rate = chaos.malformed_sse_rate,
+            latency_ms = chaos.latency_ms,
+            "Chaos mode enabled: provider failures will be injected"
+        );
+    }
+
+    if state.config.warmup.enabled {
+        state.warmup.begin();
+        let warmup_state = state.clone();
+        tokio::spawn(async move {
+            super::warmup::run(&warmup_state).await;
+        });
+    }
+
+    let (reputation, canary, circuit_breakers) = (
+        state.reputation.clone(),
+        state.canary.clone(),
+        state.circuit_breakers.clone(),
+    );
+    let (app, analytics_app) = if analytics_addrs.is_empty() {
+        (create_router(state), None)
+    } else {
+        (
+            create_scoped_router(state.clone(), RouterScope::Proxy),
+            Some(create_scoped_router(state, RouterScope::Analytics)),
+        )
+    };
+
+    // Bind everything before serving anything, so a bad address fails startup
+    let mut bindings: Vec<_> = listen_addrs.iter().map(|a| (a, &app, "proxy")).collect();
+    if let Some(analytics_app) = &analytics_app {
+        bindings.extend(
+            analytics_addrs
+                .iter()
+                .map(|a| (a, analytics_app, "analytics")),
+        );
+    }
+    let mut listeners = Vec::with_capacity(bindings.len());
+    for (addr, router, surface) in bindings {
+        let listener = Listener::bind(addr)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to listen on {}: {}", addr, e))?;
+        listeners.push((listener, router.clone(), surface));
+    }
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    let servers: Vec<_> = listeners
+        .into_iter()
+        .map(|(listener, router, surface)| {
+            tracing::info!(address = %listener.local_addr(), surface, "Starting arbstr server");
+            tokio::spawn(listener.serve(router, shutdown_rx.clone()))
+        })
+        .collect();
+    for server in servers {
+        server.await??;
+    }
 
     // Signal reconciliation task to stop and do a final pass
     if let Some(cancel_tx) = reconciliation_cancel {
@@ -272,10 +966,125 @@ pub async fn run_server(mut config: Config) -> anyhow::Result<()> {
         tokio::time::sleep(Duration::from_secs(2)).await;
     }
 
+    if let Some(cancel_tx) = reports_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = evaluation_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = arbitrage_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = anomaly_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = nostr_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = backup_cancel {
+        let _ = cancel_tx.send(true);
+    }
+
+    if let Some(cancel_tx) = rollups_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = currency_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = privacy_cancel {
+        let _ = cancel_tx.send(true);
+    }
+
+    if let Some(cancel_tx) = synthetic_probe_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(cancel_tx) = routing_state_cancel {
+        let _ = cancel_tx.send(true);
+    }
+    if let Some(pool) = &state_db {
+        persist_routing_state(pool, &reputation, &canary, &circuit_breakers).await;
+        tracing::info!("Routing state saved");
+    }
+
     tracing::info!("Server shutdown complete");
     Ok(())
 }
 
+/// Reload reputation, canary and circuit breaker state saved by the
+/// previous process.
+async fn restore_routing_state(
+    pool: &sqlx::SqlitePool,
+    reputation: &ReputationTracker,
+    canary: &CanaryTracker,
+    circuit_breakers: &CircuitBreakerRegistry,
+) {
+    match reputation.restore(pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(providers = n, "Provider reputation restored"),
+        Err(e) => tracing::warn!(error = %e, "Failed to restore provider reputation"),
+    }
+    match canary.restore(pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(providers = n, "Canary progress restored"),
+        Err(e) => tracing::warn!(error = %e, "Failed to restore canary progress"),
+    }
+    match circuit_breakers.restore(pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(providers = n, "Circuit breaker state restored"),
+        Err(e) => tracing::warn!(error = %e, "Failed to restore circuit breaker state"),
+    }
+}
+
+/// Save reputation, canary and circuit breaker state for the next start.
+async fn persist_routing_state(
+    pool: &sqlx::SqlitePool,
+    reputation: &ReputationTracker,
+    canary: &CanaryTracker,
+    circuit_breakers: &CircuitBreakerRegistry,
+) {
+    match reputation.persist(pool).await {
+        Ok(n) => tracing::debug!(providers = n, "Provider reputation saved"),
+        Err(e) => tracing::warn!(error = %e, "Failed to save provider reputation"),
+    }
+    match canary.persist(pool).await {
+        Ok(n) => tracing::debug!(providers = n, "Canary progress saved"),
+        Err(e) => tracing::warn!(error = %e, "Failed to save canary progress"),
+    }
+    match circuit_breakers.persist(pool).await {
+        Ok(n) => tracing::debug!(providers = n, "Circuit breaker state saved"),
+        Err(e) => tracing::warn!(error = %e, "Failed to save circuit breaker state"),
+    }
+}
+
+/// Save routing state every `interval` until cancelled.
+async fn routing_state_loop(
+    pool: sqlx::SqlitePool,
+    reputation: Arc<ReputationTracker>,
+    canary: Arc<CanaryTracker>,
+    circuit_breakers: Arc<CircuitBreakerRegistry>,
+    interval: Duration,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick completes immediately; state was just restored
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                persist_routing_state(&pool, &reputation, &canary, &circuit_breakers).await;
+            }
+            changed = cancel.changed() => {
+                if changed.is_err() || *cancel.borrow() {
+                    tracing::info!("Routing state save task shutting down");
+                    break;
+                }
+            }
+        }
+    }
+}
+
 /// Wait for a shutdown signal (SIGINT or SIGTERM on Unix, Ctrl+C on all platforms).
 async fn shutdown_signal() {
     let ctrl_c = async {
@@ -0,0 +1,398 @@
+//! Provider SLO tracking and burn-rate alerts.
+//!
+//! Providers with `[providers.slo]` targets have the outcome of each
+//! request they answer kept for `[routing.slo] window_secs`. Each target
+//! has an error budget: a `success_rate` of 0.99 allows 1% of requests to
+//! fail, and `p95_latency_ms` allows 5% of successful requests to be
+//! slower. The burn rate over `alert_window_secs` is the share of bad
+//! requests divided by that budget. Reaching `burn_rate_threshold` logs an
+//! alert and POSTs it to `webhook_url` when set; falling back below logs
+//! (and POSTs) a recovery. Compliance over the full window is reported in
+//! the provider scorecard. State is in memory and resets on restart.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use reqwest::Client;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::config::{ProviderConfig, ProviderSloConfig, SloConfig};
+
+/// Timeout for the alert webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Share of successful requests allowed above `p95_latency_ms`.
+const LATENCY_BUDGET: f64 = 0.05;
+
+/// An SLO target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Objective {
+    SuccessRate,
+    P95Latency,
+}
+
+impl Objective {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Objective::SuccessRate => "success_rate",
+            Objective::P95Latency => "p95_latency",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Outcome {
+    at: Instant,
+    success: bool,
+    latency_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProviderWindow {
+    outcomes: VecDeque<Outcome>,
+    /// Objectives currently alerting.
+    alerting: BTreeSet<Objective>,
+}
+
+/// Per-provider SLO windows. Inert for providers without targets.
+#[derive(Debug, Default)]
+pub struct SloTracker {
+    config: SloConfig,
+    targets: HashMap<String, ProviderSloConfig>,
+    windows: DashMap<String, ProviderWindow>,
+    webhook: Option<(Client, String)>,
+}
+
+/// Counts over a window.
+#[derive(Debug, Clone, Copy, Default)]
+struct WindowCounts {
+    requests: usize,
+    failures: usize,
+    /// Successful requests slower than the latency target.
+    slow: usize,
+}
+
+impl WindowCounts {
+    fn burn_rate(&self, objective: Objective, target: &ProviderSloConfig) -> Option<f64> {
+        match objective {
+            Objective::SuccessRate => {
+                let budget = 1.0 - target.success_rate?;
+                (self.requests > 0).then(|| self.failures as f64 / self.requests as f64 / budget)
+            }
+            Objective::P95Latency => {
+                target.p95_latency_ms?;
+                let successes = self.requests - self.failures;
+                (successes > 0).then(|| self.slow as f64 / successes as f64 / LATENCY_BUDGET)
+            }
+        }
+    }
+
+    /// Requests an objective's burn rate is based on.
+    fn sample(&self, objective: Objective) -> usize {
+        match objective {
+            Objective::SuccessRate => self.requests,
+            Objective::P95Latency => self.requests - self.failures,
+        }
+    }
+}
+
+/// SLO status of one provider, for the scorecard.
+#[derive(Debug, Clone, Serialize)]
+pub struct SloStatus {
+    pub p95_latency_ms_target: Option<u64>,
+    pub success_rate_target: Option<f64>,
+    pub window_secs: u64,
+    /// Requests answered in the compliance window.
+    pub requests: usize,
+    pub success_rate: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
+    /// Whether every target is met over the compliance window (true
+    /// without requests).
+    pub compliant: bool,
+    pub alert_window_secs: u64,
+    /// Burn rate per objective over the alert window.
+    pub burn_rates: BTreeMap<&'static str, f64>,
+    /// Objectives with an active alert.
+    pub alerting: Vec<&'static str>,
+}
+
+/// Alert body POSTed to the webhook.
+#[derive(Debug, Serialize)]
+struct Alert<'a> {
+    /// `provider_slo_burn` or `provider_slo_recovered`.
+    event: &'static str,
+    provider: &'a str,
+    objective: &'static str,
+    target: f64,
+    burn_rate: f64,
+    alert_window_secs: u64,
+    message: String,
+}
+
+impl SloTracker {
+    /// Track the providers in `providers` that declare targets. Alerts are
+    /// POSTed to the configured webhook with `client`.
+    pub fn new(config: SloConfig, providers: &[ProviderConfig], client: Client) -> Self {
+        let targets = providers
+            .iter()
+            .filter_map(|p| Some((p.name.clone(), p.slo.clone()?)))
+            .collect();
+        Self {
+            webhook: config.webhook_url.clone().map(|url| (client, url)),
+            config,
+            targets,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Record a request answered by `provider`, raising or clearing alerts
+    /// as its burn rates cross the threshold.
+    pub fn record(&self, provider: &str, success: bool, latency_ms: u64) {
+        let Some(target) = self.targets.get(provider) else {
+            return;
+        };
+        let now = Instant::now();
+        let mut window = self.windows.entry(provider.to_string()).or_default();
+        window.outcomes.push_back(Outcome {
+            at: now,
+            success,
+            latency_ms,
+        });
+        let horizon = Duration::from_secs(self.config.window_secs);
+        while window
+            .outcomes
+            .front()
+            .is_some_and(|o| now.duration_since(o.at) > horizon)
+        {
+            window.outcomes.pop_front();
+        }
+
+        let counts = self.counts(&window, target, self.config.alert_window_secs, now);
+        for objective in [Objective::SuccessRate, Objective::P95Latency] {
+            let Some(burn_rate) = counts.burn_rate(objective, target) else {
+                continue;
+            };
+            let burning = counts.sample(objective) >= self.config.min_requests
+                && burn_rate >= self.config.burn_rate_threshold;
+            let alerting = window.alerting.contains(&objective);
+            if burning && !alerting {
+                window.alerting.insert(objective);
+                self.alert(provider, objective, target, burn_rate, true);
+            } else if alerting && burn_rate < self.config.burn_rate_threshold {
+                window.alerting.remove(&objective);
+                self.alert(provider, objective, target, burn_rate, false);
+            }
+        }
+    }
+
+    /// Counts of the outcomes in the last `secs`.
+    fn counts(
+        &self,
+        window: &ProviderWindow,
+        target: &ProviderSloConfig,
+        secs: u64,
+        now: Instant,
+    ) -> WindowCounts {
+        let horizon = Duration::from_secs(secs);
+        let mut counts = WindowCounts::default();
+        for outcome in window
+            .outcomes
+            .iter()
+            .rev()
+            .take_while(|o| now.duration_since(o.at) <= horizon)
+        {
+            counts.requests += 1;
+            if !outcome.success {
+                counts.failures += 1;
+            } else if target
+                .p95_latency_ms
+                .is_some_and(|limit| outcome.latency_ms > limit)
+            {
+                counts.slow += 1;
+            }
+        }
+        counts
+    }
+
+    /// Current status of `provider`, or None without targets.
+    pub fn status(&self, provider: &str) -> Option<SloStatus> {
+        let target = self.targets.get(provider)?;
+        let now = Instant::now();
+        let empty = ProviderWindow::default();
+        let entry = self.windows.get(provider);
+        let window = entry.as_deref().unwrap_or(&empty);
+
+        let counts = self.counts(window, target, self.config.window_secs, now);
+        let success_rate = (counts.requests > 0)
+            .then(|| (counts.requests - counts.failures) as f64 / counts.requests as f64);
+        let horizon = Duration::from_secs(self.config.window_secs);
+        let mut latencies: Vec<u64> = window
+            .outcomes
+            .iter()
+            .filter(|o| o.success && now.duration_since(o.at) <= horizon)
+            .map(|o| o.latency_ms)
+            .collect();
+        latencies.sort_unstable();
+        let p95_latency_ms = (!latencies.is_empty())
+            .then(|| latencies[((latencies.len() as f64 * 0.95).ceil() as usize).max(1) - 1]);
+        let compliant = target
+            .success_rate
+            .zip(success_rate)
+            .is_none_or(|(target, actual)| actual >= target)
+            && target
+                .p95_latency_ms
+                .zip(p95_latency_ms)
+                .is_none_or(|(target, actual)| actual <= target);
+
+        let alert_counts = self.counts(window, target, self.config.alert_window_secs, now);
+        let burn_rates = [Objective::SuccessRate, Objective::P95Latency]
+            .into_iter()
+            .filter_map(|o| Some((o.as_str(), alert_counts.burn_rate(o, target)?)))
+            .collect();
+
+        Some(SloStatus {
+            p95_latency_ms_target: target.p95_latency_ms,
+            success_rate_target: target.success_rate,
+            window_secs: self.config.window_secs,
+            requests: counts.requests,
+            success_rate,
+            p95_latency_ms,
+            compliant,
+            alert_window_secs: self.config.alert_window_secs,
+            burn_rates,
+            alerting: window.alerting.iter().map(|o| o.as_str()).collect(),
+        })
+    }
+
+    /// Log an alert (or recovery) and POST it to the configured webhook.
+    fn alert(
+        &self,
+        provider: &str,
+        objective: Objective,
+        target: &ProviderSloConfig,
+        burn_rate: f64,
+        burning: bool,
+    ) {
+        let target_value = match objective {
+            Objective::SuccessRate => target.success_rate.unwrap_or_default(),
+            Objective::P95Latency => target.p95_latency_ms.unwrap_or_default() as f64,
+        };
+        let message = if burning {
+            format!(
+                "Provider '{}' is burning its {} error budget at {:.1}x over the last {}s (target {})",
+                provider,
+                objective.as_str(),
+                burn_rate,
+                self.config.alert_window_secs,
+                target_value
+            )
+        } else {
+            format!(
+                "Provider '{}' {} burn rate is back to {:.1}x",
+                provider,
+                objective.as_str(),
+                burn_rate
+            )
+        };
+        if burning {
+            tracing::warn!(provider = %provider, objective = objective.as_str(), burn_rate, "{}", message);
+        } else {
+            tracing::info!(provider = %provider, objective = objective.as_str(), burn_rate, "{}", message);
+        }
+
+        let Some((client, url)) = &self.webhook else {
+            return;
+        };
+        let alert = Alert {
+            event: if burning {
+                "provider_slo_burn"
+            } else {
+                "provider_slo_recovered"
+            },
+            provider,
+            objective: objective.as_str(),
+            target: target_value,
+            burn_rate,
+            alert_window_secs: self.config.alert_window_secs,
+            message,
+        };
+        let request = client.post(url).timeout(WEBHOOK_TIMEOUT).json(&alert);
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(r) if r.status().is_success() => {}
+                Ok(r) => tracing::warn!(status = %r.status(), "SLO alert webhook failed"),
+                Err(e) => tracing::warn!(error = %e, "SLO alert webhook failed"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(min_requests: usize) -> SloTracker {
+        let config = crate::config::Config::parse_str(
+            r#"
+            [server]
+            [[providers]]
+            name = "alpha"
+            url = "https://alpha.example/v1"
+            slo = { p95_latency_ms = 1000, success_rate = 0.9 }
+            "#,
+        )
+        .unwrap();
+        SloTracker::new(
+            SloConfig {
+                min_requests,
+                ..Default::default()
+            },
+            &config.providers,
+            Client::new(),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn alerts_while_burning_and_recovers() {
+        let slo = tracker(10);
+        assert!(slo.status("beta").is_none());
+        let status = slo.status("alpha").unwrap();
+        assert!(status.compliant);
+        assert_eq!(status.requests, 0);
+
+        for _ in 0..8 {
+            slo.record("alpha", true, 200);
+        }
+        slo.record("alpha", false, 0);
+        slo.record("alpha", false, 0);
+        let status = slo.status("alpha").unwrap();
+        // 20% failures against a 10% budget
+        assert!((status.burn_rates["success_rate"] - 2.0).abs() < 1e-9);
+        assert_eq!(status.alerting, vec!["success_rate"]);
+        assert!(!status.compliant);
+        assert_eq!(status.p95_latency_ms, Some(200));
+
+        // Past the alert window, fresh successes clear it
+        tokio::time::advance(Duration::from_secs(301)).await;
+        for _ in 0..10 {
+            slo.record("alpha", true, 200);
+        }
+        let status = slo.status("alpha").unwrap();
+        assert!(status.alerting.is_empty());
+        assert_eq!(status.requests, 20);
+        assert_eq!(status.success_rate, Some(0.9));
+        assert!(status.compliant);
+
+        // Gone from the compliance window after an hour
+        tokio::time::advance(Duration::from_secs(3601)).await;
+        slo.record("alpha", true, 5000);
+        let status = slo.status("alpha").unwrap();
+        assert_eq!(status.requests, 1);
+        assert_eq!(status.p95_latency_ms, Some(5000));
+        assert!(!status.compliant);
+        // One slow request is below min_requests
+        assert!(status.alerting.is_empty());
+    }
+}
@@ -1,21 +1,27 @@
 //! Stats endpoint types, time range resolution, and handler.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Instant;
 
 use axum::{
     extract::{Query, State},
-    response::IntoResponse,
+    http::header,
+    response::{IntoResponse, Response},
     Json,
 };
+use bytes::Bytes;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::anomaly::CostAnomaly;
 use super::server::AppState;
 use crate::error::Error;
 use crate::storage;
+use crate::storage::stats::StatsDimension;
 
 /// Query parameters for GET /v1/stats.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub struct StatsQuery {
     pub range: Option<String>,
     pub since: Option<String>,
@@ -23,6 +29,8 @@ pub struct StatsQuery {
     pub model: Option<String>,
     pub provider: Option<String>,
     pub group_by: Option<String>,
+    /// Tag filter in `key=value` form.
+    pub tag: Option<String>,
 }
 
 /// Preset time range options.
@@ -109,13 +117,34 @@ pub struct StatsResponse {
     pub empty: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    /// `memory` when stats come from the non-persistent in-memory backend.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<&'static str>,
     pub counts: CountsSection,
+    pub errors: ErrorsSection,
     pub costs: CostsSection,
     pub performance: PerformanceSection,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub models: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tiers: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub providers: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policies: Option<serde_json::Value>,
+    /// Per-conversation totals for `group_by=conversation`, keyed by
+    /// conversation ID ("none" for requests without one).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversations: Option<serde_json::Value>,
+    /// One entry per combination for multi-dimension `group_by`
+    /// (e.g. `provider,model`), highest cost first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<serde_json::Value>,
+    /// Cost anomalies active now, when `[anomaly]` is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anomalies: Option<Vec<CostAnomaly>>,
 }
 
 /// Request count breakdown.
@@ -127,25 +156,133 @@ pub struct CountsSection {
     pub streaming: i64,
 }
 
+/// Failed requests by failure mode. Failures that never reached a
+/// provider (routing, billing) are only counted in `counts.error`.
+#[derive(Debug, Serialize)]
+pub struct ErrorsSection {
+    pub timeout: i64,
+    pub connect: i64,
+    pub tls: i64,
+    pub auth: i64,
+    pub rate_limited: i64,
+    #[serde(rename = "5xx")]
+    pub server: i64,
+    #[serde(rename = "4xx")]
+    pub client: i64,
+    pub malformed: i64,
+}
+
 /// Cost and token totals.
 #[derive(Debug, Serialize)]
 pub struct CostsSection {
     pub total_cost_sats: f64,
     pub total_input_tokens: i64,
     pub total_output_tokens: i64,
+    /// Costs converted at each request's stored rate (`[currency]`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatCostsSection>,
+}
+
+/// Fiat cost totals.
+#[derive(Debug, Serialize)]
+pub struct FiatCostsSection {
+    pub currency: String,
+    pub total_cost: f64,
+    /// Requests with a sats cost but no stored rate in `currency` (logged
+    /// before conversion was enabled), left out of `total_cost`.
+    pub unpriced_requests: i64,
 }
 
 /// Performance metrics.
 #[derive(Debug, Serialize)]
 pub struct PerformanceSection {
     pub avg_latency_ms: f64,
+    /// Streaming throughput per provider, for weighing slow-but-cheap
+    /// providers against fast-and-expensive ones.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub throughput: BTreeMap<String, ThroughputSection>,
+}
+
+/// Streaming throughput of one provider.
+#[derive(Debug, Serialize)]
+pub struct ThroughputSection {
+    /// Mean output tokens per second while generating.
+    pub avg_tokens_per_second: f64,
+    /// Streams the average is taken over.
+    pub streams: i64,
+}
+
+/// Most distinct queries cached at once; the cache is emptied beyond this.
+const STATS_CACHE_MAX_ENTRIES: usize = 256;
+
+/// Recent `/v1/stats` response bodies keyed by query (`[stats] cache_ttl_secs`).
+///
+/// Keys are the raw query parameters, so `range=last_24h` keeps hitting the
+/// same entry while its window slides; entries expire after the TTL.
+#[derive(Debug, Default)]
+pub struct StatsCache {
+    entries: Mutex<HashMap<StatsQuery, (Instant, Bytes)>>,
+}
+
+impl StatsCache {
+    /// The cached body for `query` and its age, if younger than `ttl`.
+    fn get(&self, query: &StatsQuery, ttl: std::time::Duration) -> Option<(Bytes, u64)> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let (stored_at, body) = entries.get(query)?;
+        let age = stored_at.elapsed();
+        (age < ttl).then(|| (body.clone(), age.as_secs()))
+    }
+
+    fn insert(&self, query: StatsQuery, body: Bytes, ttl: std::time::Duration) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        if entries.len() >= STATS_CACHE_MAX_ENTRIES {
+            entries.clear();
+        }
+        entries.insert(query, (Instant::now(), body));
+    }
 }
 
 /// Handle GET /v1/stats -- aggregate request statistics.
+///
+/// With `[stats] cache_ttl_secs` set, repeated queries are answered from
+/// [`StatsCache`] and carry `Cache-Control: max-age` for the time left.
 pub async fn stats_handler(
     State(state): State<AppState>,
     Query(params): Query<StatsQuery>,
-) -> Result<impl IntoResponse, Error> {
+) -> Result<Response, Error> {
+    let ttl_secs = state.config.stats.cache_ttl_secs;
+    if ttl_secs == 0 {
+        return Ok(Json(build_stats(&state, &params).await?).into_response());
+    }
+    let ttl = std::time::Duration::from_secs(ttl_secs);
+
+    let (body, age) = match state.stats_cache.get(&params, ttl) {
+        Some(hit) => hit,
+        None => {
+            let response = build_stats(&state, &params).await?;
+            let body = Bytes::from(
+                serde_json::to_vec(&response).map_err(|e| Error::Internal(e.to_string()))?,
+            );
+            state.stats_cache.insert(params, body.clone(), ttl);
+            (body, 0)
+        }
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CACHE_CONTROL,
+                format!("max-age={}", ttl_secs.saturating_sub(age)),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Run the queries behind a `/v1/stats` response.
+async fn build_stats(state: &AppState, params: &StatsQuery) -> Result<StatsResponse, Error> {
     let pool = state
         .read_db
         .as_ref()
@@ -166,6 +303,7 @@ pub async fn stats_handler(
         model = ?params.model,
         provider = ?params.provider,
         group_by = ?params.group_by,
+        tag = ?params.tag,
         "Stats query"
     );
 
@@ -179,34 +317,91 @@ pub async fn stats_handler(
         super::validation::validate_provider_filter(&state.config, pool, provider_filter).await?;
     }
 
+    // Validate tag filter (key=value)
+    let tag_filter = params
+        .tag
+        .as_deref()
+        .map(super::tags::parse_tag_filter)
+        .transpose()?;
+    let tag = tag_filter.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+
     // Validate group_by
+    let mut group_by_tag: Option<String> = None;
+    let mut dimensions: Vec<StatsDimension> = Vec::new();
     if let Some(ref gb) = params.group_by {
-        if gb != "model" && gb != "tier" {
-            return Err(Error::BadRequest(
-                "Invalid group_by value. Supported: 'model', 'tier'".to_string(),
-            ));
+        if let Some(key) = super::tags::parse_group_by_tag(gb) {
+            group_by_tag = Some(key?);
+        } else {
+            dimensions = parse_group_by(gb)?;
         }
     }
 
+    // Long ranges read whole hours and days from the rollup tables, which
+    // hold one tag per row: a tag filter and a tag grouping can't be combined
+    let stats_config = &state.config.stats;
+    let rollup_plan = if stats_config.rollups
+        && !(tag.is_some() && group_by_tag.is_some())
+        && matches!(
+            dimensions.as_slice(),
+            [] | [StatsDimension::Model] | [StatsDimension::Tier]
+        )
+        && until_dt - since_dt >= Duration::hours(stats_config.rollup_min_range_hours as i64)
+    {
+        storage::rollups::rolled_until(pool)
+            .await?
+            .and_then(|rolled| storage::rollups::plan(since_dt, until_dt, rolled))
+    } else {
+        None
+    };
+
     // Query aggregate stats
-    let row = storage::stats::query_aggregate(
-        pool,
-        &since_str,
-        &until_str,
-        params.model.as_deref(),
-        params.provider.as_deref(),
-    )
-    .await?;
+    let row = match &rollup_plan {
+        Some(segments) => {
+            storage::rollups::query_aggregate(
+                pool,
+                segments,
+                params.model.as_deref(),
+                params.provider.as_deref(),
+                tag,
+            )
+            .await?
+        }
+        None => {
+            storage::stats::query_aggregate(
+                pool,
+                &since_str,
+                &until_str,
+                params.model.as_deref(),
+                params.provider.as_deref(),
+                tag,
+            )
+            .await?
+        }
+    };
 
     // Build models map if group_by=model
-    let models_value = if params.group_by.as_deref() == Some("model") {
-        let model_rows = storage::stats::query_grouped_by_model(
-            pool,
-            &since_str,
-            &until_str,
-            params.provider.as_deref(),
-        )
-        .await?;
+    let models_value = if dimensions == [StatsDimension::Model] {
+        let model_rows = match &rollup_plan {
+            Some(segments) => {
+                storage::rollups::query_grouped_by_model(
+                    pool,
+                    segments,
+                    params.provider.as_deref(),
+                    tag,
+                )
+                .await?
+            }
+            None => {
+                storage::stats::query_grouped_by_model(
+                    pool,
+                    &since_str,
+                    &until_str,
+                    params.provider.as_deref(),
+                    tag,
+                )
+                .await?
+            }
+        };
 
         // Collect all configured model names (deduped)
         let mut configured_models: HashSet<String> = HashSet::new();
@@ -248,15 +443,30 @@ pub async fn stats_handler(
     };
 
     // Build tiers map if group_by=tier
-    let tiers_value = if params.group_by.as_deref() == Some("tier") {
-        let tier_rows = storage::stats::query_grouped_by_tier(
-            pool,
-            &since_str,
-            &until_str,
-            params.model.as_deref(),
-            params.provider.as_deref(),
-        )
-        .await?;
+    let tiers_value = if dimensions == [StatsDimension::Tier] {
+        let tier_rows = match &rollup_plan {
+            Some(segments) => {
+                storage::rollups::query_grouped_by_tier(
+                    pool,
+                    segments,
+                    params.model.as_deref(),
+                    params.provider.as_deref(),
+                    tag,
+                )
+                .await?
+            }
+            None => {
+                storage::stats::query_grouped_by_tier(
+                    pool,
+                    &since_str,
+                    &until_str,
+                    params.model.as_deref(),
+                    params.provider.as_deref(),
+                    tag,
+                )
+                .await?
+            }
+        };
 
         let mut tiers_map = serde_json::Map::new();
 
@@ -288,6 +498,138 @@ pub async fn stats_handler(
         None
     };
 
+    // Build tags map if group_by=tag:<key>
+    let tags_value = if let Some(ref tag_key) = group_by_tag {
+        let tag_rows = match &rollup_plan {
+            Some(segments) => {
+                storage::rollups::query_grouped_by_tag(
+                    pool,
+                    segments,
+                    tag_key,
+                    params.model.as_deref(),
+                    params.provider.as_deref(),
+                )
+                .await?
+            }
+            None => {
+                storage::stats::query_grouped_by_tag(
+                    pool,
+                    &since_str,
+                    &until_str,
+                    tag_key,
+                    params.model.as_deref(),
+                    params.provider.as_deref(),
+                    tag,
+                )
+                .await?
+            }
+        };
+
+        let mut tags_map = serde_json::Map::new();
+        for tr in &tag_rows {
+            tags_map.insert(tr.tag_value.clone(), tag_row_to_json(tr));
+        }
+
+        Some(serde_json::json!({
+            "key": tag_key,
+            "values": serde_json::Value::Object(tags_map),
+        }))
+    } else {
+        None
+    };
+
+    // Build providers/policies maps for a single dimension, or the groups
+    // list for a combination
+    let grouped = match dimensions.as_slice() {
+        [] | [StatsDimension::Model] | [StatsDimension::Tier] => None,
+        dims => Some(
+            storage::stats::query_grouped_by(
+                pool,
+                &since_str,
+                &until_str,
+                dims,
+                params.model.as_deref(),
+                params.provider.as_deref(),
+                tag,
+            )
+            .await?,
+        ),
+    };
+    let (mut providers_value, mut policies_value, mut conversations_value, mut groups_value) =
+        (None, None, None, None);
+    if let Some(rows) = grouped {
+        match dimensions.as_slice() {
+            [StatsDimension::Provider] => {
+                let configured = state.config.providers.iter().map(|p| p.name.as_str());
+                providers_value = Some(single_dimension_map(&rows, configured));
+            }
+            [StatsDimension::Policy] => {
+                let configured = state.config.policies.rules.iter().map(|r| r.name.as_str());
+                policies_value = Some(single_dimension_map(&rows, configured));
+            }
+            [StatsDimension::Conversation] => {
+                conversations_value = Some(single_dimension_map(&rows, std::iter::empty()));
+            }
+            dims => {
+                let groups = rows
+                    .iter()
+                    .map(|row| {
+                        let mut value = group_row_to_json(row);
+                        for (dim, key) in dims.iter().zip(row.keys()) {
+                            value[dim.as_str()] = serde_json::Value::from(key);
+                        }
+                        value
+                    })
+                    .collect();
+                groups_value = Some(serde_json::Value::Array(groups));
+            }
+        }
+    }
+
+    // Fiat totals always read raw rows: rollups don't keep per-request rates
+    let fiat = match state.exchange_rate.code() {
+        Some(currency) => {
+            let fiat_row = storage::stats::query_fiat_cost(
+                pool,
+                &since_str,
+                &until_str,
+                currency,
+                params.model.as_deref(),
+                params.provider.as_deref(),
+                tag,
+            )
+            .await?;
+            Some(FiatCostsSection {
+                currency: currency.to_string(),
+                total_cost: fiat_row.total_cost,
+                unpriced_requests: fiat_row.unpriced_requests,
+            })
+        }
+        None => None,
+    };
+
+    // Throughput always reads raw rows: rollups don't keep per-stream rates
+    let throughput = storage::stats::query_throughput_by_provider(
+        pool,
+        &since_str,
+        &until_str,
+        params.model.as_deref(),
+        params.provider.as_deref(),
+        tag,
+    )
+    .await?
+    .into_iter()
+    .map(|row| {
+        (
+            row.provider,
+            ThroughputSection {
+                avg_tokens_per_second: row.avg_tokens_per_second,
+                streams: row.streams,
+            },
+        )
+    })
+    .collect();
+
     // Determine empty state
     let (empty, message) = if row.total_requests == 0 {
         (
@@ -303,25 +645,48 @@ pub async fn stats_handler(
         until: until_dt.to_rfc3339(),
         empty,
         message,
+        storage: state.config.ephemeral_storage().then_some("memory"),
         counts: CountsSection {
             total: row.total_requests,
             success: row.success_count,
             error: row.error_count,
             streaming: row.streaming_count,
         },
+        errors: ErrorsSection {
+            timeout: row.timeout_errors,
+            connect: row.connect_errors,
+            tls: row.tls_errors,
+            auth: row.auth_errors,
+            rate_limited: row.rate_limited_errors,
+            server: row.server_errors,
+            client: row.client_errors,
+            malformed: row.malformed_errors,
+        },
         costs: CostsSection {
             total_cost_sats: row.total_cost_sats,
             total_input_tokens: row.total_input_tokens as i64,
             total_output_tokens: row.total_output_tokens as i64,
+            fiat,
         },
         performance: PerformanceSection {
             avg_latency_ms: row.avg_latency_ms,
+            throughput,
         },
         models: models_value,
         tiers: tiers_value,
+        providers: providers_value,
+        policies: policies_value,
+        conversations: conversations_value,
+        groups: groups_value,
+        tags: tags_value,
+        anomalies: state
+            .config
+            .anomaly
+            .is_some()
+            .then(|| state.anomalies.active()),
     };
 
-    Ok(Json(response))
+    Ok(response)
 }
 
 /// Convert a ModelRow to JSON for the models map.
@@ -344,6 +709,75 @@ fn model_row_to_json(mr: &storage::stats::ModelRow) -> serde_json::Value {
     })
 }
 
+/// Parse a comma-separated `group_by` list of dimensions.
+fn parse_group_by(group_by: &str) -> Result<Vec<StatsDimension>, Error> {
+    let mut dimensions = Vec::new();
+    for entry in group_by.split(',').map(str::trim) {
+        let dimension = match (entry, StatsDimension::parse(entry)) {
+            (_, Some(dimension)) => dimension,
+            ("client", None) => {
+                return Err(Error::BadRequest(
+                    "group_by=client is not available yet: requests are not attributed to \
+                     client keys"
+                        .to_string(),
+                ))
+            }
+            (_, None) => {
+                return Err(Error::BadRequest(format!(
+                    "Invalid group_by value '{}'. Supported: 'model', 'provider', 'policy', \
+                     'tier', 'conversation' (comma-separated to combine), 'tag:<key>'",
+                    entry
+                )))
+            }
+        };
+        if dimensions.contains(&dimension) {
+            return Err(Error::BadRequest(format!(
+                "group_by lists '{}' twice",
+                entry
+            )));
+        }
+        dimensions.push(dimension);
+    }
+    Ok(dimensions)
+}
+
+/// Map of a single dimension's values to their stats, with zeroed
+/// entries for `configured` values that had no traffic.
+fn single_dimension_map<'a>(
+    rows: &[storage::stats::GroupRow],
+    configured: impl Iterator<Item = &'a str>,
+) -> serde_json::Value {
+    let mut map: serde_json::Map<String, serde_json::Value> = configured
+        .map(|name| (name.to_string(), zeroed_model_json()))
+        .collect();
+    for row in rows {
+        if let Some(key) = row.keys().first() {
+            map.insert(key.to_string(), group_row_to_json(row));
+        }
+    }
+    serde_json::Value::Object(map)
+}
+
+/// Convert a GroupRow to JSON, in the shape of a models map entry.
+fn group_row_to_json(row: &storage::stats::GroupRow) -> serde_json::Value {
+    serde_json::json!({
+        "counts": {
+            "total": row.total_requests,
+            "success": row.success_count,
+            "error": row.error_count,
+            "streaming": row.streaming_count,
+        },
+        "costs": {
+            "total_cost_sats": row.total_cost_sats,
+            "total_input_tokens": row.total_input_tokens as i64,
+            "total_output_tokens": row.total_output_tokens as i64,
+        },
+        "performance": {
+            "avg_latency_ms": row.avg_latency_ms,
+        }
+    })
+}
+
 /// Convert a TierRow to JSON for the tiers map.
 fn tier_row_to_json(tr: &storage::stats::TierRow) -> serde_json::Value {
     serde_json::json!({
@@ -364,6 +798,26 @@ fn tier_row_to_json(tr: &storage::stats::TierRow) -> serde_json::Value {
     })
 }
 
+/// Convert a TagRow to JSON for the tags map.
+fn tag_row_to_json(tr: &storage::stats::TagRow) -> serde_json::Value {
+    serde_json::json!({
+        "counts": {
+            "total": tr.total_requests,
+            "success": tr.success_count,
+            "error": tr.error_count,
+            "streaming": tr.streaming_count,
+        },
+        "costs": {
+            "total_cost_sats": tr.total_cost_sats,
+            "total_input_tokens": tr.total_input_tokens as i64,
+            "total_output_tokens": tr.total_output_tokens as i64,
+        },
+        "performance": {
+            "avg_latency_ms": tr.avg_latency_ms,
+        }
+    })
+}
+
 /// Return zeroed stats JSON for a configured model with no traffic.
 fn zeroed_model_json() -> serde_json::Value {
     serde_json::json!({
@@ -2,7 +2,9 @@
 //!
 //! Provides [`SseObserver`] for line-buffered extraction of usage data
 //! and finish_reason from OpenAI-compatible SSE streaming responses.
-//! Handles TCP chunk boundary reassembly correctly.
+//! Handles TCP chunk boundary reassembly correctly. Lines are parsed in
+//! place from the upstream `Bytes`; only a line split across chunks is
+//! copied, into a reused scratch buffer.
 //!
 //! The public API is [`wrap_sse_stream`], which wraps a byte stream and
 //! returns a passthrough stream plus a [`StreamResultHandle`] that will
@@ -11,12 +13,17 @@
 
 use bytes::Bytes;
 use futures::Stream;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-/// Maximum buffer size (64 KB). If exceeded, the buffer is drained entirely
-/// and a warning is logged. This prevents unbounded memory growth from a
-/// misbehaving provider that sends no newlines.
+/// Maximum length of a partial line (64 KB). If exceeded, the partial line
+/// is dropped and a warning is logged. This prevents unbounded memory growth
+/// from a misbehaving provider that sends no newlines.
 const BUFFER_CAP: usize = 64 * 1024;
 
 /// Handle for reading the [`StreamResult`] after a wrapped stream completes.
@@ -25,6 +32,10 @@ const BUFFER_CAP: usize = 64 * 1024;
 /// (or by [`SseObserver::into_result`] for direct unit-test use).
 pub type StreamResultHandle = Arc<Mutex<Option<StreamResult>>>;
 
+/// Running estimate of the output tokens in a stream's deltas, readable
+/// while the stream is still being consumed.
+pub type DeltaTokenCounter = Arc<AtomicU32>;
+
 /// Token usage extracted from the final SSE chunk.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StreamUsage {
@@ -41,6 +52,10 @@ pub struct StreamResult {
     pub finish_reason: Option<String>,
     /// Whether `data: [DONE]` was received.
     pub done_received: bool,
+    /// Time from the first to the last chunk carrying choices, i.e. how
+    /// long the provider spent generating once it started. `None` when
+    /// fewer than two such chunks arrived.
+    pub generation_time: Option<Duration>,
 }
 
 impl StreamResult {
@@ -50,27 +65,77 @@ impl StreamResult {
             usage: None,
             finish_reason: None,
             done_received: false,
+            generation_time: None,
+        }
+    }
+
+    /// Output tokens per second over [`generation_time`](Self::generation_time).
+    ///
+    /// `None` without usage, or when the whole completion arrived at once
+    /// and there is no interval to measure.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let usage = self.usage.as_ref()?;
+        let secs = self.generation_time?.as_secs_f64();
+        if secs <= 0.0 || usage.completion_tokens == 0 {
+            return None;
         }
+        Some(usage.completion_tokens as f64 / secs)
     }
 }
 
+/// The fields of an SSE chunk the observer reads; everything else is skipped
+/// without being materialised.
+#[derive(Deserialize)]
+struct ChunkFields<'a> {
+    #[serde(borrow, default)]
+    choices: Option<Vec<ChoiceFields<'a>>>,
+    #[serde(default)]
+    usage: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ChoiceFields<'a> {
+    #[serde(borrow, default)]
+    finish_reason: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    delta: Option<DeltaFields<'a>>,
+}
+
+#[derive(Deserialize)]
+struct DeltaFields<'a> {
+    #[serde(borrow, default)]
+    content: Option<Cow<'a, str>>,
+}
+
 /// Internal state for SSE line buffering and usage extraction.
 ///
-/// Buffers raw bytes across chunk boundaries, reassembles complete SSE lines,
-/// and extracts usage + finish_reason from `data:` lines.
+/// Complete lines are read straight out of each chunk. The unterminated
+/// tail of a chunk is kept as a `Bytes` slice (a reference, not a copy) in
+/// a rope of pending pieces, which is joined once its newline arrives.
+/// Extracts usage + finish_reason from `data:` lines.
 ///
 /// When created with a [`StreamResultHandle`] (via [`wrap_sse_stream`]),
 /// the `Drop` impl writes the final [`StreamResult`] to the handle,
 /// ensuring results are available even if the stream is dropped early.
 pub(crate) struct SseObserver {
-    /// Byte buffer for reassembling SSE lines across chunk boundaries.
-    buffer: Vec<u8>,
+    /// Start of a line split across chunks: slices of earlier chunks,
+    /// none containing `\n`.
+    pending: VecDeque<Bytes>,
+    /// Total length of `pending`.
+    pending_len: usize,
+    /// Reused to join `pending` with the rest of its line.
+    scratch: Vec<u8>,
     /// Extracted usage from the last chunk that had a non-null usage object.
     usage: Option<StreamUsage>,
     /// Extracted finish_reason from the last chunk with a non-null value.
     finish_reason: Option<String>,
     /// Whether `data: [DONE]` was received.
     done_received: bool,
+    /// Arrival of the first and latest chunks carrying choices.
+    first_choice_at: Option<Instant>,
+    last_choice_at: Option<Instant>,
+    /// Estimated tokens of delta content seen so far.
+    delta_tokens: DeltaTokenCounter,
     /// Optional handle for writing the result on Drop. Set to `None` when
     /// `into_result()` is called directly, to prevent double-write.
     result_handle: Option<StreamResultHandle>,
@@ -83,70 +148,95 @@ impl SseObserver {
     #[allow(dead_code)]
     pub fn new() -> Self {
         Self {
-            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            pending_len: 0,
+            scratch: Vec::new(),
             usage: None,
             finish_reason: None,
             done_received: false,
+            first_choice_at: None,
+            last_choice_at: None,
+            delta_tokens: DeltaTokenCounter::default(),
             result_handle: None,
         }
     }
 
-    /// Create a new observer that will write its result to the given handle on Drop.
-    pub fn with_handle(handle: StreamResultHandle) -> Self {
+    /// Create a new observer that will write its result to the given handle
+    /// on Drop and count delta tokens into `delta_tokens`.
+    pub fn with_handle(handle: StreamResultHandle, delta_tokens: DeltaTokenCounter) -> Self {
         Self {
-            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            pending_len: 0,
+            scratch: Vec::new(),
             usage: None,
             finish_reason: None,
             done_received: false,
+            first_choice_at: None,
+            last_choice_at: None,
+            delta_tokens,
             result_handle: Some(handle),
         }
     }
 
     /// Process a chunk of bytes from the SSE stream.
     ///
-    /// Appends bytes to the internal buffer, scans for complete lines
-    /// (terminated by `\n`), handles `\r\n` by trimming `\r` before `\n`.
-    /// Retains trailing incomplete bytes for the next chunk.
-    /// Caps buffer at [`BUFFER_CAP`] -- if exceeded, drains entirely.
-    pub fn process_chunk(&mut self, bytes: &[u8]) {
-        self.buffer.extend_from_slice(bytes);
-
-        // Safety valve: cap buffer at 64KB to prevent OOM
-        if self.buffer.len() > BUFFER_CAP {
+    /// Scans only the new bytes for line ends (`\n`, with a preceding `\r`
+    /// trimmed). Lines wholly inside the chunk are parsed without copying;
+    /// the trailing incomplete line is retained for the next chunk.
+    /// Caps the partial line at [`BUFFER_CAP`] -- if exceeded, drops it.
+    pub fn process_chunk(&mut self, chunk: &Bytes) {
+        let mut start = 0;
+        while let Some(offset) = memchr::memchr(b'\n', &chunk[start..]) {
+            let end = start + offset;
+            if self.pending.is_empty() {
+                self.process_raw_line(&chunk[start..end]);
+            } else {
+                let mut line = self.take_pending();
+                line.extend_from_slice(&chunk[start..end]);
+                self.process_raw_line(&line);
+                self.scratch = line;
+            }
+            start = end + 1;
+        }
+
+        if start == chunk.len() {
+            return;
+        }
+        self.pending_len += chunk.len() - start;
+
+        // Safety valve: cap the partial line at 64KB to prevent OOM
+        if self.pending_len > BUFFER_CAP {
             tracing::warn!(
-                buffer_len = self.buffer.len(),
+                buffer_len = self.pending_len,
                 "SSE buffer exceeded {}KB cap, draining",
                 BUFFER_CAP / 1024
             );
-            self.buffer.clear();
+            self.pending.clear();
+            self.pending_len = 0;
             return;
         }
+        self.pending.push_back(chunk.slice(start..));
+    }
 
-        // Process all complete lines in the buffer
-        loop {
-            let newline_pos = self.buffer.iter().position(|&b| b == b'\n');
-            let Some(pos) = newline_pos else {
-                break; // No complete line yet
-            };
-
-            // Handle \r\n by trimming \r before \n
-            let line_end = if pos > 0 && self.buffer[pos - 1] == b'\r' {
-                pos - 1
-            } else {
-                pos
-            };
-
-            let line_bytes = self.buffer[..line_end].to_vec();
-
-            // Remove processed bytes including the \n
-            self.buffer.drain(..=pos);
+    /// Join the pending pieces into the scratch buffer, leaving `pending`
+    /// empty. Hand the buffer back via `self.scratch` to reuse it.
+    fn take_pending(&mut self) -> Vec<u8> {
+        let mut line = std::mem::take(&mut self.scratch);
+        line.clear();
+        for piece in self.pending.drain(..) {
+            line.extend_from_slice(&piece);
+        }
+        self.pending_len = 0;
+        line
+    }
 
-            // Process if valid UTF-8
-            if let Ok(line) = std::str::from_utf8(&line_bytes) {
-                self.process_line(line);
-            } else {
-                tracing::warn!("Non-UTF8 SSE line, skipping");
-            }
+    /// Trim a trailing `\r` and process the line if it is valid UTF-8.
+    fn process_raw_line(&mut self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if let Ok(line) = std::str::from_utf8(line) {
+            self.process_line(line);
+        } else {
+            tracing::warn!("Non-UTF8 SSE line, skipping");
         }
     }
 
@@ -155,24 +245,12 @@ impl SseObserver {
     /// Handles the case where `data: [DONE]` is sent without a trailing
     /// newline (the last bytes before TCP FIN).
     fn flush_buffer(&mut self) {
-        if self.buffer.is_empty() {
+        if self.pending.is_empty() {
             return;
         }
-
-        let remaining = std::mem::take(&mut self.buffer);
-
-        // Trim trailing \r if present
-        let line_bytes = if remaining.last() == Some(&b'\r') {
-            &remaining[..remaining.len() - 1]
-        } else {
-            &remaining
-        };
-
-        if let Ok(line) = std::str::from_utf8(line_bytes) {
-            self.process_line(line);
-        } else {
-            tracing::warn!("Non-UTF8 content in final buffer flush, skipping");
-        }
+        let line = self.take_pending();
+        self.process_raw_line(&line);
+        self.scratch = line;
     }
 
     /// Process a single complete SSE line.
@@ -222,8 +300,8 @@ impl SseObserver {
             return;
         }
 
-        // Parse as JSON
-        let parsed: serde_json::Value = match serde_json::from_str(data) {
+        // Parse only the fields we need, borrowing strings from the line
+        let parsed: ChunkFields = match serde_json::from_str(data) {
             Ok(v) => v,
             Err(e) => {
                 tracing::warn!(error = %e, "Failed to parse SSE data line as JSON");
@@ -231,18 +309,39 @@ impl SseObserver {
             }
         };
 
+        if parsed.choices.as_ref().is_some_and(|c| !c.is_empty()) {
+            let now = Instant::now();
+            self.first_choice_at.get_or_insert(now);
+            self.last_choice_at = Some(now);
+        }
+
+        // Rough count of generated tokens, as for prompts: a token per four
+        // characters, at least one per non-empty delta
+        let delta_tokens: usize = parsed
+            .choices
+            .iter()
+            .flatten()
+            .filter_map(|choice| choice.delta.as_ref()?.content.as_deref())
+            .filter(|content| !content.is_empty())
+            .map(|content| (content.chars().count() / 4).max(1))
+            .sum();
+        if delta_tokens > 0 {
+            self.delta_tokens
+                .fetch_add(delta_tokens as u32, Ordering::Relaxed);
+        }
+
         // Extract finish_reason from choices[0].finish_reason
         if let Some(reason) = parsed
-            .get("choices")
-            .and_then(|c| c.get(0))
-            .and_then(|choice| choice.get("finish_reason"))
-            .and_then(|r| r.as_str())
+            .choices
+            .as_ref()
+            .and_then(|c| c.first())
+            .and_then(|choice| choice.finish_reason.as_ref())
         {
             self.finish_reason = Some(reason.to_string());
         }
 
         // Extract usage (only from chunks where usage is non-null)
-        if let Some(usage) = parsed.get("usage").filter(|u| !u.is_null()) {
+        if let Some(usage) = parsed.usage {
             if let (Some(prompt), Some(completion)) = (
                 usage.get("prompt_tokens").and_then(|v| v.as_u64()),
                 usage.get("completion_tokens").and_then(|v| v.as_u64()),
@@ -271,16 +370,7 @@ impl SseObserver {
         self.result_handle.take();
 
         self.flush_buffer();
-
-        if !self.done_received {
-            return StreamResult::empty();
-        }
-
-        StreamResult {
-            usage: self.usage.clone(),
-            finish_reason: self.finish_reason.clone(),
-            done_received: true,
-        }
+        self.build_result()
     }
 
     /// Build the [`StreamResult`] from current state.
@@ -295,6 +385,11 @@ impl SseObserver {
             usage: self.usage.clone(),
             finish_reason: self.finish_reason.clone(),
             done_received: true,
+            generation_time: self
+                .first_choice_at
+                .zip(self.last_choice_at)
+                .map(|(first, last)| last - first)
+                .filter(|d| !d.is_zero()),
         }
     }
 }
@@ -328,13 +423,28 @@ pub fn wrap_sse_stream<S>(
     impl Stream<Item = Result<Bytes, std::io::Error>>,
     StreamResultHandle,
 )
+where
+    S: Stream<Item = Result<Bytes, reqwest::Error>>,
+{
+    wrap_sse_stream_counting(stream, DeltaTokenCounter::default())
+}
+
+/// [`wrap_sse_stream`], also counting delta tokens into `delta_tokens` as
+/// each chunk passes.
+pub fn wrap_sse_stream_counting<S>(
+    stream: S,
+    delta_tokens: DeltaTokenCounter,
+) -> (
+    impl Stream<Item = Result<Bytes, std::io::Error>>,
+    StreamResultHandle,
+)
 where
     S: Stream<Item = Result<Bytes, reqwest::Error>>,
 {
     use futures::StreamExt;
 
     let handle: StreamResultHandle = Arc::new(Mutex::new(None));
-    let observer = SseObserver::with_handle(handle.clone());
+    let observer = SseObserver::with_handle(handle.clone(), delta_tokens);
     let observer = Arc::new(Mutex::new(observer));
 
     let wrapped = stream.map(move |chunk_result| match chunk_result {
@@ -359,6 +469,346 @@ where
     (wrapped, handle)
 }
 
+/// SSE comment sent to clients while a provider is idle
+/// (`server.sse_keepalive_secs`). Clients ignore comment lines.
+pub const KEEPALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// Tracks whether forwarded bytes end between SSE events, the only place a
+/// keep-alive comment can go without splitting an event the client is
+/// still reading.
+#[derive(Debug)]
+pub(crate) struct EventBoundary {
+    /// Last two bytes forwarded, ignoring `\r`.
+    tail: [u8; 2],
+}
+
+impl Default for EventBoundary {
+    fn default() -> Self {
+        // Nothing forwarded yet counts as a boundary
+        Self { tail: *b"\n\n" }
+    }
+}
+
+impl EventBoundary {
+    /// Note the next chunk forwarded to the client.
+    pub(crate) fn observe(&mut self, chunk: &[u8]) {
+        for &b in chunk.iter().filter(|&&b| b != b'\r') {
+            self.tail = [self.tail[1], b];
+        }
+    }
+
+    /// Whether everything forwarded so far ends with a blank line.
+    pub(crate) fn at_boundary(&self) -> bool {
+        &self.tail == b"\n\n"
+    }
+}
+
+/// Splits an SSE body into the `data:` payloads of its events.
+pub(crate) struct SseEvents {
+    body: futures::stream::BoxStream<'static, Result<bytes::Bytes, axum::Error>>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl SseEvents {
+    pub(crate) fn new(body: axum::body::Body) -> Self {
+        use futures::StreamExt;
+
+        Self {
+            body: body.into_data_stream().boxed(),
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// The next event's data, skipping comments and data-less events.
+    pub(crate) async fn next(&mut self) -> Option<String> {
+        use futures::StreamExt;
+
+        loop {
+            if let Some(end) = find_event_end(&self.buffer) {
+                let event: Vec<u8> = self.buffer.drain(..end).collect();
+                if let Some(data) = event_data(&event) {
+                    return Some(data);
+                }
+                continue;
+            }
+            if self.done {
+                // A final event without a blank line after it
+                let event = std::mem::take(&mut self.buffer);
+                return event_data(&event);
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    tracing::warn!(error = %e, "Stream error while reading SSE events");
+                    self.done = true;
+                }
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// Length of the first complete event in `buffer`, including its blank
+/// line (`\n\n` or `\r\n\r\n`).
+pub(crate) fn find_event_end(buffer: &[u8]) -> Option<usize> {
+    let lf = buffer
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .map(|pos| pos + 2);
+    let crlf = buffer
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|pos| pos + 4);
+    match (lf, crlf) {
+        (Some(lf), Some(crlf)) => Some(lf.min(crlf)),
+        (end, None) | (None, end) => end,
+    }
+}
+
+/// Joined `data:` lines of one SSE event.
+pub(crate) fn event_data(event: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(event);
+    let lines: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Rewrite a provider's SSE body into clean OpenAI `chat.completion.chunk`
+/// events when `enabled` (a provider's `normalize_stream`); otherwise the
+/// stream is returned as is.
+///
+/// Each event is re-emitted as a single `data:` line. `id`, `created` and
+/// `model` are carried over from earlier chunks when a chunk lacks them,
+/// the first delta of every choice gets `role: "assistant"`, vendor finish
+/// reasons (`end_turn`, `max_tokens`, `tool_use`, ...) are mapped to
+/// OpenAI's, and fields outside the chunk schema are dropped. Comments,
+/// named vendor events and events without data are dropped; `[DONE]`,
+/// error objects and non-JSON data pass through.
+pub fn normalize_sse_stream<S, E>(stream: S, enabled: bool) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    use futures::StreamExt;
+
+    stream
+        .map(Some)
+        .chain(futures::stream::once(futures::future::ready(None)))
+        .scan(ChunkNormalizer::default(), move |normalizer, item| {
+            let out = match item {
+                Some(Ok(bytes)) if enabled => Ok(normalizer.push(&bytes)),
+                Some(item) => item,
+                None => Ok(normalizer.finish()),
+            };
+            futures::future::ready(Some(out))
+        })
+        .filter(|item| futures::future::ready(!matches!(item, Ok(bytes) if bytes.is_empty())))
+}
+
+/// Buffers a provider's SSE body into events and rewrites each one.
+#[derive(Debug, Default)]
+struct ChunkNormalizer {
+    buffer: Vec<u8>,
+    id: Option<serde_json::Value>,
+    created: Option<serde_json::Value>,
+    model: Option<serde_json::Value>,
+    /// Choice indices whose first delta has been sent.
+    started: Vec<u64>,
+}
+
+/// Keys of a `chat.completion.chunk` delta; others are vendor fields.
+const DELTA_KEYS: [&str; 5] = ["role", "content", "tool_calls", "function_call", "refusal"];
+
+impl ChunkNormalizer {
+    /// Normalized events completed by `chunk`.
+    fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.buffer
+            .extend(chunk.iter().copied().filter(|&b| b != b'\r'));
+        let mut out = Vec::new();
+        while let Some(end) = find_event_end(&self.buffer) {
+            let event: Vec<u8> = self.buffer.drain(..end).collect();
+            self.write_event(&event, &mut out);
+        }
+        if self.buffer.len() > BUFFER_CAP {
+            tracing::warn!(
+                buffer_len = self.buffer.len(),
+                "SSE event exceeds buffer cap, forwarding it unnormalized"
+            );
+            out.append(&mut self.buffer);
+        }
+        Bytes::from(out)
+    }
+
+    /// A final event the provider did not terminate with a blank line.
+    fn finish(&mut self) -> Bytes {
+        let event = std::mem::take(&mut self.buffer);
+        let mut out = Vec::new();
+        self.write_event(&event, &mut out);
+        Bytes::from(out)
+    }
+
+    fn write_event(&mut self, event: &[u8], out: &mut Vec<u8>) {
+        let Some(data) = event_data(event) else {
+            return;
+        };
+        let data = match serde_json::from_str::<serde_json::Value>(&data) {
+            Ok(serde_json::Value::Object(chunk)) if !chunk.contains_key("error") => {
+                self.normalize(chunk).to_string()
+            }
+            _ => data,
+        };
+        out.extend_from_slice(b"data: ");
+        out.extend_from_slice(data.as_bytes());
+        out.extend_from_slice(b"\n\n");
+    }
+
+    fn normalize(
+        &mut self,
+        mut chunk: serde_json::Map<String, serde_json::Value>,
+    ) -> serde_json::Value {
+        use serde_json::{json, Map, Value};
+
+        let remember = |slot: &mut Option<Value>, value: Option<Value>| {
+            if let Some(value) = value.filter(|v| !v.is_null()) {
+                *slot = Some(value);
+            }
+            slot.clone()
+        };
+        let id = remember(&mut self.id, chunk.remove("id"))
+            .unwrap_or_else(|| json!(format!("chatcmpl-{}", uuid::Uuid::new_v4().simple())));
+        self.id.get_or_insert_with(|| id.clone());
+        let created = remember(&mut self.created, chunk.remove("created"))
+            .unwrap_or_else(|| json!(chrono::Utc::now().timestamp()));
+        self.created.get_or_insert_with(|| created.clone());
+
+        let mut out = Map::new();
+        out.insert("id".into(), id);
+        out.insert("object".into(), json!("chat.completion.chunk"));
+        out.insert("created".into(), created);
+        if let Some(model) = remember(&mut self.model, chunk.remove("model")) {
+            out.insert("model".into(), model);
+        }
+        if let Some(fingerprint) = chunk.remove("system_fingerprint") {
+            out.insert("system_fingerprint".into(), fingerprint);
+        }
+
+        let mut choices = match chunk.remove("choices") {
+            Some(Value::Array(choices)) => choices,
+            _ => Vec::new(),
+        };
+        // Some providers report the stop at the top level
+        let top_finish = chunk
+            .remove("finish_reason")
+            .or_else(|| chunk.remove("stop_reason"))
+            .filter(|v| !v.is_null());
+        if choices.is_empty() {
+            if let Some(reason) = top_finish {
+                choices.push(json!({"index": 0, "finish_reason": reason}));
+            }
+        }
+        let choices: Vec<Value> = choices
+            .into_iter()
+            .enumerate()
+            .map(|(i, choice)| self.normalize_choice(i, choice))
+            .collect();
+        out.insert("choices".into(), Value::Array(choices));
+
+        if let Some(Value::Object(usage)) = chunk.remove("usage") {
+            out.insert("usage".into(), normalize_usage(usage));
+        }
+        Value::Object(out)
+    }
+
+    fn normalize_choice(
+        &mut self,
+        position: usize,
+        choice: serde_json::Value,
+    ) -> serde_json::Value {
+        use serde_json::{json, Map, Value};
+
+        let Value::Object(mut choice) = choice else {
+            return choice;
+        };
+        let index = choice
+            .get("index")
+            .and_then(Value::as_u64)
+            .unwrap_or(position as u64);
+        let source = match choice.remove("delta").or_else(|| choice.remove("message")) {
+            Some(Value::Object(delta)) => delta,
+            _ => Map::new(),
+        };
+        let mut delta: Map<String, Value> = source
+            .into_iter()
+            .filter(|(k, _)| DELTA_KEYS.contains(&k.as_str()))
+            .collect();
+        if !delta.contains_key("content") {
+            if let Some(text) = choice.remove("text").filter(Value::is_string) {
+                delta.insert("content".into(), text);
+            }
+        }
+        if !self.started.contains(&index) {
+            self.started.push(index);
+            delta.entry("role").or_insert_with(|| json!("assistant"));
+        }
+        let finish_reason = choice
+            .remove("finish_reason")
+            .or_else(|| choice.remove("stop_reason"))
+            .and_then(|v| v.as_str().map(normalize_finish_reason))
+            .map_or(Value::Null, |r| json!(r));
+
+        json!({
+            "index": index,
+            "delta": delta,
+            "logprobs": choice.remove("logprobs").unwrap_or(Value::Null),
+            "finish_reason": finish_reason,
+        })
+    }
+}
+
+/// OpenAI's finish reason for a provider's stop reason.
+fn normalize_finish_reason(reason: &str) -> &'static str {
+    match reason.to_ascii_lowercase().as_str() {
+        "length" | "max_tokens" | "max_output_tokens" | "model_length" => "length",
+        "tool_calls" | "tool_call" | "tool_use" | "function_calls" => "tool_calls",
+        "function_call" => "function_call",
+        "content_filter" | "content_filtered" | "safety" | "recitation" | "blocklist" => {
+            "content_filter"
+        }
+        // stop, end_turn, stop_sequence, eos, and anything unrecognized
+        _ => "stop",
+    }
+}
+
+/// `usage` with only OpenAI's token counts, `total_tokens` filled in.
+fn normalize_usage(usage: serde_json::Map<String, serde_json::Value>) -> serde_json::Value {
+    let mut out: serde_json::Map<String, serde_json::Value> = usage
+        .into_iter()
+        .filter(|(k, _)| {
+            matches!(
+                k.as_str(),
+                "prompt_tokens"
+                    | "completion_tokens"
+                    | "total_tokens"
+                    | "prompt_tokens_details"
+                    | "completion_tokens_details"
+            )
+        })
+        .collect();
+    if !out.contains_key("total_tokens") {
+        let count = |k: &str| out.get(k).and_then(serde_json::Value::as_u64);
+        if let (Some(prompt), Some(completion)) =
+            (count("prompt_tokens"), count("completion_tokens"))
+        {
+            out.insert("total_tokens".into(), (prompt + completion).into());
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -368,7 +818,7 @@ mod tests {
     /// Each event string is appended with `\n\n` (SSE event delimiter).
     /// The resulting byte buffer is split at the specified positions to
     /// simulate TCP chunk boundaries.
-    fn split_sse_at_positions(events: &[&str], split_positions: &[usize]) -> Vec<Vec<u8>> {
+    fn split_sse_at_positions(events: &[&str], split_positions: &[usize]) -> Vec<Bytes> {
         let full: Vec<u8> = events
             .iter()
             .flat_map(|e| format!("{}\n\n", e).into_bytes())
@@ -378,11 +828,11 @@ mod tests {
         let mut prev = 0;
         for &pos in split_positions {
             if pos > prev && pos < full.len() {
-                chunks.push(full[prev..pos].to_vec());
+                chunks.push(Bytes::copy_from_slice(&full[prev..pos]));
                 prev = pos;
             }
         }
-        chunks.push(full[prev..].to_vec());
+        chunks.push(Bytes::copy_from_slice(&full[prev..]));
         chunks
     }
 
@@ -509,7 +959,7 @@ mod tests {
         let raw = b"event: message\nid: 123\nretry: 5000\n: this is a comment\ndata: {\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\ndata: [DONE]\n\n";
 
         let mut observer = SseObserver::new();
-        observer.process_chunk(raw);
+        observer.process_chunk(&Bytes::from_static(raw));
         let result = observer.into_result();
 
         assert!(result.done_received);
@@ -521,7 +971,7 @@ mod tests {
         let raw = b"data: {\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}],\"usage\":null}\r\n\r\ndata: {\"id\":\"abc\",\"choices\":[],\"usage\":{\"prompt_tokens\":4,\"completion_tokens\":2,\"total_tokens\":6}}\r\n\r\ndata: [DONE]\r\n\r\n";
 
         let mut observer = SseObserver::new();
-        observer.process_chunk(raw);
+        observer.process_chunk(&Bytes::from_static(raw));
         let result = observer.into_result();
 
         assert!(result.done_received);
@@ -541,7 +991,7 @@ mod tests {
         let raw = b"data:{\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\ndata:[DONE]\n\n";
 
         let mut observer = SseObserver::new();
-        observer.process_chunk(raw);
+        observer.process_chunk(&Bytes::from_static(raw));
         let result = observer.into_result();
 
         assert!(result.done_received);
@@ -554,7 +1004,7 @@ mod tests {
         let raw = b"data: {\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\ndata: [DONE]";
 
         let mut observer = SseObserver::new();
-        observer.process_chunk(raw);
+        observer.process_chunk(&Bytes::from_static(raw));
         let result = observer.into_result();
 
         // flush_buffer in into_result should handle this
@@ -590,10 +1040,53 @@ mod tests {
         assert_eq!(result.finish_reason, Some("stop".to_string()));
     }
 
+    #[test]
+    fn test_byte_at_a_time_with_crlf_split() {
+        let raw = b"data: {\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"length\"}],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":9}}\r\n\r\ndata: [DONE]\r\n\r\n";
+        let full = Bytes::from_static(raw);
+
+        let mut observer = SseObserver::new();
+        for i in 0..full.len() {
+            observer.process_chunk(&full.slice(i..i + 1));
+        }
+        let result = observer.into_result();
+
+        assert!(result.done_received);
+        assert_eq!(result.finish_reason, Some("length".to_string()));
+        assert_eq!(
+            result.usage,
+            Some(StreamUsage {
+                prompt_tokens: 7,
+                completion_tokens: 9,
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_chunk_of_complete_lines_not_capped() {
+        // Many complete events in one chunk exceed BUFFER_CAP in total, but
+        // only a partial line counts towards the cap.
+        let mut full = String::new();
+        for _ in 0..2000 {
+            full.push_str(r#"data: {"id":"abc","choices":[{"index":0,"delta":{"content":"token"},"finish_reason":null}],"usage":null}"#);
+            full.push_str("\n\n");
+        }
+        full.push_str(r#"data: {"id":"abc","choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":null}"#);
+        full.push_str("\n\ndata: [DONE]\n\n");
+        assert!(full.len() > BUFFER_CAP);
+
+        let mut observer = SseObserver::new();
+        observer.process_chunk(&Bytes::from(full));
+        let result = observer.into_result();
+
+        assert!(result.done_received);
+        assert_eq!(result.finish_reason, Some("stop".to_string()));
+    }
+
     #[test]
     fn test_buffer_cap() {
         // Create a chunk exceeding 64KB without any newlines
-        let huge_chunk = vec![b'x'; 65 * 1024];
+        let huge_chunk = Bytes::from(vec![b'x'; 65 * 1024]);
 
         let mut observer = SseObserver::new();
         observer.process_chunk(&huge_chunk);
@@ -601,7 +1094,7 @@ mod tests {
         // After exceeding 64KB, the buffer should be drained.
         // Then we can still process normal data.
         let normal = b"data: {\"id\":\"abc\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\ndata: [DONE]\n\n";
-        observer.process_chunk(normal);
+        observer.process_chunk(&Bytes::from_static(normal));
         let result = observer.into_result();
 
         assert!(result.done_received);
@@ -788,4 +1281,157 @@ mod tests {
         );
         assert_eq!(result.finish_reason, Some("stop".to_string()));
     }
+
+    async fn normalized(chunks: Vec<&'static str>) -> Vec<serde_json::Value> {
+        use futures::StreamExt;
+
+        let stream = futures::stream::iter(
+            chunks
+                .into_iter()
+                .map(|c| Ok::<_, std::io::Error>(Bytes::from_static(c.as_bytes()))),
+        );
+        let body: Vec<u8> = normalize_sse_stream(stream, true)
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+        let body = String::from_utf8(body).unwrap();
+        body.split("\n\n")
+            .filter(|e| !e.is_empty())
+            .map(|e| {
+                let data = e.strip_prefix("data: ").unwrap();
+                serde_json::from_str(data).unwrap_or_else(|_| serde_json::json!(data))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_normalize_vendor_chunks() {
+        let events = normalized(vec![
+            ": ping\r\n\r\nevent: message_start\ndata: {\"id\":\"msg-1\",\"model\":\"claude\",\"created\":1,\"choices\":[{\"delta\":{\"content\":\"Hi\",\"reasoning\":\"x\"}}],\"x_vendor\":1}\n",
+            "\ndata: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"!\"},\"stop_reason\":\"end_turn\"}]}\n\n",
+            "event: ping\ndata: {\"finish_reason\":\"max_tokens\",\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"cost\":9}}\n\n",
+            "data: [DONE]",
+        ])
+        .await;
+
+        assert_eq!(events.len(), 4, "{:?}", events);
+        assert_eq!(
+            events[0],
+            serde_json::json!({
+                "id": "msg-1",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "claude",
+                "choices": [{
+                    "index": 0,
+                    "delta": {"role": "assistant", "content": "Hi"},
+                    "logprobs": null,
+                    "finish_reason": null
+                }]
+            })
+        );
+        assert_eq!(events[1]["id"], "msg-1");
+        assert_eq!(events[1]["model"], "claude");
+        assert!(events[1]["choices"][0]["delta"].get("role").is_none());
+        assert_eq!(events[1]["choices"][0]["finish_reason"], "stop");
+        assert_eq!(events[2]["choices"][0]["finish_reason"], "length");
+        assert_eq!(
+            events[2]["usage"],
+            serde_json::json!({"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5})
+        );
+        assert_eq!(events[3], "[DONE]");
+    }
+
+    #[tokio::test]
+    async fn test_normalize_disabled_passes_through() {
+        use futures::StreamExt;
+
+        let raw = ": ping\n\ndata: {\"x\":1}\n\n";
+        let stream =
+            futures::stream::iter([Ok::<_, std::io::Error>(Bytes::from_static(raw.as_bytes()))]);
+        let out: Vec<Bytes> = normalize_sse_stream(stream, false)
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(out, [Bytes::from_static(raw.as_bytes())]);
+    }
+
+    #[test]
+    fn test_delta_tokens_counted_as_chunks_arrive() {
+        let counter = DeltaTokenCounter::default();
+        let mut obs = SseObserver::with_handle(Arc::new(Mutex::new(None)), counter.clone());
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+        ));
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+        ));
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"a longer \\\"quoted\\\" reply\"}}]}\n\n",
+        ));
+        assert_eq!(counter.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn test_event_boundary() {
+        let mut boundary = EventBoundary::default();
+        assert!(boundary.at_boundary());
+        boundary.observe(b"data: {\"a\":1}");
+        assert!(!boundary.at_boundary());
+        boundary.observe(b"\n");
+        assert!(!boundary.at_boundary());
+        boundary.observe(b"\n");
+        assert!(boundary.at_boundary());
+        boundary.observe(b"data: x\r\n\r\n");
+        assert!(boundary.at_boundary());
+        boundary.observe(b"");
+        assert!(boundary.at_boundary());
+    }
+
+    #[test]
+    fn test_tokens_per_second_from_choice_timeline() {
+        let mut obs = SseObserver::new();
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\n",
+        ));
+        std::thread::sleep(Duration::from_millis(50));
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"b\"},\"finish_reason\":\"stop\"}]}\n\n\
+              data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":10}}\n\n\
+              data: [DONE]\n\n",
+        ));
+        let result = obs.into_result();
+        let generation = result.generation_time.expect("two choice chunks");
+        assert!(generation >= Duration::from_millis(50));
+        let tps = result.tokens_per_second().unwrap();
+        assert!(tps > 0.0 && tps <= 200.0, "{}", tps);
+
+        // A completion delivered in a single chunk has no interval to measure
+        let mut obs = SseObserver::new();
+        obs.process_chunk(&Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":1}}\n\ndata: [DONE]\n\n",
+        ));
+        let result = obs.into_result();
+        assert!(result.generation_time.is_none());
+        assert!(result.tokens_per_second().is_none());
+    }
+
+    #[tokio::test]
+    async fn splits_events_and_skips_comments() {
+        let body = axum::body::Body::from(
+            ": keep-alive\n\n\
+             data: {\"a\":1}\n\n\
+             data: {\"b\":\n\
+             data: 2}\n\n\
+             data: [DONE]",
+        );
+        let mut events = SseEvents::new(body);
+        assert_eq!(events.next().await.as_deref(), Some("{\"a\":1}"));
+        assert_eq!(events.next().await.as_deref(), Some("{\"b\":\n2}"));
+        assert_eq!(events.next().await.as_deref(), Some("[DONE]"));
+        assert_eq!(events.next().await, None);
+    }
 }
@@ -0,0 +1,294 @@
+//! Structured output (`response_format`).
+//!
+//! Requests asking for `json_object` or `json_schema` only route to
+//! providers with `structured_output = true`; the `response_format` object
+//! itself is forwarded unchanged. With `routing.validate_structured_output`
+//! the message content of non-streaming responses is checked at the proxy,
+//! and a non-conforming reply gets one re-ask on the same provider.
+//!
+//! Schemas are checked against a subset of JSON Schema: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`,
+//! `minItems`/`maxItems`, `minLength`/`maxLength`, `minimum`/`maximum`,
+//! and `anyOf`. Other keywords (including `$ref`) are not enforced.
+
+use serde_json::Value;
+
+use super::types::{ChatCompletionRequest, Message, MessageContent};
+use crate::error::Error;
+
+/// A JSON `response_format` requested by the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// `{"type": "json_object"}`: any JSON object.
+    JsonObject,
+    /// `{"type": "json_schema", "json_schema": {"schema": ...}}`.
+    JsonSchema { name: String, schema: Value },
+}
+
+impl ResponseFormat {
+    /// The JSON format a request asks for. `Ok(None)` for plain text or no
+    /// `response_format`.
+    pub fn from_request(request: &ChatCompletionRequest) -> Result<Option<Self>, Error> {
+        let Some(format) = request.extra.get("response_format") else {
+            return Ok(None);
+        };
+        match format.get("type").and_then(Value::as_str) {
+            Some("json_object") => Ok(Some(Self::JsonObject)),
+            Some("json_schema") => {
+                let spec = format.get("json_schema").ok_or_else(|| {
+                    Error::BadRequest(
+                        "response_format json_schema requires a json_schema object".to_string(),
+                    )
+                })?;
+                let schema = spec.get("schema").cloned().unwrap_or(Value::Bool(true));
+                if !schema.is_object() && !schema.is_boolean() {
+                    return Err(Error::BadRequest(
+                        "response_format json_schema.schema must be an object".to_string(),
+                    ));
+                }
+                let name = spec
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("response")
+                    .to_string();
+                Ok(Some(Self::JsonSchema { name, schema }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Check response content against the format.
+    pub fn check(&self, content: &str) -> Result<(), String> {
+        let value: Value =
+            serde_json::from_str(content).map_err(|e| format!("reply is not valid JSON: {}", e))?;
+        match self {
+            Self::JsonObject if value.is_object() => Ok(()),
+            Self::JsonObject => Err("reply is not a JSON object".to_string()),
+            Self::JsonSchema { name, schema } => check_schema(schema, &value, "$")
+                .map_err(|e| format!("reply does not match schema '{}': {}", name, e)),
+        }
+    }
+}
+
+/// `request` with the rejected reply and a correction appended, for the
+/// re-ask.
+pub fn reask_request(
+    request: &ChatCompletionRequest,
+    reply: &str,
+    reason: &str,
+) -> ChatCompletionRequest {
+    let message = |role: &str, content: String| Message {
+        role: role.to_string(),
+        content: MessageContent::Text(content),
+        name: None,
+        extra: Default::default(),
+    };
+    let mut reask = request.clone();
+    reask.messages.push(message("assistant", reply.to_string()));
+    reask.messages.push(message(
+        "user",
+        format!(
+            "Your reply did not satisfy the required response format: {}. Reply again with only the corrected JSON.",
+            reason
+        ),
+    ));
+    reask
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Check `value` against the supported subset of JSON Schema. `path` is
+/// the JSON path reported in errors.
+fn check_schema(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{} is not allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(ty) = schema.get("type") {
+        let ok = match ty {
+            Value::String(name) => type_matches(name, value),
+            Value::Array(names) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|name| type_matches(name, value)),
+            _ => true,
+        };
+        if !ok {
+            return Err(format!("{} should be of type {}", path, ty));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!(
+                "{} is not one of {}",
+                path,
+                Value::from(options.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{} should be {}", path, expected));
+        }
+    }
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        if !any_of.iter().any(|s| check_schema(s, value, path).is_ok()) {
+            return Err(format!("{} matches none of anyOf", path));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(key) {
+                        return Err(format!("{} is missing required property '{}'", path, key));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, item) in object {
+                let item_path = format!("{}.{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(item_schema) => check_schema(item_schema, item, &item_path)?,
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            check_schema(additional, item, &item_path)?;
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    return Err(format!("{} should have at least {} items", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    return Err(format!("{} should have at most {} items", path, max));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_schema(item_schema, item, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    return Err(format!("{} should be at least {} characters", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    return Err(format!("{} should be at most {} characters", path, max));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    return Err(format!("{} should be at least {}", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    return Err(format!("{} should be at most {}", path, max));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(response_format: Value) -> ChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "response_format": response_format,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_response_format() {
+        assert_eq!(
+            ResponseFormat::from_request(&request(json!({"type": "text"}))).unwrap(),
+            None
+        );
+        assert_eq!(
+            ResponseFormat::from_request(&request(json!({"type": "json_object"}))).unwrap(),
+            Some(ResponseFormat::JsonObject)
+        );
+        let format = ResponseFormat::from_request(&request(json!({
+            "type": "json_schema",
+            "json_schema": {"name": "answer", "schema": {"type": "object"}, "strict": true}
+        })))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            format,
+            ResponseFormat::JsonSchema {
+                name: "answer".to_string(),
+                schema: json!({"type": "object"})
+            }
+        );
+        assert!(ResponseFormat::from_request(&request(json!({"type": "json_schema"}))).is_err());
+    }
+
+    #[test]
+    fn checks_schema_subset() {
+        let format = ResponseFormat::JsonSchema {
+            name: "person".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "minLength": 1},
+                    "age": {"type": "integer", "minimum": 0},
+                    "role": {"enum": ["admin", "user"]},
+                    "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2}
+                },
+                "required": ["name", "age"],
+                "additionalProperties": false
+            }),
+        };
+        assert!(format
+            .check(r#"{"name": "Ada", "age": 36, "tags": ["x"]}"#)
+            .is_ok());
+        let err = |content: &str| format.check(content).unwrap_err();
+        assert!(err("not json").contains("not valid JSON"));
+        assert!(err(r#"{"name": "Ada"}"#).contains("required property 'age'"));
+        assert!(err(r#"{"name": "Ada", "age": 1.5}"#).contains("$.age"));
+        assert!(err(r#"{"name": "Ada", "age": 1, "role": "root"}"#).contains("$.role"));
+        assert!(err(r#"{"name": "Ada", "age": 1, "tags": [1]}"#).contains("$.tags[0]"));
+        assert!(err(r#"{"name": "Ada", "age": 1, "extra": 1}"#).contains("$.extra"));
+
+        assert!(ResponseFormat::JsonObject.check("[1]").is_err());
+        assert!(ResponseFormat::JsonObject.check("{}").is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+//! Cost allocation tags.
+//!
+//! Clients attach `key=value` pairs to a request via the `X-Arbstr-Tags`
+//! header (e.g. `team=search,env=prod`). Tags are stored in the
+//! `request_tags` table and can be used to filter or group stats and
+//! request logs, so spend can be attributed to teams or projects without
+//! running separate proxies.
+
+use crate::error::Error;
+
+/// Maximum number of tags accepted on a single request.
+pub const MAX_TAGS: usize = 16;
+
+/// Maximum length of a tag key or value (bytes).
+pub const MAX_TAG_LEN: usize = 64;
+
+/// Whether a character is allowed in a tag key.
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Parse and validate a single `key=value` pair.
+fn parse_pair(pair: &str) -> Result<(String, String), String> {
+    let (key, value) = pair
+        .split_once('=')
+        .ok_or_else(|| format!("Tag '{}' must be in key=value form", pair))?;
+    let key = key.trim();
+    let value = value.trim();
+
+    if key.is_empty() {
+        return Err(format!("Tag '{}' has an empty key", pair));
+    }
+    if value.is_empty() {
+        return Err(format!("Tag '{}' has an empty value", key));
+    }
+    if key.len() > MAX_TAG_LEN || value.len() > MAX_TAG_LEN {
+        return Err(format!(
+            "Tag '{}' exceeds the {} character limit",
+            key, MAX_TAG_LEN
+        ));
+    }
+    if !key.chars().all(is_key_char) {
+        return Err(format!(
+            "Tag key '{}' may only contain letters, digits, '_', '-' and '.'",
+            key
+        ));
+    }
+    if value.chars().any(|c| c.is_control()) {
+        return Err(format!("Tag '{}' value contains control characters", key));
+    }
+
+    Ok((key.to_lowercase(), value.to_string()))
+}
+
+/// Parse an `X-Arbstr-Tags` header value into `(key, value)` pairs.
+///
+/// Pairs are comma-separated; surrounding whitespace is trimmed and keys are
+/// lowercased. A repeated key keeps the last value. Empty segments (e.g. a
+/// trailing comma) are ignored.
+pub fn parse_tags(header: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut tags: Vec<(String, String)> = Vec::new();
+
+    for segment in header.split(',') {
+        let segment = segment.trim();
+        if segment.is_empty() {
+            continue;
+        }
+        let (key, value) = parse_pair(segment).map_err(Error::BadRequest)?;
+        if let Some(existing) = tags.iter_mut().find(|(k, _)| *k == key) {
+            existing.1 = value;
+        } else {
+            tags.push((key, value));
+        }
+    }
+
+    if tags.len() > MAX_TAGS {
+        return Err(Error::BadRequest(format!(
+            "Too many tags ({}); at most {} are allowed",
+            tags.len(),
+            MAX_TAGS
+        )));
+    }
+
+    Ok(tags)
+}
+
+/// Parse a `tag=key=value` query filter (`key=value` portion).
+pub fn parse_tag_filter(filter: &str) -> Result<(String, String), Error> {
+    parse_pair(filter.trim()).map_err(|e| Error::BadRequest(format!("Invalid 'tag' filter: {}", e)))
+}
+
+/// Parse the tag key out of a `group_by=tag:<key>` value.
+///
+/// Returns `None` if the value does not use the `tag:` prefix.
+pub fn parse_group_by_tag(group_by: &str) -> Option<Result<String, Error>> {
+    let key = group_by.strip_prefix("tag:")?;
+    if key.is_empty() || key.len() > MAX_TAG_LEN || !key.chars().all(is_key_char) {
+        return Some(Err(Error::BadRequest(format!(
+            "Invalid tag key in group_by '{}'",
+            group_by
+        ))));
+    }
+    Some(Ok(key.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags_basic() {
+        let tags = parse_tags("team=search,env=prod").unwrap();
+        assert_eq!(
+            tags,
+            vec![
+                ("team".to_string(), "search".to_string()),
+                ("env".to_string(), "prod".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_tags_trims_and_lowercases_keys() {
+        let tags = parse_tags(" Team = Search , ,env=prod,").unwrap();
+        assert_eq!(tags[0], ("team".to_string(), "Search".to_string()));
+        assert_eq!(tags.len(), 2);
+    }
+
+    #[test]
+    fn parse_tags_duplicate_key_keeps_last() {
+        let tags = parse_tags("env=dev,env=prod").unwrap();
+        assert_eq!(tags, vec![("env".to_string(), "prod".to_string())]);
+    }
+
+    #[test]
+    fn parse_tags_rejects_malformed() {
+        assert!(parse_tags("team").is_err());
+        assert!(parse_tags("=search").is_err());
+        assert!(parse_tags("team=").is_err());
+        assert!(parse_tags("te am=search").is_err());
+    }
+
+    #[test]
+    fn parse_tags_rejects_too_many() {
+        let header: Vec<String> = (0..=MAX_TAGS).map(|i| format!("k{}=v", i)).collect();
+        assert!(parse_tags(&header.join(",")).is_err());
+    }
+
+    #[test]
+    fn parse_tags_rejects_long_values() {
+        let header = format!("team={}", "x".repeat(MAX_TAG_LEN + 1));
+        assert!(parse_tags(&header).is_err());
+    }
+
+    #[test]
+    fn parse_group_by_tag_variants() {
+        assert!(parse_group_by_tag("model").is_none());
+        assert_eq!(parse_group_by_tag("tag:Team").unwrap().unwrap(), "team");
+        assert!(parse_group_by_tag("tag:").unwrap().is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Ordering of candidates with equal routing cost.
+//!
+//! The router sorts candidates by routing cost (`output_rate + base_fee`),
+//! which leaves providers charging the same in the order they are declared.
+//! `[routing] tie_breakers` lists rules to order such candidates instead,
+//! each consulted only when the ones before it can't tell two candidates
+//! apart: recent latency, recent success rate, declaration order, or a
+//! random order per request. Latency and success rate come from a rolling
+//! window of each provider's last outcomes, kept in memory; providers with
+//! no recorded outcomes are ordered before those with some, so each gets
+//! measured.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use dashmap::DashMap;
+use rand::Rng;
+
+use crate::config::{ProviderConfig, TieBreaker};
+use crate::router::SelectedProvider;
+
+/// Outcomes kept per provider for `latency` and `success_rate`.
+const WINDOW: usize = 50;
+
+/// One recorded request outcome.
+#[derive(Debug, Clone, Copy)]
+struct Outcome {
+    success: bool,
+    latency_ms: u64,
+}
+
+/// What a candidate is compared on, gathered once per request.
+struct Keys {
+    routing_cost: f64,
+    probe: bool,
+    avg_latency_ms: Option<f64>,
+    success_rate: Option<f64>,
+    preference: usize,
+    random: u64,
+}
+
+/// `a` before `b` when lower; unknown values first.
+fn unknown_first(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Applies `[routing] tie_breakers`, recording the outcomes they need.
+///
+/// A tracker built without tie-breakers is inert: nothing is recorded and
+/// candidates pass through unchanged.
+#[derive(Debug, Default)]
+pub struct TieBreakTracker {
+    tie_breakers: Vec<TieBreaker>,
+    /// Position of each provider in the config, for `preference`.
+    order: HashMap<String, usize>,
+    outcomes: DashMap<String, VecDeque<Outcome>>,
+}
+
+impl TieBreakTracker {
+    /// Create a tracker for `tie_breakers` over the configured providers.
+    pub fn new(tie_breakers: &[TieBreaker], providers: &[ProviderConfig]) -> Self {
+        let mut order = HashMap::new();
+        for (i, provider) in providers.iter().enumerate() {
+            order.entry(provider.name.clone()).or_insert(i);
+        }
+        Self {
+            tie_breakers: tie_breakers.to_vec(),
+            order,
+            outcomes: DashMap::new(),
+        }
+    }
+
+    /// Whether any rule needs recorded outcomes.
+    fn tracks_outcomes(&self) -> bool {
+        self.tie_breakers
+            .iter()
+            .any(|t| matches!(t, TieBreaker::Latency | TieBreaker::SuccessRate))
+    }
+
+    /// Record a request outcome for `provider`.
+    pub fn record(&self, provider: &str, success: bool, latency_ms: u64) {
+        if !self.tracks_outcomes() {
+            return;
+        }
+        let mut outcomes = self.outcomes.entry(provider.to_string()).or_default();
+        outcomes.push_back(Outcome {
+            success,
+            latency_ms,
+        });
+        while outcomes.len() > WINDOW {
+            outcomes.pop_front();
+        }
+    }
+
+    /// Average latency of `provider`'s recent successful requests.
+    fn avg_latency_ms(&self, provider: &str) -> Option<f64> {
+        let outcomes = self.outcomes.get(provider)?;
+        let latencies: Vec<u64> = outcomes
+            .iter()
+            .filter(|o| o.success)
+            .map(|o| o.latency_ms)
+            .collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        Some(latencies.iter().sum::<u64>() as f64 / latencies.len() as f64)
+    }
+
+    /// Share of `provider`'s recent requests that succeeded.
+    fn success_rate(&self, provider: &str) -> Option<f64> {
+        let outcomes = self.outcomes.get(provider)?;
+        if outcomes.is_empty() {
+            return None;
+        }
+        let successes = outcomes.iter().filter(|o| o.success).count();
+        Some(successes as f64 / outcomes.len() as f64)
+    }
+
+    fn compare(&self, a: &Keys, b: &Keys) -> Ordering {
+        let mut ordering = b
+            .probe
+            .cmp(&a.probe)
+            .then(a.routing_cost.total_cmp(&b.routing_cost));
+        for tie_breaker in &self.tie_breakers {
+            ordering = ordering.then_with(|| match tie_breaker {
+                TieBreaker::Latency => unknown_first(a.avg_latency_ms, b.avg_latency_ms),
+                // Negated so the higher rate comes first
+                TieBreaker::SuccessRate => {
+                    unknown_first(a.success_rate.map(|r| -r), b.success_rate.map(|r| -r))
+                }
+                TieBreaker::Preference => a.preference.cmp(&b.preference),
+                TieBreaker::Random => a.random.cmp(&b.random),
+            });
+        }
+        ordering
+    }
+
+    /// Order circuit-approved candidates, cheapest first, with equal
+    /// routing costs settled by the configured tie-breakers.
+    ///
+    /// The half-open probe provider, if any, stays in front so the probe
+    /// still runs. Candidates the tie-breakers can't tell apart keep their
+    /// order.
+    pub fn apply(
+        &self,
+        candidates: Vec<SelectedProvider>,
+        probe_provider: Option<&str>,
+    ) -> Vec<SelectedProvider> {
+        if self.tie_breakers.is_empty() || candidates.len() < 2 {
+            return candidates;
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut keyed: Vec<(Keys, SelectedProvider)> = candidates
+            .into_iter()
+            .map(|c| {
+                let keys = Keys {
+                    routing_cost: c.output_rate + c.base_fee,
+                    probe: probe_provider == Some(c.name.as_str()),
+                    avg_latency_ms: self.avg_latency_ms(&c.name),
+                    success_rate: self.success_rate(&c.name),
+                    preference: self.order.get(&c.name).copied().unwrap_or(usize::MAX),
+                    random: rng.gen(),
+                };
+                (keys, c)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| self.compare(a, b));
+        keyed.into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, output_rate: f64) -> ProviderConfig {
+        ProviderConfig {
+            output_rate,
+            ..ProviderConfig::for_test(name)
+        }
+    }
+
+    fn names(candidates: &[SelectedProvider]) -> Vec<&str> {
+        candidates.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// `a`, `b` and `c` cost the same; `cheap` is cheaper than all three.
+    fn setup(tie_breakers: &[TieBreaker]) -> (TieBreakTracker, Vec<SelectedProvider>) {
+        let providers = vec![
+            provider("a", 10.0),
+            provider("b", 10.0),
+            provider("cheap", 5.0),
+            provider("c", 10.0),
+        ];
+        let tracker = TieBreakTracker::new(tie_breakers, &providers);
+        // As the router hands them over: by cost, ties in config order
+        let candidates = ["cheap", "a", "b", "c"]
+            .iter()
+            .map(|name| SelectedProvider::from(providers.iter().find(|p| p.name == *name).unwrap()))
+            .collect();
+        (tracker, candidates)
+    }
+
+    #[test]
+    fn no_tie_breakers_leave_candidates_alone() {
+        let (tracker, candidates) = setup(&[]);
+        tracker.record("c", true, 1);
+        assert!(tracker.outcomes.is_empty());
+        assert_eq!(
+            names(&tracker.apply(candidates, None)),
+            ["cheap", "a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn latency_orders_ties_and_unknown_latency_goes_first() {
+        let (tracker, candidates) = setup(&[TieBreaker::Latency]);
+        tracker.record("b", true, 100);
+        tracker.record("c", true, 50);
+        tracker.record("c", false, 0);
+        tracker.record("cheap", true, 900);
+        assert_eq!(
+            names(&tracker.apply(candidates, None)),
+            ["cheap", "a", "c", "b"]
+        );
+    }
+
+    #[test]
+    fn success_rate_then_preference_settle_remaining_ties() {
+        let (tracker, candidates) = setup(&[TieBreaker::SuccessRate, TieBreaker::Preference]);
+        // a and b both succeed every time; c fails half its requests
+        for name in ["a", "b", "c"] {
+            tracker.record(name, true, 10);
+        }
+        tracker.record("c", false, 0);
+        let candidates: Vec<_> = candidates.into_iter().rev().collect();
+        assert_eq!(
+            names(&tracker.apply(candidates, None)),
+            ["cheap", "a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn sort_is_stable_and_probe_stays_first() {
+        let (tracker, candidates) = setup(&[TieBreaker::Latency]);
+        // No outcomes: every tie stays in the order given, however often applied
+        let once = tracker.apply(candidates.clone(), Some("b"));
+        assert_eq!(names(&once), ["b", "cheap", "a", "c"]);
+        let twice = tracker.apply(once.clone(), Some("b"));
+        assert_eq!(names(&twice), names(&once));
+    }
+
+    #[test]
+    fn random_shuffles_ties_only() {
+        let (tracker, candidates) = setup(&[TieBreaker::Random]);
+        let mut firsts = std::collections::HashSet::new();
+        for _ in 0..200 {
+            let ordered = tracker.apply(candidates.clone(), None);
+            assert_eq!(ordered[0].name, "cheap");
+            let mut rest = names(&ordered[1..]);
+            firsts.insert(rest[0].to_string());
+            rest.sort();
+            assert_eq!(rest, ["a", "b", "c"]);
+        }
+        assert_eq!(firsts.len(), 3, "{:?}", firsts);
+    }
+
+    #[test]
+    fn window_keeps_recent_outcomes() {
+        let (tracker, _) = setup(&[TieBreaker::SuccessRate]);
+        tracker.record("a", false, 0);
+        for _ in 0..WINDOW {
+            tracker.record("a", true, 10);
+        }
+        assert_eq!(tracker.success_rate("a"), Some(1.0));
+    }
+}
@@ -0,0 +1,195 @@
+//! W3C trace context and `x-request-id` propagation.
+//!
+//! An incoming `traceparent` is continued (same trace ID, arbstr as the new
+//! parent span); otherwise a new trace is started. An incoming
+//! `x-request-id` is kept, defaulting to arbstr's correlation ID. Both are
+//! sent to providers, echoed back to the client, and recorded in the
+//! requests table next to the correlation ID.
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use uuid::Uuid;
+
+/// W3C trace context header.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+/// Request ID header, echoed back and forwarded upstream.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest accepted client `x-request-id`; longer values are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Trace identifiers for one request, stored in request extensions.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    /// 32 hex chars, shared by the whole distributed trace.
+    pub trace_id: String,
+    /// 16 hex chars identifying arbstr's span; the parent of provider spans.
+    pub span_id: String,
+    /// Trace flags (2 hex chars, e.g. "01" for sampled).
+    pub flags: String,
+    /// The client's `traceparent`, when it sent a valid one.
+    pub incoming_traceparent: Option<String>,
+    /// Client `x-request-id`, or the correlation ID when absent.
+    pub request_id: String,
+    /// Whether the request is traced in full (see [`super::sampling`]).
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Build from incoming request headers.
+    pub fn from_headers(headers: &HeaderMap, correlation_id: &str) -> Self {
+        let incoming = headers
+            .get(TRACEPARENT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_traceparent);
+        let request_id = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN)
+            .map(str::to_string)
+            .unwrap_or_else(|| correlation_id.to_string());
+
+        let span_id = new_span_id();
+        match incoming {
+            Some((trace_id, flags, raw)) => Self {
+                trace_id,
+                span_id,
+                flags,
+                incoming_traceparent: Some(raw),
+                request_id,
+                sampled: true,
+            },
+            None => Self {
+                trace_id: Uuid::new_v4().simple().to_string(),
+                span_id,
+                flags: "01".to_string(),
+                incoming_traceparent: None,
+                request_id,
+                sampled: true,
+            },
+        }
+    }
+
+    /// `traceparent` sent to providers: this trace, with arbstr as parent.
+    pub fn upstream_traceparent(&self) -> String {
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Insert `traceparent` and `x-request-id` for the provider request,
+    /// replacing any client-supplied values.
+    pub fn apply_upstream(&self, headers: &mut HeaderMap) {
+        insert(headers, TRACEPARENT_HEADER, &self.upstream_traceparent());
+        insert(headers, REQUEST_ID_HEADER, &self.request_id);
+    }
+
+    /// Echo `traceparent` and `x-request-id` on the client response. The
+    /// client's own `traceparent` is returned unchanged when it sent one.
+    pub fn apply_response(&self, headers: &mut HeaderMap) {
+        let traceparent = self
+            .incoming_traceparent
+            .clone()
+            .unwrap_or_else(|| self.upstream_traceparent());
+        insert(headers, TRACEPARENT_HEADER, &traceparent);
+        insert(headers, REQUEST_ID_HEADER, &self.request_id);
+    }
+}
+
+fn insert(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// Parse a version-00 `traceparent`, returning (trace_id, flags, original).
+/// All-zero IDs and malformed values are rejected per the W3C spec.
+fn parse_traceparent(value: &str) -> Option<(String, String, String)> {
+    let value = value.trim();
+    let mut parts = value.split('-');
+    let (version, trace_id, parent_id, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || version != "00" {
+        return None;
+    }
+    let is_hex = |s: &str, len: usize| {
+        s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+        return None;
+    }
+    if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    Some((trace_id.to_string(), flags.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn continues_incoming_trace() {
+        let ctx = TraceContext::from_headers(
+            &headers(&[("traceparent", VALID), ("x-request-id", "req-1")]),
+            "corr",
+        );
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.flags, "01");
+        assert_eq!(ctx.span_id.len(), 16);
+        assert_ne!(ctx.span_id, "00f067aa0ba902b7");
+        assert_eq!(ctx.request_id, "req-1");
+
+        let mut response = HeaderMap::new();
+        ctx.apply_response(&mut response);
+        assert_eq!(response.get("traceparent").unwrap(), VALID);
+
+        let mut upstream = headers(&[("traceparent", VALID)]);
+        ctx.apply_upstream(&mut upstream);
+        assert_eq!(
+            upstream.get("traceparent").unwrap().to_str().unwrap(),
+            format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", ctx.span_id)
+        );
+        assert_eq!(upstream.get("x-request-id").unwrap(), "req-1");
+    }
+
+    #[test]
+    fn starts_new_trace_when_missing_or_invalid() {
+        for bad in [
+            None,
+            Some("00-abc-def-01"),
+            Some("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            Some("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+            Some("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"),
+        ] {
+            let map = match bad {
+                Some(v) => headers(&[("traceparent", v)]),
+                None => HeaderMap::new(),
+            };
+            let ctx = TraceContext::from_headers(&map, "corr-id");
+            assert!(ctx.incoming_traceparent.is_none(), "{:?}", bad);
+            assert_eq!(ctx.trace_id.len(), 32);
+            assert_eq!(ctx.request_id, "corr-id");
+            assert!(parse_traceparent(&ctx.upstream_traceparent()).is_some());
+        }
+    }
+
+    #[test]
+    fn oversized_request_id_is_replaced() {
+        let long = "x".repeat(MAX_REQUEST_ID_LEN + 1);
+        let ctx = TraceContext::from_headers(&headers(&[("x-request-id", &long)]), "corr");
+        assert_eq!(ctx.request_id, "corr");
+    }
+}
@@ -0,0 +1,172 @@
+//! Conversation trimming (`[policies.rules.trim]`).
+//!
+//! Long agent conversations eventually outgrow the model's context window
+//! and fail upstream. A policy with `trim` removes the oldest non-system
+//! messages until the estimated prompt plus the reply fits, optionally
+//! replacing them with a summary written by a cheap model. Token counts use
+//! the same chars/4 estimate as cost estimation.
+
+use super::types::{ChatCompletionRequest, Message, MessageContent};
+
+/// Log tag key recording a trimmed conversation ("dropped" or
+/// "summarized"; "summary" on the summarization request's own row).
+pub const TRIM_TAG: &str = "trim";
+/// Log tag key recording how many messages were removed.
+pub const TRIMMED_MESSAGES_TAG: &str = "trimmed_messages";
+
+/// Reply length requested from the summary model, and kept free for the
+/// summary when trimming.
+pub const SUMMARY_MAX_TOKENS: u32 = 512;
+
+/// Remove the oldest non-system messages until the estimated prompt leaves
+/// `reserve_tokens` of `context_tokens` free, returning them in order.
+///
+/// System messages and the latest message are never removed. Tool results
+/// directly after a removed message go with it, so no tool result is left
+/// without its call.
+pub fn drop_oldest(
+    request: &mut ChatCompletionRequest,
+    context_tokens: u32,
+    reserve_tokens: u32,
+) -> Vec<Message> {
+    let budget = context_tokens.saturating_sub(reserve_tokens);
+    let mut removed = Vec::new();
+    while request.estimate_tokens(0).0 > budget {
+        let last = request.messages.len().saturating_sub(1);
+        let Some(i) = request.messages[..last]
+            .iter()
+            .position(|m| m.role != "system")
+        else {
+            break;
+        };
+        removed.push(request.messages.remove(i));
+        while i + 1 < request.messages.len() && request.messages[i].role == "tool" {
+            removed.push(request.messages.remove(i));
+        }
+    }
+    removed
+}
+
+/// Request asking `model` to summarize `removed` messages.
+pub fn summary_request(model: &str, removed: &[Message]) -> ChatCompletionRequest {
+    let transcript = removed
+        .iter()
+        .map(|m| format!("{}: {}", m.role, m.content.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            text_message(
+                "system",
+                "Summarize the following conversation excerpt in a few sentences. \
+                 Keep facts, decisions, names and open questions; omit pleasantries.",
+            ),
+            text_message("user", &transcript),
+        ],
+        temperature: Some(0.0),
+        max_tokens: Some(SUMMARY_MAX_TOKENS),
+        stream: Some(false),
+        stream_options: None,
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        stop: None,
+        user: None,
+        extra: Default::default(),
+    }
+}
+
+/// Insert `summary` as a system message after the leading system messages.
+pub fn insert_summary(request: &mut ChatCompletionRequest, summary: &str) {
+    let at = request
+        .messages
+        .iter()
+        .position(|m| m.role != "system")
+        .unwrap_or(request.messages.len());
+    request.messages.insert(
+        at,
+        text_message(
+            "system",
+            &format!("Summary of the earlier conversation:\n{}", summary.trim()),
+        ),
+    );
+}
+
+pub(super) fn text_message(role: &str, content: &str) -> Message {
+    Message {
+        role: role.to_string(),
+        content: MessageContent::Text(content.to_string()),
+        name: None,
+        extra: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(turns: &[(&str, usize)]) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: turns
+                .iter()
+                .map(|(role, chars)| text_message(role, &"x".repeat(*chars)))
+                .collect(),
+            ..summary_request("gpt-4o", &[])
+        }
+    }
+
+    fn roles(request: &ChatCompletionRequest) -> Vec<&str> {
+        request.messages.iter().map(|m| m.role.as_str()).collect()
+    }
+
+    #[test]
+    fn drops_oldest_non_system_messages_until_it_fits() {
+        // 100 tokens per message
+        let mut request = conversation(&[
+            ("system", 400),
+            ("user", 400),
+            ("assistant", 400),
+            ("user", 400),
+            ("assistant", 400),
+            ("user", 400),
+        ]);
+        let removed = drop_oldest(&mut request, 450, 100);
+        assert_eq!(removed.len(), 3);
+        assert_eq!(roles(&request), vec!["system", "assistant", "user"]);
+
+        // Fits already: untouched
+        let removed = drop_oldest(&mut request, 1000, 100);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_latest_message_and_tool_pairs() {
+        let mut request = conversation(&[
+            ("assistant", 400),
+            ("tool", 400),
+            ("tool", 400),
+            ("user", 400),
+        ]);
+        let removed = drop_oldest(&mut request, 150, 0);
+        assert_eq!(
+            removed.iter().map(|m| m.role.as_str()).collect::<Vec<_>>(),
+            vec!["assistant", "tool", "tool"]
+        );
+        // Still too long, but the latest message stays
+        let removed = drop_oldest(&mut request, 10, 0);
+        assert!(removed.is_empty());
+        assert_eq!(roles(&request), vec!["user"]);
+    }
+
+    #[test]
+    fn summary_goes_after_system_messages() {
+        let mut request = conversation(&[("system", 4), ("user", 4)]);
+        insert_summary(&mut request, " they agreed on Tuesday ");
+        assert_eq!(roles(&request), vec!["system", "system", "user"]);
+        assert_eq!(
+            request.messages[1].content.as_str(),
+            "Summary of the earlier conversation:\nthey agreed on Tuesday"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Finish-reason and truncation analytics endpoint.
+//!
+//! Breaks successful completions down by upstream `finish_reason` per
+//! model/provider pair and reports the share cut off with `length`. A
+//! provider whose truncation rate stands out from its peers on the same
+//! model is likely capping `max_tokens` below what was requested.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::server::AppState;
+use super::stats::resolve_time_range;
+use crate::error::Error;
+use crate::storage;
+use crate::storage::stats::FinishReasonRow;
+
+/// finish_reason reported when generation stopped at the token limit.
+const TRUNCATED_FINISH_REASON: &str = "length";
+
+/// Query parameters for GET /v1/stats/truncation.
+#[derive(Debug, Deserialize)]
+pub struct TruncationQuery {
+    pub range: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    /// Tag filter in `key=value` form.
+    pub tag: Option<String>,
+}
+
+/// Response for GET /v1/stats/truncation.
+#[derive(Debug, Serialize)]
+pub struct TruncationResponse {
+    pub since: String,
+    pub until: String,
+    pub totals: TruncationSummary,
+    /// One entry per model/provider pair, highest truncation rate first.
+    pub groups: Vec<TruncationGroup>,
+}
+
+/// finish_reason counts and truncation rate for a set of completions.
+#[derive(Debug, Default, Serialize)]
+pub struct TruncationSummary {
+    /// Successful requests with a recorded finish_reason.
+    pub completions: i64,
+    /// Completions that stopped with `length`.
+    pub truncated: i64,
+    /// `truncated / completions` (0 when there are no completions).
+    pub truncation_rate: f64,
+    pub finish_reasons: BTreeMap<String, i64>,
+}
+
+impl TruncationSummary {
+    fn add(&mut self, finish_reason: &str, count: i64) {
+        self.completions += count;
+        if finish_reason == TRUNCATED_FINISH_REASON {
+            self.truncated += count;
+        }
+        *self
+            .finish_reasons
+            .entry(finish_reason.to_string())
+            .or_insert(0) += count;
+        self.truncation_rate = self.truncated as f64 / self.completions as f64;
+    }
+}
+
+/// Truncation summary for one model/provider pair.
+#[derive(Debug, Serialize)]
+pub struct TruncationGroup {
+    pub model: String,
+    pub provider: String,
+    #[serde(flatten)]
+    pub summary: TruncationSummary,
+}
+
+/// Fold per-reason rows into overall totals and per-pair groups.
+///
+/// Groups are ordered by truncation rate (descending), then by completion
+/// count (descending), then by model and provider name.
+pub fn summarize(rows: &[FinishReasonRow]) -> (TruncationSummary, Vec<TruncationGroup>) {
+    let mut totals = TruncationSummary::default();
+    let mut pairs: BTreeMap<(String, String), TruncationSummary> = BTreeMap::new();
+
+    for row in rows {
+        totals.add(&row.finish_reason, row.count);
+        pairs
+            .entry((row.model.clone(), row.provider.clone()))
+            .or_default()
+            .add(&row.finish_reason, row.count);
+    }
+
+    let mut groups: Vec<TruncationGroup> = pairs
+        .into_iter()
+        .map(|((model, provider), summary)| TruncationGroup {
+            model,
+            provider,
+            summary,
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.summary
+            .truncation_rate
+            .total_cmp(&a.summary.truncation_rate)
+            .then(b.summary.completions.cmp(&a.summary.completions))
+            .then_with(|| a.model.cmp(&b.model))
+            .then_with(|| a.provider.cmp(&b.provider))
+    });
+
+    (totals, groups)
+}
+
+/// Handle GET /v1/stats/truncation -- finish_reason breakdown per model/provider.
+pub async fn truncation_handler(
+    State(state): State<AppState>,
+    Query(params): Query<TruncationQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let pool = state
+        .read_db
+        .as_ref()
+        .ok_or_else(|| Error::Internal("Database not available".to_string()))?;
+
+    let (since_dt, until_dt) = resolve_time_range(
+        params.range.as_deref(),
+        params.since.as_deref(),
+        params.until.as_deref(),
+    )?;
+    let since_str = since_dt.to_rfc3339();
+    let until_str = until_dt.to_rfc3339();
+
+    if let Some(ref model_filter) = params.model {
+        super::validation::validate_model_filter(&state.config, pool, model_filter).await?;
+    }
+    if let Some(ref provider_filter) = params.provider {
+        super::validation::validate_provider_filter(&state.config, pool, provider_filter).await?;
+    }
+
+    let tag_filter = params
+        .tag
+        .as_deref()
+        .map(super::tags::parse_tag_filter)
+        .transpose()?;
+    let tag = tag_filter.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+
+    let rows = storage::stats::query_finish_reasons(
+        pool,
+        &since_str,
+        &until_str,
+        params.model.as_deref(),
+        params.provider.as_deref(),
+        tag,
+    )
+    .await?;
+    let (totals, groups) = summarize(&rows);
+
+    Ok(Json(TruncationResponse {
+        since: since_str,
+        until: until_str,
+        totals,
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(model: &str, provider: &str, reason: &str, count: i64) -> FinishReasonRow {
+        FinishReasonRow {
+            model: model.to_string(),
+            provider: provider.to_string(),
+            finish_reason: reason.to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn summarize_computes_rates_and_orders_worst_first() {
+        let rows = vec![
+            row("gpt-4o", "alpha", "stop", 9),
+            row("gpt-4o", "alpha", "length", 1),
+            row("gpt-4o", "beta", "stop", 5),
+            row("gpt-4o", "beta", "length", 5),
+            row("gpt-4o-mini", "beta", "stop", 3),
+        ];
+        let (totals, groups) = summarize(&rows);
+
+        assert_eq!(totals.completions, 23);
+        assert_eq!(totals.truncated, 6);
+        assert_eq!(totals.finish_reasons["stop"], 17);
+        assert!((totals.truncation_rate - 6.0 / 23.0).abs() < 1e-9);
+
+        let order: Vec<(&str, &str)> = groups
+            .iter()
+            .map(|g| (g.model.as_str(), g.provider.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("gpt-4o", "beta"),
+                ("gpt-4o", "alpha"),
+                ("gpt-4o-mini", "beta")
+            ]
+        );
+        assert!((groups[0].summary.truncation_rate - 0.5).abs() < 1e-9);
+        assert_eq!(groups[2].summary.truncation_rate, 0.0);
+    }
+
+    #[test]
+    fn summarize_empty() {
+        let (totals, groups) = summarize(&[]);
+        assert_eq!(totals.completions, 0);
+        assert_eq!(totals.truncation_rate, 0.0);
+        assert!(groups.is_empty());
+    }
+}
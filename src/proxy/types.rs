@@ -109,9 +109,10 @@ pub struct ChatCompletionResponse {
     pub choices: Vec<Choice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<Usage>,
-    /// arbstr extension: which provider handled this request
+    /// arbstr extension: routing metadata (provider, cost, latency,
+    /// retries), present when `server.metadata_mode = "body"`
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub arbstr_provider: Option<String>,
+    pub arbstr: Option<serde_json::Value>,
 }
 
 /// A completion choice.
@@ -223,6 +224,87 @@ pub fn ensure_stream_options(request: &mut ChatCompletionRequest) {
     }
 }
 
+/// How a policy's token limits changed a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxTokensAdjustment {
+    /// `max_tokens` was omitted and filled in.
+    Injected,
+    /// The client's limit exceeded `max_output_tokens` and was lowered.
+    Clamped,
+}
+
+impl MaxTokensAdjustment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxTokensAdjustment::Injected => "injected",
+            MaxTokensAdjustment::Clamped => "clamped",
+        }
+    }
+}
+
+/// Apply a policy's `default_max_tokens` and `max_output_tokens`.
+///
+/// A client limit, in `max_tokens` or `max_completion_tokens`, above
+/// `max_output_tokens` is lowered to it. Without a client limit,
+/// `max_tokens` is set to `default_max_tokens`, falling back to
+/// `max_output_tokens`.
+pub fn limit_max_tokens(
+    request: &mut ChatCompletionRequest,
+    default_max_tokens: Option<u32>,
+    max_output_tokens: Option<u32>,
+) -> Option<MaxTokensAdjustment> {
+    let mut adjustment = None;
+    if let (Some(requested), Some(max)) = (request.max_tokens, max_output_tokens) {
+        if requested > max {
+            request.max_tokens = Some(max);
+            adjustment = Some(MaxTokensAdjustment::Clamped);
+        }
+    }
+    let completion_tokens = request
+        .extra
+        .get("max_completion_tokens")
+        .and_then(|v| v.as_u64());
+    if let (Some(requested), Some(max)) = (completion_tokens, max_output_tokens) {
+        if requested > u64::from(max) {
+            request
+                .extra
+                .insert("max_completion_tokens".to_string(), max.into());
+            adjustment = Some(MaxTokensAdjustment::Clamped);
+        }
+    }
+    if request.max_tokens.is_none() && completion_tokens.is_none() {
+        if let Some(limit) = default_max_tokens.or(max_output_tokens) {
+            request.max_tokens = Some(limit);
+            adjustment = Some(MaxTokensAdjustment::Injected);
+        }
+    }
+    adjustment
+}
+
+/// Put `text` ahead of the conversation as system prompt.
+///
+/// A leading text system message is extended rather than joined by a second
+/// one, since some providers accept only one system message.
+pub fn prepend_system_prompt(request: &mut ChatCompletionRequest, text: &str) {
+    if let Some(first) = request.messages.first_mut() {
+        if first.role == "system" {
+            if let MessageContent::Text(existing) = &mut first.content {
+                *existing = format!("{}\n\n{}", text, existing);
+                return;
+            }
+        }
+    }
+    request.messages.insert(
+        0,
+        Message {
+            role: "system".to_string(),
+            content: MessageContent::Text(text.to_string()),
+            name: None,
+            extra: Default::default(),
+        },
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +396,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn limit_max_tokens_injects_default_then_max() {
+        let mut req = minimal_request();
+        assert_eq!(
+            limit_max_tokens(&mut req, Some(256), Some(1024)),
+            Some(MaxTokensAdjustment::Injected)
+        );
+        assert_eq!(req.max_tokens, Some(256));
+
+        let mut req = minimal_request();
+        limit_max_tokens(&mut req, None, Some(1024));
+        assert_eq!(req.max_tokens, Some(1024));
+
+        let mut req = minimal_request();
+        assert_eq!(limit_max_tokens(&mut req, None, None), None);
+        assert_eq!(req.max_tokens, None);
+    }
+
+    #[test]
+    fn limit_max_tokens_clamps_client_limits() {
+        let mut req = minimal_request();
+        req.max_tokens = Some(4096);
+        assert_eq!(
+            limit_max_tokens(&mut req, Some(256), Some(1024)),
+            Some(MaxTokensAdjustment::Clamped)
+        );
+        assert_eq!(req.max_tokens, Some(1024));
+
+        let mut req = minimal_request();
+        req.max_tokens = Some(512);
+        assert_eq!(limit_max_tokens(&mut req, Some(256), Some(1024)), None);
+        assert_eq!(req.max_tokens, Some(512));
+
+        let mut req = minimal_request();
+        req.extra
+            .insert("max_completion_tokens".to_string(), 4096.into());
+        assert_eq!(
+            limit_max_tokens(&mut req, Some(256), Some(1024)),
+            Some(MaxTokensAdjustment::Clamped)
+        );
+        assert_eq!(req.extra["max_completion_tokens"], 1024);
+        assert_eq!(req.max_tokens, None, "no second limit is injected");
+    }
+
+    #[test]
+    fn prepend_system_prompt_inserts_or_extends() {
+        let mut req = minimal_request();
+        prepend_system_prompt(&mut req, "Be concise.");
+        assert_eq!(req.messages.len(), 2);
+        assert_eq!(req.messages[0].role, "system");
+        assert_eq!(req.messages[0].content.as_str(), "Be concise.");
+
+        prepend_system_prompt(&mut req, "Follow policy.");
+        assert_eq!(req.messages.len(), 2);
+        assert_eq!(
+            req.messages[0].content.as_str(),
+            "Follow policy.\n\nBe concise."
+        );
+    }
+
     #[test]
     fn stream_options_serialized_after_ensure() {
         let mut req = minimal_request();
@@ -306,19 +306,23 @@ impl VaultClient {
 ///
 /// Uses the cheapest candidate's rates and the request's token estimate.
 /// If `max_tokens` is set, uses that as the output ceiling.
-/// Otherwise uses `default_reserve_tokens` from vault config.
+/// Otherwise uses `default_reserve_tokens` from vault config. The reserve is
+/// never less than the provider's minimum charge.
 pub fn estimate_reserve_msats(
     estimated_input_tokens: u32,
     estimated_output_tokens: u32,
-    input_rate: u64,
-    output_rate: u64,
-    base_fee: u64,
+    input_rate: f64,
+    output_rate: f64,
+    base_fee: f64,
+    min_charge_sats: f64,
 ) -> u64 {
-    // Rates are in sats per 1000 tokens. Convert to msats.
-    let input_cost_msats = (estimated_input_tokens as u64 * input_rate * 1000) / 1000;
-    let output_cost_msats = (estimated_output_tokens as u64 * output_rate * 1000) / 1000;
-    let base_fee_msats = base_fee * 1000;
-    input_cost_msats + output_cost_msats + base_fee_msats
+    // Rates are in sats per 1000 tokens, i.e. msats per token.
+    let input_cost_msats = estimated_input_tokens as f64 * input_rate;
+    let output_cost_msats = estimated_output_tokens as f64 * output_rate;
+    let base_fee_msats = base_fee * 1000.0;
+    (input_cost_msats + output_cost_msats + base_fee_msats)
+        .max(min_charge_sats * 1000.0)
+        .ceil() as u64
 }
 
 /// A pending settlement record for when vault is unreachable.
@@ -477,7 +481,7 @@ pub async fn reconciliation_loop(
 }
 
 /// Wait for the cancellation signal.
-async fn cancel_wait(cancel: &tokio::sync::watch::Receiver<bool>) {
+pub(crate) async fn cancel_wait(cancel: &tokio::sync::watch::Receiver<bool>) {
     let mut cancel = cancel.clone();
     // Wait until the value becomes true
     while !*cancel.borrow_and_update() {
@@ -597,14 +601,14 @@ mod tests {
         // 200 output tokens * 30 sats/1k = 6 sats = 6000 msats
         // base_fee 1 sat = 1000 msats
         // total = 8000 msats
-        let result = estimate_reserve_msats(100, 200, 10, 30, 1);
+        let result = estimate_reserve_msats(100, 200, 10.0, 30.0, 1.0, 0.0);
         assert_eq!(result, 8000);
     }
 
     #[test]
     fn test_estimate_reserve_zero_tokens() {
         // base_fee only
-        let result = estimate_reserve_msats(0, 0, 10, 30, 5);
+        let result = estimate_reserve_msats(0, 0, 10.0, 30.0, 5.0, 0.0);
         assert_eq!(result, 5000);
     }
 
@@ -613,7 +617,7 @@ mod tests {
         // 1000 input * 10/1k = 10 sats = 10000 msats
         // 4096 output * 30/1k = 122.88 sats → 122880 msats (integer truncation)
         // base_fee 0
-        let result = estimate_reserve_msats(1000, 4096, 10, 30, 0);
+        let result = estimate_reserve_msats(1000, 4096, 10.0, 30.0, 0.0, 0.0);
         assert_eq!(result, 10000 + 122880);
     }
 
@@ -624,10 +628,16 @@ mod tests {
         // 4096 output * 30/1k = 122.88 sats = 122880 msats
         // base_fee 2 sats = 2000 msats
         // total = 125880 msats
-        let result = estimate_reserve_msats(100, 4096, 10, 30, 2);
+        let result = estimate_reserve_msats(100, 4096, 10.0, 30.0, 2.0, 0.0);
         assert_eq!(result, 125880);
     }
 
+    #[test]
+    fn test_estimate_reserve_min_charge() {
+        // 10 output * 30/1k = 300 msats, raised to the 2 sat minimum
+        assert_eq!(estimate_reserve_msats(0, 10, 10.0, 30.0, 0.0, 2.0), 2000);
+    }
+
     #[test]
     fn test_vault_error_status_codes() {
         assert_eq!(VaultError::InsufficientBalance.status_code(), 402);
@@ -0,0 +1,377 @@
+//! Prepaid request vouchers and the `/admin/vouchers` endpoints.
+//!
+//! A voucher grants limited use of the proxy without an account: up to a
+//! number of sats, a number of requests, or both, optionally only for some
+//! models. Clients present its code in `x-arbstr-voucher`, which stands in
+//! for the `auth_token` bearer on chat completions; a request carrying a
+//! voucher is authorized by the voucher alone. Each request counts against
+//! the voucher once routed, and its cost is debited once known (streams
+//! once their usage arrives); a voucher that is used up, or whose remaining
+//! sats can't cover the request's estimated cost, is rejected with 402.
+//! Requests are tagged `voucher=<id>`, and with a database the running
+//! totals are restored from them on startup.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::server::AppState;
+use crate::error::Error;
+use crate::storage::vouchers::{self, VoucherRow};
+
+/// Request header carrying a voucher code.
+pub const VOUCHER_HEADER: &str = "x-arbstr-voucher";
+
+/// Issued vouchers and their running totals.
+#[derive(Debug, Default)]
+pub struct Vouchers {
+    inner: Mutex<VoucherBook>,
+}
+
+#[derive(Debug, Default)]
+struct VoucherBook {
+    /// By voucher ID.
+    vouchers: BTreeMap<String, VoucherRow>,
+    /// Voucher ID by code.
+    codes: HashMap<String, String>,
+    /// Voucher ID by correlation ID, for streams awaiting their usage.
+    streams: HashMap<String, String>,
+}
+
+impl VoucherBook {
+    fn add(&mut self, voucher: VoucherRow) {
+        self.codes.insert(voucher.code.clone(), voucher.id.clone());
+        self.vouchers.insert(voucher.id.clone(), voucher);
+    }
+}
+
+/// One voucher, for `/admin/vouchers`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct VoucherSnapshot {
+    pub id: String,
+    pub code: String,
+    pub created_at: String,
+    pub limit_sats: Option<f64>,
+    pub limit_requests: Option<u64>,
+    /// Models the voucher may be used for; empty for any.
+    pub models: Vec<String>,
+    pub note: Option<String>,
+    pub spent_sats: f64,
+    pub requests: u64,
+    pub remaining_sats: Option<f64>,
+    pub remaining_requests: Option<u64>,
+    pub exhausted: bool,
+}
+
+fn remaining_sats(voucher: &VoucherRow) -> Option<f64> {
+    voucher
+        .limit_sats
+        .map(|limit| (limit - voucher.spent_sats).max(0.0))
+}
+
+fn remaining_requests(voucher: &VoucherRow) -> Option<u64> {
+    voucher
+        .limit_requests
+        .map(|limit| limit.saturating_sub(voucher.requests))
+}
+
+fn exhausted(voucher: &VoucherRow) -> bool {
+    remaining_sats(voucher).is_some_and(|sats| sats <= 0.0)
+        || remaining_requests(voucher) == Some(0)
+}
+
+fn snapshot(voucher: &VoucherRow) -> VoucherSnapshot {
+    VoucherSnapshot {
+        id: voucher.id.clone(),
+        code: voucher.code.clone(),
+        created_at: voucher.created_at.clone(),
+        limit_sats: voucher.limit_sats,
+        limit_requests: voucher.limit_requests,
+        models: voucher.models.clone(),
+        note: voucher.note.clone(),
+        spent_sats: voucher.spent_sats,
+        requests: voucher.requests,
+        remaining_sats: remaining_sats(voucher),
+        remaining_requests: remaining_requests(voucher),
+        exhausted: exhausted(voucher),
+    }
+}
+
+impl Vouchers {
+    fn lock(&self) -> std::sync::MutexGuard<'_, VoucherBook> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Load the persisted vouchers with the spend logged against them.
+    pub async fn restore(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let loaded = vouchers::load(pool).await?;
+        let count = loaded.len();
+        let mut book = self.lock();
+        for voucher in loaded {
+            book.add(voucher);
+        }
+        if count > 0 {
+            tracing::info!(count, "Vouchers restored");
+        }
+        Ok(())
+    }
+
+    /// Issue a voucher, storing it in the database when one is available.
+    pub async fn issue(
+        &self,
+        pool: Option<&SqlitePool>,
+        request: CreateVoucherRequest,
+    ) -> Result<VoucherSnapshot, Error> {
+        let voucher = VoucherRow {
+            id: format!("v-{}", &uuid::Uuid::new_v4().simple().to_string()[..12]),
+            code: format!("arbv_{}", uuid::Uuid::new_v4().simple()),
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            limit_sats: request.sats,
+            limit_requests: request.requests,
+            models: request.models,
+            note: request.note,
+            spent_sats: 0.0,
+            requests: 0,
+        };
+        if let Some(pool) = pool {
+            vouchers::insert(pool, &voucher).await?;
+        }
+        let snapshot = snapshot(&voucher);
+        self.lock().add(voucher);
+        Ok(snapshot)
+    }
+
+    /// Whether `code` belongs to an issued voucher.
+    pub fn is_known(&self, code: &str) -> bool {
+        self.lock().codes.contains_key(code)
+    }
+
+    /// Accept a request for `model` paid with the voucher `code`, counting
+    /// it against the voucher if its remaining sats cover
+    /// `estimated_cost_sats`. Returns the voucher ID.
+    pub fn redeem(
+        &self,
+        code: &str,
+        model: &str,
+        estimated_cost_sats: f64,
+    ) -> Result<String, Error> {
+        let mut book = self.lock();
+        let voucher = book
+            .codes
+            .get(code)
+            .cloned()
+            .and_then(|id| book.vouchers.get_mut(&id))
+            .ok_or_else(|| Error::PaymentRequired("Unknown voucher".to_string()))?;
+        if !voucher.models.is_empty()
+            && !voucher.models.iter().any(|m| m.eq_ignore_ascii_case(model))
+        {
+            return Err(Error::Forbidden(format!(
+                "Voucher is not valid for model '{}'",
+                model
+            )));
+        }
+        if exhausted(voucher) {
+            return Err(Error::PaymentRequired("Voucher is exhausted".to_string()));
+        }
+        if remaining_sats(voucher).is_some_and(|sats| sats < estimated_cost_sats) {
+            return Err(Error::PaymentRequired(format!(
+                "Voucher balance too low for an estimated {:.3} sats",
+                estimated_cost_sats
+            )));
+        }
+        voucher.requests += 1;
+        Ok(voucher.id.clone())
+    }
+
+    /// Charge `cost_sats` to the voucher `id`.
+    pub fn debit(&self, id: &str, cost_sats: f64) {
+        if let Some(voucher) = self.lock().vouchers.get_mut(id) {
+            voucher.spent_sats += cost_sats;
+        }
+    }
+
+    /// Charge the stream `correlation_id` to the voucher `id` once its
+    /// usage arrives (see [`Vouchers::settle_stream`]).
+    pub fn await_stream(&self, correlation_id: &str, id: &str) {
+        self.lock()
+            .streams
+            .insert(correlation_id.to_string(), id.to_string());
+    }
+
+    /// Charge a finished stream to its voucher, if it has one.
+    pub fn settle_stream(&self, correlation_id: &str, cost_sats: Option<f64>) {
+        let mut book = self.lock();
+        let Some(id) = book.streams.remove(correlation_id) else {
+            return;
+        };
+        if let (Some(voucher), Some(cost)) = (book.vouchers.get_mut(&id), cost_sats) {
+            voucher.spent_sats += cost;
+        }
+    }
+
+    /// All vouchers, oldest first.
+    pub fn snapshots(&self) -> Vec<VoucherSnapshot> {
+        let book = self.lock();
+        let mut snapshots: Vec<VoucherSnapshot> = book.vouchers.values().map(snapshot).collect();
+        snapshots.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        snapshots
+    }
+}
+
+/// The `x-arbstr-voucher` code, if sent.
+pub fn from_headers(headers: &HeaderMap) -> Result<Option<&str>, Error> {
+    headers
+        .get(VOUCHER_HEADER)
+        .map(|v| {
+            v.to_str()
+                .map(str::trim)
+                .map_err(|_| Error::BadRequest(format!("{} must be valid ASCII", VOUCHER_HEADER)))
+        })
+        .transpose()
+}
+
+/// Body for POST /admin/vouchers.
+#[derive(Debug, Deserialize)]
+pub struct CreateVoucherRequest {
+    /// Most sats the voucher may spend.
+    #[serde(default)]
+    pub sats: Option<f64>,
+    /// Most requests the voucher may make.
+    #[serde(default)]
+    pub requests: Option<u64>,
+    /// Models the voucher may be used for (any when empty).
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Response for GET /admin/vouchers.
+#[derive(Debug, Serialize)]
+pub struct VouchersResponse {
+    pub vouchers: Vec<VoucherSnapshot>,
+}
+
+/// Handle GET /admin/vouchers -- issued vouchers and what is left on them.
+pub async fn vouchers_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(VouchersResponse {
+        vouchers: state.vouchers.snapshots(),
+    })
+}
+
+/// Handle POST /admin/vouchers -- issue a voucher.
+pub async fn create_voucher_handler(
+    State(state): State<AppState>,
+    Json(body): Json<CreateVoucherRequest>,
+) -> Result<impl IntoResponse, Error> {
+    if body.sats.is_none() && body.requests.is_none() {
+        return Err(Error::BadRequest(
+            "A voucher needs a sats or requests limit".to_string(),
+        ));
+    }
+    if body
+        .sats
+        .is_some_and(|sats| !sats.is_finite() || sats <= 0.0)
+    {
+        return Err(Error::BadRequest(
+            "sats must be a positive number".to_string(),
+        ));
+    }
+    if body.requests == Some(0) {
+        return Err(Error::BadRequest("requests must be at least 1".to_string()));
+    }
+    if body.models.iter().any(|m| m.trim().is_empty()) {
+        return Err(Error::BadRequest(
+            "models entries must not be empty".to_string(),
+        ));
+    }
+    let snapshot = state.vouchers.issue(state.db.as_ref(), body).await?;
+    tracing::info!(
+        voucher = %snapshot.id,
+        limit_sats = ?snapshot.limit_sats,
+        limit_requests = ?snapshot.limit_requests,
+        models = ?snapshot.models,
+        "Voucher issued"
+    );
+    Ok((axum::http::StatusCode::CREATED, Json(snapshot)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn issue(
+        vouchers: &Vouchers,
+        sats: Option<f64>,
+        requests: Option<u64>,
+        models: &[&str],
+    ) -> VoucherSnapshot {
+        vouchers
+            .issue(
+                None,
+                CreateVoucherRequest {
+                    sats,
+                    requests,
+                    models: models.iter().map(|m| m.to_string()).collect(),
+                    note: None,
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_limit_counts_each_redemption() {
+        let vouchers = Vouchers::default();
+        let voucher = issue(&vouchers, None, Some(2), &[]).await;
+        assert!(voucher.code.starts_with("arbv_"));
+        assert!(vouchers.is_known(&voucher.code));
+        assert!(!vouchers.is_known("arbv_nope"));
+
+        assert_eq!(
+            vouchers.redeem(&voucher.code, "gpt-4o", 0.0).unwrap(),
+            voucher.id
+        );
+        vouchers.redeem(&voucher.code, "gpt-4o", 0.0).unwrap();
+        assert!(matches!(
+            vouchers.redeem(&voucher.code, "gpt-4o", 0.0),
+            Err(Error::PaymentRequired(_))
+        ));
+        assert!(matches!(
+            vouchers.redeem("arbv_nope", "gpt-4o", 0.0),
+            Err(Error::PaymentRequired(_))
+        ));
+        assert!(vouchers.snapshots()[0].exhausted);
+    }
+
+    #[tokio::test]
+    async fn sats_limit_and_model_restriction() {
+        let vouchers = Vouchers::default();
+        let voucher = issue(&vouchers, Some(100.0), None, &["gpt-4o"]).await;
+        assert!(matches!(
+            vouchers.redeem(&voucher.code, "claude", 0.0),
+            Err(Error::Forbidden(_))
+        ));
+
+        let id = vouchers.redeem(&voucher.code, "GPT-4o", 50.0).unwrap();
+        vouchers.debit(&id, 60.0);
+        assert!(matches!(
+            vouchers.redeem(&voucher.code, "gpt-4o", 50.0),
+            Err(Error::PaymentRequired(_))
+        ));
+        vouchers.redeem(&voucher.code, "gpt-4o", 40.0).unwrap();
+        vouchers.await_stream("stream-1", &id);
+        assert_eq!(vouchers.snapshots()[0].remaining_sats, Some(40.0));
+        vouchers.settle_stream("stream-1", Some(45.0));
+        vouchers.settle_stream("stream-1", Some(45.0));
+
+        let snapshot = &vouchers.snapshots()[0];
+        assert_eq!(snapshot.spent_sats, 105.0);
+        assert_eq!(snapshot.remaining_sats, Some(0.0));
+        assert!(snapshot.exhausted);
+        assert!(vouchers.redeem(&voucher.code, "gpt-4o", 0.0).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Connection warmup at startup (`[warmup]`) and the `/ready` endpoint.
+//!
+//! Every provider is sent a `GET /models` through the client that will carry
+//! its traffic, so the pooled connection (DNS, TCP, TLS) already exists when
+//! the first user request arrives. Any HTTP response counts as warmed: a 401
+//! or 404 still leaves an open connection behind. With `completion = true`
+//! a 1-token completion follows, to warm the provider's serving path too.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use super::server::AppState;
+use crate::config::ProviderConfig;
+
+/// Outcome of warming one provider.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WarmupResult {
+    /// Whether the provider answered at all.
+    pub connected: bool,
+    /// Status of the `GET /models` response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Time to response headers, including connection setup.
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+    /// Status of the 1-token completion, when `completion = true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Warmup progress, shared with the `/ready` handler.
+///
+/// Starts out ready, so deployments without `[warmup]` are ready at once.
+#[derive(Debug, Default)]
+pub struct WarmupTracker {
+    pending: AtomicBool,
+    results: Mutex<Option<BTreeMap<String, WarmupResult>>>,
+}
+
+impl WarmupTracker {
+    /// Mark warmup as in progress; `/ready` answers 503 until [`Self::finish`].
+    pub fn begin(&self) {
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Record results and mark the proxy ready.
+    pub fn finish(&self, results: BTreeMap<String, WarmupResult>) {
+        *self.results.lock().unwrap_or_else(|e| e.into_inner()) = Some(results);
+        self.pending.store(false, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        !self.pending.load(Ordering::Acquire)
+    }
+
+    /// Per-provider results, once warmup has run.
+    pub fn results(&self) -> Option<BTreeMap<String, WarmupResult>> {
+        self.results
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Warm every provider concurrently and record the results on `state.warmup`.
+///
+/// Callers mark the tracker with [`WarmupTracker::begin`] first, so `/ready`
+/// reports warming up from the moment the server starts listening.
+pub async fn run(state: &AppState) -> BTreeMap<String, WarmupResult> {
+    let config = &state.config.warmup;
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let start = Instant::now();
+
+    let results: BTreeMap<String, WarmupResult> =
+        futures::future::join_all(state.config.providers.iter().map(|provider| async move {
+            let result = warm_provider(state, provider, timeout, config.completion).await;
+            (provider.name.clone(), result)
+        }))
+        .await
+        .into_iter()
+        .collect();
+
+    let warmed = results.values().filter(|r| r.connected).count();
+    tracing::info!(
+        warmed = warmed,
+        failed = results.len() - warmed,
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "Provider warmup complete"
+    );
+    state.warmup.finish(results.clone());
+    results
+}
+
+async fn warm_provider(
+    state: &AppState,
+    provider: &ProviderConfig,
+    timeout: Duration,
+    completion: bool,
+) -> WarmupResult {
+    let client = state
+        .provider_clients
+        .get(&provider.name)
+        .unwrap_or(&state.http_client);
+    let base = provider.url.trim_end_matches('/');
+
+    let start = Instant::now();
+    let request = super::handlers::apply_provider_headers(
+        client.get(format!("{}/models", base)).timeout(timeout),
+        provider.api_key.as_ref(),
+        provider.auth_scheme,
+        &provider.extra_headers,
+    );
+    let mut result = match request.send().await {
+        Ok(response) => WarmupResult {
+            connected: true,
+            status: Some(response.status().as_u16()),
+            latency_ms: start.elapsed().as_millis() as u64,
+            http_version: Some(format!("{:?}", response.version())),
+            completion_status: None,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(provider = %provider.name, error = %e, "Provider warmup failed");
+            return WarmupResult {
+                connected: false,
+                status: None,
+                latency_ms: start.elapsed().as_millis() as u64,
+                http_version: None,
+                completion_status: None,
+                error: Some(e.to_string()),
+            };
+        }
+    };
+
+    if let (true, Some(model)) = (completion, provider.models.first()) {
+        let request = super::handlers::apply_provider_headers(
+            client
+                .post(format!("{}/chat/completions", base))
+                .timeout(timeout)
+                .json(&serde_json::json!({
+                    "model": model,
+                    "messages": [{"role": "user", "content": "ping"}],
+                    "max_tokens": 1
+                })),
+            provider.api_key.as_ref(),
+            provider.auth_scheme,
+            &provider.extra_headers,
+        );
+        match request.send().await {
+            Ok(response) => result.completion_status = Some(response.status().as_u16()),
+            Err(e) => result.error = Some(format!("completion: {}", e)),
+        }
+    }
+
+    tracing::info!(
+        provider = %provider.name,
+        status = ?result.status,
+        latency_ms = result.latency_ms,
+        http_version = ?result.http_version,
+        completion_status = ?result.completion_status,
+        "Provider warmed up"
+    );
+    result
+}
+
+/// Response body for `GET /ready`.
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    /// `ready` or `warming_up`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<BTreeMap<String, WarmupResult>>,
+}
+
+/// Handle GET /ready: 503 while startup warmup is running, 200 after.
+pub async fn ready_handler(State(state): State<AppState>) -> impl IntoResponse {
+    if state.warmup.is_ready() {
+        (
+            StatusCode::OK,
+            Json(ReadyResponse {
+                status: "ready",
+                warmup: state.warmup.results(),
+            }),
+        )
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ReadyResponse {
+                status: "warming_up",
+                warmup: None,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_is_ready_until_warmup_begins() {
+        let tracker = WarmupTracker::default();
+        assert!(tracker.is_ready());
+        assert!(tracker.results().is_none());
+
+        tracker.begin();
+        assert!(!tracker.is_ready());
+
+        let result = WarmupResult {
+            connected: true,
+            status: Some(200),
+            latency_ms: 12,
+            http_version: Some("HTTP/1.1".to_string()),
+            completion_status: None,
+            error: None,
+        };
+        tracker.finish(BTreeMap::from([("alpha".to_string(), result.clone())]));
+        assert!(tracker.is_ready());
+        assert_eq!(tracker.results().unwrap()["alpha"], result);
+    }
+}
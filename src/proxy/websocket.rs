@@ -0,0 +1,108 @@
+//! Chat completions over WebSocket (`GET /v1/chat/completions/ws`).
+//!
+//! For clients behind proxies that buffer SSE. Each text message on the
+//! socket is a chat completion request; it is forced to `stream: true` and
+//! sent through the same pipeline as `POST /v1/chat/completions`, so routing,
+//! retries, observation, and logging are unchanged. Every SSE `data:`
+//! payload of the response becomes one text frame (chunks, the `arbstr`
+//! metadata object in `body` mode, and a single final `[DONE]`). Error responses
+//! are sent as a single JSON frame. Requests on one socket run one at a
+//! time; each gets its own correlation ID, and its receipt hashes the
+//! message text.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Extension, State,
+    },
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::handlers::handle_parsed_request;
+use super::nostr::encode_hex;
+use super::server::{AppState, RequestId};
+use super::trace::TraceContext;
+use super::types::ChatCompletionRequest;
+
+/// Handle GET /v1/chat/completions/ws -- upgrade and serve requests.
+pub async fn chat_completions_ws(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| serve_socket(state, request_id, headers, socket))
+}
+
+async fn serve_socket(
+    state: AppState,
+    request_id: RequestId,
+    headers: HeaderMap,
+    mut socket: WebSocket,
+) {
+    // The upgrade request's correlation ID goes to the first request
+    let mut next_id = Some(request_id);
+    while let Some(Ok(message)) = socket.recv().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let request_id = next_id.take().unwrap_or_else(|| RequestId(Uuid::new_v4()));
+        let response = match serde_json::from_str::<ChatCompletionRequest>(&text) {
+            Ok(mut request) => {
+                request.stream = Some(true);
+                let trace = TraceContext::from_headers(&headers, &request_id.0.to_string());
+                let request_sha256 = encode_hex(&Sha256::digest(text.as_bytes()));
+                handle_parsed_request(
+                    state.clone(),
+                    request_id,
+                    trace,
+                    headers.clone(),
+                    request,
+                    Some(request_sha256),
+                )
+                .await
+                .into_response()
+            }
+            Err(e) => crate::error::Error::BadRequest(format!("Invalid chat request: {}", e))
+                .into_response(),
+        };
+        if forward_response(&mut socket, response).await.is_err() {
+            tracing::debug!("WebSocket client went away mid-response");
+            return;
+        }
+    }
+}
+
+/// Send `response` to the socket: one frame per SSE event for streams,
+/// the whole body as one frame otherwise.
+async fn forward_response(socket: &mut WebSocket, response: Response) -> Result<(), axum::Error> {
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let body = response.into_body();
+    if !is_stream {
+        let bytes = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .unwrap_or_default();
+        return socket
+            .send(Message::Text(String::from_utf8_lossy(&bytes).into_owned()))
+            .await;
+    }
+
+    // The provider's [DONE] is followed by arbstr's trailing event, so only
+    // one [DONE] is sent, once the body ends
+    let mut events = super::stream::SseEvents::new(body);
+    while let Some(data) = events.next().await {
+        if data != "[DONE]" {
+            socket.send(Message::Text(data)).await?;
+        }
+    }
+    socket.send(Message::Text("[DONE]".to_string())).await
+}
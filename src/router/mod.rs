@@ -6,7 +6,13 @@
 //! - Policy constraints
 
 mod complexity;
+mod schedule;
 mod selector;
+mod system_prompt;
+mod validator;
 
 pub use complexity::{score_complexity, score_to_max_tier};
-pub use selector::{actual_cost_sats, Router, SelectedProvider};
+pub use schedule::Schedule;
+pub use selector::{actual_cost_sats, PolicyEvaluation, Router, SelectedProvider};
+pub use system_prompt::{PromptVars, SystemPrompt};
+pub use validator::ResponseValidator;
@@ -0,0 +1,195 @@
+//! Time-of-day and day-of-week constraints for policies.
+//!
+//! A policy with `active_hours` and/or `days` only grants its normal
+//! routing inside that window. Outside it, requests under the policy are
+//! capped at `off_hours_tier` (default: local), so expensive models are
+//! reachable during work hours only.
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Utc, Weekday};
+
+use crate::config::PolicyRule;
+
+/// A parsed policy schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// Start (inclusive) and end (exclusive); wraps past midnight when
+    /// start > end.
+    hours: Option<(NaiveTime, NaiveTime)>,
+    /// Days the window applies on, by local date. Empty means every day.
+    days: Vec<Weekday>,
+    offset: FixedOffset,
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| format!("invalid time '{}' (expected HH:MM)", s.trim()))
+}
+
+fn parse_hours(s: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| format!("active_hours '{}' must look like \"09:00-18:00\"", s))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end {
+        return Err(format!("active_hours '{}' is an empty window", s));
+    }
+    Ok((start, end))
+}
+
+fn parse_offset(s: &str) -> Result<FixedOffset, String> {
+    match s.trim() {
+        "Z" | "UTC" | "utc" => Ok(FixedOffset::east_opt(0).expect("zero offset")),
+        other => other
+            .parse()
+            .map_err(|_| format!("invalid utc_offset '{}' (expected e.g. \"+02:00\")", other)),
+    }
+}
+
+impl Schedule {
+    /// Parse a policy's schedule. `Ok(None)` when it has no time constraints.
+    pub fn from_policy(rule: &PolicyRule) -> Result<Option<Self>, String> {
+        if rule.active_hours.is_none() && rule.days.is_empty() {
+            if rule.utc_offset.is_some() {
+                return Err("utc_offset requires active_hours or days".to_string());
+            }
+            return Ok(None);
+        }
+        let hours = rule.active_hours.as_deref().map(parse_hours).transpose()?;
+        let days = rule
+            .days
+            .iter()
+            .map(|d| {
+                d.parse::<Weekday>()
+                    .map_err(|_| format!("invalid day '{}' (expected mon..sun)", d))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let offset = match rule.utc_offset.as_deref() {
+            Some(s) => parse_offset(s)?,
+            None => FixedOffset::east_opt(0).expect("zero offset"),
+        };
+        Ok(Some(Self {
+            hours,
+            days,
+            offset,
+        }))
+    }
+
+    /// Whether `now` falls inside the window.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.offset);
+        if !self.days.is_empty() && !self.days.contains(&local.weekday()) {
+            return false;
+        }
+        match self.hours {
+            None => true,
+            Some((start, end)) => {
+                let t = local.time();
+                if start < end {
+                    start <= t && t < end
+                } else {
+                    t >= start || t < end
+                }
+            }
+        }
+    }
+
+    /// Human-readable window, e.g. `09:00-18:00 mon,tue (UTC+02:00)`.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some((start, end)) = self.hours {
+            parts.push(format!("{}-{}", start.format("%H:%M"), end.format("%H:%M")));
+        }
+        if !self.days.is_empty() {
+            let days: Vec<String> = self
+                .days
+                .iter()
+                .map(|d| d.to_string().to_lowercase())
+                .collect();
+            parts.push(days.join(","));
+        }
+        parts.push(format!("(UTC{})", self.offset));
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(hours: Option<&str>, days: &[&str], offset: Option<&str>) -> PolicyRule {
+        PolicyRule {
+            name: "work".to_string(),
+            allowed_models: vec![],
+            strategy: "lowest_cost".to_string(),
+            max_sats_per_1k_output: None,
+            keywords: vec![],
+            backoff: None,
+            active_hours: hours.map(str::to_string),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            utc_offset: offset.map(str::to_string),
+            off_hours_tier: Default::default(),
+            allowed_regions: vec![],
+            validate: None,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: None,
+            system_prompt_bypass_token: None,
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
+        }
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn work_hours_on_weekdays() {
+        let schedule = Schedule::from_policy(&rule(
+            Some("09:00-18:00"),
+            &["mon", "tue", "wed", "thu", "fri"],
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        // 2026-01-05 is a Monday
+        assert!(schedule.is_active(at("2026-01-05T09:00:00Z")));
+        assert!(schedule.is_active(at("2026-01-05T17:59:59Z")));
+        assert!(!schedule.is_active(at("2026-01-05T18:00:00Z")));
+        assert!(!schedule.is_active(at("2026-01-05T08:59:00Z")));
+        assert!(!schedule.is_active(at("2026-01-04T12:00:00Z")));
+        assert_eq!(
+            schedule.describe(),
+            "09:00-18:00 mon,tue,wed,thu,fri (UTC+00:00)"
+        );
+    }
+
+    #[test]
+    fn overnight_window_and_offset() {
+        let schedule = Schedule::from_policy(&rule(Some("22:00-06:00"), &[], Some("+02:00")))
+            .unwrap()
+            .unwrap();
+        // 21:00 UTC is 23:00 local
+        assert!(schedule.is_active(at("2026-01-05T21:00:00Z")));
+        assert!(schedule.is_active(at("2026-01-06T03:30:00Z")));
+        assert!(!schedule.is_active(at("2026-01-06T04:00:00Z")));
+        assert!(!schedule.is_active(at("2026-01-05T12:00:00Z")));
+    }
+
+    #[test]
+    fn rejects_malformed_schedules() {
+        assert_eq!(Schedule::from_policy(&rule(None, &[], None)), Ok(None));
+        assert!(Schedule::from_policy(&rule(Some("9-18"), &[], None)).is_err());
+        assert!(Schedule::from_policy(&rule(Some("09:00-09:00"), &[], None)).is_err());
+        assert!(Schedule::from_policy(&rule(None, &["funday"], None)).is_err());
+        assert!(Schedule::from_policy(&rule(Some("09:00-18:00"), &[], Some("CET"))).is_err());
+        assert!(Schedule::from_policy(&rule(None, &[], Some("+01:00"))).is_err());
+    }
+}
@@ -1,8 +1,17 @@
 //! Provider selection logic.
 
-use std::collections::HashSet;
-
-use crate::config::{ApiKey, PolicyRule, ProviderConfig, Tier};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::schedule::Schedule;
+use super::system_prompt::SystemPrompt;
+use super::validator::ResponseValidator;
+use crate::config::{
+    ApiKey, AuthScheme, DatasetCaptureConfig, PolicyRule, PromptCompressionConfig, ProviderConfig,
+    Tier, TrimConfig,
+};
 use crate::error::{Error, Result};
 
 /// A provider selected for routing.
@@ -11,10 +20,14 @@ pub struct SelectedProvider {
     pub name: String,
     pub url: String,
     pub api_key: Option<ApiKey>,
-    pub input_rate: u64,
-    pub output_rate: u64,
-    pub base_fee: u64,
+    pub input_rate: f64,
+    pub output_rate: f64,
+    pub base_fee: f64,
+    pub min_charge_sats: f64,
     pub tier: Tier,
+    pub auth_scheme: AuthScheme,
+    pub extra_headers: BTreeMap<String, String>,
+    pub structured_output: bool,
 }
 
 impl From<&ProviderConfig> for SelectedProvider {
@@ -26,16 +39,74 @@ impl From<&ProviderConfig> for SelectedProvider {
             input_rate: config.input_rate,
             output_rate: config.output_rate,
             base_fee: config.base_fee,
+            min_charge_sats: config.min_charge_sats,
             tier: config.tier,
+            auth_scheme: config.auth_scheme,
+            extra_headers: config.extra_headers.clone(),
+            structured_output: config.structured_output,
         }
     }
 }
 
+/// How the policy for a request applies at a given time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyEvaluation {
+    pub name: String,
+    /// `header` or `keywords`.
+    pub matched_by: &'static str,
+    /// False outside the policy's schedule.
+    pub active: bool,
+    /// The policy's window, when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    /// Tier cap in effect while inactive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tier: Option<Tier>,
+    pub reason: String,
+}
+
+/// Whether `policy` sets `min_prompt_tokens` or `max_prompt_tokens`.
+fn has_prompt_bounds(policy: &PolicyRule) -> bool {
+    policy.min_prompt_tokens.is_some() || policy.max_prompt_tokens.is_some()
+}
+
+/// Whether `policy` applies to a request without an `X-Arbstr-Policy`
+/// header. Keyword and prompt-length conditions must all hold; rules with
+/// neither never match automatically, and rules with prompt bounds only
+/// when `prompt_tokens` is known.
+fn matches_automatically(
+    policy: &PolicyRule,
+    prompt_lower: Option<&str>,
+    prompt_tokens: Option<u32>,
+) -> bool {
+    let keywords = !policy.keywords.is_empty();
+    let bounds = has_prompt_bounds(policy);
+    let keywords_hold = !keywords
+        || prompt_lower.is_some_and(|prompt| {
+            policy
+                .keywords
+                .iter()
+                .any(|kw| prompt.contains(&kw.to_lowercase()))
+        });
+    let bounds_hold = !bounds
+        || prompt_tokens.is_some_and(|tokens| {
+            policy.min_prompt_tokens.is_none_or(|min| tokens >= min)
+                && policy.max_prompt_tokens.is_none_or(|max| tokens <= max)
+        });
+    (keywords || bounds) && keywords_hold && bounds_hold
+}
+
 /// Router for selecting providers.
 #[derive(Debug, Clone)]
 pub struct Router {
     providers: Vec<ProviderConfig>,
     policy_rules: Vec<PolicyRule>,
+    /// Parsed schedules of time-constrained policies, by policy name.
+    schedules: HashMap<String, Schedule>,
+    /// Response validators of policies with `validate`, by policy name.
+    validators: HashMap<String, ResponseValidator>,
+    /// System prompts of policies with `system_prompt_prepend`, by policy name.
+    system_prompts: HashMap<String, SystemPrompt>,
     #[allow(dead_code)]
     // Preserved for future strategy-based dispatch (lowest_latency, round_robin)
     default_strategy: String,
@@ -48,9 +119,42 @@ impl Router {
         policy_rules: Vec<PolicyRule>,
         default_strategy: String,
     ) -> Self {
+        let schedules = policy_rules
+            .iter()
+            .filter_map(|r| match Schedule::from_policy(r) {
+                Ok(schedule) => schedule.map(|s| (r.name.clone(), s)),
+                Err(e) => {
+                    tracing::warn!(policy = %r.name, error = %e, "Ignoring invalid policy schedule");
+                    None
+                }
+            })
+            .collect();
+        let validators = policy_rules
+            .iter()
+            .filter_map(|r| match ResponseValidator::from_policy(r) {
+                Ok(validator) => validator.map(|v| (r.name.clone(), v)),
+                Err(e) => {
+                    tracing::warn!(policy = %r.name, error = %e, "Ignoring invalid response validator");
+                    None
+                }
+            })
+            .collect();
+        let system_prompts = policy_rules
+            .iter()
+            .filter_map(|r| match SystemPrompt::from_policy(r) {
+                Ok(prompt) => prompt.map(|p| (r.name.clone(), p)),
+                Err(e) => {
+                    tracing::warn!(policy = %r.name, error = %e, "Ignoring invalid system prompt");
+                    None
+                }
+            })
+            .collect();
         Self {
             providers,
             policy_rules,
+            schedules,
+            validators,
+            system_prompts,
             default_strategy,
         }
     }
@@ -93,6 +197,19 @@ impl Router {
         policy_name: Option<&str>,
         prompt: Option<&str>,
         max_tier: Option<Tier>,
+    ) -> Result<Vec<SelectedProvider>> {
+        self.select_candidates_at(model, policy_name, prompt, max_tier, Utc::now())
+    }
+
+    /// [`select_candidates`](Self::select_candidates) with policy schedules
+    /// evaluated at `now`.
+    pub fn select_candidates_at(
+        &self,
+        model: &str,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+        max_tier: Option<Tier>,
+        now: DateTime<Utc>,
     ) -> Result<Vec<SelectedProvider>> {
         // Find matching policy
         let policy = self.find_policy(policy_name, prompt);
@@ -124,10 +241,27 @@ impl Router {
         // Apply policy constraints if present
         if let Some(policy) = &policy {
             candidates = self.apply_policy_constraints(candidates, policy, model)?;
+
+            // Outside its schedule, the policy only reaches its off-hours tier
+            if let Some(schedule) = self.schedules.get(&policy.name) {
+                if !schedule.is_active(now) {
+                    candidates.retain(|p| p.tier <= policy.off_hours_tier);
+                    if candidates.is_empty() {
+                        return Err(Error::BadRequest(format!(
+                            "Policy '{}' is outside its active hours ({}); no providers for model '{}' at tier '{}' or below",
+                            policy.name,
+                            schedule.describe(),
+                            model,
+                            policy.off_hours_tier
+                        )));
+                    }
+                }
+            }
         }
 
         // Sort by routing cost (output_rate + base_fee), cheapest first
-        candidates.sort_by_key(|p| p.output_rate + p.base_fee);
+        candidates
+            .sort_by(|a, b| (a.output_rate + a.base_fee).total_cmp(&(b.output_rate + b.base_fee)));
 
         // Deduplicate by provider name (keep first occurrence = cheapest)
         let mut seen = HashSet::new();
@@ -144,6 +278,147 @@ impl Router {
         Ok(unique)
     }
 
+    /// Name of the policy a request would get and whether its schedule is
+    /// active at `now`: with the model and tier, everything
+    /// [`select_candidates_at`](Self::select_candidates_at) depends on.
+    pub fn policy_state(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Option<(&str, bool)> {
+        let policy = self.find_policy(policy_name, prompt)?;
+        let active = self
+            .schedules
+            .get(&policy.name)
+            .is_none_or(|s| s.is_active(now));
+        Some((policy.name.as_str(), active))
+    }
+
+    /// Explain which policy a request would get at `now` and whether its
+    /// schedule lets it route normally.
+    pub fn evaluate_policy(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Option<PolicyEvaluation> {
+        let policy = self.find_policy(policy_name, prompt)?;
+        let matched_by = if policy_name == Some(policy.name.as_str()) {
+            "header"
+        } else {
+            "keywords"
+        };
+        let schedule = self.schedules.get(&policy.name);
+        let active = schedule.is_none_or(|s| s.is_active(now));
+        let reason = match schedule {
+            None => "no schedule; always active".to_string(),
+            Some(s) if active => format!("within active hours {}", s.describe()),
+            Some(s) => format!(
+                "outside active hours {}; capped at tier '{}'",
+                s.describe(),
+                policy.off_hours_tier
+            ),
+        };
+        Some(PolicyEvaluation {
+            name: policy.name.clone(),
+            matched_by,
+            active,
+            schedule: schedule.map(Schedule::describe),
+            max_tier: (!active).then_some(policy.off_hours_tier),
+            reason,
+        })
+    }
+
+    /// Response validator of the policy a request would get, if it has one.
+    pub fn response_validator(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<&ResponseValidator> {
+        let policy = self.find_policy(policy_name, prompt)?;
+        self.validators.get(&policy.name)
+    }
+
+    /// System prompt of the policy a request would get, with the policy's
+    /// name, if it has one.
+    pub fn system_prompt(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<(&str, &SystemPrompt)> {
+        let policy = self.find_policy(policy_name, prompt)?;
+        self.system_prompts
+            .get(&policy.name)
+            .map(|p| (policy.name.as_str(), p))
+    }
+
+    /// Tier the policy a request would get pins it to, if any.
+    pub fn policy_tier(&self, policy_name: Option<&str>, prompt: Option<&str>) -> Option<Tier> {
+        self.find_policy(policy_name, prompt)?.tier
+    }
+
+    /// Conversation trimming of the policy a request would get, if it has any.
+    pub fn trim_config(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<&TrimConfig> {
+        self.find_policy(policy_name, prompt)?.trim.as_ref()
+    }
+
+    /// Prompt compression of the policy a request would get, if it has any.
+    pub fn compress_config(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<&PromptCompressionConfig> {
+        self.find_policy(policy_name, prompt)?.compress.as_ref()
+    }
+
+    /// `response_headers` and `omit_headers` of the policy a request would
+    /// get, if it sets either.
+    pub fn response_headers(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<(&BTreeMap<String, String>, &[String])> {
+        let policy = self.find_policy(policy_name, prompt)?;
+        if policy.response_headers.is_empty() && policy.omit_headers.is_empty() {
+            return None;
+        }
+        Some((&policy.response_headers, &policy.omit_headers))
+    }
+
+    /// Dataset capture of the policy a request would get, if it has any.
+    pub fn dataset_capture(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> Option<&DatasetCaptureConfig> {
+        self.find_policy(policy_name, prompt)?
+            .dataset_capture
+            .as_ref()
+    }
+
+    /// Whether the policy a request would get accumulates streamed responses.
+    pub fn accumulates(&self, policy_name: Option<&str>, prompt: Option<&str>) -> bool {
+        self.find_policy(policy_name, prompt)
+            .is_some_and(|policy| policy.accumulate)
+    }
+
+    /// `default_max_tokens` and `max_output_tokens` of the policy a request
+    /// would get.
+    pub fn max_tokens_limits(
+        &self,
+        policy_name: Option<&str>,
+        prompt: Option<&str>,
+    ) -> (Option<u32>, Option<u32>) {
+        self.find_policy(policy_name, prompt)
+            .map(|policy| (policy.default_max_tokens, policy.max_output_tokens))
+            .unwrap_or_default()
+    }
+
     /// Find a matching policy by name or heuristics.
     fn find_policy(&self, policy_name: Option<&str>, prompt: Option<&str>) -> Option<&PolicyRule> {
         // First try explicit policy name
@@ -154,15 +429,12 @@ impl Router {
             }
         }
 
-        // Fall back to keyword heuristics
+        // Fall back to keyword heuristics; prompt-length rules are resolved
+        // up front by `prompt_length_policy`
         if let Some(prompt) = prompt {
             let prompt_lower = prompt.to_lowercase();
             for policy in &self.policy_rules {
-                if policy
-                    .keywords
-                    .iter()
-                    .any(|kw| prompt_lower.contains(&kw.to_lowercase()))
-                {
+                if matches_automatically(policy, Some(&prompt_lower), None) {
                     tracing::debug!(policy = %policy.name, "Matched policy by keyword heuristics");
                     return Some(policy);
                 }
@@ -172,6 +444,29 @@ impl Router {
         None
     }
 
+    /// Name of the policy picked by prompt length for a request without an
+    /// `X-Arbstr-Policy` header, if any.
+    ///
+    /// Rules are tried in order and the first whose conditions all hold
+    /// wins: `prompt_tokens` within `min_prompt_tokens..=max_prompt_tokens`
+    /// and, when the rule has keywords, a keyword in `prompt`. Returns
+    /// `None` when that rule is keyword-only, which keyword matching picks
+    /// on its own.
+    pub fn prompt_length_policy(&self, prompt: Option<&str>, prompt_tokens: u32) -> Option<&str> {
+        let prompt_lower = prompt.map(str::to_lowercase);
+        self.policy_rules
+            .iter()
+            .find(|p| matches_automatically(p, prompt_lower.as_deref(), Some(prompt_tokens)))
+            .filter(|p| has_prompt_bounds(p))
+            .map(|p| p.name.as_str())
+    }
+
+    /// Whether any policy rule is picked by prompt length, which needs the
+    /// whole prompt to estimate.
+    pub fn has_prompt_length_policies(&self) -> bool {
+        self.policy_rules.iter().any(has_prompt_bounds)
+    }
+
     /// Apply policy constraints to filter providers.
     fn apply_policy_constraints<'a>(
         &self,
@@ -194,6 +489,43 @@ impl Router {
             )));
         }
 
+        // Filter by jurisdiction; untagged providers never qualify
+        if !policy.allowed_regions.is_empty() {
+            filtered.retain(|p| {
+                p.region.as_deref().is_some_and(|region| {
+                    policy
+                        .allowed_regions
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(region))
+                })
+            });
+            if filtered.is_empty() {
+                tracing::warn!(
+                    model = %model,
+                    policy = %policy.name,
+                    regions = ?policy.allowed_regions,
+                    "No provider in allowed regions"
+                );
+                return Err(Error::BadRequest(format!(
+                    "No provider in allowed regions [{}] serves model '{}' under policy '{}'",
+                    policy.allowed_regions.join(", "),
+                    model,
+                    policy.name
+                )));
+            }
+        }
+
+        // Pin to the policy's tier
+        if let Some(tier) = policy.tier {
+            filtered.retain(|p| p.tier == tier);
+            if filtered.is_empty() {
+                return Err(Error::BadRequest(format!(
+                    "No provider at tier '{}' serves model '{}' under policy '{}'",
+                    tier, model, policy.name
+                )));
+            }
+        }
+
         // Filter by max cost
         if let Some(max_sats) = policy.max_sats_per_1k_output {
             filtered.retain(|p| p.output_rate <= max_sats);
@@ -209,11 +541,12 @@ impl Router {
     /// Return the most expensive (frontier-tier) rates for a model across all providers.
     ///
     /// Used for worst-case reserve estimation (per D-03/D-04). Takes the maximum
-    /// `input_rate`, `output_rate`, and `base_fee` independently across all providers
-    /// that serve the requested model.
+    /// `input_rate`, `output_rate`, `base_fee` and `min_charge_sats` independently across
+    /// all providers that serve the requested model.
     ///
-    /// Returns `(input_rate, output_rate, base_fee)` or `None` if no providers serve the model.
-    pub fn frontier_rates(&self, model: &str) -> Option<(u64, u64, u64)> {
+    /// Returns `(input_rate, output_rate, base_fee, min_charge_sats)` or `None` if no
+    /// providers serve the model.
+    pub fn frontier_rates(&self, model: &str) -> Option<(f64, f64, f64, f64)> {
         let candidates: Vec<&ProviderConfig> = self
             .providers
             .iter()
@@ -222,10 +555,14 @@ impl Router {
         if candidates.is_empty() {
             return None;
         }
-        let max_input = candidates.iter().map(|p| p.input_rate).max().unwrap_or(0);
-        let max_output = candidates.iter().map(|p| p.output_rate).max().unwrap_or(0);
-        let max_base = candidates.iter().map(|p| p.base_fee).max().unwrap_or(0);
-        Some((max_input, max_output, max_base))
+        let max_input = candidates.iter().map(|p| p.input_rate).fold(0.0, f64::max);
+        let max_output = candidates.iter().map(|p| p.output_rate).fold(0.0, f64::max);
+        let max_base = candidates.iter().map(|p| p.base_fee).fold(0.0, f64::max);
+        let max_min_charge = candidates
+            .iter()
+            .map(|p| p.min_charge_sats)
+            .fold(0.0, f64::max);
+        Some((max_input, max_output, max_base, max_min_charge))
     }
 
     /// Get all configured providers.
@@ -237,20 +574,25 @@ impl Router {
 /// Calculate the actual cost in satoshis for a completed request.
 ///
 /// # Formula
-/// `(input_tokens * input_rate + output_tokens * output_rate) / 1000.0 + base_fee`
+/// `(input_tokens * input_rate + output_tokens * output_rate) / 1000.0 + base_fee`,
+/// raised to `min_charge_sats` when it comes to less (the provider's minimum
+/// charge per request).
 ///
 /// Rates are in sats per 1000 tokens. The result is an `f64` to preserve
-/// sub-satoshi precision (important for cheap models with small token counts).
+/// sub-satoshi precision (important for cheap models with small token counts),
+/// rounded to the nearest millisat.
 pub fn actual_cost_sats(
     input_tokens: u32,
     output_tokens: u32,
-    input_rate: u64,
-    output_rate: u64,
-    base_fee: u64,
+    input_rate: f64,
+    output_rate: f64,
+    base_fee: f64,
+    min_charge_sats: f64,
 ) -> f64 {
-    let input_cost = input_tokens as f64 * input_rate as f64;
-    let output_cost = output_tokens as f64 * output_rate as f64;
-    (input_cost + output_cost) / 1000.0 + base_fee as f64
+    // sats per 1k tokens is msats per token
+    let msats =
+        input_tokens as f64 * input_rate + output_tokens as f64 * output_rate + base_fee * 1000.0;
+    msats.max(min_charge_sats * 1000.0).round() / 1000.0
 }
 
 #[cfg(test)]
@@ -264,22 +606,58 @@ mod tests {
                 url: "https://cheap.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 0,
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "expensive".to_string(),
                 url: "https://expensive.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string(), "claude-3.5-sonnet".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 1,
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 1.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ]
     }
@@ -311,22 +689,58 @@ mod tests {
                 url: "https://a.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 5,
-                output_rate: 10,
-                base_fee: 8,
+                input_rate: 5.0,
+                output_rate: 10.0,
+                base_fee: 8.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "high-rate-no-fee".to_string(),
                 url: "https://b.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 8,
-                output_rate: 15,
-                base_fee: 0,
+                input_rate: 8.0,
+                output_rate: 15.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ];
 
@@ -341,38 +755,46 @@ mod tests {
     #[test]
     fn test_actual_cost_calculation() {
         // Case 1: (100*10 + 200*30)/1000.0 + 1 = 8.0
-        let cost1 = actual_cost_sats(100, 200, 10, 30, 1);
+        let cost1 = actual_cost_sats(100, 200, 10.0, 30.0, 1.0, 0.0);
         assert!(
             (cost1 - 8.0).abs() < f64::EPSILON,
             "Case 1: expected 8.0, got {cost1}"
         );
 
         // Case 2: (10*5 + 5*15)/1000.0 + 0 = 0.125
-        let cost2 = actual_cost_sats(10, 5, 5, 15, 0);
+        let cost2 = actual_cost_sats(10, 5, 5.0, 15.0, 0.0, 0.0);
         assert!(
             (cost2 - 0.125).abs() < f64::EPSILON,
             "Case 2: expected 0.125, got {cost2}"
         );
 
         // Case 3: (0*10 + 0*30)/1000.0 + 5 = 5.0 (base_fee only)
-        let cost3 = actual_cost_sats(0, 0, 10, 30, 5);
+        let cost3 = actual_cost_sats(0, 0, 10.0, 30.0, 5.0, 0.0);
         assert!(
             (cost3 - 5.0).abs() < f64::EPSILON,
             "Case 3: expected 5.0, got {cost3}"
         );
 
         // Case 4: (1000*10 + 1000*30)/1000.0 + 0 = 40.0
-        let cost4 = actual_cost_sats(1000, 1000, 10, 30, 0);
+        let cost4 = actual_cost_sats(1000, 1000, 10.0, 30.0, 0.0, 0.0);
         assert!(
             (cost4 - 40.0).abs() < f64::EPSILON,
             "Case 4: expected 40.0, got {cost4}"
         );
     }
 
+    #[test]
+    fn test_actual_cost_min_charge() {
+        // 0.125 sats of tokens is billed at the 2 sat minimum
+        assert_eq!(actual_cost_sats(10, 5, 5.0, 15.0, 0.0, 2.0), 2.0);
+        // Costs above the minimum are unaffected
+        assert_eq!(actual_cost_sats(100, 200, 10.0, 30.0, 1.0, 2.0), 8.0);
+    }
+
     #[test]
     fn test_actual_cost_fractional_sats() {
         // Verify sub-sat precision: (10*5 + 5*15)/1000.0 = 0.125, not 0
-        let cost = actual_cost_sats(10, 5, 5, 15, 0);
+        let cost = actual_cost_sats(10, 5, 5.0, 15.0, 0.0, 0.0);
         assert!(cost > 0.0, "Fractional sats must be preserved, got {cost}");
         assert!(
             (cost - 0.125).abs() < f64::EPSILON,
@@ -386,8 +808,28 @@ mod tests {
             name: "code".to_string(),
             allowed_models: vec!["gpt-4o".to_string()],
             strategy: "lowest_cost".to_string(),
-            max_sats_per_1k_output: Some(20),
+            max_sats_per_1k_output: Some(20.0),
             keywords: vec!["function".to_string(), "code".to_string()],
+            backoff: None,
+            active_hours: None,
+            days: vec![],
+            utc_offset: None,
+            off_hours_tier: Default::default(),
+            allowed_regions: vec![],
+            validate: None,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: None,
+            system_prompt_bypass_token: None,
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
         }];
 
         let router = Router::new(test_providers(), policies, "cheapest".to_string());
@@ -408,33 +850,87 @@ mod tests {
                 url: "https://medium.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 8,
-                output_rate: 20,
-                base_fee: 5, // routing cost: 25
+                input_rate: 8.0,
+                output_rate: 20.0,
+                base_fee: 5.0, // routing cost: 25
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "cheapest".to_string(),
                 url: "https://cheapest.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 3,
-                output_rate: 10,
-                base_fee: 0, // routing cost: 10
+                input_rate: 3.0,
+                output_rate: 10.0,
+                base_fee: 0.0, // routing cost: 10
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "pricey".to_string(),
                 url: "https://pricey.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 15,
-                output_rate: 40,
-                base_fee: 10, // routing cost: 50
+                input_rate: 15.0,
+                output_rate: 40.0,
+                base_fee: 10.0, // routing cost: 50
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ];
 
@@ -458,33 +954,87 @@ mod tests {
                 url: "https://alpha-expensive.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 5, // routing cost: 35
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 5.0, // routing cost: 35
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "alpha".to_string(),
                 url: "https://alpha-cheap.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 3,
-                output_rate: 10,
-                base_fee: 0, // routing cost: 10
+                input_rate: 3.0,
+                output_rate: 10.0,
+                base_fee: 0.0, // routing cost: 10
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "beta".to_string(),
                 url: "https://beta.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 2, // routing cost: 17
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 2.0, // routing cost: 17
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ];
 
@@ -497,7 +1047,7 @@ mod tests {
         assert_eq!(candidates.len(), 2);
         // cheapest alpha (cost 10) kept, expensive alpha (cost 35) removed
         assert_eq!(candidates[0].name, "alpha");
-        assert_eq!(candidates[0].output_rate, 10);
+        assert_eq!(candidates[0].output_rate, 10.0);
         assert_eq!(candidates[1].name, "beta");
     }
 
@@ -524,22 +1074,58 @@ mod tests {
                 url: "https://a.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 0,
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "no-model".to_string(),
                 url: "https://b.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["claude-3.5-sonnet".to_string()],
-                input_rate: 3,
-                output_rate: 10,
-                base_fee: 0,
+                input_rate: 3.0,
+                output_rate: 10.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ];
 
@@ -559,33 +1145,87 @@ mod tests {
                 url: "https://local.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 1,
-                output_rate: 5,
-                base_fee: 0,
+                input_rate: 1.0,
+                output_rate: 5.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::Local,
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "standard-mid".to_string(),
                 url: "https://standard.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 1,
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 1.0,
+                min_charge_sats: 0.0,
                 tier: Tier::Standard,
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "frontier-expensive".to_string(),
                 url: "https://frontier.example.com/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 2,
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 2.0,
+                min_charge_sats: 0.0,
                 tier: Tier::Frontier,
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ]
     }
@@ -637,11 +1277,29 @@ mod tests {
             url: "https://frontier.example.com/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 2,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 2.0,
+            min_charge_sats: 0.0,
             tier: Tier::Frontier,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         }];
         let router = Router::new(providers, vec![], "cheapest".to_string());
         let result = router.select_candidates("gpt-4o", None, None, Some(Tier::Local));
@@ -661,7 +1319,7 @@ mod tests {
         // local(1,5,0), standard(5,15,1), frontier(10,30,2)
         let router = Router::new(tiered_providers(), vec![], "cheapest".to_string());
         let rates = router.frontier_rates("gpt-4o");
-        assert_eq!(rates, Some((10, 30, 2)));
+        assert_eq!(rates, Some((10.0, 30.0, 2.0, 0.0)));
     }
 
     #[test]
@@ -672,15 +1330,33 @@ mod tests {
             url: "https://local.example.com/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 1,
-            output_rate: 5,
-            base_fee: 0,
+            input_rate: 1.0,
+            output_rate: 5.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: Tier::Local,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         }];
         let router = Router::new(providers, vec![], "cheapest".to_string());
         let rates = router.frontier_rates("gpt-4o");
-        assert_eq!(rates, Some((1, 5, 0)));
+        assert_eq!(rates, Some((1.0, 5.0, 0.0, 0.0)));
     }
 
     #[test]
@@ -689,4 +1365,204 @@ mod tests {
         let rates = router.frontier_rates("nonexistent-model");
         assert_eq!(rates, None);
     }
+
+    fn work_hours_policy(off_hours_tier: Tier) -> PolicyRule {
+        PolicyRule {
+            name: "work".to_string(),
+            allowed_models: vec![],
+            strategy: "lowest_cost".to_string(),
+            max_sats_per_1k_output: None,
+            keywords: vec![],
+            backoff: None,
+            active_hours: Some("09:00-18:00".to_string()),
+            days: vec!["mon".to_string(), "fri".to_string()],
+            utc_offset: None,
+            off_hours_tier,
+            allowed_regions: vec![],
+            validate: None,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: None,
+            system_prompt_bypass_token: None,
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
+        }
+    }
+
+    #[test]
+    fn test_policy_off_hours_caps_tier() {
+        let router = Router::new(
+            tiered_providers(),
+            vec![work_hours_policy(Tier::Local)],
+            "cheapest".to_string(),
+        );
+        // 2026-01-05 is a Monday
+        let monday_noon = "2026-01-05T12:00:00Z".parse().unwrap();
+        let monday_night = "2026-01-05T22:00:00Z".parse().unwrap();
+
+        let candidates = router
+            .select_candidates_at("gpt-4o", Some("work"), None, None, monday_noon)
+            .unwrap();
+        assert_eq!(candidates.len(), 3);
+        let eval = router
+            .evaluate_policy(Some("work"), None, monday_noon)
+            .unwrap();
+        assert!(eval.active);
+        assert_eq!(eval.matched_by, "header");
+        assert_eq!(eval.max_tier, None);
+
+        let candidates = router
+            .select_candidates_at("gpt-4o", Some("work"), None, None, monday_night)
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name, "local-cheap");
+        let eval = router
+            .evaluate_policy(Some("work"), None, monday_night)
+            .unwrap();
+        assert!(!eval.active);
+        assert_eq!(eval.max_tier, Some(Tier::Local));
+        assert!(
+            eval.reason.contains("outside active hours"),
+            "{}",
+            eval.reason
+        );
+
+        // Requests without the policy are unaffected
+        let candidates = router
+            .select_candidates_at("gpt-4o", None, None, None, monday_night)
+            .unwrap();
+        assert_eq!(candidates.len(), 3);
+    }
+
+    #[test]
+    fn test_policy_off_hours_without_providers_errors() {
+        let providers: Vec<ProviderConfig> = tiered_providers()
+            .into_iter()
+            .filter(|p| p.tier == Tier::Frontier)
+            .collect();
+        let router = Router::new(
+            providers,
+            vec![work_hours_policy(Tier::Standard)],
+            "cheapest".to_string(),
+        );
+        let sunday = "2026-01-04T12:00:00Z".parse().unwrap();
+        let err = router
+            .select_candidates_at("gpt-4o", Some("work"), None, None, sunday)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("outside its active hours"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_policy_allowed_regions() {
+        let mut providers = tiered_providers();
+        providers[0].region = Some("EU".to_string());
+        providers[1].region = Some("us".to_string());
+        let policy = PolicyRule {
+            allowed_regions: vec!["eu".to_string()],
+            active_hours: None,
+            days: vec![],
+            ..work_hours_policy(Tier::Local)
+        };
+        let router = Router::new(providers, vec![policy], "cheapest".to_string());
+
+        let candidates = router
+            .select_candidates("gpt-4o", Some("work"), None, None)
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name, "local-cheap");
+
+        // Without the policy every provider is eligible
+        let candidates = router
+            .select_candidates("gpt-4o", None, None, None)
+            .unwrap();
+        assert_eq!(candidates.len(), 3);
+
+        // No provider is tagged for Switzerland
+        let policy = PolicyRule {
+            allowed_regions: vec!["ch".to_string()],
+            active_hours: None,
+            days: vec![],
+            ..work_hours_policy(Tier::Local)
+        };
+        let router = Router::new(tiered_providers(), vec![policy], "cheapest".to_string());
+        let err = router
+            .select_candidates("gpt-4o", Some("work"), None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("allowed regions [ch]"), "{}", err);
+    }
+
+    fn prompt_length_policy(name: &str, min: Option<u32>, max: Option<u32>) -> PolicyRule {
+        PolicyRule {
+            name: name.to_string(),
+            active_hours: None,
+            days: vec![],
+            min_prompt_tokens: min,
+            max_prompt_tokens: max,
+            ..work_hours_policy(Tier::Local)
+        }
+    }
+
+    #[test]
+    fn test_prompt_length_policy_first_match_wins() {
+        let policies = vec![
+            prompt_length_policy("short", None, Some(4000)),
+            prompt_length_policy("long", Some(4001), None),
+        ];
+        let router = Router::new(test_providers(), policies, "cheapest".to_string());
+
+        assert_eq!(router.prompt_length_policy(None, 1), Some("short"));
+        assert_eq!(router.prompt_length_policy(None, 4000), Some("short"));
+        assert_eq!(router.prompt_length_policy(None, 4001), Some("long"));
+        assert_eq!(router.prompt_length_policy(None, 100_000), Some("long"));
+
+        // Length rules need a token estimate; keyword matching alone skips them
+        assert!(router.find_policy(None, Some("hello")).is_none());
+    }
+
+    #[test]
+    fn test_prompt_length_policy_with_keywords() {
+        let policies = vec![
+            PolicyRule {
+                keywords: vec!["contract".to_string()],
+                ..prompt_length_policy("long-legal", Some(1000), None)
+            },
+            PolicyRule {
+                keywords: vec!["code".to_string()],
+                ..prompt_length_policy("code", None, None)
+            },
+        ];
+        let router = Router::new(test_providers(), policies, "cheapest".to_string());
+
+        // Both conditions must hold for a rule with keywords and bounds
+        assert_eq!(
+            router.prompt_length_policy(Some("Review this Contract"), 5000),
+            Some("long-legal")
+        );
+        assert_eq!(
+            router.prompt_length_policy(Some("Review this contract"), 10),
+            None
+        );
+        assert_eq!(
+            router.prompt_length_policy(Some("Some long text"), 5000),
+            None
+        );
+
+        // A keyword-only rule matching first is left to keyword matching
+        assert_eq!(router.prompt_length_policy(Some("write code"), 5000), None);
+        assert_eq!(
+            router.find_policy(None, Some("write code")).unwrap().name,
+            "code"
+        );
+    }
 }
@@ -0,0 +1,222 @@
+//! System prompts injected by policies.
+//!
+//! A policy with `system_prompt_prepend` puts its text ahead of every
+//! conversation routed under it, e.g. a compliance preamble or an org-wide
+//! style guide. The text is a template: `{policy}`, `{model}`, `{date}`
+//! (UTC, `YYYY-MM-DD`) and `{request_id}` are filled in per request. A
+//! client holding the policy's `system_prompt_bypass_token` may skip it.
+
+use chrono::NaiveDate;
+
+use crate::config::PolicyRule;
+
+/// Values substituted into a system prompt template.
+#[derive(Debug, Clone, Copy)]
+pub struct PromptVars<'a> {
+    pub policy: &'a str,
+    pub model: &'a str,
+    pub date: NaiveDate,
+    pub request_id: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    Policy,
+    Model,
+    Date,
+    RequestId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Var(Var),
+}
+
+/// A policy's parsed `system_prompt_prepend` template.
+#[derive(Debug, Clone)]
+pub struct SystemPrompt {
+    segments: Vec<Segment>,
+    bypass_token: Option<String>,
+}
+
+impl SystemPrompt {
+    /// Parse a policy's system prompt. `Ok(None)` when it has none.
+    pub fn from_policy(rule: &PolicyRule) -> Result<Option<Self>, String> {
+        let Some(template) = &rule.system_prompt_prepend else {
+            if rule.system_prompt_bypass_token.is_some() {
+                return Err("system_prompt_bypass_token needs system_prompt_prepend".to_string());
+            }
+            return Ok(None);
+        };
+        if template.trim().is_empty() {
+            return Err("system_prompt_prepend is empty".to_string());
+        }
+        if rule
+            .system_prompt_bypass_token
+            .as_deref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return Err("system_prompt_bypass_token is empty".to_string());
+        }
+        Ok(Some(Self {
+            segments: parse(template)?,
+            bypass_token: rule.system_prompt_bypass_token.clone(),
+        }))
+    }
+
+    /// The prompt with `vars` filled in.
+    pub fn render(&self, vars: &PromptVars<'_>) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Var(Var::Policy) => out.push_str(vars.policy),
+                Segment::Var(Var::Model) => out.push_str(vars.model),
+                Segment::Var(Var::Date) => out.push_str(&vars.date.format("%Y-%m-%d").to_string()),
+                Segment::Var(Var::RequestId) => out.push_str(vars.request_id),
+            }
+        }
+        out
+    }
+
+    /// Whether `token` is this policy's bypass token.
+    pub fn allows_bypass(&self, token: &str) -> bool {
+        self.bypass_token.as_deref() == Some(token)
+    }
+}
+
+fn parse(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let var = match name.trim() {
+                    "policy" => Var::Policy,
+                    "model" => Var::Model,
+                    "date" => Var::Date,
+                    "request_id" => Var::RequestId,
+                    other => {
+                        return Err(format!(
+                            "unknown system_prompt_prepend variable '{{{}}}' \
+                             (expected policy, model, date, request_id)",
+                            other
+                        ))
+                    }
+                };
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Var(var));
+            }
+            '}' => return Err("unmatched '}' in system_prompt_prepend (use '}}')".to_string()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prepend: Option<&str>, bypass: Option<&str>) -> PolicyRule {
+        PolicyRule {
+            name: "compliance".to_string(),
+            allowed_models: vec![],
+            strategy: "lowest_cost".to_string(),
+            max_sats_per_1k_output: None,
+            keywords: vec![],
+            backoff: None,
+            active_hours: None,
+            days: vec![],
+            utc_offset: None,
+            off_hours_tier: Default::default(),
+            allowed_regions: vec![],
+            validate: None,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: prepend.map(str::to_string),
+            system_prompt_bypass_token: bypass.map(str::to_string),
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
+        }
+    }
+
+    #[test]
+    fn renders_variables_and_escaped_braces() {
+        let prompt = SystemPrompt::from_policy(&rule(
+            Some(
+                "Policy {policy} for {model} on {date} ({request_id}). Reply as {{\"ok\": true}}.",
+            ),
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        let rendered = prompt.render(&PromptVars {
+            policy: "compliance",
+            model: "gpt-4o",
+            date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            request_id: "abc",
+        });
+        assert_eq!(
+            rendered,
+            "Policy compliance for gpt-4o on 2026-03-01 (abc). Reply as {\"ok\": true}."
+        );
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(SystemPrompt::from_policy(&rule(None, None))
+            .unwrap()
+            .is_none());
+        for (prepend, bypass, needle) in [
+            (
+                Some("Hi {user}"),
+                None,
+                "unknown system_prompt_prepend variable",
+            ),
+            (Some("Hi }"), None, "unmatched"),
+            (Some("  "), None, "empty"),
+            (None, Some("secret"), "needs system_prompt_prepend"),
+        ] {
+            let err = SystemPrompt::from_policy(&rule(prepend, bypass)).unwrap_err();
+            assert!(err.contains(needle), "{}", err);
+        }
+    }
+
+    #[test]
+    fn bypass_needs_the_configured_token() {
+        let prompt = SystemPrompt::from_policy(&rule(Some("Be nice."), Some("trusted")))
+            .unwrap()
+            .unwrap();
+        assert!(prompt.allows_bypass("trusted"));
+        assert!(!prompt.allows_bypass("guess"));
+
+        let prompt = SystemPrompt::from_policy(&rule(Some("Be nice."), None))
+            .unwrap()
+            .unwrap();
+        assert!(!prompt.allows_bypass(""));
+    }
+}
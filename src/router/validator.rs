@@ -0,0 +1,130 @@
+//! Response checks for policies.
+//!
+//! The cheapest provider is not always good enough for structured-output
+//! workloads: it may answer in prose where JSON was asked for, or return a
+//! truncated reply. A policy with `validate` checks the message content of
+//! non-streaming responses; a response that fails is re-sent once to a
+//! provider of a higher tier.
+
+use regex::Regex;
+
+use crate::config::PolicyRule;
+
+/// A policy's parsed `validate` settings.
+#[derive(Debug, Clone)]
+pub struct ResponseValidator {
+    json: bool,
+    min_length: Option<usize>,
+    pattern: Option<Regex>,
+}
+
+impl ResponseValidator {
+    /// Parse a policy's validator. `Ok(None)` when it has none.
+    pub fn from_policy(rule: &PolicyRule) -> Result<Option<Self>, String> {
+        let Some(config) = &rule.validate else {
+            return Ok(None);
+        };
+        if !config.json && config.min_length.is_none() && config.pattern.is_none() {
+            return Err("validate needs at least one of json, min_length, pattern".to_string());
+        }
+        let pattern = config
+            .pattern
+            .as_deref()
+            .map(|p| Regex::new(p).map_err(|e| format!("invalid validate.pattern: {}", e)))
+            .transpose()?;
+        Ok(Some(Self {
+            json: config.json,
+            min_length: config.min_length,
+            pattern,
+        }))
+    }
+
+    /// Check response content, returning the first failed check.
+    pub fn check(&self, content: &str) -> Result<(), String> {
+        if let Some(min) = self.min_length {
+            let len = content.chars().count();
+            if len < min {
+                return Err(format!("content is {} chars, need {}", len, min));
+            }
+        }
+        if self.json {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(content) {
+                return Err(format!("content is not valid JSON: {}", e));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(content) {
+                return Err(format!("content does not match /{}/", pattern));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ResponseValidationConfig;
+
+    fn rule(validate: Option<ResponseValidationConfig>) -> PolicyRule {
+        PolicyRule {
+            name: "structured".to_string(),
+            allowed_models: vec![],
+            strategy: "lowest_cost".to_string(),
+            max_sats_per_1k_output: None,
+            keywords: vec![],
+            backoff: None,
+            active_hours: None,
+            days: vec![],
+            utc_offset: None,
+            off_hours_tier: Default::default(),
+            allowed_regions: vec![],
+            validate,
+            max_output_tokens: None,
+            default_max_tokens: None,
+            system_prompt_prepend: None,
+            system_prompt_bypass_token: None,
+            trim: None,
+            min_prompt_tokens: None,
+            max_prompt_tokens: None,
+            tier: None,
+            accumulate: false,
+            compress: None,
+            response_headers: Default::default(),
+            omit_headers: vec![],
+            dataset_capture: None,
+        }
+    }
+
+    #[test]
+    fn checks_json_length_and_pattern() {
+        assert!(ResponseValidator::from_policy(&rule(None))
+            .unwrap()
+            .is_none());
+        assert!(ResponseValidator::from_policy(&rule(Some(Default::default()))).is_err());
+        let err = ResponseValidator::from_policy(&rule(Some(ResponseValidationConfig {
+            pattern: Some("(".to_string()),
+            ..Default::default()
+        })))
+        .unwrap_err();
+        assert!(err.contains("validate.pattern"), "{}", err);
+
+        let validator = ResponseValidator::from_policy(&rule(Some(ResponseValidationConfig {
+            json: true,
+            min_length: Some(10),
+            pattern: Some(r#""status"\s*:"#.to_string()),
+        })))
+        .unwrap()
+        .unwrap();
+        assert!(validator.check(r#"{"status": "ok"}"#).is_ok());
+        assert!(validator.check("{}").unwrap_err().contains("chars"));
+        assert!(validator
+            .check("Sure! Here is the JSON you asked for")
+            .unwrap_err()
+            .contains("JSON"));
+        assert!(validator
+            .check(r#"{"result": "ok"}"#)
+            .unwrap_err()
+            .contains("match"));
+    }
+}
@@ -0,0 +1,554 @@
+//! Smoke test behind `arbstr selftest`.
+//!
+//! Runs a battery of requests against a proxy routing to two built-in mock
+//! providers: a healthy one serving the usual models and one that fails
+//! every request for [`FAILING_MODEL`]. The checks cover streaming and
+//! non-streaming completions and their `x-arbstr-*` headers, rejected
+//! requests, provider errors tripping a circuit, the rows written to the
+//! request log, and `/v1/stats`. The report reads like pytest's, so a
+//! packaged build can be checked end to end with one command.
+
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::config::*;
+
+/// Provider answering every completion.
+pub const HEALTHY_PROVIDER: &str = "selftest-ok";
+
+/// Provider failing every completion.
+pub const FAILING_PROVIDER: &str = "selftest-failing";
+
+/// The only model served by [`FAILING_PROVIDER`].
+pub const FAILING_MODEL: &str = "selftest-broken";
+
+/// Model requested by the passing checks.
+const MODEL: &str = "gpt-4o";
+
+/// Failing requests sent before the circuit check gives up.
+const MAX_FAILING_REQUESTS: usize = 6;
+
+/// How long to wait for request rows to be written.
+const LOG_WAIT: Duration = Duration::from_secs(5);
+
+/// Configuration for the proxy under test: [`HEALTHY_PROVIDER`] at
+/// `healthy_url`, [`FAILING_PROVIDER`] at `failing_url`, and the request
+/// log in the SQLite file at `db_path`.
+pub fn config(healthy_url: &str, failing_url: &str, db_path: &str) -> Config {
+    let provider = |name: &str, url: &str, models: Vec<String>| ProviderConfig {
+        name: name.to_string(),
+        url: url.to_string(),
+        api_key: Some(ApiKey::from("selftest-key")),
+        models,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
+        tier: Tier::default(),
+        auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        // Keep retries of the failing provider quick
+        backoff: Some(BackoffConfig {
+            base_ms: 10,
+            max_ms: 20,
+            ..Default::default()
+        }),
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
+    };
+
+    Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: Some(DatabaseConfig {
+            path: db_path.to_string(),
+            ..Default::default()
+        }),
+        vault: None,
+        providers: vec![
+            provider(
+                HEALTHY_PROVIDER,
+                healthy_url,
+                vec![MODEL.to_string(), "gpt-4o-mini".to_string()],
+            ),
+            provider(
+                FAILING_PROVIDER,
+                failing_url,
+                vec![FAILING_MODEL.to_string()],
+            ),
+        ],
+        policies: PoliciesConfig::default(),
+        logging: LoggingConfig::default(),
+        routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    }
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    /// Why the check failed; None when it passed.
+    pub failure: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// Result of one selftest run.
+#[derive(Debug, Clone)]
+pub struct SelftestReport {
+    pub checks: Vec<CheckResult>,
+    pub elapsed: Duration,
+}
+
+impl SelftestReport {
+    pub fn passed(&self) -> usize {
+        self.checks.iter().filter(|c| c.failure.is_none()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.checks.len() - self.passed()
+    }
+
+    /// Whether every check passed.
+    pub fn success(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// Run every check, in order, against the proxy at `target` (e.g.
+/// `http://127.0.0.1:8080`), which must be configured by [`config`].
+/// Later checks rely on the requests sent by earlier ones.
+pub async fn run(client: &reqwest::Client, target: &str) -> SelftestReport {
+    let checks = Checks {
+        client,
+        target: target.trim_end_matches('/'),
+    };
+    let start = Instant::now();
+    let results = vec![
+        timed("test_health", checks.health()).await,
+        timed("test_completion", checks.completion()).await,
+        timed("test_streaming_completion", checks.streaming_completion()).await,
+        timed("test_malformed_request", checks.malformed_request()).await,
+        timed("test_unknown_model", checks.unknown_model()).await,
+        timed("test_provider_error", checks.provider_error()).await,
+        timed("test_circuit_trips", checks.circuit_trips()).await,
+        timed("test_request_log", checks.request_log()).await,
+        timed("test_stats", checks.stats()).await,
+    ];
+    SelftestReport {
+        checks: results,
+        elapsed: start.elapsed(),
+    }
+}
+
+async fn timed(name: &'static str, check: impl Future<Output = Result<(), String>>) -> CheckResult {
+    let start = Instant::now();
+    let failure = check.await.err();
+    CheckResult {
+        name,
+        failure,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Fail the enclosing check with a formatted message unless `cond` holds.
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err(format!($($arg)+));
+        }
+    };
+}
+
+struct Checks<'a> {
+    client: &'a reqwest::Client,
+    target: &'a str,
+}
+
+impl Checks<'_> {
+    async fn health(&self) -> Result<(), String> {
+        let (status, body) = self.get_json("/health").await?;
+        ensure!(status == 200, "GET /health returned {}: {}", status, body);
+        ensure!(
+            body["status"] == "ok",
+            "expected status \"ok\", got {}",
+            body["status"]
+        );
+        for name in [HEALTHY_PROVIDER, FAILING_PROVIDER] {
+            ensure!(
+                body["providers"][name]["state"] == "closed",
+                "expected {} to be closed, got {}",
+                name,
+                body["providers"][name]
+            );
+        }
+        Ok(())
+    }
+
+    async fn completion(&self) -> Result<(), String> {
+        let response = self.complete(MODEL, false).await?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        ensure!(status == 200, "expected 200, got {}: {}", status, body);
+        expect_header(&headers, "x-arbstr-provider", Some(HEALTHY_PROVIDER))?;
+        expect_header(&headers, "x-arbstr-request-id", None)?;
+        expect_header(&headers, "x-arbstr-latency-ms", None)?;
+        let cost = expect_header(&headers, "x-arbstr-cost-sats", None)?;
+        ensure!(
+            cost.parse::<f64>().is_ok_and(|c| c > 0.0),
+            "expected a positive x-arbstr-cost-sats, got {}",
+            cost
+        );
+        ensure!(
+            body["choices"][0]["message"]["content"]
+                .as_str()
+                .is_some_and(|c| !c.is_empty()),
+            "response has no message content: {}",
+            body
+        );
+        ensure!(
+            body["usage"]["completion_tokens"].as_u64().is_some(),
+            "response has no usage: {}",
+            body
+        );
+        Ok(())
+    }
+
+    async fn streaming_completion(&self) -> Result<(), String> {
+        let response = self.complete(MODEL, true).await?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        ensure!(status == 200, "expected 200, got {}: {}", status, body);
+        expect_header(&headers, "content-type", Some("text/event-stream"))?;
+        expect_header(&headers, "x-arbstr-streaming", Some("true"))?;
+        expect_header(&headers, "x-arbstr-provider", Some(HEALTHY_PROVIDER))?;
+
+        let events: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect();
+        let content: String = events
+            .iter()
+            .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+            .filter_map(|chunk| {
+                chunk["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .collect();
+        ensure!(!content.is_empty(), "stream carried no content: {}", body);
+        ensure!(
+            events.last() == Some(&"[DONE]"),
+            "stream did not end with [DONE]: {}",
+            body
+        );
+        Ok(())
+    }
+
+    async fn malformed_request(&self) -> Result<(), String> {
+        let response = self
+            .client
+            .post(self.url("/v1/chat/completions"))
+            .header("content-type", "application/json")
+            .body("{not json")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        ensure!(status == 400, "expected 400, got {}", status);
+        Ok(())
+    }
+
+    async fn unknown_model(&self) -> Result<(), String> {
+        let response = self.complete("selftest-no-such-model", false).await?;
+        let status = response.status().as_u16();
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        ensure!(status == 400, "expected 400, got {}: {}", status, body);
+        ensure!(
+            body["error"]["message"].is_string(),
+            "expected an OpenAI-style error, got {}",
+            body
+        );
+        Ok(())
+    }
+
+    async fn provider_error(&self) -> Result<(), String> {
+        let response = self.complete(FAILING_MODEL, false).await?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        ensure!(status >= 500, "expected a 5xx, got {}: {}", status, body);
+        expect_header(&headers, "x-arbstr-provider", Some(FAILING_PROVIDER))?;
+        ensure!(
+            body["error"]["message"].is_string(),
+            "expected an OpenAI-style error, got {}",
+            body
+        );
+        Ok(())
+    }
+
+    async fn circuit_trips(&self) -> Result<(), String> {
+        for _ in 0..MAX_FAILING_REQUESTS {
+            let (_, health) = self.get_json("/health").await?;
+            if health["providers"][FAILING_PROVIDER]["state"] == "open" {
+                ensure!(
+                    health["status"] == "degraded",
+                    "expected status \"degraded\" with one circuit open, got {}",
+                    health["status"]
+                );
+                ensure!(
+                    health["providers"][HEALTHY_PROVIDER]["state"] == "closed",
+                    "{} was affected by {}'s failures",
+                    HEALTHY_PROVIDER,
+                    FAILING_PROVIDER
+                );
+                return Ok(());
+            }
+            self.complete(FAILING_MODEL, false).await?;
+        }
+        Err(format!(
+            "{} circuit still closed after {} failing requests",
+            FAILING_PROVIDER, MAX_FAILING_REQUESTS
+        ))
+    }
+
+    async fn request_log(&self) -> Result<(), String> {
+        let deadline = Instant::now() + LOG_WAIT;
+        loop {
+            let (status, body) = self.get_json("/v1/requests?per_page=100").await?;
+            ensure!(
+                status == 200,
+                "GET /v1/requests returned {}: {}",
+                status,
+                body
+            );
+            let rows = body["data"].as_array().cloned().unwrap_or_default();
+            let has = |streaming: bool, success: bool| {
+                rows.iter().any(|row| {
+                    row["streaming"] == streaming
+                        && row["success"] == success
+                        && (!success || row["provider"] == HEALTHY_PROVIDER)
+                })
+            };
+            let missing: Vec<&str> = [
+                (has(false, true), "a successful completion"),
+                (has(true, true), "a successful stream"),
+                (has(false, false), "a failed request"),
+            ]
+            .into_iter()
+            .filter(|(found, _)| !found)
+            .map(|(_, what)| what)
+            .collect();
+            if missing.is_empty() {
+                return Ok(());
+            }
+            ensure!(
+                Instant::now() < deadline,
+                "request log is missing {} ({} rows)",
+                missing.join(", "),
+                rows.len()
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn stats(&self) -> Result<(), String> {
+        let (status, body) = self.get_json("/v1/stats").await?;
+        ensure!(status == 200, "GET /v1/stats returned {}: {}", status, body);
+        let count = |key: &str| body["counts"][key].as_i64().unwrap_or(0);
+        ensure!(
+            count("success") >= 2 && count("streaming") >= 1 && count("error") >= 1,
+            "unexpected counts: {}",
+            body["counts"]
+        );
+        ensure!(
+            body["costs"]["total_cost_sats"]
+                .as_f64()
+                .is_some_and(|c| c > 0.0),
+            "expected a positive total cost, got {}",
+            body["costs"]
+        );
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.target, path)
+    }
+
+    async fn complete(&self, model: &str, stream: bool) -> Result<reqwest::Response, String> {
+        self.client
+            .post(self.url("/v1/chat/completions"))
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Selftest: reply with a short sentence."}],
+                "stream": stream
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {}", e))
+    }
+
+    async fn get_json(&self, path: &str) -> Result<(u16, Value), String> {
+        let response = self
+            .client
+            .get(self.url(path))
+            .send()
+            .await
+            .map_err(|e| format!("GET {} failed: {}", path, e))?;
+        let status = response.status().as_u16();
+        let body = response
+            .json()
+            .await
+            .map_err(|e| format!("GET {} returned invalid JSON: {}", path, e))?;
+        Ok((status, body))
+    }
+}
+
+/// The value of header `name`, which must equal `expected` when given.
+fn expect_header(
+    headers: &reqwest::header::HeaderMap,
+    name: &str,
+    expected: Option<&str>,
+) -> Result<String, String> {
+    let value = headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| format!("missing {} header", name))?;
+    if let Some(expected) = expected {
+        ensure!(
+            value == expected,
+            "expected {}: {}, got {}",
+            name,
+            expected,
+            value
+        );
+    }
+    Ok(value.to_string())
+}
+
+/// `text` centered in a line of `fill`, like pytest's section banners.
+fn banner(text: &str, fill: char) -> String {
+    const WIDTH: usize = 72;
+    let text = format!(" {} ", text);
+    let side = WIDTH.saturating_sub(text.len()) / 2;
+    let pad: String = std::iter::repeat_n(fill, side).collect();
+    format!("{}{}{}", pad, text, pad)
+}
+
+impl fmt::Display for SelftestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(
+                f,
+                "selftest::{:<32} {:<7} ({:.0}ms)",
+                check.name,
+                if check.failure.is_some() {
+                    "FAILED"
+                } else {
+                    "PASSED"
+                },
+                check.elapsed.as_secs_f64() * 1000.0
+            )?;
+        }
+        if self.failed() > 0 {
+            writeln!(f)?;
+            writeln!(f, "{}", banner("FAILURES", '='))?;
+            for check in &self.checks {
+                if let Some(failure) = &check.failure {
+                    writeln!(f, "{}", banner(check.name, '_'))?;
+                    writeln!(f, "{}", failure)?;
+                }
+            }
+        }
+        let summary = if self.failed() > 0 {
+            format!(
+                "{} failed, {} passed in {:.2}s",
+                self.failed(),
+                self.passed(),
+                self.elapsed.as_secs_f64()
+            )
+        } else {
+            format!(
+                "{} passed in {:.2}s",
+                self.passed(),
+                self.elapsed.as_secs_f64()
+            )
+        };
+        writeln!(f, "{}", banner(&summary, '='))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_failures_like_pytest() {
+        let report = SelftestReport {
+            checks: vec![
+                CheckResult {
+                    name: "test_health",
+                    failure: None,
+                    elapsed: Duration::from_millis(3),
+                },
+                CheckResult {
+                    name: "test_stats",
+                    failure: Some("unexpected counts".to_string()),
+                    elapsed: Duration::from_millis(5),
+                },
+            ],
+            elapsed: Duration::from_millis(8),
+        };
+        assert!(!report.success());
+        let text = report.to_string();
+        assert!(text.contains("selftest::test_health"));
+        assert!(text.contains("PASSED"));
+        assert!(text.contains("= FAILURES ="));
+        assert!(text.contains("_ test_stats _"));
+        assert!(text.contains("_\nunexpected counts\n"));
+        assert!(text.contains("= 1 failed, 1 passed in 0.01s ="));
+    }
+}
@@ -0,0 +1,179 @@
+//! Health probe behind `arbstr status`.
+//!
+//! Queries a running proxy's `/ready` and `/health` and condenses them to
+//! one line, e.g. `degraded: ready, 1/2 circuits closed; open: alpha (3
+//! failures)`. The command exits non-zero unless the proxy is ready and
+//! every circuit is closed, so it can serve as a Docker `HEALTHCHECK` or
+//! a guard in shell scripts.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// A provider's circuit as reported by `/health`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CircuitStatus {
+    /// `closed`, `open` or `half_open`.
+    pub state: String,
+    pub failure_count: u32,
+    /// Seconds left in a trip-storm cool-down.
+    #[serde(default)]
+    pub storm_cooldown_secs: Option<u64>,
+}
+
+/// What `arbstr status` found.
+#[derive(Debug, Clone)]
+pub struct StatusReport {
+    /// `/ready` status: `ready` or `warming_up`.
+    pub ready: String,
+    /// `/health` status: `ok`, `degraded` or `unhealthy`.
+    pub health: String,
+    pub circuits: BTreeMap<String, CircuitStatus>,
+}
+
+#[derive(Deserialize)]
+struct ReadyBody {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct HealthBody {
+    status: String,
+    providers: BTreeMap<String, CircuitStatus>,
+}
+
+impl StatusReport {
+    /// Ready with every circuit closed.
+    pub fn is_healthy(&self) -> bool {
+        self.ready == "ready" && self.health == "ok"
+    }
+
+    /// One-word summary: the readiness status until the proxy is ready,
+    /// then the health status.
+    pub fn status(&self) -> &str {
+        if self.ready == "ready" {
+            &self.health
+        } else {
+            &self.ready
+        }
+    }
+}
+
+/// Query the proxy at `url` (e.g. `http://127.0.0.1:8080`). `/ready` and
+/// `/health` answer 503 when not ready or unhealthy, so their bodies are
+/// read whatever the status code.
+pub async fn fetch(client: &reqwest::Client, url: &str) -> anyhow::Result<StatusReport> {
+    let url = url.trim_end_matches('/');
+    let ready: ReadyBody = get(client, &format!("{}/ready", url)).await?;
+    let health: HealthBody = get(client, &format!("{}/health", url)).await?;
+    Ok(StatusReport {
+        ready: ready.status,
+        health: health.status,
+        circuits: health.providers,
+    })
+}
+
+async fn get<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+) -> anyhow::Result<T> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("{} unreachable", url))?;
+    let status = response.status();
+    response
+        .json()
+        .await
+        .with_context(|| format!("{} returned HTTP {} without a status body", url, status))
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let closed = self
+            .circuits
+            .values()
+            .filter(|c| c.state == "closed")
+            .count();
+        write!(
+            f,
+            "{}: {}, {}/{} circuits closed",
+            self.status(),
+            if self.ready == "ready" {
+                "ready"
+            } else {
+                "not ready"
+            },
+            closed,
+            self.circuits.len()
+        )?;
+        for state in ["open", "half_open"] {
+            let providers: Vec<String> = self
+                .circuits
+                .iter()
+                .filter(|(_, c)| c.state == state)
+                .map(|(name, c)| match c.storm_cooldown_secs {
+                    Some(secs) => format!(
+                        "{} ({} failures, cool-down {}s)",
+                        name, c.failure_count, secs
+                    ),
+                    None => format!("{} ({} failures)", name, c.failure_count),
+                })
+                .collect();
+            if !providers.is_empty() {
+                write!(f, "; {}: {}", state, providers.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit(state: &str, failure_count: u32) -> CircuitStatus {
+        CircuitStatus {
+            state: state.to_string(),
+            failure_count,
+            storm_cooldown_secs: None,
+        }
+    }
+
+    #[test]
+    fn summarizes_unhealthy_circuits() {
+        let mut report = StatusReport {
+            ready: "ready".to_string(),
+            health: "ok".to_string(),
+            circuits: BTreeMap::from([
+                ("alpha".to_string(), circuit("closed", 0)),
+                ("beta".to_string(), circuit("closed", 1)),
+            ]),
+        };
+        assert!(report.is_healthy());
+        assert_eq!(report.to_string(), "ok: ready, 2/2 circuits closed");
+
+        report.health = "degraded".to_string();
+        report
+            .circuits
+            .insert("beta".to_string(), circuit("open", 3));
+        report.circuits.insert(
+            "gamma".to_string(),
+            CircuitStatus {
+                storm_cooldown_secs: Some(600),
+                ..circuit("open", 5)
+            },
+        );
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.to_string(),
+            "degraded: ready, 1/3 circuits closed; open: beta (3 failures), gamma (5 failures, cool-down 600s)"
+        );
+
+        report.ready = "warming_up".to_string();
+        assert!(report.to_string().starts_with("warming_up: not ready"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Prompt archive (`[archive]`): Brotli-compressed request messages kept
+//! for offline duplicate analysis (`arbstr analyze duplicates`).
+
+use std::io::{Read, Write};
+
+use sqlx::SqlitePool;
+
+/// Brotli quality for archived prompts; prompts are compressed on the
+/// request path, so this trades some ratio for speed.
+const QUALITY: u32 = 5;
+
+/// Brotli window size (log2).
+const WINDOW: u32 = 22;
+
+/// Compress a prompt for the archive.
+pub fn compress(prompt: &str) -> Vec<u8> {
+    let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, QUALITY, WINDOW);
+    writer
+        .write_all(prompt.as_bytes())
+        .expect("writing to a Vec cannot fail");
+    writer.into_inner()
+}
+
+/// Decompress an archived prompt; None if it is not valid Brotli or UTF-8.
+pub fn decompress(data: &[u8]) -> Option<String> {
+    let mut prompt = String::new();
+    brotli::Decompressor::new(data, 4096)
+        .read_to_string(&mut prompt)
+        .ok()?;
+    Some(prompt)
+}
+
+/// Store a request's compressed messages.
+pub async fn insert(
+    pool: &SqlitePool,
+    correlation_id: &str,
+    timestamp: &str,
+    model: &str,
+    prompt_br: &[u8],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO prompt_archive (correlation_id, timestamp, model, prompt_br)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(correlation_id)
+    .bind(timestamp)
+    .bind(model)
+    .bind(prompt_br)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// An archived prompt with what its request cost.
+#[derive(Debug, Clone)]
+pub struct ArchivedPrompt {
+    pub correlation_id: String,
+    pub model: String,
+    /// Messages JSON, decompressed.
+    pub prompt: String,
+    /// Total cost of the request's rows (a validation retry adds a second).
+    pub cost_sats: f64,
+}
+
+/// Archived prompts in `[since, until]`, oldest first. Rows that fail to
+/// decompress are skipped with a warning.
+pub async fn query_range(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+) -> Result<Vec<ArchivedPrompt>, sqlx::Error> {
+    let rows: Vec<(String, String, Vec<u8>, Option<f64>)> = sqlx::query_as(
+        "SELECT a.correlation_id, a.model, a.prompt_br,
+                (SELECT SUM(r.cost_sats) FROM requests r
+                 WHERE r.correlation_id = a.correlation_id)
+         FROM prompt_archive a
+         WHERE a.timestamp >= ? AND a.timestamp <= ?
+         ORDER BY a.timestamp ASC",
+    )
+    .bind(since)
+    .bind(until)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(correlation_id, model, prompt_br, cost_sats)| {
+            let Some(prompt) = decompress(&prompt_br) else {
+                tracing::warn!(correlation_id = %correlation_id, "Skipping unreadable archived prompt");
+                return None;
+            };
+            Some(ArchivedPrompt {
+                correlation_id,
+                model,
+                prompt,
+                cost_sats: cost_sats.unwrap_or(0.0),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_shrinks() {
+        let prompt = r#"[{"role":"user","content":"Summarize this report. "}]"#.repeat(20);
+        let compressed = compress(&prompt);
+        assert!(compressed.len() < prompt.len() / 4);
+        assert_eq!(decompress(&compressed).as_deref(), Some(prompt.as_str()));
+        assert_eq!(decompress(b"not brotli"), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! Online SQLite backups and WAL checkpointing.
+//!
+//! Backups use `VACUUM INTO`, which writes a consistent, compacted copy of
+//! the database from a single read transaction while other connections keep
+//! reading and writing. Periodic backups are rotated by file name so only
+//! the newest N remain.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::BackupConfig;
+
+/// File name prefix for rotated backups.
+const BACKUP_PREFIX: &str = "arbstr-";
+
+/// File name suffix for rotated backups.
+const BACKUP_SUFFIX: &str = ".db";
+
+/// Errors from backup operations.
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("Backup target '{0}' already exists")]
+    TargetExists(String),
+
+    #[error("Backup I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Backup database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Result of a completed backup.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupResult {
+    pub path: String,
+    pub size_bytes: u64,
+    pub duration_ms: u64,
+    /// Older backups deleted by rotation.
+    pub rotated: Vec<String>,
+}
+
+/// Result of `PRAGMA wal_checkpoint`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CheckpointResult {
+    /// 1 if the checkpoint could not complete because of concurrent activity.
+    pub busy: i64,
+    /// Frames in the WAL (-1 when not in WAL mode).
+    pub log_frames: i64,
+    /// Frames copied back into the database file.
+    pub checkpointed_frames: i64,
+}
+
+/// Run a `TRUNCATE` WAL checkpoint, folding the WAL back into the main file.
+pub async fn checkpoint(pool: &SqlitePool) -> Result<CheckpointResult, sqlx::Error> {
+    let (busy, log_frames, checkpointed_frames): (i64, i64, i64) =
+        sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(pool)
+            .await?;
+    Ok(CheckpointResult {
+        busy,
+        log_frames,
+        checkpointed_frames,
+    })
+}
+
+/// Write a consistent copy of the database to `target` with `VACUUM INTO`.
+///
+/// Fails if `target` already exists. Parent directories are created.
+pub async fn backup_to(pool: &SqlitePool, target: &Path) -> Result<u64, BackupError> {
+    if target.exists() {
+        return Err(BackupError::TargetExists(target.display().to_string()));
+    }
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(target.to_string_lossy().as_ref())
+        .execute(pool)
+        .await?;
+
+    Ok(tokio::fs::metadata(target).await?.len())
+}
+
+/// Path for a new timestamped backup inside `dir`.
+fn timestamped_path(dir: &Path) -> PathBuf {
+    dir.join(format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+        BACKUP_SUFFIX
+    ))
+}
+
+/// Delete all but the newest `keep` rotated backups in `dir`.
+///
+/// Only files named `arbstr-*.db` are considered. Timestamps in the names
+/// sort lexicographically, so name order is age order.
+pub async fn rotate(dir: &Path, keep: usize) -> Result<Vec<String>, std::io::Error> {
+    let mut backups = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX) {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let mut removed = Vec::with_capacity(excess);
+    for path in backups.into_iter().take(excess) {
+        tokio::fs::remove_file(&path).await?;
+        removed.push(path.display().to_string());
+    }
+    Ok(removed)
+}
+
+/// Write a timestamped backup into the configured directory and rotate.
+pub async fn run_backup(
+    pool: &SqlitePool,
+    config: &BackupConfig,
+) -> Result<BackupResult, BackupError> {
+    let start = std::time::Instant::now();
+    let dir = Path::new(&config.dir);
+    let target = timestamped_path(dir);
+
+    let size_bytes = backup_to(pool, &target).await?;
+    let rotated = rotate(dir, config.keep).await?;
+
+    Ok(BackupResult {
+        path: target.display().to_string(),
+        size_bytes,
+        duration_ms: start.elapsed().as_millis() as u64,
+        rotated,
+    })
+}
+
+/// Background backup task.
+///
+/// Writes a rotated backup every `interval_hours`, starting one interval
+/// after startup. Stops when the cancellation token is triggered.
+pub async fn backup_loop(
+    pool: SqlitePool,
+    config: BackupConfig,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_hours * 3600));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick completes immediately; skip it so restarts don't churn rotations.
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                match run_backup(&pool, &config).await {
+                    Ok(result) => tracing::info!(
+                        path = %result.path,
+                        size_bytes = result.size_bytes,
+                        duration_ms = result.duration_ms,
+                        rotated = result.rotated.len(),
+                        "Database backup complete"
+                    ),
+                    Err(e) => tracing::error!(error = %e, "Database backup failed"),
+                }
+            }
+            changed = cancel.changed() => {
+                if changed.is_err() || *cancel.borrow() {
+                    tracing::info!("Backup task shutting down");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn file_pool(dir: &Path) -> SqlitePool {
+        let pool = super::super::init_pool(&dir.join("source.db").display().to_string())
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO requests (correlation_id, timestamp, model, streaming, latency_ms, success) \
+             VALUES ('b-1', '2026-01-01T00:00:00Z', 'gpt-4o', 0, 10, 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("arbstr-backup-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn backup_to_copies_rows_and_refuses_overwrite() {
+        let dir = temp_dir("copy");
+        let pool = file_pool(&dir).await;
+        let target = dir.join("out").join("copy.db");
+
+        let size = backup_to(&pool, &target).await.unwrap();
+        assert!(size > 0);
+
+        let copy = SqlitePool::connect(&format!("sqlite://{}", target.display()))
+            .await
+            .unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM requests")
+            .fetch_one(&copy)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        assert!(matches!(
+            backup_to(&pool, &target).await,
+            Err(BackupError::TargetExists(_))
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn run_backup_rotates_old_files() {
+        let dir = temp_dir("rotate");
+        let pool = file_pool(&dir).await;
+        let backups = dir.join("backups");
+        std::fs::create_dir_all(&backups).unwrap();
+        for old in [
+            "arbstr-20200101T000000.000Z.db",
+            "arbstr-20200102T000000.000Z.db",
+        ] {
+            std::fs::write(backups.join(old), b"old").unwrap();
+        }
+        std::fs::write(backups.join("unrelated.txt"), b"keep me").unwrap();
+
+        let config = BackupConfig {
+            dir: backups.display().to_string(),
+            interval_hours: 24,
+            keep: 2,
+        };
+        let result = run_backup(&pool, &config).await.unwrap();
+
+        assert_eq!(result.rotated.len(), 1);
+        assert!(result.rotated[0].ends_with("arbstr-20200101T000000.000Z.db"));
+        assert!(backups.join("arbstr-20200102T000000.000Z.db").exists());
+        assert!(backups.join("unrelated.txt").exists());
+        assert!(Path::new(&result.path).exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn checkpoint_reports_wal_mode() {
+        let dir = temp_dir("checkpoint");
+        let pool = file_pool(&dir).await;
+        let result = checkpoint(&pool).await.unwrap();
+        assert_eq!(result.busy, 0);
+        assert!(result.log_frames >= 0);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,140 @@
+//! Persistence for fan-out comparisons (`POST /v1/compare`).
+
+use sqlx::SqlitePool;
+
+/// A comparison set ready for insertion.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub id: String,
+    pub timestamp: String,
+    /// The request without its targets, as JSON.
+    pub request: Option<String>,
+    pub results: Vec<ComparisonResult>,
+}
+
+/// One target's outcome within a comparison.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ComparisonResult {
+    pub model: String,
+    pub provider: Option<String>,
+    pub correlation_id: String,
+    pub success: bool,
+    pub latency_ms: i64,
+    pub cost_sats: Option<f64>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub content: Option<String>,
+    pub error_status: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+impl Comparison {
+    /// Insert the comparison and its results in a single transaction.
+    pub async fn insert(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("INSERT INTO comparisons (id, timestamp, request) VALUES (?, ?, ?)")
+            .bind(&self.id)
+            .bind(&self.timestamp)
+            .bind(self.request.as_deref())
+            .execute(&mut *tx)
+            .await?;
+
+        for (i, result) in self.results.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO comparison_results (
+                    comparison_id, position, model, provider, correlation_id, success,
+                    latency_ms, cost_sats, input_tokens, output_tokens, content,
+                    error_status, error_message
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&self.id)
+            .bind(i as i64 + 1)
+            .bind(&result.model)
+            .bind(result.provider.as_deref())
+            .bind(&result.correlation_id)
+            .bind(result.success)
+            .bind(result.latency_ms)
+            .bind(result.cost_sats)
+            .bind(result.input_tokens)
+            .bind(result.output_tokens)
+            .bind(result.content.as_deref())
+            .bind(result.error_status)
+            .bind(result.error_message.as_deref())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Load a comparison and its results (in target order) by id.
+pub async fn query_comparison(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Option<Comparison>, sqlx::Error> {
+    let row: Option<(String, Option<String>)> =
+        sqlx::query_as("SELECT timestamp, request FROM comparisons WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+    let Some((timestamp, request)) = row else {
+        return Ok(None);
+    };
+
+    let results = sqlx::query_as::<_, ComparisonResult>(
+        "SELECT model, provider, correlation_id, success, latency_ms, cost_sats, \
+         input_tokens, output_tokens, content, error_status, error_message \
+         FROM comparison_results WHERE comparison_id = ? ORDER BY position",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(Comparison {
+        id: id.to_string(),
+        timestamp,
+        request,
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn comparison_round_trips() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let result = |model: &str, success: bool| ComparisonResult {
+            model: model.to_string(),
+            provider: Some("alpha".to_string()),
+            correlation_id: format!("cmp-1-{}", model),
+            success,
+            latency_ms: 120,
+            cost_sats: success.then_some(1.5),
+            input_tokens: Some(10),
+            output_tokens: success.then_some(5),
+            content: success.then(|| "hello".to_string()),
+            error_status: (!success).then_some(502),
+            error_message: None,
+        };
+        let comparison = Comparison {
+            id: "cmp-1".to_string(),
+            timestamp: "2026-10-01T00:00:00Z".to_string(),
+            request: Some(r#"{"messages":[]}"#.to_string()),
+            results: vec![result("gpt-4o", true), result("llama-3", false)],
+        };
+        comparison.insert(&pool).await.unwrap();
+
+        let loaded = query_comparison(&pool, "cmp-1").await.unwrap().unwrap();
+        assert_eq!(loaded.timestamp, comparison.timestamp);
+        assert_eq!(loaded.request, comparison.request);
+        assert_eq!(loaded.results, comparison.results);
+
+        assert!(query_comparison(&pool, "cmp-2").await.unwrap().is_none());
+    }
+}
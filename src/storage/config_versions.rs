@@ -0,0 +1,134 @@
+//! Persistence for config versions (`config_versions`).
+//!
+//! Each row is a configuration that served traffic: one per startup whose
+//! config differs from the last one recorded, and one per change made
+//! through the admin API. Request rows carry the version active when they
+//! were dispatched.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// A recorded config version.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ConfigVersionRow {
+    pub version: i64,
+    pub created_at: String,
+    /// Hash of the config file's settings (see `/v1/about`).
+    pub config_hash: String,
+    /// `startup` or `admin`.
+    pub source: String,
+    /// Who made an admin change (`X-Arbstr-Actor`).
+    pub actor: Option<String>,
+    /// What an admin change did.
+    pub change: Option<String>,
+}
+
+/// The version to run under at startup: the latest one when it was itself
+/// recorded at startup with the same hash, otherwise a new `startup` row.
+/// Admin changes are not persisted across restarts, so a restart after
+/// one starts a new version even if the file is unchanged.
+pub async fn restore(pool: &SqlitePool, config_hash: &str, now: &str) -> Result<i64, sqlx::Error> {
+    let latest: Option<(i64, String, String)> = sqlx::query_as(
+        "SELECT version, config_hash, source FROM config_versions ORDER BY version DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if let Some((version, hash, source)) = latest {
+        if hash == config_hash && source == "startup" {
+            return Ok(version);
+        }
+    }
+    insert(pool, now, config_hash, "startup", None, None).await
+}
+
+/// Record a new version; returns its number.
+pub async fn insert(
+    pool: &SqlitePool,
+    created_at: &str,
+    config_hash: &str,
+    source: &str,
+    actor: Option<&str>,
+    change: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    Ok(sqlx::query(
+        "INSERT INTO config_versions (created_at, config_hash, source, actor, change)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(created_at)
+    .bind(config_hash)
+    .bind(source)
+    .bind(actor)
+    .bind(change)
+    .execute(pool)
+    .await?
+    .last_insert_rowid())
+}
+
+/// The most recent `limit` versions, newest first.
+pub async fn history(pool: &SqlitePool, limit: u32) -> Result<Vec<ConfigVersionRow>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT version, created_at, config_hash, source, actor, change
+         FROM config_versions ORDER BY version DESC LIMIT ?",
+    )
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn restarts_reuse_unchanged_versions() {
+        let pool = pool().await;
+        assert_eq!(
+            restore(&pool, "aaaa", "2026-01-01T00:00:00Z")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            restore(&pool, "aaaa", "2026-01-02T00:00:00Z")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            restore(&pool, "bbbb", "2026-01-03T00:00:00Z")
+                .await
+                .unwrap(),
+            2
+        );
+
+        let admin = insert(
+            &pool,
+            "2026-01-04T00:00:00Z",
+            "bbbb",
+            "admin",
+            Some("ops"),
+            Some("provider alpha: enabled=false"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(admin, 3);
+        // The admin change is gone after a restart
+        assert_eq!(
+            restore(&pool, "bbbb", "2026-01-05T00:00:00Z")
+                .await
+                .unwrap(),
+            4
+        );
+
+        let rows = history(&pool, 2).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].version, 4);
+        assert_eq!(rows[1].actor.as_deref(), Some("ops"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Persistence for scheduled evaluation runs (`[evaluation]`).
+
+use sqlx::SqlitePool;
+
+/// One prompt's result for one provider/model pair.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct EvaluationRow {
+    pub provider: String,
+    pub model: String,
+    pub prompt: String,
+    pub success: bool,
+    pub status: Option<i64>,
+    pub latency_ms: i64,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cost_sats: Option<f64>,
+    pub passed: Option<bool>,
+    pub score: f64,
+}
+
+/// Insert a run's results in a single transaction.
+pub async fn insert_run(
+    pool: &SqlitePool,
+    run_id: &str,
+    rows: &[EvaluationRow],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for row in rows {
+        sqlx::query(
+            "INSERT INTO evaluations (
+                run_id, provider, model, prompt, success, status, latency_ms,
+                input_tokens, output_tokens, cost_sats, passed, score
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(run_id)
+        .bind(&row.provider)
+        .bind(&row.model)
+        .bind(&row.prompt)
+        .bind(row.success)
+        .bind(row.status)
+        .bind(row.latency_ms)
+        .bind(row.input_tokens)
+        .bind(row.output_tokens)
+        .bind(row.cost_sats)
+        .bind(row.passed)
+        .bind(row.score)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Mean score per (provider, model) in the latest run.
+pub async fn latest_scores(pool: &SqlitePool) -> Result<Vec<(String, String, f64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT provider, model, AVG(score) FROM evaluations \
+         WHERE run_id = (SELECT MAX(run_id) FROM evaluations) \
+         GROUP BY provider, model ORDER BY provider, model",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(provider: &str, prompt: &str, score: f64) -> EvaluationRow {
+        EvaluationRow {
+            provider: provider.to_string(),
+            model: "gpt-4o".to_string(),
+            prompt: prompt.to_string(),
+            success: true,
+            status: Some(200),
+            latency_ms: 100,
+            input_tokens: Some(10),
+            output_tokens: Some(2),
+            cost_sats: Some(0.5),
+            passed: Some(score == 1.0),
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn latest_scores_use_only_the_newest_run() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        insert_run(&pool, "2026-10-01T03:00:00.000Z", &[row("alpha", "a", 1.0)])
+            .await
+            .unwrap();
+        insert_run(
+            &pool,
+            "2026-10-02T03:00:00.000Z",
+            &[
+                row("alpha", "a", 1.0),
+                row("alpha", "b", 0.0),
+                row("beta", "a", 1.0),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let scores = latest_scores(&pool).await.unwrap();
+        assert_eq!(
+            scores,
+            vec![
+                ("alpha".to_string(), "gpt-4o".to_string(), 0.5),
+                ("beta".to_string(), "gpt-4o".to_string(), 1.0),
+            ]
+        );
+    }
+}
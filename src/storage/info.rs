@@ -0,0 +1,103 @@
+//! Database statistics for storage observability.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// Oldest and newest request timestamps.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RequestSpan {
+    pub oldest: Option<String>,
+    pub newest: Option<String>,
+}
+
+/// Most recently applied embedded migration.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MigrationInfo {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Row count for every user table, keyed by table name.
+///
+/// Table names come from `sqlite_master`, so they are quoted rather than
+/// bound when interpolated into the COUNT query.
+pub async fn table_row_counts(pool: &SqlitePool) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+    let tables: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+         ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut counts = BTreeMap::new();
+    for (name,) in tables {
+        let sql = format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\""));
+        let count: i64 = sqlx::query_scalar(&sql).fetch_one(pool).await?;
+        counts.insert(name, count);
+    }
+    Ok(counts)
+}
+
+/// Oldest and newest request timestamps (both NULL when empty).
+pub async fn request_span(pool: &SqlitePool) -> Result<RequestSpan, sqlx::Error> {
+    sqlx::query_as::<_, RequestSpan>(
+        "SELECT MIN(timestamp) as oldest, MAX(timestamp) as newest FROM requests",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Latest successfully applied migration, if any.
+pub async fn last_migration(pool: &SqlitePool) -> Result<Option<MigrationInfo>, sqlx::Error> {
+    sqlx::query_as::<_, MigrationInfo>(
+        "SELECT version, description FROM _sqlx_migrations WHERE success = 1 \
+         ORDER BY version DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Size in bytes of a file on disk, or None if it does not exist.
+pub async fn file_size(path: &str) -> Option<u64> {
+    tokio::fs::metadata(path).await.ok().map(|m| m.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn counts_span_and_migration() {
+        let pool = test_pool().await;
+        for ts in ["2026-01-02T00:00:00Z", "2026-01-01T00:00:00Z"] {
+            sqlx::query(
+                "INSERT INTO requests (correlation_id, timestamp, model, streaming, latency_ms, success) \
+                 VALUES (?, ?, 'gpt-4o', 0, 10, 1)",
+            )
+            .bind(ts)
+            .bind(ts)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let counts = table_row_counts(&pool).await.unwrap();
+        assert_eq!(counts["requests"], 2);
+        assert_eq!(counts["pending_settlements"], 0);
+
+        let span = request_span(&pool).await.unwrap();
+        assert_eq!(span.oldest.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert_eq!(span.newest.as_deref(), Some("2026-01-02T00:00:00Z"));
+
+        let migration = last_migration(&pool).await.unwrap().unwrap();
+        assert!(migration.version > 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Persistence for the prepaid provider spend ledger.
+//!
+//! Credits live in `provider_ledger`; spend is summed from `requests`, so a
+//! restart picks up where the running balance left off. Changing a
+//! provider's configured `balance_sats` opens a fresh ledger. Providers with
+//! a `balance_reset` schedule restore only the current period (and, for
+//! rollover, the one before it) or the rolling window.
+
+use sqlx::SqlitePool;
+
+/// Credits and spend since a provider's ledger was opened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerTotals {
+    pub opened_at: String,
+    pub opening_sats: f64,
+    pub topped_up_sats: f64,
+    pub spent_sats: f64,
+}
+
+/// Load a provider's ledger, opening a new one at `now` when none exists or
+/// the configured starting balance has changed.
+pub async fn restore(
+    pool: &SqlitePool,
+    provider: &str,
+    opening_sats: f64,
+    now: &str,
+) -> Result<LedgerTotals, sqlx::Error> {
+    let open: Option<(i64, String, f64)> = sqlx::query_as(
+        "SELECT id, timestamp, amount_sats FROM provider_ledger \
+         WHERE provider = ? AND kind = 'open' ORDER BY id DESC LIMIT 1",
+    )
+    .bind(provider)
+    .fetch_optional(pool)
+    .await?;
+
+    let (open_id, opened_at) = match open {
+        Some((id, timestamp, amount)) if amount == opening_sats => (id, timestamp),
+        _ => {
+            let id = sqlx::query(
+                "INSERT INTO provider_ledger (provider, timestamp, kind, amount_sats) \
+                 VALUES (?, ?, 'open', ?)",
+            )
+            .bind(provider)
+            .bind(now)
+            .bind(opening_sats)
+            .execute(pool)
+            .await?
+            .last_insert_rowid();
+            (id, now.to_string())
+        }
+    };
+
+    let topped_up_sats: f64 = sqlx::query_scalar(
+        "SELECT TOTAL(amount_sats) FROM provider_ledger \
+         WHERE provider = ? AND kind = 'topup' AND id > ?",
+    )
+    .bind(provider)
+    .bind(open_id)
+    .fetch_one(pool)
+    .await?;
+
+    let spent_sats: f64 = sqlx::query_scalar(
+        "SELECT TOTAL(cost_sats) FROM requests WHERE provider = ? AND timestamp >= ?",
+    )
+    .bind(provider)
+    .bind(&opened_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(LedgerTotals {
+        opened_at,
+        opening_sats,
+        topped_up_sats,
+        spent_sats,
+    })
+}
+
+/// Top-ups and spend of `provider` in `[since, until)`.
+pub async fn totals_between(
+    pool: &SqlitePool,
+    provider: &str,
+    since: &str,
+    until: &str,
+) -> Result<(f64, f64), sqlx::Error> {
+    let topped_up_sats: f64 = sqlx::query_scalar(
+        "SELECT TOTAL(amount_sats) FROM provider_ledger \
+         WHERE provider = ? AND kind = 'topup' AND timestamp >= ? AND timestamp < ?",
+    )
+    .bind(provider)
+    .bind(since)
+    .bind(until)
+    .fetch_one(pool)
+    .await?;
+
+    let spent_sats: f64 = sqlx::query_scalar(
+        "SELECT TOTAL(cost_sats) FROM requests \
+         WHERE provider = ? AND timestamp >= ? AND timestamp < ?",
+    )
+    .bind(provider)
+    .bind(since)
+    .bind(until)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((topped_up_sats, spent_sats))
+}
+
+/// Top-ups and spend of `provider` since `since`, summed per minute
+/// (`YYYY-MM-DDTHH:MM`), oldest first.
+pub async fn activity_by_minute(
+    pool: &SqlitePool,
+    provider: &str,
+    since: &str,
+) -> Result<Vec<(String, f64, f64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT minute, TOTAL(topped_up), TOTAL(spent) FROM ( \
+           SELECT substr(timestamp, 1, 16) AS minute, amount_sats AS topped_up, 0 AS spent \
+           FROM provider_ledger WHERE provider = ? AND kind = 'topup' AND timestamp >= ? \
+           UNION ALL \
+           SELECT substr(timestamp, 1, 16), 0, cost_sats \
+           FROM requests WHERE provider = ? AND timestamp >= ? \
+         ) GROUP BY minute ORDER BY minute",
+    )
+    .bind(provider)
+    .bind(since)
+    .bind(provider)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+}
+
+/// Record a manual top-up.
+pub async fn insert_topup(
+    pool: &SqlitePool,
+    provider: &str,
+    amount_sats: f64,
+    note: Option<&str>,
+    timestamp: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO provider_ledger (provider, timestamp, kind, amount_sats, note) \
+         VALUES (?, ?, 'topup', ?, ?)",
+    )
+    .bind(provider)
+    .bind(timestamp)
+    .bind(amount_sats)
+    .bind(note)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory;
+    use crate::storage::RequestLog;
+
+    fn log(id: u32, provider: &str, timestamp: &str, cost: f64) -> RequestLog {
+        RequestLog {
+            correlation_id: format!("ledger-{}", id),
+            timestamp: timestamp.to_string(),
+            model: "gpt-4o".to_string(),
+            provider: Some(provider.to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: Some(10),
+            output_tokens: Some(20),
+            cost_sats: Some(cost),
+            provider_cost_sats: None,
+            latency_ms: 5,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn restore_sums_spend_and_topups_since_open() {
+        let pool = memory::init_pool().await.unwrap();
+        let t0 = "2026-01-01T00:00:00Z";
+
+        let totals = restore(&pool, "alpha", 100.0, t0).await.unwrap();
+        assert_eq!(totals.opened_at, t0);
+        assert_eq!(totals.spent_sats, 0.0);
+
+        log(1, "alpha", "2025-12-31T23:59:59Z", 50.0)
+            .insert(&pool)
+            .await
+            .unwrap();
+        log(2, "alpha", "2026-01-01T01:00:00Z", 7.5)
+            .insert(&pool)
+            .await
+            .unwrap();
+        log(3, "beta", "2026-01-01T01:00:00Z", 3.0)
+            .insert(&pool)
+            .await
+            .unwrap();
+        insert_topup(&pool, "alpha", 20.0, Some("cashu"), "2026-01-01T02:00:00Z")
+            .await
+            .unwrap();
+
+        // Same configured balance: the existing ledger is kept
+        let totals = restore(&pool, "alpha", 100.0, "2026-01-02T00:00:00Z")
+            .await
+            .unwrap();
+        assert_eq!(totals.opened_at, t0);
+        assert_eq!(totals.topped_up_sats, 20.0);
+        assert_eq!(totals.spent_sats, 7.5);
+
+        // New configured balance: a fresh ledger without earlier top-ups
+        let t1 = "2026-01-03T00:00:00Z";
+        let totals = restore(&pool, "alpha", 500.0, t1).await.unwrap();
+        assert_eq!(totals.opened_at, t1);
+        assert_eq!(totals.opening_sats, 500.0);
+        assert_eq!(totals.topped_up_sats, 0.0);
+        assert_eq!(totals.spent_sats, 0.0);
+    }
+
+    #[tokio::test]
+    async fn period_totals_and_minute_activity() {
+        let pool = memory::init_pool().await.unwrap();
+        log(1, "alpha", "2026-03-07T23:59:59Z", 50.0)
+            .insert(&pool)
+            .await
+            .unwrap();
+        log(2, "alpha", "2026-03-08T00:00:00Z", 7.5)
+            .insert(&pool)
+            .await
+            .unwrap();
+        log(3, "alpha", "2026-03-08T00:00:40Z", 2.5)
+            .insert(&pool)
+            .await
+            .unwrap();
+        log(4, "beta", "2026-03-08T00:00:10Z", 3.0)
+            .insert(&pool)
+            .await
+            .unwrap();
+        insert_topup(&pool, "alpha", 20.0, None, "2026-03-08T00:01:00Z")
+            .await
+            .unwrap();
+
+        // Half-open: the boundary second belongs to the later period
+        let (topped_up, spent) = totals_between(
+            &pool,
+            "alpha",
+            "2026-03-07T00:00:00Z",
+            "2026-03-08T00:00:00Z",
+        )
+        .await
+        .unwrap();
+        assert_eq!((topped_up, spent), (0.0, 50.0));
+        let (topped_up, spent) = totals_between(
+            &pool,
+            "alpha",
+            "2026-03-08T00:00:00Z",
+            "2026-03-09T00:00:00Z",
+        )
+        .await
+        .unwrap();
+        assert_eq!((topped_up, spent), (20.0, 10.0));
+
+        let activity = activity_by_minute(&pool, "alpha", "2026-03-08T00:00:00Z")
+            .await
+            .unwrap();
+        assert_eq!(
+            activity,
+            vec![
+                ("2026-03-08T00:00".to_string(), 0.0, 10.0),
+                ("2026-03-08T00:01".to_string(), 20.0, 0.0),
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Request logging data types and database operations.
 
+use serde::Serialize;
 use sqlx::SqlitePool;
 
 /// A completed request log entry ready for database insertion.
@@ -19,22 +20,65 @@ pub struct RequestLog {
     pub latency_ms: i64,
     pub success: bool,
     pub error_status: Option<u16>,
+    /// Failure classification (`ProviderErrorKind::as_str`), when known.
+    pub error_type: Option<String>,
     pub error_message: Option<String>,
     pub complexity_score: Option<f64>,
     pub tier: Option<String>,
+    /// Upstream finish_reason (for streams, filled in on completion).
+    pub finish_reason: Option<String>,
+    /// Cost allocation tags from the `X-Arbstr-Tags` header.
+    pub tags: Vec<(String, String)>,
+    /// Failed upstream attempts (retries and fallbacks), in order.
+    pub attempts: Vec<AttemptLog>,
+    /// Circuit breaker states when the request was routed (JSON object).
+    pub circuit_snapshot: Option<String>,
+    /// W3C trace ID (continued from the client's `traceparent` or generated).
+    pub trace_id: Option<String>,
+    /// Client `x-request-id` (defaults to the correlation ID).
+    pub client_request_id: Option<String>,
+    /// Currency of `fiat_rate` (`[currency] code`).
+    pub fiat_currency: Option<String>,
+    /// Price of one bitcoin in `fiat_currency` when the request was logged.
+    pub fiat_rate: Option<f64>,
+    /// SHA-256 (hex) of the request body, for receipts.
+    pub request_sha256: Option<String>,
+    /// Config version active when the request was dispatched.
+    pub config_version: Option<i64>,
+    /// Client conversation the request belongs to.
+    pub conversation_id: Option<String>,
+}
+
+/// A failed upstream attempt, written to `request_attempts`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttemptLog {
+    pub provider: String,
+    pub status: u16,
+    /// Failure classification (`ProviderErrorKind::as_str`).
+    pub error_type: String,
+    /// Backoff waited before the attempt.
+    pub backoff_ms: u64,
+    /// When the attempt was sent (RFC 3339).
+    pub timestamp: String,
 }
 
 impl RequestLog {
-    /// Insert this log entry into the database.
+    /// Insert this log entry (and its tags and attempts) into the database.
+    ///
+    /// The request row, its tag rows and its attempt rows are written in a
+    /// single transaction.
     pub async fn insert(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
         sqlx::query(
             "INSERT INTO requests (
                 correlation_id, timestamp, model, provider, policy,
                 streaming, input_tokens, output_tokens,
                 cost_sats, provider_cost_sats,
-                latency_ms, success, error_status, error_message,
-                complexity_score, tier
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                latency_ms, success, error_status, error_type, error_message,
+                complexity_score, tier, finish_reason,
+                trace_id, client_request_id, fiat_currency, fiat_rate, circuit_snapshot,
+                request_sha256, config_version, conversation_id
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&self.correlation_id)
         .bind(&self.timestamp)
@@ -49,11 +93,49 @@ impl RequestLog {
         .bind(self.latency_ms)
         .bind(self.success)
         .bind(self.error_status.map(|v| v as i32))
+        .bind(self.error_type.as_deref())
         .bind(self.error_message.as_deref())
         .bind(self.complexity_score)
         .bind(self.tier.as_deref())
-        .execute(pool)
+        .bind(self.finish_reason.as_deref())
+        .bind(self.trace_id.as_deref())
+        .bind(self.client_request_id.as_deref())
+        .bind(self.fiat_currency.as_deref())
+        .bind(self.fiat_rate)
+        .bind(self.circuit_snapshot.as_deref())
+        .bind(self.request_sha256.as_deref())
+        .bind(self.config_version)
+        .bind(self.conversation_id.as_deref())
+        .execute(&mut *tx)
         .await?;
+
+        for (key, value) in &self.tags {
+            sqlx::query("INSERT INTO request_tags (correlation_id, key, value) VALUES (?, ?, ?)")
+                .bind(&self.correlation_id)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO request_attempts (
+                    correlation_id, attempt, provider, status, error_type, backoff_ms, timestamp
+                ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&self.correlation_id)
+            .bind(i as i64 + 1)
+            .bind(&attempt.provider)
+            .bind(attempt.status as i64)
+            .bind(&attempt.error_type)
+            .bind(attempt.backoff_ms as i64)
+            .bind(&attempt.timestamp)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 }
@@ -148,7 +230,8 @@ pub fn spawn_usage_update(
 /// Update an existing request log entry with post-stream completion data.
 ///
 /// Writes input_tokens, output_tokens, cost_sats, stream_duration_ms,
-/// success, and error_message to the row matching the given correlation_id.
+/// success, error_message, finish_reason, and tokens_per_second to the row
+/// matching the given correlation_id.
 /// Returns the number of rows affected.
 #[allow(clippy::too_many_arguments)]
 pub async fn update_stream_completion(
@@ -162,9 +245,11 @@ pub async fn update_stream_completion(
     error_message: Option<&str>,
     complexity_score: Option<f64>,
     tier: Option<&str>,
+    finish_reason: Option<&str>,
+    tokens_per_second: Option<f64>,
 ) -> Result<u64, sqlx::Error> {
     let result = sqlx::query(
-        "UPDATE requests SET input_tokens = ?, output_tokens = ?, cost_sats = ?, stream_duration_ms = ?, success = ?, error_message = ?, complexity_score = ?, tier = ? WHERE correlation_id = ?",
+        "UPDATE requests SET input_tokens = ?, output_tokens = ?, cost_sats = ?, stream_duration_ms = ?, success = ?, error_message = ?, complexity_score = ?, tier = ?, finish_reason = ?, tokens_per_second = ? WHERE correlation_id = ?",
     )
     .bind(input_tokens.map(|v| v as i64))
     .bind(output_tokens.map(|v| v as i64))
@@ -174,12 +259,28 @@ pub async fn update_stream_completion(
     .bind(error_message)
     .bind(complexity_score)
     .bind(tier)
+    .bind(finish_reason)
+    .bind(tokens_per_second)
     .bind(correlation_id)
     .execute(pool)
     .await?;
     Ok(result.rows_affected())
 }
 
+/// Clear trace, client request, and conversation IDs from rows logged
+/// before `before` (RFC 3339). Returns the number of rows changed.
+pub async fn clear_correlation_ids(pool: &SqlitePool, before: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE requests SET trace_id = NULL, client_request_id = NULL, conversation_id = NULL \
+         WHERE timestamp < ? \
+         AND (trace_id IS NOT NULL OR client_request_id IS NOT NULL OR conversation_id IS NOT NULL)",
+    )
+    .bind(before)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
 /// Spawn a fire-and-forget database stream completion update.
 ///
 /// Warns if the update affects zero rows (row not found) or fails.
@@ -196,6 +297,8 @@ pub fn spawn_stream_completion_update(
     error_message: Option<String>,
     complexity_score: Option<f64>,
     tier: Option<String>,
+    finish_reason: Option<String>,
+    tokens_per_second: Option<f64>,
 ) {
     let pool = pool.clone();
     tokio::spawn(async move {
@@ -210,6 +313,8 @@ pub fn spawn_stream_completion_update(
             error_message.as_deref(),
             complexity_score,
             tier.as_deref(),
+            finish_reason.as_deref(),
+            tokens_per_second,
         )
         .await
         {
@@ -240,6 +345,17 @@ pub fn spawn_stream_completion_update(
 mod tests {
     use super::*;
 
+    /// Columns written by `update_stream_completion`, in SELECT order.
+    type StreamCompletionRow = (
+        Option<i64>,
+        Option<i64>,
+        Option<f64>,
+        Option<i64>,
+        bool,
+        Option<String>,
+        Option<String>,
+    );
+
     /// Helper: create an in-memory SQLite pool with migrations applied.
     async fn test_pool() -> SqlitePool {
         let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
@@ -247,9 +363,9 @@ mod tests {
         pool
     }
 
-    /// Helper: insert a test row and return its correlation_id.
-    async fn insert_test_row(pool: &SqlitePool, correlation_id: &str) {
-        let log = RequestLog {
+    /// Helper: a successful streaming row with no usage yet.
+    fn test_log(correlation_id: &str) -> RequestLog {
+        RequestLog {
             correlation_id: correlation_id.to_string(),
             timestamp: "2026-01-01T00:00:00Z".to_string(),
             model: "gpt-4o".to_string(),
@@ -263,11 +379,166 @@ mod tests {
             latency_ms: 100,
             success: true,
             error_status: None,
+            error_type: None,
             error_message: None,
             complexity_score: None,
             tier: None,
+            finish_reason: None,
+            tags: Vec::new(),
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        }
+    }
+
+    /// Helper: insert a test row with the given correlation_id.
+    async fn insert_test_row(pool: &SqlitePool, correlation_id: &str) {
+        test_log(correlation_id).insert(pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn insert_writes_tags() {
+        let pool = test_pool().await;
+        let log = RequestLog {
+            correlation_id: "test-tags-001".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: Some("test-provider".to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: None,
+            output_tokens: None,
+            cost_sats: None,
+            provider_cost_sats: None,
+            latency_ms: 100,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![
+                ("team".to_string(), "search".to_string()),
+                ("env".to_string(), "prod".to_string()),
+            ],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
         };
-        log.insert(pool).await.unwrap();
+        log.insert(&pool).await.unwrap();
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT key, value FROM request_tags WHERE correlation_id = ? ORDER BY key",
+        )
+        .bind("test-tags-001")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("team".to_string(), "search".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_writes_trace_ids() {
+        let pool = test_pool().await;
+        let log = RequestLog {
+            correlation_id: "test-trace-001".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: None,
+            policy: None,
+            streaming: false,
+            input_tokens: None,
+            output_tokens: None,
+            cost_sats: None,
+            provider_cost_sats: None,
+            latency_ms: 100,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: Vec::new(),
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string()),
+            client_request_id: Some("client-req-9".to_string()),
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        };
+        log.insert(&pool).await.unwrap();
+
+        let row: (Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT trace_id, client_request_id FROM requests WHERE correlation_id = ?",
+        )
+        .bind("test-trace-001")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            row,
+            (
+                Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string()),
+                Some("client-req-9".to_string())
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_writes_attempts_in_order() {
+        let pool = test_pool().await;
+        let attempt = |provider: &str, status: u16, backoff_ms: u64| AttemptLog {
+            provider: provider.to_string(),
+            status,
+            error_type: "5xx".to_string(),
+            backoff_ms,
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let log = RequestLog {
+            attempts: vec![attempt("alpha", 503, 0), attempt("beta", 502, 1000)],
+            ..test_log("test-attempts-001")
+        };
+        log.insert(&pool).await.unwrap();
+
+        let rows: Vec<(i64, String, i64, i64)> = sqlx::query_as(
+            "SELECT attempt, provider, status, backoff_ms FROM request_attempts \
+             WHERE correlation_id = ? ORDER BY id",
+        )
+        .bind("test-attempts-001")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (1, "alpha".to_string(), 503, 0),
+                (2, "beta".to_string(), 502, 1000),
+            ]
+        );
     }
 
     #[tokio::test]
@@ -348,15 +619,17 @@ mod tests {
             None,
             None,
             None,
+            Some("stop"),
+            Some(85.5),
         )
         .await
         .unwrap();
         assert_eq!(rows, 1);
 
-        // Verify all 6 columns were set correctly
-        let row: (Option<i64>, Option<i64>, Option<f64>, Option<i64>, bool, Option<String>) =
+        // Verify all 7 columns were set correctly
+        let row: StreamCompletionRow =
             sqlx::query_as(
-                "SELECT input_tokens, output_tokens, cost_sats, stream_duration_ms, success, error_message FROM requests WHERE correlation_id = ?",
+                "SELECT input_tokens, output_tokens, cost_sats, stream_duration_ms, success, error_message, finish_reason FROM requests WHERE correlation_id = ?",
             )
             .bind(cid)
             .fetch_one(&pool)
@@ -369,6 +642,15 @@ mod tests {
         assert_eq!(row.3, Some(2500));
         assert!(row.4);
         assert!(row.5.is_none());
+        assert_eq!(row.6.as_deref(), Some("stop"));
+
+        let (tps,): (Option<f64>,) =
+            sqlx::query_as("SELECT tokens_per_second FROM requests WHERE correlation_id = ?")
+                .bind(cid)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tps, Some(85.5));
     }
 
     #[tokio::test]
@@ -388,14 +670,16 @@ mod tests {
             Some("client_disconnected"),
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
         assert_eq!(rows, 1);
 
-        let row: (Option<i64>, Option<i64>, Option<f64>, Option<i64>, bool, Option<String>) =
+        let row: StreamCompletionRow =
             sqlx::query_as(
-                "SELECT input_tokens, output_tokens, cost_sats, stream_duration_ms, success, error_message FROM requests WHERE correlation_id = ?",
+                "SELECT input_tokens, output_tokens, cost_sats, stream_duration_ms, success, error_message, finish_reason FROM requests WHERE correlation_id = ?",
             )
             .bind(cid)
             .fetch_one(&pool)
@@ -408,5 +692,6 @@ mod tests {
         assert_eq!(row.3, Some(1800));
         assert!(row.4);
         assert_eq!(row.5.as_deref(), Some("client_disconnected"));
+        assert!(row.6.is_none());
     }
 }
@@ -2,6 +2,33 @@
 
 use sqlx::SqlitePool;
 
+use super::stats::TAG_FILTER_CLAUSE;
+
+/// Columns selected into a [`LogRow`].
+const LOG_COLUMNS: &str =
+    "id, timestamp, model, provider, streaming, input_tokens, output_tokens, \
+     cost_sats, latency_ms, stream_duration_ms, tokens_per_second, success, error_status, \
+     error_message, finish_reason, trace_id, client_request_id, fiat_currency, fiat_rate, \
+     config_version, conversation_id";
+
+/// Columns selected into a [`DetailRow`] besides [`LOG_COLUMNS`].
+const DETAIL_COLUMNS: &str =
+    "correlation_id, policy, tier, complexity_score, error_type, circuit_snapshot, request_sha256";
+
+/// Case-insensitive substring match on `error_message`; bind with
+/// [`like_pattern`].
+const ERROR_CONTAINS_CLAUSE: &str = " AND error_message LIKE ? ESCAPE '\\'";
+
+/// Wrap `needle` in `%` wildcards, escaping LIKE metacharacters so it
+/// matches literally.
+fn like_pattern(needle: &str) -> String {
+    let escaped = needle
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
 /// A single request log row from the database.
 #[derive(Debug, sqlx::FromRow)]
 pub struct LogRow {
@@ -15,15 +42,26 @@ pub struct LogRow {
     pub cost_sats: Option<f64>,
     pub latency_ms: i64,
     pub stream_duration_ms: Option<i64>,
+    pub tokens_per_second: Option<f64>,
     pub success: bool,
     pub error_status: Option<i32>,
     pub error_message: Option<String>,
+    pub finish_reason: Option<String>,
+    pub trace_id: Option<String>,
+    pub client_request_id: Option<String>,
+    pub fiat_currency: Option<String>,
+    pub fiat_rate: Option<f64>,
+    pub config_version: Option<i64>,
+    pub conversation_id: Option<String>,
 }
 
 /// Count request logs matching the given filters.
 ///
 /// Builds a dynamic WHERE clause with time range and optional model, provider,
-/// success, and streaming filters. All string comparisons are case-insensitive.
+/// success, streaming, tag, trace ID, error message and error status filters.
+/// Model, provider and error message comparisons are case-insensitive;
+/// `status` is an inclusive range of `error_status` codes.
+#[allow(clippy::too_many_arguments)]
 pub async fn count_logs(
     pool: &SqlitePool,
     since: &str,
@@ -32,6 +70,10 @@ pub async fn count_logs(
     provider: Option<&str>,
     success: Option<bool>,
     streaming: Option<bool>,
+    tag: Option<(&str, &str)>,
+    trace_id: Option<&str>,
+    error_contains: Option<&str>,
+    status: Option<(i32, i32)>,
 ) -> Result<i64, sqlx::Error> {
     let mut sql =
         String::from("SELECT COUNT(*) FROM requests WHERE timestamp >= ? AND timestamp <= ?");
@@ -48,6 +90,18 @@ pub async fn count_logs(
     if streaming.is_some() {
         sql.push_str(" AND streaming = ?");
     }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    if trace_id.is_some() {
+        sql.push_str(" AND trace_id = ?");
+    }
+    if error_contains.is_some() {
+        sql.push_str(ERROR_CONTAINS_CLAUSE);
+    }
+    if status.is_some() {
+        sql.push_str(" AND error_status BETWEEN ? AND ?");
+    }
 
     let mut query = sqlx::query_scalar::<_, i64>(&sql).bind(since).bind(until);
 
@@ -63,6 +117,18 @@ pub async fn count_logs(
     if let Some(st) = streaming {
         query = query.bind(st);
     }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+    if let Some(t) = trace_id {
+        query = query.bind(t);
+    }
+    if let Some(e) = error_contains {
+        query = query.bind(like_pattern(e));
+    }
+    if let Some((low, high)) = status {
+        query = query.bind(low).bind(high);
+    }
 
     query.fetch_one(pool).await
 }
@@ -81,15 +147,18 @@ pub async fn query_logs(
     provider: Option<&str>,
     success: Option<bool>,
     streaming: Option<bool>,
+    tag: Option<(&str, &str)>,
+    trace_id: Option<&str>,
+    error_contains: Option<&str>,
+    status: Option<(i32, i32)>,
     sort_column: &str,
     sort_direction: &str,
     limit: u32,
     offset: u32,
 ) -> Result<Vec<LogRow>, sqlx::Error> {
-    let mut sql = String::from(
-        "SELECT id, timestamp, model, provider, streaming, input_tokens, output_tokens, \
-         cost_sats, latency_ms, stream_duration_ms, success, error_status, error_message \
-         FROM requests WHERE timestamp >= ? AND timestamp <= ?",
+    let mut sql = format!(
+        "SELECT {} FROM requests WHERE timestamp >= ? AND timestamp <= ?",
+        LOG_COLUMNS
     );
 
     if model.is_some() {
@@ -104,6 +173,18 @@ pub async fn query_logs(
     if streaming.is_some() {
         sql.push_str(" AND streaming = ?");
     }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    if trace_id.is_some() {
+        sql.push_str(" AND trace_id = ?");
+    }
+    if error_contains.is_some() {
+        sql.push_str(ERROR_CONTAINS_CLAUSE);
+    }
+    if status.is_some() {
+        sql.push_str(" AND error_status BETWEEN ? AND ?");
+    }
 
     // sort_column and sort_direction are validated &'static str -- safe to interpolate
     sql.push_str(&format!(" ORDER BY {} {}", sort_column, sort_direction));
@@ -123,8 +204,124 @@ pub async fn query_logs(
     if let Some(st) = streaming {
         query = query.bind(st);
     }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+    if let Some(t) = trace_id {
+        query = query.bind(t);
+    }
+    if let Some(e) = error_contains {
+        query = query.bind(like_pattern(e));
+    }
+    if let Some((low, high)) = status {
+        query = query.bind(low).bind(high);
+    }
 
     query = query.bind(limit as i64).bind(offset as i64);
 
     query.fetch_all(pool).await
 }
+
+/// A failed upstream attempt behind a request.
+#[derive(Debug, sqlx::FromRow)]
+pub struct AttemptRow {
+    pub attempt: i64,
+    pub provider: String,
+    pub status: i64,
+    pub error_type: String,
+    pub backoff_ms: i64,
+    pub timestamp: String,
+}
+
+/// A request log row with the extra columns shown by the detail view.
+#[derive(Debug, sqlx::FromRow)]
+pub struct DetailRow {
+    #[sqlx(flatten)]
+    pub log: LogRow,
+    pub correlation_id: String,
+    pub policy: Option<String>,
+    pub tier: Option<String>,
+    pub complexity_score: Option<f64>,
+    pub error_type: Option<String>,
+    pub circuit_snapshot: Option<String>,
+    pub request_sha256: Option<String>,
+}
+
+/// Fetch the detail row for a correlation ID.
+///
+/// When several rows share the ID (e.g. a rejected response and its
+/// re-ask), the last one logged is returned.
+pub async fn query_detail(
+    pool: &SqlitePool,
+    correlation_id: &str,
+) -> Result<Option<DetailRow>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {}, {} FROM requests WHERE correlation_id = ? ORDER BY id DESC LIMIT 1",
+        LOG_COLUMNS, DETAIL_COLUMNS
+    );
+    sqlx::query_as::<_, DetailRow>(&sql)
+        .bind(correlation_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Fetch the detail row for a request row id.
+pub async fn query_detail_by_id(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<Option<DetailRow>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {}, {} FROM requests WHERE id = ?",
+        LOG_COLUMNS, DETAIL_COLUMNS
+    );
+    sqlx::query_as::<_, DetailRow>(&sql)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Fetch the failed attempts behind a correlation ID, in the order they
+/// were made.
+///
+/// Every row logged under the ID carries the attempts (e.g. a rejected
+/// response and its re-ask), so duplicates are folded with `DISTINCT`.
+pub async fn query_attempts(
+    pool: &SqlitePool,
+    correlation_id: &str,
+) -> Result<Vec<AttemptRow>, sqlx::Error> {
+    sqlx::query_as::<_, AttemptRow>(
+        "SELECT DISTINCT attempt, provider, status, error_type, backoff_ms, timestamp \
+         FROM request_attempts WHERE correlation_id = ? ORDER BY attempt",
+    )
+    .bind(correlation_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Fetch the tags for a set of request rows, keyed by request row id.
+///
+/// Returns `(id, key, value)` tuples ordered by id then key. An empty `ids`
+/// slice returns an empty result without querying.
+pub async fn query_tags_for_ids(
+    pool: &SqlitePool,
+    ids: &[i64],
+) -> Result<Vec<(i64, String, String)>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let sql = format!(
+        "SELECT r.id, t.key, t.value FROM request_tags t \
+         JOIN requests r ON r.correlation_id = t.correlation_id \
+         WHERE r.id IN ({}) ORDER BY r.id, t.key",
+        placeholders
+    );
+
+    let mut query = sqlx::query_as::<_, (i64, String, String)>(&sql);
+    for id in ids {
+        query = query.bind(id);
+    }
+
+    query.fetch_all(pool).await
+}
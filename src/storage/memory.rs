@@ -0,0 +1,235 @@
+//! In-memory storage backend (`[database] backend = "memory"`).
+//!
+//! An in-memory SQLite database with the normal schema, so every stats and
+//! log query works unchanged. It lives on a single connection that is never
+//! recycled: SQLite drops an in-memory database with its last connection,
+//! and separate connections would each see their own empty database. Rows
+//! beyond `database.max_rows` are pruned oldest first, along with the
+//! archive, receipt, comparison, and evaluation rows from the same period.
+
+use std::str::FromStr;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+/// Inserts between prunes, so the table overshoots `max_rows` by at most this.
+pub const PRUNE_EVERY: u64 = 100;
+
+/// Create the in-memory database and apply migrations.
+///
+/// The returned pool serves both writes and reads.
+pub async fn init_pool() -> Result<SqlitePool, sqlx::Error> {
+    let opts = SqliteConnectOptions::from_str("sqlite::memory:")?;
+
+    let pool = SqlitePoolOptions::new()
+        .min_connections(1)
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(opts)
+        .await?;
+
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Delete all but the newest `max_rows` request rows, with their tags,
+/// attempts, archived prompts, and receipts. Comparisons and evaluation
+/// runs no newer than the newest deleted request go too.
+///
+/// Returns the number of request rows deleted.
+pub async fn prune(pool: &SqlitePool, max_rows: u64) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let cutoff: Option<i64> = sqlx::query_scalar("SELECT MAX(id) - ? FROM requests")
+        .bind(max_rows as i64)
+        .fetch_one(&mut *tx)
+        .await?;
+    let Some(cutoff) = cutoff.filter(|c| *c > 0) else {
+        return Ok(0);
+    };
+
+    for table in [
+        "request_tags",
+        "request_attempts",
+        "prompt_archive",
+        "request_receipts",
+    ] {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE correlation_id IN \
+             (SELECT correlation_id FROM requests WHERE id <= ?)",
+            table
+        ))
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Timestamps differ in precision between tables, so compare them as
+    // Julian days
+    let cutoff_day: Option<f64> =
+        sqlx::query_scalar("SELECT MAX(julianday(timestamp)) FROM requests WHERE id <= ?")
+            .bind(cutoff)
+            .fetch_one(&mut *tx)
+            .await?;
+    if let Some(cutoff_day) = cutoff_day {
+        sqlx::query(
+            "DELETE FROM comparison_results WHERE comparison_id IN \
+             (SELECT id FROM comparisons WHERE julianday(timestamp) <= ?)",
+        )
+        .bind(cutoff_day)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM comparisons WHERE julianday(timestamp) <= ?")
+            .bind(cutoff_day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM evaluations WHERE julianday(run_id) <= ?")
+            .bind(cutoff_day)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let deleted = sqlx::query("DELETE FROM requests WHERE id <= ?")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::RequestLog;
+
+    fn log(id: u32) -> RequestLog {
+        RequestLog {
+            correlation_id: format!("mem-{}", id),
+            timestamp: format!("2026-01-01T00:00:{:02}Z", id),
+            model: "gpt-4o".to_string(),
+            provider: Some("alpha".to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: Some(10),
+            output_tokens: Some(20),
+            cost_sats: Some(1.0),
+            provider_cost_sats: None,
+            latency_ms: 5,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![("team".to_string(), "a".to_string())],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_shares_one_database_and_prunes_oldest() {
+        let pool = init_pool().await.unwrap();
+        for i in 0..10 {
+            log(i).insert(&pool).await.unwrap();
+        }
+        // A clone sees the same rows
+        let reader = pool.clone();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(&reader)
+            .await
+            .unwrap();
+        assert_eq!(count, 10);
+
+        assert_eq!(prune(&pool, 4).await.unwrap(), 6);
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT correlation_id FROM requests ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(ids, vec!["mem-6", "mem-7", "mem-8", "mem-9"]);
+        let tags: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM request_tags")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, 4);
+
+        assert_eq!(prune(&pool, 4).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn prune_drops_related_rows_from_the_same_period() {
+        let pool = init_pool().await.unwrap();
+        for i in 0..10 {
+            log(i).insert(&pool).await.unwrap();
+        }
+        for id in ["mem-0", "mem-9"] {
+            sqlx::query(
+                "INSERT INTO prompt_archive (correlation_id, timestamp, model, prompt_br) \
+                 VALUES (?, '2026-01-01T00:00:00Z', 'gpt-4o', x'00')",
+            )
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query("INSERT INTO request_receipts (correlation_id, receipt) VALUES (?, '{}')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        // One comparison and evaluation run from before the cutoff, one after
+        for (id, at) in [
+            ("old", "2026-01-01T00:00:02.500Z"),
+            ("new", "2026-01-01T00:00:08.500Z"),
+        ] {
+            sqlx::query("INSERT INTO comparisons (id, timestamp) VALUES (?, ?)")
+                .bind(id)
+                .bind(at)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                "INSERT INTO comparison_results (comparison_id, position, model, \
+                 correlation_id, success, latency_ms) VALUES (?, 1, 'gpt-4o', 'x', 1, 5)",
+            )
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO evaluations (run_id, provider, model, prompt, success, \
+                 latency_ms, score) VALUES (?, 'alpha', 'gpt-4o', 'p', 1, 5, 1.0)",
+            )
+            .bind(at)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // Deletes mem-0 through mem-5, last logged at 00:00:05
+        assert_eq!(prune(&pool, 4).await.unwrap(), 6);
+
+        for (query, expected) in [
+            ("SELECT correlation_id FROM prompt_archive", "mem-9"),
+            ("SELECT correlation_id FROM request_receipts", "mem-9"),
+            ("SELECT id FROM comparisons", "new"),
+            ("SELECT comparison_id FROM comparison_results", "new"),
+            ("SELECT run_id FROM evaluations", "2026-01-01T00:00:08.500Z"),
+        ] {
+            let rows: Vec<String> = sqlx::query_scalar(query).fetch_all(&pool).await.unwrap();
+            assert_eq!(rows, vec![expected], "{}", query);
+        }
+    }
+}
@@ -1,17 +1,30 @@
 //! SQLite storage for request logging and metrics.
 
+pub mod archive;
+pub mod backup;
+pub mod comparisons;
+pub mod config_versions;
+pub mod evaluations;
+pub mod info;
+pub mod ledger;
 pub mod logging;
 pub mod logs;
+pub mod memory;
+pub mod receipts;
+pub mod rollups;
+pub mod routing_state;
+pub mod scorecard;
 pub mod stats;
+pub mod vouchers;
 pub mod writer;
 
 pub use logging::{
     spawn_stream_completion_update, spawn_usage_update, update_stream_completion, update_usage,
-    RequestLog,
+    AttemptLog, RequestLog,
 };
 pub use logs::{count_logs, query_logs, LogRow};
 pub use stats::{query_aggregate, query_grouped_by_model, AggregateRow, ModelRow};
-pub use writer::DbWriter;
+pub use writer::{ConfirmedWriteStats, DbWriter};
 
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use sqlx::SqlitePool;
@@ -0,0 +1,25 @@
+//! Signed request receipts, stored as JSON when a request completes.
+
+use sqlx::SqlitePool;
+
+/// Store the signed receipt of a completed request.
+pub async fn insert(
+    pool: &SqlitePool,
+    correlation_id: &str,
+    receipt: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR REPLACE INTO request_receipts (correlation_id, receipt) VALUES (?, ?)")
+        .bind(correlation_id)
+        .bind(receipt)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The stored receipt JSON for `correlation_id`, if one was issued.
+pub async fn get(pool: &SqlitePool, correlation_id: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT receipt FROM request_receipts WHERE correlation_id = ?")
+        .bind(correlation_id)
+        .fetch_optional(pool)
+        .await
+}
@@ -0,0 +1,813 @@
+//! Hourly and daily request rollups for long-range stats (`[stats] rollups`).
+//!
+//! A background job aggregates completed hours of the `requests` table into
+//! `request_rollups` per model, provider, and tier, and into
+//! `request_tag_rollups` per tag as well, and sums those hours into days.
+//! Long-range stats queries then read whole days and hours from the rollups
+//! and only scan raw rows for the partial hours at either end of the range
+//! and for anything not yet rolled up. A tag filter reads the tag rollups;
+//! grouping by a tag key does too, with untagged requests found by
+//! subtracting the tagged ones from the totals.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, DurationRound, SecondsFormat, TimeDelta, Utc};
+use sqlx::SqlitePool;
+
+use super::stats::{AggregateRow, ModelRow, TagRow, TierRow};
+
+/// Hours before the watermark re-aggregated on every run, to pick up
+/// streams that completed after their hour was first rolled up.
+const LOOKBACK_HOURS: i64 = 2;
+
+/// Bucket timestamps, as `to_rfc3339` gives them. Request rows end in `Z`
+/// (with or without milliseconds), and `+` sorts before both `Z` and `.`,
+/// so comparing rows with a bucket as strings puts every row of the
+/// bucket's first second at or after it.
+const BUCKET_FORMAT: &str = "%Y-%m-%dT%H:00:00+00:00";
+
+/// Columns every rollup table stores, in insertion order.
+const ROLLUP_COLUMNS: &str = "total_requests, success_count, error_count, streaming_count, \
+     total_cost_sats, total_input_tokens, total_output_tokens, total_latency_ms, \
+     timeout_errors, connect_errors, tls_errors, auth_errors, rate_limited_errors, \
+     server_errors, client_errors, malformed_errors";
+
+/// [`ROLLUP_COLUMNS`] aggregated from raw rows `r`.
+const HOUR_SUMS: &str = "COUNT(*), \
+     COUNT(CASE WHEN r.success = 1 THEN 1 END), \
+     COUNT(CASE WHEN r.success = 0 THEN 1 END), \
+     COUNT(CASE WHEN r.streaming = 1 THEN 1 END), \
+     TOTAL(r.cost_sats), TOTAL(r.input_tokens), TOTAL(r.output_tokens), TOTAL(r.latency_ms), \
+     COUNT(CASE WHEN r.error_type = 'timeout' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = 'connect' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = 'tls' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = 'auth' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = 'rate_limited' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = '5xx' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = '4xx' THEN 1 END), \
+     COUNT(CASE WHEN r.error_type = 'malformed' THEN 1 END)";
+
+/// [`ROLLUP_COLUMNS`] summed from hour buckets.
+const DAY_SUMS: &str = "SUM(total_requests), SUM(success_count), SUM(error_count), \
+     SUM(streaming_count), TOTAL(total_cost_sats), TOTAL(total_input_tokens), \
+     TOTAL(total_output_tokens), TOTAL(total_latency_ms), SUM(timeout_errors), \
+     SUM(connect_errors), SUM(tls_errors), SUM(auth_errors), SUM(rate_limited_errors), \
+     SUM(server_errors), SUM(client_errors), SUM(malformed_errors)";
+
+/// Summed columns shared by every rollup query, named like the raw queries'.
+const ROLLUP_SUMS: &str = "CAST(TOTAL(total_requests) AS INTEGER) as total_requests, \
+     TOTAL(total_cost_sats) as total_cost_sats, \
+     TOTAL(total_input_tokens) as total_input_tokens, \
+     TOTAL(total_output_tokens) as total_output_tokens, \
+     COALESCE(TOTAL(total_latency_ms) / NULLIF(TOTAL(total_requests), 0), 0.0) as avg_latency_ms, \
+     CAST(TOTAL(success_count) AS INTEGER) as success_count, \
+     CAST(TOTAL(error_count) AS INTEGER) as error_count, \
+     CAST(TOTAL(streaming_count) AS INTEGER) as streaming_count";
+
+/// Rollup bucket width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Hour,
+    Day,
+}
+
+impl Granularity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+}
+
+/// One part of a planned stats query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Raw rows with `since <= timestamp <= until`.
+    Raw { since: String, until: String },
+    /// Rollup buckets with `from <= bucket_start < to`.
+    Rollup {
+        granularity: Granularity,
+        from: String,
+        to: String,
+    },
+}
+
+fn floor_to(t: DateTime<Utc>, width: TimeDelta) -> DateTime<Utc> {
+    t.duration_trunc(width).unwrap_or(t)
+}
+
+fn ceil_to(t: DateTime<Utc>, width: TimeDelta) -> DateTime<Utc> {
+    let floor = floor_to(t, width);
+    if floor == t {
+        floor
+    } else {
+        floor + width
+    }
+}
+
+fn bucket(t: DateTime<Utc>) -> String {
+    t.format(BUCKET_FORMAT).to_string()
+}
+
+/// Split `[since, until]` into rollup segments for the whole days and hours
+/// rolled up before `rolled_until`, and raw segments for the rest.
+///
+/// Returns None when no whole rolled-up hour falls in the range, in which
+/// case the raw queries should be used as they are.
+pub fn plan(
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    rolled_until: DateTime<Utc>,
+) -> Option<Vec<Segment>> {
+    let hour = TimeDelta::hours(1);
+    let day = TimeDelta::days(1);
+    let start = ceil_to(since, hour);
+    let end = floor_to(until, hour).min(floor_to(rolled_until, hour));
+    if start >= end {
+        return None;
+    }
+
+    let rollup = |granularity, from, to| Segment::Rollup {
+        granularity,
+        from: bucket(from),
+        to: bucket(to),
+    };
+    let mut segments = Vec::new();
+    if since < start {
+        // `Z` sorts after every other ending, so this takes in the whole
+        // last second, whatever precision its rows were logged at
+        segments.push(Segment::Raw {
+            since: since.to_rfc3339(),
+            until: (start - TimeDelta::seconds(1)).to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+    }
+    let (day_start, day_end) = (ceil_to(start, day), floor_to(end, day));
+    if day_start < day_end {
+        if start < day_start {
+            segments.push(rollup(Granularity::Hour, start, day_start));
+        }
+        segments.push(rollup(Granularity::Day, day_start, day_end));
+        if day_end < end {
+            segments.push(rollup(Granularity::Hour, day_end, end));
+        }
+    } else {
+        segments.push(rollup(Granularity::Hour, start, end));
+    }
+    segments.push(Segment::Raw {
+        since: bucket(end),
+        until: until.to_rfc3339(),
+    });
+    Some(segments)
+}
+
+/// End of the last hour aggregated, or None before the first run.
+pub async fn rolled_until(pool: &SqlitePool) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let value: Option<String> =
+        sqlx::query_scalar("SELECT rolled_until FROM rollup_state WHERE id = 1")
+            .fetch_optional(pool)
+            .await?;
+    Ok(value
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|t| t.with_timezone(&Utc)))
+}
+
+/// Aggregate every completed hour up to `now` not yet rolled up (plus the
+/// lookback window) and rebuild the days they fall in.
+///
+/// Returns the new watermark: the start of the hour containing `now`.
+pub async fn refresh(pool: &SqlitePool, now: DateTime<Utc>) -> Result<DateTime<Utc>, sqlx::Error> {
+    let now_hour = floor_to(now, TimeDelta::hours(1));
+    let start = match rolled_until(pool).await? {
+        Some(rolled) => (rolled - TimeDelta::hours(LOOKBACK_HOURS)).min(now_hour),
+        None => {
+            let first: Option<String> = sqlx::query_scalar(&format!(
+                "SELECT strftime('{}', MIN(timestamp)) FROM requests",
+                BUCKET_FORMAT
+            ))
+            .fetch_one(pool)
+            .await?;
+            first
+                .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+                .map_or(now_hour, |t| t.with_timezone(&Utc).min(now_hour))
+        }
+    };
+    let day_start = floor_to(start, TimeDelta::days(1));
+
+    let mut tx = pool.begin().await?;
+
+    for table in ["request_rollups", "request_tag_rollups"] {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE granularity = 'hour' AND bucket_start >= ?",
+            table
+        ))
+        .bind(bucket(start))
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query(&format!(
+        "INSERT INTO request_rollups (granularity, bucket_start, model, provider, tier, {}) \
+         SELECT 'hour', strftime('{}', r.timestamp) as bucket, r.model, \
+         COALESCE(r.provider, ''), COALESCE(r.tier, 'unknown'), {} \
+         FROM requests r WHERE r.timestamp >= ? AND r.timestamp < ? \
+         GROUP BY bucket, r.model, COALESCE(r.provider, ''), COALESCE(r.tier, 'unknown') \
+         HAVING bucket IS NOT NULL",
+        ROLLUP_COLUMNS, BUCKET_FORMAT, HOUR_SUMS
+    ))
+    .bind(bucket(start))
+    .bind(bucket(now_hour))
+    .execute(&mut *tx)
+    .await?;
+    // DISTINCT matches the raw tag filter, which counts a row once however
+    // many times its tag was stored
+    sqlx::query(&format!(
+        "INSERT INTO request_tag_rollups (granularity, bucket_start, tag_key, tag_value, \
+         model, provider, tier, {}) \
+         SELECT 'hour', strftime('{}', r.timestamp) as bucket, t.key, t.value, r.model, \
+         COALESCE(r.provider, ''), COALESCE(r.tier, 'unknown'), {} \
+         FROM requests r \
+         JOIN (SELECT DISTINCT correlation_id, key, value FROM request_tags) t \
+         ON t.correlation_id = r.correlation_id \
+         WHERE r.timestamp >= ? AND r.timestamp < ? \
+         GROUP BY bucket, t.key, t.value, r.model, COALESCE(r.provider, ''), \
+         COALESCE(r.tier, 'unknown') \
+         HAVING bucket IS NOT NULL",
+        ROLLUP_COLUMNS, BUCKET_FORMAT, HOUR_SUMS
+    ))
+    .bind(bucket(start))
+    .bind(bucket(now_hour))
+    .execute(&mut *tx)
+    .await?;
+
+    for (table, keys) in [
+        ("request_rollups", "model, provider, tier"),
+        (
+            "request_tag_rollups",
+            "tag_key, tag_value, model, provider, tier",
+        ),
+    ] {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE granularity = 'day' AND bucket_start >= ?",
+            table
+        ))
+        .bind(bucket(day_start))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {table} (granularity, bucket_start, {keys}, {}) \
+             SELECT 'day', substr(bucket_start, 1, 10) || 'T00:00:00+00:00' as day, {keys}, {} \
+             FROM {table} WHERE granularity = 'hour' AND bucket_start >= ? \
+             GROUP BY day, {keys}",
+            ROLLUP_COLUMNS, DAY_SUMS,
+        ))
+        .bind(bucket(day_start))
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO rollup_state (id, rolled_until) VALUES (1, ?) \
+         ON CONFLICT(id) DO UPDATE SET rolled_until = excluded.rolled_until",
+    )
+    .bind(bucket(now_hour))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(now_hour)
+}
+
+/// Background task that refreshes rollups every `interval`, starting at once.
+pub async fn rollup_loop(
+    pool: SqlitePool,
+    interval: Duration,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let start = std::time::Instant::now();
+                match refresh(&pool, Utc::now()).await {
+                    Ok(rolled_until) => tracing::debug!(
+                        rolled_until = %rolled_until,
+                        duration_ms = start.elapsed().as_millis() as u64,
+                        "Request rollups refreshed"
+                    ),
+                    Err(e) => tracing::warn!(error = %e, "Request rollup refresh failed"),
+                }
+            }
+            changed = cancel.changed() => {
+                if changed.is_err() || *cancel.borrow() {
+                    tracing::info!("Rollup task shutting down");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Requests a rollup query covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rows {
+    All,
+    /// Those carrying a tag key, whatever its value.
+    TagKey,
+    /// Those carrying a tag key with a given value.
+    Tag,
+}
+
+/// Append rollup filters to `sql`: bucket range, then the tag key and value
+/// `rows` calls for, then optional model/provider.
+fn rollup_filters(sql: &mut String, rows: Rows, model: bool, provider: bool) {
+    sql.push_str(match rows {
+        Rows::All => " FROM request_rollups",
+        Rows::TagKey | Rows::Tag => " FROM request_tag_rollups",
+    });
+    sql.push_str(" WHERE granularity = ? AND bucket_start >= ? AND bucket_start < ?");
+    match rows {
+        Rows::All => {}
+        Rows::TagKey => sql.push_str(" AND tag_key = ?"),
+        Rows::Tag => sql.push_str(" AND tag_key = ? AND tag_value = ?"),
+    }
+    if model {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if provider {
+        sql.push_str(" AND LOWER(provider) = LOWER(?)");
+    }
+}
+
+fn rows(tag: Option<(&str, &str)>) -> Rows {
+    if tag.is_some() {
+        Rows::Tag
+    } else {
+        Rows::All
+    }
+}
+
+fn weighted_avg(a: f64, a_count: i64, b: f64, b_count: i64) -> f64 {
+    let count = a_count + b_count;
+    if count == 0 {
+        0.0
+    } else {
+        (a * a_count as f64 + b * b_count as f64) / count as f64
+    }
+}
+
+/// Add `$b` into `$a`, averaging latency by request count.
+macro_rules! merge_rows {
+    ($a:expr, $b:expr; $($field:ident),*) => {{
+        $a.avg_latency_ms =
+            weighted_avg($a.avg_latency_ms, $a.total_requests, $b.avg_latency_ms, $b.total_requests);
+        $a.total_requests += $b.total_requests;
+        $a.total_cost_sats += $b.total_cost_sats;
+        $a.total_input_tokens += $b.total_input_tokens;
+        $a.total_output_tokens += $b.total_output_tokens;
+        $a.success_count += $b.success_count;
+        $a.error_count += $b.error_count;
+        $a.streaming_count += $b.streaming_count;
+        $($a.$field += $b.$field;)*
+    }};
+}
+
+/// [`super::stats::query_aggregate`] over a planned set of segments.
+pub async fn query_aggregate(
+    pool: &SqlitePool,
+    segments: &[Segment],
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<AggregateRow, sqlx::Error> {
+    let mut total = AggregateRow::default();
+    for segment in segments {
+        let row = match segment {
+            Segment::Raw { since, until } => {
+                super::stats::query_aggregate(pool, since, until, model, provider, tag).await?
+            }
+            Segment::Rollup {
+                granularity,
+                from,
+                to,
+            } => {
+                let mut sql = format!(
+                    "SELECT {}, \
+                     CAST(TOTAL(timeout_errors) AS INTEGER) as timeout_errors, \
+                     CAST(TOTAL(connect_errors) AS INTEGER) as connect_errors, \
+                     CAST(TOTAL(tls_errors) AS INTEGER) as tls_errors, \
+                     CAST(TOTAL(auth_errors) AS INTEGER) as auth_errors, \
+                     CAST(TOTAL(rate_limited_errors) AS INTEGER) as rate_limited_errors, \
+                     CAST(TOTAL(server_errors) AS INTEGER) as server_errors, \
+                     CAST(TOTAL(client_errors) AS INTEGER) as client_errors, \
+                     CAST(TOTAL(malformed_errors) AS INTEGER) as malformed_errors",
+                    ROLLUP_SUMS
+                );
+                rollup_filters(&mut sql, rows(tag), model.is_some(), provider.is_some());
+                let mut query = sqlx::query_as::<_, AggregateRow>(&sql)
+                    .bind(granularity.as_str())
+                    .bind(from)
+                    .bind(to);
+                if let Some((k, v)) = tag {
+                    query = query.bind(k).bind(v);
+                }
+                if let Some(m) = model {
+                    query = query.bind(m);
+                }
+                if let Some(p) = provider {
+                    query = query.bind(p);
+                }
+                query.fetch_one(pool).await?
+            }
+        };
+        merge_rows!(total, row; timeout_errors, connect_errors, tls_errors, auth_errors,
+            rate_limited_errors, server_errors, client_errors, malformed_errors);
+    }
+    Ok(total)
+}
+
+/// [`super::stats::query_grouped_by_model`] over a planned set of segments.
+pub async fn query_grouped_by_model(
+    pool: &SqlitePool,
+    segments: &[Segment],
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<ModelRow>, sqlx::Error> {
+    let mut models: HashMap<String, ModelRow> = HashMap::new();
+    for segment in segments {
+        let rows = match segment {
+            Segment::Raw { since, until } => {
+                super::stats::query_grouped_by_model(pool, since, until, provider, tag).await?
+            }
+            Segment::Rollup {
+                granularity,
+                from,
+                to,
+            } => {
+                let mut sql = format!("SELECT model, {}", ROLLUP_SUMS);
+                rollup_filters(&mut sql, rows(tag), false, provider.is_some());
+                sql.push_str(" GROUP BY model");
+                let mut query = sqlx::query_as::<_, ModelRow>(&sql)
+                    .bind(granularity.as_str())
+                    .bind(from)
+                    .bind(to);
+                if let Some((k, v)) = tag {
+                    query = query.bind(k).bind(v);
+                }
+                if let Some(p) = provider {
+                    query = query.bind(p);
+                }
+                query.fetch_all(pool).await?
+            }
+        };
+        for row in rows {
+            match models.get_mut(&row.model) {
+                Some(existing) => merge_rows!(existing, row;),
+                None => {
+                    models.insert(row.model.clone(), row);
+                }
+            }
+        }
+    }
+    Ok(models.into_values().collect())
+}
+
+/// [`super::stats::query_grouped_by_tier`] over a planned set of segments.
+pub async fn query_grouped_by_tier(
+    pool: &SqlitePool,
+    segments: &[Segment],
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<TierRow>, sqlx::Error> {
+    let mut tiers: HashMap<String, TierRow> = HashMap::new();
+    for segment in segments {
+        let rows = match segment {
+            Segment::Raw { since, until } => {
+                super::stats::query_grouped_by_tier(pool, since, until, model, provider, tag)
+                    .await?
+            }
+            Segment::Rollup {
+                granularity,
+                from,
+                to,
+            } => {
+                let mut sql = format!("SELECT tier, {}", ROLLUP_SUMS);
+                rollup_filters(&mut sql, rows(tag), model.is_some(), provider.is_some());
+                sql.push_str(" GROUP BY tier");
+                let mut query = sqlx::query_as::<_, TierRow>(&sql)
+                    .bind(granularity.as_str())
+                    .bind(from)
+                    .bind(to);
+                if let Some((k, v)) = tag {
+                    query = query.bind(k).bind(v);
+                }
+                if let Some(m) = model {
+                    query = query.bind(m);
+                }
+                if let Some(p) = provider {
+                    query = query.bind(p);
+                }
+                query.fetch_all(pool).await?
+            }
+        };
+        for row in rows {
+            match tiers.get_mut(&row.tier) {
+                Some(existing) => merge_rows!(existing, row;),
+                None => {
+                    tiers.insert(row.tier.clone(), row);
+                }
+            }
+        }
+    }
+    Ok(tiers.into_values().collect())
+}
+
+/// [`super::stats::query_grouped_by_tag`] over a planned set of segments.
+///
+/// Rolled-up requests without `tag_key` are what the totals have beyond the
+/// tagged ones; every request carries at most one value per key.
+pub async fn query_grouped_by_tag(
+    pool: &SqlitePool,
+    segments: &[Segment],
+    tag_key: &str,
+    model: Option<&str>,
+    provider: Option<&str>,
+) -> Result<Vec<TagRow>, sqlx::Error> {
+    let mut values: HashMap<String, TagRow> = HashMap::new();
+    for segment in segments {
+        let rows = match segment {
+            Segment::Raw { since, until } => {
+                super::stats::query_grouped_by_tag(
+                    pool, since, until, tag_key, model, provider, None,
+                )
+                .await?
+            }
+            Segment::Rollup {
+                granularity,
+                from,
+                to,
+            } => {
+                let mut sql = format!("SELECT tag_value, {}", ROLLUP_SUMS);
+                rollup_filters(&mut sql, Rows::TagKey, model.is_some(), provider.is_some());
+                sql.push_str(" GROUP BY tag_value");
+                let mut query = sqlx::query_as::<_, TagRow>(&sql)
+                    .bind(granularity.as_str())
+                    .bind(from)
+                    .bind(to)
+                    .bind(tag_key);
+                if let Some(m) = model {
+                    query = query.bind(m);
+                }
+                if let Some(p) = provider {
+                    query = query.bind(p);
+                }
+                let mut rows = query.fetch_all(pool).await?;
+
+                let mut sql = format!("SELECT 'untagged' as tag_value, {}", ROLLUP_SUMS);
+                rollup_filters(&mut sql, Rows::All, model.is_some(), provider.is_some());
+                let mut query = sqlx::query_as::<_, TagRow>(&sql)
+                    .bind(granularity.as_str())
+                    .bind(from)
+                    .bind(to);
+                if let Some(m) = model {
+                    query = query.bind(m);
+                }
+                if let Some(p) = provider {
+                    query = query.bind(p);
+                }
+                let mut untagged = query.fetch_one(pool).await?;
+                for row in &rows {
+                    let latency = untagged.avg_latency_ms * untagged.total_requests as f64
+                        - row.avg_latency_ms * row.total_requests as f64;
+                    untagged.total_requests -= row.total_requests;
+                    untagged.total_cost_sats -= row.total_cost_sats;
+                    untagged.total_input_tokens -= row.total_input_tokens;
+                    untagged.total_output_tokens -= row.total_output_tokens;
+                    untagged.success_count -= row.success_count;
+                    untagged.error_count -= row.error_count;
+                    untagged.streaming_count -= row.streaming_count;
+                    untagged.avg_latency_ms = if untagged.total_requests > 0 {
+                        latency / untagged.total_requests as f64
+                    } else {
+                        0.0
+                    };
+                }
+                if untagged.total_requests > 0 {
+                    rows.push(untagged);
+                }
+                rows
+            }
+        };
+        for row in rows {
+            match values.get_mut(&row.tag_value) {
+                Some(existing) => merge_rows!(existing, row;),
+                None => {
+                    values.insert(row.tag_value.clone(), row);
+                }
+            }
+        }
+    }
+    Ok(values.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap()
+    }
+
+    async fn seeded_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        // Every 7 minutes from March 1 00:00 to March 4 12:00, plus the last
+        // second before the first whole hour queried
+        let mut times = Vec::new();
+        let mut t = at(1, 0, 0);
+        while t < at(4, 12, 0) {
+            times.push(t);
+            t += TimeDelta::minutes(7);
+        }
+        times.push(at(1, 5, 59) + TimeDelta::seconds(59));
+        for (i, t) in times.into_iter().enumerate() {
+            let (model, provider) = if i % 3 == 0 {
+                ("gpt-4o", Some("alpha"))
+            } else if i % 3 == 1 {
+                ("claude-3.5-sonnet", Some("beta"))
+            } else {
+                ("gpt-4o", None)
+            };
+            sqlx::query(
+                "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+                 input_tokens, output_tokens, cost_sats, latency_ms, success, tier, error_type) \
+                 VALUES (?, ?, ?, ?, ?, 10, 20, ?, ?, ?, ?, ?)",
+            )
+            .bind(format!("r{}", i))
+            .bind(t.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .bind(model)
+            .bind(provider)
+            .bind(i % 4 == 0)
+            .bind(provider.map(|_| 1.5))
+            .bind(100 + (i % 50) as i64)
+            .bind(provider.is_some())
+            .bind((i % 5 == 0).then_some("frontier"))
+            .bind(provider.is_none().then_some("5xx"))
+            .execute(&pool)
+            .await
+            .unwrap();
+            let team = match i % 4 {
+                0 => Some("search"),
+                1 => Some("ads"),
+                _ => None,
+            };
+            let tags = team
+                .map(|team| ("team", team))
+                .into_iter()
+                .chain((i % 3 == 0).then_some(("env", "prod")));
+            for (key, value) in tags {
+                sqlx::query(
+                    "INSERT INTO request_tags (correlation_id, key, value) VALUES (?, ?, ?)",
+                )
+                .bind(format!("r{}", i))
+                .bind(key)
+                .bind(value)
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+        }
+        pool
+    }
+
+    #[test]
+    fn plan_uses_days_hours_and_raw_edges() {
+        let segments = plan(at(1, 10, 30), at(4, 6, 15), at(4, 3, 0)).unwrap();
+        let kinds: Vec<String> = segments
+            .iter()
+            .map(|s| match s {
+                Segment::Raw { since, until } => format!("raw {}..{}", since, until),
+                Segment::Rollup {
+                    granularity,
+                    from,
+                    to,
+                } => format!("{} {}..{}", granularity.as_str(), from, to),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "raw 2026-03-01T10:30:00+00:00..2026-03-01T10:59:59Z",
+                "hour 2026-03-01T11:00:00+00:00..2026-03-02T00:00:00+00:00",
+                "day 2026-03-02T00:00:00+00:00..2026-03-04T00:00:00+00:00",
+                "hour 2026-03-04T00:00:00+00:00..2026-03-04T03:00:00+00:00",
+                "raw 2026-03-04T03:00:00+00:00..2026-03-04T06:15:00+00:00",
+            ]
+        );
+
+        // Nothing rolled up inside the range
+        assert!(plan(at(1, 10, 30), at(1, 10, 50), at(4, 0, 0)).is_none());
+        assert!(plan(at(3, 0, 0), at(4, 0, 0), at(2, 0, 0)).is_none());
+    }
+
+    #[tokio::test]
+    async fn planned_queries_match_raw_queries() {
+        let pool = seeded_pool().await;
+        assert_eq!(rolled_until(&pool).await.unwrap(), None);
+        let rolled = refresh(&pool, at(4, 9, 40)).await.unwrap();
+        assert_eq!(rolled, at(4, 9, 0));
+        assert_eq!(rolled_until(&pool).await.unwrap(), Some(rolled));
+        // A second run rebuilds the lookback window without double counting
+        refresh(&pool, at(4, 9, 50)).await.unwrap();
+
+        let (since, until) = (at(1, 5, 17), at(4, 11, 3));
+        let segments = plan(since, until, rolled).unwrap();
+        let (since_str, until_str) = (since.to_rfc3339(), until.to_rfc3339());
+
+        for (model, provider, tag) in [
+            (None, None, None),
+            (Some("GPT-4o"), None, None),
+            (None, Some("beta"), None),
+            (None, None, Some(("team", "search"))),
+            (Some("gpt-4o"), None, Some(("env", "prod"))),
+        ] {
+            let raw = crate::storage::stats::query_aggregate(
+                &pool, &since_str, &until_str, model, provider, tag,
+            )
+            .await
+            .unwrap();
+            let planned = query_aggregate(&pool, &segments, model, provider, tag)
+                .await
+                .unwrap();
+            assert_eq!(planned.total_requests, raw.total_requests);
+            assert_eq!(planned.success_count, raw.success_count);
+            assert_eq!(planned.streaming_count, raw.streaming_count);
+            assert_eq!(planned.server_errors, raw.server_errors);
+            assert!((planned.total_cost_sats - raw.total_cost_sats).abs() < 1e-6);
+            assert!((planned.total_output_tokens - raw.total_output_tokens).abs() < 1e-6);
+            assert!((planned.avg_latency_ms - raw.avg_latency_ms).abs() < 1e-6);
+        }
+
+        let mut raw_models = crate::storage::stats::query_grouped_by_model(
+            &pool, &since_str, &until_str, None, None,
+        )
+        .await
+        .unwrap();
+        let mut planned_models = query_grouped_by_model(&pool, &segments, None, None)
+            .await
+            .unwrap();
+        raw_models.sort_by(|a, b| a.model.cmp(&b.model));
+        planned_models.sort_by(|a, b| a.model.cmp(&b.model));
+        let counts = |rows: &[ModelRow]| -> Vec<(String, i64)> {
+            rows.iter()
+                .map(|r| (r.model.clone(), r.total_requests))
+                .collect()
+        };
+        assert_eq!(counts(&planned_models), counts(&raw_models));
+
+        let raw_tiers = crate::storage::stats::query_grouped_by_tier(
+            &pool, &since_str, &until_str, None, None, None,
+        )
+        .await
+        .unwrap();
+        let planned_tiers = query_grouped_by_tier(&pool, &segments, None, None, None)
+            .await
+            .unwrap();
+        let tier_count = |rows: &[TierRow], tier: &str| {
+            rows.iter()
+                .find(|r| r.tier == tier)
+                .map(|r| r.total_requests)
+        };
+        for tier in ["frontier", "unknown"] {
+            assert_eq!(
+                tier_count(&planned_tiers, tier),
+                tier_count(&raw_tiers, tier)
+            );
+        }
+
+        for provider in [None, Some("alpha")] {
+            let mut raw_tags = crate::storage::stats::query_grouped_by_tag(
+                &pool, &since_str, &until_str, "team", None, provider, None,
+            )
+            .await
+            .unwrap();
+            let mut planned_tags = query_grouped_by_tag(&pool, &segments, "team", None, provider)
+                .await
+                .unwrap();
+            raw_tags.sort_by(|a, b| a.tag_value.cmp(&b.tag_value));
+            planned_tags.sort_by(|a, b| a.tag_value.cmp(&b.tag_value));
+            assert_eq!(planned_tags.len(), raw_tags.len());
+            for (planned, raw) in planned_tags.iter().zip(&raw_tags) {
+                assert_eq!(planned.tag_value, raw.tag_value);
+                assert_eq!(planned.total_requests, raw.total_requests);
+                assert_eq!(planned.error_count, raw.error_count);
+                assert!((planned.total_cost_sats - raw.total_cost_sats).abs() < 1e-6);
+                assert!((planned.avg_latency_ms - raw.avg_latency_ms).abs() < 1e-6);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Persistence for in-memory routing state.
+//!
+//! Trackers serialize their per-provider state to JSON under a `kind`;
+//! a save replaces every row of that kind, so providers removed from the
+//! config don't linger.
+
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::SqlitePool;
+
+/// One provider's saved state.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SavedState {
+    pub provider: String,
+    pub state: String,
+    /// RFC 3339 time of the save.
+    pub saved_at: String,
+}
+
+impl SavedState {
+    /// Time since the save; zero when `saved_at` is unreadable or ahead of
+    /// the clock.
+    pub fn age(&self) -> Duration {
+        DateTime::parse_from_rfc3339(&self.saved_at)
+            .ok()
+            .and_then(|at| (Utc::now() - at.with_timezone(&Utc)).to_std().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Current time in the format stored in `saved_at`.
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Replace all saved state of `kind` with `entries` (provider, JSON state).
+pub async fn save(
+    pool: &SqlitePool,
+    kind: &str,
+    entries: &[(String, String)],
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM routing_state WHERE kind = ?")
+        .bind(kind)
+        .execute(&mut *tx)
+        .await?;
+    for (provider, state) in entries {
+        sqlx::query(
+            "INSERT INTO routing_state (kind, provider, state, saved_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(kind)
+        .bind(provider)
+        .bind(state)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Load all saved state of `kind`.
+pub async fn load(pool: &SqlitePool, kind: &str) -> Result<Vec<SavedState>, sqlx::Error> {
+    sqlx::query_as::<_, SavedState>(
+        "SELECT provider, state, saved_at FROM routing_state WHERE kind = ? ORDER BY provider",
+    )
+    .bind(kind)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn save_replaces_previous_state_of_kind() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let entries = |names: &[&str]| -> Vec<(String, String)> {
+            names
+                .iter()
+                .map(|n| (n.to_string(), "{}".to_string()))
+                .collect()
+        };
+        save(
+            &pool,
+            "reputation",
+            &entries(&["alpha", "beta"]),
+            "2026-01-01T00:00:00Z",
+        )
+        .await
+        .unwrap();
+        save(
+            &pool,
+            "canary",
+            &entries(&["gamma"]),
+            "2026-01-01T00:00:00Z",
+        )
+        .await
+        .unwrap();
+        save(
+            &pool,
+            "reputation",
+            &entries(&["beta"]),
+            "2026-01-02T00:00:00Z",
+        )
+        .await
+        .unwrap();
+
+        let saved = load(&pool, "reputation").await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].provider, "beta");
+        assert_eq!(saved[0].saved_at, "2026-01-02T00:00:00Z");
+        assert_eq!(load(&pool, "canary").await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,186 @@
+//! Per-provider queries for the provider scorecard endpoint.
+//!
+//! All queries match the provider name case-insensitively and restrict rows
+//! to `timestamp` within `[since, until]`.
+
+use sqlx::SqlitePool;
+
+/// Request counts, latency, and cost for one provider.
+#[derive(sqlx::FromRow)]
+pub struct ProviderSummaryRow {
+    pub total_requests: i64,
+    pub success_count: i64,
+    pub error_count: i64,
+    pub avg_latency_ms: f64,
+    pub total_cost_sats: f64,
+    /// Successful requests with a recorded cost (denominator for avg cost).
+    pub costed_requests: i64,
+}
+
+/// A recent failed request.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ProviderErrorRow {
+    pub timestamp: String,
+    pub model: String,
+    pub error_status: Option<i32>,
+    pub error_message: Option<String>,
+}
+
+/// Aggregate counts, average latency, and total cost for a provider.
+pub async fn query_provider_summary(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    provider: &str,
+) -> Result<ProviderSummaryRow, sqlx::Error> {
+    sqlx::query_as::<_, ProviderSummaryRow>(
+        "SELECT \
+         COUNT(*) as total_requests, \
+         COUNT(CASE WHEN success = 1 THEN 1 END) as success_count, \
+         COUNT(CASE WHEN success = 0 THEN 1 END) as error_count, \
+         COALESCE(AVG(latency_ms), 0.0) as avg_latency_ms, \
+         TOTAL(cost_sats) as total_cost_sats, \
+         COUNT(CASE WHEN success = 1 AND cost_sats IS NOT NULL THEN 1 END) as costed_requests \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ? AND LOWER(provider) = LOWER(?)",
+    )
+    .bind(since)
+    .bind(until)
+    .bind(provider)
+    .fetch_one(pool)
+    .await
+}
+
+/// Latency percentile (nearest-rank) over successful requests.
+///
+/// `percentile` is in `(0, 1]`. Returns None when there are no successful
+/// requests in the window.
+pub async fn query_latency_percentile(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    provider: &str,
+    percentile: f64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         AND LOWER(provider) = LOWER(?) AND success = 1",
+    )
+    .bind(since)
+    .bind(until)
+    .bind(provider)
+    .fetch_one(pool)
+    .await?;
+    if count == 0 {
+        return Ok(None);
+    }
+
+    let rank = ((percentile * count as f64).ceil() as i64).clamp(1, count);
+    sqlx::query_scalar(
+        "SELECT latency_ms FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         AND LOWER(provider) = LOWER(?) AND success = 1 \
+         ORDER BY latency_ms LIMIT 1 OFFSET ?",
+    )
+    .bind(since)
+    .bind(until)
+    .bind(provider)
+    .bind(rank - 1)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Most recent failed requests for a provider, newest first.
+pub async fn query_recent_errors(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    provider: &str,
+    limit: u32,
+) -> Result<Vec<ProviderErrorRow>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderErrorRow>(
+        "SELECT timestamp, model, error_status, error_message FROM requests \
+         WHERE timestamp >= ? AND timestamp <= ? AND LOWER(provider) = LOWER(?) \
+         AND success = 0 ORDER BY timestamp DESC, id DESC LIMIT ?",
+    )
+    .bind(since)
+    .bind(until)
+    .bind(provider)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn seed(pool: &SqlitePool, id: &str, latency_ms: i64, success: bool) {
+        sqlx::query(
+            "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+             latency_ms, success, cost_sats, error_message) \
+             VALUES (?, '2026-01-01T00:00:00Z', 'gpt-4o', 'alpha', 0, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(latency_ms)
+        .bind(success)
+        .bind(success.then_some(2.0))
+        .bind((!success).then_some("boom"))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    const SINCE: &str = "2025-12-31T00:00:00Z";
+    const UNTIL: &str = "2026-01-02T00:00:00Z";
+
+    #[tokio::test]
+    async fn percentiles_use_nearest_rank_over_successes() {
+        let pool = test_pool().await;
+        for i in 1..=20 {
+            seed(&pool, &format!("ok-{}", i), i * 10, true).await;
+        }
+        seed(&pool, "err-1", 10_000, false).await;
+
+        let p50 = query_latency_percentile(&pool, SINCE, UNTIL, "ALPHA", 0.5)
+            .await
+            .unwrap();
+        let p95 = query_latency_percentile(&pool, SINCE, UNTIL, "alpha", 0.95)
+            .await
+            .unwrap();
+        assert_eq!(p50, Some(100));
+        assert_eq!(p95, Some(190));
+
+        let none = query_latency_percentile(&pool, SINCE, UNTIL, "beta", 0.5)
+            .await
+            .unwrap();
+        assert_eq!(none, None);
+    }
+
+    #[tokio::test]
+    async fn summary_and_recent_errors() {
+        let pool = test_pool().await;
+        seed(&pool, "ok-1", 100, true).await;
+        seed(&pool, "ok-2", 300, true).await;
+        seed(&pool, "err-1", 50, false).await;
+
+        let summary = query_provider_summary(&pool, SINCE, UNTIL, "alpha")
+            .await
+            .unwrap();
+        assert_eq!(summary.total_requests, 3);
+        assert_eq!(summary.success_count, 2);
+        assert_eq!(summary.error_count, 1);
+        assert_eq!(summary.costed_requests, 2);
+        assert!((summary.total_cost_sats - 4.0).abs() < f64::EPSILON);
+
+        let errors = query_recent_errors(&pool, SINCE, UNTIL, "alpha", 10)
+            .await
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_message.as_deref(), Some("boom"));
+    }
+}
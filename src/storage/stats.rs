@@ -2,8 +2,14 @@
 
 use sqlx::SqlitePool;
 
+/// WHERE fragment restricting rows to requests carrying a given tag.
+///
+/// Binds two parameters: tag key, then tag value.
+pub(crate) const TAG_FILTER_CLAUSE: &str =
+    " AND correlation_id IN (SELECT correlation_id FROM request_tags WHERE key = ? AND value = ?)";
+
 /// Aggregate statistics for a time range.
-#[derive(sqlx::FromRow)]
+#[derive(Default, sqlx::FromRow)]
 pub struct AggregateRow {
     pub total_requests: i64,
     pub total_cost_sats: f64,
@@ -13,10 +19,18 @@ pub struct AggregateRow {
     pub success_count: i64,
     pub error_count: i64,
     pub streaming_count: i64,
+    pub timeout_errors: i64,
+    pub connect_errors: i64,
+    pub tls_errors: i64,
+    pub auth_errors: i64,
+    pub rate_limited_errors: i64,
+    pub server_errors: i64,
+    pub client_errors: i64,
+    pub malformed_errors: i64,
 }
 
 /// Per-model statistics for a time range.
-#[derive(sqlx::FromRow)]
+#[derive(Default, sqlx::FromRow)]
 pub struct ModelRow {
     pub model: String,
     pub total_requests: i64,
@@ -29,7 +43,7 @@ pub struct ModelRow {
     pub streaming_count: i64,
 }
 
-/// Query aggregate statistics for a time range with optional model/provider filters.
+/// Query aggregate statistics for a time range with optional model/provider/tag filters.
 ///
 /// Uses `TOTAL()` for nullable numeric columns (returns 0.0 instead of NULL)
 /// and `COALESCE(AVG(), 0)` for latency to ensure non-null results.
@@ -39,6 +53,7 @@ pub async fn query_aggregate(
     until: &str,
     model: Option<&str>,
     provider: Option<&str>,
+    tag: Option<(&str, &str)>,
 ) -> Result<AggregateRow, sqlx::Error> {
     let mut sql = String::from(
         "SELECT \
@@ -49,7 +64,15 @@ pub async fn query_aggregate(
          COALESCE(AVG(latency_ms), 0.0) as avg_latency_ms, \
          COUNT(CASE WHEN success = 1 THEN 1 END) as success_count, \
          COUNT(CASE WHEN success = 0 THEN 1 END) as error_count, \
-         COUNT(CASE WHEN streaming = 1 THEN 1 END) as streaming_count \
+         COUNT(CASE WHEN streaming = 1 THEN 1 END) as streaming_count, \
+         COUNT(CASE WHEN error_type = 'timeout' THEN 1 END) as timeout_errors, \
+         COUNT(CASE WHEN error_type = 'connect' THEN 1 END) as connect_errors, \
+         COUNT(CASE WHEN error_type = 'tls' THEN 1 END) as tls_errors, \
+         COUNT(CASE WHEN error_type = 'auth' THEN 1 END) as auth_errors, \
+         COUNT(CASE WHEN error_type = 'rate_limited' THEN 1 END) as rate_limited_errors, \
+         COUNT(CASE WHEN error_type = '5xx' THEN 1 END) as server_errors, \
+         COUNT(CASE WHEN error_type = '4xx' THEN 1 END) as client_errors, \
+         COUNT(CASE WHEN error_type = 'malformed' THEN 1 END) as malformed_errors \
          FROM requests WHERE timestamp >= ? AND timestamp <= ?",
     );
 
@@ -59,6 +82,9 @@ pub async fn query_aggregate(
     if provider.is_some() {
         sql.push_str(" AND LOWER(provider) = LOWER(?)");
     }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
 
     let mut query = sqlx::query_as::<_, AggregateRow>(&sql)
         .bind(since)
@@ -70,11 +96,14 @@ pub async fn query_aggregate(
     if let Some(p) = provider {
         query = query.bind(p);
     }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
 
     query.fetch_one(pool).await
 }
 
-/// Query per-model statistics for a time range with optional provider filter.
+/// Query per-model statistics for a time range with optional provider/tag filters.
 ///
 /// Returns one row per model with aggregate stats, grouped by model name.
 pub async fn query_grouped_by_model(
@@ -82,6 +111,7 @@ pub async fn query_grouped_by_model(
     since: &str,
     until: &str,
     provider: Option<&str>,
+    tag: Option<(&str, &str)>,
 ) -> Result<Vec<ModelRow>, sqlx::Error> {
     let mut sql = String::from(
         "SELECT \
@@ -100,6 +130,9 @@ pub async fn query_grouped_by_model(
     if provider.is_some() {
         sql.push_str(" AND LOWER(provider) = LOWER(?)");
     }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
 
     sql.push_str(" GROUP BY model");
 
@@ -108,12 +141,182 @@ pub async fn query_grouped_by_model(
     if let Some(p) = provider {
         query = query.bind(p);
     }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
 
     query.fetch_all(pool).await
 }
 
-/// Per-tier statistics for a time range.
+/// A request attribute `/v1/stats` can group by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsDimension {
+    Model,
+    Provider,
+    Policy,
+    Tier,
+    Conversation,
+}
+
+impl StatsDimension {
+    /// Parse a `group_by` entry.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "model" => Some(Self::Model),
+            "provider" => Some(Self::Provider),
+            "policy" => Some(Self::Policy),
+            "tier" => Some(Self::Tier),
+            "conversation" => Some(Self::Conversation),
+            _ => None,
+        }
+    }
+
+    /// Name used for the dimension in responses.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Provider => "provider",
+            Self::Policy => "policy",
+            Self::Tier => "tier",
+            Self::Conversation => "conversation",
+        }
+    }
+
+    /// SQL expression for the dimension. Requests rejected before routing
+    /// have no provider or tier ('unknown'); requests without a policy or
+    /// conversation are grouped under 'none'.
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Provider => "COALESCE(provider, 'unknown')",
+            Self::Policy => "COALESCE(policy, 'none')",
+            Self::Tier => "COALESCE(tier, 'unknown')",
+            Self::Conversation => "COALESCE(conversation_id, 'none')",
+        }
+    }
+}
+
+/// Separator between dimension values in [`GroupRow::group_key`].
+const GROUP_KEY_SEPARATOR: char = '\u{1f}';
+
+/// Statistics for one combination of dimension values.
 #[derive(sqlx::FromRow)]
+pub struct GroupRow {
+    group_key: String,
+    pub total_requests: i64,
+    pub total_cost_sats: f64,
+    pub total_input_tokens: f64,
+    pub total_output_tokens: f64,
+    pub avg_latency_ms: f64,
+    pub success_count: i64,
+    pub error_count: i64,
+    pub streaming_count: i64,
+}
+
+impl GroupRow {
+    /// Values of the grouped dimensions, in the order requested.
+    pub fn keys(&self) -> Vec<&str> {
+        self.group_key.split(GROUP_KEY_SEPARATOR).collect()
+    }
+}
+
+/// Query statistics grouped by one or more dimensions, with optional
+/// model/provider/tag filters as in [`query_aggregate`].
+///
+/// Rows are ordered by total cost, highest first.
+pub async fn query_grouped_by(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    dimensions: &[StatsDimension],
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<GroupRow>, sqlx::Error> {
+    let key = dimensions
+        .iter()
+        .map(StatsDimension::column)
+        .collect::<Vec<_>>()
+        .join(" || char(31) || ");
+    let mut sql = format!(
+        "SELECT \
+         {} as group_key, \
+         COUNT(*) as total_requests, \
+         TOTAL(cost_sats) as total_cost_sats, \
+         TOTAL(input_tokens) as total_input_tokens, \
+         TOTAL(output_tokens) as total_output_tokens, \
+         COALESCE(AVG(latency_ms), 0.0) as avg_latency_ms, \
+         COUNT(CASE WHEN success = 1 THEN 1 END) as success_count, \
+         COUNT(CASE WHEN success = 0 THEN 1 END) as error_count, \
+         COUNT(CASE WHEN streaming = 1 THEN 1 END) as streaming_count \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ?",
+        key
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+
+    sql.push_str(" GROUP BY group_key ORDER BY total_cost_sats DESC, group_key");
+
+    let mut query = sqlx::query_as::<_, GroupRow>(&sql).bind(since).bind(until);
+
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Cumulative usage of one conversation.
+#[derive(Default, sqlx::FromRow)]
+pub struct ConversationRow {
+    pub total_requests: i64,
+    pub total_cost_sats: f64,
+    pub total_input_tokens: f64,
+    pub total_output_tokens: f64,
+    pub success_count: i64,
+    pub error_count: i64,
+    pub first_request_at: Option<String>,
+    pub last_request_at: Option<String>,
+}
+
+/// Query the cumulative usage of a conversation over all logged requests.
+pub async fn query_conversation(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<ConversationRow, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT \
+         COUNT(*) as total_requests, \
+         TOTAL(cost_sats) as total_cost_sats, \
+         TOTAL(input_tokens) as total_input_tokens, \
+         TOTAL(output_tokens) as total_output_tokens, \
+         COUNT(CASE WHEN success = 1 THEN 1 END) as success_count, \
+         COUNT(CASE WHEN success = 0 THEN 1 END) as error_count, \
+         MIN(timestamp) as first_request_at, \
+         MAX(timestamp) as last_request_at \
+         FROM requests WHERE conversation_id = ?",
+    )
+    .bind(conversation_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Per-tier statistics for a time range.
+#[derive(Default, sqlx::FromRow)]
 pub struct TierRow {
     pub tier: String,
     pub total_requests: i64,
@@ -126,7 +329,7 @@ pub struct TierRow {
     pub streaming_count: i64,
 }
 
-/// Query per-tier statistics for a time range with optional model/provider filters.
+/// Query per-tier statistics for a time range with optional model/provider/tag filters.
 ///
 /// Returns one row per tier with aggregate stats, grouped by tier name.
 /// NULL tier values are coalesced to 'unknown'.
@@ -136,6 +339,7 @@ pub async fn query_grouped_by_tier(
     until: &str,
     model: Option<&str>,
     provider: Option<&str>,
+    tag: Option<(&str, &str)>,
 ) -> Result<Vec<TierRow>, sqlx::Error> {
     let mut sql = String::from(
         "SELECT \
@@ -157,6 +361,9 @@ pub async fn query_grouped_by_tier(
     if provider.is_some() {
         sql.push_str(" AND LOWER(provider) = LOWER(?)");
     }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
 
     sql.push_str(" GROUP BY COALESCE(tier, 'unknown')");
 
@@ -168,6 +375,540 @@ pub async fn query_grouped_by_tier(
     if let Some(p) = provider {
         query = query.bind(p);
     }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Per-tag-value statistics for a time range.
+#[derive(sqlx::FromRow)]
+pub struct TagRow {
+    pub tag_value: String,
+    pub total_requests: i64,
+    pub total_cost_sats: f64,
+    pub total_input_tokens: f64,
+    pub total_output_tokens: f64,
+    pub avg_latency_ms: f64,
+    pub success_count: i64,
+    pub error_count: i64,
+    pub streaming_count: i64,
+}
+
+/// Query statistics grouped by the value of a single tag key.
+///
+/// Requests that do not carry `tag_key` are grouped under 'untagged'.
+/// Optional model/provider/tag filters apply as in [`query_aggregate`].
+#[allow(clippy::too_many_arguments)]
+pub async fn query_grouped_by_tag(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    tag_key: &str,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<TagRow>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT \
+         COALESCE(t.value, 'untagged') as tag_value, \
+         COUNT(*) as total_requests, \
+         TOTAL(r.cost_sats) as total_cost_sats, \
+         TOTAL(r.input_tokens) as total_input_tokens, \
+         TOTAL(r.output_tokens) as total_output_tokens, \
+         COALESCE(AVG(r.latency_ms), 0.0) as avg_latency_ms, \
+         COUNT(CASE WHEN r.success = 1 THEN 1 END) as success_count, \
+         COUNT(CASE WHEN r.success = 0 THEN 1 END) as error_count, \
+         COUNT(CASE WHEN r.streaming = 1 THEN 1 END) as streaming_count \
+         FROM requests r \
+         LEFT JOIN request_tags t ON t.correlation_id = r.correlation_id AND t.key = ? \
+         WHERE r.timestamp >= ? AND r.timestamp <= ?",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(r.model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(r.provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(
+            " AND r.correlation_id IN (SELECT correlation_id FROM request_tags WHERE key = ? AND value = ?)",
+        );
+    }
+
+    sql.push_str(" GROUP BY COALESCE(t.value, 'untagged')");
+
+    let mut query = sqlx::query_as::<_, TagRow>(&sql)
+        .bind(tag_key)
+        .bind(since)
+        .bind(until);
+
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Per-provider statistics for a time range.
+#[derive(sqlx::FromRow)]
+pub struct ProviderRow {
+    pub provider: String,
+    pub total_requests: i64,
+    pub total_cost_sats: f64,
+    pub success_count: i64,
+    pub error_count: i64,
+}
+
+/// Query per-provider statistics for a time range.
+///
+/// Returns one row per provider. NULL providers (requests rejected before
+/// routing) are coalesced to 'unknown'.
+pub async fn query_grouped_by_provider(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+) -> Result<Vec<ProviderRow>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderRow>(
+        "SELECT \
+         COALESCE(provider, 'unknown') as provider, \
+         COUNT(*) as total_requests, \
+         TOTAL(cost_sats) as total_cost_sats, \
+         COUNT(CASE WHEN success = 1 THEN 1 END) as success_count, \
+         COUNT(CASE WHEN success = 0 THEN 1 END) as error_count \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         GROUP BY COALESCE(provider, 'unknown')",
+    )
+    .bind(since)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+}
+
+/// Successful traffic for one (model, provider) pair.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ModelProviderRow {
+    pub model: String,
+    pub provider: String,
+    pub success_count: i64,
+    pub total_cost_sats: f64,
+    pub total_input_tokens: f64,
+    pub total_output_tokens: f64,
+}
+
+/// Query successful requests per (model, provider) for a time range.
+pub async fn query_grouped_by_model_provider(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+) -> Result<Vec<ModelProviderRow>, sqlx::Error> {
+    sqlx::query_as::<_, ModelProviderRow>(
+        "SELECT \
+         model, \
+         provider, \
+         COUNT(*) as success_count, \
+         TOTAL(cost_sats) as total_cost_sats, \
+         TOTAL(input_tokens) as total_input_tokens, \
+         TOTAL(output_tokens) as total_output_tokens \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         AND success = 1 AND provider IS NOT NULL \
+         GROUP BY model, provider ORDER BY model, provider",
+    )
+    .bind(since)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+}
+
+/// Completion count for one (model, provider, finish_reason) combination.
+#[derive(sqlx::FromRow)]
+pub struct FinishReasonRow {
+    pub model: String,
+    pub provider: String,
+    pub finish_reason: String,
+    pub count: i64,
+}
+
+/// Count successful requests by model, provider, and finish_reason.
+///
+/// Rows without a recorded finish_reason (errors, incomplete streams, and
+/// requests logged before the column existed) are excluded.
+pub async fn query_finish_reasons(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<FinishReasonRow>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT \
+         model, \
+         COALESCE(provider, 'unknown') as provider, \
+         finish_reason, \
+         COUNT(*) as count \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         AND success = 1 AND finish_reason IS NOT NULL",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    sql.push_str(" GROUP BY model, COALESCE(provider, 'unknown'), finish_reason");
+
+    let mut query = sqlx::query_as::<_, FinishReasonRow>(&sql)
+        .bind(since)
+        .bind(until);
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Retry and fallback outcomes for the requests logged against one provider.
+#[derive(sqlx::FromRow)]
+pub struct ReliabilityRow {
+    pub provider: String,
+    pub requests: i64,
+    /// Requests with at least one failed attempt before the final one.
+    pub retried: i64,
+    /// Requests with a failed attempt on a different provider.
+    pub fallbacks: i64,
+    pub fallback_successes: i64,
+    /// Latency of successful retried requests, summed, and their count.
+    pub retried_latency_ms: i64,
+    pub retried_successes: i64,
+    /// Latency of successful first-attempt requests, summed, and their count.
+    pub direct_latency_ms: i64,
+    pub direct_successes: i64,
+}
+
+/// Count retried and fallen-back requests by the provider they were logged
+/// against, with the latency sums needed to compare them to first-attempt
+/// successes.
+///
+/// A request falls back when any of its failed attempts named a provider
+/// other than the one it was logged against.
+pub async fn query_reliability(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    model: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<ReliabilityRow>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT \
+         provider, \
+         COUNT(*) as requests, \
+         COALESCE(SUM(failed > 0), 0) as retried, \
+         COALESCE(SUM(fell_back), 0) as fallbacks, \
+         COALESCE(SUM(fell_back AND success), 0) as fallback_successes, \
+         COALESCE(SUM(CASE WHEN failed > 0 AND success THEN latency_ms END), 0) as retried_latency_ms, \
+         COALESCE(SUM(failed > 0 AND success), 0) as retried_successes, \
+         COALESCE(SUM(CASE WHEN failed = 0 AND success THEN latency_ms END), 0) as direct_latency_ms, \
+         COALESCE(SUM(failed = 0 AND success), 0) as direct_successes \
+         FROM (SELECT \
+           COALESCE(r.provider, 'unknown') as provider, r.success, r.latency_ms, \
+           (SELECT COUNT(DISTINCT a.attempt) FROM request_attempts a \
+            WHERE a.correlation_id = r.correlation_id) as failed, \
+           EXISTS (SELECT 1 FROM request_attempts a \
+            WHERE a.correlation_id = r.correlation_id \
+            AND a.provider != COALESCE(r.provider, '')) as fell_back \
+           FROM requests r WHERE timestamp >= ? AND timestamp <= ?",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    sql.push_str(") GROUP BY provider");
+
+    let mut query = sqlx::query_as::<_, ReliabilityRow>(&sql)
+        .bind(since)
+        .bind(until);
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Failed attempts on one provider, by error type.
+#[derive(sqlx::FromRow)]
+pub struct AttemptErrorRow {
+    pub provider: String,
+    pub error_type: String,
+    pub count: i64,
+}
+
+/// Count the failed attempts behind requests in a time range by the
+/// provider that failed them and the error type.
+///
+/// Attempts are repeated under every row logged for a correlation ID, so
+/// each is counted once.
+pub async fn query_attempt_errors(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    model: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<AttemptErrorRow>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT provider, error_type, COUNT(*) as count FROM \
+         (SELECT DISTINCT correlation_id, attempt, provider, error_type FROM request_attempts \
+          WHERE correlation_id IN \
+          (SELECT correlation_id FROM requests WHERE timestamp >= ? AND timestamp <= ?",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    sql.push_str(")) GROUP BY provider, error_type");
+
+    let mut query = sqlx::query_as::<_, AttemptErrorRow>(&sql)
+        .bind(since)
+        .bind(until);
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Dimension used to split spend buckets for forecasting.
+#[derive(Debug, Clone, Copy)]
+pub enum SpendGrouping<'a> {
+    /// A single series covering all matching requests.
+    Total,
+    /// One series per model.
+    Model,
+    /// One series per provider (NULL providers grouped under 'unknown').
+    Provider,
+    /// One series per value of a tag key (untagged requests under 'untagged').
+    Tag(&'a str),
+}
+
+/// Spend within one time bucket for one group.
+#[derive(sqlx::FromRow)]
+pub struct SpendBucketRow {
+    /// Bucket age: 0 is the bucket ending at `until`, 1 the one before, etc.
+    pub bucket: i64,
+    pub group_key: String,
+    pub cost_sats: f64,
+}
+
+/// Query spend per fixed-width time bucket, counted backwards from `until`.
+///
+/// `buckets_per_day` sets the bucket width (24 = hourly, 1 = daily). Buckets
+/// are anchored at `until` rather than calendar boundaries so every bucket in
+/// the window is complete. Empty buckets are not returned.
+#[allow(clippy::too_many_arguments)]
+pub async fn query_spend_buckets(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    buckets_per_day: i64,
+    grouping: SpendGrouping<'_>,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<SpendBucketRow>, sqlx::Error> {
+    let group_expr = match grouping {
+        SpendGrouping::Total => "'total'",
+        SpendGrouping::Model => "r.model",
+        SpendGrouping::Provider => "COALESCE(r.provider, 'unknown')",
+        SpendGrouping::Tag(_) => "COALESCE(t.value, 'untagged')",
+    };
+
+    // group_expr is a whitelisted &'static str -- safe to interpolate
+    let mut sql = format!(
+        "SELECT \
+         CAST((julianday(?) - julianday(r.timestamp)) * ? AS INTEGER) as bucket, \
+         {} as group_key, \
+         TOTAL(r.cost_sats) as cost_sats \
+         FROM requests r",
+        group_expr
+    );
+
+    if matches!(grouping, SpendGrouping::Tag(_)) {
+        sql.push_str(
+            " LEFT JOIN request_tags t ON t.correlation_id = r.correlation_id AND t.key = ?",
+        );
+    }
+    sql.push_str(" WHERE r.timestamp >= ? AND r.timestamp <= ?");
+    if model.is_some() {
+        sql.push_str(" AND LOWER(r.model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(r.provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(
+            " AND r.correlation_id IN (SELECT correlation_id FROM request_tags WHERE key = ? AND value = ?)",
+        );
+    }
+    sql.push_str(" GROUP BY bucket, group_key");
+
+    let mut query = sqlx::query_as::<_, SpendBucketRow>(&sql)
+        .bind(until)
+        .bind(buckets_per_day);
+
+    if let SpendGrouping::Tag(key) = grouping {
+        query = query.bind(key);
+    }
+    query = query.bind(since).bind(until);
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_all(pool).await
+}
+
+/// Fiat cost totals for one currency over a time range.
+#[derive(Default, sqlx::FromRow)]
+pub struct FiatCostRow {
+    pub total_cost: f64,
+    /// Requests with a sats cost but no rate stored in this currency.
+    pub unpriced_requests: i64,
+}
+
+/// Sum request costs in `currency` at the rate stored with each request,
+/// with optional model/provider/tag filters.
+pub async fn query_fiat_cost(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    currency: &str,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<FiatCostRow, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT \
+         TOTAL(CASE WHEN fiat_currency = ? THEN cost_sats * fiat_rate / 100000000.0 END) as total_cost, \
+         COUNT(CASE WHEN cost_sats IS NOT NULL AND (fiat_rate IS NULL OR fiat_currency IS NOT ?) \
+         THEN 1 END) as unpriced_requests \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ?",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+
+    let mut query = sqlx::query_as::<_, FiatCostRow>(&sql)
+        .bind(currency)
+        .bind(currency)
+        .bind(since)
+        .bind(until);
+
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
+
+    query.fetch_one(pool).await
+}
+
+/// Streaming throughput of one provider.
+#[derive(sqlx::FromRow)]
+pub struct ThroughputRow {
+    pub provider: String,
+    pub avg_tokens_per_second: f64,
+    /// Streams with a measured rate.
+    pub streams: i64,
+}
+
+/// Average `tokens_per_second` per provider over streams that recorded
+/// one, with optional model/provider/tag filters.
+pub async fn query_throughput_by_provider(
+    pool: &SqlitePool,
+    since: &str,
+    until: &str,
+    model: Option<&str>,
+    provider: Option<&str>,
+    tag: Option<(&str, &str)>,
+) -> Result<Vec<ThroughputRow>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT \
+         provider, \
+         AVG(tokens_per_second) as avg_tokens_per_second, \
+         COUNT(*) as streams \
+         FROM requests WHERE timestamp >= ? AND timestamp <= ? \
+         AND tokens_per_second IS NOT NULL AND provider IS NOT NULL",
+    );
+
+    if model.is_some() {
+        sql.push_str(" AND LOWER(model) = LOWER(?)");
+    }
+    if provider.is_some() {
+        sql.push_str(" AND LOWER(provider) = LOWER(?)");
+    }
+    if tag.is_some() {
+        sql.push_str(TAG_FILTER_CLAUSE);
+    }
+    sql.push_str(" GROUP BY provider");
+
+    let mut query = sqlx::query_as::<_, ThroughputRow>(&sql)
+        .bind(since)
+        .bind(until);
+    if let Some(m) = model {
+        query = query.bind(m);
+    }
+    if let Some(p) = provider {
+        query = query.bind(p);
+    }
+    if let Some((k, v)) = tag {
+        query = query.bind(k).bind(v);
+    }
 
     query.fetch_all(pool).await
 }
@@ -0,0 +1,165 @@
+//! Persistence for prepaid request vouchers.
+//!
+//! Vouchers live in `vouchers`; their spend and use are counted from the
+//! requests tagged with the voucher ID, so a restart picks up where the
+//! running totals left off.
+
+use sqlx::SqlitePool;
+
+/// Tag key carrying the voucher ID on request rows.
+pub const VOUCHER_TAG: &str = "voucher";
+
+/// A stored voucher with its spend and use so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoucherRow {
+    pub id: String,
+    pub code: String,
+    pub created_at: String,
+    pub limit_sats: Option<f64>,
+    pub limit_requests: Option<u64>,
+    /// Allowed models; empty for any.
+    pub models: Vec<String>,
+    pub note: Option<String>,
+    pub spent_sats: f64,
+    pub requests: u64,
+}
+
+/// Store a new voucher.
+pub async fn insert(pool: &SqlitePool, voucher: &VoucherRow) -> Result<(), sqlx::Error> {
+    let models = (!voucher.models.is_empty())
+        .then(|| serde_json::to_string(&voucher.models).unwrap_or_default());
+    sqlx::query(
+        "INSERT INTO vouchers (id, code, created_at, limit_sats, limit_requests, models, note) \
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&voucher.id)
+    .bind(&voucher.code)
+    .bind(&voucher.created_at)
+    .bind(voucher.limit_sats)
+    .bind(voucher.limit_requests.map(|n| n as i64))
+    .bind(models)
+    .bind(&voucher.note)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Load every voucher with the spend and requests logged against it.
+pub async fn load(pool: &SqlitePool) -> Result<Vec<VoucherRow>, sqlx::Error> {
+    type Row = (
+        String,
+        String,
+        String,
+        Option<f64>,
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        f64,
+        i64,
+    );
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT v.id, v.code, v.created_at, v.limit_sats, v.limit_requests, v.models, v.note, \
+         (SELECT TOTAL(r.cost_sats) FROM requests r WHERE r.correlation_id IN \
+          (SELECT correlation_id FROM request_tags WHERE key = ? AND value = v.id)), \
+         (SELECT COUNT(DISTINCT correlation_id) FROM request_tags WHERE key = ? AND value = v.id) \
+         FROM vouchers v ORDER BY v.created_at, v.id",
+    )
+    .bind(VOUCHER_TAG)
+    .bind(VOUCHER_TAG)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, code, created_at, limit_sats, limit_requests, models, note, spent, requests)| {
+                VoucherRow {
+                    id,
+                    code,
+                    created_at,
+                    limit_sats,
+                    limit_requests: limit_requests.map(|n| n.max(0) as u64),
+                    models: models
+                        .and_then(|m| serde_json::from_str(&m).ok())
+                        .unwrap_or_default(),
+                    note,
+                    spent_sats: spent,
+                    requests: requests.max(0) as u64,
+                }
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory;
+    use crate::storage::RequestLog;
+
+    fn log(id: &str, voucher: &str, cost: f64) -> RequestLog {
+        RequestLog {
+            correlation_id: id.to_string(),
+            timestamp: "2026-10-14T00:00:00Z".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: Some("alpha".to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: Some(10),
+            output_tokens: Some(20),
+            cost_sats: Some(cost),
+            provider_cost_sats: None,
+            latency_ms: 5,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![(VOUCHER_TAG.to_string(), voucher.to_string())],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn load_counts_tagged_requests_once() {
+        let pool = memory::init_pool().await.unwrap();
+        let voucher = VoucherRow {
+            id: "v-trial".to_string(),
+            code: "arbv_secret".to_string(),
+            created_at: "2026-10-14T00:00:00Z".to_string(),
+            limit_sats: Some(500.0),
+            limit_requests: None,
+            models: vec!["gpt-4o".to_string()],
+            note: Some("trial".to_string()),
+            spent_sats: 0.0,
+            requests: 0,
+        };
+        insert(&pool, &voucher).await.unwrap();
+
+        // Two rows under one request (e.g. a re-ask), one other request
+        log("req-1", "v-trial", 10.0).insert(&pool).await.unwrap();
+        log("req-1", "v-trial", 5.0).insert(&pool).await.unwrap();
+        log("req-2", "v-trial", 20.0).insert(&pool).await.unwrap();
+        log("req-3", "v-other", 99.0).insert(&pool).await.unwrap();
+
+        let loaded = load(&pool).await.unwrap();
+        assert_eq!(
+            loaded,
+            vec![VoucherRow {
+                spent_sats: 35.0,
+                requests: 2,
+                ..voucher
+            }]
+        );
+    }
+}
@@ -3,9 +3,19 @@
 //! Replaces fire-and-forget `tokio::spawn` writes with a bounded mpsc channel
 //! and a dedicated writer task. This prevents unbounded queue growth under load
 //! and provides backpressure when the channel fills up.
+//!
+//! With `logging.mode = "strict"`, request rows are written through
+//! [`DbWriter::log_write_confirmed`] instead, which waits for the insert and
+//! reports failure so the request can be rejected. The time requests spend
+//! waiting is tracked in [`ConfirmedWriteStats`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use serde::Serialize;
 use sqlx::SqlitePool;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 use super::logging::RequestLog;
 
@@ -14,8 +24,9 @@ const DEFAULT_CAPACITY: usize = 1024;
 
 /// Commands that the writer task processes.
 enum WriteCommand {
-    /// Insert a new request log row.
-    Insert(RequestLog),
+    /// Insert a new request log row, reporting whether it was written when
+    /// a sender is given.
+    Insert(Box<RequestLog>, Option<oneshot::Sender<bool>>),
     /// Update usage data on an existing row.
     UpdateUsage {
         correlation_id: String,
@@ -23,6 +34,18 @@ enum WriteCommand {
         output_tokens: Option<u32>,
         cost_sats: Option<f64>,
     },
+    /// Store a request's compressed messages in the prompt archive.
+    ArchivePrompt {
+        correlation_id: String,
+        timestamp: String,
+        model: String,
+        prompt_br: Vec<u8>,
+    },
+    /// Store a completed request's signed receipt.
+    StoreReceipt {
+        correlation_id: String,
+        receipt: String,
+    },
     /// Update stream completion data on an existing row.
     UpdateStreamCompletion {
         correlation_id: String,
@@ -34,6 +57,8 @@ enum WriteCommand {
         error_message: Option<String>,
         complexity_score: Option<f64>,
         tier: Option<String>,
+        finish_reason: Option<String>,
+        tokens_per_second: Option<f64>,
     },
 }
 
@@ -45,6 +70,26 @@ enum WriteCommand {
 #[derive(Clone)]
 pub struct DbWriter {
     tx: mpsc::Sender<WriteCommand>,
+    confirmed: Arc<ConfirmedWrites>,
+}
+
+/// Process-lifetime counters for confirmed (strict mode) writes.
+#[derive(Debug, Default)]
+struct ConfirmedWrites {
+    written: AtomicU64,
+    failed: AtomicU64,
+    wait_us_total: AtomicU64,
+    wait_us_max: AtomicU64,
+}
+
+/// Confirmed writes and the time requests waited for them.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfirmedWriteStats {
+    pub written: u64,
+    /// Writes that failed or timed out; each rejected its request.
+    pub failed: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
 }
 
 impl DbWriter {
@@ -56,13 +101,93 @@ impl DbWriter {
     /// Spawn the writer task with a custom channel capacity.
     pub fn with_capacity(pool: SqlitePool, capacity: usize) -> Self {
         let (tx, rx) = mpsc::channel(capacity);
-        tokio::spawn(writer_loop(pool, rx));
-        DbWriter { tx }
+        tokio::spawn(writer_loop(pool, rx, None));
+        DbWriter {
+            tx,
+            confirmed: Arc::default(),
+        }
+    }
+
+    /// Spawn a writer that keeps at most about `max_rows` request rows,
+    /// pruning the oldest (used by the in-memory backend).
+    pub fn with_row_limit(pool: SqlitePool, max_rows: u64) -> Self {
+        let (tx, rx) = mpsc::channel(DEFAULT_CAPACITY);
+        tokio::spawn(writer_loop(pool, rx, Some(max_rows)));
+        DbWriter {
+            tx,
+            confirmed: Arc::default(),
+        }
+    }
+
+    /// Number of writes currently queued and not yet processed.
+    pub fn queue_depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Maximum number of writes the channel can hold.
+    pub fn queue_capacity(&self) -> usize {
+        self.tx.max_capacity()
+    }
+
+    /// Confirmed write counters and wait times.
+    pub fn confirmed_stats(&self) -> ConfirmedWriteStats {
+        let written = self.confirmed.written.load(Ordering::Relaxed);
+        let failed = self.confirmed.failed.load(Ordering::Relaxed);
+        let total_us = self.confirmed.wait_us_total.load(Ordering::Relaxed);
+        let attempts = written + failed;
+        ConfirmedWriteStats {
+            written,
+            failed,
+            avg_wait_ms: if attempts == 0 {
+                0.0
+            } else {
+                total_us as f64 / attempts as f64 / 1000.0
+            },
+            max_wait_ms: self.confirmed.wait_us_max.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+
+    /// Insert a request log row and wait for it to be written.
+    ///
+    /// Waits for room in the channel rather than dropping the write. Returns
+    /// an error when the insert fails or doesn't finish within `timeout`.
+    pub async fn log_write_confirmed(
+        &self,
+        log: RequestLog,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let start = Instant::now();
+        let (ack_tx, ack_rx) = oneshot::channel();
+        let result = tokio::time::timeout(timeout, async {
+            self.tx
+                .send(WriteCommand::Insert(Box::new(log), Some(ack_tx)))
+                .await
+                .map_err(|_| "DB writer channel closed".to_string())?;
+            match ack_rx.await {
+                Ok(true) => Ok(()),
+                Ok(false) => Err("insert failed".to_string()),
+                Err(_) => Err("DB writer stopped before the insert".to_string()),
+            }
+        })
+        .await
+        .unwrap_or_else(|_| Err(format!("insert not confirmed within {:?}", timeout)));
+
+        let wait_us = start.elapsed().as_micros() as u64;
+        let confirmed = &self.confirmed;
+        confirmed
+            .wait_us_total
+            .fetch_add(wait_us, Ordering::Relaxed);
+        confirmed.wait_us_max.fetch_max(wait_us, Ordering::Relaxed);
+        match &result {
+            Ok(()) => confirmed.written.fetch_add(1, Ordering::Relaxed),
+            Err(_) => confirmed.failed.fetch_add(1, Ordering::Relaxed),
+        };
+        result
     }
 
     /// Queue a request log insert. Drops the write if the channel is full.
     pub fn log_write(&self, log: RequestLog) {
-        if let Err(e) = self.tx.try_send(WriteCommand::Insert(log)) {
+        if let Err(e) = self.tx.try_send(WriteCommand::Insert(Box::new(log), None)) {
             match e {
                 mpsc::error::TrySendError::Full(_) => {
                     tracing::warn!("DB writer channel full, dropping log write");
@@ -99,6 +224,48 @@ impl DbWriter {
         }
     }
 
+    /// Queue a prompt archive insert. Drops the write if the channel is full.
+    pub fn archive_prompt(
+        &self,
+        correlation_id: String,
+        timestamp: String,
+        model: String,
+        prompt_br: Vec<u8>,
+    ) {
+        if let Err(e) = self.tx.try_send(WriteCommand::ArchivePrompt {
+            correlation_id,
+            timestamp,
+            model,
+            prompt_br,
+        }) {
+            match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    tracing::warn!("DB writer channel full, dropping prompt archive write");
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    tracing::warn!("DB writer channel closed, dropping prompt archive write");
+                }
+            }
+        }
+    }
+
+    /// Queue a signed receipt insert. Drops the write if the channel is full.
+    pub fn store_receipt(&self, correlation_id: String, receipt: String) {
+        if let Err(e) = self.tx.try_send(WriteCommand::StoreReceipt {
+            correlation_id,
+            receipt,
+        }) {
+            match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    tracing::warn!("DB writer channel full, dropping receipt write");
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    tracing::warn!("DB writer channel closed, dropping receipt write");
+                }
+            }
+        }
+    }
+
     /// Queue a stream completion update. Drops the write if the channel is full.
     #[allow(clippy::too_many_arguments)]
     pub fn stream_completion_update(
@@ -112,6 +279,8 @@ impl DbWriter {
         error_message: Option<String>,
         complexity_score: Option<f64>,
         tier: Option<String>,
+        finish_reason: Option<String>,
+        tokens_per_second: Option<f64>,
     ) {
         if let Err(e) = self.tx.try_send(WriteCommand::UpdateStreamCompletion {
             correlation_id,
@@ -123,6 +292,8 @@ impl DbWriter {
             error_message,
             complexity_score,
             tier,
+            finish_reason,
+            tokens_per_second,
         }) {
             match e {
                 mpsc::error::TrySendError::Full(_) => {
@@ -137,17 +308,40 @@ impl DbWriter {
 }
 
 /// Background task that processes write commands sequentially.
-async fn writer_loop(pool: SqlitePool, mut rx: mpsc::Receiver<WriteCommand>) {
+async fn writer_loop(
+    pool: SqlitePool,
+    mut rx: mpsc::Receiver<WriteCommand>,
+    max_rows: Option<u64>,
+) {
+    let mut inserts: u64 = 0;
     while let Some(cmd) = rx.recv().await {
         match cmd {
-            WriteCommand::Insert(log) => {
-                if let Err(e) = log.insert(&pool).await {
+            WriteCommand::Insert(log, ack) => {
+                let result = log.insert(&pool).await;
+                if let Err(e) = &result {
                     tracing::warn!(
                         correlation_id = %log.correlation_id,
                         error = %e,
                         "Failed to write request log to database"
                     );
                 }
+                if let Some(ack) = ack {
+                    let _ = ack.send(result.is_ok());
+                }
+                inserts += 1;
+                if let Some(max_rows) =
+                    max_rows.filter(|_| inserts.is_multiple_of(super::memory::PRUNE_EVERY))
+                {
+                    match super::memory::prune(&pool, max_rows).await {
+                        Ok(0) => {}
+                        Ok(deleted) => {
+                            tracing::debug!(deleted = deleted, "Pruned oldest request logs");
+                        }
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Failed to prune request logs");
+                        }
+                    }
+                }
             }
             WriteCommand::UpdateUsage {
                 correlation_id,
@@ -185,6 +379,35 @@ async fn writer_loop(pool: SqlitePool, mut rx: mpsc::Receiver<WriteCommand>) {
                     }
                 }
             }
+            WriteCommand::ArchivePrompt {
+                correlation_id,
+                timestamp,
+                model,
+                prompt_br,
+            } => {
+                if let Err(e) =
+                    super::archive::insert(&pool, &correlation_id, &timestamp, &model, &prompt_br)
+                        .await
+                {
+                    tracing::warn!(
+                        correlation_id = %correlation_id,
+                        error = %e,
+                        "Failed to write prompt to archive"
+                    );
+                }
+            }
+            WriteCommand::StoreReceipt {
+                correlation_id,
+                receipt,
+            } => {
+                if let Err(e) = super::receipts::insert(&pool, &correlation_id, &receipt).await {
+                    tracing::warn!(
+                        correlation_id = %correlation_id,
+                        error = %e,
+                        "Failed to write receipt"
+                    );
+                }
+            }
             WriteCommand::UpdateStreamCompletion {
                 correlation_id,
                 input_tokens,
@@ -195,6 +418,8 @@ async fn writer_loop(pool: SqlitePool, mut rx: mpsc::Receiver<WriteCommand>) {
                 error_message,
                 complexity_score,
                 tier,
+                finish_reason,
+                tokens_per_second,
             } => {
                 match super::logging::update_stream_completion(
                     &pool,
@@ -207,6 +432,8 @@ async fn writer_loop(pool: SqlitePool, mut rx: mpsc::Receiver<WriteCommand>) {
                     error_message.as_deref(),
                     complexity_score,
                     tier.as_deref(),
+                    finish_reason.as_deref(),
+                    tokens_per_second,
                 )
                 .await
                 {
@@ -246,6 +473,14 @@ mod tests {
         pool
     }
 
+    #[tokio::test]
+    async fn queue_depth_starts_empty() {
+        let pool = test_pool().await;
+        let writer = DbWriter::with_capacity(pool, 8);
+        assert_eq!(writer.queue_depth(), 0);
+        assert_eq!(writer.queue_capacity(), 8);
+    }
+
     #[tokio::test]
     async fn writer_processes_insert() {
         let pool = test_pool().await;
@@ -265,9 +500,21 @@ mod tests {
             latency_ms: 50,
             success: true,
             error_status: None,
+            error_type: None,
             error_message: None,
             complexity_score: None,
             tier: None,
+            finish_reason: None,
+            tags: Vec::new(),
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
         });
 
         // Give the writer task time to process
@@ -302,9 +549,21 @@ mod tests {
             latency_ms: 50,
             success: true,
             error_status: None,
+            error_type: None,
             error_message: None,
             complexity_score: None,
             tier: None,
+            finish_reason: None,
+            tags: Vec::new(),
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
         });
 
         // Let insert complete
@@ -321,6 +580,8 @@ mod tests {
             None,
             None,
             None,
+            Some("length".to_string()),
+            Some(120.0),
         );
 
         tokio::time::sleep(std::time::Duration::from_millis(50)).await;
@@ -337,5 +598,77 @@ mod tests {
         assert_eq!(row.1, Some(300));
         assert!((row.2.unwrap() - 42.5).abs() < f64::EPSILON);
         assert_eq!(row.3, Some(2500));
+
+        let finish_reason: Option<String> = sqlx::query_scalar(
+            "SELECT finish_reason FROM requests WHERE correlation_id = 'writer-test-002'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(finish_reason.as_deref(), Some("length"));
+    }
+
+    #[tokio::test]
+    async fn confirmed_writes_report_failure() {
+        let pool = test_pool().await;
+        let writer = DbWriter::new(pool.clone());
+        let log = |id: &str| RequestLog {
+            correlation_id: id.to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: Some("test-provider".to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: Some(100),
+            output_tokens: Some(200),
+            cost_sats: Some(10.0),
+            provider_cost_sats: None,
+            latency_ms: 50,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: Vec::new(),
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: None,
+            client_request_id: None,
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: None,
+        };
+
+        // Written before the call returns
+        writer
+            .log_write_confirmed(log("writer-test-003"), Duration::from_secs(5))
+            .await
+            .unwrap();
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM requests WHERE correlation_id = 'writer-test-003'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+
+        // Without the table the insert fails
+        sqlx::query("DROP TABLE requests")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let err = writer
+            .log_write_confirmed(log("writer-test-004"), Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "insert failed");
+
+        let stats = writer.confirmed_stats();
+        assert_eq!((stats.written, stats.failed), (1, 1));
+        assert!(stats.max_wait_ms >= stats.avg_wait_ms);
     }
 }
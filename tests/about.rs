@@ -0,0 +1,59 @@
+//! Integration tests for `GET /v1/about`.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+async fn about(app: axum::Router, token: Option<&str>) -> (http::StatusCode, serde_json::Value) {
+    let mut request = Request::get("/v1/about");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn reports_build_config_and_features() {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        common::test_provider("alpha"),
+        common::test_provider("beta"),
+    ];
+    config.archive.enabled = true;
+    let expected_hash = arbstr::proxy::about::config_hash(&config);
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    let (status, body) = about(app, None).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!body["commit"].as_str().unwrap().is_empty());
+    assert_eq!(body["config_hash"], expected_hash);
+    assert!(body["uptime_secs"].as_u64().is_some());
+    assert!(body["started_at"].as_str().unwrap().ends_with('Z'));
+    assert_eq!(body["providers"], 2);
+    assert_eq!(body["policies"], 0);
+    assert_eq!(body["storage"], "sqlite");
+    assert_eq!(body["features"]["db"], true);
+    assert_eq!(body["features"]["archive"], true);
+    assert_eq!(body["features"]["auth"], false);
+    assert_eq!(body["features"]["vault"], false);
+}
+
+#[tokio::test]
+async fn requires_the_admin_token() {
+    let mut config = common::db_test_config();
+    config.server.admin_token = Some("ops-token".to_string());
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    let (status, _) = about(app.clone(), None).await;
+    assert_eq!(status, 401);
+    let (status, body) = about(app, Some("ops-token")).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["features"]["admin_auth"], true);
+}
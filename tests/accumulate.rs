@@ -0,0 +1,173 @@
+//! Integration tests for response buffering (`X-Arbstr-Accumulate` and the
+//! policy `accumulate` flag).
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const STREAM: &str = "data: {\"id\":\"c\",\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n\
+     data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}],\
+     \"usage\":{\"prompt_tokens\":10,\"completion_tokens\":5,\"total_tokens\":15}}\n\n\
+     data: [DONE]\n\n";
+
+async fn mock_streaming_provider(body: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(server: &MockServer) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "buffered".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: true,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    create_router(state)
+}
+
+async fn complete(
+    app: axum::Router,
+    stream: bool,
+    headers: &[(&str, &str)],
+) -> axum::response::Response {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    app.oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn header_returns_one_json_completion() {
+    let server = mock_streaming_provider(STREAM).await;
+    let app = setup_app(&server).await;
+    let response = complete(app.clone(), false, &[("x-arbstr-accumulate", "true")]).await;
+    assert!(response.headers().get("x-arbstr-streaming").is_none());
+    assert_eq!(response.headers()["x-arbstr-provider"], "upstream");
+    assert!(response.headers().contains_key("x-arbstr-cost-sats"));
+    assert!(response.headers().contains_key("x-arbstr-latency-ms"));
+
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200, "{}", json);
+    assert_eq!(json["object"], "chat.completion");
+    assert_eq!(json["choices"][0]["message"]["content"], "Hello");
+    assert_eq!(json["choices"][0]["finish_reason"], "stop");
+    assert_eq!(json["usage"]["completion_tokens"], 5);
+    assert!(json["arbstr"]["cost_sats"].as_f64().unwrap() > 0.0);
+
+    // The provider was asked to stream
+    let requests = server.received_requests().await.unwrap();
+    let upstream: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(upstream["stream"], true);
+
+    let response = app
+        .oneshot(
+            Request::get("/v1/requests/recent")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, body) = common::parse_body(response).await;
+    assert_eq!(body["data"][0]["streaming"], true);
+    assert_eq!(body["data"][0]["tags"]["accumulated"], "true");
+}
+
+#[tokio::test]
+async fn policy_flag_accumulates_and_header_opts_out() {
+    let server = mock_streaming_provider(STREAM).await;
+    let app = setup_app(&server).await;
+    let response = complete(app.clone(), false, &[("x-arbstr-policy", "buffered")]).await;
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["choices"][0]["message"]["content"], "Hello");
+
+    complete(
+        app,
+        false,
+        &[
+            ("x-arbstr-policy", "buffered"),
+            ("x-arbstr-accumulate", "false"),
+        ],
+    )
+    .await;
+    let requests = server.received_requests().await.unwrap();
+    let upstream: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(upstream["stream"], false);
+}
+
+#[tokio::test]
+async fn streaming_requests_stay_streams() {
+    let server = mock_streaming_provider(STREAM).await;
+    let app = setup_app(&server).await;
+    let response = complete(app, true, &[("x-arbstr-accumulate", "true")]).await;
+    assert_eq!(response.headers()["x-arbstr-streaming"], "true");
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+}
+
+#[tokio::test]
+async fn cut_short_stream_is_a_provider_error() {
+    let server = mock_streaming_provider(
+        "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+    )
+    .await;
+    let app = setup_app(&server).await;
+    let response = complete(app, false, &[("x-arbstr-accumulate", "true")]).await;
+    assert_eq!(response.headers()["x-arbstr-provider"], "upstream");
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 502, "{}", json);
+    assert_eq!(json["error"]["code"], "provider_malformed_response");
+}
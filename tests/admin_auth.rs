@@ -0,0 +1,107 @@
+//! Integration tests for the admin token: analytics, logs, and `/admin`
+//! endpoints are split from the client-facing proxy routes.
+
+mod common;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+
+async fn app(auth_token: Option<&str>, admin_token: Option<&str>) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.server.auth_token = auth_token.map(String::from);
+    config.server.admin_token = admin_token.map(String::from);
+    common::setup_db_test_app_with_config(config).await.0
+}
+
+async fn get(app: &axum::Router, uri: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::get(uri);
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+const ANALYTICS: &[&str] = &[
+    "/v1/stats?since=2020-01-01T00:00:00Z&until=2100-01-01T00:00:00Z",
+    "/v1/requests",
+    "/v1/requests/recent",
+    "/v1/stats/compression",
+    "/providers",
+];
+
+#[tokio::test]
+async fn admin_token_guards_analytics_and_admin_routes() {
+    let app = app(Some("client-key"), Some("admin-key")).await;
+
+    for uri in ANALYTICS.iter().chain(&["/admin/db"]) {
+        assert_eq!(
+            get(&app, uri, None).await,
+            StatusCode::UNAUTHORIZED,
+            "{}",
+            uri
+        );
+        assert_eq!(
+            get(&app, uri, Some("client-key")).await,
+            StatusCode::UNAUTHORIZED,
+            "{}",
+            uri
+        );
+        assert_eq!(
+            get(&app, uri, Some("admin-key")).await,
+            StatusCode::OK,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn client_routes_use_client_token_only() {
+    let app = app(Some("client-key"), Some("admin-key")).await;
+
+    assert_eq!(
+        get(&app, "/v1/models", Some("client-key")).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, "/v1/models", Some("admin-key")).await,
+        StatusCode::UNAUTHORIZED
+    );
+    // Probes stay open
+    assert_eq!(get(&app, "/health", None).await, StatusCode::OK);
+    assert_eq!(get(&app, "/ready", None).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn without_admin_token_admin_routes_fall_back_to_client_token() {
+    let app = app(Some("client-key"), None).await;
+
+    assert_eq!(get(&app, "/admin/db", None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        get(&app, "/admin/db", Some("client-key")).await,
+        StatusCode::OK
+    );
+    for uri in ANALYTICS {
+        assert_eq!(get(&app, uri, None).await, StatusCode::OK, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn admin_token_alone_leaves_client_routes_open() {
+    let app = app(None, Some("admin-key")).await;
+
+    assert_eq!(get(&app, "/v1/models", None).await, StatusCode::OK);
+    assert_eq!(
+        get(&app, "/v1/requests", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get(&app, "/admin/ledger", Some("admin-key")).await,
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,103 @@
+//! Integration tests for `server.analytics_listen`: analytics served on a
+//! separate, read-only router and removed from the proxy one.
+
+mod common;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+
+use arbstr::proxy::{create_scoped_router, RouterScope};
+
+const STATS: &str = "/v1/stats?since=2020-01-01T00:00:00Z&until=2100-01-01T00:00:00Z";
+
+async fn status(app: &axum::Router, method: &str, uri: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn router(scope: RouterScope) -> axum::Router {
+    let (state, _pool) = common::setup_db_test_state(common::db_test_config()).await;
+    create_scoped_router(state, scope)
+}
+
+#[tokio::test]
+async fn analytics_router_serves_only_read_only_endpoints() {
+    let app = router(RouterScope::Analytics).await;
+
+    for uri in [
+        STATS,
+        "/v1/requests",
+        "/v1/requests/recent",
+        "/providers",
+        "/health",
+    ] {
+        assert_eq!(status(&app, "GET", uri).await, StatusCode::OK, "{}", uri);
+    }
+    assert_eq!(
+        status(&app, "POST", "/v1/chat/completions").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status(&app, "GET", "/v1/models").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status(&app, "GET", "/admin/db").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status(&app, "POST", "/admin/db/checkpoint").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn proxy_router_drops_analytics() {
+    let app = router(RouterScope::Proxy).await;
+
+    assert_eq!(status(&app, "GET", "/v1/models").await, StatusCode::OK);
+    assert_eq!(status(&app, "GET", "/admin/db").await, StatusCode::OK);
+    assert_eq!(status(&app, "GET", "/health").await, StatusCode::OK);
+    for uri in [STATS, "/v1/requests", "/v1/route/explain?model=gpt-4o"] {
+        assert_eq!(
+            status(&app, "GET", uri).await,
+            StatusCode::NOT_FOUND,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn analytics_router_keeps_admin_token() {
+    let mut config = common::db_test_config();
+    config.server.admin_token = Some("admin-key".to_string());
+    let (state, _pool) = common::setup_db_test_state(config).await;
+    let app = create_scoped_router(state, RouterScope::Analytics);
+
+    assert_eq!(
+        status(&app, "GET", "/v1/requests").await,
+        StatusCode::UNAUTHORIZED
+    );
+    let response = app
+        .oneshot(
+            Request::get("/v1/requests")
+                .header("authorization", "Bearer admin-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
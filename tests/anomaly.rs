@@ -0,0 +1,148 @@
+//! Integration tests for cost anomaly detection and its /v1/stats listing.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::AnomalyConfig;
+use arbstr::proxy::anomaly::check;
+use arbstr::proxy::create_router;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Insert a gpt-4o request on alpha, tagged `team=team-a`.
+async fn seed(pool: &SqlitePool, id: &str, minutes_ago: i64, cost: f64) {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::minutes(minutes_ago))
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         cost_sats, latency_ms, success) VALUES (?, ?, 'gpt-4o', 'alpha', 0, ?, 100, 1)",
+    )
+    .bind(id)
+    .bind(&timestamp)
+    .bind(cost)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+    sqlx::query(
+        "INSERT INTO request_tags (correlation_id, key, value) VALUES (?, 'team', 'team-a')",
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .expect("Failed to seed tag");
+}
+
+/// Webhook requests once `n` have arrived (alerts are POSTed in the
+/// background).
+async fn wait_for_alerts(webhook: &MockServer, n: usize) -> Vec<wiremock::Request> {
+    let mut received = Vec::new();
+    for _ in 0..100 {
+        received = webhook.received_requests().await.unwrap();
+        if received.len() >= n {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    received
+}
+
+async fn get_stats(app: axum::Router) -> serde_json::Value {
+    let response = app
+        .oneshot(Request::get("/v1/stats").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+#[tokio::test]
+async fn spike_is_alerted_listed_in_stats_and_resolved() {
+    let webhook = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/alert"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&webhook)
+        .await;
+    let anomaly = AnomalyConfig {
+        interval_secs: 300,
+        bucket_minutes: 60,
+        baseline_buckets: 4,
+        z_threshold: 3.0,
+        min_cost_sats: 100.0,
+        tenant_tag: Some("team".to_string()),
+        webhook_url: Some(format!("{}/alert", webhook.uri())),
+    };
+    let mut config = common::db_test_config();
+    config.anomaly = Some(anomaly.clone());
+    let (state, pool) = common::setup_db_test_state(config).await;
+
+    // Steady 100-140 sats an hour, then 2000 in the last half hour
+    for (hour, cost) in [(1, 100.0), (2, 140.0), (3, 100.0), (4, 140.0)] {
+        seed(&pool, &format!("baseline-{}", hour), hour * 60 + 30, cost).await;
+    }
+    seed(&pool, "spike", 30, 2000.0).await;
+
+    check(&state, &pool, &anomaly).await.unwrap();
+
+    let body = get_stats(create_router(state.clone())).await;
+    let anomalies = body["anomalies"].as_array().unwrap();
+    let keys: Vec<(&str, &str)> = anomalies
+        .iter()
+        .map(|a| (a["dimension"].as_str().unwrap(), a["key"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            ("model", "gpt-4o"),
+            ("provider", "alpha"),
+            ("tenant", "team-a")
+        ]
+    );
+    assert_eq!(anomalies[0]["cost_sats"], 2000.0);
+    assert_eq!(anomalies[0]["baseline_mean_sats"], 120.0);
+    assert_eq!(anomalies[0]["z_score"], 94.0);
+
+    let received = wait_for_alerts(&webhook, 3).await;
+    assert_eq!(received.len(), 3);
+    let alert: serde_json::Value = received
+        .iter()
+        .map(|r| r.body_json::<serde_json::Value>().unwrap())
+        .find(|a| a["dimension"] == "tenant")
+        .unwrap();
+    assert_eq!(alert["event"], "cost_anomaly");
+    assert_eq!(alert["dimension"], "tenant");
+    assert_eq!(alert["key"], "team-a");
+
+    // Still spiking: no repeat alerts
+    check(&state, &pool, &anomaly).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(webhook.received_requests().await.unwrap().len(), 3);
+
+    sqlx::query("DELETE FROM requests WHERE correlation_id = 'spike'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    check(&state, &pool, &anomaly).await.unwrap();
+
+    let received = wait_for_alerts(&webhook, 6).await;
+    assert_eq!(received.len(), 6);
+    for request in &received[3..] {
+        let alert: serde_json::Value = request.body_json().unwrap();
+        assert_eq!(alert["event"], "cost_anomaly_resolved");
+    }
+    let body = get_stats(create_router(state)).await;
+    assert!(body["anomalies"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn stats_omit_anomalies_without_config() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let body = get_stats(app).await;
+    assert!(body.get("anomalies").is_none());
+}
@@ -0,0 +1,177 @@
+//! Integration tests for GET /v1/arbitrage and the background analyzer.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{ArbitrageConfig, ProviderConfig};
+use arbstr::proxy::arbitrage::arbitrage_loop;
+use arbstr::proxy::create_router;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn seed(
+    pool: &SqlitePool,
+    id: &str,
+    hours_ago: i64,
+    provider: &str,
+    output_tokens: i64,
+    cost: f64,
+    success: bool,
+) {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(hours_ago))
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         input_tokens, output_tokens, cost_sats, latency_ms, success) \
+         VALUES (?, ?, 'gpt-4o', ?, 0, 0, ?, ?, 100, ?)",
+    )
+    .bind(id)
+    .bind(&timestamp)
+    .bind(provider)
+    .bind(output_tokens)
+    .bind(cost)
+    .bind(success)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+}
+
+/// 60% of the last day's gpt-4o traffic on mock-expensive (30 sats/1k
+/// output), 40% on mock-cheap (10 sats/1k), plus rows the analysis ignores.
+async fn setup() -> (arbstr::proxy::AppState, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            output_rate: 30.0,
+            input_rate: 0.0,
+            ..common::test_provider("mock-expensive")
+        },
+        ProviderConfig {
+            output_rate: 10.0,
+            input_rate: 0.0,
+            ..common::test_provider("mock-cheap")
+        },
+    ];
+    let (state, pool) = common::setup_db_test_state(config).await;
+    for i in 0..6 {
+        seed(
+            &pool,
+            &format!("exp-{}", i),
+            2,
+            "mock-expensive",
+            100_000,
+            3000.0,
+            true,
+        )
+        .await;
+    }
+    for i in 0..4 {
+        seed(
+            &pool,
+            &format!("cheap-{}", i),
+            2,
+            "mock-cheap",
+            100_000,
+            1000.0,
+            true,
+        )
+        .await;
+    }
+    seed(&pool, "old", 30, "mock-expensive", 100_000, 3000.0, true).await;
+    seed(&pool, "failed", 2, "mock-expensive", 0, 0.0, false).await;
+    (state, pool)
+}
+
+#[tokio::test]
+async fn report_flags_traffic_on_the_dearer_provider() {
+    let (state, _pool) = setup().await;
+    let app = create_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/v1/arbitrage").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["window_hours"], 24);
+    assert_eq!(body["opportunities"], 1);
+
+    let model = &body["models"][0];
+    assert_eq!(model["model"], "gpt-4o");
+    assert_eq!(model["requests"], 10);
+    assert_eq!(model["cost_sats"], 22000.0);
+    assert_eq!(model["cheapest_provider"], "mock-cheap");
+    assert_eq!(model["savings_sats_per_day"], 12000.0);
+    assert_eq!(model["opportunity"], true);
+    assert_eq!(model["providers"][0]["provider"], "mock-expensive");
+    assert_eq!(model["providers"][0]["share"], 0.6);
+    assert!(model["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("mock-expensive served 60% of gpt-4o traffic while mock-cheap was healthy"));
+
+    // The older row falls inside a 48h window
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/arbitrage?window_hours=48")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, body) = common::parse_body(response).await;
+    assert_eq!(body["models"][0]["requests"], 11);
+
+    let response = app
+        .oneshot(
+            Request::get("/v1/arbitrage?window_hours=0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn background_analyzer_posts_new_opportunities() {
+    let webhook = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/alert"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&webhook)
+        .await;
+    let (state, pool) = setup().await;
+    let config = ArbitrageConfig {
+        enabled: true,
+        webhook_url: Some(format!("{}/alert", webhook.uri())),
+        ..ArbitrageConfig::default()
+    };
+
+    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+    let task = tokio::spawn(arbitrage_loop(state, pool, config, cancel_rx));
+
+    let mut received = Vec::new();
+    for _ in 0..100 {
+        received = webhook.received_requests().await.unwrap();
+        if !received.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let _ = cancel_tx.send(true);
+    task.await.unwrap();
+
+    assert_eq!(received.len(), 1);
+    let alert: serde_json::Value = received[0].body_json().unwrap();
+    assert_eq!(alert["event"], "arbitrage_opportunity");
+    assert_eq!(alert["model"], "gpt-4o");
+    assert_eq!(alert["cheapest_provider"], "mock-cheap");
+}
@@ -0,0 +1,119 @@
+//! Integration tests for the prompt archive (`[archive]`) and the
+//! duplicate analysis that reads it.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_app(server: &MockServer, enabled: bool) -> (axum::Router, SqlitePool) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-archive",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.archive.enabled = enabled;
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+async fn complete(app: &axum::Router, content: &str) {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": content}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Wait for `rows` request rows, so their archive writes (queued ahead of
+/// them) are done too.
+async fn wait_for_requests(pool: &SqlitePool, rows: i64) {
+    for _ in 0..100 {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count >= rows {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn archived_prompts_feed_the_duplicate_report() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server, true).await;
+    complete(&app, "Summarize the incident report for the weekly review").await;
+    complete(&app, "summarize the incident report  for the weekly review").await;
+    complete(&app, "Write a haiku about routers").await;
+    wait_for_requests(&pool, 3).await;
+
+    let prompts = arbstr::storage::archive::query_range(
+        &pool,
+        "2000-01-01T00:00:00Z",
+        "2100-01-01T00:00:00Z",
+    )
+    .await
+    .unwrap();
+    assert_eq!(prompts.len(), 3);
+    assert_eq!(prompts[0].model, "gpt-4o");
+    let messages: serde_json::Value = serde_json::from_str(&prompts[2].prompt).unwrap();
+    assert_eq!(messages[0]["content"], "Write a haiku about routers");
+    assert!(prompts.iter().all(|p| p.cost_sats > 0.0));
+
+    let report = arbstr::analyze::find_duplicates(&prompts, 0.9, "last_30d", 10);
+    assert_eq!(report.clusters.len(), 1);
+    assert_eq!(report.duplicate_requests(), 1);
+    assert!((report.cacheable_sats() - prompts[1].cost_sats).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn nothing_is_archived_by_default() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server, false).await;
+    complete(&app, "hello").await;
+    wait_for_requests(&pool, 1).await;
+
+    let archived: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM prompt_archive")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(archived, 0);
+}
@@ -0,0 +1,149 @@
+//! Integration tests for quarantining providers that reject their API key.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{AuthQuarantineConfig, Config, ProviderConfig, RoutingConfig};
+use arbstr::proxy::{create_router, AppState, AuthQuarantine, CircuitBreakerRegistry};
+
+async fn mock_provider(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(status).set_body_json(serde_json::json!({
+                "id": "chatcmpl-quarantine",
+                "object": "chat.completion",
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "ok"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+            })),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+/// App with a cheap provider at `bad_url` and a pricier one at `good_url`.
+fn setup_app(bad_url: &str, good_url: &str, webhook_url: &str) -> axum::Router {
+    let providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", bad_url),
+            output_rate: 5.0,
+            ..common::test_provider("cheap")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", good_url),
+            output_rate: 20.0,
+            ..common::test_provider("backup")
+        },
+    ];
+    let quarantine = AuthQuarantineConfig {
+        webhook_url: Some(webhook_url.to_string()),
+        ..Default::default()
+    };
+    let config = Config {
+        providers: providers.clone(),
+        routing: RoutingConfig {
+            auth_quarantine: quarantine.clone(),
+            ..Default::default()
+        },
+        ..common::db_test_config()
+    };
+
+    let state = AppState {
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&[
+            "cheap".to_string(),
+            "backup".to_string(),
+        ])),
+        auth_quarantine: Arc::new(AuthQuarantine::new(quarantine)),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+async fn chat(app: &axum::Router) -> http::Response<Body> {
+    app.clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn rejected_key_quarantines_provider_and_alerts() {
+    let bad = mock_provider(401).await;
+    let good = mock_provider(200).await;
+    let webhook = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&webhook)
+        .await;
+    let app = setup_app(&bad.uri(), &good.uri(), &webhook.uri());
+
+    // First request: one attempt on the bad provider, then straight to fallback
+    let response = chat(&app).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("x-arbstr-provider").unwrap(),
+        "backup"
+    );
+    assert_eq!(
+        response.headers().get("x-arbstr-retries").unwrap(),
+        "1/cheap"
+    );
+    assert_eq!(bad.received_requests().await.unwrap().len(), 1);
+
+    // Second request skips the quarantined provider entirely
+    let response = chat(&app).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("x-arbstr-retries").is_none());
+    assert_eq!(bad.received_requests().await.unwrap().len(), 1);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, json) = common::parse_body(response).await;
+    let quarantine = &json["providers"]["cheap"]["auth_quarantine"];
+    assert_eq!(quarantine["status"], 401);
+    assert_eq!(quarantine["env_var"], "ARBSTR_CHEAP_API_KEY");
+    // Auth failures are kept apart from the 5xx circuit
+    assert_eq!(json["providers"]["cheap"]["state"], "closed");
+    assert_eq!(json["providers"]["cheap"]["failure_count"], 0);
+    assert!(json["providers"]["backup"].get("auth_quarantine").is_none());
+
+    // Exactly one alert, delivered in the background
+    let mut alerts = Vec::new();
+    for _ in 0..100 {
+        alerts = webhook.received_requests().await.unwrap();
+        if !alerts.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(alerts.len(), 1);
+    let alert: serde_json::Value = serde_json::from_slice(&alerts[0].body).unwrap();
+    assert_eq!(alert["event"], "provider_auth_quarantined");
+    assert_eq!(alert["provider"], "cheap");
+    assert_eq!(alert["env_var"], "ARBSTR_CHEAP_API_KEY");
+}
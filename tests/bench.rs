@@ -0,0 +1,67 @@
+//! Integration tests for the `arbstr bench` load generator.
+
+mod common;
+
+use arbstr::bench::{self, BenchOptions};
+use arbstr::mock_provider::MockProviderConfig;
+
+/// Serve a proxy routing to one mock provider and return its base URL.
+async fn spawn_proxy(mock: MockProviderConfig) -> String {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(mock).await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+fn options(target: String, stream: bool) -> BenchOptions {
+    BenchOptions {
+        target,
+        requests: 20,
+        concurrency: 5,
+        model: "gpt-4o".to_string(),
+        stream,
+    }
+}
+
+#[tokio::test]
+async fn bench_reports_successful_run() {
+    let target = spawn_proxy(MockProviderConfig::default()).await;
+    let report = bench::run(&reqwest::Client::new(), &options(target, false)).await;
+
+    assert_eq!(report.succeeded, 20);
+    assert_eq!(report.failed, 0);
+    assert_eq!(report.latencies.len(), 20);
+    assert_eq!(report.providers.get("mock"), Some(&20));
+    assert!(report.throughput() > 0.0);
+}
+
+#[tokio::test]
+async fn bench_times_streams_to_completion() {
+    let target = spawn_proxy(MockProviderConfig::default()).await;
+    let report = bench::run(&reqwest::Client::new(), &options(target, true)).await;
+
+    assert_eq!(report.succeeded, 20);
+    assert!(report.first_byte[0] <= report.latencies[0]);
+    assert!(
+        bench::percentile(&report.first_byte, 50.0) < bench::percentile(&report.latencies, 50.0)
+    );
+}
+
+#[tokio::test]
+async fn bench_counts_failures_by_status() {
+    let target = spawn_proxy(MockProviderConfig {
+        error_rate: 1.0,
+        error_status: 400,
+        ..Default::default()
+    })
+    .await;
+    let report = bench::run(&reqwest::Client::new(), &options(target, false)).await;
+
+    assert_eq!(report.succeeded, 0);
+    assert_eq!(report.errors.get(&502), Some(&20));
+    assert!(report.to_string().contains("HTTP 502"));
+}
@@ -0,0 +1,220 @@
+//! Integration tests for streaming large request bodies to the provider
+//! (`server.stream_body_threshold_bytes`).
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{Config, PoliciesConfig, ProviderConfig, RoutingConfig, ServerConfig};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const THRESHOLD: u64 = 1024;
+
+async fn mock_provider(stream: bool) -> MockServer {
+    let server = MockServer::start().await;
+    let template = if stream {
+        ResponseTemplate::new(200).set_body_raw(
+            "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"}}]}\n\n\
+             data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n\
+             data: [DONE]\n\n",
+            "text/event-stream",
+        )
+    } else {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-big",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 900, "completion_tokens": 5, "total_tokens": 905}
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(template)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn app_for(server: &MockServer) -> axum::Router {
+    let providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    let names: Vec<String> = providers.iter().map(|p| p.name.clone()).collect();
+    let config = Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: Some(THRESHOLD),
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: None,
+        vault: None,
+        providers: providers.clone(),
+        policies: PoliciesConfig::default(),
+        logging: Default::default(),
+        routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    };
+    let state = AppState {
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&names)),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+/// A request with `padding` bytes of image data, `model` placed before or after it.
+fn big_body(stream: bool, padding: usize, model_first: bool) -> String {
+    let image = format!("data:image/png;base64,{}", "A".repeat(padding));
+    let messages = serde_json::json!([{
+        "role": "user",
+        "content": [
+            {"type": "text", "text": "What is in this image?"},
+            {"type": "image_url", "image_url": {"url": image}}
+        ]
+    }]);
+    if model_first {
+        format!(
+            r#"{{"model": "gpt-4o", "stream": {}, "messages": {}}}"#,
+            stream, messages
+        )
+    } else {
+        format!(
+            r#"{{"stream": {}, "messages": {}, "model": "gpt-4o"}}"#,
+            stream, messages
+        )
+    }
+}
+
+/// Send `body` in 8KB chunks, as a client uploading it would.
+async fn send(app: axum::Router, body: &str) -> axum::response::Response {
+    let chunks: Vec<Result<bytes::Bytes, std::io::Error>> = body
+        .as_bytes()
+        .chunks(8 * 1024)
+        .map(|c| Ok(bytes::Bytes::copy_from_slice(c)))
+        .collect();
+    app.oneshot(
+        Request::post("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .header("content-length", body.len())
+            .body(Body::from_stream(futures::stream::iter(chunks)))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn upstream_body(server: &MockServer) -> String {
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    String::from_utf8(requests[0].body.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn large_body_is_forwarded_unmodified() {
+    let server = mock_provider(false).await;
+    let body = big_body(false, 8 * 1024, true);
+
+    let response = send(app_for(&server), &body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-arbstr-provider"], "upstream");
+    assert!(response.headers().get("x-arbstr-streaming").is_none());
+    assert!(response.headers().get("x-arbstr-cost-sats").is_some());
+
+    // Byte-for-byte: not re-serialized by arbstr
+    assert_eq!(upstream_body(&server).await, body);
+}
+
+#[tokio::test]
+async fn large_streaming_body_gets_streamed_response() {
+    let server = mock_provider(true).await;
+    let body = big_body(true, 8 * 1024, true);
+
+    let response = send(app_for(&server), &body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-arbstr-streaming"], "true");
+    let bytes = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains(r#""finish_reason":"stop""#));
+    assert!(text.contains("[DONE]"));
+
+    // No stream_options injection on a streamed-through body
+    assert_eq!(upstream_body(&server).await, body);
+}
+
+#[tokio::test]
+async fn model_after_scan_limit_falls_back_to_parsing() {
+    let server = mock_provider(false).await;
+    let body = big_body(false, 100 * 1024, false);
+
+    let response = send(app_for(&server), &body).await;
+    assert_eq!(response.status(), 200);
+
+    // Parsed and re-serialized, so the model now leads the body
+    let forwarded = upstream_body(&server).await;
+    assert_ne!(forwarded, body);
+    assert!(forwarded.starts_with(r#"{"model":"gpt-4o""#));
+}
+
+#[tokio::test]
+async fn small_body_is_parsed() {
+    let server = mock_provider(true).await;
+    let body = big_body(true, 16, true);
+    assert!((body.len() as u64) < THRESHOLD);
+
+    let response = send(app_for(&server), &body).await;
+    assert_eq!(response.status(), 200);
+    assert!(upstream_body(&server).await.contains("stream_options"));
+}
+
+#[tokio::test]
+async fn response_format_is_routed_on_the_parsed_body() {
+    let server = mock_provider(false).await;
+    let padded = big_body(false, 8 * 1024, true);
+    let body = padded.replacen(
+        r#""stream": false,"#,
+        r#""stream": false, "response_format": {"type": "json_object"},"#,
+        1,
+    );
+    assert_ne!(body, padded);
+
+    // The only provider lacks structured output, which a streamed-through
+    // body would have skipped
+    let response = send(app_for(&server), &body).await;
+    assert!(response.status().is_client_error(), "{}", response.status());
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
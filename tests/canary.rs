@@ -0,0 +1,186 @@
+//! Integration tests for canary provider onboarding.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{
+    CanaryConfig, Config, PoliciesConfig, ProviderConfig, RoutingConfig, ServerConfig,
+};
+use arbstr::proxy::{create_router, AppState, CanaryTracker, CircuitBreakerRegistry};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-canary",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Build an app with a stable provider and a cheaper canary at 25%.
+fn setup_app(stable_url: &str, canary_url: &str) -> axum::Router {
+    let providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", stable_url),
+            output_rate: 20.0,
+            ..common::test_provider("stable")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", canary_url),
+            output_rate: 5.0,
+            canary: true,
+            canary_percent: 25,
+            ..common::test_provider("newcomer")
+        },
+    ];
+    let names: Vec<String> = providers.iter().map(|p| p.name.clone()).collect();
+    let canary_config = CanaryConfig {
+        min_requests: 2,
+        min_success_rate: 1.0,
+        auto_promote: true,
+    };
+
+    let config = Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: None,
+        vault: None,
+        providers,
+        policies: PoliciesConfig::default(),
+        logging: Default::default(),
+        routing: RoutingConfig {
+            canary: canary_config.clone(),
+            ..Default::default()
+        },
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    };
+
+    let state = AppState {
+        canary: Arc::new(CanaryTracker::new(&config.providers, canary_config)),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&names)),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+async fn send_chat(app: &axum::Router) {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+async fn hits(server: &MockServer) -> usize {
+    server.received_requests().await.unwrap_or_default().len()
+}
+
+#[tokio::test]
+async fn canary_gets_slice_then_promotes() {
+    let stable = mock_provider().await;
+    let canary = mock_provider().await;
+    let app = setup_app(&stable.uri(), &canary.uri());
+
+    for _ in 0..8 {
+        send_chat(&app).await;
+    }
+    // 25% of 8 requests, despite the canary being cheapest
+    assert_eq!(hits(&canary).await, 2);
+    assert_eq!(hits(&stable).await, 6);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/providers").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, json) = common::parse_body(response).await;
+    let providers = json["providers"].as_array().unwrap();
+    let newcomer = providers.iter().find(|p| p["name"] == "newcomer").unwrap();
+    assert_eq!(newcomer["canary"]["promoted"], true);
+    assert_eq!(newcomer["canary"]["percent"], 25);
+    let stable_entry = providers.iter().find(|p| p["name"] == "stable").unwrap();
+    assert!(stable_entry.get("canary").is_none());
+
+    // Promoted: cheapest provider now takes all traffic
+    for _ in 0..3 {
+        send_chat(&app).await;
+    }
+    assert_eq!(hits(&canary).await, 5);
+    assert_eq!(hits(&stable).await, 6);
+}
+
+#[tokio::test]
+async fn explain_passes_over_unpromoted_canary() {
+    let stable = mock_provider().await;
+    let canary = mock_provider().await;
+    let app = setup_app(&stable.uri(), &canary.uri());
+
+    let response = app
+        .oneshot(
+            Request::get("/v1/route/explain?model=gpt-4o")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["selected"], "stable");
+    let candidates = json["candidates"].as_array().unwrap();
+    assert_eq!(candidates[0]["provider"], "newcomer");
+    assert_eq!(candidates[0]["canary"]["promoted"], false);
+    assert_eq!(candidates[0]["canary"]["samples"], 0);
+}
@@ -0,0 +1,178 @@
+//! Integration tests for `[chaos]` fault injection.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{
+    BackoffConfig, ChaosConfig, Config, PoliciesConfig, ProviderConfig, RoutingConfig, ServerConfig,
+};
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+/// Two mock-backed providers: `alpha` (cheaper, preferred) and `beta`.
+async fn setup_app(chaos: ChaosConfig) -> axum::Router {
+    let providers = vec![
+        ProviderConfig {
+            url: common::spawn_mock_provider(MockProviderConfig::default()).await,
+            output_rate: 5.0,
+            ..common::test_provider("alpha")
+        },
+        ProviderConfig {
+            url: common::spawn_mock_provider(MockProviderConfig::default()).await,
+            output_rate: 20.0,
+            ..common::test_provider("beta")
+        },
+    ];
+    let names: Vec<String> = providers.iter().map(|p| p.name.clone()).collect();
+
+    let config = Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: None,
+        vault: None,
+        providers,
+        policies: PoliciesConfig::default(),
+        logging: Default::default(),
+        routing: RoutingConfig {
+            backoff: BackoffConfig {
+                base_ms: 1,
+                max_ms: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos,
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    };
+
+    let state = AppState {
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&names)),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+fn chat_request(stream: bool) -> Request<Body> {
+    Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "stream": stream
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+async fn body_text(response: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn injected_errors_fall_back_to_healthy_provider() {
+    let app = setup_app(ChaosConfig {
+        enabled: true,
+        providers: vec!["alpha".to_string()],
+        error_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let response = app.oneshot(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-arbstr-provider"], "beta");
+}
+
+#[tokio::test]
+async fn injected_errors_surface_when_every_provider_fails() {
+    let app = setup_app(ChaosConfig {
+        enabled: true,
+        error_rate: 1.0,
+        error_status: 500,
+        ..Default::default()
+    })
+    .await;
+
+    let response = app.oneshot(chat_request(false)).await.unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 502);
+    assert_eq!(body["error"]["code"], "provider_server_error");
+}
+
+#[tokio::test]
+async fn dropped_stream_is_cut_short() {
+    let app = setup_app(ChaosConfig {
+        enabled: true,
+        drop_stream_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let response = app.oneshot(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let text = body_text(response).await;
+    assert!(text.contains("chat.completion.chunk"));
+    assert!(!text.contains("\"finish_reason\":\"stop\""));
+}
+
+#[tokio::test]
+async fn malformed_sse_is_spliced_into_stream() {
+    let app = setup_app(ChaosConfig {
+        enabled: true,
+        malformed_sse_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let response = app.oneshot(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let text = body_text(response).await;
+    assert!(text.contains("{\"id\":\"chaos\""));
+    assert!(text.contains("data: [DONE]"));
+}
+
+#[tokio::test]
+async fn disabled_chaos_leaves_traffic_alone() {
+    let app = setup_app(ChaosConfig {
+        error_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let response = app.oneshot(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-arbstr-provider"], "alpha");
+}
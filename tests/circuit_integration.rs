@@ -134,22 +134,58 @@ async fn test_non_streaming_503_all_circuits_open() {
             url: "https://fake-a.test/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 5,
-            output_rate: 15,
-            base_fee: 0,
+            input_rate: 5.0,
+            output_rate: 15.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "provider-b".to_string(),
             url: "https://fake-b.test/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
     ];
 
@@ -192,22 +228,58 @@ async fn test_non_streaming_skips_open_circuit() {
             url: "https://fake-a.test/v1".to_string(), // unreachable, but circuit is open
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 3, // cheaper, would be selected first
-            output_rate: 10,
-            base_fee: 0,
+            input_rate: 3.0, // cheaper, would be selected first
+            output_rate: 10.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "provider-b".to_string(),
             url: mock_url,
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
     ];
 
@@ -247,22 +319,58 @@ async fn test_streaming_503_all_circuits_open() {
             url: "https://fake-a.test/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 5,
-            output_rate: 15,
-            base_fee: 0,
+            input_rate: 5.0,
+            output_rate: 15.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "provider-b".to_string(),
             url: "https://fake-b.test/v1".to_string(),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
     ];
 
@@ -305,22 +413,58 @@ async fn test_streaming_skips_open_circuit() {
             url: "https://fake-a.test/v1".to_string(), // unreachable, circuit open
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 3, // cheaper
-            output_rate: 10,
-            base_fee: 0,
+            input_rate: 3.0, // cheaper
+            output_rate: 10.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "provider-b".to_string(),
             url: mock_url,
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: arbstr::config::Tier::default(),
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
     ];
 
@@ -365,11 +509,29 @@ async fn test_circuit_records_failure_on_5xx() {
         url: mock_url,
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: arbstr::config::Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }];
 
     let (app, registry) = common::setup_circuit_test_app(providers);
@@ -410,11 +572,29 @@ async fn test_circuit_stays_closed_on_4xx() {
         url: mock_url,
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: arbstr::config::Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }];
 
     let (app, registry) = common::setup_circuit_test_app(providers);
@@ -454,11 +634,29 @@ async fn test_non_streaming_records_success() {
         url: mock_url,
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: arbstr::config::Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }];
 
     let (app, registry) = common::setup_circuit_test_app(providers);
@@ -503,11 +701,29 @@ async fn test_streaming_records_failure_on_5xx() {
         url: mock_url,
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: arbstr::config::Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }];
 
     let (app, registry) = common::setup_circuit_test_app(providers);
@@ -541,11 +757,29 @@ async fn test_503_has_request_id_header() {
         url: "https://fake.test/v1".to_string(),
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: arbstr::config::Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }];
 
     let (app, registry) = common::setup_circuit_test_app(providers);
@@ -0,0 +1,119 @@
+//! Integration tests for client-supplied `x-arbstr-request-id` correlation IDs.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-cid",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(server)
+        .await;
+
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+/// Send a completion with an optional `x-arbstr-request-id`; returns the
+/// status and the echoed `x-arbstr-request-id`.
+async fn complete(app: &axum::Router, request_id: Option<&str>) -> (StatusCode, Option<String>) {
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(id) = request_id {
+        request = request.header("x-arbstr-request-id", id);
+    }
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let echoed = response
+        .headers()
+        .get("x-arbstr-request-id")
+        .map(|v| v.to_str().unwrap().to_string());
+    (response.status(), echoed)
+}
+
+async fn logged_correlation_ids(pool: &SqlitePool, rows: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT correlation_id FROM requests ORDER BY id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        if ids.len() >= rows {
+            return ids;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn client_uuid_becomes_the_correlation_id() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server).await;
+    let id = Uuid::new_v4().to_string();
+
+    let (status, echoed) = complete(&app, Some(&id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(echoed.as_deref(), Some(id.as_str()));
+    assert_eq!(logged_correlation_ids(&pool, 1).await, vec![id.clone()]);
+
+    // Reusing it within the window is rejected before reaching a provider
+    let (status, _) = complete(&app, Some(&id)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn invalid_or_missing_ids_are_generated() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let (status, echoed) = complete(&app, Some("not-a-uuid")).await;
+    assert_eq!(status, StatusCode::OK);
+    let generated = echoed.unwrap();
+    assert!(Uuid::parse_str(&generated).is_ok());
+
+    let (status, second) = complete(&app, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(second.as_deref(), Some(generated.as_str()));
+
+    // The same invalid value can be sent again: it was never claimed
+    let (status, _) = complete(&app, Some("not-a-uuid")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(logged_correlation_ids(&pool, 3).await.len(), 3);
+}
@@ -8,8 +8,9 @@ use sqlx::SqlitePool;
 use arbstr::config::{
     Config, PoliciesConfig, ProviderConfig, RoutingConfig, ServerConfig, Tier, VaultConfig,
 };
+use arbstr::proxy::maintenance::ProviderMaintenance;
 use arbstr::proxy::vault::VaultClient;
-use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
 use arbstr::router::Router as ProviderRouter;
 
 /// Parse an axum response body as JSON.
@@ -31,11 +32,29 @@ pub fn test_provider(name: &str) -> ProviderConfig {
         url: "https://fake.test/v1".to_string(),
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }
 }
 
@@ -48,9 +67,16 @@ pub fn setup_circuit_test_app(
 
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: None,
@@ -58,6 +84,22 @@ pub fn setup_circuit_test_app(
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_router = ProviderRouter::new(
@@ -66,6 +108,7 @@ pub fn setup_circuit_test_app(
         config.policies.default_strategy.clone(),
     );
 
+    let maintenance = Arc::new(ProviderMaintenance::new(&config.providers));
     let state = AppState {
         router: Arc::new(provider_router),
         http_client: reqwest::Client::new(),
@@ -74,6 +117,34 @@ pub fn setup_circuit_test_app(
         read_db: None,
         db_writer: None,
         circuit_breakers: registry.clone(),
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance,
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: None,
     };
 
@@ -85,9 +156,16 @@ pub fn setup_circuit_test_app(
 pub fn db_test_config() -> Config {
     Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: None,
@@ -97,33 +175,97 @@ pub fn db_test_config() -> Config {
                 url: "https://alpha.test/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string(), "claude-3.5-sonnet".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 1,
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 1.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "beta".to_string(),
                 url: "https://beta.test/v1".to_string(),
                 api_key: None,
                 models: vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
-                input_rate: 5,
-                output_rate: 15,
-                base_fee: 0,
+                input_rate: 5.0,
+                output_rate: 15.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::default(),
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ],
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     }
 }
 
 /// Create an in-memory SQLite pool with migrations applied, and return the
 /// pool along with an axum Router ready for `oneshot` requests.
 pub async fn setup_db_test_app() -> (axum::Router, SqlitePool) {
+    setup_db_test_app_with_config(db_test_config()).await
+}
+
+/// [`setup_db_test_app`] with a custom config.
+pub async fn setup_db_test_app_with_config(config: Config) -> (axum::Router, SqlitePool) {
+    let (state, pool) = setup_db_test_state(config).await;
+    (create_router(state), pool)
+}
+
+/// The `AppState` behind [`setup_db_test_app_with_config`], for building
+/// routers directly.
+pub async fn setup_db_test_state(config: Config) -> (AppState, SqlitePool) {
     let pool = SqlitePool::connect("sqlite::memory:")
         .await
         .expect("Failed to create in-memory SQLite pool");
@@ -133,26 +275,63 @@ pub async fn setup_db_test_app() -> (axum::Router, SqlitePool) {
         .await
         .expect("Failed to run migrations");
 
-    let config = db_test_config();
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        ..setup_test_state(config)
+    };
+
+    (state, pool)
+}
+
+/// An `AppState` for `config` with no database and default subsystems.
+/// Tests override the fields they exercise with struct update syntax.
+pub fn setup_test_state(config: Config) -> AppState {
     let provider_router = ProviderRouter::new(
         config.providers.clone(),
         config.policies.rules.clone(),
         config.policies.default_strategy.clone(),
     );
 
-    let state = AppState {
+    let maintenance = Arc::new(ProviderMaintenance::new(&config.providers));
+    AppState {
         router: Arc::new(provider_router),
         http_client: reqwest::Client::new(),
         config: Arc::new(config),
-        db: Some(pool.clone()),
-        read_db: Some(pool.clone()),
+        db: None,
+        read_db: None,
         db_writer: None,
         circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&[])),
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance,
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: None,
-    };
-
-    let app = create_router(state);
-    (app, pool)
+    }
 }
 
 /// Build a test app with vault billing enabled, connecting to a mock vault at the given URL
@@ -170,9 +349,16 @@ pub fn setup_vault_test_app_with_auth(
 ) -> axum::Router {
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: auth_token.map(|s| s.to_string()),
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: Some(VaultConfig {
@@ -187,27 +373,79 @@ pub fn setup_vault_test_app_with_auth(
                 url: format!("{}/v1", provider_url),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 1,
-                output_rate: 5,
-                base_fee: 0,
+                input_rate: 1.0,
+                output_rate: 5.0,
+                base_fee: 0.0,
+                min_charge_sats: 0.0,
                 tier: Tier::Local,
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
             ProviderConfig {
                 name: "expensive-frontier".to_string(),
                 url: format!("{}/v1", provider_url),
                 api_key: None,
                 models: vec!["gpt-4o".to_string()],
-                input_rate: 10,
-                output_rate: 30,
-                base_fee: 2,
+                input_rate: 10.0,
+                output_rate: 30.0,
+                base_fee: 2.0,
+                min_charge_sats: 0.0,
                 tier: Tier::Frontier,
                 auto_discover: false,
+                canary: false,
+                canary_percent: 5,
+                auth_scheme: Default::default(),
+                extra_headers: Default::default(),
+                pool: None,
+                resolve: Default::default(),
+                backoff: None,
+                balance_sats: None,
+                balance_reset: None,
+                region: None,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                structured_output: false,
+                enabled: true,
+                maintenance_until: None,
+                slo: None,
+                normalize_stream: false,
             },
         ],
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_names: Vec<String> = config.providers.iter().map(|p| p.name.clone()).collect();
@@ -222,6 +460,7 @@ pub fn setup_vault_test_app_with_auth(
         config.policies.default_strategy.clone(),
     );
 
+    let maintenance = Arc::new(ProviderMaintenance::new(&config.providers));
     let state = AppState {
         router: Arc::new(provider_router),
         http_client: reqwest::Client::new(),
@@ -230,6 +469,34 @@ pub fn setup_vault_test_app_with_auth(
         read_db: None,
         db_writer: None,
         circuit_breakers: registry,
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance,
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: Some(vault),
     };
 
@@ -254,9 +521,16 @@ pub async fn setup_test_db() -> SqlitePool {
 pub fn setup_free_proxy_test_app(provider_url: &str) -> axum::Router {
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: None,
@@ -265,15 +539,49 @@ pub fn setup_free_proxy_test_app(provider_url: &str) -> axum::Router {
             url: format!("{}/v1", provider_url),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 1,
-            output_rate: 5,
-            base_fee: 0,
+            input_rate: 1.0,
+            output_rate: 5.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: Tier::Local,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         }],
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_names: Vec<String> = config.providers.iter().map(|p| p.name.clone()).collect();
@@ -285,6 +593,7 @@ pub fn setup_free_proxy_test_app(provider_url: &str) -> axum::Router {
         config.policies.default_strategy.clone(),
     );
 
+    let maintenance = Arc::new(ProviderMaintenance::new(&config.providers));
     let state = AppState {
         router: Arc::new(provider_router),
         http_client: reqwest::Client::new(),
@@ -293,8 +602,48 @@ pub fn setup_free_proxy_test_app(provider_url: &str) -> axum::Router {
         read_db: None,
         db_writer: None,
         circuit_breakers: registry,
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance,
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: None,
     };
 
     create_router(state)
 }
+
+/// Serve the mock provider on an ephemeral port and return its base URL.
+pub async fn spawn_mock_provider(config: arbstr::mock_provider::MockProviderConfig) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, arbstr::mock_provider::create_router(config))
+            .await
+            .unwrap();
+    });
+    format!("http://{}/v1", addr)
+}
@@ -0,0 +1,192 @@
+//! Integration tests for POST /v1/compare and GET /v1/compare/{id}.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider(status: u16, content: &str) -> MockServer {
+    let server = MockServer::start().await;
+    let response = if status == 200 {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-compare",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    } else {
+        ResponseTemplate::new(status).set_body_string("upstream unavailable")
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// "alpha" serves gpt-4o; "beta" serves gpt-4o and llama-3 at a higher rate.
+async fn setup_app(alpha: &MockServer, beta: &MockServer) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", alpha.uri()),
+            ..common::test_provider("alpha")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", beta.uri()),
+            models: vec!["gpt-4o".to_string(), "llama-3".to_string()],
+            input_rate: 50.0,
+            ..common::test_provider("beta")
+        },
+    ];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+async fn compare(
+    app: &axum::Router,
+    targets: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let body = serde_json::json!({
+        "targets": targets,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/compare")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+async fn logged_correlation_ids(pool: &SqlitePool, rows: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT correlation_id FROM requests ORDER BY correlation_id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        if ids.len() >= rows {
+            return ids;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn fans_out_and_stores_the_set() {
+    let alpha = mock_provider(200, "from alpha").await;
+    let beta = mock_provider(200, "from beta").await;
+    let (app, pool) = setup_app(&alpha, &beta).await;
+
+    let (status, body) = compare(
+        &app,
+        serde_json::json!([
+            {"model": "gpt-4o"},
+            {"model": "gpt-4o", "provider": "beta"},
+            {"model": "llama-3"}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = body["id"].as_str().unwrap().to_string();
+    assert_eq!(body["persisted"], true);
+
+    let results = body["results"].as_array().unwrap();
+    let providers: Vec<_> = results.iter().map(|r| r["provider"].clone()).collect();
+    assert_eq!(providers, ["alpha", "beta", "beta"]);
+    assert_eq!(results[0]["content"], "from alpha");
+    assert_eq!(results[1]["content"], "from beta");
+    assert_eq!(results[2]["model"], "llama-3");
+    for result in results {
+        assert_eq!(result["success"], true);
+        assert_eq!(result["input_tokens"], 10);
+        assert!(result["cost_sats"].is_number());
+        assert!(result["latency_ms"].is_number());
+        assert_eq!(result["response"]["object"], "chat.completion");
+    }
+    assert!(results[1]["cost_sats"].as_f64() > results[0]["cost_sats"].as_f64());
+
+    // Every response is logged as its own request
+    let expected: Vec<String> = (1..=3).map(|n| format!("{}-{}", id, n)).collect();
+    assert_eq!(logged_correlation_ids(&pool, 3).await, expected);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get(format!("/v1/compare/{}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, stored) = common::parse_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stored["id"], id.as_str());
+    let stored_results = stored["results"].as_array().unwrap();
+    assert_eq!(stored_results.len(), 3);
+    assert_eq!(stored_results[1]["content"], "from beta");
+    assert_eq!(stored_results[1]["cost_sats"], results[1]["cost_sats"]);
+    assert!(stored_results[1].get("response").is_none());
+}
+
+#[tokio::test]
+async fn failures_are_reported_per_target() {
+    let alpha = mock_provider(200, "from alpha").await;
+    let beta = mock_provider(503, "").await;
+    let (app, _pool) = setup_app(&alpha, &beta).await;
+
+    let (status, body) = compare(
+        &app,
+        serde_json::json!([{"model": "gpt-4o"}, {"model": "llama-3"}]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["results"][0]["success"], true);
+    assert_eq!(body["results"][1]["success"], false);
+    assert_eq!(body["results"][1]["error"]["status"], 503);
+    assert!(body["results"][1]["cost_sats"].is_null());
+
+    // A target no provider can serve fails the whole comparison up front
+    let sent = alpha.received_requests().await.unwrap().len();
+    let (status, _) = compare(
+        &app,
+        serde_json::json!([{"model": "gpt-4o"}, {"model": "llama-3", "provider": "alpha"}]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(alpha.received_requests().await.unwrap().len(), sent);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/compare/missing")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,221 @@
+//! Integration tests for request decompression, response compression,
+//! upstream compression negotiation, and GET /v1/stats/compression.
+
+mod common;
+
+use std::io::{Read, Write};
+
+use axum::body::Body;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::ProviderConfig;
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn gunzip(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut out).unwrap();
+    out
+}
+
+fn completion_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-compression",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "ok ".repeat(200)},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 200, "total_tokens": 210}
+    })
+}
+
+fn chat_body(stream: bool) -> String {
+    serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "summarize this ".repeat(50)}]
+    })
+    .to_string()
+}
+
+fn app_for(server: &MockServer) -> axum::Router {
+    let provider = ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    };
+    common::setup_circuit_test_app(vec![provider]).0
+}
+
+async fn get_stats(app: axum::Router) -> serde_json::Value {
+    let response = app
+        .oneshot(
+            Request::get("/v1/stats/compression")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    common::parse_body(response).await.1
+}
+
+#[tokio::test]
+async fn compressed_request_and_response_round_trip() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion_json()))
+        .mount(&server)
+        .await;
+    let app = app_for(&server);
+
+    let raw = chat_body(false);
+    let compressed = gzip(raw.as_bytes());
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("content-encoding", "gzip")
+                .header("accept-encoding", "gzip")
+                .body(Body::from(compressed.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+    assert!(response.headers().get("x-arbstr-provider").is_some());
+
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&gunzip(&body)).unwrap();
+    assert_eq!(json["arbstr"]["provider"], "upstream");
+
+    // Provider received the decompressed JSON
+    let requests = server.received_requests().await.unwrap();
+    let upstream: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(upstream["model"], "gpt-4o");
+
+    let stats = get_stats(app).await;
+    assert_eq!(stats["requests_decompressed"], 1);
+    assert_eq!(stats["request_bytes_compressed"], compressed.len());
+    assert_eq!(stats["request_bytes_decompressed"], raw.len());
+    assert_eq!(stats["responses_compressed"], 1);
+    assert_eq!(stats["response_bytes_compressed"], body.len());
+    assert!(stats["response_bytes_saved"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn response_uncompressed_without_accept_encoding() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion_json()))
+        .mount(&server)
+        .await;
+    let app = app_for(&server);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(chat_body(false)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("content-encoding").is_none());
+    let (_, json) = common::parse_body(response).await;
+    assert_eq!(json["arbstr"]["provider"], "upstream");
+
+    let stats = get_stats(app).await;
+    assert_eq!(stats["responses_compressed"], 0);
+    assert_eq!(stats["requests_decompressed"], 0);
+}
+
+#[tokio::test]
+async fn streaming_responses_are_not_compressed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!(
+                    "data: {{\"id\":\"c\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{}\"}}}}]}}\n\n\
+                     data: [DONE]\n\n",
+                    "ok ".repeat(100)
+                )),
+        )
+        .mount(&server)
+        .await;
+    let app = app_for(&server);
+
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("accept-encoding", "gzip, br")
+                .body(Body::from(chat_body(true)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn upstream_compression_is_negotiated() {
+    let server = MockServer::start().await;
+    let encoded = gzip(completion_json().to_string().as_bytes());
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-encoding", "gzip")
+                .set_body_raw(encoded, "application/json"),
+        )
+        .mount(&server)
+        .await;
+    let app = app_for(&server);
+
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(chat_body(false)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["usage"]["completion_tokens"], 200);
+
+    let requests = server.received_requests().await.unwrap();
+    let accept = requests[0]
+        .headers
+        .get("accept-encoding")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(accept.contains("gzip"), "accept-encoding: {}", accept);
+    assert!(accept.contains("br"), "accept-encoding: {}", accept);
+}
@@ -0,0 +1,136 @@
+//! Integration tests for config diffs: the dry-run check of a candidate
+//! config file and the diffs recorded for admin updates.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+const CANDIDATE: &str = r#"
+[server]
+listen = "127.0.0.1:0"
+
+[[providers]]
+name = "alpha"
+url = "https://alpha.test/v1"
+models = ["gpt-4o", "claude-3.5-sonnet"]
+input_rate = 10
+output_rate = 45
+base_fee = 1
+
+[[providers]]
+name = "gamma"
+url = "https://gamma.test/v1"
+models = ["gpt-4o"]
+
+[[policies.rules]]
+name = "code"
+allowed_models = ["gpt-4o"]
+"#;
+
+async fn send(app: &axum::Router, request: Request<Body>) -> (http::StatusCode, serde_json::Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    common::parse_body(response).await
+}
+
+fn dry_run(body: &str) -> Request<Body> {
+    Request::post("/admin/config/diff")
+        .header("x-arbstr-actor", "alice")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn last_diff() -> Request<Body> {
+    Request::get("/admin/config/diff")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn dry_run_reports_changes_without_applying_them() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let (status, _) = send(&app, last_diff()).await;
+    assert_eq!(status, 404);
+
+    let (status, body) = send(&app, dry_run(CANDIDATE)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["source"], "dry_run");
+    assert_eq!(body["applied"], false);
+    assert_eq!(body["actor"], "alice");
+    let diff = &body["diff"];
+    assert_eq!(diff["providers_added"], serde_json::json!(["gamma"]));
+    assert_eq!(diff["providers_removed"], serde_json::json!(["beta"]));
+    assert_eq!(
+        diff["providers_changed"],
+        serde_json::json!([{
+            "name": "alpha",
+            "changes": [{"field": "output_rate", "from": "30.0", "to": "45.0"}]
+        }])
+    );
+    assert_eq!(diff["policies_added"], serde_json::json!(["code"]));
+    assert!(body["summary"]
+        .as_str()
+        .unwrap()
+        .contains("provider alpha: output_rate 30.0 -> 45.0"));
+
+    let (status, last) = send(&app, last_diff()).await;
+    assert_eq!(status, 200);
+    assert_eq!(last, body);
+
+    // Nothing was applied: beta still serves gpt-4o-mini
+    let (status, providers) = send(
+        &app,
+        Request::get("/providers").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(providers.to_string().contains("beta"), "{}", providers);
+}
+
+#[tokio::test]
+async fn invalid_config_is_refused_and_the_last_diff_kept() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let (status, first) = send(&app, dry_run(CANDIDATE)).await;
+    assert_eq!(status, 200);
+
+    let (status, body) = send(&app, dry_run("[server]\nlisten = []\n")).await;
+    assert_eq!(status, 400, "{}", body);
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(
+        message.contains("keeping the current config"),
+        "{}",
+        message
+    );
+
+    let (status, body) = send(&app, dry_run("not = [valid toml")).await;
+    assert_eq!(status, 400, "{}", body);
+
+    let (_, last) = send(&app, last_diff()).await;
+    assert_eq!(last, first);
+}
+
+#[tokio::test]
+async fn maintenance_updates_record_an_applied_diff() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let request = Request::put("/admin/providers/beta/maintenance")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({"enabled": false}).to_string(),
+        ))
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, 200);
+
+    let (status, body) = send(&app, last_diff()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["source"], "admin");
+    assert_eq!(body["applied"], true);
+    assert_eq!(body["actor"], "admin");
+    assert_eq!(
+        body["diff"]["providers_changed"],
+        serde_json::json!([{
+            "name": "beta",
+            "changes": [{"field": "enabled", "from": "true", "to": "false"}]
+        }])
+    );
+}
@@ -0,0 +1,153 @@
+//! Integration tests for config versioning: the version stamped on request
+//! rows and the change history from the admin API.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-versions",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config.clone()).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    assert_eq!(
+        state.config_versions.restore(&pool, &config).await.unwrap(),
+        1
+    );
+    (create_router(state), pool)
+}
+
+async fn send(app: &axum::Router, request: Request<Body>) -> (http::StatusCode, serde_json::Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    common::parse_body(response).await
+}
+
+async fn complete(app: &axum::Router) {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let request = Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    assert_eq!(send(app, request).await.0, 200);
+}
+
+/// Config versions of the logged requests, oldest first, once `rows` exist.
+async fn logged_versions(pool: &SqlitePool, rows: usize) -> Vec<Option<i64>> {
+    for _ in 0..100 {
+        let versions: Vec<Option<i64>> =
+            sqlx::query_scalar("SELECT config_version FROM requests ORDER BY id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        if versions.len() >= rows {
+            return versions;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn requests_carry_the_version_active_at_dispatch() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server).await;
+    complete(&app).await;
+
+    let request = Request::put("/admin/providers/upstream/maintenance")
+        .header("content-type", "application/json")
+        .header("x-arbstr-actor", "alice")
+        .body(Body::from(
+            serde_json::json!({"maintenance_until": "2000-01-01T00:00:00Z"}).to_string(),
+        ))
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, 200);
+    complete(&app).await;
+
+    assert_eq!(logged_versions(&pool, 2).await, [Some(1), Some(2)]);
+
+    let (status, body) = send(
+        &app,
+        // Rows logged this second sort after a fractional-second `until`
+        Request::get("/v1/requests?until=2100-01-01T00:00:00Z")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"][0]["config_version"], 2, "{}", body);
+
+    let (status, body) = send(
+        &app,
+        Request::get("/admin/config/versions")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["current"], 2);
+    let versions = body["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["version"], 2);
+    assert_eq!(versions[0]["source"], "admin");
+    assert_eq!(versions[0]["actor"], "alice");
+    assert_eq!(
+        versions[0]["change"],
+        "provider upstream: enabled=true, maintenance_until=2000-01-01T00:00:00Z"
+    );
+    assert_eq!(versions[1]["source"], "startup");
+    assert_eq!(versions[0]["config_hash"], versions[1]["config_hash"]);
+}
+
+#[tokio::test]
+async fn admin_changes_default_to_the_admin_actor() {
+    let server = MockServer::start().await;
+    let (app, _pool) = setup_app(&server).await;
+    let request = Request::put("/admin/providers/upstream/maintenance")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({"enabled": false}).to_string(),
+        ))
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, 200);
+
+    let (_, body) = send(
+        &app,
+        Request::get("/admin/config/versions?limit=1")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(body["versions"].as_array().unwrap().len(), 1);
+    assert_eq!(body["versions"][0]["actor"], "admin");
+}
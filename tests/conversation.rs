@@ -0,0 +1,153 @@
+//! Integration tests for per-conversation cost attribution.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-conversation",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+/// Send a completion in `header` conversation, with `metadata` in the body.
+async fn complete(
+    app: &axum::Router,
+    header: Option<&str>,
+    metadata: Option<serde_json::Value>,
+) -> (http::StatusCode, serde_json::Value) {
+    let mut body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    if let Some(metadata) = metadata {
+        body["metadata"] = metadata;
+    }
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(id) = header {
+        request = request.header("x-arbstr-conversation-id", id);
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+    common::parse_body(app.clone().oneshot(request).await.unwrap()).await
+}
+
+async fn get(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let (status, body) = common::parse_body(app.clone().oneshot(request).await.unwrap()).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+async fn wait_for_rows(pool: &SqlitePool, rows: i64) {
+    for _ in 0..100 {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count >= rows {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn costs_are_attributed_to_conversations() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server).await;
+
+    assert_eq!(complete(&app, Some("chat-a"), None).await.0, 200);
+    // The header wins over the body
+    let metadata = serde_json::json!({"conversation_id": "chat-b"});
+    assert_eq!(
+        complete(&app, Some("chat-a"), Some(metadata.clone()))
+            .await
+            .0,
+        200
+    );
+    assert_eq!(complete(&app, None, Some(metadata)).await.0, 200);
+    assert_eq!(complete(&app, None, None).await.0, 200);
+    wait_for_rows(&pool, 4).await;
+
+    let body = get(&app, "/v1/conversations/chat-a").await;
+    assert_eq!(body["conversation_id"], "chat-a");
+    assert_eq!(body["requests"], 2);
+    assert_eq!(body["success"], 2);
+    assert_eq!(body["total_input_tokens"], 2000);
+    assert_eq!(body["total_output_tokens"], 1000);
+    let cost_a = body["total_cost_sats"].as_f64().unwrap();
+    assert!(cost_a > 0.0, "{}", body);
+    assert!(body["first_request_at"].is_string(), "{}", body);
+
+    let body = get(&app, "/v1/conversations/unknown").await;
+    assert_eq!(body["requests"], 0);
+    assert_eq!(body["total_cost_sats"], 0.0);
+    assert!(body.get("first_request_at").is_none(), "{}", body);
+
+    let stats = get(
+        &app,
+        "/v1/stats?group_by=conversation&until=2100-01-01T00:00:00Z",
+    )
+    .await;
+    let conversations = &stats["conversations"];
+    assert_eq!(conversations["chat-a"]["counts"]["total"], 2);
+    assert_eq!(conversations["chat-a"]["costs"]["total_cost_sats"], cost_a);
+    assert_eq!(conversations["chat-b"]["counts"]["total"], 1);
+    assert_eq!(conversations["none"]["counts"]["total"], 1);
+
+    let logs = get(&app, "/v1/requests?until=2100-01-01T00:00:00Z").await;
+    let ids: Vec<_> = logs["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|r| r["conversation_id"].as_str())
+        .collect();
+    assert_eq!(ids.len(), 3, "{}", logs);
+}
+
+#[tokio::test]
+async fn invalid_conversation_ids_are_rejected() {
+    let server = MockServer::start().await;
+    let (app, _pool) = setup_app(&server).await;
+
+    let long = "x".repeat(200);
+    let (status, body) = complete(&app, Some(&long), None).await;
+    assert_eq!(status, 400, "{}", body);
+
+    let metadata = serde_json::json!({"conversation_id": 42});
+    let (status, body) = complete(&app, None, Some(metadata)).await;
+    assert_eq!(status, 400, "{}", body);
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
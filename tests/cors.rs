@@ -0,0 +1,121 @@
+//! Integration tests for `[server.cors]`.
+
+mod common;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+
+use arbstr::config::CorsConfig;
+
+const ORIGIN: &str = "http://localhost:3000";
+
+async fn cors_app() -> axum::Router {
+    let mut config = common::db_test_config();
+    config.server.auth_token = Some("secret".to_string());
+    config.server.cors = Some(CorsConfig {
+        allowed_origins: vec![ORIGIN.to_string()],
+        allowed_headers: vec!["authorization".to_string(), "content-type".to_string()],
+        allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+        expose_headers: vec!["x-arbstr-cost-sats".to_string()],
+        allow_credentials: true,
+        max_age_secs: Some(600),
+    });
+    common::setup_db_test_app_with_config(config).await.0
+}
+
+fn header<'a>(response: &'a axum::response::Response, name: &str) -> Option<&'a str> {
+    response.headers().get(name).map(|v| v.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn preflight_is_answered_without_auth() {
+    let response = cors_app()
+        .await
+        .oneshot(
+            Request::options("/v1/chat/completions")
+                .header("origin", ORIGIN)
+                .header("access-control-request-method", "POST")
+                .header(
+                    "access-control-request-headers",
+                    "authorization,content-type",
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some(ORIGIN)
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(header(&response, "access-control-max-age"), Some("600"));
+    let methods = header(&response, "access-control-allow-methods").unwrap();
+    assert!(methods.contains("POST"), "{}", methods);
+}
+
+#[tokio::test]
+async fn responses_carry_headers_for_allowed_origins_only() {
+    let app = cors_app().await;
+    let get = |origin: &str| {
+        Request::get("/health")
+            .header("origin", origin)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(get(ORIGIN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some(ORIGIN)
+    );
+    assert_eq!(
+        header(&response, "access-control-expose-headers"),
+        Some("x-arbstr-cost-sats")
+    );
+
+    let response = app.oneshot(get("https://evil.example")).await.unwrap();
+    assert!(header(&response, "access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn auth_failures_remain_readable_cross_origin() {
+    let response = cors_app()
+        .await
+        .oneshot(
+            Request::get("/v1/models")
+                .header("origin", ORIGIN)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some(ORIGIN)
+    );
+}
+
+#[tokio::test]
+async fn no_cors_headers_without_config() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let response = app
+        .oneshot(
+            Request::get("/health")
+                .header("origin", ORIGIN)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(header(&response, "access-control-allow-origin").is_none());
+}
@@ -34,9 +34,16 @@ fn setup_cost_test_app(
 
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: None,
@@ -47,6 +54,22 @@ fn setup_cost_test_app(
         },
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_router = ProviderRouter::new(
@@ -63,6 +86,34 @@ fn setup_cost_test_app(
         read_db: None,
         db_writer: None,
         circuit_breakers: registry,
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance: Default::default(),
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: None,
     };
 
@@ -76,9 +127,16 @@ fn setup_cost_test_app_with_auth(providers: Vec<ProviderConfig>, auth_token: &st
 
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: Some(auth_token.to_string()),
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: None,
@@ -86,6 +144,22 @@ fn setup_cost_test_app_with_auth(providers: Vec<ProviderConfig>, auth_token: &st
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_router = ProviderRouter::new(
@@ -102,6 +176,34 @@ fn setup_cost_test_app_with_auth(providers: Vec<ProviderConfig>, auth_token: &st
         read_db: None,
         db_writer: None,
         circuit_breakers: registry,
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance: Default::default(),
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: None,
     };
 
@@ -111,9 +213,9 @@ fn setup_cost_test_app_with_auth(providers: Vec<ProviderConfig>, auth_token: &st
 /// Standard provider config with controllable rates.
 fn provider_with_rates(
     name: &str,
-    input_rate: u64,
-    output_rate: u64,
-    base_fee: u64,
+    input_rate: f64,
+    output_rate: f64,
+    base_fee: f64,
 ) -> ProviderConfig {
     ProviderConfig {
         name: name.to_string(),
@@ -123,8 +225,26 @@ fn provider_with_rates(
         input_rate,
         output_rate,
         base_fee,
+        min_charge_sats: 0.0,
         tier: Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }
 }
 
@@ -153,7 +273,7 @@ fn cost_request_body(model: &str, message: &str, max_tokens: Option<u32>) -> Str
 
 #[tokio::test]
 async fn test_cost_basic() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 1)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 1.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     let body = cost_request_body("gpt-4o", "Hello, world!", None);
@@ -181,9 +301,9 @@ async fn test_cost_basic() {
         "expected estimated_cost_sats to be a number"
     );
     assert!(json["rates"].is_object(), "expected rates to be an object");
-    assert_eq!(json["rates"]["input_rate_sats_per_1k"], 10);
-    assert_eq!(json["rates"]["output_rate_sats_per_1k"], 30);
-    assert_eq!(json["rates"]["base_fee_sats"], 1);
+    assert_eq!(json["rates"]["input_rate_sats_per_1k"], 10.0);
+    assert_eq!(json["rates"]["output_rate_sats_per_1k"], 30.0);
+    assert_eq!(json["rates"]["base_fee_sats"], 1.0);
 }
 
 // ============================================================================
@@ -192,7 +312,7 @@ async fn test_cost_basic() {
 
 #[tokio::test]
 async fn test_cost_unknown_model() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 1)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 1.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     let body = cost_request_body("nonexistent-model", "hello", None);
@@ -214,8 +334,8 @@ async fn test_cost_unknown_model() {
 #[tokio::test]
 async fn test_cost_picks_cheapest() {
     let providers = vec![
-        provider_with_rates("expensive", 15, 40, 5),
-        provider_with_rates("cheap", 3, 10, 0),
+        provider_with_rates("expensive", 15.0, 40.0, 5.0),
+        provider_with_rates("cheap", 3.0, 10.0, 0.0),
     ];
     let app = setup_cost_test_app(providers, vec![]);
 
@@ -241,7 +361,7 @@ async fn test_cost_picks_cheapest() {
 
 #[tokio::test]
 async fn test_cost_max_tokens_used() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 0)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 0.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     let body = cost_request_body("gpt-4o", "hello", Some(100));
@@ -263,7 +383,7 @@ async fn test_cost_max_tokens_used() {
 
 #[tokio::test]
 async fn test_cost_default_output_tokens() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 0)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 0.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     let body = cost_request_body("gpt-4o", "hello", None);
@@ -288,16 +408,36 @@ async fn test_cost_with_policy_header() {
     // "expensive" has output_rate=40 (above max_sats_per_1k_output=20)
     // "budget" has output_rate=10 (below max_sats_per_1k_output=20)
     let providers = vec![
-        provider_with_rates("expensive", 15, 40, 0),
-        provider_with_rates("budget", 3, 10, 0),
+        provider_with_rates("expensive", 15.0, 40.0, 0.0),
+        provider_with_rates("budget", 3.0, 10.0, 0.0),
     ];
 
     let policy = PolicyRule {
         name: "strict_budget".to_string(),
         allowed_models: vec!["gpt-4o".to_string()],
         strategy: "lowest_cost".to_string(),
-        max_sats_per_1k_output: Some(20),
+        max_sats_per_1k_output: Some(20.0),
         keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Default::default(),
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
     };
 
     let app = setup_cost_test_app(providers, vec![policy]);
@@ -325,7 +465,7 @@ async fn test_cost_with_policy_header() {
 
 #[tokio::test]
 async fn test_cost_input_estimation() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 0)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 0.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     // 400 characters of content -> ~100 estimated tokens (400/4)
@@ -350,7 +490,7 @@ async fn test_cost_input_estimation() {
 
 #[tokio::test]
 async fn test_cost_rates_in_response() {
-    let providers = vec![provider_with_rates("alpha", 7, 22, 3)];
+    let providers = vec![provider_with_rates("alpha", 7.0, 22.0, 3.0)];
     let app = setup_cost_test_app(providers, vec![]);
 
     let body = cost_request_body("gpt-4o", "hello", None);
@@ -363,9 +503,9 @@ async fn test_cost_rates_in_response() {
     let (status, json) = common::parse_body(response).await;
 
     assert_eq!(status, http::StatusCode::OK);
-    assert_eq!(json["rates"]["input_rate_sats_per_1k"], 7);
-    assert_eq!(json["rates"]["output_rate_sats_per_1k"], 22);
-    assert_eq!(json["rates"]["base_fee_sats"], 3);
+    assert_eq!(json["rates"]["input_rate_sats_per_1k"], 7.0);
+    assert_eq!(json["rates"]["output_rate_sats_per_1k"], 22.0);
+    assert_eq!(json["rates"]["base_fee_sats"], 3.0);
 }
 
 // ============================================================================
@@ -374,7 +514,7 @@ async fn test_cost_rates_in_response() {
 
 #[tokio::test]
 async fn test_cost_requires_auth() {
-    let providers = vec![provider_with_rates("alpha", 10, 30, 0)];
+    let providers = vec![provider_with_rates("alpha", 10.0, 30.0, 0.0)];
     let app = setup_cost_test_app_with_auth(providers, "secret-token-123");
 
     let body = cost_request_body("gpt-4o", "hello", None);
@@ -390,3 +530,54 @@ async fn test_cost_requires_auth() {
 
     assert_eq!(status, http::StatusCode::UNAUTHORIZED);
 }
+
+// ============================================================================
+// Test 10: Sub-sat rates route and price to the millisat
+// ============================================================================
+
+#[tokio::test]
+async fn test_cost_decimal_rates() {
+    let providers = vec![
+        provider_with_rates("alpha", 0.15, 0.6, 0.0),
+        provider_with_rates("beta", 0.2, 0.5, 0.0),
+    ];
+    let app = setup_cost_test_app(providers, vec![]);
+
+    let body = cost_request_body("gpt-4o", "hello", Some(1000));
+    let request = Request::post("/v1/cost")
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let (status, json) = common::parse_body(response).await;
+
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(json["provider"], "beta", "0.5 sats/1k output beats 0.6");
+    assert_eq!(json["rates"]["input_rate_sats_per_1k"], 0.2);
+    assert_eq!(json["rates"]["output_rate_sats_per_1k"], 0.5);
+    let input_tokens = json["estimated_input_tokens"].as_u64().unwrap() as f64;
+    let expected = ((input_tokens * 0.2 + 1000.0 * 0.5).round()) / 1000.0;
+    assert_eq!(json["estimated_cost_sats"].as_f64().unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_cost_min_charge_floors_estimate() {
+    let mut provider = provider_with_rates("alpha", 1.0, 2.0, 0.5);
+    provider.min_charge_sats = 5.0;
+    let app = setup_cost_test_app(vec![provider], vec![]);
+
+    let body = cost_request_body("gpt-4o", "hi", Some(10));
+    let request = Request::post("/v1/cost")
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let (status, json) = common::parse_body(response).await;
+
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(json["rates"]["min_charge_sats"], 5.0);
+    // A handful of tokens plus the 0.5 sat base fee come to well under 5 sats
+    assert_eq!(json["estimated_cost_sats"].as_f64().unwrap(), 5.0);
+}
@@ -0,0 +1,163 @@
+//! Integration tests for fiat cost conversion (`[currency]`).
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::CurrencyConfig;
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::currency::{self, ExchangeRate};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use arbstr::storage::{memory, DbWriter};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn usd(btc_price: Option<f64>, price_url: Option<String>) -> CurrencyConfig {
+    CurrencyConfig {
+        code: "USD".to_string(),
+        btc_price,
+        price_url,
+        price_pointer: "/data/amount".to_string(),
+        refresh_secs: 300,
+    }
+}
+
+/// A provider charging a flat 1000 sats per request, priced at 50k USD/BTC.
+async fn setup_app() -> (axum::Router, Arc<ExchangeRate>) {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    provider.input_rate = 0.0;
+    provider.output_rate = 0.0;
+    provider.base_fee = 1000.0;
+    let providers = vec![provider];
+
+    let mut config = common::db_test_config();
+    config.providers = providers.clone();
+    config.currency = Some(usd(Some(50_000.0), None));
+
+    let exchange_rate = Arc::new(ExchangeRate::new(config.currency.as_ref()));
+    let pool = memory::init_pool().await.unwrap();
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool)),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["mock".to_string()])),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        exchange_rate: exchange_rate.clone(),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), exchange_rate)
+}
+
+async fn complete(app: &axum::Router) -> http::HeaderMap {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers().clone()
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+#[tokio::test]
+async fn costs_are_reported_at_the_rate_of_each_request() {
+    let (app, rate) = setup_app().await;
+
+    let headers = complete(&app).await;
+    assert_eq!(headers["x-arbstr-cost-sats"], "1000.000");
+    assert_eq!(headers["x-arbstr-cost-usd"], "0.500000");
+
+    rate.set(100_000.0);
+    let headers = complete(&app).await;
+    assert_eq!(headers["x-arbstr-cost-usd"], "1.000000");
+
+    // Writes go through the background writer
+    let mut stats = get_json(&app, "/v1/stats").await;
+    for _ in 0..50 {
+        if stats["counts"]["total"] == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stats = get_json(&app, "/v1/stats").await;
+    }
+    let fiat = &stats["costs"]["fiat"];
+    assert_eq!(fiat["currency"], "USD");
+    assert_eq!(fiat["total_cost"], 1.5);
+    assert_eq!(fiat["unpriced_requests"], 0);
+
+    let logs = get_json(&app, "/v1/requests?sort=timestamp&order=asc").await;
+    let prices: Vec<f64> = logs["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["cost"]["fiat"]["btc_price"].as_f64().unwrap())
+        .collect();
+    assert_eq!(prices.len(), 2);
+    assert!(prices.contains(&50_000.0) && prices.contains(&100_000.0));
+    for entry in logs["data"].as_array().unwrap() {
+        let fiat = &entry["cost"]["fiat"];
+        assert_eq!(
+            fiat["amount"].as_f64().unwrap(),
+            1000.0 * fiat["btc_price"].as_f64().unwrap() / 1e8
+        );
+    }
+}
+
+#[tokio::test]
+async fn stats_omit_fiat_without_currency_config() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let stats = get_json(&app, "/v1/stats").await;
+    assert!(stats["costs"].get("fiat").is_none());
+}
+
+#[tokio::test]
+async fn price_is_fetched_from_the_configured_source() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/spot"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!({"data": {"amount": "64250.10", "base": "BTC", "currency": "USD"}}),
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/broken"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"data": {}})))
+        .mount(&server)
+        .await;
+
+    let client = reqwest::Client::new();
+    let config = usd(None, Some(format!("{}/spot", server.uri())));
+    let price = currency::fetch_price(&client, &config).await.unwrap();
+    assert_eq!(price, 64250.10);
+
+    let config = usd(None, Some(format!("{}/broken", server.uri())));
+    let err = currency::fetch_price(&client, &config).await.unwrap_err();
+    assert!(err.contains("/data/amount"), "{}", err);
+}
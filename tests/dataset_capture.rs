@@ -0,0 +1,180 @@
+//! Integration tests for `[policies.rules.dataset_capture]`.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use arbstr::config::{DatasetCaptureConfig, PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::create_router;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("arbstr-dataset-test-{}", uuid::Uuid::new_v4()))
+        .join("search.jsonl")
+}
+
+async fn setup_app(server: &MockServer, capture: DatasetCaptureConfig) -> axum::Router {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-dataset",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Write to ops@example.com."},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "search".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: Some(capture),
+    }];
+    let (state, _pool) = common::setup_db_test_state(config).await;
+    create_router(state)
+}
+
+async fn complete(app: &axum::Router, policy: Option<&str>) -> serde_json::Value {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "user": "customer-42",
+        "messages": [{
+            "role": "user",
+            "name": "jane",
+            "content": "My key is sk-abcdefghijklmnopqrstuvwx, email me at jane@example.com"
+        }]
+    });
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(policy) = policy {
+        request = request.header("x-arbstr-policy", policy);
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+    let (status, body) = common::parse_body(app.clone().oneshot(request).await.unwrap()).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+/// Lines of the dataset file, once it has at least `count`.
+async fn lines(path: &Path, count: usize) -> Vec<serde_json::Value> {
+    for _ in 0..100 {
+        if let Ok(text) = std::fs::read_to_string(path) {
+            let lines: Vec<serde_json::Value> = text
+                .lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect();
+            if lines.len() >= count {
+                return lines;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("dataset file never got {} lines", count);
+}
+
+#[tokio::test]
+async fn captures_sanitized_pairs_for_the_policy() {
+    let server = MockServer::start().await;
+    let path = temp_path();
+    let app = setup_app(
+        &server,
+        DatasetCaptureConfig {
+            path: path.to_string_lossy().into_owned(),
+            sample_rate: 1.0,
+            max_file_bytes: 1024 * 1024,
+            keep: 2,
+            sanitize: true,
+        },
+    )
+    .await;
+
+    complete(&app, Some("search")).await;
+    // Requests outside the policy are not captured
+    complete(&app, None).await;
+
+    let lines = lines(&path, 1).await;
+    assert_eq!(lines.len(), 1);
+    let record = &lines[0];
+    assert_eq!(
+        record["request_id"].as_str().unwrap().len(),
+        36,
+        "{}",
+        record
+    );
+    assert_eq!(record["policy"], "search");
+    assert_eq!(record["model"], "gpt-4o");
+    assert_eq!(record["provider"], "upstream");
+    assert_eq!(record["input_tokens"], 1000);
+    assert_eq!(record["output_tokens"], 500);
+    assert!(record["cost_sats"].as_f64().unwrap() > 0.0, "{}", record);
+    assert!(record["latency_ms"].is_i64(), "{}", record);
+    assert_eq!(
+        record["messages"][0]["content"],
+        "My key is [REDACTED], email me at [EMAIL]"
+    );
+    assert!(record["messages"][0].get("name").is_none(), "{}", record);
+    assert_eq!(record["response"]["role"], "assistant");
+    assert_eq!(record["response"]["content"], "Write to [EMAIL].");
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(!text.contains("customer-42"), "{}", text);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn unsampled_requests_are_not_captured() {
+    let server = MockServer::start().await;
+    let path = temp_path();
+    let app = setup_app(
+        &server,
+        DatasetCaptureConfig {
+            path: path.to_string_lossy().into_owned(),
+            sample_rate: 0.0,
+            max_file_bytes: 1024 * 1024,
+            keep: 2,
+            sanitize: true,
+        },
+    )
+    .await;
+
+    complete(&app, Some("search")).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!path.exists());
+}
@@ -0,0 +1,137 @@
+//! Integration tests for the /admin/db endpoints.
+//!
+//! Backs up a file-based SQLite database through the HTTP API and checks
+//! rotation, missing-config handling, bearer token protection, and the
+//! storage statistics report.
+
+mod common;
+
+use std::path::PathBuf;
+
+use arbstr::config::{BackupConfig, DatabaseConfig};
+use arbstr::proxy::{create_router, AppState};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("arbstr-admin-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Build an app backed by a file database in `dir`, optionally with backups
+/// configured and an auth token set.
+async fn setup_app(dir: &std::path::Path, backup: bool, auth_token: Option<&str>) -> axum::Router {
+    let db_path = dir.join("arbstr.db").display().to_string();
+    let pool = arbstr::storage::init_pool(&db_path).await.unwrap();
+
+    let mut config = common::db_test_config();
+    config.server.auth_token = auth_token.map(str::to_string);
+    config.database = Some(DatabaseConfig {
+        path: db_path,
+        backup: backup.then(|| BackupConfig {
+            dir: dir.join("backups").display().to_string(),
+            interval_hours: 0,
+            keep: 2,
+        }),
+        ..Default::default()
+    });
+
+    create_router(AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool),
+        ..common::setup_test_state(config)
+    })
+}
+
+fn post(uri: &str, token: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().method("POST").uri(uri);
+    if let Some(t) = token {
+        builder = builder.header("authorization", format!("Bearer {}", t));
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn backup_writes_file_and_rotates() {
+    let dir = temp_dir();
+    let app = setup_app(&dir, true, None).await;
+
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        let response = app
+            .clone()
+            .oneshot(post("/admin/db/backup", None))
+            .await
+            .unwrap();
+        let (status, json) = common::parse_body(response).await;
+        assert_eq!(status, 200);
+        assert!(json["size_bytes"].as_u64().unwrap() > 0);
+        paths.push(json["path"].as_str().unwrap().to_string());
+    }
+
+    // keep = 2: the first backup was rotated away
+    assert!(!std::path::Path::new(&paths[0]).exists());
+    assert!(std::path::Path::new(&paths[1]).exists());
+    assert!(std::path::Path::new(&paths[2]).exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn backup_without_config_returns_400() {
+    let dir = temp_dir();
+    let app = setup_app(&dir, false, None).await;
+
+    let response = app.oneshot(post("/admin/db/backup", None)).await.unwrap();
+    assert_eq!(response.status(), 400);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn admin_requires_token_when_configured() {
+    let dir = temp_dir();
+    let app = setup_app(&dir, true, Some("secret-token")).await;
+
+    let response = app
+        .clone()
+        .oneshot(post("/admin/db/checkpoint", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = app
+        .oneshot(post("/admin/db/checkpoint", Some("secret-token")))
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["busy"], 0);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn db_info_reports_sizes_counts_and_migration() {
+    let dir = temp_dir();
+    let app = setup_app(&dir, false, None).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/db")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert!(json["file_size_bytes"].as_u64().unwrap() > 0);
+    assert_eq!(json["tables"]["requests"], 0);
+    assert!(json["tables"].get("pending_settlements").is_some());
+    assert!(json["requests"]["oldest"].is_null());
+    assert!(json["migration"]["version"].as_i64().unwrap() > 0);
+    // No DbWriter in this app
+    assert!(json.get("write_queue").is_none());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,233 @@
+//! Integration tests for POST /v1/debug/echo.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use arbstr::config::{ApiKey, Config, PolicyRule, ProviderConfig, Tier, TrimConfig, TrimStrategy};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+/// App with one provider at `server` carrying an API key and an extra
+/// header, and a "brief" policy with a system prompt and token limits.
+async fn setup_app(server: &MockServer) -> axum::Router {
+    setup_app_with(server, |_| {}).await.0
+}
+
+/// [`setup_app`], with `customize` applied to the config.
+async fn setup_app_with(
+    server: &MockServer,
+    customize: impl FnOnce(&mut Config),
+) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1/", server.uri()),
+            api_key: Some(ApiKey::from("secret-key")),
+            extra_headers: BTreeMap::from([("x-org".to_string(), "org-secret".to_string())]),
+            ..common::test_provider("upstream")
+        },
+        ProviderConfig {
+            output_rate: 50.0,
+            ..common::test_provider("pricey")
+        },
+    ];
+    config.headers.forward_request = vec!["x-client-tag".to_string()];
+    config.policies.rules = vec![PolicyRule {
+        name: "brief".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: Some(1024),
+        default_max_tokens: Some(256),
+        system_prompt_prepend: Some("Be brief.".to_string()),
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    customize(&mut config);
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+async fn echo(
+    app: &axum::Router,
+    query: &str,
+    body: serde_json::Value,
+) -> (http::StatusCode, serde_json::Value) {
+    let request = Request::post(format!("/v1/debug/echo{}", query))
+        .header("content-type", "application/json")
+        .header("x-arbstr-policy", "brief")
+        .header("x-client-tag", "checkout")
+        .header("x-request-id", "echo-1")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    common::parse_body(app.clone().oneshot(request).await.unwrap()).await
+}
+
+#[tokio::test]
+async fn echo_shows_the_upstream_request_without_sending_it() {
+    let server = MockServer::start().await;
+    let app = setup_app(&server).await;
+
+    let (status, body) = echo(
+        &app,
+        "",
+        serde_json::json!({
+            "model": "gpt-4o",
+            "stream": true,
+            "messages": [{"role": "user", "content": "hi"}]
+        }),
+    )
+    .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["provider"], "upstream");
+    assert_eq!(body["policy"], "brief");
+    assert_eq!(body["method"], "POST");
+    assert_eq!(body["url"], format!("{}/v1/chat/completions", server.uri()));
+
+    let headers = &body["headers"];
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers["authorization"], "[REDACTED]");
+    assert_eq!(headers["x-org"], "[REDACTED]");
+    assert_eq!(headers["x-client-tag"], "checkout");
+    assert!(headers["idempotency-key"].is_string(), "{}", headers);
+    assert!(!body.to_string().contains("secret"), "{}", body);
+
+    let upstream = &body["body"];
+    assert_eq!(upstream["max_tokens"], 256);
+    assert_eq!(upstream["stream_options"]["include_usage"], true);
+    assert_eq!(upstream["messages"][0]["role"], "system");
+    assert_eq!(upstream["messages"][0]["content"], "Be brief.");
+    assert_eq!(
+        body["transformations"],
+        serde_json::json!([
+            "max_tokens: injected",
+            "system_prompt: injected",
+            "stream_options: include_usage"
+        ])
+    );
+
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn echo_for_a_named_candidate() {
+    let server = MockServer::start().await;
+    let app = setup_app(&server).await;
+    let request = serde_json::json!({
+        "model": "gpt-4o",
+        "max_tokens": 4096,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+
+    let (status, body) = echo(&app, "?provider=pricey", request.clone()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["provider"], "pricey");
+    assert_eq!(body["url"], "https://fake.test/v1/chat/completions");
+    assert!(body["headers"].get("authorization").is_none(), "{}", body);
+    assert_eq!(body["body"]["max_tokens"], 1024);
+    assert!(body["body"].get("stream_options").is_none(), "{}", body);
+
+    let (status, body) = echo(&app, "?provider=nope", request).await;
+    assert_eq!(status, 400, "{}", body);
+}
+
+#[tokio::test]
+async fn echo_runs_the_rewrites_without_side_effects() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app_with(&server, |config| {
+        config.archive.enabled = true;
+        config.policies.rules[0].trim = Some(TrimConfig {
+            context_tokens: 300,
+            strategy: TrimStrategy::Summarize,
+            summary_model: Some("gpt-4o".to_string()),
+        });
+    })
+    .await;
+    let long = "word ".repeat(200);
+
+    let (status, body) = echo(
+        &app,
+        "",
+        serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": long},
+                {"role": "user", "content": "and now?"}
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        body["transformations"],
+        serde_json::json!([
+            "max_tokens: injected",
+            "trim: dropped 2 messages (summary not generated)",
+            "system_prompt: injected"
+        ])
+    );
+
+    // No summary call, no archived prompt, no request log
+    assert!(server.received_requests().await.unwrap().is_empty());
+    for table in ["prompt_archive", "requests"] {
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 0, "{}", table);
+    }
+}
+
+#[tokio::test]
+async fn echo_falls_back_to_the_client_token() {
+    let server = MockServer::start().await;
+    let (app, _pool) = setup_app_with(&server, |config| {
+        config.server.auth_token = Some("client-secret".to_string());
+    })
+    .await;
+    let request = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+
+    // The system prompt is not for anyone who can reach the port
+    let (status, _) = echo(&app, "", request.clone()).await;
+    assert_eq!(status, 401);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/debug/echo")
+                .header("content-type", "application/json")
+                .header("authorization", "Bearer client-secret")
+                .body(Body::from(request.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
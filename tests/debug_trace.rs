@@ -0,0 +1,151 @@
+//! Integration tests for `x-arbstr-debug: true` decision traces and
+//! GET /v1/requests/{id}/trace.
+
+mod common;
+
+use arbstr::config::{BackoffConfig, ProviderConfig};
+use arbstr::proxy::create_router;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    let response = if status == 200 {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-trace",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    } else {
+        ResponseTemplate::new(status).set_body_string("upstream unavailable")
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// "primary" (cheapest) answers 503, "fallback" answers 200, "retired" is
+/// disabled. Backoff is 10ms doubling, without jitter.
+async fn setup_app(primary: &MockServer, fallback: &MockServer) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", primary.uri()),
+            ..common::test_provider("primary")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", fallback.uri()),
+            output_rate: 50.0,
+            ..common::test_provider("fallback")
+        },
+        ProviderConfig {
+            enabled: false,
+            ..common::test_provider("retired")
+        },
+    ];
+    config.routing.backoff = BackoffConfig {
+        base_ms: 10,
+        jitter: false,
+        ..Default::default()
+    };
+    let (state, _pool) = common::setup_db_test_state(config).await;
+    create_router(state)
+}
+
+/// Send a completion, returning its `x-arbstr-request-id`.
+async fn complete(app: &axum::Router, debug: bool) -> String {
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if debug {
+        request = request.header("x-arbstr-debug", "true");
+    }
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.headers()["x-arbstr-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn trace(app: &axum::Router, id: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::get(format!("/v1/requests/{}/trace", id))
+        .body(Body::empty())
+        .unwrap();
+    common::parse_body(app.clone().oneshot(request).await.unwrap()).await
+}
+
+#[tokio::test]
+async fn debug_request_records_its_routing_decisions() {
+    let primary = mock_provider(503).await;
+    let fallback = mock_provider(200).await;
+    let app = setup_app(&primary, &fallback).await;
+
+    let id = complete(&app, true).await;
+    let (status, trace) = trace(&app, &id).await;
+    assert_eq!(status, StatusCode::OK, "{}", trace);
+
+    assert_eq!(trace["request_id"], id.as_str());
+    assert_eq!(trace["model"], "gpt-4o");
+    assert_eq!(trace["streaming"], false);
+    assert!(trace["complexity_score"].is_number());
+    assert!(!trace["tiers"].as_array().unwrap().is_empty());
+
+    let candidates = trace["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), 3, "{}", trace);
+    assert_eq!(candidates[0]["provider"], "primary");
+    assert_eq!(candidates[0]["rank"], 1);
+    assert_eq!(candidates[0]["routing_cost"], 15.0);
+    assert_eq!(candidates[0]["circuit_state"], "closed");
+    assert_eq!(candidates[1]["provider"], "fallback");
+    assert_eq!(candidates[1]["rank"], 2);
+    assert_eq!(candidates[1]["effective_cost"], 50.0);
+    assert_eq!(candidates[2]["provider"], "retired");
+    assert!(candidates[2].get("rank").is_none());
+    assert_eq!(candidates[2]["skip_reason"], "maintenance");
+
+    let attempts = trace["attempts"].as_array().unwrap();
+    assert_eq!(attempts.len(), 3);
+    assert!(attempts
+        .iter()
+        .all(|a| a["provider"] == "primary" && a["status"] == 503));
+    let backoffs: Vec<i64> = attempts
+        .iter()
+        .map(|a| a["backoff_ms"].as_i64().unwrap())
+        .collect();
+    assert_eq!(backoffs, vec![0, 10, 20]);
+
+    assert_eq!(trace["provider"], "fallback");
+    assert_eq!(trace["success"], true);
+    assert!(trace["latency_ms"].is_i64());
+}
+
+#[tokio::test]
+async fn requests_without_the_header_are_not_traced() {
+    let primary = mock_provider(200).await;
+    let fallback = mock_provider(200).await;
+    let app = setup_app(&primary, &fallback).await;
+
+    let id = complete(&app, false).await;
+    let (status, _) = trace(&app, &id).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -17,11 +17,29 @@ fn test_provider(
         url: url.to_string(),
         api_key: None,
         models,
-        input_rate: 0,
-        output_rate: 0,
-        base_fee: 0,
+        input_rate: 0.0,
+        output_rate: 0.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: Tier::Local,
         auto_discover,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }
 }
 
@@ -0,0 +1,116 @@
+//! Integration tests for per-provider `resolve` overrides and the
+//! DNS-over-HTTPS resolver.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::dns::DohResolver;
+
+fn completion_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-dns",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "ok"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion_json()))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn resolve_override_pins_provider_host() {
+    let server = mock_provider().await;
+    let port = server.address().port();
+
+    // `.invalid` never resolves through real DNS
+    let mut resolve = BTreeMap::new();
+    resolve.insert("node.arbstr.invalid".to_string(), server.address().ip());
+    let provider = ProviderConfig {
+        url: format!("http://node.arbstr.invalid:{}/v1", port),
+        resolve,
+        ..common::test_provider("pinned")
+    };
+    let (app, _registry) = common::setup_circuit_test_app(vec![provider]);
+
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200, "{}", json);
+    assert_eq!(json["arbstr"]["provider"], "pinned");
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(
+        requests[0].headers.get("host").unwrap(),
+        &format!("node.arbstr.invalid:{}", port)
+    );
+}
+
+#[tokio::test]
+async fn doh_resolver_routes_client_requests() {
+    let provider = mock_provider().await;
+    let doh = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("name", "node.arbstr.invalid"))
+        .and(query_param("type", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "Status": 0,
+            "Answer": [{"name": "node.arbstr.invalid", "type": 1, "TTL": 30,
+                        "data": provider.address().ip().to_string()}]
+        })))
+        .mount(&doh)
+        .await;
+    Mock::given(method("GET"))
+        .and(query_param("type", "28"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"Status": 0})))
+        .mount(&doh)
+        .await;
+
+    let resolver = Arc::new(DohResolver::new(doh.uri()).unwrap());
+    let client = reqwest::Client::builder()
+        .dns_resolver(resolver)
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!(
+            "http://node.arbstr.invalid:{}/v1/chat/completions",
+            provider.address().port()
+        ))
+        .json(&serde_json::json!({"model": "gpt-4o", "messages": []}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
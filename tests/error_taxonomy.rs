@@ -0,0 +1,194 @@
+//! Integration tests for typed provider errors: client-facing codes,
+//! stored `error_type`, circuit breaker error types, and stats counters.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{BackoffConfig, Config, RoutingConfig};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+
+/// App with a single provider at `url` and near-zero retry backoff.
+async fn setup_app(url: String) -> (axum::Router, SqlitePool) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let mut provider = common::test_provider("alpha");
+    provider.url = url;
+    let config = Config {
+        providers: vec![provider],
+        routing: RoutingConfig {
+            backoff: BackoffConfig {
+                base_ms: 1,
+                max_ms: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..common::db_test_config()
+    };
+
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["alpha".to_string()])),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool)
+}
+
+async fn mock_status(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(status).set_body_string("nope"))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn send(app: &axum::Router) -> (u16, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    (status.as_u16(), json)
+}
+
+/// Wait for the writer to persist the request row, then return its error_type.
+async fn stored_error_type(pool: &SqlitePool) -> Option<String> {
+    for _ in 0..100 {
+        let row: Option<(Option<String>,)> =
+            sqlx::query_as("SELECT error_type FROM requests LIMIT 1")
+                .fetch_optional(pool)
+                .await
+                .unwrap();
+        if let Some((error_type,)) = row {
+            return error_type;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("request row never written");
+}
+
+async fn stats_errors(app: &axum::Router) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/stats?since=2020-01-01T00:00:00Z&until=2100-01-01T00:00:00Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, json) = common::parse_body(response).await;
+    json["errors"].clone()
+}
+
+#[tokio::test]
+async fn rate_limit_is_passed_through_as_429() {
+    let server = mock_status(429).await;
+    let (app, pool) = setup_app(format!("{}/v1", server.uri())).await;
+
+    let (status, json) = send(&app).await;
+    assert_eq!(status, 429);
+    assert_eq!(json["error"]["type"], "provider_error");
+    assert_eq!(json["error"]["code"], "provider_rate_limited");
+    assert_eq!(
+        stored_error_type(&pool).await.as_deref(),
+        Some("rate_limited")
+    );
+
+    let errors = stats_errors(&app).await;
+    assert_eq!(errors["rate_limited"], 1);
+    assert_eq!(errors["5xx"], 0);
+}
+
+#[tokio::test]
+async fn auth_failure_has_its_own_code_and_keeps_circuit_closed() {
+    let server = mock_status(401).await;
+    let (app, pool) = setup_app(format!("{}/v1", server.uri())).await;
+
+    let (status, json) = send(&app).await;
+    assert_eq!(status, 502);
+    assert_eq!(json["error"]["code"], "provider_auth_failed");
+    assert_eq!(stored_error_type(&pool).await.as_deref(), Some("auth"));
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, health) = common::parse_body(response).await;
+    assert_eq!(health["providers"]["alpha"]["failure_count"], 0);
+    assert_eq!(stats_errors(&app).await["auth"], 1);
+}
+
+#[tokio::test]
+async fn server_error_is_classified_as_5xx() {
+    let server = mock_status(503).await;
+    let (app, pool) = setup_app(format!("{}/v1", server.uri())).await;
+
+    let (status, json) = send(&app).await;
+    assert_eq!(status, 502);
+    assert_eq!(json["error"]["code"], "provider_server_error");
+    assert_eq!(stored_error_type(&pool).await.as_deref(), Some("5xx"));
+    assert_eq!(stats_errors(&app).await["5xx"], 1);
+}
+
+#[tokio::test]
+async fn malformed_response_is_classified() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+        .mount(&server)
+        .await;
+    let (app, pool) = setup_app(format!("{}/v1", server.uri())).await;
+
+    let (status, json) = send(&app).await;
+    assert_eq!(status, 502);
+    assert_eq!(json["error"]["code"], "provider_malformed_response");
+    assert_eq!(stored_error_type(&pool).await.as_deref(), Some("malformed"));
+    assert_eq!(stats_errors(&app).await["malformed"], 1);
+}
+
+#[tokio::test]
+async fn connect_failure_is_classified_and_trips_circuit_breaker() {
+    // Nothing listens on port 1
+    let (app, pool) = setup_app("http://127.0.0.1:1/v1".to_string()).await;
+
+    let (status, json) = send(&app).await;
+    assert_eq!(status, 502);
+    assert_eq!(json["error"]["code"], "provider_unreachable");
+    assert_eq!(stored_error_type(&pool).await.as_deref(), Some("connect"));
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, health) = common::parse_body(response).await;
+    // One failure per attempt: the first try and two retries
+    assert_eq!(health["providers"]["alpha"]["failure_count"], 3);
+    assert_eq!(stats_errors(&app).await["connect"], 1);
+}
@@ -76,33 +76,87 @@ async fn tiered_providers() -> Vec<ProviderConfig> {
             url: local_url,
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 1,
-            output_rate: 5,
-            base_fee: 0,
+            input_rate: 1.0,
+            output_rate: 5.0,
+            base_fee: 0.0,
+            min_charge_sats: 0.0,
             tier: Tier::Local,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "standard-provider".to_string(),
             url: standard_url,
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 5,
-            output_rate: 15,
-            base_fee: 1,
+            input_rate: 5.0,
+            output_rate: 15.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: Tier::Standard,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
         ProviderConfig {
             name: "frontier-provider".to_string(),
             url: frontier_url,
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 2,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 2.0,
+            min_charge_sats: 0.0,
             tier: Tier::Frontier,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         },
     ]
 }
@@ -0,0 +1,152 @@
+//! Integration tests for `[evaluation]` runs and quality-score routing.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{EvaluationConfig, EvaluationPrompt, ProviderConfig};
+use arbstr::proxy::create_router;
+use arbstr::proxy::evaluation::{self, QualityScores};
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider(reply: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-eval",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": reply},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 1, "total_tokens": 13}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn evaluation_config() -> EvaluationConfig {
+    EvaluationConfig {
+        hour_utc: 3,
+        max_tokens: 8,
+        timeout_secs: 5,
+        min_quality_score: Some(0.8),
+        prompts: vec![
+            EvaluationPrompt {
+                name: "arithmetic".to_string(),
+                prompt: "What is 2+2? Reply with the number only.".to_string(),
+                expect: Some("4".to_string()),
+                pattern: None,
+            },
+            EvaluationPrompt {
+                name: "smoke".to_string(),
+                prompt: "Say anything.".to_string(),
+                expect: None,
+                pattern: None,
+            },
+        ],
+    }
+}
+
+#[tokio::test]
+async fn evaluation_scores_pairs_and_demotes_low_quality_providers() {
+    let good = mock_provider("4").await;
+    let cheap = mock_provider("five").await;
+
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", good.uri()),
+            output_rate: 20.0,
+            ..common::test_provider("good")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", cheap.uri()),
+            output_rate: 5.0,
+            ..common::test_provider("cheap")
+        },
+    ];
+    config.evaluation = Some(evaluation_config());
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.quality = Arc::new(QualityScores::new(state.config.evaluation.as_ref()));
+
+    let rows = evaluation::run(&state, &evaluation_config()).await;
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|r| r.success && r.cost_sats.is_some()));
+
+    let stored: Vec<(String, String, Option<bool>, f64)> = sqlx::query_as(
+        "SELECT provider, prompt, passed, score FROM evaluations ORDER BY provider, prompt",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        stored,
+        vec![
+            (
+                "cheap".to_string(),
+                "arithmetic".to_string(),
+                Some(false),
+                0.0
+            ),
+            ("cheap".to_string(), "smoke".to_string(), None, 1.0),
+            (
+                "good".to_string(),
+                "arithmetic".to_string(),
+                Some(true),
+                1.0
+            ),
+            ("good".to_string(), "smoke".to_string(), None, 1.0),
+        ]
+    );
+    assert_eq!(state.quality.get("cheap", "gpt-4o"), Some(0.5));
+    assert_eq!(state.quality.get("good", "gpt-4o"), Some(1.0));
+
+    // Scores survive a restart via the evaluations table
+    let restored = QualityScores::default();
+    restored.restore(&pool).await.unwrap();
+    assert_eq!(restored.get("cheap", "gpt-4o"), Some(0.5));
+
+    let app = create_router(state);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/route/explain?model=gpt-4o")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["selected"], "good");
+    assert_eq!(body["candidates"][1]["provider"], "cheap");
+    assert_eq!(body["candidates"][1]["quality_score"], 0.5);
+
+    // The cheaper provider scored below min_quality_score, so it goes last
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hi"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-arbstr-provider"], "good");
+}
@@ -0,0 +1,141 @@
+//! Integration tests for the GET /v1/stats/forecast endpoint.
+//!
+//! Seeds a steady daily spend history and verifies the projection,
+//! grouping, budget comparison, and parameter validation.
+
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// Global counter for generating unique correlation IDs.
+static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Insert a successful request `hours_ago` hours before now.
+async fn seed_spend(pool: &SqlitePool, hours_ago: i64, model: &str, provider: &str, cost: f64) {
+    let correlation_id = format!(
+        "forecast-{}",
+        CORRELATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(hours_ago)).to_rfc3339();
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         cost_sats, latency_ms, success) VALUES (?, ?, ?, ?, 0, ?, 100, 1)",
+    )
+    .bind(&correlation_id)
+    .bind(&timestamp)
+    .bind(model)
+    .bind(provider)
+    .bind(cost)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+}
+
+/// Seed 7 days of spend: 10 sats/day on alpha, 5 sats/day on beta.
+async fn seed_steady_spend(pool: &SqlitePool) {
+    for day in 0..7 {
+        let hours_ago = day * 24 + 12;
+        seed_spend(pool, hours_ago, "gpt-4o", "alpha", 10.0).await;
+        seed_spend(pool, hours_ago, "gpt-4o-mini", "beta", 5.0).await;
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn forecast_steady_burn_rate() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_steady_spend(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/forecast").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["interval"], "daily");
+    assert_eq!(json["lookback"]["buckets"], 7);
+
+    let total = &json["total"];
+    let burn = total["burn_rate_sats"].as_f64().unwrap();
+    assert!((burn - 15.0).abs() < 1e-6, "burn rate was {}", burn);
+
+    let mtd = total["month_to_date_sats"].as_f64().unwrap();
+    let projected = total["projected_sats"].as_f64().unwrap();
+    assert!(projected >= mtd);
+    // Flat series has no residual spread
+    assert_eq!(total["lower_sats"], total["upper_sats"]);
+}
+
+#[tokio::test]
+async fn forecast_group_by_provider() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_steady_spend(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/forecast?group_by=provider").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["group_by"], "provider");
+    let alpha = json["groups"]["alpha"]["burn_rate_sats"].as_f64().unwrap();
+    let beta = json["groups"]["beta"]["burn_rate_sats"].as_f64().unwrap();
+    assert!((alpha - 10.0).abs() < 1e-6);
+    assert!((beta - 5.0).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn forecast_model_filter() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_steady_spend(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/forecast?model=gpt-4o-mini").await;
+    assert_eq!(status, 200);
+    let burn = json["total"]["burn_rate_sats"].as_f64().unwrap();
+    assert!((burn - 5.0).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn forecast_budget_flags() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_steady_spend(&pool).await;
+
+    let (status, json) = get_json(app.clone(), "/v1/stats/forecast?budget_sats=0").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["budget"]["projected_over"], true);
+
+    let (status, json) = get_json(app, "/v1/stats/forecast?budget_sats=1000000").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["budget"]["projected_over"], false);
+    assert_eq!(json["budget"]["at_risk"], false);
+}
+
+#[tokio::test]
+async fn forecast_empty_db() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let (status, json) = get_json(app, "/v1/stats/forecast?interval=hourly").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["lookback"]["buckets"], 24);
+    assert_eq!(json["total"]["projected_sats"], 0.0);
+    assert!(json.get("groups").is_none());
+}
+
+#[tokio::test]
+async fn forecast_invalid_params_return_400() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    for uri in [
+        "/v1/stats/forecast?interval=weekly",
+        "/v1/stats/forecast?range=last_24h",
+        "/v1/stats/forecast?group_by=tier",
+        "/v1/stats/forecast?budget_sats=-5",
+    ] {
+        let (status, _) = get_json(app.clone(), uri).await;
+        assert_eq!(status, 400, "expected 400 for {}", uri);
+    }
+}
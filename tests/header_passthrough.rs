@@ -0,0 +1,133 @@
+//! Integration tests for client/provider header passthrough.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::ProviderConfig;
+
+fn completion_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-passthrough",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "ok"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+/// Provider returning rate limit headers plus one that should not leak.
+async fn mock_provider(stream: bool) -> MockServer {
+    let server = MockServer::start().await;
+    let template = ResponseTemplate::new(200)
+        .insert_header("x-ratelimit-remaining-requests", "99")
+        .insert_header("x-internal-node", "node-7");
+    let template = if stream {
+        template
+            .insert_header("content-type", "text/event-stream")
+            .set_body_string(
+            "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"}}]}\n\n\
+                 data: [DONE]\n\n",
+        )
+    } else {
+        template.set_body_json(completion_json())
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(template)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn app_for(server: &MockServer) -> axum::Router {
+    let provider = ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    };
+    common::setup_circuit_test_app(vec![provider]).0
+}
+
+async fn send_chat(app: axum::Router, stream: bool) -> axum::response::Response {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    app.oneshot(
+        Request::post("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .header("OpenAI-Organization", "org-abc")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .header("x-client-secret", "do-not-forward")
+            .header("cookie", "session=1")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn allowed_request_headers_reach_provider() {
+    let server = mock_provider(false).await;
+    let response = send_chat(app_for(&server), false).await;
+    assert_eq!(response.status(), 200);
+
+    let requests = server.received_requests().await.unwrap();
+    let headers = &requests[0].headers;
+    assert_eq!(headers.get("openai-organization").unwrap(), "org-abc");
+    // Trace context is continued with arbstr as the parent span
+    let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+    assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+    assert!(headers.get("x-client-secret").is_none());
+    assert!(headers.get("cookie").is_none());
+}
+
+#[tokio::test]
+async fn allowed_response_headers_reach_client() {
+    let server = mock_provider(false).await;
+    let response = send_chat(app_for(&server), false).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("x-ratelimit-remaining-requests")
+            .unwrap(),
+        "99"
+    );
+    assert!(response.headers().get("x-internal-node").is_none());
+    assert!(response.headers().get("x-arbstr-provider").is_some());
+}
+
+#[tokio::test]
+async fn streaming_response_forwards_allowed_headers() {
+    let server = mock_provider(true).await;
+    let response = send_chat(app_for(&server), true).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("x-ratelimit-remaining-requests")
+            .unwrap(),
+        "99"
+    );
+    assert!(response.headers().get("x-internal-node").is_none());
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(
+        requests[0].headers.get("openai-organization").unwrap(),
+        "org-abc"
+    );
+}
@@ -8,6 +8,7 @@
 //! - Zero configured providers returns "ok" with empty providers object
 //! - Half-open providers count as degraded, not unhealthy
 //! - Failure count is accurately reported
+//! - Connection stats and pool settings are reported per provider
 
 mod common;
 
@@ -16,8 +17,10 @@ use std::time::Duration;
 use axum::body::Body;
 use http::Request;
 use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
-use arbstr::config::{ProviderConfig, Tier};
+use arbstr::config::{PoolConfig, ProviderConfig, Tier};
 use arbstr::proxy::{CircuitBreakerRegistry, CircuitState};
 
 /// Number of failures needed to trip a circuit (matches FAILURE_THRESHOLD in circuit_breaker.rs).
@@ -37,11 +40,29 @@ fn test_provider(name: &str) -> ProviderConfig {
         url: "https://fake.test/v1".to_string(),
         api_key: None,
         models: vec!["gpt-4o".to_string()],
-        input_rate: 5,
-        output_rate: 15,
-        base_fee: 0,
+        input_rate: 5.0,
+        output_rate: 15.0,
+        base_fee: 0.0,
+        min_charge_sats: 0.0,
         tier: Tier::default(),
         auto_discover: false,
+        canary: false,
+        canary_percent: 5,
+        auth_scheme: Default::default(),
+        extra_headers: Default::default(),
+        pool: None,
+        resolve: Default::default(),
+        backoff: None,
+        balance_sats: None,
+        balance_reset: None,
+        region: None,
+        requests_per_minute: None,
+        tokens_per_minute: None,
+        structured_output: false,
+        enabled: true,
+        maintenance_until: None,
+        slo: None,
+        normalize_stream: false,
     }
 }
 
@@ -245,7 +266,7 @@ async fn test_health_failure_count_increments() {
 
     // Record 2 failures (below threshold of 3)
     registry.record_failure("provider-a", "5xx", "Error 1");
-    registry.record_failure("provider-a", "5xx", "Error 2");
+    registry.record_failure("provider-a", "timeout", "Error 2");
 
     let request = Request::get("/health").body(Body::empty()).unwrap();
     let response = app.oneshot(request).await.unwrap();
@@ -255,4 +276,83 @@ async fn test_health_failure_count_increments() {
     assert_eq!(json["status"], "ok");
     assert_eq!(json["providers"]["provider-a"]["state"], "closed");
     assert_eq!(json["providers"]["provider-a"]["failure_count"], 2);
+    assert_eq!(
+        json["providers"]["provider-a"]["failures_by_type"],
+        serde_json::json!({"5xx": 1, "timeout": 1})
+    );
+}
+
+// ============================================================================
+// Test 9: Connection stats and pool settings
+// ============================================================================
+
+#[tokio::test]
+async fn test_health_reports_connection_stats() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-pool",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+
+    let pooled = ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        input_rate: 1.0,
+        pool: Some(PoolConfig {
+            max_idle_per_host: Some(8),
+            idle_timeout_secs: Some(30),
+            tcp_keepalive_secs: Some(15),
+            ..Default::default()
+        }),
+        ..test_provider("pooled")
+    };
+    let providers = vec![pooled, test_provider("plain")];
+    let (app, _registry) = common::setup_circuit_test_app(providers);
+
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("x-arbstr-provider").unwrap(),
+        "pooled"
+    );
+
+    let request = Request::get("/health").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let (_, json) = common::parse_body(response).await;
+
+    let pooled = &json["providers"]["pooled"]["connections"];
+    assert_eq!(pooled["requests"], 1);
+    assert_eq!(pooled["in_flight"], 0);
+    assert_eq!(pooled["errors"], 0);
+    assert_eq!(pooled["http_version"], "HTTP/1.1");
+    assert!(pooled["avg_time_to_headers_ms"].is_number());
+    assert_eq!(pooled["pool"]["max_idle_per_host"], 8);
+    assert_eq!(pooled["pool"]["idle_timeout_secs"], 30);
+
+    let plain = &json["providers"]["plain"]["connections"];
+    assert_eq!(plain["requests"], 0);
+    assert!(plain.get("pool").is_none());
 }
@@ -0,0 +1,162 @@
+//! Integration tests for the prepaid provider ledger (`balance_sats`).
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::ledger::ProviderLedger;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use arbstr::storage::{memory, DbWriter};
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// "prepaid" is cheapest but holds 15 sats; "backup" costs more and is unmetered.
+async fn setup_app() -> (axum::Router, SqlitePool, Arc<ProviderLedger>) {
+    let url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let mut prepaid = common::test_provider("prepaid");
+    prepaid.url = url.clone();
+    prepaid.input_rate = 0.0;
+    prepaid.output_rate = 0.0;
+    prepaid.base_fee = 10.0;
+    prepaid.balance_sats = Some(15.0);
+    let mut backup = common::test_provider("backup");
+    backup.url = url;
+    backup.input_rate = 0.0;
+    backup.output_rate = 0.0;
+    backup.base_fee = 20.0;
+    let providers = vec![prepaid, backup];
+
+    let mut config = common::db_test_config();
+    config.providers = providers.clone();
+
+    let pool = memory::init_pool().await.unwrap();
+    let ledger = Arc::new(ProviderLedger::new(&providers));
+    ledger.restore(&pool).await.unwrap();
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&[
+            "prepaid".to_string(),
+            "backup".to_string(),
+        ])),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        ledger: ledger.clone(),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool, ledger)
+}
+
+/// Send a chat completion and return the provider that served it.
+async fn complete(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn top_up(
+    app: &axum::Router,
+    provider: &str,
+    body: serde_json::Value,
+) -> (u16, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post(format!("/admin/ledger/{}/topup", provider))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    (status.as_u16(), body)
+}
+
+#[tokio::test]
+async fn exhausted_provider_is_skipped_until_topped_up() {
+    let (app, pool, _ledger) = setup_app().await;
+
+    assert_eq!(complete(&app).await, "prepaid");
+    // 5 sats left, a request costs 10
+    assert_eq!(complete(&app).await, "backup");
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/admin/ledger").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    let accounts = body["providers"].as_array().unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0]["provider"], "prepaid");
+    assert_eq!(accounts[0]["spent_sats"], 10.0);
+    assert_eq!(accounts[0]["remaining_sats"], 5.0);
+
+    let (status, body) = top_up(
+        &app,
+        "prepaid",
+        serde_json::json!({"amount_sats": 100, "note": "cashu token"}),
+    )
+    .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["topped_up_sats"], 100.0);
+    assert_eq!(body["remaining_sats"], 105.0);
+    assert_eq!(complete(&app).await, "prepaid");
+
+    let note: String = sqlx::query_scalar("SELECT note FROM provider_ledger WHERE kind = 'topup'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(note, "cashu token");
+
+    // A restart restores the balance from the ledger and request log
+    let restored = ProviderLedger::new(&[{
+        let mut p = common::test_provider("prepaid");
+        p.balance_sats = Some(15.0);
+        p
+    }]);
+    let mut remaining = 0.0;
+    for _ in 0..50 {
+        restored.restore(&pool).await.unwrap();
+        remaining = restored.snapshots()[0].remaining_sats;
+        if remaining == 95.0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(remaining, 95.0);
+}
+
+#[tokio::test]
+async fn topup_rejects_unknown_providers_and_bad_amounts() {
+    let (app, _pool, _ledger) = setup_app().await;
+
+    let (status, _) = top_up(&app, "backup", serde_json::json!({"amount_sats": 10})).await;
+    assert_eq!(status, 404);
+    let (status, _) = top_up(&app, "prepaid", serde_json::json!({"amount_sats": -5})).await;
+    assert_eq!(status, 400);
+}
@@ -0,0 +1,192 @@
+//! Integration tests for `[server.limits]` edge limits and their counters.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{LimitsConfig, ProviderConfig};
+
+/// Provider answering every completion after `delay`.
+async fn mock_provider(delay: Duration) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_delay(delay).set_body_json(
+            serde_json::json!({
+                "id": "chatcmpl-limits",
+                "object": "chat.completion",
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "ok"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+            }),
+        ))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn app_with_limits(server: &MockServer, limits: LimitsConfig) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.server.limits = limits;
+    common::setup_db_test_app_with_config(config).await.0
+}
+
+fn chat_body() -> String {
+    serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    })
+    .to_string()
+}
+
+fn chat(body: Body) -> Request<Body> {
+    Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap()
+}
+
+async fn limits_stats(app: axum::Router) -> serde_json::Value {
+    let response = app
+        .oneshot(
+            Request::get("/v1/stats/limits")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    common::parse_body(response).await.1
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_rejected() {
+    let server = mock_provider(Duration::ZERO).await;
+    let app = app_with_limits(
+        &server,
+        LimitsConfig {
+            max_body_bytes: Some(256),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let response = app.clone().oneshot(chat(chat_body().into())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let padded = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "x".repeat(1000)}]
+    })
+    .to_string();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("content-length", padded.len())
+                .body(Body::from(padded.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let (_, body) = common::parse_body(response).await;
+    assert_eq!(body["error"]["code"], "request_too_large");
+
+    // Without Content-Length the body is cut off as it is read
+    let chunks: Vec<Result<String, std::io::Error>> = padded
+        .as_bytes()
+        .chunks(100)
+        .map(|c| Ok(String::from_utf8(c.to_vec()).unwrap()))
+        .collect();
+    let response = app
+        .clone()
+        .oneshot(chat(Body::from_stream(futures::stream::iter(chunks))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let stats = limits_stats(app).await;
+    assert_eq!(stats["max_body_bytes"], 256);
+    assert_eq!(stats["rejected"]["body_too_large"], 2);
+    assert_eq!(stats["rejected"]["timed_out"], 0);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn slow_requests_time_out_at_the_edge() {
+    let server = mock_provider(Duration::from_secs(3)).await;
+    let app = app_with_limits(
+        &server,
+        LimitsConfig {
+            request_timeout_secs: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let response = app.clone().oneshot(chat(chat_body().into())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    let (_, body) = common::parse_body(response).await;
+    assert_eq!(body["error"]["code"], "request_timeout");
+
+    let stats = limits_stats(app).await;
+    assert_eq!(stats["rejected"]["timed_out"], 1);
+}
+
+#[tokio::test]
+async fn requests_beyond_concurrency_limit_are_shed() {
+    let server = mock_provider(Duration::from_millis(500)).await;
+    let app = app_with_limits(
+        &server,
+        LimitsConfig {
+            max_concurrent_requests: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let first = tokio::spawn(app.clone().oneshot(chat(chat_body().into())));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let response = app.clone().oneshot(chat(chat_body().into())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()["retry-after"], "1");
+    let (_, body) = common::parse_body(response).await;
+    assert_eq!(body["error"]["code"], "server_overloaded");
+
+    let response = first.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // The slot is held until the body is consumed
+    common::parse_body(response).await;
+
+    let stats = limits_stats(app).await;
+    assert_eq!(stats["max_concurrent_requests"], 1);
+    assert_eq!(stats["rejected"]["over_capacity"], 1);
+    assert_eq!(stats["in_flight"], 1, "the stats request holds the slot");
+}
+
+#[tokio::test]
+async fn limits_are_off_by_default() {
+    let server = mock_provider(Duration::ZERO).await;
+    let app = app_with_limits(&server, LimitsConfig::default()).await;
+    let stats = limits_stats(app).await;
+    assert!(stats["max_body_bytes"].is_null());
+    assert_eq!(stats["in_flight"], 0);
+    assert_eq!(stats["rejected"]["over_capacity"], 0);
+}
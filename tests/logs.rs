@@ -646,3 +646,61 @@ async fn test_logs_error_section_absent_on_success() {
         entry
     );
 }
+
+/// Test 21: error_contains and status filters slice failures
+#[tokio::test]
+async fn test_logs_error_message_and_status_filters() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_logs_data(&pool).await;
+    let recent = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    for (status, message) in [
+        (504, "Upstream TIMEOUT after 30s"),
+        (429, "Rate limited: retry in 100% of window"),
+    ] {
+        seed_request(
+            &pool,
+            &recent,
+            "gpt-4o",
+            "beta",
+            false,
+            false,
+            None,
+            None,
+            None,
+            30_000,
+            None,
+            Some(status),
+            Some(message),
+        )
+        .await;
+    }
+
+    let (status, body) = get(app.clone(), "/v1/requests?error_contains=timeout").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["data"][0]["error"]["status"], 504);
+
+    // LIKE metacharacters match literally
+    let (_, body) = get(app.clone(), "/v1/requests?error_contains=100%25").await;
+    assert_eq!(body["total"], 1);
+    let (_, body) = get(app.clone(), "/v1/requests?error_contains=%25").await;
+    assert_eq!(body["total"], 1);
+
+    let (_, body) = get(app.clone(), "/v1/requests?status=5xx").await;
+    assert_eq!(body["total"], 2);
+    let (_, body) = get(app.clone(), "/v1/requests?status=4XX").await;
+    assert_eq!(body["total"], 1);
+    let (_, body) = get(app.clone(), "/v1/requests?status=502").await;
+    assert_eq!(body["total"], 1);
+    let (_, body) = get(
+        app.clone(),
+        "/v1/requests?status=5xx&error_contains=returned",
+    )
+    .await;
+    assert_eq!(body["total"], 1);
+
+    for uri in ["/v1/requests?status=6xx", "/v1/requests?status=abc"] {
+        let (status, _) = get(app.clone(), uri).await;
+        assert_eq!(status, 400, "expected 400 for {}", uri);
+    }
+}
@@ -0,0 +1,174 @@
+//! Integration tests for provider maintenance mode (`enabled`,
+//! `maintenance_until`) and PUT /admin/providers/{name}/maintenance.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::CircuitState;
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-maintenance",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A cheap provider disabled in config and a pricier enabled one.
+fn providers(cheap: &MockServer, backup: &MockServer) -> Vec<ProviderConfig> {
+    vec![
+        ProviderConfig {
+            url: format!("{}/v1", cheap.uri()),
+            output_rate: 5.0,
+            enabled: false,
+            ..common::test_provider("cheap")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", backup.uri()),
+            output_rate: 20.0,
+            ..common::test_provider("backup")
+        },
+    ]
+}
+
+async fn routed_to(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hi"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn put_maintenance(
+    app: &axum::Router,
+    provider: &str,
+    body: serde_json::Value,
+) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::put(format!("/admin/providers/{}/maintenance", provider))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await.1
+}
+
+#[tokio::test]
+async fn disabled_provider_is_skipped_until_enabled() {
+    let (cheap, backup) = (mock_provider().await, mock_provider().await);
+    let (app, registry) = common::setup_circuit_test_app(providers(&cheap, &backup));
+
+    assert_eq!(routed_to(&app).await, "backup");
+    assert!(cheap.received_requests().await.unwrap().is_empty());
+
+    let listed = get_json(&app, "/providers").await;
+    let entry = &listed["providers"][0];
+    assert_eq!(entry["name"], "cheap");
+    assert_eq!(entry["status"], "disabled");
+    assert_eq!(entry["enabled"], false);
+
+    let explain = get_json(&app, "/v1/route/explain?model=gpt-4o").await;
+    assert_eq!(explain["selected"], "backup");
+    let skipped = &explain["candidates"][1];
+    assert_eq!(skipped["provider"], "cheap");
+    assert_eq!(skipped["eligible"], false);
+    assert_eq!(skipped["skip_reason"], "disabled");
+
+    let (status, body) = put_maintenance(&app, "cheap", serde_json::json!({"enabled": true})).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "active");
+    assert_eq!(routed_to(&app).await, "cheap");
+
+    // Circuit state is left alone throughout
+    assert_eq!(registry.state("cheap"), Some(CircuitState::Closed));
+}
+
+#[tokio::test]
+async fn maintenance_window_takes_provider_out_of_rotation() {
+    let (cheap, backup) = (mock_provider().await, mock_provider().await);
+    let (app, _) = common::setup_circuit_test_app(providers(&cheap, &backup));
+    put_maintenance(&app, "cheap", serde_json::json!({})).await;
+    assert_eq!(routed_to(&app).await, "cheap");
+
+    let until = "2099-07-01T00:00:00Z";
+    let (status, body) = put_maintenance(
+        &app,
+        "cheap",
+        serde_json::json!({"maintenance_until": until}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "maintenance");
+    assert_eq!(body["maintenance_until"], until);
+    assert_eq!(routed_to(&app).await, "backup");
+
+    let explain = get_json(&app, "/v1/route/explain?model=gpt-4o").await;
+    assert_eq!(
+        explain["candidates"][1]["skip_reason"],
+        format!("maintenance until {}", until)
+    );
+
+    // A window in the past no longer applies
+    put_maintenance(
+        &app,
+        "cheap",
+        serde_json::json!({"maintenance_until": "2020-01-01T00:00:00Z"}),
+    )
+    .await;
+    assert_eq!(routed_to(&app).await, "cheap");
+}
+
+#[tokio::test]
+async fn unknown_provider_returns_404() {
+    let (cheap, backup) = (mock_provider().await, mock_provider().await);
+    let (app, _) = common::setup_circuit_test_app(providers(&cheap, &backup));
+    let (status, _) = put_maintenance(&app, "nope", serde_json::json!({"enabled": false})).await;
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,54 @@
+//! Integration tests for provider discovery from a marketplace listing.
+
+use arbstr::marketplace::{append_providers, fetch};
+use arbstr::Config;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A fetched listing can be appended to a config and routed to.
+#[tokio::test]
+async fn listed_providers_are_added_to_config() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/providers"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "providers": [
+                {
+                    "name": "Node A",
+                    "url": "https://a.example/v1",
+                    "models": [{"id": "gpt-4o", "sats_pricing": {"prompt": 0.005, "completion": 0.015}}]
+                },
+                {"name": "node-b", "url": "https://b.example/v1", "models": ["llama-3"]}
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/providers", mock_server.uri());
+    let listed = fetch(&Client::new(), &url).await.unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].name, "node-a");
+
+    let existing = "[server]\nlisten = \"127.0.0.1:8080\"\n";
+    let config = append_providers(existing, &[&listed[0]], &url).unwrap();
+    let config = Config::parse_str(&config).unwrap();
+    assert_eq!(config.providers.len(), 1);
+    let provider = &config.providers[0];
+    assert_eq!(provider.url, "https://a.example/v1");
+    assert_eq!(provider.models, ["gpt-4o"]);
+    assert_eq!((provider.input_rate, provider.output_rate), (5.0, 15.0));
+}
+
+/// A listing endpoint that errors is reported, not parsed.
+#[tokio::test]
+async fn listing_error_status_fails() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock_server)
+        .await;
+
+    let err = fetch(&Client::new(), &mock_server.uri()).await.unwrap_err();
+    assert!(err.to_string().contains("503"));
+}
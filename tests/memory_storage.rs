@@ -0,0 +1,106 @@
+//! Integration tests for the in-memory storage backend
+//! (`[database] backend = "memory"`).
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::{DatabaseConfig, StorageBackend};
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use arbstr::storage::{memory, DbWriter};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+async fn setup_app() -> axum::Router {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let providers = vec![provider];
+
+    let mut config = common::db_test_config();
+    config.providers = providers.clone();
+    config.database = Some(DatabaseConfig {
+        backend: StorageBackend::Memory,
+        ..Default::default()
+    });
+
+    let pool = memory::init_pool().await.unwrap();
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::with_row_limit(pool, 1000)),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["mock".to_string()])),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+#[tokio::test]
+async fn stats_and_logs_work_without_a_database_file() {
+    let app = setup_app().await;
+
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({
+                            "model": "gpt-4o",
+                            "messages": [{"role": "user", "content": "Hello"}]
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    // Writes go through the background writer
+    let mut stats = get_json(&app, "/v1/stats").await;
+    for _ in 0..50 {
+        if stats["counts"]["total"] == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stats = get_json(&app, "/v1/stats").await;
+    }
+    assert_eq!(stats["counts"]["total"], 2);
+    assert_eq!(stats["counts"]["success"], 2);
+    assert_eq!(stats["storage"], "memory");
+
+    let logs = get_json(&app, "/v1/requests?provider=mock").await;
+    assert_eq!(logs["total"], 2);
+    assert_eq!(logs["data"][0]["model"], "gpt-4o");
+    assert_eq!(logs["storage"], "memory");
+
+    let info = get_json(&app, "/admin/db").await;
+    assert_eq!(info["path"], ":memory:");
+    assert_eq!(info["tables"]["requests"], 2);
+}
+
+#[tokio::test]
+async fn file_backed_responses_are_not_labeled() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let stats = get_json(&app, "/v1/stats").await;
+    assert!(stats.get("storage").is_none());
+    let logs = get_json(&app, "/v1/requests").await;
+    assert!(logs.get("storage").is_none());
+}
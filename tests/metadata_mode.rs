@@ -0,0 +1,151 @@
+//! Integration tests for `server.metadata_mode`: where routing metadata
+//! goes on chat completion responses.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{MetadataMode, ProviderConfig};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-meta",
+            "object": "chat.completion",
+            "created": 1_700_000_000,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn mock_streaming_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(
+                    "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"}}]}\n\n\
+                     data: [DONE]\n\n",
+                ),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(server: &MockServer, mode: MetadataMode) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.server.metadata_mode = mode;
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    create_router(state)
+}
+
+async fn complete(app: axum::Router, stream: bool) -> axum::response::Response {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    app.oneshot(
+        Request::post("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn body_text(response: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn body_mode_adds_one_arbstr_object() {
+    let server = mock_provider().await;
+    let response = complete(setup_app(&server, MetadataMode::Body).await, false).await;
+    assert_eq!(response.headers()["x-arbstr-provider"], "upstream");
+
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200, "{}", json);
+    let arbstr = &json["arbstr"];
+    assert_eq!(arbstr["provider"], "upstream");
+    assert!(arbstr["cost_sats"].as_f64().unwrap() > 0.0);
+    assert!(arbstr["latency_ms"].is_i64());
+    assert_eq!(arbstr["retries"], serde_json::json!([]));
+    // No other top-level extension fields
+    assert!(json.get("arbstr_provider").is_none());
+
+    // Clients can still deserialize the OpenAI shape
+    let parsed: arbstr::proxy::types::ChatCompletionResponse =
+        serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.id, "chatcmpl-meta");
+}
+
+#[tokio::test]
+async fn headers_mode_keeps_the_provider_body() {
+    let server = mock_provider().await;
+    let response = complete(setup_app(&server, MetadataMode::Headers).await, false).await;
+    assert_eq!(response.headers()["x-arbstr-provider"], "upstream");
+    assert!(response.headers().contains_key("x-arbstr-cost-sats"));
+
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert!(json.get("arbstr").is_none());
+}
+
+#[tokio::test]
+async fn none_mode_keeps_only_the_request_id() {
+    let server = mock_provider().await;
+    let response = complete(setup_app(&server, MetadataMode::None).await, false).await;
+    let metadata: Vec<&str> = response
+        .headers()
+        .keys()
+        .map(|name| name.as_str())
+        .filter(|name| name.starts_with("x-arbstr-"))
+        .collect();
+    assert_eq!(metadata, ["x-arbstr-request-id"]);
+
+    let (_, json) = common::parse_body(response).await;
+    assert!(json.get("arbstr").is_none());
+}
+
+#[tokio::test]
+async fn streams_end_with_metadata_only_in_body_mode() {
+    let server = mock_streaming_provider().await;
+    let text = body_text(complete(setup_app(&server, MetadataMode::Body).await, true).await).await;
+    assert!(text.contains("data: {\"arbstr\":"), "{}", text);
+    assert!(text.ends_with("data: [DONE]\n\n"));
+
+    let response = complete(setup_app(&server, MetadataMode::Headers).await, true).await;
+    assert_eq!(response.headers()["x-arbstr-streaming"], "true");
+    let text = body_text(response).await;
+    assert!(!text.contains("arbstr"), "{}", text);
+    assert!(text.ends_with("data: [DONE]\n\n"));
+}
@@ -0,0 +1,70 @@
+//! Integration tests for the built-in mock provider (`arbstr mock-provider`)
+//! used as an upstream for the proxy.
+
+mod common;
+
+use arbstr::mock_provider::MockProviderConfig;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+fn chat_request(stream: bool) -> Request<Body> {
+    Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "stream": stream
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn proxy_routes_to_mock_provider() {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    let response = app.oneshot(chat_request(false)).await.unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["model"], "gpt-4o");
+    assert!(body["usage"]["completion_tokens"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn proxy_streams_from_mock_provider() {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    let response = app.oneshot(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let bytes = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains("chat.completion.chunk"));
+    assert!(text.contains("\"finish_reason\":\"stop\""));
+    assert!(text.contains("data: [DONE]"));
+}
+
+#[tokio::test]
+async fn injected_errors_reach_the_client() {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig {
+        error_rate: 1.0,
+        error_status: 400,
+        ..Default::default()
+    })
+    .await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    let response = app.oneshot(chat_request(false)).await.unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 502);
+    assert_eq!(body["error"]["code"], "provider_rejected");
+}
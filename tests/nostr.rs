@@ -0,0 +1,105 @@
+//! Integration tests for Nostr catalogue announcements against an
+//! in-process relay.
+
+use std::sync::{Arc, Mutex};
+
+use arbstr::config::NostrConfig;
+use arbstr::proxy::nostr::{
+    announcement, fetch_announcements, listed_providers, publish, Event, Keys,
+};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+const SECRET: &str = "5c0c523f52a5b6fad39ed2403092df8cebc36318b39383bca6c00808626fab3a";
+
+/// A minimal relay: stores EVENTs (acking with OK) and answers every REQ
+/// with the stored events followed by EOSE.
+async fn start_relay() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let events: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let events = events.clone();
+            tokio::spawn(async move {
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(text))) = socket.next().await {
+                    let message: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+                    let replies = match message[0].as_str().unwrap() {
+                        "EVENT" => {
+                            events.lock().unwrap().push(message[1].clone());
+                            vec![serde_json::json!(["OK", message[1]["id"], true, ""])]
+                        }
+                        "REQ" => {
+                            let sub = &message[1];
+                            let mut replies: Vec<_> = events
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .map(|e| serde_json::json!(["EVENT", sub, e]))
+                                .collect();
+                            replies.push(serde_json::json!(["EOSE", sub]));
+                            replies
+                        }
+                        _ => vec![],
+                    };
+                    for reply in replies {
+                        socket.send(Message::Text(reply.to_string())).await.unwrap();
+                    }
+                }
+            });
+        }
+    });
+    format!("ws://{}", addr)
+}
+
+fn nostr_config(relay: &str) -> NostrConfig {
+    toml::from_str(&format!(
+        "secret_key = \"{}\"\nrelays = [\"{}\"]\npublic_url = \"https://arbstr.example/v1\"\nname = \"Team Proxy\"",
+        SECRET, relay
+    ))
+    .unwrap()
+}
+
+/// A published announcement is discovered from the relay as a provider.
+#[tokio::test]
+async fn announcement_round_trips_through_relay() {
+    let relay = start_relay().await;
+    let keys = Keys::from_hex(SECRET).unwrap();
+    let providers = [common::test_provider("alpha")];
+
+    let event = announcement(&keys, &nostr_config(&relay), &providers, 1_700_000_000);
+    publish(&relay, &event).await.unwrap();
+    // A newer announcement from the same key replaces the older one
+    let newer = announcement(&keys, &nostr_config(&relay), &providers, 1_700_000_100);
+    publish(&relay, &newer).await.unwrap();
+
+    let events = fetch_announcements(&relay).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id, newer.id);
+    assert_eq!(events[0].pubkey, keys.public_key());
+
+    let listed = listed_providers(&events);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].name, "team-proxy");
+    assert_eq!(listed[0].models, ["gpt-4o"]);
+    assert_eq!((listed[0].input_rate, listed[0].output_rate), (5.0, 15.0));
+}
+
+/// Events whose signature does not match are ignored.
+#[tokio::test]
+async fn forged_announcements_are_dropped() {
+    let relay = start_relay().await;
+    let keys = Keys::from_hex(SECRET).unwrap();
+    let providers = [common::test_provider("alpha")];
+
+    let mut forged: Event = announcement(&keys, &nostr_config(&relay), &providers, 1_700_000_000);
+    forged.content = forged.content.replace("arbstr.example", "evil.example");
+    // The relay here does not validate; the id no longer matches the content
+    let _ = publish(&relay, &forged).await;
+
+    assert!(fetch_announcements(&relay).await.unwrap().is_empty());
+}
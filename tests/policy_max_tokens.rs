@@ -0,0 +1,207 @@
+//! Integration tests for policy `max_output_tokens` / `default_max_tokens`.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-limits",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// The "capped" policy allows 1024 output tokens and defaults to 256.
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    setup_app_with_threshold(server, None).await
+}
+
+/// [`setup_app`] streaming bodies over `threshold` bytes to the provider.
+async fn setup_app_with_threshold(
+    server: &MockServer,
+    threshold: Option<u64>,
+) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.server.stream_body_threshold_bytes = threshold;
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "capped".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: Some(1024),
+        default_max_tokens: Some(256),
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+/// Send a completion under `policy`; returns the status, the
+/// `x-arbstr-max-tokens` header and the `max_tokens` the provider saw.
+async fn complete(
+    app: &axum::Router,
+    server: &MockServer,
+    policy: Option<&str>,
+    max_tokens: Option<u32>,
+) -> (StatusCode, Option<String>, serde_json::Value) {
+    let mut body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    if let Some(max_tokens) = max_tokens {
+        body["max_tokens"] = max_tokens.into();
+    }
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(policy) = policy {
+        request = request.header("x-arbstr-policy", policy);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let adjustment = response
+        .headers()
+        .get("x-arbstr-max-tokens")
+        .map(|v| v.to_str().unwrap().to_string());
+    let received = server.received_requests().await.unwrap();
+    let forwarded: serde_json::Value = received.last().unwrap().body_json().unwrap();
+    (status, adjustment, forwarded["max_tokens"].clone())
+}
+
+/// `max_tokens` tag values, once `requests` rows have been logged.
+async fn max_tokens_tags(pool: &SqlitePool, requests: i64) -> Vec<String> {
+    for _ in 0..100 {
+        let logged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if logged == requests {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    // Tags are written with their row
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    sqlx::query_scalar("SELECT value FROM request_tags WHERE key = 'max_tokens' ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn omitted_max_tokens_gets_the_policy_default() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let (status, adjustment, forwarded) = complete(&app, &server, Some("capped"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(adjustment.as_deref(), Some("injected"));
+    assert_eq!(forwarded, 256);
+    assert_eq!(max_tokens_tags(&pool, 1).await, vec!["injected"]);
+}
+
+#[tokio::test]
+async fn larger_client_limit_is_clamped() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let (status, adjustment, forwarded) =
+        complete(&app, &server, Some("capped"), Some(100_000)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(adjustment.as_deref(), Some("clamped"));
+    assert_eq!(forwarded, 1024);
+    assert_eq!(max_tokens_tags(&pool, 1).await, vec!["clamped"]);
+}
+
+#[tokio::test]
+async fn limits_within_policy_or_without_one_pass_unchanged() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let (_, adjustment, forwarded) = complete(&app, &server, Some("capped"), Some(512)).await;
+    assert!(adjustment.is_none());
+    assert_eq!(forwarded, 512);
+
+    let (_, adjustment, forwarded) = complete(&app, &server, None, None).await;
+    assert!(adjustment.is_none());
+    assert!(forwarded.is_null());
+    assert!(max_tokens_tags(&pool, 2).await.is_empty());
+}
+
+#[tokio::test]
+async fn oversized_body_is_buffered_to_apply_limits() {
+    let server = mock_provider().await;
+    let (app, _pool) = setup_app_with_threshold(&server, Some(64)).await;
+
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "max_tokens": 100_000,
+        "messages": [{"role": "user", "content": "pad ".repeat(64)}]
+    })
+    .to_string();
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("content-length", body.len())
+                .header("x-arbstr-policy", "capped")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-arbstr-max-tokens"], "clamped");
+    let received = server.received_requests().await.unwrap();
+    let forwarded: serde_json::Value = received[0].body_json().unwrap();
+    assert_eq!(forwarded["max_tokens"], 1024);
+}
@@ -0,0 +1,124 @@
+//! Integration tests for policy `response_headers` and `omit_headers`.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::create_router;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_app(server: &MockServer) -> axum::Router {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-headers",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "search".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: BTreeMap::from([
+            ("x-team".to_string(), "search".to_string()),
+            ("Cache-Control".to_string(), "no-store".to_string()),
+        ]),
+        omit_headers: vec![
+            "x-arbstr-cost-sats".to_string(),
+            "X-Arbstr-Latency-Ms".to_string(),
+        ],
+        dataset_capture: None,
+    }];
+    let (state, _pool) = common::setup_db_test_state(config).await;
+    create_router(state)
+}
+
+async fn complete(app: &axum::Router, model: &str, policy: Option<&str>) -> http::Response<Body> {
+    let body = serde_json::json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(policy) = policy {
+        request = request.header("x-arbstr-policy", policy);
+    }
+    app.clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn policy_sets_and_omits_response_headers() {
+    let server = MockServer::start().await;
+    let app = setup_app(&server).await;
+
+    let response = complete(&app, "gpt-4o", Some("search")).await;
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(headers["x-team"], "search");
+    assert_eq!(headers["cache-control"], "no-store");
+    assert_eq!(headers["x-arbstr-provider"], "upstream");
+    assert!(headers.contains_key("x-arbstr-request-id"));
+    assert!(!headers.contains_key("x-arbstr-cost-sats"), "{:?}", headers);
+    assert!(
+        !headers.contains_key("x-arbstr-latency-ms"),
+        "{:?}",
+        headers
+    );
+
+    // Other requests are untouched
+    let response = complete(&app, "gpt-4o", None).await;
+    assert_eq!(response.status(), 200);
+    assert!(!response.headers().contains_key("x-team"));
+    assert!(response.headers().contains_key("x-arbstr-cost-sats"));
+}
+
+#[tokio::test]
+async fn policy_headers_are_set_on_errors() {
+    let server = MockServer::start().await;
+    let app = setup_app(&server).await;
+
+    let response = complete(&app, "no-such-model", Some("search")).await;
+    assert!(response.status().is_client_error(), "{}", response.status());
+    assert_eq!(response.headers()["x-team"], "search");
+    assert!(response.headers().contains_key("x-arbstr-request-id"));
+}
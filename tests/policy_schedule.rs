@@ -0,0 +1,113 @@
+//! Integration tests for policy time windows (`active_hours` / `days`).
+
+mod common;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+/// "local" is cheap and local-tier, "frontier" serves gpt-4o at a premium.
+/// The "work" policy allows frontier only on weekdays 09:00-18:00 UTC.
+async fn setup_app() -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            tier: Tier::Local,
+            output_rate: 5.0,
+            ..common::test_provider("local")
+        },
+        ProviderConfig {
+            tier: Tier::Frontier,
+            output_rate: 1.0,
+            ..common::test_provider("frontier")
+        },
+    ];
+    config.policies.rules = vec![PolicyRule {
+        name: "work".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: Some("09:00-18:00".to_string()),
+        days: ["mon", "tue", "wed", "thu", "fri"]
+            .iter()
+            .map(|d| d.to_string())
+            .collect(),
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    common::setup_db_test_app_with_config(config).await.0
+}
+
+async fn explain(app: &axum::Router, query: &str) -> (u16, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get(format!("/v1/route/explain?model=gpt-4o&{}", query))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    (status.as_u16(), body)
+}
+
+#[tokio::test]
+async fn explain_shows_policy_inside_its_window() {
+    let app = setup_app().await;
+    // 2026-01-05 is a Monday
+    let (status, body) = explain(&app, "policy=work&at=2026-01-05T10:00:00Z").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["selected"], "frontier");
+    let policy = &body["policy_status"];
+    assert_eq!(policy["name"], "work");
+    assert_eq!(policy["active"], true);
+    assert_eq!(
+        policy["schedule"],
+        "09:00-18:00 mon,tue,wed,thu,fri (UTC+00:00)"
+    );
+}
+
+#[tokio::test]
+async fn explain_caps_tier_outside_the_window() {
+    let app = setup_app().await;
+    // Saturday
+    let (status, body) = explain(&app, "policy=work&at=2026-01-10T10:00:00Z").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["selected"], "local");
+    assert_eq!(body["candidates"].as_array().unwrap().len(), 1);
+    let policy = &body["policy_status"];
+    assert_eq!(policy["active"], false);
+    assert_eq!(policy["max_tier"], "local");
+    assert!(
+        policy["reason"]
+            .as_str()
+            .unwrap()
+            .contains("outside active hours"),
+        "{}",
+        policy
+    );
+
+    // Without the policy, frontier stays reachable
+    let (_, body) = explain(&app, "at=2026-01-10T10:00:00Z").await;
+    assert_eq!(body["selected"], "frontier");
+    assert!(body["policy_status"].is_null());
+}
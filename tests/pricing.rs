@@ -0,0 +1,61 @@
+//! Integration tests for `arbstr check --pricing` reference tables.
+
+mod common;
+
+use arbstr::pricing::{lint, load, Problem};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TABLE: &str =
+    r#"{"models": [{"model": "house-model", "input_rate": 50, "output_rate": 200}]}"#;
+
+#[tokio::test]
+async fn fetched_and_file_tables_replace_the_bundled_one() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/prices.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(TABLE))
+        .mount(&server)
+        .await;
+    let fetched = load(&format!("{}/prices.json", server.uri()))
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("prices.json");
+    std::fs::write(&file, TABLE).unwrap();
+    let read = load(file.to_str().unwrap()).await.unwrap();
+    assert_eq!(fetched, read);
+
+    let mut provider = common::test_provider("alpha");
+    provider.models = vec!["house-model-v2".to_string(), "gpt-4o".to_string()];
+    provider.input_rate = 2.0;
+    provider.output_rate = 150.0;
+    let report = lint(&[provider], &fetched);
+    // gpt-4o is only in the bundled table
+    assert_eq!(report.checked.len(), 1);
+    assert_eq!(report.unknown.len(), 1);
+    assert_eq!(report.findings.len(), 1);
+    assert_eq!(
+        report.findings[0].problem,
+        Problem::TooLow {
+            model: "house-model-v2".to_string(),
+            reference: 50.0
+        }
+    );
+}
+
+#[tokio::test]
+async fn unusable_tables_are_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/missing.json"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    let err = load(&format!("{}/missing.json", server.uri()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("HTTP 404"), "{}", err);
+    assert!(load("/nonexistent/prices.json").await.is_err());
+}
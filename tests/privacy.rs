@@ -0,0 +1,212 @@
+//! Integration tests for request log anonymization (`[privacy]`).
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::{ClientIdMode, PrivacyConfig};
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::privacy::Anonymizer;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use arbstr::storage::logging::clear_correlation_ids;
+use arbstr::storage::{memory, DbWriter, RequestLog};
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+async fn setup_app(privacy: PrivacyConfig) -> (axum::Router, SqlitePool) {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let providers = vec![provider];
+
+    let mut config = common::db_test_config();
+    config.providers = providers.clone();
+    config.privacy = privacy;
+
+    let pool = memory::init_pool().await.unwrap();
+    let state = AppState {
+        privacy: Arc::new(Anonymizer::new(&config.privacy)),
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["mock".to_string()])),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool)
+}
+
+async fn complete(app: &axum::Router, request_id: &str) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-request-id", request_id)
+                .header("x-arbstr-conversation-id", "chat-7")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Client request IDs of logged rows, once `count` rows have been written.
+async fn logged_ids(pool: &SqlitePool, count: usize) -> Vec<(Option<String>, Option<String>)> {
+    for _ in 0..50 {
+        let rows: Vec<(Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT client_request_id, trace_id FROM requests ORDER BY id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        if rows.len() >= count {
+            return rows;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("request rows were not written");
+}
+
+/// Conversation IDs of logged rows.
+async fn logged_conversations(pool: &SqlitePool) -> Vec<Option<String>> {
+    sqlx::query_scalar("SELECT conversation_id FROM requests ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn hashed_ids_correlate_without_revealing_the_client_id() {
+    let (app, pool) = setup_app(PrivacyConfig {
+        mode: ClientIdMode::Hash,
+        salt: Some("pepper".to_string().into()),
+        strip_prompts: true,
+        correlation_retention_days: Some(30),
+    })
+    .await;
+
+    complete(&app, "client-42").await;
+    complete(&app, "client-42").await;
+    let rows = logged_ids(&pool, 2).await;
+    let (first, trace) = &rows[0];
+    let first = first.as_deref().unwrap();
+    assert!(first.starts_with("h:"), "{}", first);
+    assert!(!first.contains("client-42"));
+    assert_eq!(rows[1].0.as_deref(), Some(first));
+    assert!(trace.as_deref().unwrap().starts_with("h:"));
+    let conversations = logged_conversations(&pool).await;
+    let conversation = conversations[0].as_deref().unwrap();
+    assert!(conversation.starts_with("h:") && conversation != first);
+    assert_eq!(conversations[1].as_deref(), Some(conversation));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/conversations/chat-7")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, totals) = common::parse_body(response).await;
+    assert_eq!(totals["conversation_id"], "chat-7");
+    assert_eq!(totals["requests"], 2);
+
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, health) = common::parse_body(response).await;
+    assert_eq!(
+        health["privacy"],
+        serde_json::json!({
+            "mode": "hash",
+            "salted": true,
+            "strip_prompts": true,
+            "correlation_retention_days": 30
+        })
+    );
+}
+
+#[tokio::test]
+async fn omitted_ids_are_not_stored() {
+    let (app, pool) = setup_app(PrivacyConfig {
+        mode: ClientIdMode::Omit,
+        ..Default::default()
+    })
+    .await;
+
+    complete(&app, "client-42").await;
+    assert_eq!(logged_ids(&pool, 1).await, vec![(None, None)]);
+    assert_eq!(logged_conversations(&pool).await, vec![None]);
+}
+
+#[tokio::test]
+async fn expired_correlation_ids_are_cleared() {
+    let pool = memory::init_pool().await.unwrap();
+    for (id, timestamp) in [
+        ("old", "2026-01-01T00:00:00Z"),
+        ("new", "2026-03-01T00:00:00Z"),
+    ] {
+        RequestLog {
+            correlation_id: id.to_string(),
+            timestamp: timestamp.to_string(),
+            model: "gpt-4o".to_string(),
+            provider: Some("alpha".to_string()),
+            policy: None,
+            streaming: false,
+            input_tokens: Some(10),
+            output_tokens: Some(5),
+            cost_sats: Some(1.0),
+            provider_cost_sats: None,
+            latency_ms: 100,
+            success: true,
+            error_status: None,
+            error_type: None,
+            error_message: None,
+            complexity_score: None,
+            tier: None,
+            finish_reason: None,
+            tags: vec![],
+            attempts: Vec::new(),
+            circuit_snapshot: None,
+            trace_id: Some(format!("trace-{}", id)),
+            client_request_id: Some(format!("client-{}", id)),
+            fiat_currency: None,
+            fiat_rate: None,
+            request_sha256: None,
+            config_version: None,
+            conversation_id: Some(format!("chat-{}", id)),
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+    }
+
+    let cleared = clear_correlation_ids(&pool, "2026-02-01T00:00:00Z")
+        .await
+        .unwrap();
+    assert_eq!(cleared, 1);
+    let rows = logged_ids(&pool, 2).await;
+    assert_eq!(rows[0], (None, None));
+    assert_eq!(
+        rows[1],
+        (
+            Some("client-new".to_string()),
+            Some("trace-new".to_string())
+        )
+    );
+    assert_eq!(
+        logged_conversations(&pool).await,
+        vec![None, Some("chat-new".to_string())]
+    );
+}
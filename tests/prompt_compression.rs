@@ -0,0 +1,290 @@
+//! Integration tests for `[policies.rules.compress]`: long prompts are
+//! compressed before forwarding, with token counts and savings recorded.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{PolicyRule, PromptCompressionConfig, ProviderConfig, Tier};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn completion(content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-compress",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+/// Answers gpt-4o-mini (the compression model) with a short rewrite,
+/// anything else with "ok".
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "gpt-4o-mini"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("Report: revenue +12%.")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("ok")))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(
+    server: &MockServer,
+    compress: PromptCompressionConfig,
+) -> (axum::Router, SqlitePool) {
+    setup_app_with_threshold(server, compress, None).await
+}
+
+/// [`setup_app`] streaming bodies over `threshold` bytes to the provider.
+async fn setup_app_with_threshold(
+    server: &MockServer,
+    compress: PromptCompressionConfig,
+    threshold: Option<u64>,
+) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.server.stream_body_threshold_bytes = threshold;
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        models: vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
+        input_rate: 100.0,
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "rag".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: Some(compress),
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+const REPORT: &str = "The quarterly report shows revenue grew twelve percent while \
+                      costs stayed flat across every region we operate in.";
+
+/// A conversation that pastes the same report twice, padded with spaces.
+async fn chat(app: &axum::Router) -> StatusCode {
+    let messages = serde_json::json!([
+        {"role": "user", "content": format!("{}\n\n\n\nSummarize   it.   ", REPORT)},
+        {"role": "assistant", "content": "Revenue is up."},
+        {"role": "user", "content": format!("{}\n\nWhat about costs?", REPORT)},
+    ]);
+    let body = serde_json::json!({"model": "gpt-4o", "messages": messages}).to_string();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("content-length", body.len())
+                .header("x-arbstr-policy", "rag")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response.status()
+}
+
+/// The messages of the last gpt-4o request the provider saw.
+async fn forwarded_messages(server: &MockServer) -> Vec<serde_json::Value> {
+    let received = server.received_requests().await.unwrap();
+    let body: serde_json::Value = received
+        .iter()
+        .rev()
+        .map(|r| r.body_json::<serde_json::Value>().unwrap())
+        .find(|b| b["model"] == "gpt-4o")
+        .unwrap();
+    body["messages"].as_array().unwrap().clone()
+}
+
+/// Tags of the gpt-4o row, once `rows` rows have been logged.
+async fn tags(pool: &SqlitePool, rows: i64) -> std::collections::BTreeMap<String, String> {
+    for _ in 0..100 {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count >= rows {
+            let tags: Vec<(String, String)> = sqlx::query_as(
+                "SELECT t.key, t.value FROM request_tags t
+                 JOIN requests r ON r.correlation_id = t.correlation_id
+                 WHERE r.model = 'gpt-4o'",
+            )
+            .fetch_all(pool)
+            .await
+            .unwrap();
+            return tags.into_iter().collect();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+async fn compression_stats(app: &axum::Router) -> serde_json::Value {
+    let request = Request::get("/v1/stats/compression")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = common::parse_body(app.clone().oneshot(request).await.unwrap()).await;
+    assert_eq!(status, 200);
+    body["prompt"].clone()
+}
+
+#[tokio::test]
+async fn whitespace_and_repeated_context_are_removed() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        PromptCompressionConfig {
+            min_prompt_tokens: 10,
+            whitespace: true,
+            dedupe: true,
+            model: None,
+        },
+    )
+    .await;
+
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    let messages = forwarded_messages(&server).await;
+    assert_eq!(
+        messages[0]["content"],
+        format!("{}\n\nSummarize it.", REPORT)
+    );
+    assert_eq!(
+        messages[2]["content"],
+        "[repeated context omitted]\n\nWhat about costs?"
+    );
+
+    let tags = tags(&pool, 1).await;
+    assert_eq!(tags["prompt_compression"], "whitespace,dedupe");
+    let original: u32 = tags["prompt_tokens_original"].parse().unwrap();
+    let compressed: u32 = tags["prompt_tokens_compressed"].parse().unwrap();
+    assert!(compressed < original, "{:?}", tags);
+    let saved: f64 = tags["prompt_sats_saved"].parse().unwrap();
+    assert!((saved - (original - compressed) as f64 * 0.1).abs() < 0.001);
+
+    let stats = compression_stats(&app).await;
+    assert_eq!(stats["requests"], 1);
+    assert_eq!(stats["tokens_saved"], original - compressed);
+    assert!(stats["sats_saved"].as_f64().unwrap() > 0.0);
+}
+
+#[tokio::test]
+async fn short_prompts_are_left_alone() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        PromptCompressionConfig {
+            min_prompt_tokens: 10_000,
+            whitespace: true,
+            dedupe: true,
+            model: Some("gpt-4o-mini".to_string()),
+        },
+    )
+    .await;
+
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    assert!(tags(&pool, 1).await.is_empty());
+    assert_eq!(compression_stats(&app).await["requests"], 0);
+}
+
+#[tokio::test]
+async fn model_rewrites_the_longest_earlier_message() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        PromptCompressionConfig {
+            min_prompt_tokens: 10,
+            whitespace: false,
+            dedupe: false,
+            model: Some("gpt-4o-mini".to_string()),
+        },
+    )
+    .await;
+
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    let messages = forwarded_messages(&server).await;
+    assert_eq!(messages[0]["content"], "Report: revenue +12%.");
+    assert_eq!(
+        messages[2]["content"],
+        format!("{}\n\nWhat about costs?", REPORT)
+    );
+
+    let tags = tags(&pool, 2).await;
+    assert_eq!(tags["prompt_compression"], "model");
+    let models: Vec<String> = sqlx::query_scalar("SELECT model FROM requests ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(models, ["gpt-4o-mini", "gpt-4o"]);
+}
+
+#[tokio::test]
+async fn oversized_body_is_buffered_to_compress() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app_with_threshold(
+        &server,
+        PromptCompressionConfig {
+            min_prompt_tokens: 10,
+            whitespace: true,
+            dedupe: true,
+            model: None,
+        },
+        Some(64),
+    )
+    .await;
+
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    let messages = forwarded_messages(&server).await;
+    assert_eq!(
+        messages[2]["content"],
+        "[repeated context omitted]\n\nWhat about costs?"
+    );
+    assert_eq!(
+        tags(&pool, 1).await["prompt_compression"],
+        "whitespace,dedupe"
+    );
+}
@@ -0,0 +1,155 @@
+//! Integration tests for policies picked by estimated prompt length
+//! (`min_prompt_tokens` / `max_prompt_tokens`) and pinned to a tier.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-length",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn policy(name: &str, min: Option<u32>, max: Option<u32>, tier: Tier) -> PolicyRule {
+    PolicyRule {
+        name: name.to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: min,
+        max_prompt_tokens: max,
+        tier: Some(tier),
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }
+}
+
+/// "small" (local) and "long-context" (frontier) serve the same model;
+/// prompts up to 4000 tokens go to the first, longer ones to the second.
+async fn setup_app(server: &MockServer) -> axum::Router {
+    setup_app_with_threshold(server, None).await
+}
+
+/// [`setup_app`] with `server.stream_body_threshold_bytes` set.
+async fn setup_app_with_threshold(server: &MockServer, threshold: Option<u64>) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.server.stream_body_threshold_bytes = threshold;
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", server.uri()),
+            tier: Tier::Local,
+            ..common::test_provider("small")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", server.uri()),
+            tier: Tier::Frontier,
+            output_rate: 60.0,
+            ..common::test_provider("long-context")
+        },
+    ];
+    config.policies.rules = vec![
+        policy("short", None, Some(4000), Tier::Local),
+        policy("long", Some(4001), None, Tier::Frontier),
+    ];
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&[
+        "small".to_string(),
+        "long-context".to_string(),
+    ]));
+    create_router(state)
+}
+
+/// Send a completion with a `chars`-long prompt; returns the provider used.
+async fn provider_for(app: &axum::Router, chars: usize, policy: Option<&str>) -> String {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "a".repeat(chars)}]
+    });
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(policy) = policy {
+        request = request.header("x-arbstr-policy", policy);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn prompt_length_picks_the_policy_tier() {
+    let server = mock_provider().await;
+    let app = setup_app(&server).await;
+
+    // ~4 characters per token
+    assert_eq!(provider_for(&app, 400, None).await, "small");
+    assert_eq!(provider_for(&app, 16_000, None).await, "small");
+    assert_eq!(provider_for(&app, 16_004, None).await, "long-context");
+    assert_eq!(provider_for(&app, 200_000, None).await, "long-context");
+}
+
+#[tokio::test]
+async fn policy_header_overrides_prompt_length() {
+    let server = mock_provider().await;
+    let app = setup_app(&server).await;
+
+    assert_eq!(provider_for(&app, 400, Some("long")).await, "long-context");
+    assert_eq!(provider_for(&app, 200_000, Some("short")).await, "small");
+}
+
+#[tokio::test]
+async fn prompt_length_applies_to_bodies_over_the_stream_threshold() {
+    let server = mock_provider().await;
+    let app = setup_app_with_threshold(&server, Some(1024)).await;
+
+    // Both bodies are over the threshold, so they would otherwise be
+    // streamed to the provider on headers alone
+    assert_eq!(provider_for(&app, 4_000, None).await, "small");
+    assert_eq!(provider_for(&app, 200_000, None).await, "long-context");
+    assert_eq!(provider_for(&app, 200_000, Some("short")).await, "small");
+}
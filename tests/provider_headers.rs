@@ -0,0 +1,152 @@
+//! Integration tests for per-provider auth schemes and extra headers.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{ApiKey, AuthScheme, ProviderConfig};
+use arbstr::proxy::discovery::discover_models;
+
+fn completion_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-headers",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "ok"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+fn sse_body() -> String {
+    "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"},\"finish_reason\":\"stop\"}]}\n\n\
+     data: [DONE]\n\n"
+        .to_string()
+}
+
+/// Provider using `x-api-key` auth plus two extra headers.
+fn custom_provider(url: &str) -> ProviderConfig {
+    let mut extra_headers = BTreeMap::new();
+    extra_headers.insert("x-org-id".to_string(), "org-42".to_string());
+    extra_headers.insert("x-routing-hint".to_string(), "eu".to_string());
+    ProviderConfig {
+        url: format!("{}/v1", url),
+        api_key: Some(ApiKey::from("secret-key")),
+        auth_scheme: AuthScheme::XApiKey,
+        extra_headers,
+        ..common::test_provider("custom")
+    }
+}
+
+async fn send_chat(app: axum::Router, stream: bool) -> http::StatusCode {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    // Drain the body so streaming requests run to completion
+    let _ = axum::body::to_bytes(response.into_body(), 1_048_576).await;
+    status
+}
+
+async fn assert_custom_headers(server: &MockServer) {
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let headers = &requests[0].headers;
+    assert_eq!(headers.get("x-api-key").unwrap(), "secret-key");
+    assert_eq!(headers.get("x-org-id").unwrap(), "org-42");
+    assert_eq!(headers.get("x-routing-hint").unwrap(), "eu");
+    assert!(headers.get("authorization").is_none());
+}
+
+#[tokio::test]
+async fn non_streaming_request_uses_custom_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion_json()))
+        .mount(&server)
+        .await;
+
+    let (app, _registry) = common::setup_circuit_test_app(vec![custom_provider(&server.uri())]);
+    assert_eq!(send_chat(app, false).await, 200);
+    assert_custom_headers(&server).await;
+}
+
+#[tokio::test]
+async fn streaming_request_uses_custom_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse_body()),
+        )
+        .mount(&server)
+        .await;
+
+    let (app, _registry) = common::setup_circuit_test_app(vec![custom_provider(&server.uri())]);
+    assert_eq!(send_chat(app, true).await, 200);
+    assert_custom_headers(&server).await;
+}
+
+#[tokio::test]
+async fn default_scheme_sends_bearer() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer secret-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion_json()))
+        .mount(&server)
+        .await;
+
+    let provider = ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        api_key: Some(ApiKey::from("secret-key")),
+        ..common::test_provider("default")
+    };
+    let (app, _registry) = common::setup_circuit_test_app(vec![provider]);
+    assert_eq!(send_chat(app, false).await, 200);
+}
+
+#[tokio::test]
+async fn discovery_uses_custom_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(header("x-api-key", "secret-key"))
+        .and(header("x-org-id", "org-42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "object": "list",
+            "data": [{"id": "claude-3-haiku", "object": "model"}]
+        })))
+        .mount(&server)
+        .await;
+
+    let mut providers = vec![ProviderConfig {
+        auto_discover: true,
+        ..custom_provider(&server.uri())
+    }];
+    discover_models(&mut providers, &reqwest::Client::new()).await;
+    assert_eq!(providers[0].models, vec!["claude-3-haiku".to_string()]);
+}
@@ -0,0 +1,124 @@
+//! Integration tests for `arbstr test-provider`.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{ApiKey, AuthScheme, Config};
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::provider_test::{run, ProviderTestOptions};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A config whose only provider, "alpha", is at `url`.
+fn config(url: &str) -> Config {
+    let mut provider = common::test_provider("alpha");
+    provider.url = url.to_string();
+    provider.models = vec!["gpt-4o".to_string()];
+    provider.input_rate = 1000.0;
+    provider.output_rate = 2000.0;
+    provider.base_fee = 1.0;
+    let mut config = common::db_test_config();
+    config.providers = vec![provider];
+    config
+}
+
+fn options(model: Option<&str>, stream: bool) -> ProviderTestOptions {
+    ProviderTestOptions {
+        provider: "alpha".to_string(),
+        model: model.map(str::to_string),
+        stream,
+        timeout: Duration::from_secs(5),
+    }
+}
+
+#[tokio::test]
+async fn reports_usage_cost_and_reply() {
+    let url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let config = config(&url);
+
+    for stream in [false, true] {
+        let report = run(&config, &options(None, stream)).await.unwrap();
+        assert!(report.success(), "{}", report);
+        assert_eq!(report.model, "gpt-4o");
+        assert!(report.model_listed);
+        assert_eq!(report.status, 200);
+        assert!(report.snippet.starts_with("This is a mock response"));
+        let (input, output) = (report.input_tokens.unwrap(), report.output_tokens.unwrap());
+        assert_eq!(output, 10);
+        assert_eq!(
+            report.cost_sats,
+            Some(input as f64 + output as f64 * 2.0 + 1.0)
+        );
+        assert_eq!(report.first_token_ms.is_some(), stream);
+        assert!(report.to_string().ends_with("OK"));
+    }
+
+    let report = run(&config, &options(Some("gpt-4o-mini"), false))
+        .await
+        .unwrap();
+    assert!(!report.model_listed);
+    assert!(report.to_string().contains("not in the provider's models"));
+}
+
+#[tokio::test]
+async fn provider_errors_are_reported() {
+    let url = common::spawn_mock_provider(MockProviderConfig {
+        error_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+    let report = run(&config(&url), &options(None, false)).await.unwrap();
+    assert!(!report.success());
+    assert_eq!(report.status, 503);
+    assert!(report.snippet.contains("Injected error"));
+    assert_eq!(report.cost_sats, None);
+    assert!(report.to_string().contains("FAILED: HTTP 503"));
+}
+
+#[tokio::test]
+async fn sends_provider_headers_and_flags_malformed_replies() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("x-api-key", "sk-test"))
+        .and(header("x-org", "acme"))
+        .and(body_partial_json(serde_json::json!({"model": "gpt-4o"})))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>gateway</html>"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = config(&server.uri());
+    config.providers[0].api_key = Some(ApiKey::from("sk-test"));
+    config.providers[0].auth_scheme = AuthScheme::XApiKey;
+    config.providers[0]
+        .extra_headers
+        .insert("x-org".to_string(), "acme".to_string());
+
+    let report = run(&config, &options(None, false)).await.unwrap();
+    assert!(!report.success());
+    assert_eq!(
+        report.problem.as_deref(),
+        Some("response is not a JSON completion")
+    );
+    assert_eq!(report.snippet, "<html>gateway</html>");
+}
+
+#[tokio::test]
+async fn unknown_provider_or_missing_model_is_an_error() {
+    let mut config = config("http://127.0.0.1:9");
+    let err = run(
+        &config,
+        &ProviderTestOptions {
+            provider: "nope".to_string(),
+            ..options(None, false)
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("configured: alpha"), "{}", err);
+
+    config.providers[0].models.clear();
+    let err = run(&config, &options(None, false)).await.unwrap_err();
+    assert!(err.to_string().contains("pass --model"), "{}", err);
+}
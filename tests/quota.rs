@@ -0,0 +1,111 @@
+//! Integration tests for provider rate quotas (`requests_per_minute`,
+//! `tokens_per_minute`).
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::quota::ProviderQuotas;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ProviderClients};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+/// "limited" is cheapest but allows `rpm` requests and `tpm` tokens per
+/// minute; "backup" costs more and has no quota.
+async fn setup_app(rpm: Option<u32>, tpm: Option<u64>) -> axum::Router {
+    let url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let mut limited = common::test_provider("limited");
+    limited.url = url.clone();
+    limited.input_rate = 0.0;
+    limited.output_rate = 0.0;
+    limited.base_fee = 10.0;
+    limited.requests_per_minute = rpm;
+    limited.tokens_per_minute = tpm;
+    let mut backup = common::test_provider("backup");
+    backup.url = url;
+    backup.input_rate = 0.0;
+    backup.output_rate = 0.0;
+    backup.base_fee = 20.0;
+    let providers = vec![limited, backup];
+
+    let mut config = common::db_test_config();
+    config.providers = providers.clone();
+
+    let state = AppState {
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&[
+            "limited".to_string(),
+            "backup".to_string(),
+        ])),
+        provider_clients: Arc::new(ProviderClients::new(&providers, None).unwrap()),
+        quotas: Arc::new(ProviderQuotas::new(&providers)),
+        ..common::setup_test_state(config)
+    };
+    create_router(state)
+}
+
+/// Send a chat completion and return the provider that served it.
+async fn complete(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}],
+                        "max_tokens": 5
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn health(app: &axum::Router) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await.1
+}
+
+#[tokio::test]
+async fn requests_shift_away_once_request_quota_is_used() {
+    let app = setup_app(Some(2), None).await;
+
+    assert_eq!(complete(&app).await, "limited");
+    assert_eq!(complete(&app).await, "limited");
+    assert_eq!(complete(&app).await, "backup");
+
+    let body = health(&app).await;
+    assert_eq!(
+        body["providers"]["limited"]["quota"],
+        serde_json::json!({"requests_per_minute": 2, "requests_remaining": 0})
+    );
+    assert!(body["providers"]["backup"].get("quota").is_none());
+}
+
+#[tokio::test]
+async fn reported_tokens_count_against_token_quota() {
+    let app = setup_app(None, Some(15)).await;
+
+    // Estimated at 1 input + 5 output tokens; the mock reports 2 + 10
+    assert_eq!(complete(&app).await, "limited");
+    let body = health(&app).await;
+    assert_eq!(
+        body["providers"]["limited"]["quota"],
+        serde_json::json!({"tokens_per_minute": 15, "tokens_remaining": 3})
+    );
+    assert_eq!(complete(&app).await, "backup");
+}
@@ -0,0 +1,177 @@
+//! Integration tests for the `race_first_token` strategy: the top two
+//! candidates are streamed at once and only the first to emit a token is
+//! kept and billed.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+
+/// Streaming provider answering `content` after `delay`.
+async fn sse_provider(content: &str, delay: Duration) -> MockServer {
+    let server = MockServer::start().await;
+    let sse = format!(
+        "data: {{\"id\":\"c\",\"choices\":[{{\"index\":0,\"delta\":{{\"role\":\"assistant\",\"content\":\"\"}}}}]}}\n\n\
+         data: {{\"id\":\"c\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{}\"}}}}]}}\n\n\
+         data: {{\"id\":\"c\",\"choices\":[],\"usage\":{{\"prompt_tokens\":100,\"completion_tokens\":200,\"total_tokens\":300}}}}\n\n\
+         data: [DONE]\n\n",
+        content
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(delay)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+/// `cheap` is the first candidate, `fast` the second.
+async fn app(cheap: &MockServer, fast: &MockServer, strategy: &str) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", cheap.uri()),
+            input_rate: 1.0,
+            output_rate: 1.0,
+            ..common::test_provider("cheap")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", fast.uri()),
+            ..common::test_provider("fast")
+        },
+    ];
+    config.policies.default_strategy = strategy.to_string();
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    // Stream usage lands through the writer
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+async fn stream_chat(app: axum::Router) -> (StatusCode, Option<String>, String) {
+    let response = app
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}],"stream":true}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let provider = response
+        .headers()
+        .get("x-arbstr-provider")
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        provider,
+        String::from_utf8_lossy(&body).into_owned(),
+    )
+}
+
+/// provider, cost_sats, success, stream_duration_ms
+type LoggedRow = (Option<String>, Option<f64>, bool, Option<i64>);
+
+/// Poll until the stream's usage update has landed.
+async fn logged_rows(pool: &SqlitePool) -> Vec<LoggedRow> {
+    for _ in 0..100 {
+        let rows: Vec<LoggedRow> = sqlx::query_as(
+            "SELECT provider, cost_sats, success, stream_duration_ms FROM requests ORDER BY id",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        if !rows.is_empty() && rows.iter().all(|r| r.3.is_some()) {
+            return rows;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("stream completion update never landed");
+}
+
+#[tokio::test]
+async fn first_token_wins_and_only_the_winner_is_billed() {
+    let cheap = sse_provider("slow answer", Duration::from_secs(2)).await;
+    let fast = sse_provider("fast answer", Duration::ZERO).await;
+    let (app, pool) = app(&cheap, &fast, "race_first_token").await;
+
+    let (status, provider, body) = stream_chat(app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(provider.as_deref(), Some("fast"));
+    assert!(body.contains("fast answer"), "{}", body);
+    assert!(!body.contains("slow answer"));
+
+    // Both were asked; only the winner is logged, billed at its own rates
+    assert_eq!(cheap.received_requests().await.unwrap().len(), 1);
+    let rows = logged_rows(&pool).await;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0.as_deref(), Some("fast"));
+    // 100 * 5 / 1000 + 200 * 15 / 1000 = 3.5 sats
+    assert!((rows[0].1.unwrap() - 3.5).abs() < 1e-9);
+    assert!(rows[0].2);
+
+    let tags: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM request_tags")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        tags,
+        vec![("race_abandoned".to_string(), "cheap".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn failed_candidate_falls_back_to_the_other() {
+    let cheap = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&cheap)
+        .await;
+    let fast = sse_provider("fast answer", Duration::from_millis(200)).await;
+    let (app, pool) = app(&cheap, &fast, "race_first_token").await;
+
+    let (status, provider, body) = stream_chat(app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(provider.as_deref(), Some("fast"));
+    assert!(body.contains("fast answer"));
+    let rows = logged_rows(&pool).await;
+    assert_eq!(rows.len(), 1);
+    let tags: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM request_tags")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(tags.is_empty(), "nothing was abandoned: {:?}", tags);
+}
+
+#[tokio::test]
+async fn other_strategies_send_to_one_provider() {
+    let cheap = sse_provider("slow answer", Duration::from_millis(200)).await;
+    let fast = sse_provider("fast answer", Duration::ZERO).await;
+    let (app, _pool) = app(&cheap, &fast, "cheapest").await;
+
+    let (status, provider, body) = stream_chat(app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(provider.as_deref(), Some("cheap"));
+    assert!(body.contains("slow answer"));
+    assert!(fast.received_requests().await.unwrap().is_empty());
+}
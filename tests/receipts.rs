@@ -0,0 +1,201 @@
+//! Integration tests for GET /v1/requests/{id}/receipt, signed receipts
+//! stored when requests (streamed ones included) complete.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::receipts::{ReceiptSigner, SignedReceipt};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SECRET: &str = "5c0c523f52a5b6fad39ed2403092df8cebc36318b39383bca6c00808626fab3a";
+
+async fn mock_provider(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    let response = if status == 200 {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-receipt",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    } else {
+        ResponseTemplate::new(status).set_body_string("bad request")
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(serde_json::json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"}}]}\n\n\
+             data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":7}}\n\n\
+             data: [DONE]\n\n",
+            "text/event-stream",
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.receipts.secret_key = Some(SECRET.into());
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    state.receipts = Arc::new(ReceiptSigner::new(&state.config));
+    (create_router(state), pool)
+}
+
+/// Send `body` as a completion; returns its correlation ID.
+async fn complete(app: &axum::Router, body: &str) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let correlation_id = response.headers()["x-arbstr-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    // Read the body so a stream runs to completion
+    let _ = axum::body::to_bytes(response.into_body(), usize::MAX).await;
+    correlation_id
+}
+
+/// GET /v1/requests/{id}/receipt, waiting for the row to be written.
+async fn receipt(app: &axum::Router, id: &str) -> (StatusCode, serde_json::Value) {
+    for _ in 0..100 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/v1/requests/{}/receipt", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let (status, body) = common::parse_body(response).await;
+        if status != StatusCode::NOT_FOUND || !body.to_string().contains("not found") {
+            return (status, body);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("request {} was never logged", id);
+}
+
+#[tokio::test]
+async fn completed_request_has_a_verifiable_receipt() {
+    let server = mock_provider(200).await;
+    let (app, _pool) = setup_app(&server).await;
+
+    let body = r#"{"model": "gpt-4o", "messages": [{"role": "user", "content": "hi"}]}"#;
+    let correlation_id = complete(&app, body).await;
+    let (status, json) = receipt(&app, &correlation_id).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let signed: SignedReceipt = serde_json::from_value(json).unwrap();
+    assert!(signed.verify());
+    let receipt = &signed.receipt;
+    assert_eq!(receipt.request_id, correlation_id);
+    assert_eq!(receipt.provider.as_deref(), Some("upstream"));
+    assert_eq!(receipt.model, "gpt-4o");
+    assert_eq!(
+        (receipt.input_tokens, receipt.output_tokens),
+        (Some(10), Some(5))
+    );
+    assert!(receipt.cost_sats.unwrap() > 0.0);
+    // Signed with the configured instance key
+    let instance = arbstr::proxy::nostr::Keys::from_hex(SECRET)
+        .unwrap()
+        .public_key();
+    assert_eq!(receipt.instance, instance);
+
+    // The hash is of the body exactly as the client sent it
+    let expected: String = Sha256::digest(body.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(receipt.request_sha256.as_deref(), Some(expected.as_str()));
+}
+
+#[tokio::test]
+async fn failed_request_has_no_receipt() {
+    let server = mock_provider(400).await;
+    let (app, _pool) = setup_app(&server).await;
+
+    let body = r#"{"model": "gpt-4o", "messages": [{"role": "user", "content": "hi"}]}"#;
+    let correlation_id = complete(&app, body).await;
+    let (status, json) = receipt(&app, &correlation_id).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(json.to_string().contains("did not complete"));
+}
+
+#[tokio::test]
+async fn stream_receipt_carries_final_usage() {
+    let server = mock_provider(200).await;
+    let (app, _pool) = setup_app(&server).await;
+
+    let body =
+        r#"{"model": "gpt-4o", "stream": true, "messages": [{"role": "user", "content": "hi"}]}"#;
+    let correlation_id = complete(&app, body).await;
+    let (status, json) = receipt(&app, &correlation_id).await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+
+    let signed: SignedReceipt = serde_json::from_value(json).unwrap();
+    assert!(signed.verify());
+    assert_eq!(
+        (signed.receipt.input_tokens, signed.receipt.output_tokens),
+        (Some(12), Some(7))
+    );
+    assert!(signed.receipt.cost_sats.unwrap() > 0.0);
+}
+
+#[tokio::test]
+async fn receipt_is_fixed_at_completion() {
+    let server = mock_provider(200).await;
+    let (app, pool) = setup_app(&server).await;
+
+    let body = r#"{"model": "gpt-4o", "messages": [{"role": "user", "content": "hi"}]}"#;
+    let correlation_id = complete(&app, body).await;
+    let (_, issued) = receipt(&app, &correlation_id).await;
+
+    // Rewriting the log afterwards doesn't change what was signed
+    sqlx::query("UPDATE requests SET cost_sats = 0, input_tokens = 1 WHERE correlation_id = ?")
+        .bind(&correlation_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, fetched) = receipt(&app, &correlation_id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched, issued);
+    assert_eq!(fetched["receipt"]["input_tokens"], 10);
+}
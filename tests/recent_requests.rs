@@ -0,0 +1,124 @@
+//! Integration tests for the in-memory request buffer (`/v1/requests/recent`).
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::mock_provider::MockProviderConfig;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+fn chat_request(model: &str, stream: bool) -> Request<Body> {
+    Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Hello"}],
+                "stream": stream
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+async fn get_recent(app: &axum::Router, query: &str) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get(format!("/v1/requests/recent{}", query))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    body
+}
+
+#[tokio::test]
+async fn records_requests_without_a_database() {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    let response = app
+        .clone()
+        .oneshot(chat_request("gpt-4o", false))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = app
+        .clone()
+        .oneshot(chat_request("no-such-model", false))
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
+    let response = app
+        .clone()
+        .oneshot(chat_request("gpt-4o", true))
+        .await
+        .unwrap();
+    axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+
+    // The stream's entry is completed by a background task
+    let mut body = get_recent(&app, "").await;
+    for _ in 0..50 {
+        if body["data"][0]["in_progress"] == false {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        body = get_recent(&app, "").await;
+    }
+
+    assert_eq!(body["capacity"], 1000);
+    assert_eq!(body["recorded"], 3);
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 3);
+
+    // Newest first
+    assert_eq!(data[0]["streaming"], true);
+    assert_eq!(data[0]["in_progress"], false);
+    assert_eq!(data[0]["success"], true);
+    assert!(data[0]["stream_duration_ms"].is_number());
+    assert!(data[0]["output_tokens"].as_u64().unwrap() > 0);
+
+    assert_eq!(data[1]["model"], "no-such-model");
+    assert_eq!(data[1]["success"], false);
+    assert!(data[1]["error_status"].is_number());
+
+    assert_eq!(data[2]["model"], "gpt-4o");
+    assert_eq!(data[2]["provider"], "mock");
+    assert_eq!(data[2]["streaming"], false);
+    assert_eq!(data[2]["success"], true);
+}
+
+#[tokio::test]
+async fn filters_and_limit() {
+    let mut provider = common::test_provider("mock");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let (app, _) = common::setup_circuit_test_app(vec![provider]);
+
+    for model in ["gpt-4o", "no-such-model", "gpt-4o"] {
+        app.clone()
+            .oneshot(chat_request(model, false))
+            .await
+            .unwrap();
+    }
+
+    let body = get_recent(&app, "?success=false").await;
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["model"], "no-such-model");
+
+    let body = get_recent(&app, "?model=GPT-4o&provider=mock").await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let body = get_recent(&app, "?limit=1").await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["recorded"], 3);
+}
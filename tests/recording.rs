@@ -0,0 +1,151 @@
+//! Integration tests for `[recording]`: interactions recorded against a
+//! live provider are replayed with the provider gone.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{Config, ProviderConfig, RecordingConfig, RecordingMode};
+use arbstr::proxy::create_router;
+use arbstr::proxy::recording::Recorder;
+
+const SSE: &str = "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"streamed\"}}]}\n\n\
+     data: {\"id\":\"c\",\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":1,\"total_tokens\":4}}\n\n\
+     data: [DONE]\n\n";
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(serde_json::json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .insert_header("set-cookie", "session=secret")
+                .set_body_string(SSE),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-ratelimit-remaining-requests", "41")
+                .set_body_json(serde_json::json!({
+                    "id": "chatcmpl-recorded",
+                    "object": "chat.completion",
+                    "model": "gpt-4o",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "recorded answer"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+                })),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+fn config(provider_url: &str) -> Config {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: provider_url.to_string(),
+        ..common::test_provider("upstream")
+    }];
+    config
+}
+
+async fn app(provider_url: &str, mode: RecordingMode, dir: &std::path::Path) -> axum::Router {
+    let (mut state, _pool) = common::setup_db_test_state(config(provider_url)).await;
+    state.recording = Arc::new(
+        Recorder::new(Some(&RecordingConfig {
+            mode,
+            dir: dir.display().to_string(),
+            realtime: false,
+        }))
+        .unwrap(),
+    );
+    create_router(state)
+}
+
+async fn chat(app: &axum::Router, content: &str, stream: bool) -> (StatusCode, String) {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": stream,
+        "messages": [{"role": "user", "content": content}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[tokio::test]
+async fn recorded_interactions_replay_without_the_provider() {
+    let dir = std::env::temp_dir().join(format!("arbstr-recording-{}", uuid::Uuid::new_v4()));
+    let server = mock_provider().await;
+    let provider_url = format!("{}/v1", server.uri());
+
+    let recorder = app(&provider_url, RecordingMode::Record, &dir).await;
+    let (status, live) = chat(&recorder, "hello", false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(live.contains("recorded answer"));
+    let (status, live_stream) = chat(&recorder, "hello", true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(live_stream.contains("streamed"));
+
+    // Streams are written once the body has been read to the end
+    let tape = dir.join("upstream.jsonl");
+    for _ in 0..50 {
+        if std::fs::read_to_string(&tape).is_ok_and(|t| t.lines().count() == 2) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let recorded = std::fs::read_to_string(&tape).unwrap();
+    assert_eq!(recorded.lines().count(), 2);
+    assert!(!recorded.contains("session=secret"), "cookies are dropped");
+    assert!(recorded.contains("x-ratelimit-remaining-requests"));
+
+    // The provider is gone; replay answers from the recording
+    drop(server);
+    let replayer = app(&provider_url, RecordingMode::Replay, &dir).await;
+    let (status, replayed) = chat(&replayer, "hello", false).await;
+    assert_eq!(status, StatusCode::OK);
+    // Identical apart from the measured latency
+    let without_latency = |body: &str| {
+        let mut json: serde_json::Value = serde_json::from_str(body).unwrap();
+        json["arbstr"]["latency_ms"].take();
+        json
+    };
+    assert_eq!(without_latency(&replayed), without_latency(&live));
+    let (status, replayed_stream) = chat(&replayer, "hello", true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(replayed_stream.contains("streamed"));
+
+    // An unrecorded request fails like an unreachable provider
+    let (status, body) = chat(&replayer, "something new", false).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert!(body.contains("no recorded interaction"), "{}", body);
+
+    std::fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,145 @@
+//! Integration tests for the GET /v1/stats/reliability endpoint.
+
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// Global counter for generating unique correlation IDs.
+static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Insert a request one hour ago, preceded by failed attempts given as
+/// `(provider, error_type)`.
+async fn seed(
+    pool: &SqlitePool,
+    provider: &str,
+    success: bool,
+    latency_ms: i64,
+    failed: &[(&str, &str)],
+) -> String {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    let correlation_id = format!(
+        "reliability-{}",
+        CORRELATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         latency_ms, success) VALUES (?, ?, 'gpt-4o', ?, 0, ?, ?)",
+    )
+    .bind(&correlation_id)
+    .bind(&timestamp)
+    .bind(provider)
+    .bind(latency_ms)
+    .bind(success)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+    seed_attempts(pool, &correlation_id, failed).await;
+    correlation_id
+}
+
+async fn seed_attempts(pool: &SqlitePool, correlation_id: &str, failed: &[(&str, &str)]) {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    for (i, (provider, error_type)) in failed.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO request_attempts (correlation_id, attempt, provider, status, \
+             error_type, backoff_ms, timestamp) VALUES (?, ?, ?, 503, ?, 0, ?)",
+        )
+        .bind(correlation_id)
+        .bind(i as i64 + 1)
+        .bind(provider)
+        .bind(error_type)
+        .bind(&timestamp)
+        .execute(pool)
+        .await
+        .expect("Failed to seed attempt");
+    }
+}
+
+/// alpha serves three requests first time and one after a retry; two
+/// requests fall back from alpha to beta, one of which still fails.
+async fn seed_reliability_data(pool: &SqlitePool) {
+    for _ in 0..3 {
+        seed(pool, "alpha", true, 100, &[]).await;
+    }
+    seed(pool, "alpha", true, 700, &[("alpha", "5xx")]).await;
+    let rescued = seed(
+        pool,
+        "beta",
+        true,
+        500,
+        &[("alpha", "5xx"), ("alpha", "timeout")],
+    )
+    .await;
+    // Attempts are repeated under every row logged for a request
+    seed_attempts(pool, &rescued, &[("alpha", "5xx"), ("alpha", "timeout")]).await;
+    seed(pool, "beta", false, 900, &[("alpha", "5xx")]).await;
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn reliability_totals_and_provider_breakdown() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_reliability_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/reliability?range=last_24h").await;
+    assert_eq!(status, 200);
+
+    let totals = &json["totals"];
+    assert_eq!(totals["requests"], 6);
+    assert_eq!(totals["retried"], 3);
+    assert_eq!(totals["retry_rate"], 0.5);
+    assert_eq!(totals["fallbacks"], 2);
+    assert_eq!(totals["fallback_successes"], 1);
+    assert_eq!(totals["fallback_success_rate"], 0.5);
+    // Retried successes average 600ms, first-attempt successes 100ms
+    assert_eq!(totals["avg_extra_latency_ms"], 500.0);
+    assert_eq!(totals["failed_attempts"], 4);
+    assert_eq!(totals["attempt_errors"]["5xx"], 3);
+    assert_eq!(totals["attempt_errors"]["timeout"], 1);
+
+    let providers = json["providers"].as_array().unwrap();
+    assert_eq!(providers.len(), 2);
+    assert_eq!(providers[0]["provider"], "alpha");
+    assert_eq!(providers[0]["requests"], 4);
+    assert_eq!(providers[0]["retried"], 1);
+    assert_eq!(providers[0]["fallbacks"], 0);
+    assert_eq!(providers[0]["failed_attempts"], 4);
+    assert_eq!(providers[0]["avg_extra_latency_ms"], 600.0);
+    assert_eq!(providers[1]["provider"], "beta");
+    assert_eq!(providers[1]["retried"], 2);
+    assert_eq!(providers[1]["fallback_success_rate"], 0.5);
+    assert_eq!(providers[1]["failed_attempts"], 0);
+    assert!(providers[1]["avg_extra_latency_ms"].is_null());
+}
+
+#[tokio::test]
+async fn reliability_empty_range() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let (status, json) = get_json(app, "/v1/stats/reliability").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["totals"]["requests"], 0);
+    assert_eq!(json["totals"]["retry_rate"], 0.0);
+    assert!(json["totals"]["fallback_success_rate"].is_null());
+    assert!(json["providers"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reliability_unknown_model_returns_404() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let (status, _json) = get_json(app, "/v1/stats/reliability?model=nope").await;
+    assert_eq!(status, 404);
+}
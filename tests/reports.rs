@@ -0,0 +1,123 @@
+//! Integration tests for scheduled cost and reliability reports.
+//!
+//! Seeds the request log, builds a report directly, and verifies webhook
+//! delivery against a mock HTTP receiver.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use arbstr::config::ReportSchedule;
+use arbstr::proxy::reports::{build_report, send_webhook};
+use axum::routing::post;
+use axum::Router;
+use sqlx::SqlitePool;
+
+/// Insert a request `hours_ago` hours before `now`.
+#[allow(clippy::too_many_arguments)]
+async fn seed_request(
+    pool: &SqlitePool,
+    id: &str,
+    now: chrono::DateTime<chrono::Utc>,
+    hours_ago: i64,
+    model: &str,
+    provider: &str,
+    success: bool,
+    cost_sats: f64,
+) {
+    let timestamp = (now - chrono::Duration::hours(hours_ago)).to_rfc3339();
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         input_tokens, output_tokens, cost_sats, latency_ms, success) \
+         VALUES (?, ?, ?, ?, 0, 1000, 1000, ?, 100, ?)",
+    )
+    .bind(id)
+    .bind(&timestamp)
+    .bind(model)
+    .bind(provider)
+    .bind(cost_sats)
+    .bind(success)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+}
+
+#[tokio::test]
+async fn daily_report_summarises_previous_day() {
+    let pool = common::setup_test_db().await;
+    let now = chrono::Utc::now();
+
+    seed_request(&pool, "r1", now, 1, "gpt-4o", "beta", true, 20.0).await;
+    seed_request(&pool, "r2", now, 2, "gpt-4o", "beta", true, 20.0).await;
+    seed_request(&pool, "r3", now, 3, "gpt-4o-mini", "beta", true, 5.0).await;
+    for i in 0..6 {
+        let id = format!("err-{}", i);
+        seed_request(&pool, &id, now, 4, "gpt-4o", "alpha", false, 0.0).await;
+    }
+    // Outside the daily window
+    seed_request(&pool, "old", now, 30, "gpt-4o", "alpha", true, 100.0).await;
+
+    let config = common::db_test_config();
+    let report = build_report(&pool, &config, ReportSchedule::Daily, now)
+        .await
+        .unwrap();
+
+    assert_eq!(report.schedule, "daily");
+    assert_eq!(report.totals.requests, 9);
+    assert_eq!(report.totals.errors, 6);
+    assert_eq!(report.totals.cost_sats, 45.0);
+
+    assert_eq!(report.top_models[0].model, "gpt-4o");
+    assert_eq!(report.top_models[0].cost_sats, 40.0);
+
+    let alpha = report
+        .providers
+        .iter()
+        .find(|p| p.provider == "alpha")
+        .unwrap();
+    assert_eq!(alpha.errors, 6);
+    assert!(alpha.error_spike);
+
+    // alpha is the most expensive gpt-4o provider, so routing to beta saved sats
+    assert!(report.savings_sats > 0.0);
+}
+
+#[tokio::test]
+async fn webhook_receives_report_json() {
+    let pool = common::setup_test_db().await;
+    let now = chrono::Utc::now();
+    seed_request(&pool, "w1", now, 1, "gpt-4o", "alpha", true, 12.0).await;
+
+    let received: Arc<Mutex<Vec<serde_json::Value>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let app = Router::new().route(
+        "/hook",
+        post(move |body: axum::Json<serde_json::Value>| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push(body.0);
+                "ok"
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let config = common::db_test_config();
+    let report = build_report(&pool, &config, ReportSchedule::Weekly, now)
+        .await
+        .unwrap();
+    send_webhook(
+        &reqwest::Client::new(),
+        &format!("http://{}/hook", addr),
+        &report,
+    )
+    .await
+    .unwrap();
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["schedule"], "weekly");
+    assert_eq!(received[0]["totals"]["cost_sats"], 12.0);
+}
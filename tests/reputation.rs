@@ -0,0 +1,193 @@
+//! Integration tests for reputation-based demotion via GET /v1/route/explain.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+use arbstr::config::{
+    Config, PoliciesConfig, ProviderConfig, ReputationAction, ReputationConfig, RoutingConfig,
+    ServerConfig,
+};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, ReputationTracker};
+
+fn provider(name: &str, output_rate: f64) -> ProviderConfig {
+    ProviderConfig {
+        output_rate,
+        ..common::test_provider(name)
+    }
+}
+
+fn reputation_config(action: ReputationAction) -> ReputationConfig {
+    ReputationConfig {
+        window: 20,
+        min_requests: 5,
+        max_error_rate: 0.25,
+        max_avg_latency_ms: None,
+        action,
+        cost_multiplier: 3.0,
+        penalty_secs: 600,
+    }
+}
+
+/// Build an app with providers cheap (10), pricier (20), and broken (5).
+fn setup_app(
+    reputation: Option<ReputationConfig>,
+) -> (
+    axum::Router,
+    Arc<CircuitBreakerRegistry>,
+    Arc<ReputationTracker>,
+) {
+    let providers = vec![
+        provider("cheap", 10.0),
+        provider("pricier", 20.0),
+        provider("broken", 5.0),
+    ];
+    let names: Vec<String> = providers.iter().map(|p| p.name.clone()).collect();
+    let registry = Arc::new(CircuitBreakerRegistry::new(&names));
+    let tracker = Arc::new(ReputationTracker::new(reputation.clone()));
+
+    let config = Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: None,
+        vault: None,
+        providers,
+        policies: PoliciesConfig::default(),
+        logging: Default::default(),
+        routing: RoutingConfig {
+            reputation,
+            ..Default::default()
+        },
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    };
+
+    let state = AppState {
+        circuit_breakers: registry.clone(),
+        reputation: tracker.clone(),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), registry, tracker)
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+fn open_circuit(registry: &CircuitBreakerRegistry, name: &str) {
+    for _ in 0..3 {
+        registry.record_failure(name, "5xx", "HTTP 502");
+    }
+}
+
+#[tokio::test]
+async fn explain_without_reputation_uses_cost_order() {
+    let (app, registry, _tracker) = setup_app(None);
+    open_circuit(&registry, "broken");
+
+    let (status, json) = get_json(app, "/v1/route/explain?model=gpt-4o").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["selected"], "cheap");
+
+    let candidates = json["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), 3);
+    assert_eq!(candidates[0]["provider"], "cheap");
+    assert_eq!(candidates[1]["provider"], "pricier");
+    assert_eq!(candidates[2]["provider"], "broken");
+    assert_eq!(candidates[2]["eligible"], false);
+    assert_eq!(candidates[2]["skip_reason"], "circuit open");
+    assert!(candidates[0].get("reputation").is_none());
+}
+
+#[tokio::test]
+async fn penalized_provider_is_demoted_while_circuit_closed() {
+    let (app, registry, tracker) = setup_app(Some(reputation_config(ReputationAction::Penalize)));
+    // Alternating failures: never 3 in a row, so the circuit stays closed
+    for i in 0..6 {
+        if i % 2 == 0 {
+            registry.record_failure("cheap", "5xx", "HTTP 502");
+            tracker.record("cheap", false, 0);
+        } else {
+            registry.record_success("cheap");
+            tracker.record("cheap", true, 100);
+        }
+    }
+
+    let (status, json) = get_json(app, "/v1/route/explain?model=gpt-4o").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["selected"], "broken");
+
+    let candidates = json["candidates"].as_array().unwrap();
+    let names: Vec<&str> = candidates
+        .iter()
+        .map(|c| c["provider"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["broken", "pricier", "cheap"]);
+
+    let cheap = &candidates[2];
+    assert_eq!(cheap["circuit_state"], "closed");
+    assert_eq!(cheap["eligible"], true);
+    let penalty = &cheap["reputation"]["penalty"];
+    assert_eq!(penalty["excluded"], false);
+    assert!(penalty["cost_multiplier"].as_f64().unwrap() > 2.9);
+    assert!(penalty["reason"].as_str().unwrap().contains("error rate"));
+    assert!(cheap["effective_cost"].as_f64().unwrap() > 29.0);
+}
+
+#[tokio::test]
+async fn excluded_provider_is_skipped() {
+    let (app, _registry, tracker) = setup_app(Some(reputation_config(ReputationAction::Exclude)));
+    for _ in 0..5 {
+        tracker.record("broken", false, 0);
+    }
+
+    let (status, json) = get_json(app, "/v1/route/explain?model=gpt-4o").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["selected"], "cheap");
+
+    let candidates = json["candidates"].as_array().unwrap();
+    let broken = candidates.last().unwrap();
+    assert_eq!(broken["provider"], "broken");
+    assert_eq!(broken["eligible"], false);
+    assert_eq!(broken["skip_reason"], "reputation exclusion");
+}
+
+#[tokio::test]
+async fn explain_unknown_model_returns_400() {
+    let (app, _registry, _tracker) = setup_app(None);
+
+    let (status, _json) = get_json(app, "/v1/route/explain?model=nope").await;
+    assert_eq!(status, 400);
+}
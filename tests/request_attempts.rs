@@ -0,0 +1,147 @@
+//! Integration tests for the `request_attempts` table and the attempts
+//! breakdown in GET /v1/requests/{id}.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{BackoffConfig, ProviderConfig};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    let response = if status == 200 {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-attempts",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    } else {
+        ResponseTemplate::new(status).set_body_string("upstream unavailable")
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// "primary" (cheapest) answers 503, "fallback" answers 200. Backoff is
+/// 10ms doubling, without jitter.
+async fn setup_app(primary: &MockServer, fallback: &MockServer) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", primary.uri()),
+            ..common::test_provider("primary")
+        },
+        ProviderConfig {
+            url: format!("{}/v1", fallback.uri()),
+            input_rate: 50.0,
+            ..common::test_provider("fallback")
+        },
+    ];
+    config.routing.backoff = BackoffConfig {
+        base_ms: 10,
+        jitter: false,
+        ..Default::default()
+    };
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool));
+    create_router(state)
+}
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn retried_request_exposes_its_attempts() {
+    let primary = mock_provider(503).await;
+    let fallback = mock_provider(200).await;
+    let app = setup_app(&primary, &fallback).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hi"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-arbstr-retries"], "3/primary");
+
+    let mut id = None;
+    for _ in 0..100 {
+        let (_, logs) = get(&app, "/v1/requests").await;
+        if let Some(row) = logs["data"].as_array().and_then(|d| d.first()) {
+            id = row["id"].as_i64();
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let id = id.expect("request was never logged");
+
+    let (status, detail) = get(&app, &format!("/v1/requests/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(detail["provider"], "fallback");
+    assert_eq!(detail["success"], true);
+
+    let attempts = detail["attempts"].as_array().unwrap();
+    assert_eq!(attempts.len(), 3);
+    for (i, attempt) in attempts.iter().enumerate() {
+        assert_eq!(attempt["attempt"], i as i64 + 1);
+        assert_eq!(attempt["provider"], "primary");
+        assert_eq!(attempt["status"], 503);
+        assert_eq!(attempt["error_type"], "5xx");
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(attempt["timestamp"].as_str().unwrap()).is_ok()
+        );
+    }
+    let backoffs: Vec<i64> = attempts
+        .iter()
+        .map(|a| a["backoff_ms"].as_i64().unwrap())
+        .collect();
+    assert_eq!(backoffs, vec![0, 10, 20]);
+}
+
+#[tokio::test]
+async fn unknown_request_id_is_404() {
+    let primary = mock_provider(503).await;
+    let fallback = mock_provider(200).await;
+    let app = setup_app(&primary, &fallback).await;
+
+    let (status, _) = get(&app, "/v1/requests/4242").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The static route still wins over the id parameter
+    let (status, _) = get(&app, "/v1/requests/recent").await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,174 @@
+//! Integration tests for GET /v1/requests/{id}, the full record of a
+//! single request.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider(status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    let response = if status == 200 {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-detail",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    } else {
+        ResponseTemplate::new(status).set_body_string("bad request")
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// One provider behind the "support" policy.
+async fn setup_app(server: &MockServer) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "support".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool));
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    create_router(state)
+}
+
+/// Send a completion under the "support" policy; returns its correlation ID.
+async fn complete(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-arbstr-policy", "support")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hi"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response.headers()["x-arbstr-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// GET /v1/requests/{id}, waiting for the row to be written.
+async fn detail(app: &axum::Router, id: &str) -> serde_json::Value {
+    for _ in 0..100 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/v1/requests/{}", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let (status, body) = common::parse_body(response).await;
+        if status == StatusCode::OK {
+            return body;
+        }
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("request {} was never logged", id);
+}
+
+#[tokio::test]
+async fn detail_by_correlation_id_has_the_full_record() {
+    let server = mock_provider(200).await;
+    let app = setup_app(&server).await;
+
+    let correlation_id = complete(&app).await;
+    let body = detail(&app, &correlation_id).await;
+
+    assert_eq!(body["correlation_id"], correlation_id.as_str());
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["provider"], "upstream");
+    assert_eq!(body["policy"], "support");
+    assert!(body["tier"].is_string());
+    assert_eq!(body["success"], true);
+    assert_eq!(body["tokens"]["input"], 10);
+    assert_eq!(body["tokens"]["output"], 5);
+    assert!(body["cost"]["sats"].is_number());
+    assert!(body["timing"]["latency_ms"].is_number());
+    assert!(body.get("error").is_none());
+    assert_eq!(body["attempts"], serde_json::json!([]));
+    assert_eq!(
+        body["circuit_snapshot"],
+        serde_json::json!({"upstream": {"state": "closed", "failure_count": 0}})
+    );
+
+    // The row id from the listing finds the same record
+    let by_id = detail(&app, &body["id"].to_string()).await;
+    assert_eq!(by_id["correlation_id"], correlation_id.as_str());
+}
+
+#[tokio::test]
+async fn failed_request_detail_has_error_info() {
+    let server = mock_provider(400).await;
+    let app = setup_app(&server).await;
+
+    let correlation_id = complete(&app).await;
+    let body = detail(&app, &correlation_id).await;
+
+    assert_eq!(body["success"], false);
+    assert_eq!(body["error"]["status"], 400);
+    assert_eq!(body["error_type"], "4xx");
+    let attempts = body["attempts"].as_array().unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0]["status"], 400);
+}
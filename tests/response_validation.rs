@@ -0,0 +1,190 @@
+//! Integration tests for policy response validation (`validate`) and the
+//! higher-tier retry on failed responses.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+use arbstr::config::{Config, PolicyRule, ResponseValidationConfig, Tier};
+
+/// Start a provider that always answers with `content`.
+async fn start_provider(content: &'static str) -> String {
+    use axum::{routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move || async move {
+            Json(serde_json::json!({
+                "id": "chatcmpl-mock",
+                "object": "chat.completion",
+                "choices": [{
+                    "message": {"role": "assistant", "content": content},
+                    "index": 0,
+                    "finish_reason": "stop"
+                }],
+                "usage": {
+                    "prompt_tokens": 10,
+                    "completion_tokens": 5,
+                    "total_tokens": 15
+                }
+            }))
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock provider");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.ok();
+    });
+
+    format!("http://127.0.0.1:{}/v1", addr.port())
+}
+
+/// A cheap local provider answering `cheap`, a pricier standard provider
+/// answering `standard`, and a "structured" policy requiring JSON.
+async fn config(cheap: &'static str, standard: &'static str) -> Config {
+    let mut local = common::test_provider("cheap");
+    local.url = start_provider(cheap).await;
+    local.tier = Tier::Local;
+    local.base_fee = 1.0;
+    let mut upper = common::test_provider("standard");
+    upper.url = start_provider(standard).await;
+    upper.tier = Tier::Standard;
+    upper.base_fee = 10.0;
+
+    let mut config = common::db_test_config();
+    config.providers = vec![local, upper];
+    config.policies.rules = vec![PolicyRule {
+        name: "structured".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: Some(ResponseValidationConfig {
+            json: true,
+            ..Default::default()
+        }),
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    config
+}
+
+async fn complete(app: &axum::Router) -> (http::HeaderMap, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-arbstr-policy", "structured")
+                .header("x-arbstr-complexity", "low")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Reply in JSON"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let headers = response.headers().clone();
+    (headers, common::parse_body(response).await.1)
+}
+
+async fn recent(app: &axum::Router) -> Vec<serde_json::Value> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/requests/recent")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, body) = common::parse_body(response).await;
+    body["data"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn valid_response_is_returned_without_retry() {
+    let (app, _pool) =
+        common::setup_db_test_app_with_config(config(r#"{"ok": true}"#, r#"{"ok": 1}"#).await)
+            .await;
+
+    let (headers, body) = complete(&app).await;
+    assert_eq!(headers["x-arbstr-provider"], "cheap");
+    assert_eq!(headers["x-arbstr-validation"], "passed");
+    assert_eq!(body["choices"][0]["message"]["content"], r#"{"ok": true}"#);
+    assert_eq!(recent(&app).await.len(), 1);
+}
+
+#[tokio::test]
+async fn invalid_response_is_retried_on_higher_tier() {
+    let (app, _pool) = common::setup_db_test_app_with_config(
+        config("Sure, here is some JSON!", r#"{"ok": true}"#).await,
+    )
+    .await;
+
+    let (headers, body) = complete(&app).await;
+    assert_eq!(headers["x-arbstr-provider"], "standard");
+    assert_eq!(headers["x-arbstr-validation"], "retried");
+    assert_eq!(body["choices"][0]["message"]["content"], r#"{"ok": true}"#);
+
+    // Both attempts are recorded, and the client is charged for both
+    let rows = recent(&app).await;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["provider"], "standard");
+    assert_eq!(rows[0]["tags"]["validation"], "retried");
+    assert_eq!(rows[1]["provider"], "cheap");
+    assert_eq!(rows[1]["tags"]["validation"], "failed");
+    assert_eq!(rows[0]["correlation_id"], rows[1]["correlation_id"]);
+    let total = rows[0]["cost_sats"].as_f64().unwrap() + rows[1]["cost_sats"].as_f64().unwrap();
+    let header_cost: f64 = headers["x-arbstr-cost-sats"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(
+        (header_cost - total).abs() < 0.01,
+        "{} vs {}",
+        header_cost,
+        total
+    );
+}
+
+#[tokio::test]
+async fn failed_retry_keeps_the_retried_response() {
+    let (app, _pool) =
+        common::setup_db_test_app_with_config(config("not json", "still not json").await).await;
+
+    let (headers, _) = complete(&app).await;
+    assert_eq!(headers["x-arbstr-provider"], "standard");
+    assert_eq!(headers["x-arbstr-validation"], "failed");
+    let rows = recent(&app).await;
+    assert_eq!(rows[0]["tags"]["validation"], "failed");
+    assert_eq!(rows[1]["tags"]["validation"], "failed");
+}
@@ -0,0 +1,125 @@
+//! Integration tests for the global retry budget.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{Config, RetryBudgetConfig, RoutingConfig};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry, RetryBudget};
+use arbstr::storage::DbWriter;
+
+/// App backed by an in-memory DB with a retry budget that allows no retries.
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let mut primary = common::test_provider("primary");
+    primary.url = format!("{}/v1", server.uri());
+    let mut fallback = common::test_provider("fallback");
+    fallback.url = format!("{}/v1", server.uri());
+    fallback.input_rate = 50.0;
+
+    let retry_budget = RetryBudgetConfig {
+        ratio: 0.0,
+        window_secs: 60,
+        min_retries: 0,
+    };
+    let config = Config {
+        providers: vec![primary, fallback],
+        routing: RoutingConfig {
+            retry_budget: Some(retry_budget.clone()),
+            ..Default::default()
+        },
+        ..common::db_test_config()
+    };
+
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&[
+            "primary".to_string(),
+            "fallback".to_string(),
+        ])),
+        retry_budget: Arc::new(RetryBudget::new(Some(retry_budget))),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool)
+}
+
+#[tokio::test]
+async fn exhausted_budget_fails_fast_and_tags_log() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let (app, pool) = setup_app(&server).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+    assert_eq!(
+        response.headers().get("x-arbstr-retry-budget").unwrap(),
+        "exhausted"
+    );
+    assert_eq!(
+        response.headers().get("x-arbstr-retries").unwrap(),
+        "1/primary"
+    );
+    let correlation_id = response
+        .headers()
+        .get("x-arbstr-request-id")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    // One upstream call: no retries on the primary, no fallback
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for _ in 0..100 {
+        tags = sqlx::query_as("SELECT key, value FROM request_tags WHERE correlation_id = ?")
+            .bind(&correlation_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        if !tags.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        tags,
+        vec![("retry_budget".to_string(), "exhausted".to_string())]
+    );
+
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (_, json) = common::parse_body(response).await;
+    assert_eq!(json["retry_budget"]["requests"], 1);
+    assert_eq!(json["retry_budget"]["retries"], 0);
+    assert_eq!(json["retry_budget"]["denied_total"], 2);
+}
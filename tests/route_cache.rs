@@ -0,0 +1,114 @@
+//! Integration tests for `[routing] route_cache_ttl_ms` and
+//! GET /v1/stats/route-cache.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::{create_router, CircuitBreakerRegistry, ProviderClients};
+use arbstr::router::Router as ProviderRouter;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+/// Providers `cheap` and `pricier`, with the route cache on.
+async fn setup_app() -> (axum::Router, Arc<CircuitBreakerRegistry>) {
+    let mut config = common::db_test_config();
+    let mut providers = Vec::new();
+    for (name, rate) in [("cheap", 10.0), ("pricier", 20.0)] {
+        let mut provider = common::test_provider(name);
+        provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+        provider.models = vec!["gpt-4o".to_string()];
+        provider.output_rate = rate;
+        providers.push(provider);
+    }
+    config.providers = providers;
+    config.routing.route_cache_ttl_ms = 60_000;
+
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    let names: Vec<String> = state
+        .config
+        .providers
+        .iter()
+        .map(|p| p.name.clone())
+        .collect();
+    let registry = Arc::new(CircuitBreakerRegistry::new(&names));
+    state.router = Arc::new(ProviderRouter::new(
+        state.config.providers.clone(),
+        state.config.policies.rules.clone(),
+        state.config.policies.default_strategy.clone(),
+    ));
+    state.circuit_breakers = registry.clone();
+    state.provider_clients = Arc::new(ProviderClients::new(&state.config.providers, None).unwrap());
+    (create_router(state), registry)
+}
+
+/// Send a chat completion and return the provider that served it.
+async fn served_by(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn cache_stats(app: &axum::Router) -> serde_json::Value {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/stats/route-cache")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    body
+}
+
+#[tokio::test]
+async fn repeated_requests_hit_and_circuit_changes_invalidate() {
+    let (app, registry) = setup_app().await;
+
+    let stats = cache_stats(&app).await;
+    assert_eq!(stats["enabled"], true);
+    assert_eq!(stats["ttl_ms"], 60_000);
+    assert_eq!(stats["hit_rate"], serde_json::Value::Null);
+
+    for _ in 0..3 {
+        assert_eq!(served_by(&app).await, "cheap");
+    }
+    let stats = cache_stats(&app).await;
+    assert_eq!(stats["misses"], 1);
+    assert_eq!(stats["hits"], 2);
+    assert_eq!(stats["entries"], 1);
+    assert!((stats["hit_rate"].as_f64().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+    // Opening a circuit drops the cached ordering; the open circuit is
+    // still skipped per request
+    for _ in 0..3 {
+        registry.record_failure("cheap", "5xx", "HTTP 502");
+    }
+    assert_eq!(served_by(&app).await, "pricier");
+    let stats = cache_stats(&app).await;
+    assert_eq!(stats["invalidations"], 1);
+    assert_eq!(stats["misses"], 2);
+}
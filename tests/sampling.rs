@@ -0,0 +1,115 @@
+//! Integration tests for request sampling (`[logging.sampling]`) applied to
+//! the prompt archive.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{BackoffConfig, ProviderConfig, SamplingConfig};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// App archiving prompts with no successes sampled; prompts containing
+/// "fail" get a 500 from the provider.
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_string_contains("fail"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .with_priority(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-sampling",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(server)
+        .await;
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        backoff: Some(BackoffConfig {
+            base_ms: 1,
+            max_ms: 1,
+            ..Default::default()
+        }),
+        ..common::test_provider("upstream")
+    }];
+    config.archive.enabled = true;
+    config.logging.sampling = SamplingConfig {
+        success_rate: 0.0,
+        debug_header: true,
+    };
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+async fn complete(app: &axum::Router, content: &str, debug: bool) -> u16 {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": content}]
+    });
+    let mut request = Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .header("x-request-id", content);
+    if debug {
+        request = request.header("x-arbstr-debug", "true");
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+    app.clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+/// Client request IDs of the archived prompts, once `rows` requests are
+/// logged (their archive writes are queued first).
+async fn archived(pool: &SqlitePool, rows: i64) -> Vec<String> {
+    for _ in 0..100 {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM requests")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count >= rows {
+            return sqlx::query_scalar(
+                "SELECT r.client_request_id FROM prompt_archive a
+                 JOIN requests r USING (correlation_id) ORDER BY r.client_request_id",
+            )
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn unsampled_prompts_are_archived_only_on_failure() {
+    let server = MockServer::start().await;
+    let (app, pool) = setup_app(&server).await;
+
+    assert_eq!(complete(&app, "unsampled", false).await, 200);
+    assert_eq!(complete(&app, "forced", true).await, 200);
+    assert!(complete(&app, "will-fail", false).await >= 500);
+
+    assert_eq!(archived(&pool, 3).await, ["forced", "will-fail"]);
+}
@@ -0,0 +1,151 @@
+//! Integration tests for the GET /v1/providers/{name}/scorecard endpoint.
+
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+
+/// Global counter for generating unique correlation IDs.
+static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Insert a request one hour ago.
+async fn seed(
+    pool: &SqlitePool,
+    provider: &str,
+    latency_ms: i64,
+    cost_sats: Option<f64>,
+    error: Option<(i32, &str)>,
+) {
+    let correlation_id = format!(
+        "scorecard-{}",
+        CORRELATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         cost_sats, latency_ms, success, error_status, error_message) \
+         VALUES (?, ?, 'gpt-4o', ?, 0, ?, ?, ?, ?, ?)",
+    )
+    .bind(&correlation_id)
+    .bind(&timestamp)
+    .bind(provider)
+    .bind(cost_sats)
+    .bind(latency_ms)
+    .bind(error.is_none())
+    .bind(error.map(|e| e.0))
+    .bind(error.map(|e| e.1))
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+}
+
+/// Build the DB test app with a circuit breaker registry for alpha and beta.
+async fn setup_app() -> (axum::Router, SqlitePool, Arc<CircuitBreakerRegistry>) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let config = common::db_test_config();
+    let names: Vec<String> = config.providers.iter().map(|p| p.name.clone()).collect();
+    let registry = Arc::new(CircuitBreakerRegistry::new(&names));
+
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        circuit_breakers: registry.clone(),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool, registry)
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn scorecard_combines_config_history_and_circuit() {
+    let (app, pool, registry) = setup_app().await;
+    for latency in [100, 200, 300, 400] {
+        seed(&pool, "alpha", latency, Some(5.0), None).await;
+    }
+    seed(
+        &pool,
+        "alpha",
+        50,
+        None,
+        Some((502, "Provider returned 502")),
+    )
+    .await;
+    seed(&pool, "beta", 10, Some(1.0), None).await;
+
+    for _ in 0..3 {
+        registry.record_failure("alpha", "5xx", "HTTP 502");
+    }
+
+    let (status, json) = get_json(app, "/v1/providers/alpha/scorecard?range=last_24h").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["provider"], "alpha");
+
+    assert_eq!(json["config"]["input_rate_sats_per_1k"], 10.0);
+    assert_eq!(json["config"]["base_fee_sats"], 1.0);
+
+    assert_eq!(json["requests"]["total"], 5);
+    assert_eq!(json["requests"]["error"], 1);
+    assert_eq!(json["requests"]["success_rate"], 0.8);
+
+    assert_eq!(json["latency"]["p50_ms"], 200);
+    assert_eq!(json["latency"]["p95_ms"], 400);
+
+    assert_eq!(json["cost"]["total_sats"], 20.0);
+    assert_eq!(json["cost"]["avg_sats_per_request"], 5.0);
+
+    let errors = json["last_errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["status"], 502);
+
+    assert_eq!(json["circuit"]["state"], "open");
+    assert_eq!(json["circuit"]["trip_count"], 1);
+    let trips = json["circuit"]["recent_trips"].as_array().unwrap();
+    assert_eq!(trips.len(), 1);
+    assert_eq!(trips[0]["reason"], "5xx: HTTP 502");
+}
+
+#[tokio::test]
+async fn scorecard_with_no_traffic() {
+    let (app, _pool, _registry) = setup_app().await;
+
+    let (status, json) = get_json(app, "/v1/providers/beta/scorecard").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["requests"]["total"], 0);
+    assert!(json["requests"]["success_rate"].is_null());
+    assert!(json["latency"]["p50_ms"].is_null());
+    assert!(json["cost"]["avg_sats_per_request"].is_null());
+    assert_eq!(json["circuit"]["state"], "closed");
+    assert!(json["last_errors"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn scorecard_unknown_provider_returns_404() {
+    let (app, _pool, _registry) = setup_app().await;
+
+    let (status, _json) = get_json(app, "/v1/providers/nope/scorecard").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn scorecard_invalid_range_returns_400() {
+    let (app, _pool, _registry) = setup_app().await;
+
+    let (status, _json) = get_json(app, "/v1/providers/alpha/scorecard?range=forever").await;
+    assert_eq!(status, 400);
+}
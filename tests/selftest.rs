@@ -0,0 +1,60 @@
+//! Integration tests for `arbstr selftest`.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use arbstr::selftest::{self, FAILING_PROVIDER, HEALTHY_PROVIDER};
+use arbstr::storage::DbWriter;
+
+/// Serve a proxy with the selftest configuration; `failing_rate` is the
+/// error rate of the provider the circuit check expects to fail.
+async fn spawn_proxy(failing_rate: f64) -> String {
+    let healthy = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let failing = common::spawn_mock_provider(MockProviderConfig {
+        error_rate: failing_rate,
+        ..Default::default()
+    })
+    .await;
+    let config = selftest::config(&healthy, &failing, ":memory:");
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool));
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&[
+        HEALTHY_PROVIDER.to_string(),
+        FAILING_PROVIDER.to_string(),
+    ]));
+    let app = create_router(state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn selftest_passes_against_mock_providers() {
+    let target = spawn_proxy(1.0).await;
+    let report = selftest::run(&reqwest::Client::new(), &target).await;
+    assert!(report.success(), "{}", report);
+    assert_eq!(report.passed(), 9);
+    assert!(report.to_string().contains("9 passed in"));
+}
+
+#[tokio::test]
+async fn selftest_reports_failed_checks() {
+    // The "failing" provider succeeds, so the error-path checks fail
+    let target = spawn_proxy(0.0).await;
+    let report = selftest::run(&reqwest::Client::new(), &target).await;
+    let failed: Vec<&str> = report
+        .checks
+        .iter()
+        .filter(|c| c.failure.is_some())
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(failed, ["test_provider_error", "test_circuit_trips"]);
+    let text = report.to_string();
+    assert!(text.contains("= FAILURES ="), "{}", text);
+    assert!(text.contains("2 failed, 7 passed in"), "{}", text);
+}
@@ -0,0 +1,204 @@
+//! Integration tests for provider SLOs: burn-rate alerts to the webhook
+//! and SLO status in the scorecard.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::{BackoffConfig, ProviderConfig, ProviderSloConfig, SloConfig};
+use arbstr::proxy::create_router;
+use arbstr::proxy::slo::SloTracker;
+
+fn completion() -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-slo",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "ok"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+/// App routing to one provider at `upstream` with `slo` targets, alerting
+/// to `hook` after two requests.
+async fn setup_app(
+    upstream: &MockServer,
+    hook: &MockServer,
+    slo: ProviderSloConfig,
+) -> axum::Router {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", upstream.uri()),
+        backoff: Some(BackoffConfig {
+            base_ms: 1,
+            max_ms: 1,
+            ..Default::default()
+        }),
+        slo: Some(slo),
+        normalize_stream: false,
+        ..common::test_provider("upstream")
+    }];
+    config.routing.slo = SloConfig {
+        min_requests: 2,
+        webhook_url: Some(format!("{}/hook", hook.uri())),
+        ..Default::default()
+    };
+    let (mut state, _pool) = common::setup_db_test_state(config.clone()).await;
+    state.slo = Arc::new(SloTracker::new(
+        config.routing.slo.clone(),
+        &config.providers,
+        reqwest::Client::new(),
+    ));
+    create_router(state)
+}
+
+async fn hook_server() -> MockServer {
+    let hook = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&hook)
+        .await;
+    hook
+}
+
+async fn complete(app: &axum::Router) -> u16 {
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    });
+    let request = Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn scorecard(app: &axum::Router) -> serde_json::Value {
+    let request = Request::get("/v1/providers/upstream/scorecard")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = common::parse_body(app.clone().oneshot(request).await.unwrap()).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+/// Alerts POSTed to `hook`, once `count` have arrived.
+async fn alerts(hook: &MockServer, count: usize) -> Vec<serde_json::Value> {
+    for _ in 0..100 {
+        let received = hook.received_requests().await.unwrap();
+        if received.len() >= count {
+            return received
+                .iter()
+                .map(|r| serde_json::from_slice(&r.body).unwrap())
+                .collect();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("expected {} alerts", count);
+}
+
+#[tokio::test]
+async fn failing_provider_burns_its_success_budget() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .mount(&upstream)
+        .await;
+    let hook = hook_server().await;
+    let app = setup_app(
+        &upstream,
+        &hook,
+        ProviderSloConfig {
+            p95_latency_ms: None,
+            success_rate: Some(0.99),
+        },
+    )
+    .await;
+
+    assert!(complete(&app).await >= 500);
+    let body = scorecard(&app).await;
+    assert_eq!(body["slo"]["requests"], 1);
+    assert_eq!(body["slo"]["alerting"], serde_json::json!([]));
+
+    assert!(complete(&app).await >= 500);
+    let body = scorecard(&app).await;
+    let slo = &body["slo"];
+    assert_eq!(slo["success_rate_target"], 0.99);
+    assert_eq!(slo["success_rate"], 0.0);
+    assert_eq!(slo["compliant"], false);
+    assert_eq!(slo["alerting"], serde_json::json!(["success_rate"]));
+    assert!(slo["burn_rates"]["success_rate"].as_f64().unwrap() > 99.0);
+
+    let alerts = alerts(&hook, 1).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["event"], "provider_slo_burn");
+    assert_eq!(alerts[0]["provider"], "upstream");
+    assert_eq!(alerts[0]["objective"], "success_rate");
+}
+
+#[tokio::test]
+async fn slow_provider_burns_its_latency_budget() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(completion())
+                .set_delay(Duration::from_millis(100)),
+        )
+        .mount(&upstream)
+        .await;
+    let hook = hook_server().await;
+    let app = setup_app(
+        &upstream,
+        &hook,
+        ProviderSloConfig {
+            p95_latency_ms: Some(20),
+            success_rate: Some(0.99),
+        },
+    )
+    .await;
+
+    for _ in 0..2 {
+        assert_eq!(complete(&app).await, 200);
+    }
+    let body = scorecard(&app).await;
+    let slo = &body["slo"];
+    assert_eq!(slo["success_rate"], 1.0);
+    assert!(slo["p95_latency_ms"].as_u64().unwrap() >= 100, "{}", slo);
+    assert_eq!(slo["compliant"], false);
+    assert_eq!(slo["alerting"], serde_json::json!(["p95_latency"]));
+
+    let alerts = alerts(&hook, 1).await;
+    assert_eq!(alerts[0]["objective"], "p95_latency");
+    assert_eq!(alerts[0]["target"], 20.0);
+}
+
+#[tokio::test]
+async fn scorecard_omits_slo_without_targets() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let request = Request::get("/v1/providers/alpha/scorecard")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = common::parse_body(app.oneshot(request).await.unwrap()).await;
+    assert_eq!(status, 200);
+    assert!(body.get("slo").is_none(), "{}", body);
+}
@@ -24,6 +24,7 @@ fn rfc3339z(dt: &DateTime<Utc>) -> String {
 static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 /// Insert a request row into the database.
+#[allow(clippy::too_many_arguments)]
 async fn seed_request(
     pool: &SqlitePool,
     timestamp: &str,
@@ -547,3 +548,236 @@ async fn test_stats_group_by_invalid_400() {
         message
     );
 }
+
+// ──────────────────────────────────────────────────
+// Test 19: [stats] cache_ttl_secs serves repeated queries from cache
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_cache_serves_repeat_queries() {
+    let mut config = common::db_test_config();
+    config.stats.cache_ttl_secs = 60;
+    let (app, pool) = common::setup_db_test_app_with_config(config).await;
+    seed_standard_data(&pool).await;
+
+    let request = || {
+        Request::builder()
+            .uri("/v1/stats")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.headers()["cache-control"], "max-age=60");
+    let (status, body) = parse_response(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["counts"]["total"], 3);
+
+    // A new row is not visible until the entry expires
+    let now = rfc3339z(&(Utc::now() - chrono::Duration::minutes(1)));
+    seed_request(
+        &pool,
+        &now,
+        "gpt-4o",
+        "alpha",
+        true,
+        false,
+        Some(1.0),
+        None,
+        None,
+        10,
+    )
+    .await;
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert!(response.headers().contains_key("cache-control"));
+    let (_, body) = parse_response(response).await;
+    assert_eq!(body["counts"]["total"], 3);
+
+    // Each filter combination has its own entry
+    let (_, body) = get(app.clone(), "/v1/stats?range=last_7d").await;
+    assert_eq!(body["counts"]["total"], 4);
+}
+
+// ──────────────────────────────────────────────────
+// Test 20: no caching or Cache-Control by default
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_uncached_by_default() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_standard_data(&pool).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/stats")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key("cache-control"));
+
+    let now = rfc3339z(&(Utc::now() - chrono::Duration::minutes(1)));
+    seed_request(
+        &pool,
+        &now,
+        "gpt-4o",
+        "alpha",
+        true,
+        false,
+        Some(1.0),
+        None,
+        None,
+        10,
+    )
+    .await;
+    let (_, body) = get(app, "/v1/stats").await;
+    assert_eq!(body["counts"]["total"], 4);
+}
+
+// ──────────────────────────────────────────────────
+// Test 21: [stats] rollups answers long ranges from rollup tables
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_long_range_reads_rollups() {
+    let mut config = common::db_test_config();
+    config.stats.rollups = true;
+    let (app, pool) = common::setup_db_test_app_with_config(config).await;
+
+    let three_days_ago = rfc3339z(&(Utc::now() - chrono::Duration::days(3)));
+    for _ in 0..2 {
+        seed_request(
+            &pool,
+            &three_days_ago,
+            "gpt-4o",
+            "alpha",
+            true,
+            false,
+            Some(5.0),
+            Some(10),
+            Some(20),
+            100,
+        )
+        .await;
+    }
+    sqlx::query(
+        "INSERT INTO request_tags (correlation_id, key, value) \
+         SELECT correlation_id, 'team', 'a' FROM requests ORDER BY id LIMIT 1",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    arbstr::storage::rollups::refresh(&pool, Utc::now())
+        .await
+        .unwrap();
+
+    // Rolled-up rows are no longer read from the requests table
+    for table in ["requests", "request_tags"] {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let (status, body) = get(app.clone(), "/v1/stats?range=last_7d&group_by=model").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["counts"]["total"], 2);
+    assert_eq!(body["costs"]["total_cost_sats"], 10.0);
+    assert_eq!(body["performance"]["avg_latency_ms"], 100.0);
+    assert_eq!(body["models"]["gpt-4o"]["counts"]["total"], 2);
+
+    // Tag filters and tag groupings read the tag rollups
+    let (_, body) = get(app.clone(), "/v1/stats?range=last_7d&tag=team=a").await;
+    assert_eq!(body["counts"]["total"], 1);
+    assert_eq!(body["costs"]["total_cost_sats"], 5.0);
+    let (_, body) = get(app.clone(), "/v1/stats?range=last_7d&group_by=tag:team").await;
+    assert_eq!(body["tags"]["values"]["a"]["counts"]["total"], 1);
+    assert_eq!(body["tags"]["values"]["untagged"]["counts"]["total"], 1);
+
+    // Short ranges scan raw rows
+    let (_, body) = get(app, "/v1/stats?range=last_1h").await;
+    assert_eq!(body["counts"]["total"], 0);
+}
+
+// ──────────────────────────────────────────────────
+// Test 22: group_by=provider includes idle configured providers
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_group_by_provider() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_standard_data(&pool).await;
+    sqlx::query("DELETE FROM requests WHERE provider = 'beta'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, body) = get(app, "/v1/stats?group_by=provider").await;
+
+    assert_eq!(status, 200);
+    let providers = &body["providers"];
+    assert_eq!(providers["alpha"]["counts"]["total"], 2);
+    assert_eq!(providers["alpha"]["costs"]["total_cost_sats"], 30.0);
+    assert_eq!(providers["beta"]["counts"]["total"], 0);
+    assert!(body.get("models").is_none());
+}
+
+// ──────────────────────────────────────────────────
+// Test 23: group_by=policy groups unrouted requests under "none"
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_group_by_policy() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_standard_data(&pool).await;
+    sqlx::query("UPDATE requests SET policy = 'code' WHERE model = 'claude-3.5-sonnet'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, body) = get(app, "/v1/stats?group_by=policy").await;
+
+    assert_eq!(status, 200);
+    let policies = &body["policies"];
+    assert_eq!(policies["code"]["counts"]["total"], 1);
+    assert_eq!(policies["code"]["costs"]["total_cost_sats"], 20.0);
+    assert_eq!(policies["none"]["counts"]["total"], 2);
+}
+
+// ──────────────────────────────────────────────────
+// Test 24: combined group_by returns one group per combination
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_group_by_provider_and_model() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_standard_data(&pool).await;
+
+    let (status, body) = get(app, "/v1/stats?group_by=provider,model").await;
+
+    assert_eq!(status, 200);
+    let groups = body["groups"].as_array().expect("groups array");
+    assert_eq!(groups.len(), 3);
+    // Highest cost first
+    assert_eq!(groups[0]["provider"], "alpha");
+    assert_eq!(groups[0]["model"], "claude-3.5-sonnet");
+    assert_eq!(groups[0]["costs"]["total_cost_sats"], 20.0);
+    assert_eq!(groups[1]["provider"], "alpha");
+    assert_eq!(groups[1]["model"], "gpt-4o");
+    assert_eq!(groups[2]["provider"], "beta");
+    assert_eq!(groups[2]["counts"]["error"], 1);
+}
+
+// ──────────────────────────────────────────────────
+// Test 25: group_by=client, duplicates and mixing tags return 400
+// ──────────────────────────────────────────────────
+#[tokio::test]
+async fn test_stats_group_by_rejects_unsupported_combinations() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    for uri in [
+        "/v1/stats?group_by=client",
+        "/v1/stats?group_by=model,model",
+        "/v1/stats?group_by=model,tag:team",
+        "/v1/stats?group_by=provider,",
+    ] {
+        let (status, _) = get(app.clone(), uri).await;
+        assert_eq!(status, 400, "expected 400 for {}", uri);
+    }
+}
@@ -0,0 +1,59 @@
+//! Integration tests for `arbstr status`.
+
+mod common;
+
+use arbstr::proxy::CircuitBreakerRegistry;
+use arbstr::status;
+
+fn trip_circuit(registry: &CircuitBreakerRegistry, provider: &str) {
+    for _ in 0..3 {
+        registry.record_failure(provider, "5xx", "Internal Server Error");
+    }
+}
+
+#[tokio::test]
+async fn reports_healthy_then_degraded() {
+    let (app, registry) = common::setup_circuit_test_app(vec![
+        common::test_provider("alpha"),
+        common::test_provider("beta"),
+    ]);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let client = reqwest::Client::new();
+
+    let report = status::fetch(&client, &url).await.unwrap();
+    assert!(report.is_healthy());
+    assert_eq!(report.to_string(), "ok: ready, 2/2 circuits closed");
+
+    trip_circuit(&registry, "beta");
+    let report = status::fetch(&client, &url).await.unwrap();
+    assert!(!report.is_healthy());
+    assert_eq!(report.circuits["beta"].state, "open");
+    assert_eq!(
+        report.to_string(),
+        "degraded: ready, 1/2 circuits closed; open: beta (3 failures)"
+    );
+
+    // /health answers 503 once every circuit is open; the body still counts
+    trip_circuit(&registry, "alpha");
+    let report = status::fetch(&client, &url).await.unwrap();
+    assert_eq!(report.status(), "unhealthy");
+    assert!(!report.is_healthy());
+}
+
+#[tokio::test]
+async fn unreachable_proxy_is_an_error() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let err = status::fetch(
+        &reqwest::Client::new(),
+        &format!("http://127.0.0.1:{}", port),
+    )
+    .await
+    .unwrap_err();
+    assert!(format!("{:#}", err).contains("unreachable"), "{:#}", err);
+}
@@ -0,0 +1,436 @@
+//! Integration tests for streaming completion logging.
+//!
+//! Runs a mock SSE provider and verifies that once a stream ends, the
+//! request row is updated with tokens, cost, stream duration, and
+//! finish_reason -- including when the client disconnects mid-stream --
+//! that idle streams get keep-alive comments, and that `normalize_stream`
+//! providers' chunks reach the client in OpenAI's shape, and that the
+//! content filter and `x-arbstr-max-output-tokens` stop a stream and
+//! record why, withholding a crossing event split across chunks.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::routing::post;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+use arbstr::config::Config;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+
+/// Columns written by the stream completion update.
+type CompletionRow = (
+    Option<i64>,
+    Option<i64>,
+    Option<f64>,
+    Option<i64>,
+    bool,
+    Option<String>,
+    Option<String>,
+);
+
+const SSE_CHUNKS: [&str; 3] = [
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":100,\"completion_tokens\":200}}\n\n",
+    "data: [DONE]\n\n",
+];
+
+/// Ten one-token deltas, then usage and `[DONE]`.
+const LONG_SSE_CHUNKS: [&str; 12] = [
+    "data: {\"choices\":[{\"delta\":{\"content\":\"one \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"two \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"six \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"ten \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"red \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"sun \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"sky \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"sea \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"owl \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"end.\"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":100,\"completion_tokens\":10}}\n\n",
+    "data: [DONE]\n\n",
+];
+
+/// Three one-token deltas, then a fourth split across two chunks.
+const SPLIT_SSE_CHUNKS: [&str; 6] = [
+    "data: {\"choices\":[{\"delta\":{\"content\":\"one \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"two \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"six \"},\"finish_reason\":null}]}\n\ndata: {\"choices\":[{\"delta\":",
+    "{\"content\":\"ten \"},\"finish_reason\":null}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":100,\"completion_tokens\":4}}\n\n",
+    "data: [DONE]\n\n",
+];
+
+/// Start a mock provider that streams `SSE_CHUNKS`, pausing `delay` before
+/// each chunk after the first. Returns its base URL.
+async fn start_sse_provider(delay: Duration) -> String {
+    start_sse_provider_with(&SSE_CHUNKS, delay).await
+}
+
+/// [`start_sse_provider`] streaming `events` instead.
+async fn start_sse_provider_with(events: &'static [&'static str], delay: Duration) -> String {
+    let app = axum::Router::new().route(
+        "/v1/chat/completions",
+        post(move || async move {
+            let chunks = futures::stream::unfold(0usize, move |i| async move {
+                if i >= events.len() {
+                    return None;
+                }
+                if i > 0 {
+                    tokio::time::sleep(delay).await;
+                }
+                Some((
+                    Ok::<_, std::io::Error>(bytes::Bytes::from_static(events[i].as_bytes())),
+                    i + 1,
+                ))
+            });
+            axum::response::Response::builder()
+                .header("content-type", "text/event-stream")
+                .body(Body::from_stream(chunks))
+                .unwrap()
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/v1", addr)
+}
+
+/// Build an app backed by an in-memory DB and a bounded writer, routing
+/// gpt-4o to a single provider at `provider_url`.
+async fn setup_app(provider_url: &str) -> (axum::Router, SqlitePool) {
+    setup_app_with(provider_url, |_| {}).await
+}
+
+async fn setup_app_with(
+    provider_url: &str,
+    configure: impl FnOnce(&mut Config),
+) -> (axum::Router, SqlitePool) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let mut provider = common::test_provider("streamer");
+    provider.url = provider_url.to_string();
+    let mut config = Config {
+        providers: vec![provider],
+        ..common::db_test_config()
+    };
+    configure(&mut config);
+
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["streamer".to_string()])),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool)
+}
+
+fn streaming_request() -> Request<Body> {
+    Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}],"stream":true}"#,
+        ))
+        .unwrap()
+}
+
+/// Poll until the completion update has landed (stream_duration_ms set).
+async fn wait_for_completion(pool: &SqlitePool, correlation_id: &str) -> CompletionRow {
+    for _ in 0..100 {
+        let row: Option<CompletionRow> = sqlx::query_as(
+            "SELECT input_tokens, output_tokens, cost_sats, stream_duration_ms, success, \
+             error_message, finish_reason FROM requests WHERE correlation_id = ?",
+        )
+        .bind(correlation_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap();
+        if let Some(row) = row.filter(|r| r.3.is_some()) {
+            return row;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!(
+        "stream completion update never landed for {}",
+        correlation_id
+    );
+}
+
+fn correlation_id(response: &axum::response::Response) -> String {
+    response
+        .headers()
+        .get("x-arbstr-request-id")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn completed_stream_records_usage_duration_and_finish_reason() {
+    let provider_url = start_sse_provider(Duration::ZERO).await;
+    let (app, pool) = setup_app(&provider_url).await;
+
+    let response = app.oneshot(streaming_request()).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("[DONE]"));
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert_eq!(row.0, Some(100));
+    assert_eq!(row.1, Some(200));
+    // 100 * 5 / 1000 + 200 * 15 / 1000 = 3.5 sats
+    assert!((row.2.unwrap() - 3.5).abs() < 1e-9);
+    assert!(row.3.unwrap() >= 0);
+    assert!(row.4);
+    assert!(row.5.is_none());
+    assert_eq!(row.6.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn early_disconnect_still_records_completion() {
+    let provider_url = start_sse_provider(Duration::from_millis(50)).await;
+    let (app, pool) = setup_app(&provider_url).await;
+
+    let response = app.oneshot(streaming_request()).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let cid = correlation_id(&response);
+    // Client goes away before the provider finishes streaming.
+    drop(response);
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert_eq!(row.0, Some(100));
+    assert_eq!(row.1, Some(200));
+    assert!(row.2.is_some());
+    assert!(row.4);
+    assert_eq!(row.5.as_deref(), Some("client_disconnected"));
+    assert_eq!(row.6.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn stream_throughput_is_recorded_and_reported_per_provider() {
+    let provider_url = start_sse_provider(Duration::from_millis(100)).await;
+    let (app, pool) = setup_app(&provider_url).await;
+
+    let response = app.clone().oneshot(streaming_request()).await.unwrap();
+    let cid = correlation_id(&response);
+    axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    wait_for_completion(&pool, &cid).await;
+
+    // 200 completion tokens between the first and second chunk, >= 100ms apart
+    let (tps,): (Option<f64>,) =
+        sqlx::query_as("SELECT tokens_per_second FROM requests WHERE correlation_id = ?")
+            .bind(&cid)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    let tps = tps.expect("tokens_per_second recorded");
+    assert!(tps > 0.0 && tps <= 2000.0, "{}", tps);
+
+    let response = app
+        .oneshot(
+            Request::get("/v1/stats?since=2020-01-01T00:00:00Z&until=2100-01-01T00:00:00Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, stats) = common::parse_body(response).await;
+    let throughput = &stats["performance"]["throughput"]["streamer"];
+    assert_eq!(throughput["streams"], 1);
+    assert!((throughput["avg_tokens_per_second"].as_f64().unwrap() - tps).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn idle_stream_gets_keepalive_comments_between_events() {
+    let provider_url = start_sse_provider(Duration::from_millis(1300)).await;
+    let (app, pool) = setup_app_with(&provider_url, |config| {
+        config.server.sse_keepalive_secs = Some(1)
+    })
+    .await;
+
+    let response = app.oneshot(streaming_request()).await.unwrap();
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+
+    let events: Vec<&str> = body.split("\n\n").filter(|e| !e.is_empty()).collect();
+    assert_eq!(
+        events.iter().filter(|e| **e == ": keep-alive").count(),
+        2,
+        "{}",
+        body
+    );
+    assert_eq!(events[0], SSE_CHUNKS[0].trim_end());
+    assert_eq!(events[1], ": keep-alive");
+    assert_eq!(events[2], SSE_CHUNKS[1].trim_end());
+
+    // The comments never reach the usage observer
+    let row = wait_for_completion(&pool, &cid).await;
+    assert_eq!(row.0, Some(100));
+    assert_eq!(row.1, Some(200));
+    assert!(row.4);
+    assert_eq!(row.6.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn normalized_stream_reaches_client_in_openai_shape() {
+    let provider_url = start_sse_provider(Duration::ZERO).await;
+    let (app, pool) = setup_app_with(&provider_url, |config| {
+        config.providers[0].normalize_stream = true
+    })
+    .await;
+
+    let response = app.oneshot(streaming_request()).await.unwrap();
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+    let chunks: Vec<serde_json::Value> = body
+        .split("\n\n")
+        .filter_map(|e| e.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str(data).ok())
+        .filter(|chunk: &serde_json::Value| chunk.get("choices").is_some())
+        .collect();
+
+    assert_eq!(chunks.len(), 2, "{}", body);
+    assert_eq!(chunks[0]["object"], "chat.completion.chunk");
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    assert_eq!(chunks[0]["id"], chunks[1]["id"]);
+    assert_eq!(chunks[1]["usage"]["total_tokens"], 300);
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert_eq!(row.0, Some(100));
+    assert_eq!(row.6.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn content_filter_stops_stream_and_records_rule() {
+    let provider_url = start_sse_provider(Duration::ZERO).await;
+    let (app, pool) = setup_app_with(&provider_url, |config| {
+        config.content_filter = Some(arbstr::config::ContentFilterConfig {
+            blocklist: vec!["hello".to_string()],
+            patterns: vec![],
+        })
+    })
+    .await;
+
+    let response = app.oneshot(streaming_request()).await.unwrap();
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(!body.contains("Hello"), "{}", body);
+    assert!(!body.contains("[DONE]"), "{}", body);
+    let error: serde_json::Value =
+        serde_json::from_str(body.trim().strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(error["error"]["type"], "content_policy_violation");
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert!(!row.4);
+    assert_eq!(row.5.as_deref(), Some("content_filter: blocklist:hello"));
+    assert_eq!(row.6.as_deref(), Some("content_filter"));
+}
+
+#[tokio::test]
+async fn output_budget_aborts_stream_and_prorates_cost() {
+    let provider_url = start_sse_provider_with(&LONG_SSE_CHUNKS, Duration::ZERO).await;
+    let (app, pool) = setup_app(&provider_url).await;
+
+    let mut request = streaming_request();
+    request
+        .headers_mut()
+        .insert("x-arbstr-max-output-tokens", "3".parse().unwrap());
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+
+    // Three deltas, then the closing chunk in place of the fourth
+    assert!(body.contains("\"six \""), "{}", body);
+    assert!(!body.contains("\"ten \""), "{}", body);
+    assert!(body.contains("\"finish_reason\":\"length\""), "{}", body);
+    assert!(body.trim_end().ends_with("data: [DONE]"), "{}", body);
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert!(row.4);
+    assert_eq!(row.6.as_deref(), Some("max_output_tokens"));
+    // Four deltas generated; the prompt "hi" estimates to one token
+    assert_eq!(row.0, Some(1));
+    assert_eq!(row.1, Some(4));
+    // 1 * 5 / 1000 + 4 * 15 / 1000
+    assert!((row.2.unwrap() - 0.065).abs() < 1e-9, "{:?}", row.2);
+
+    let mut request = streaming_request();
+    request
+        .headers_mut()
+        .insert("x-arbstr-max-output-tokens", "none".parse().unwrap());
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn output_budget_withholds_event_split_across_chunks() {
+    let provider_url = start_sse_provider_with(&SPLIT_SSE_CHUNKS, Duration::from_millis(20)).await;
+    let (app, pool) = setup_app(&provider_url).await;
+
+    let mut request = streaming_request();
+    request
+        .headers_mut()
+        .insert("x-arbstr-max-output-tokens", "3".parse().unwrap());
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let cid = correlation_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), 1_048_576)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+
+    // No half of the crossing event reaches the client: every event is
+    // whole, and the stream closes with the length chunk and [DONE]
+    assert!(body.contains("\"six \""), "{}", body);
+    assert!(!body.contains("\"ten \""), "{}", body);
+    let events: Vec<&str> = body.split("\n\n").filter(|e| !e.is_empty()).collect();
+    for event in &events {
+        let data = event.strip_prefix("data: ").expect(event);
+        assert!(
+            data == "[DONE]" || serde_json::from_str::<serde_json::Value>(data).is_ok(),
+            "{}",
+            body
+        );
+    }
+    assert!(
+        events
+            .iter()
+            .any(|e| e.contains("\"finish_reason\":\"length\"")),
+        "{}",
+        body
+    );
+    assert_eq!(events[events.len() - 1], "data: [DONE]");
+
+    let row = wait_for_completion(&pool, &cid).await;
+    assert_eq!(row.6.as_deref(), Some("max_output_tokens"));
+    assert_eq!(row.1, Some(4));
+}
@@ -0,0 +1,126 @@
+//! Integration tests for `logging.mode = "strict"`: completed requests wait
+//! for their billing record and are rejected when it can't be saved.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{LoggingMode, ProviderConfig};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-strict",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(server: &MockServer, mode: LoggingMode) -> (axum::Router, SqlitePool, DbWriter) {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.logging.mode = mode;
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    let writer = DbWriter::new(pool.clone());
+    state.db_writer = Some(writer.clone());
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    (create_router(state), pool, writer)
+}
+
+async fn complete(app: &axum::Router) -> (StatusCode, serde_json::Value, String) {
+    let body = r#"{"model": "gpt-4o", "messages": [{"role": "user", "content": "hi"}]}"#;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let id = response.headers()["x-arbstr-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let (status, json) = common::parse_body(response).await;
+    (status, json, id)
+}
+
+#[tokio::test]
+async fn strict_mode_records_before_responding() {
+    let server = mock_provider().await;
+    let (app, pool, writer) = setup_app(&server, LoggingMode::Strict).await;
+
+    let (status, _, id) = complete(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    // No waiting for the writer: the row is there once the response is
+    let cost: Option<f64> =
+        sqlx::query_scalar("SELECT cost_sats FROM requests WHERE correlation_id = ?")
+            .bind(&id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(cost.unwrap() > 0.0);
+
+    let stats = writer.confirmed_stats();
+    assert_eq!((stats.written, stats.failed), (1, 0));
+}
+
+#[tokio::test]
+async fn strict_mode_rejects_unrecorded_requests() {
+    let server = mock_provider().await;
+    let (app, pool, writer) = setup_app(&server, LoggingMode::Strict).await;
+    sqlx::query("DROP TABLE requests")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, json, _) = complete(&app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(
+        json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("could not be recorded"),
+        "{}",
+        json
+    );
+    assert_eq!(writer.confirmed_stats().failed, 1);
+}
+
+#[tokio::test]
+async fn best_effort_mode_ignores_write_failures() {
+    let server = mock_provider().await;
+    let (app, pool, writer) = setup_app(&server, LoggingMode::BestEffort).await;
+    sqlx::query("DROP TABLE requests")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, json, _) = complete(&app).await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+    assert_eq!(writer.confirmed_stats().written, 0);
+}
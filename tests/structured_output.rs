@@ -0,0 +1,226 @@
+//! Integration tests for `response_format` routing, pass-through, and the
+//! proxy-side structured output check with one re-ask.
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+use arbstr::config::Config;
+
+type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+/// Start a provider answering with `replies` in turn (the last one
+/// repeats), recording each request body.
+async fn start_provider(replies: &'static [&'static str]) -> (String, Received) {
+    use axum::{routing::post, Json, Router};
+
+    let received: Received = Default::default();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let seen = received.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            let calls = calls.clone();
+            async move {
+                seen.lock().unwrap().push(body);
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                let content = replies[n.min(replies.len() - 1)];
+                Json(serde_json::json!({
+                    "id": "chatcmpl-mock",
+                    "object": "chat.completion",
+                    "choices": [{
+                        "message": {"role": "assistant", "content": content},
+                        "index": 0,
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {
+                        "prompt_tokens": 10,
+                        "completion_tokens": 5,
+                        "total_tokens": 15
+                    }
+                }))
+            }
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock provider");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.ok();
+    });
+
+    (format!("http://127.0.0.1:{}/v1", addr.port()), received)
+}
+
+/// A cheap provider without structured output and a pricier one with it.
+async fn config(
+    cheap: &'static [&'static str],
+    structured: &'static [&'static str],
+) -> (Config, Received, Received) {
+    let (cheap_url, cheap_received) = start_provider(cheap).await;
+    let (structured_url, structured_received) = start_provider(structured).await;
+
+    let mut plain = common::test_provider("plain");
+    plain.url = cheap_url;
+    plain.output_rate = 5.0;
+    let mut json = common::test_provider("json");
+    json.url = structured_url;
+    json.output_rate = 20.0;
+    json.structured_output = true;
+
+    let mut config = common::db_test_config();
+    config.providers = vec![plain, json];
+    (config, cheap_received, structured_received)
+}
+
+fn schema_format() -> serde_json::Value {
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "answer",
+            "strict": true,
+            "schema": {
+                "type": "object",
+                "properties": {"answer": {"type": "integer"}},
+                "required": ["answer"],
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+async fn complete(
+    app: &axum::Router,
+    response_format: Option<serde_json::Value>,
+) -> (u16, http::HeaderMap, serde_json::Value) {
+    let mut body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "What is 6 * 7?"}]
+    });
+    if let Some(format) = response_format {
+        body["response_format"] = format;
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let headers = response.headers().clone();
+    let (status, json) = common::parse_body(response).await;
+    (status.as_u16(), headers, json)
+}
+
+async fn recent(app: &axum::Router) -> Vec<serde_json::Value> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/requests/recent")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, body) = common::parse_body(response).await;
+    body["data"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn response_format_routes_to_structured_output_providers() {
+    let (config, plain, json) = config(&["42"], &[r#"{"answer": 42}"#]).await;
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    // Plain requests still go to the cheapest provider
+    let (status, headers, _) = complete(&app, None).await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-arbstr-provider"], "plain");
+
+    let (status, headers, _) = complete(&app, Some(schema_format())).await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-arbstr-provider"], "json");
+    // No proxy-side check unless enabled
+    assert!(headers.get("x-arbstr-structured-output").is_none());
+
+    assert_eq!(plain.lock().unwrap().len(), 1);
+    let received = json.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["response_format"], schema_format());
+}
+
+#[tokio::test]
+async fn response_format_without_capable_provider_is_rejected() {
+    let (mut config, _, _) = config(&["42"], &["42"]).await;
+    config.providers.retain(|p| p.name == "plain");
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    let (status, _, body) = complete(&app, Some(serde_json::json!({"type": "json_object"}))).await;
+    assert_eq!(status, 400);
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("structured_output"));
+
+    let (status, _, _) = complete(&app, Some(serde_json::json!({"type": "json_schema"}))).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn non_conforming_reply_is_reasked_once() {
+    let (mut config, _, json) = config(
+        &["42"],
+        &[r#"{"answer": "forty-two"}"#, r#"{"answer": 42}"#],
+    )
+    .await;
+    config.routing.validate_structured_output = true;
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    let (status, headers, body) = complete(&app, Some(schema_format())).await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-arbstr-structured-output"], "reasked");
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        r#"{"answer": 42}"#
+    );
+
+    // The re-ask carries the rejected reply and the reason
+    let received = json.lock().unwrap().clone();
+    assert_eq!(received.len(), 2);
+    let messages = received[1]["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert!(messages[2]["content"]
+        .as_str()
+        .unwrap()
+        .contains("$.answer"));
+    assert_eq!(received[1]["response_format"], schema_format());
+
+    let rows = recent(&app).await;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["tags"]["structured_output"], "reasked");
+    assert_eq!(rows[1]["tags"]["structured_output"], "failed");
+}
+
+#[tokio::test]
+async fn conforming_reply_passes_without_reask() {
+    let (mut config, _, json) = config(&["42"], &[r#"{"answer": 42}"#]).await;
+    config.routing.validate_structured_output = true;
+    let (app, _pool) = common::setup_db_test_app_with_config(config).await;
+
+    let (status, headers, _) = complete(&app, Some(schema_format())).await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-arbstr-structured-output"], "passed");
+    assert_eq!(json.lock().unwrap().len(), 1);
+    assert_eq!(recent(&app).await.len(), 1);
+}
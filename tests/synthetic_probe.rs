@@ -0,0 +1,98 @@
+//! Integration tests for `[routing.circuit_breaker] synthetic_probe`.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::circuit_breaker::{run_synthetic_probes, CircuitState};
+use arbstr::proxy::{AppState, CircuitBreakerRegistry};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn setup_state(server: &MockServer) -> AppState {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.routing.circuit_breaker.synthetic_probe = true;
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    state.circuit_breakers = Arc::new(
+        CircuitBreakerRegistry::new(&["upstream".to_string()])
+            .with_synthetic_probe(true, &state.config.providers),
+    );
+    state
+}
+
+/// Trip the circuit and let its 30 second open timeout run out.
+async fn open_and_expire(state: &AppState) {
+    for _ in 0..3 {
+        state
+            .circuit_breakers
+            .record_failure("upstream", "5xx", "Internal Server Error");
+    }
+    assert!(run_synthetic_probes(state).await.is_empty());
+    tokio::time::pause();
+    tokio::time::advance(Duration::from_secs(31)).await;
+    tokio::time::resume();
+}
+
+#[tokio::test]
+async fn successful_probe_closes_the_circuit() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "gpt-4o", "max_tokens": 1}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "p"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = setup_state(&server).await;
+    open_and_expire(&state).await;
+
+    // The first user request after the timeout is rejected, not the probe
+    assert!(state
+        .circuit_breakers
+        .acquire_permit("upstream")
+        .await
+        .is_err());
+
+    assert_eq!(run_synthetic_probes(&state).await, ["upstream"]);
+    assert_eq!(
+        state.circuit_breakers.state("upstream"),
+        Some(CircuitState::Closed)
+    );
+    assert!(state
+        .circuit_breakers
+        .acquire_permit("upstream")
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn failed_probe_reopens_the_circuit() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let state = setup_state(&server).await;
+    open_and_expire(&state).await;
+
+    assert_eq!(run_synthetic_probes(&state).await, ["upstream"]);
+    assert_eq!(
+        state.circuit_breakers.state("upstream"),
+        Some(CircuitState::Open)
+    );
+    let details = state.circuit_breakers.details("upstream").unwrap();
+    assert_eq!(details.last_error.unwrap().error_type, "5xx");
+    // A fresh open timeout started, so no probe is due yet
+    assert!(run_synthetic_probes(&state).await.is_empty());
+}
@@ -0,0 +1,178 @@
+//! Integration tests for policy `system_prompt_prepend` and its bypass header.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-system",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// The "compliance" policy prepends a preamble; "trusted-client" skips it.
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "compliance".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: Some("Follow the {policy} rules when using {model}.".to_string()),
+        system_prompt_bypass_token: Some("trusted-client".to_string()),
+        trim: None,
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+/// Send `messages` under the "compliance" policy; returns the status, the
+/// `x-arbstr-system-prompt` header and the messages the provider saw.
+async fn complete(
+    app: &axum::Router,
+    server: &MockServer,
+    messages: serde_json::Value,
+    bypass: Option<&str>,
+) -> (StatusCode, Option<String>, serde_json::Value) {
+    let body = serde_json::json!({"model": "gpt-4o", "messages": messages});
+    let mut request = Request::post("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .header("x-arbstr-policy", "compliance");
+    if let Some(token) = bypass {
+        request = request.header("x-arbstr-system-prompt-bypass", token);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let outcome = response
+        .headers()
+        .get("x-arbstr-system-prompt")
+        .map(|v| v.to_str().unwrap().to_string());
+    let received = server.received_requests().await.unwrap();
+    let forwarded: serde_json::Value = received.last().unwrap().body_json().unwrap();
+    (status, outcome, forwarded["messages"].clone())
+}
+
+#[tokio::test]
+async fn policy_prompt_is_prepended() {
+    let server = mock_provider().await;
+    let (app, _pool) = setup_app(&server).await;
+
+    let (status, outcome, messages) = complete(
+        &app,
+        &server,
+        serde_json::json!([{"role": "user", "content": "hi"}]),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(outcome.as_deref(), Some("injected"));
+    assert_eq!(messages.as_array().unwrap().len(), 2);
+    assert_eq!(messages[0]["role"], "system");
+    assert_eq!(
+        messages[0]["content"],
+        "Follow the compliance rules when using gpt-4o."
+    );
+    assert_eq!(messages[1]["content"], "hi");
+
+    // A client system prompt is kept, after the policy's
+    let (_, _, messages) = complete(
+        &app,
+        &server,
+        serde_json::json!([
+            {"role": "system", "content": "Answer in French."},
+            {"role": "user", "content": "hi"}
+        ]),
+        None,
+    )
+    .await;
+    assert_eq!(messages.as_array().unwrap().len(), 2);
+    assert_eq!(
+        messages[0]["content"],
+        "Follow the compliance rules when using gpt-4o.\n\nAnswer in French."
+    );
+}
+
+#[tokio::test]
+async fn trusted_client_can_bypass_the_prompt() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+    let user_only = serde_json::json!([{"role": "user", "content": "hi"}]);
+
+    let (status, outcome, messages) =
+        complete(&app, &server, user_only.clone(), Some("trusted-client")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(outcome.as_deref(), Some("bypassed"));
+    assert_eq!(messages, user_only);
+
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for _ in 0..100 {
+        tags = sqlx::query_as("SELECT key, value FROM request_tags")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        if !tags.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        tags,
+        vec![("system_prompt".to_string(), "bypassed".to_string())]
+    );
+
+    // A wrong token is ignored
+    let (_, outcome, messages) = complete(&app, &server, user_only, Some("guess")).await;
+    assert_eq!(outcome.as_deref(), Some("injected"));
+    assert_eq!(messages[0]["role"], "system");
+}
@@ -0,0 +1,185 @@
+//! Integration tests for cost allocation tags.
+//!
+//! Seeds requests with rows in `request_tags` and verifies tag filtering and
+//! grouping on GET /v1/stats and GET /v1/requests, plus header validation on
+//! POST /v1/chat/completions.
+
+mod common;
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// Insert a request row plus its tags.
+async fn seed_tagged_request(
+    pool: &SqlitePool,
+    correlation_id: &str,
+    model: &str,
+    cost_sats: f64,
+    tags: &[(&str, &str)],
+) {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::minutes(5)).to_rfc3339();
+    sqlx::query(
+        "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+         cost_sats, latency_ms, success) VALUES (?, ?, ?, 'alpha', 0, ?, 100, 1)",
+    )
+    .bind(correlation_id)
+    .bind(&timestamp)
+    .bind(model)
+    .bind(cost_sats)
+    .execute(pool)
+    .await
+    .expect("Failed to seed request");
+
+    for (key, value) in tags {
+        sqlx::query("INSERT INTO request_tags (correlation_id, key, value) VALUES (?, ?, ?)")
+            .bind(correlation_id)
+            .bind(key)
+            .bind(value)
+            .execute(pool)
+            .await
+            .expect("Failed to seed tag");
+    }
+}
+
+async fn seed_tags_data(pool: &SqlitePool) {
+    seed_tagged_request(
+        pool,
+        "tag-1",
+        "gpt-4o",
+        10.0,
+        &[("team", "search"), ("env", "prod")],
+    )
+    .await;
+    seed_tagged_request(
+        pool,
+        "tag-2",
+        "gpt-4o",
+        20.0,
+        &[("team", "search"), ("env", "dev")],
+    )
+    .await;
+    seed_tagged_request(pool, "tag-3", "gpt-4o-mini", 5.0, &[("team", "ads")]).await;
+    seed_tagged_request(pool, "tag-4", "gpt-4o", 1.0, &[]).await;
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn stats_filter_by_tag() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_tags_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats?range=last_24h&tag=team=search").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["counts"]["total"], 2);
+    assert_eq!(json["costs"]["total_cost_sats"], 30.0);
+}
+
+#[tokio::test]
+async fn stats_group_by_tag() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_tags_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats?range=last_24h&group_by=tag:team").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["tags"]["key"], "team");
+    let values = &json["tags"]["values"];
+    assert_eq!(values["search"]["counts"]["total"], 2);
+    assert_eq!(values["search"]["costs"]["total_cost_sats"], 30.0);
+    assert_eq!(values["ads"]["counts"]["total"], 1);
+    assert_eq!(values["untagged"]["counts"]["total"], 1);
+}
+
+#[tokio::test]
+async fn stats_group_by_tag_with_tag_filter() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_tags_data(&pool).await;
+
+    let (status, json) = get_json(
+        app,
+        "/v1/stats?range=last_24h&group_by=tag:env&tag=team=search",
+    )
+    .await;
+    assert_eq!(status, 200);
+    let values = &json["tags"]["values"];
+    assert_eq!(values["prod"]["counts"]["total"], 1);
+    assert_eq!(values["dev"]["counts"]["total"], 1);
+    assert!(values.get("untagged").is_none());
+}
+
+#[tokio::test]
+async fn stats_invalid_tag_filter_returns_400() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let (status, _) = get_json(app, "/v1/stats?tag=team").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn stats_invalid_group_by_tag_returns_400() {
+    let (app, _pool) = common::setup_db_test_app().await;
+    let (status, _) = get_json(app, "/v1/stats?group_by=tag:").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn logs_filter_by_tag_and_include_tags() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_tags_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/requests?range=last_24h&tag=env=prod").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["total"], 1);
+    let entry = &json["data"][0];
+    assert_eq!(entry["tags"]["team"], "search");
+    assert_eq!(entry["tags"]["env"], "prod");
+}
+
+#[tokio::test]
+async fn logs_untagged_entry_omits_tags() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_tags_data(&pool).await;
+
+    let (status, json) =
+        get_json(app, "/v1/requests?range=last_24h&sort=cost_sats&order=asc").await;
+    assert_eq!(status, 200);
+    // Cheapest row (cost 1.0) is the untagged one
+    assert!(json["data"][0].get("tags").is_none());
+}
+
+#[tokio::test]
+async fn chat_rejects_malformed_tags_header() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello there"}]
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-arbstr-tags", "team")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert!(response.headers().contains_key("x-arbstr-request-id"));
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 400);
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("key=value"));
+}
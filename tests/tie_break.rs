@@ -0,0 +1,98 @@
+//! Integration tests for `[routing] tie_breakers`.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::TieBreaker;
+use arbstr::mock_provider::{LatencyDistribution, MockProviderConfig};
+use arbstr::proxy::{create_router, CircuitBreakerRegistry, ProviderClients, TieBreakTracker};
+use arbstr::router::Router as ProviderRouter;
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+
+/// Providers `slow` (declared first) and `fast` at the same rates.
+async fn setup_app(tie_breakers: &[TieBreaker]) -> axum::Router {
+    let mut slow = common::test_provider("slow");
+    slow.url = common::spawn_mock_provider(MockProviderConfig {
+        latency: LatencyDistribution::Fixed(Duration::from_millis(150)),
+        ..Default::default()
+    })
+    .await;
+    let mut fast = common::test_provider("fast");
+    fast.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    let mut config = common::db_test_config();
+    config.providers = vec![slow, fast];
+    for provider in &mut config.providers {
+        provider.models = vec!["gpt-4o".to_string()];
+    }
+    config.routing.tie_breakers = tie_breakers.to_vec();
+
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    let names: Vec<String> = state
+        .config
+        .providers
+        .iter()
+        .map(|p| p.name.clone())
+        .collect();
+    state.router = Arc::new(ProviderRouter::new(
+        state.config.providers.clone(),
+        state.config.policies.rules.clone(),
+        state.config.policies.default_strategy.clone(),
+    ));
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&names));
+    state.provider_clients = Arc::new(ProviderClients::new(&state.config.providers, None).unwrap());
+    state.tie_breaks = Arc::new(TieBreakTracker::new(
+        &state.config.routing.tie_breakers,
+        &state.config.providers,
+    ));
+    create_router(state)
+}
+
+/// Send a chat completion and return the provider that served it.
+async fn served_by(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()["x-arbstr-provider"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn latency_tie_breaker_moves_traffic_to_faster_provider() {
+    let app = setup_app(&[TieBreaker::Latency]).await;
+
+    // Neither is measured: declaration order. Then the unmeasured provider
+    // gets its turn, and from there the faster one wins the tie.
+    let mut served = Vec::new();
+    for _ in 0..4 {
+        served.push(served_by(&app).await);
+    }
+    assert_eq!(served, ["slow", "fast", "fast", "fast"]);
+}
+
+#[tokio::test]
+async fn without_tie_breakers_declaration_order_wins() {
+    let app = setup_app(&[]).await;
+    for _ in 0..3 {
+        assert_eq!(served_by(&app).await, "slow");
+    }
+}
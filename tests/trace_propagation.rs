@@ -0,0 +1,177 @@
+//! Integration tests for W3C `traceparent` and `x-request-id` propagation.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use arbstr::config::Config;
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use arbstr::storage::DbWriter;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-trace",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// App backed by an in-memory DB, routing gpt-4o to `server`.
+async fn setup_app(server: &MockServer) -> (axum::Router, SqlitePool) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let mut provider = common::test_provider("traced");
+    provider.url = format!("{}/v1", server.uri());
+    let config = Config {
+        providers: vec![provider],
+        ..common::db_test_config()
+    };
+
+    let state = AppState {
+        db: Some(pool.clone()),
+        read_db: Some(pool.clone()),
+        db_writer: Some(DbWriter::new(pool.clone())),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&["traced".to_string()])),
+        ..common::setup_test_state(config)
+    };
+    (create_router(state), pool)
+}
+
+fn chat_request(headers: &[(&str, &str)]) -> Request<Body> {
+    let mut builder =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder
+        .body(Body::from(
+            r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#,
+        ))
+        .unwrap()
+}
+
+fn header<'a>(headers: &'a http::HeaderMap, name: &str) -> &'a str {
+    headers.get(name).unwrap().to_str().unwrap()
+}
+
+/// Poll until the request row for `correlation_id` is written.
+async fn wait_for_row(pool: &SqlitePool, correlation_id: &str) -> (Option<String>, Option<String>) {
+    for _ in 0..100 {
+        let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT trace_id, client_request_id FROM requests WHERE correlation_id = ?",
+        )
+        .bind(correlation_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap();
+        if let Some(row) = row {
+            return row;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("request row never written for {}", correlation_id);
+}
+
+#[tokio::test]
+async fn incoming_trace_is_continued_and_recorded() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let response = app
+        .clone()
+        .oneshot(chat_request(&[
+            ("traceparent", TRACEPARENT),
+            ("x-request-id", "client-req-42"),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(header(response.headers(), "traceparent"), TRACEPARENT);
+    assert_eq!(header(response.headers(), "x-request-id"), "client-req-42");
+    let correlation_id = header(response.headers(), "x-arbstr-request-id").to_string();
+
+    // Provider sees the same trace with arbstr as the parent span
+    let requests = server.received_requests().await.unwrap();
+    let upstream = header(&requests[0].headers, "traceparent");
+    let parts: Vec<&str> = upstream.split('-').collect();
+    assert_eq!(parts[1], TRACE_ID);
+    assert_ne!(parts[2], "00f067aa0ba902b7");
+    assert_eq!(parts[3], "01");
+    assert_eq!(
+        header(&requests[0].headers, "x-request-id"),
+        "client-req-42"
+    );
+
+    let row = wait_for_row(&pool, &correlation_id).await;
+    assert_eq!(
+        row,
+        (
+            Some(TRACE_ID.to_string()),
+            Some("client-req-42".to_string())
+        )
+    );
+
+    let response = app
+        .oneshot(
+            Request::get(format!(
+                "/v1/requests?since=2020-01-01T00:00:00Z&until=2100-01-01T00:00:00Z&trace_id={}",
+                TRACE_ID
+            ))
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, json) = common::parse_body(response).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["data"][0]["trace_id"], TRACE_ID);
+    assert_eq!(json["data"][0]["request_id"], "client-req-42");
+}
+
+#[tokio::test]
+async fn trace_is_generated_when_absent() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(&server).await;
+
+    let response = app.oneshot(chat_request(&[])).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let correlation_id = header(response.headers(), "x-arbstr-request-id").to_string();
+    // x-request-id defaults to the correlation ID
+    assert_eq!(header(response.headers(), "x-request-id"), correlation_id);
+
+    let echoed = header(response.headers(), "traceparent").to_string();
+    let requests = server.received_requests().await.unwrap();
+    let upstream = header(&requests[0].headers, "traceparent");
+    assert_eq!(upstream, echoed);
+    let trace_id = upstream.split('-').nth(1).unwrap().to_string();
+    assert_eq!(trace_id.len(), 32);
+    assert_eq!(header(&requests[0].headers, "x-request-id"), correlation_id);
+
+    let row = wait_for_row(&pool, &correlation_id).await;
+    assert_eq!(row, (Some(trace_id), Some(correlation_id.clone())));
+}
@@ -0,0 +1,234 @@
+//! Integration tests for `[policies.rules.trim]`: conversations beyond the
+//! context window lose their oldest messages, or have them summarized.
+
+mod common;
+
+use std::time::Duration;
+
+use arbstr::config::{PolicyRule, ProviderConfig, Tier, TrimConfig, TrimStrategy};
+use arbstr::proxy::create_router;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::{Request, StatusCode};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn completion(content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-trim",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+/// Answers gpt-4o-mini (the summary model) with a summary, anything else
+/// with "ok".
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "gpt-4o-mini"}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(completion("They settled on plan B.")),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("ok")))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn setup_app(server: &MockServer, trim: TrimConfig) -> (axum::Router, SqlitePool) {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        models: vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
+        ..common::test_provider("upstream")
+    }];
+    config.policies.rules = vec![PolicyRule {
+        name: "long-chat".to_string(),
+        allowed_models: vec![],
+        strategy: "lowest_cost".to_string(),
+        max_sats_per_1k_output: None,
+        keywords: vec![],
+        backoff: None,
+        active_hours: None,
+        days: vec![],
+        utc_offset: None,
+        off_hours_tier: Tier::Local,
+        allowed_regions: vec![],
+        validate: None,
+        max_output_tokens: None,
+        default_max_tokens: None,
+        system_prompt_prepend: None,
+        system_prompt_bypass_token: None,
+        trim: Some(trim),
+        min_prompt_tokens: None,
+        max_prompt_tokens: None,
+        tier: None,
+        accumulate: false,
+        compress: None,
+        response_headers: Default::default(),
+        omit_headers: vec![],
+        dataset_capture: None,
+    }];
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    (create_router(state), pool)
+}
+
+/// A system message and six ~100-token turns, with a 100-token reply.
+async fn long_chat(app: &axum::Router) -> StatusCode {
+    let mut messages = vec![serde_json::json!({"role": "system", "content": "Be brief."})];
+    for i in 0..6 {
+        let role = if i % 2 == 0 { "user" } else { "assistant" };
+        messages.push(
+            serde_json::json!({"role": role, "content": format!("{}{}", i, "x".repeat(399))}),
+        );
+    }
+    let body = serde_json::json!({"model": "gpt-4o", "max_tokens": 100, "messages": messages});
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-arbstr-policy", "long-chat")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response.status()
+}
+
+/// The messages of the last gpt-4o request the provider saw.
+async fn forwarded_messages(server: &MockServer) -> Vec<serde_json::Value> {
+    let received = server.received_requests().await.unwrap();
+    let body: serde_json::Value = received
+        .iter()
+        .rev()
+        .map(|r| r.body_json::<serde_json::Value>().unwrap())
+        .find(|b| b["model"] == "gpt-4o")
+        .unwrap();
+    body["messages"].as_array().unwrap().clone()
+}
+
+/// Models of the logged rows, once `rows` have been logged, and the
+/// request's tags (shared by rows with the same request ID).
+async fn logged(pool: &SqlitePool, rows: usize) -> (Vec<String>, Vec<(String, String)>) {
+    for _ in 0..100 {
+        let models: Vec<String> = sqlx::query_scalar("SELECT model FROM requests ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        if models.len() >= rows {
+            // Tags are written with their row
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let tags = sqlx::query_as("SELECT key, value FROM request_tags ORDER BY key, value")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+            return (models, tags);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("requests were never logged");
+}
+
+#[tokio::test]
+async fn oldest_messages_are_dropped_to_fit() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        TrimConfig {
+            context_tokens: 450,
+            strategy: TrimStrategy::DropOldest,
+            summary_model: None,
+        },
+    )
+    .await;
+
+    assert_eq!(long_chat(&app).await, StatusCode::OK);
+    let messages = forwarded_messages(&server).await;
+    let firsts: Vec<String> = messages
+        .iter()
+        .map(|m| m["content"].as_str().unwrap()[..1].to_string())
+        .collect();
+    assert_eq!(
+        firsts,
+        vec!["B", "3", "4", "5"],
+        "system kept, turns 0-2 dropped"
+    );
+
+    let (models, tags) = logged(&pool, 1).await;
+    assert_eq!(models.len(), 1);
+    assert_eq!(
+        tags,
+        vec![
+            ("trim".to_string(), "dropped".to_string()),
+            ("trimmed_messages".to_string(), "3".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dropped_messages_can_be_summarized() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        TrimConfig {
+            context_tokens: 1000,
+            strategy: TrimStrategy::Summarize,
+            summary_model: Some("gpt-4o-mini".to_string()),
+        },
+    )
+    .await;
+
+    assert_eq!(long_chat(&app).await, StatusCode::OK);
+    let messages = forwarded_messages(&server).await;
+    assert_eq!(messages[0]["content"], "Be brief.");
+    assert_eq!(messages[1]["role"], "system");
+    assert_eq!(
+        messages[1]["content"],
+        "Summary of the earlier conversation:\nThey settled on plan B."
+    );
+    assert_eq!(messages.len(), 2 + 3, "turns 3-5 kept");
+
+    // The summary call is logged under the same request ID
+    let (models, tags) = logged(&pool, 2).await;
+    assert_eq!(models, vec!["gpt-4o-mini", "gpt-4o"]);
+    assert!(tags.contains(&("trim".to_string(), "summary".to_string())));
+    assert!(tags.contains(&("trim".to_string(), "summarized".to_string())));
+}
+
+#[tokio::test]
+async fn short_conversations_are_untouched() {
+    let server = mock_provider().await;
+    let (app, pool) = setup_app(
+        &server,
+        TrimConfig {
+            context_tokens: 100_000,
+            strategy: TrimStrategy::DropOldest,
+            summary_model: None,
+        },
+    )
+    .await;
+
+    assert_eq!(long_chat(&app).await, StatusCode::OK);
+    assert_eq!(forwarded_messages(&server).await.len(), 7);
+    let (_, tags) = logged(&pool, 1).await;
+    assert!(tags.is_empty());
+}
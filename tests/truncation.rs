@@ -0,0 +1,120 @@
+//! Integration tests for the GET /v1/stats/truncation endpoint.
+
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// Global counter for generating unique correlation IDs.
+static CORRELATION_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Insert `n` requests one hour ago with the given outcome.
+async fn seed(
+    pool: &SqlitePool,
+    n: usize,
+    model: &str,
+    provider: &str,
+    success: bool,
+    finish_reason: Option<&str>,
+) {
+    let timestamp = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    for _ in 0..n {
+        let correlation_id = format!(
+            "trunc-{}",
+            CORRELATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        sqlx::query(
+            "INSERT INTO requests (correlation_id, timestamp, model, provider, streaming, \
+             latency_ms, success, finish_reason) VALUES (?, ?, ?, ?, 0, 100, ?, ?)",
+        )
+        .bind(&correlation_id)
+        .bind(&timestamp)
+        .bind(model)
+        .bind(provider)
+        .bind(success)
+        .bind(finish_reason)
+        .execute(pool)
+        .await
+        .expect("Failed to seed request");
+    }
+}
+
+/// alpha truncates 1 in 4 gpt-4o completions, beta 3 in 4.
+async fn seed_truncation_data(pool: &SqlitePool) {
+    seed(pool, 3, "gpt-4o", "alpha", true, Some("stop")).await;
+    seed(pool, 1, "gpt-4o", "alpha", true, Some("length")).await;
+    seed(pool, 1, "gpt-4o", "beta", true, Some("stop")).await;
+    seed(pool, 3, "gpt-4o", "beta", true, Some("length")).await;
+    seed(pool, 2, "gpt-4o-mini", "beta", true, Some("tool_calls")).await;
+    // Excluded: errors and rows without a recorded finish_reason
+    seed(pool, 2, "gpt-4o", "alpha", false, None).await;
+    seed(pool, 2, "gpt-4o", "beta", true, None).await;
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    common::parse_body(response).await
+}
+
+#[tokio::test]
+async fn truncation_rates_per_model_provider() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_truncation_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/truncation?range=last_24h").await;
+    assert_eq!(status, 200);
+
+    let totals = &json["totals"];
+    assert_eq!(totals["completions"], 10);
+    assert_eq!(totals["truncated"], 4);
+    assert_eq!(totals["finish_reasons"]["tool_calls"], 2);
+
+    let groups = json["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0]["model"], "gpt-4o");
+    assert_eq!(groups[0]["provider"], "beta");
+    assert_eq!(groups[0]["truncation_rate"], 0.75);
+    assert_eq!(groups[1]["provider"], "alpha");
+    assert_eq!(groups[1]["truncation_rate"], 0.25);
+    assert_eq!(groups[2]["model"], "gpt-4o-mini");
+    assert_eq!(groups[2]["truncated"], 0);
+}
+
+#[tokio::test]
+async fn truncation_filter_by_provider() {
+    let (app, pool) = common::setup_db_test_app().await;
+    seed_truncation_data(&pool).await;
+
+    let (status, json) = get_json(app, "/v1/stats/truncation?provider=alpha").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["totals"]["completions"], 4);
+    let groups = json["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["finish_reasons"]["length"], 1);
+}
+
+#[tokio::test]
+async fn truncation_empty_range() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let (status, json) = get_json(app, "/v1/stats/truncation").await;
+    assert_eq!(status, 200);
+    assert_eq!(json["totals"]["completions"], 0);
+    assert_eq!(json["totals"]["truncation_rate"], 0.0);
+    assert!(json["groups"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn truncation_unknown_model_returns_404() {
+    let (app, _pool) = common::setup_db_test_app().await;
+
+    let (status, _json) = get_json(app, "/v1/stats/truncation?model=nope").await;
+    assert_eq!(status, 404);
+}
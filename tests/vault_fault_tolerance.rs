@@ -76,7 +76,7 @@ async fn start_mock_vault_for_reconciliation(
                             Instant::now(),
                         ));
                         let status_code = axum::http::StatusCode::from_u16(status).unwrap();
-                        if status >= 200 && status < 300 {
+                        if (200..300).contains(&status) {
                             (
                                 status_code,
                                 axum::Json(
@@ -107,7 +107,7 @@ async fn start_mock_vault_for_reconciliation(
                             Instant::now(),
                         ));
                         let status_code = axum::http::StatusCode::from_u16(status).unwrap();
-                        if status >= 200 && status < 300 {
+                        if (200..300).contains(&status) {
                             (
                                 status_code,
                                 axum::Json(serde_json::json!({"released": true})),
@@ -509,9 +509,16 @@ fn setup_vault_test_app_with_db(
 
     let config = Config {
         server: ServerConfig {
-            listen: "127.0.0.1:0".to_string(),
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
             rate_limit_rps: None,
             auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
         },
         database: None,
         vault: Some(VaultConfig {
@@ -525,15 +532,49 @@ fn setup_vault_test_app_with_db(
             url: format!("{}/v1", provider_url),
             api_key: None,
             models: vec!["gpt-4o".to_string()],
-            input_rate: 10,
-            output_rate: 30,
-            base_fee: 1,
+            input_rate: 10.0,
+            output_rate: 30.0,
+            base_fee: 1.0,
+            min_charge_sats: 0.0,
             tier: Tier::Standard,
             auto_discover: false,
+            canary: false,
+            canary_percent: 5,
+            auth_scheme: Default::default(),
+            extra_headers: Default::default(),
+            pool: None,
+            resolve: Default::default(),
+            backoff: None,
+            balance_sats: None,
+            balance_reset: None,
+            region: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            structured_output: false,
+            enabled: true,
+            maintenance_until: None,
+            slo: None,
+            normalize_stream: false,
         }],
         policies: PoliciesConfig::default(),
         logging: Default::default(),
         routing: RoutingConfig::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup: Default::default(),
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
     };
 
     let provider_names: Vec<String> = config.providers.iter().map(|p| p.name.clone()).collect();
@@ -556,6 +597,34 @@ fn setup_vault_test_app_with_db(
         read_db: Some(pool),
         db_writer: None,
         circuit_breakers: registry,
+        reputation: Default::default(),
+        tie_breaks: Default::default(),
+        route_cache: Default::default(),
+        canary: Default::default(),
+        retry_budget: Default::default(),
+        auth_quarantine: Default::default(),
+        compression: Default::default(),
+        limits: Default::default(),
+        recording: Default::default(),
+        provider_clients: Default::default(),
+        recent: Default::default(),
+        warmup: Default::default(),
+        stats_cache: Default::default(),
+        ledger: Default::default(),
+        quotas: Default::default(),
+        exchange_rate: Default::default(),
+        privacy: Default::default(),
+        client_request_ids: Default::default(),
+        quality: Default::default(),
+        maintenance: Default::default(),
+        receipts: Default::default(),
+        started_at: std::time::Instant::now(),
+        config_versions: Default::default(),
+        slo: Default::default(),
+        datasets: Default::default(),
+        debug_traces: Default::default(),
+        anomalies: Default::default(),
+        vouchers: Default::default(),
         vault: Some(vault),
     };
 
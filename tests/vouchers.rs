@@ -0,0 +1,266 @@
+//! Integration tests for prepaid request vouchers (`x-arbstr-voucher`),
+//! including vouchers standing in for the proxy's bearer token.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arbstr::config::Config;
+use arbstr::mock_provider::MockProviderConfig;
+use arbstr::proxy::vouchers::Vouchers;
+use arbstr::proxy::{create_router, CircuitBreakerRegistry, ProviderClients};
+use arbstr::router::Router as ProviderRouter;
+use arbstr::storage::DbWriter;
+use axum::body::Body;
+use http::Request;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+/// Bearer token sent to the admin endpoints.
+const ADMIN_TOKEN: &str = "operator-secret";
+
+/// One provider charging a flat 10 sats per request.
+async fn setup_app() -> (axum::Router, SqlitePool) {
+    setup_app_with_token(None).await
+}
+
+/// [`setup_app`] with the proxy behind `auth_token`.
+async fn setup_app_with_token(auth_token: Option<&str>) -> (axum::Router, SqlitePool) {
+    setup_app_with(auth_token, |_| {}).await
+}
+
+/// [`setup_app_with_token`], with `customize` applied to the config.
+async fn setup_app_with(
+    auth_token: Option<&str>,
+    customize: impl FnOnce(&mut Config),
+) -> (axum::Router, SqlitePool) {
+    let mut provider = common::test_provider("alpha");
+    provider.url = common::spawn_mock_provider(MockProviderConfig::default()).await;
+    provider.models = vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()];
+    provider.input_rate = 0.0;
+    provider.output_rate = 0.0;
+    provider.base_fee = 10.0;
+    let mut config = common::db_test_config();
+    config.providers = vec![provider];
+    config.server.auth_token = auth_token.map(str::to_string);
+    config.server.admin_token = Some(ADMIN_TOKEN.to_string());
+    customize(&mut config);
+
+    let (mut state, pool) = common::setup_db_test_state(config).await;
+    state.router = Arc::new(ProviderRouter::new(
+        state.config.providers.clone(),
+        state.config.policies.rules.clone(),
+        state.config.policies.default_strategy.clone(),
+    ));
+    state.db_writer = Some(DbWriter::new(pool.clone()));
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["alpha".to_string()]));
+    state.provider_clients = Arc::new(ProviderClients::new(&state.config.providers, None).unwrap());
+    (create_router(state), pool)
+}
+
+async fn create_voucher(app: &axum::Router, body: serde_json::Value) -> (u16, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/admin/vouchers")
+                .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    (status.as_u16(), body)
+}
+
+/// Send a chat completion paid with `code` and return the status code.
+async fn complete(app: &axum::Router, code: &str, model: &str, stream: bool) -> u16 {
+    send(app, Some(code), None, model, stream).await
+}
+
+/// Send a chat completion with an optional voucher and bearer token and
+/// return the status code.
+async fn send(
+    app: &axum::Router,
+    code: Option<&str>,
+    bearer: Option<&str>,
+    model: &str,
+    stream: bool,
+) -> u16 {
+    let mut request =
+        Request::post("/v1/chat/completions").header("content-type", "application/json");
+    if let Some(code) = code {
+        request = request.header("x-arbstr-voucher", code);
+    }
+    if let Some(bearer) = bearer {
+        request = request.header("authorization", format!("Bearer {}", bearer));
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            request
+                .body(Body::from(
+                    serde_json::json!({
+                        "model": model,
+                        "messages": [{"role": "user", "content": "Hello"}],
+                        "stream": stream,
+                        "stream_options": {"include_usage": true}
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    // Read the body so a stream runs to completion
+    let _ = axum::body::to_bytes(response.into_body(), usize::MAX).await;
+    status
+}
+
+/// The voucher listing from GET /admin/vouchers, once `spent_sats` reaches
+/// `spent` (stream costs are settled in the background).
+async fn wait_for_spend(app: &axum::Router, spent: f64) -> serde_json::Value {
+    let mut voucher = serde_json::Value::Null;
+    for _ in 0..50 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/admin/vouchers")
+                    .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let (_, body) = common::parse_body(response).await;
+        voucher = body["vouchers"][0].clone();
+        if voucher["spent_sats"] == spent {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    voucher
+}
+
+#[tokio::test]
+async fn sats_voucher_is_debited_until_exhausted() {
+    let (app, pool) = setup_app().await;
+
+    let (status, voucher) = create_voucher(
+        &app,
+        serde_json::json!({"sats": 25, "models": ["gpt-4o"], "note": "trial"}),
+    )
+    .await;
+    assert_eq!(status, 201, "{}", voucher);
+    let code = voucher["code"].as_str().unwrap();
+    assert_eq!(voucher["remaining_sats"], 25.0);
+
+    assert_eq!(complete(&app, code, "gpt-4o-mini", false).await, 403);
+    assert_eq!(complete(&app, code, "gpt-4o", false).await, 200);
+    assert_eq!(complete(&app, code, "gpt-4o", true).await, 200);
+    let listed = wait_for_spend(&app, 20.0).await;
+    assert_eq!(listed["spent_sats"], 20.0);
+    assert_eq!(listed["requests"], 2);
+    assert_eq!(listed["remaining_sats"], 5.0);
+
+    // What is left can't cover another request's estimated cost
+    assert_eq!(complete(&app, code, "gpt-4o", false).await, 402);
+    let listed = wait_for_spend(&app, 20.0).await;
+    assert_eq!(listed["requests"], 2);
+    assert_eq!(listed["exhausted"], false);
+
+    // A restart restores the spend from the request log
+    let mut restored = Vouchers::default();
+    for _ in 0..50 {
+        restored = Vouchers::default();
+        restored.restore(&pool).await.unwrap();
+        if restored.snapshots()[0].spent_sats == 20.0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let snapshot = &restored.snapshots()[0];
+    assert_eq!(snapshot.spent_sats, 20.0);
+    assert_eq!(snapshot.requests, 2);
+    assert!(restored.redeem(code, "gpt-4o", 10.0).is_err());
+    assert!(restored.redeem(code, "gpt-4o", 5.0).is_ok());
+}
+
+#[tokio::test]
+async fn request_voucher_and_rejections() {
+    let (app, _pool) = setup_app().await;
+
+    let (status, _) = create_voucher(&app, serde_json::json!({"note": "no limit"})).await;
+    assert_eq!(status, 400);
+    let (status, _) = create_voucher(&app, serde_json::json!({"requests": 0})).await;
+    assert_eq!(status, 400);
+    let (status, _) = create_voucher(&app, serde_json::json!({"sats": -1})).await;
+    assert_eq!(status, 400);
+
+    let (status, voucher) = create_voucher(&app, serde_json::json!({"requests": 1})).await;
+    assert_eq!(status, 201);
+    let code = voucher["code"].as_str().unwrap();
+    assert_eq!(complete(&app, code, "gpt-4o-mini", false).await, 200);
+    assert_eq!(complete(&app, code, "gpt-4o", false).await, 402);
+    assert_eq!(complete(&app, "arbv_unknown", "gpt-4o", false).await, 402);
+}
+
+#[tokio::test]
+async fn voucher_stands_in_for_the_bearer_token() {
+    let (app, _pool) = setup_app_with_token(Some("client-secret")).await;
+    let (status, voucher) = create_voucher(&app, serde_json::json!({"requests": 1})).await;
+    assert_eq!(status, 201);
+    let code = voucher["code"].as_str().unwrap();
+
+    assert_eq!(send(&app, None, None, "gpt-4o", false).await, 401);
+    assert_eq!(
+        send(&app, None, Some("client-secret"), "gpt-4o", false).await,
+        200
+    );
+    assert_eq!(
+        send(&app, Some("arbv_unknown"), None, "gpt-4o", false).await,
+        402
+    );
+    // With a voucher the voucher alone decides, bearer token or not
+    assert_eq!(send(&app, Some(code), None, "gpt-4o", false).await, 200);
+    assert_eq!(
+        send(&app, Some(code), Some("client-secret"), "gpt-4o", false).await,
+        402
+    );
+}
+
+#[tokio::test]
+async fn large_body_is_checked_against_the_voucher_balance() {
+    let (app, _pool) = setup_app_with(None, |config| {
+        config.server.stream_body_threshold_bytes = Some(1024);
+        config.providers[0].input_rate = 10.0;
+    })
+    .await;
+    let (status, voucher) = create_voucher(&app, serde_json::json!({"sats": 15})).await;
+    assert_eq!(status, 201);
+    let code = voucher["code"].as_str().unwrap();
+
+    // ~2000 prompt tokens: far more than the balance, though over the
+    // threshold the body would be streamed through without an estimate
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "a".repeat(8000)}]
+    })
+    .to_string();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("content-length", body.len())
+                .header("x-arbstr-voucher", code)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 402);
+}
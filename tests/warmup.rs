@@ -0,0 +1,136 @@
+//! Integration tests for startup connection warmup and `/ready`.
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::{Config, PoliciesConfig, ProviderConfig, ServerConfig, WarmupConfig};
+use arbstr::proxy::{create_router, AppState, CircuitBreakerRegistry};
+use axum::body::Body;
+use http::Request;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn state_for(providers: Vec<ProviderConfig>, warmup: WarmupConfig) -> AppState {
+    let names: Vec<String> = providers.iter().map(|p| p.name.clone()).collect();
+    let config = Config {
+        server: ServerConfig {
+            listen: vec!["127.0.0.1:0".to_string()],
+            analytics_listen: vec![],
+            rate_limit_rps: None,
+            auth_token: None,
+            admin_token: None,
+            stream_body_threshold_bytes: None,
+            cors: None,
+            limits: Default::default(),
+            metadata_mode: Default::default(),
+            sse_keepalive_secs: None,
+        },
+        database: None,
+        vault: None,
+        providers,
+        policies: PoliciesConfig::default(),
+        logging: Default::default(),
+        routing: Default::default(),
+        reports: None,
+        headers: Default::default(),
+        dns: Default::default(),
+        chaos: Default::default(),
+        warmup,
+        stats: Default::default(),
+        currency: None,
+        privacy: Default::default(),
+        recording: None,
+        evaluation: None,
+        arbitrage: Default::default(),
+        nostr: None,
+        receipts: Default::default(),
+        archive: Default::default(),
+        content_filter: None,
+        anomaly: None,
+    };
+    AppState {
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::new(&names)),
+        ..common::setup_test_state(config)
+    }
+}
+
+async fn get_ready(state: &AppState) -> (u16, serde_json::Value) {
+    let response = create_router(state.clone())
+        .oneshot(Request::get("/ready").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, body) = common::parse_body(response).await;
+    (status.as_u16(), body)
+}
+
+#[tokio::test]
+async fn ready_without_warmup() {
+    let state = state_for(
+        vec![common::test_provider("alpha")],
+        WarmupConfig::default(),
+    );
+    let (status, body) = get_ready(&state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ready");
+    assert!(body.get("warmup").is_none());
+}
+
+#[tokio::test]
+async fn warmup_connects_to_each_provider_and_reports_results() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"data": []})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "gpt-4o", "max_tokens": 1}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let providers = vec![
+        ProviderConfig {
+            url: format!("{}/v1", server.uri()),
+            ..common::test_provider("alpha")
+        },
+        ProviderConfig {
+            // Nothing listens on port 1
+            url: "http://127.0.0.1:1/v1".to_string(),
+            ..common::test_provider("down")
+        },
+    ];
+    let state = state_for(
+        providers,
+        WarmupConfig {
+            enabled: true,
+            completion: true,
+            timeout_ms: 2000,
+        },
+    );
+
+    state.warmup.begin();
+    let (status, body) = get_ready(&state).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "warming_up");
+
+    let results = arbstr::proxy::warmup::run(&state).await;
+    assert!(results["alpha"].connected);
+    assert_eq!(results["alpha"].status, Some(200));
+    assert_eq!(results["alpha"].completion_status, Some(200));
+    assert!(!results["down"].connected);
+    assert!(results["down"].error.is_some());
+
+    let (status, body) = get_ready(&state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["warmup"]["alpha"]["connected"], true);
+    assert_eq!(body["warmup"]["down"]["connected"], false);
+}
@@ -0,0 +1,146 @@
+//! Integration tests for chat completions over WebSocket
+//! (`/v1/chat/completions/ws`).
+
+mod common;
+
+use std::sync::Arc;
+
+use arbstr::config::ProviderConfig;
+use arbstr::proxy::{create_router, CircuitBreakerRegistry};
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn mock_streaming_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(
+                    "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+                     data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"}}],\
+                     \"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"total_tokens\":5}}\n\n\
+                     data: [DONE]\n\n",
+                ),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Serve the app on a local port and return the WebSocket URL and app.
+async fn start(server: &MockServer) -> (String, axum::Router) {
+    let mut config = common::db_test_config();
+    config.providers = vec![ProviderConfig {
+        url: format!("{}/v1", server.uri()),
+        ..common::test_provider("upstream")
+    }];
+    let (mut state, _pool) = common::setup_db_test_state(config).await;
+    state.circuit_breakers = Arc::new(CircuitBreakerRegistry::new(&["upstream".to_string()]));
+    let app = create_router(state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = app.clone();
+    tokio::spawn(async move {
+        axum::serve(listener, served).await.ok();
+    });
+    (format!("ws://{}/v1/chat/completions/ws", addr), app)
+}
+
+/// Send one request and collect text frames through `[DONE]` (or a single
+/// error frame).
+async fn exchange(socket: &mut Socket, request: &str) -> Vec<String> {
+    socket
+        .send(Message::Text(request.to_string()))
+        .await
+        .unwrap();
+    let mut frames = Vec::new();
+    while let Some(Ok(Message::Text(text))) = socket.next().await {
+        let done = text == "[DONE]" || text.contains("\"error\"");
+        frames.push(text);
+        if done {
+            break;
+        }
+    }
+    frames
+}
+
+fn chat_request() -> String {
+    serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hi"}]
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn streams_deltas_as_frames() {
+    let server = mock_streaming_provider().await;
+    let (url, _app) = start(&server).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+    let frames = exchange(&mut socket, &chat_request()).await;
+    assert_eq!(frames.last().unwrap(), "[DONE]");
+    let content: String = frames
+        .iter()
+        .filter_map(|f| serde_json::from_str::<serde_json::Value>(f).ok())
+        .filter_map(|v| {
+            v["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(String::from)
+        })
+        .collect();
+    assert_eq!(content, "Hello");
+    // Metadata follows the default body mode
+    let metadata: serde_json::Value = serde_json::from_str(&frames[frames.len() - 2]).unwrap();
+    assert!(metadata["arbstr"]["cost_sats"].as_f64().unwrap() > 0.0);
+
+    // The provider was asked to stream even though the client didn't say so
+    let requests = server.received_requests().await.unwrap();
+    let upstream: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(upstream["stream"], true);
+}
+
+#[tokio::test]
+async fn serves_several_requests_per_socket() {
+    let server = mock_streaming_provider().await;
+    let (url, app) = start(&server).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+    let error = exchange(&mut socket, "not json").await;
+    assert_eq!(error.len(), 1);
+    let error: serde_json::Value = serde_json::from_str(&error[0]).unwrap();
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Invalid chat request"));
+
+    for _ in 0..2 {
+        let frames = exchange(&mut socket, &chat_request()).await;
+        assert_eq!(frames.last().unwrap(), "[DONE]");
+    }
+    socket.close(None).await.unwrap();
+
+    // Both completions went through the logging pipeline
+    use tower::ServiceExt;
+    let response = app
+        .oneshot(
+            http::Request::get("/v1/requests/recent")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, body) = common::parse_body(response).await;
+    let rows = body["data"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_ne!(rows[0]["correlation_id"], rows[1]["correlation_id"]);
+    assert_eq!(rows[0]["streaming"], true);
+}